@@ -0,0 +1,45 @@
+/*
+ * Mel spectrogram of a 440 Hz tone through the C API.
+ *
+ *   cargo build --release -p spectrs-ffi
+ *   cc spectrs-ffi/examples/mel.c -Ispectrs-ffi/include -Ltarget/release -lspectrs_ffi -lm -o mel
+ *   LD_LIBRARY_PATH=target/release ./mel
+ */
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "spectrs.h"
+
+int main(void) {
+    const uint32_t sample_rate = 16000;
+    const size_t n_samples = sample_rate;
+    float *samples = malloc(n_samples * sizeof(float));
+    for (size_t i = 0; i < n_samples; i++) {
+        samples[i] = sinf(2.0f * 3.14159265f * 440.0f * (float)i / (float)sample_rate);
+    }
+
+    spectrs_params params = spectrs_default_params();
+    params.n_fft = 512;
+    params.win_length = 512;
+    params.hop_length = 160;
+    params.n_mels = 64;
+
+    size_t n_rows, n_frames;
+    if (spectrs_output_shape(&params, n_samples, 1, &n_rows, &n_frames) != SPECTRS_OK) {
+        fprintf(stderr, "error: %s\n", spectrs_last_error());
+        return 1;
+    }
+
+    float *mel = malloc(n_rows * n_frames * sizeof(float));
+    if (spectrs_compute_mel(samples, n_samples, sample_rate, &params, mel, n_rows * n_frames) !=
+        SPECTRS_OK) {
+        fprintf(stderr, "error: %s\n", spectrs_last_error());
+        return 1;
+    }
+
+    printf("%zu mel bands x %zu frames, first band of frame 0: %f\n", n_rows, n_frames, mel[0]);
+    free(mel);
+    free(samples);
+    return 0;
+}
@@ -0,0 +1,97 @@
+/*
+ * C API of spectrs, a fast spectrogram creation library.
+ *
+ * All functions are thread-safe and never abort: failures are reported through
+ * spectrs_status and a message retrievable with spectrs_last_error() (per thread).
+ * Output buffers are allocated by the caller; query their size with
+ * spectrs_output_shape(). Spectrograms are written row-major, one row per
+ * frequency bin (or mel band) and one column per frame.
+ */
+#ifndef SPECTRS_H
+#define SPECTRS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Incremented on every incompatible change of this header */
+#define SPECTRS_ABI_VERSION 1
+
+typedef enum spectrs_status {
+    SPECTRS_OK = 0,
+    /* A required pointer is NULL */
+    SPECTRS_ERROR_NULL_POINTER = 1,
+    /* The parameters are invalid (see spectrs_last_error) */
+    SPECTRS_ERROR_INVALID_PARAMS = 2,
+    /* The output buffer is smaller than the spectrogram */
+    SPECTRS_ERROR_BUFFER_TOO_SMALL = 3,
+    /* Unexpected internal error */
+    SPECTRS_ERROR_INTERNAL = 4
+} spectrs_status;
+
+typedef enum spectrs_spectrogram_type {
+    SPECTRS_POWER = 0,
+    SPECTRS_MAGNITUDE = 1
+} spectrs_spectrogram_type;
+
+typedef enum spectrs_mel_scale {
+    SPECTRS_MEL_SLANEY = 0,
+    SPECTRS_MEL_HTK = 1
+} spectrs_mel_scale;
+
+typedef struct spectrs_params {
+    /* Number of samples in each FFT window */
+    uint32_t n_fft;
+    /* Stride between successive frames */
+    uint32_t hop_length;
+    /* Number of samples in the (Hann) window, at most n_fft */
+    uint32_t win_length;
+    /* Non-zero to center the window inside the FFT frame */
+    uint32_t center;
+    /* A spectrs_spectrogram_type value */
+    uint32_t spectrogram_type;
+    /* Number of mel bands (only used by spectrs_compute_mel) */
+    uint32_t n_mels;
+    /* Lower cut-off frequency of the mel filter bank (Hz) */
+    float f_min;
+    /* Upper cut-off frequency of the mel filter bank (Hz), 0 for Nyquist */
+    float f_max;
+    /* A spectrs_mel_scale value */
+    uint32_t mel_scale;
+} spectrs_params;
+
+/* Version of the ABI the library was built with (SPECTRS_ABI_VERSION) */
+uint32_t spectrs_abi_version(void);
+
+/* Default parameters: n_fft=2048, hop_length=512, win_length=2048, centered power
+ * spectrogram, 128 Slaney mel bands over the full band */
+spectrs_params spectrs_default_params(void);
+
+/* Number of rows and frames of the spectrogram of n_samples samples.
+ * With mel != 0 the rows are mel bands, otherwise frequency bins. */
+spectrs_status spectrs_output_shape(const spectrs_params *params, size_t n_samples,
+                                    int mel, size_t *n_rows, size_t *n_frames);
+
+/* Linear-frequency spectrogram of mono samples at sample_rate Hz, written to out
+ * (out_len floats available, n_fft/2+1 rows) */
+spectrs_status spectrs_compute_spectrogram(const float *samples, size_t n_samples,
+                                           uint32_t sample_rate, const spectrs_params *params,
+                                           float *out, size_t out_len);
+
+/* Mel spectrogram of mono samples at sample_rate Hz, written to out
+ * (out_len floats available, n_mels rows) */
+spectrs_status spectrs_compute_mel(const float *samples, size_t n_samples, uint32_t sample_rate,
+                                   const spectrs_params *params, float *out, size_t out_len);
+
+/* Message describing the last error of the calling thread (empty if none). The pointer
+ * stays valid until the next spectrs call on the same thread. */
+const char *spectrs_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SPECTRS_H */
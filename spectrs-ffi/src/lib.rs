@@ -0,0 +1,272 @@
+//! C API of spectrs (see `include/spectrs.h`).
+//!
+//! Every entry point validates its pointers and parameters, catches panics and reports
+//! failures as a `spectrs_status` plus a per-thread message, so that no Rust error ever
+//! crosses the FFI boundary.
+
+use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use std::cell::RefCell;
+use std::ffi::{CString, c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+/// Version of the ABI described by `include/spectrs.h`
+pub const SPECTRS_ABI_VERSION: u32 = 1;
+
+/// Outcome of a call (`spectrs_status` in C)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrsStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidParams = 2,
+    BufferTooSmall = 3,
+    Internal = 4,
+}
+
+/// Spectrogram parameters (`spectrs_params` in C). Enumerations are plain integers so that
+/// out-of-range values coming from C are reported instead of being undefined behaviour.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrsParams {
+    pub n_fft: u32,
+    pub hop_length: u32,
+    pub win_length: u32,
+    pub center: u32,
+    pub spectrogram_type: u32,
+    pub n_mels: u32,
+    pub f_min: f32,
+    pub f_max: f32,
+    pub mel_scale: u32,
+}
+
+impl SpectrsParams {
+    /// Validated library parameters; `mel` selects whether the mel fields are used
+    fn to_params(self, mel: bool, sample_rate: Option<u32>) -> Result<SpectrogramParams, String> {
+        let spectrogram_type = match self.spectrogram_type {
+            0 => SpectrogramType::Power,
+            1 => SpectrogramType::Magnitude,
+            other => return Err(format!("Invalid spectrogram type {}", other)),
+        };
+        let mel_scale = match self.mel_scale {
+            0 => MelScale::Slaney,
+            1 => MelScale::HTK,
+            other => return Err(format!("Invalid mel scale {}", other)),
+        };
+
+        let mut builder = SpectrogramParams::builder()
+            .n_fft(self.n_fft as usize)
+            .hop_length(self.hop_length as usize)
+            .win_length(self.win_length as usize)
+            .center(self.center != 0)
+            .spectrogram_type(spectrogram_type)
+            .mel_scale(mel_scale);
+        if mel {
+            builder = builder
+                .n_mels(Some(self.n_mels as usize))
+                .f_min(Some(self.f_min))
+                .f_max((self.f_max > 0.0).then_some(self.f_max));
+        }
+        if let Some(sr) = sample_rate {
+            builder = builder.sample_rate(sr);
+        }
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Record the message returned by `spectrs_last_error`
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Run `f`, turning errors and panics into a status and the thread's last error
+fn guarded<F: FnOnce() -> Result<(), (SpectrsStatus, String)>>(f: F) -> SpectrsStatus {
+    set_last_error("");
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => SpectrsStatus::Ok,
+        Ok(Err((status, message))) => {
+            set_last_error(&message);
+            status
+        }
+        Err(_) => {
+            set_last_error("Internal error (panic) in spectrs");
+            SpectrsStatus::Internal
+        }
+    }
+}
+
+fn invalid(message: String) -> (SpectrsStatus, String) {
+    (SpectrsStatus::InvalidParams, message)
+}
+
+/// Number of frames `compute_spectrogram` produces
+fn n_frames(params: &SpectrogramParams, n_samples: usize) -> usize {
+    n_samples.saturating_sub(params.win_length) / params.hop_length + 1
+}
+
+/// Version of the ABI the library was built with
+#[unsafe(no_mangle)]
+pub extern "C" fn spectrs_abi_version() -> u32 {
+    SPECTRS_ABI_VERSION
+}
+
+/// Default parameters (those of the CLI, with 128 mel bands)
+#[unsafe(no_mangle)]
+pub extern "C" fn spectrs_default_params() -> SpectrsParams {
+    let defaults = SpectrogramParams::default();
+    SpectrsParams {
+        n_fft: defaults.n_fft as u32,
+        hop_length: defaults.hop_length as u32,
+        win_length: defaults.win_length as u32,
+        center: defaults.center as u32,
+        spectrogram_type: 0,
+        n_mels: 128,
+        f_min: 0.0,
+        f_max: 0.0,
+        mel_scale: 0,
+    }
+}
+
+/// Shape of the spectrogram of `n_samples` samples
+///
+/// # Safety
+/// `params`, `n_rows` and `n_frames` must be null or valid pointers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spectrs_output_shape(
+    params: *const SpectrsParams,
+    n_samples: usize,
+    mel: c_int,
+    n_rows: *mut usize,
+    n_frames_out: *mut usize,
+) -> SpectrsStatus {
+    guarded(|| {
+        if params.is_null() || n_rows.is_null() || n_frames_out.is_null() {
+            return Err((SpectrsStatus::NullPointer, "Null pointer argument".into()));
+        }
+        let params = unsafe { *params }
+            .to_params(mel != 0, None)
+            .map_err(invalid)?;
+        let rows = match params.n_mels {
+            Some(n_mels) if mel != 0 => n_mels,
+            _ => params.n_freq_bins(),
+        };
+        unsafe {
+            *n_rows = rows;
+            *n_frames_out = n_frames(&params, n_samples);
+        }
+        Ok(())
+    })
+}
+
+/// Shared implementation of the compute functions
+///
+/// # Safety
+/// See `spectrs_compute_spectrogram`.
+unsafe fn compute(
+    samples: *const f32,
+    n_samples: usize,
+    sample_rate: u32,
+    params: *const SpectrsParams,
+    out: *mut f32,
+    out_len: usize,
+    mel: bool,
+) -> SpectrsStatus {
+    guarded(|| {
+        if samples.is_null() || params.is_null() || out.is_null() {
+            return Err((SpectrsStatus::NullPointer, "Null pointer argument".into()));
+        }
+        if sample_rate == 0 {
+            return Err(invalid("Sample rate must be greater than 0".into()));
+        }
+        let params = unsafe { *params }
+            .to_params(mel, Some(sample_rate))
+            .map_err(invalid)?;
+        let audio = unsafe { std::slice::from_raw_parts(samples, n_samples) };
+
+        // Check the buffer before doing any work
+        let rows = if mel {
+            params.n_mels.unwrap_or_default()
+        } else {
+            params.n_freq_bins()
+        };
+        let frames = n_frames(&params, n_samples);
+        let needed = rows * frames;
+        if out_len < needed {
+            return Err((
+                SpectrsStatus::BufferTooSmall,
+                format!("Output buffer holds {} floats, {} needed", out_len, needed),
+            ));
+        }
+
+        let mut spec = compute_spectrogram(
+            audio,
+            params.n_fft,
+            params.hop_length,
+            params.win_length,
+            params.center,
+            params.spectrogram_type,
+        );
+        if let Some(n_mels) = params.n_mels.filter(|_| mel) {
+            spec = convert_to_mel(
+                &spec,
+                sample_rate,
+                params.n_fft,
+                n_mels,
+                params.f_min,
+                params.f_max,
+                params.mel_scale,
+            );
+        }
+
+        let out = unsafe { std::slice::from_raw_parts_mut(out, needed) };
+        for (dst, row) in out.chunks_exact_mut(frames).zip(&spec) {
+            dst.copy_from_slice(row);
+        }
+        Ok(())
+    })
+}
+
+/// Linear-frequency spectrogram of mono samples, row-major [freq][time]
+///
+/// # Safety
+/// `samples` must point to `n_samples` floats and `out` to `out_len` writable floats;
+/// `params` must be a valid pointer. Null pointers are reported as errors.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spectrs_compute_spectrogram(
+    samples: *const f32,
+    n_samples: usize,
+    sample_rate: u32,
+    params: *const SpectrsParams,
+    out: *mut f32,
+    out_len: usize,
+) -> SpectrsStatus {
+    unsafe { compute(samples, n_samples, sample_rate, params, out, out_len, false) }
+}
+
+/// Mel spectrogram of mono samples, row-major [mel][time]
+///
+/// # Safety
+/// Same requirements as `spectrs_compute_spectrogram`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spectrs_compute_mel(
+    samples: *const f32,
+    n_samples: usize,
+    sample_rate: u32,
+    params: *const SpectrsParams,
+    out: *mut f32,
+    out_len: usize,
+) -> SpectrsStatus {
+    unsafe { compute(samples, n_samples, sample_rate, params, out, out_len, true) }
+}
+
+/// Message describing the last error of the calling thread
+#[unsafe(no_mangle)]
+pub extern "C" fn spectrs_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
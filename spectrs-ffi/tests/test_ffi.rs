@@ -0,0 +1,144 @@
+use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use spectrs_ffi::*;
+use std::ffi::CStr;
+use std::ptr;
+
+const SR: u32 = 16000;
+
+fn sine(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|t| (2.0 * std::f32::consts::PI * 440.0 * t as f32 / SR as f32).sin())
+        .collect()
+}
+
+fn small_params() -> SpectrsParams {
+    SpectrsParams {
+        n_fft: 512,
+        hop_length: 128,
+        win_length: 400,
+        n_mels: 40,
+        mel_scale: 1,
+        ..spectrs_default_params()
+    }
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(spectrs_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn test_ffi_matches_library() {
+    let audio = sine(8000);
+    let params = small_params();
+    assert_eq!(spectrs_abi_version(), SPECTRS_ABI_VERSION);
+
+    let (mut rows, mut frames) = (0, 0);
+    let status = unsafe { spectrs_output_shape(&params, audio.len(), 1, &mut rows, &mut frames) };
+    assert_eq!(status, SpectrsStatus::Ok);
+    assert_eq!((rows, frames), (40, 60));
+
+    let mut out = vec![0.0f32; rows * frames];
+    let status = unsafe {
+        spectrs_compute_mel(
+            audio.as_ptr(),
+            audio.len(),
+            SR,
+            &params,
+            out.as_mut_ptr(),
+            out.len(),
+        )
+    };
+    assert_eq!(status, SpectrsStatus::Ok, "{}", last_error());
+    assert_eq!(last_error(), "");
+
+    // Row-major copy of the library output
+    let spec = compute_spectrogram(&audio, 512, 128, 400, true, SpectrogramType::Power);
+    let mel = convert_to_mel(&spec, SR, 512, 40, Some(0.0), None, MelScale::HTK);
+    assert_eq!(out, mel.concat());
+
+    // Linear spectrogram
+    let status = unsafe { spectrs_output_shape(&params, audio.len(), 0, &mut rows, &mut frames) };
+    assert_eq!(status, SpectrsStatus::Ok);
+    let mut out = vec![0.0f32; rows * frames];
+    let status = unsafe {
+        spectrs_compute_spectrogram(
+            audio.as_ptr(),
+            audio.len(),
+            SR,
+            &params,
+            out.as_mut_ptr(),
+            out.len(),
+        )
+    };
+    assert_eq!(status, SpectrsStatus::Ok);
+    assert_eq!(out, spec.concat());
+}
+
+#[test]
+fn test_ffi_errors() {
+    let audio = sine(4000);
+    let params = small_params();
+    let mut out = vec![0.0f32; 10];
+
+    let status = unsafe {
+        spectrs_compute_mel(
+            audio.as_ptr(),
+            audio.len(),
+            SR,
+            &params,
+            out.as_mut_ptr(),
+            out.len(),
+        )
+    };
+    assert_eq!(status, SpectrsStatus::BufferTooSmall);
+    assert!(last_error().contains("needed"));
+
+    let status =
+        unsafe { spectrs_compute_mel(ptr::null(), 0, SR, &params, out.as_mut_ptr(), out.len()) };
+    assert_eq!(status, SpectrsStatus::NullPointer);
+
+    // Out-of-range enumerations and invalid combinations are reported, not undefined
+    let bad_type = SpectrsParams {
+        spectrogram_type: 7,
+        ..params
+    };
+    let bad_window = SpectrsParams {
+        win_length: 1024,
+        ..params
+    };
+    for bad in [bad_type, bad_window] {
+        let status = unsafe {
+            spectrs_compute_spectrogram(
+                audio.as_ptr(),
+                audio.len(),
+                SR,
+                &bad,
+                out.as_mut_ptr(),
+                out.len(),
+            )
+        };
+        assert_eq!(status, SpectrsStatus::InvalidParams);
+        assert!(!last_error().is_empty());
+    }
+
+    // f_max above Nyquist is only detectable with the sample rate
+    let bad_f_max = SpectrsParams {
+        f_max: 9000.0,
+        ..params
+    };
+    let status = unsafe {
+        spectrs_compute_mel(
+            audio.as_ptr(),
+            audio.len(),
+            SR,
+            &bad_f_max,
+            out.as_mut_ptr(),
+            out.len(),
+        )
+    };
+    assert_eq!(status, SpectrsStatus::InvalidParams);
+    assert!(last_error().contains("Nyquist"), "{}", last_error());
+}
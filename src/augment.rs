@@ -0,0 +1,254 @@
+// Augmentation of audio before the STFT, to generate varied spectrogram datasets from the same
+// recordings: additive white or pink noise at a signal-to-noise ratio, random gain, random time
+// shift and time stretch (by resampling, which shifts the pitch as well). Transforms apply in
+// order and draw their random amounts from a seeded generator, so that a file is augmented
+// identically by every pass over it and by every run with the same seed. SpecAugment masks
+// (Park et al., 2019) are applied to the computed features instead.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::audio::{ResampleQuality, StreamingResampler, resampled_len};
+use crate::spectrogram::fbank::GaussianNoise;
+use crate::spectrogram::stft::{Float, constant};
+
+/// Spectrum of additive noise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseColor {
+    /// Flat spectrum
+    White,
+    /// Power falling by 3 dB per octave
+    Pink,
+}
+
+/// Transform of the audio fed to the STFT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Augmentation {
+    /// Noise added at a signal-to-noise ratio (dB) relative to the mean power of the audio
+    /// (nothing is added to silence)
+    Noise { color: NoiseColor, snr_db: f32 },
+    /// Gain (dB) drawn uniformly between `min_db` and `max_db`
+    Gain { min_db: f32, max_db: f32 },
+    /// Delay (seconds) drawn uniformly between `-max_seconds` and `max_seconds`, keeping the
+    /// length of the audio: samples shifted out are dropped and the gap is filled with silence
+    TimeShift { max_seconds: f32 },
+    /// Speed factor drawn uniformly between `min_rate` and `max_rate` (above 1 shortens the
+    /// audio and raises its pitch)
+    TimeStretch { min_rate: f32, max_rate: f32 },
+}
+
+impl Augmentation {
+    /// Check that the amounts are finite and the ranges ordered
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(SpectrsError::InvalidParams(message));
+        match *self {
+            Augmentation::Noise { snr_db, .. } if !snr_db.is_finite() => {
+                invalid(format!("SNR must be a finite number of dB, got {}", snr_db))
+            }
+            Augmentation::Gain { min_db, max_db }
+                if !(min_db.is_finite() && max_db.is_finite() && min_db <= max_db) =>
+            {
+                invalid(format!(
+                    "Gain range must be finite and ordered, got {} dB to {} dB",
+                    min_db, max_db
+                ))
+            }
+            Augmentation::TimeShift { max_seconds }
+                if !(max_seconds >= 0.0 && max_seconds.is_finite()) =>
+            {
+                invalid(format!(
+                    "Time shift must be a non-negative number of seconds, got {}",
+                    max_seconds
+                ))
+            }
+            Augmentation::TimeStretch { min_rate, max_rate }
+                if !(min_rate > 0.0 && max_rate.is_finite() && min_rate <= max_rate) =>
+            {
+                invalid(format!(
+                    "Stretch rates must be positive and ordered, got {} to {}",
+                    min_rate, max_rate
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Transform the audio at `sr`, drawing random amounts from `rng`
+    fn apply(&self, mut audio: Vec<f32>, sr: u32, rng: &mut GaussianNoise) -> Result<Vec<f32>> {
+        match *self {
+            Augmentation::Noise { color, snr_db } => {
+                add_noise(&mut audio, color, snr_db, rng);
+                Ok(audio)
+            }
+            Augmentation::Gain { min_db, max_db } => {
+                let gain = 10f32.powf(uniform(rng, min_db, max_db) / 20.0);
+                audio.iter_mut().for_each(|s| *s *= gain);
+                Ok(audio)
+            }
+            Augmentation::TimeShift { max_seconds } => {
+                let shift = (uniform(rng, -max_seconds, max_seconds) * sr as f32).round() as isize;
+                Ok(shift_samples(&audio, shift))
+            }
+            Augmentation::TimeStretch { min_rate, max_rate } => {
+                // Audio resampled to sr / rate and played back at sr lasts 1 / rate as long
+                let rate = uniform(rng, min_rate, max_rate);
+                let stretched_sr = ((sr as f32 / rate).round() as u32).max(1);
+                // Resampled in fixed-size chunks like --sr, so that audio shorter than a chunk
+                // (or empty) is padded instead of sizing the resampler after it
+                let mut resampler =
+                    StreamingResampler::new(sr, stretched_sr, ResampleQuality::default())?;
+                let mut stretched =
+                    Vec::with_capacity(resampled_len(audio.len(), sr, stretched_sr));
+                resampler.process(&audio, &mut stretched)?;
+                resampler.finish(&mut stretched)?;
+                Ok(stretched)
+            }
+        }
+    }
+}
+
+/// Apply the augmentations in order to mono audio at `sr`. The same `seed` gives the same
+/// random amounts and noise.
+pub fn augment(
+    audio: Vec<f32>,
+    sr: u32,
+    augmentations: &[Augmentation],
+    seed: u64,
+) -> Result<Vec<f32>> {
+    let mut rng = GaussianNoise::new(seed);
+    augmentations.iter().try_fold(audio, |audio, augmentation| {
+        augmentation.validate()?;
+        augmentation.apply(audio, sr, &mut rng)
+    })
+}
+
+/// Value drawn uniformly between `low` and `high`
+fn uniform(rng: &mut GaussianNoise, low: f32, high: f32) -> f32 {
+    low + rng.uniform() * (high - low)
+}
+
+/// Delay the audio by `shift` samples (advance it if negative), keeping its length
+fn shift_samples(audio: &[f32], shift: isize) -> Vec<f32> {
+    let n = audio.len() as isize;
+    (0..n)
+        .map(|i| {
+            let source = i - shift;
+            if (0..n).contains(&source) {
+                audio[source as usize]
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Add noise of the given colour at `snr_db` below the mean power of the audio
+fn add_noise(audio: &mut [f32], color: NoiseColor, snr_db: f32, rng: &mut GaussianNoise) {
+    if audio.is_empty() {
+        return;
+    }
+    let power = |samples: &[f32]| {
+        samples.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / samples.len() as f64
+    };
+    let signal_power = power(audio);
+    if signal_power == 0.0 {
+        return;
+    }
+
+    let mut noise: Vec<f32> = (0..audio.len()).map(|_| rng.sample()).collect();
+    if color == NoiseColor::Pink {
+        // Paul Kellet's economy filter: three first-order low-pass filters of white noise
+        let mut state = [0.0f32; 3];
+        for n in noise.iter_mut() {
+            let white = *n;
+            state[0] = 0.99765 * state[0] + white * 0.0990460;
+            state[1] = 0.96300 * state[1] + white * 0.2965164;
+            state[2] = 0.57000 * state[2] + white * 1.0526913;
+            *n = state[0] + state[1] + state[2] + white * 0.1848;
+        }
+    }
+    let noise_power = power(&noise);
+    if noise_power == 0.0 {
+        return;
+    }
+    let scale = (signal_power / noise_power / 10f64.powf(snr_db as f64 / 10.0)).sqrt() as f32;
+    for (s, n) in audio.iter_mut().zip(noise) {
+        *s += scale * n;
+    }
+}
+
+/// Value masked bands and frames are set to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskFill {
+    Zero,
+    /// Mean of the whole spectrogram before masking
+    Mean,
+    Value(f32),
+}
+
+/// SpecAugment masking of a [freq][time] spectrogram: `freq_masks` bands of up to
+/// `max_freq_width` rows and `time_masks` spans of up to `max_time_width` frames, with widths
+/// and positions drawn uniformly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpecAugment {
+    pub freq_masks: usize,
+    pub max_freq_width: usize,
+    pub time_masks: usize,
+    pub max_time_width: usize,
+    pub fill: MaskFill,
+}
+
+impl SpecAugment {
+    /// Check that the fill value is finite
+    pub fn validate(&self) -> Result<()> {
+        if let MaskFill::Value(value) = self.fill
+            && !value.is_finite()
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Mask fill value must be finite, got {}",
+                value
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mask the spectrogram in place. The same `seed` gives the same masks.
+    pub fn apply<T: Float>(&self, spectrogram: &mut [Vec<T>], seed: u64) {
+        let n_rows = spectrogram.len();
+        let n_frames = spectrogram.first().map_or(0, Vec::len);
+        if n_rows == 0 || n_frames == 0 {
+            return;
+        }
+        let fill = match self.fill {
+            MaskFill::Zero => T::zero(),
+            MaskFill::Value(value) => constant(value as f64),
+            MaskFill::Mean => {
+                spectrogram
+                    .iter()
+                    .flatten()
+                    .fold(T::zero(), |acc, &v| acc + v)
+                    / constant((n_rows * n_frames) as f64)
+            }
+        };
+
+        let mut rng = GaussianNoise::new(seed);
+        for _ in 0..self.freq_masks {
+            for row in mask_range(&mut rng, self.max_freq_width, n_rows) {
+                spectrogram[row].fill(fill);
+            }
+        }
+        for _ in 0..self.time_masks {
+            let frames = mask_range(&mut rng, self.max_time_width, n_frames);
+            for row in spectrogram.iter_mut() {
+                row[frames.clone()].fill(fill);
+            }
+        }
+    }
+}
+
+/// Range of a mask of up to `max_width` (and at most `len`) indices, placed uniformly in `len`
+fn mask_range(rng: &mut GaussianNoise, max_width: usize, len: usize) -> std::ops::Range<usize> {
+    let draw =
+        |rng: &mut GaussianNoise, n: usize| ((rng.uniform() * (n + 1) as f32) as usize).min(n);
+    let width = draw(rng, max_width.min(len));
+    let start = draw(rng, len - width);
+    start..start + width
+}
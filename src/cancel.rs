@@ -0,0 +1,45 @@
+// Cooperative cancellation of long computations: a flag shared between the code running the
+// computation and the code deciding to stop it (e.g. a Ctrl-C handler). Computations check it
+// between units of work (blocks of frames, chunks of audio) and return
+// `SpectrsError::Cancelled`, so that nothing is left half-done behind them.
+
+use crate::error::{Result, SpectrsError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag requesting computations to stop. Clones share the flag, so that cancelling one
+/// cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the computations holding the token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// `SpectrsError::Cancelled` once cancellation was requested, to stop with `?`
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(SpectrsError::Cancelled("Computation cancelled".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Token sharing an existing flag (e.g. one set by a signal handler)
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
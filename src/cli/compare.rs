@@ -0,0 +1,125 @@
+//! `spectrs compare`: similarity metrics between two spectrograms
+
+use anyhow::{Context, Result};
+use spectrs::io::export::{
+    CsvOptions, OutputFormat, load_spectrogram, save_spectrogram_csv, save_spectrogram_json,
+    save_spectrogram_npy,
+};
+use spectrs::spectrogram::compare::{
+    DiffMode, Metric, TimeAlignment, common_shape, diff_spectrograms,
+};
+use std::path::{Path, PathBuf};
+
+/// Compare two spectrograms (.npy, .csv, .json, or .png with its sidecar, by extension) and
+/// print similarity metrics, one `name: value` line each. Spectrograms of different shapes are
+/// compared over their common region, with a warning.
+#[derive(clap::Args)]
+pub struct CompareArgs {
+    /// Spectrogram to evaluate
+    pub a: PathBuf,
+
+    /// Reference spectrogram (relative errors are relative to it)
+    pub b: PathBuf,
+
+    /// Metrics to print, comma separated
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "correlation,mse,rmse,normalized-rmse,relative-error,max-abs-error"
+    )]
+    pub metrics: Vec<Metric>,
+
+    /// Fail if the shapes differ instead of comparing the common region
+    #[arg(long)]
+    pub strict_shape: bool,
+
+    /// Also write the map of the differences of every bin to this file (.npy, .csv or .json,
+    /// by extension)
+    #[arg(long)]
+    pub diff_out: Option<PathBuf>,
+
+    /// Differences written by --diff-out: absolute differences, levels relative to the
+    /// reference (dB) or signal-to-noise ratios (dB), taking values as powers
+    #[arg(long, value_enum, default_value_t = DiffMode::Absolute, requires = "diff_out")]
+    pub diff_mode: DiffMode,
+
+    /// How --diff-out aligns spectrograms of different lengths: crop them to the common frames
+    /// or pad the shorter with silence
+    #[arg(long, value_enum, default_value_t = TimeAlignment::Crop, requires = "diff_out")]
+    pub align: TimeAlignment,
+
+    /// Field delimiter of CSV inputs
+    #[arg(long, default_value = ",")]
+    pub csv_delimiter: char,
+
+    /// Decimal separator of CSV inputs
+    #[arg(long, default_value = ".")]
+    pub csv_decimal: char,
+}
+
+/// Run `spectrs compare`
+pub fn run(args: CompareArgs) -> Result<()> {
+    let csv = CsvOptions {
+        delimiter: args.csv_delimiter,
+        decimal: args.csv_decimal,
+        precision: None,
+    };
+    let diff_format = args.diff_out.as_deref().map(diff_format).transpose()?;
+    let a = load_spectrogram(&args.a, &csv).with_context(|| "Failed to load spectrogram")?;
+    let b = load_spectrogram(&args.b, &csv).with_context(|| "Failed to load spectrogram")?;
+
+    let shape = |spec: &[Vec<f32>]| [spec.len(), spec.first().map_or(0, Vec::len)];
+    let common = common_shape(&a, &b);
+    if shape(&a) != shape(&b) {
+        let message = format!(
+            "Shapes differ: {}x{} for {} and {}x{} for {}",
+            shape(&a)[0],
+            shape(&a)[1],
+            args.a.display(),
+            shape(&b)[0],
+            shape(&b)[1],
+            args.b.display()
+        );
+        if args.strict_shape {
+            anyhow::bail!(message);
+        }
+        tracing::warn!(
+            "{}; comparing the common {}x{} region",
+            message,
+            common[0],
+            common[1]
+        );
+    }
+    if common.contains(&0) {
+        anyhow::bail!("Nothing to compare: the common region is empty");
+    }
+
+    for metric in &args.metrics {
+        println!("{}: {}", metric.name(), metric.compute(&a, &b));
+    }
+    if let (Some(path), Some(format)) = (&args.diff_out, diff_format) {
+        let diff = diff_spectrograms(&a, &b, args.diff_mode, args.align)?;
+        save_diff(&diff, path, format, &csv).with_context(|| "Failed to save the differences")?;
+    }
+    Ok(())
+}
+
+/// Format of the difference map of --diff-out, by extension
+fn diff_format(path: &Path) -> Result<OutputFormat> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("npy") => Ok(OutputFormat::Npy),
+        Some("csv") => Ok(OutputFormat::Csv),
+        Some("json") => Ok(OutputFormat::Json),
+        _ => anyhow::bail!("{}: expected a .npy, .csv or .json file", path.display()),
+    }
+}
+
+/// Write a difference map in the format of --diff-out
+fn save_diff(diff: &[Vec<f32>], path: &Path, format: OutputFormat, csv: &CsvOptions) -> Result<()> {
+    match format {
+        OutputFormat::Csv => save_spectrogram_csv(diff, path, csv)?,
+        OutputFormat::Json => save_spectrogram_json(diff, path)?,
+        _ => save_spectrogram_npy(diff, path)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! `spectrs compare-image`: before/after spectrograms of two recordings and their difference
+//! in a single image, e.g. to evaluate denoising or a codec
+
+use super::InvalidConfig;
+use super::compute::{STDIO, rendered_spectrogram};
+use anyhow::{Context, Result};
+use spectrs::io::image::save_comparison_image;
+use spectrs::io::source::{is_store_url, is_url};
+use spectrs::spectrogram::pooling::{pool_freq, pool_time};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Render the spectrograms of two recordings (e.g. before and after denoising or encoding)
+/// stacked in one PNG, over a panel of their difference in dB. Both are computed as `spectrs
+/// compute` would, with the options of `spectrs compute` given after `--`, and share a single
+/// colour scale.
+#[derive(clap::Args)]
+pub struct CompareImageArgs {
+    /// Recording before processing (WAV file or URL)
+    pub before: String,
+
+    /// Recording after processing, at the same sample rate or brought to one with --sr
+    pub after: String,
+
+    /// Output PNG (default: <before>.comparison.png)
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Difference (dB) shown with the full colours of the difference panel (default: 99th
+    /// percentile of the absolute differences)
+    #[arg(long)]
+    pub diff_range: Option<f32>,
+
+    /// Options of `spectrs compute` used for both spectrograms, after `--` (e.g. -- --n-mels
+    /// 128 --sr 16000 --vmin -80)
+    #[arg(last = true, allow_hyphen_values = true)]
+    pub compute: Vec<OsString>,
+}
+
+/// Run `spectrs compare-image`
+pub fn run(args: CompareImageArgs) -> Result<()> {
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None if is_url(&args.before) || is_store_url(&args.before) => {
+            return Err(
+                anyhow::anyhow!("--output is required when the first input is remote")
+                    .context(InvalidConfig),
+            );
+        }
+        None => Path::new(&args.before).with_extension("comparison.png"),
+    };
+    if args.before == STDIO || args.after == STDIO {
+        return Err(
+            anyhow::anyhow!("spectrs compare-image cannot read stdin").context(InvalidConfig)
+        );
+    }
+
+    let (before, width, height) = render(&args.before, &args.compute)?;
+    let (after, _, _) = render(&args.after, &args.compute)?;
+    if before.sample_rate != after.sample_rate {
+        return Err(anyhow::anyhow!(
+            "The recordings have different sample rates ({} Hz and {} Hz): give --sr to \
+             compute both spectrograms at the same one",
+            before.sample_rate,
+            after.sample_rate
+        )
+        .context(InvalidConfig));
+    }
+    let (mut before_values, mut after_values) = (before.values, after.values);
+    // Recordings of different lengths are compared over their common duration
+    let frames = |spec: &[Vec<f32>]| spec.first().map_or(0, Vec::len);
+    let common = frames(&before_values).min(frames(&after_values));
+    if frames(&before_values) != frames(&after_values) {
+        tracing::warn!(
+            "Lengths differ: {} frames for {} and {} for {}; comparing the first {}",
+            frames(&before_values),
+            args.before,
+            frames(&after_values),
+            args.after,
+            common
+        );
+        for row in before_values.iter_mut().chain(after_values.iter_mut()) {
+            row.truncate(common);
+        }
+    }
+    if common == 0 {
+        anyhow::bail!("Nothing to compare: the common duration is empty");
+    }
+    let shrink = |spec: Vec<Vec<f32>>| match (width, height) {
+        (None, None) => spec,
+        (width, height) => pool_freq(
+            &pool_time(&spec, width.unwrap_or(0) as usize),
+            height.unwrap_or(0) as usize,
+        ),
+    };
+
+    save_comparison_image(
+        &shrink(before_values),
+        &shrink(after_values),
+        output.clone(),
+        before.colormap,
+        &before.scale,
+        args.diff_range,
+    )
+    .with_context(|| "Failed to save the comparison")?;
+    tracing::info!("Saved comparison to {}", output.display());
+    Ok(())
+}
+
+/// Spectrogram of an input as rendered by `spectrs compute` with the given options, and the
+/// maximum image width and height of the options
+type Rendered = (
+    super::compute::RenderedSpectrogram,
+    Option<u32>,
+    Option<u32>,
+);
+
+/// Compute the spectrogram of one of the inputs with the compute options
+fn render(input: &str, compute: &[OsString]) -> Result<Rendered> {
+    let argv = std::iter::once(OsString::from(input)).chain(compute.iter().cloned());
+    let (args, matches) = super::compute::parse_args(argv).map_err(|e| e.context(InvalidConfig))?;
+    // Only the spectrograms are kept: options writing files of their own or other outputs are
+    // refused rather than silently ignored
+    let unsupported = [
+        ("--output-dir", args.output_dir.is_some()),
+        ("--name-template", args.name_template.is_some()),
+        ("--sweep", !args.sweep.is_empty()),
+        (
+            "--format",
+            args.format != [spectrs::io::export::OutputFormat::Png],
+        ),
+        ("--live", args.live),
+        ("--manifest", args.manifest.is_some()),
+        ("--manifest-out", args.manifest_out.is_some()),
+        ("--report", args.report.is_some()),
+        ("--dry-run", args.dry_run),
+        ("--index-db", args.index_db.is_some()),
+        ("--checkpoint", args.checkpoint.is_some()),
+        ("--dataset-out", args.dataset_out.is_some()),
+        ("--tile-width", args.tile_width.is_some()),
+        ("--with-waveform", args.with_waveform),
+        ("--sidecar", args.sidecar),
+        ("--dump-resampled", args.dump_resampled),
+        ("--stats-out", args.stats_out.is_some()),
+        ("--summary-png", args.summary_png.is_some()),
+        ("--average-out", args.average_out.is_some()),
+        ("--cmvn-out", args.cmvn_out.is_some()),
+        ("--global-scale", args.global_scale),
+        ("--pitch-out", args.pitch_out.is_some()),
+        ("--pitch-overlay", args.pitch_overlay),
+        ("--formants-out", args.formants_out.is_some()),
+        ("--formant-overlay", args.formant_overlay),
+        ("--onsets-out", args.onsets_out.is_some()),
+        ("--onset-overlay", args.onset_overlay),
+        ("--segments-out", args.segments_out.is_some()),
+        ("--annotations", args.annotations.is_some()),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+        return Err(
+            anyhow::anyhow!("{} is not available with spectrs compare-image", flag)
+                .context(InvalidConfig),
+        );
+    }
+    let (width, height) = (args.width, args.height);
+    Ok((rendered_spectrogram(args, &matches)?, width, height))
+}
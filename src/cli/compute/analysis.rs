@@ -0,0 +1,612 @@
+//! Decoding of an input and computation of its spectrogram, from the cache or the audio
+
+use super::batch::{CacheSettings, CachedSummary, FileSummary};
+use super::options::AudioOptions;
+use super::{check_timed_out, interrupt};
+use anyhow::{Context, Result};
+use spectrs::augment::augment;
+use spectrs::features::cepstrum::{cepstral_peak_prominence, real_cepstrum};
+use spectrs::features::formant::par_track_formants;
+use spectrs::features::loudness::{Loudness, LoudnessMeter};
+use spectrs::features::lpc::{lpc_envelope, par_lpc_envelope};
+use spectrs::features::octave::band_levels;
+use spectrs::features::pitch::{YinParams, par_yin};
+use spectrs::features::temporal::ZeroCrossingCounter;
+use spectrs::features::tonal::chroma;
+use spectrs::io::audio::{
+    MonoChunks, WavFormat, resample_with_quality, slice_segment, write_audio_file,
+};
+use spectrs::io::cache::SpectrogramCache;
+use spectrs::io::source::AudioSource;
+use spectrs::spectrogram::compression::floor_spectrogram;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::fft::FftBackendType;
+use spectrs::spectrogram::filterbank::filter_bank;
+use spectrs::spectrogram::mel::{apply_filter_bank, par_apply_filter_bank};
+use spectrs::spectrogram::ops::{gate_frames, non_finite_frames, sanitize_non_finite};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::{
+    Float, FrameAlignment, compute_spectrogram_with_fft, librosa_framed_signal,
+    par_compute_spectrogram_cancellable, samples_from_ms,
+};
+use spectrs::spectrogram::streaming::{stream_pooled_spectrogram, stream_spectrogram};
+use spectrs::spectrogram::weighting::{apply_weighting, par_apply_weighting};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Read the whole audio source (or only the requested segment), convert it to mono and
+/// resample it if necessary. Returns the prepared audio, its sample rate and a summary of
+/// the decoded file. The variants of an input (--sweep) reuse the audio prepared for the
+/// first one.
+pub(super) fn load_audio(
+    source: &dyn AudioSource,
+    output: &Path,
+    options: &AudioOptions,
+) -> Result<(Vec<f32>, u32, FileSummary)> {
+    let Some(shared) = &options.shared else {
+        return decode_audio(source, output, options);
+    };
+    let mut decoded = shared
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Shared audio poisoned by a failed decoding"))?;
+    if decoded.is_none() {
+        *decoded = Some(decode_audio(source, output, options)?);
+    }
+    let (audio, sample_rate, summary) = decoded.as_ref().expect("decoded above");
+    Ok((audio.clone(), *sample_rate, summary.clone()))
+}
+
+/// Decode and prepare the audio of a source (see `load_audio`)
+pub(super) fn decode_audio(
+    source: &dyn AudioSource,
+    output: &Path,
+    options: &AudioOptions,
+) -> Result<(Vec<f32>, u32, FileSummary)> {
+    options.cancellation().check()?;
+    interrupt().check()?;
+    // Read audio file (or the requested segment) and convert to mono
+    let whole_file = options.start.is_none() && options.end.is_none();
+    let (mut audio, original_sr) = if options.lenient {
+        let (audio, sr, report) = source
+            .read_mono_lenient()
+            .with_context(|| "Failed to read audio")?;
+        if let Some(report) = report {
+            tracing::warn!(
+                "{} is damaged ({}); recovered {:.3}s{}",
+                source.name(),
+                report.reason,
+                report.recovered_duration(),
+                report
+                    .declared_frames
+                    .map(|frames| format!(" of {:.3}s", frames as f32 / sr as f32))
+                    .unwrap_or_default()
+            );
+        }
+        if whole_file {
+            (audio, sr)
+        } else {
+            let segment = slice_segment(&audio, sr, options.start.unwrap_or(0.0), options.end)
+                .with_context(|| "Failed to read audio")?;
+            (segment, sr)
+        }
+    } else if whole_file {
+        source.read_mono().with_context(|| "Failed to read audio")?
+    } else {
+        source
+            .read_segment(options.start.unwrap_or(0.0), options.end)
+            .with_context(|| "Failed to read audio")?
+    };
+    let mut summary = FileSummary::from_audio(&audio, original_sr);
+    if options.loudness {
+        summary.loudness = Some(Loudness::from_samples(&audio, original_sr));
+    }
+
+    // Resample if necessary
+    let target_sr = match options.sr {
+        Some(sample_rate) if sample_rate != original_sr => {
+            audio =
+                resample_with_quality(audio, original_sr, sample_rate, options.resample_quality)
+                    .with_context(|| "Failed to resample audio")?;
+            sample_rate
+        }
+        Some(sample_rate) => sample_rate,
+        None => original_sr,
+    };
+    if !options.augment.is_empty() {
+        let seed = options.augment_seed ^ name_hash(&source.name());
+        audio = augment(audio, target_sr, &options.augment, seed)
+            .with_context(|| "Failed to augment audio")?;
+    }
+    options
+        .filter
+        .apply(&mut audio, target_sr)
+        .with_context(|| "Failed to filter audio")?;
+    if let Some(yin) = &options.pitch {
+        let yin = YinParams {
+            frame_length: yin
+                .frame_length
+                .max(YinParams::min_frame_length(yin.f_min, target_sr)),
+            ..*yin
+        };
+        summary.pitch =
+            Some(par_yin(&audio, target_sr, &yin).with_context(|| "Failed to track pitch")?);
+    }
+    if let Some(formants) = &options.formants {
+        summary.formants = Some(
+            par_track_formants(&audio, target_sr, formants)
+                .with_context(|| "Failed to track formants")?,
+        );
+    }
+    if let Some(bands) = &options.bands {
+        let frame_length = bands
+            .frame_ms
+            .map_or(bands.hop_length, |ms| samples_from_ms(ms, target_sr));
+        summary.bands = Some(
+            band_levels(&audio, target_sr, bands.fraction, frame_length)
+                .with_context(|| "Failed to analyze the octave bands")?,
+        );
+    }
+
+    // Dump the audio actually fed to the STFT if requested
+    if options.dump_resampled {
+        check_timed_out(options.cancel.as_ref())?;
+        write_audio_file(
+            &output.with_extension("resampled.wav"),
+            &audio,
+            target_sr,
+            WavFormat::Float32,
+        )
+        .with_context(|| "Failed to dump resampled audio")?;
+    }
+    if options.waveform {
+        summary.waveform = Some(audio.clone());
+    }
+
+    Ok((audio, target_sr, summary))
+}
+
+/// FNV-1a hash of the name of an input, stable across runs and platforms
+pub(super) fn name_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Compute the linear-frequency spectrogram of a source, parallelized over frames if requested.
+/// Local WAV files are streamed (chunked read → resample → STFT) so that neither the decoded
+/// nor the resampled audio is held in memory as a whole; the other sources, lenient decoding,
+/// audio dumps, the GPU and the variants of --sweep (which share the decoded audio) go through
+/// the in-memory path. With `pool_width`, streamed frames
+/// are pooled on the fly so the full-resolution spectrogram is never held either.
+pub(super) fn linear_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    parallel: bool,
+    pool_width: Option<usize>,
+) -> Result<(Vec<Vec<f32>>, u32, FileSummary)> {
+    if let Some(path) = source.local_path()
+        && audio_options.raw_pcm.is_none()
+        && !audio_options.lenient
+        && !audio_options.dump_resampled
+        && audio_options.augment.is_empty()
+        && audio_options.filter.is_empty()
+        && audio_options.pitch.is_none()
+        && audio_options.formants.is_none()
+        && audio_options.bands.is_none()
+        && audio_options.gpu.is_none()
+        && audio_options.shared.is_none()
+        && params.frame_alignment == FrameAlignment::Spectrs
+        && params.fft_backend == FftBackendType::RustFft
+    {
+        let mut chunks = if audio_options.start.is_none() && audio_options.end.is_none() {
+            MonoChunks::open(path)
+        } else {
+            MonoChunks::open_segment(path, audio_options.start.unwrap_or(0.0), audio_options.end)
+        }
+        .and_then(|chunks| chunks.with_downmix(audio_options.downmix))
+        .with_context(|| "Failed to read audio")?
+        .with_cancellation(audio_options.cancellation().clone());
+        let original_sr = chunks.sample_rate();
+
+        // Frequency limits can only be checked once the sample rate is known
+        params.validate_for_sample_rate(audio_options.sr.unwrap_or(original_sr))?;
+
+        // Gather the file summary on the fly
+        let mut n_samples = 0;
+        let mut peak = 0.0f32;
+        let mut zero_crossings = ZeroCrossingCounter::default();
+        let mut waveform = Vec::new();
+        let mut loudness = audio_options
+            .loudness
+            .then(|| LoudnessMeter::new(original_sr));
+        let inspect = |chunk: &[f32]| {
+            n_samples += chunk.len();
+            if let Some(meter) = &mut loudness {
+                meter.push(chunk);
+            }
+            peak = chunk.iter().fold(peak, |acc, s| acc.max(s.abs()));
+            zero_crossings.push(chunk);
+            if audio_options.waveform {
+                waveform.extend_from_slice(chunk);
+            }
+        };
+        let (spec, target_sr) = match pool_width {
+            Some(width) => stream_pooled_spectrogram(
+                &mut chunks,
+                audio_options.sr,
+                audio_options.resample_quality,
+                params,
+                parallel,
+                width,
+                inspect,
+            ),
+            None => stream_spectrogram(
+                &mut chunks,
+                audio_options.sr,
+                audio_options.resample_quality,
+                params,
+                parallel,
+                inspect,
+            ),
+        }
+        .with_context(|| "Failed to read audio")?;
+
+        let summary = FileSummary {
+            duration: n_samples as f32 / original_sr as f32,
+            peak_db: 20.0 * peak.max(1e-10).log10(),
+            zero_crossing_rate: zero_crossings.rate(),
+            features: None,
+            loudness: loudness.map(|meter| meter.finish()),
+            waveform: audio_options.waveform.then_some(waveform),
+            pitch: None,
+            formants: None,
+            chroma: None,
+            cpp: None,
+            bands: None,
+            average: None,
+            thumbnail: None,
+        };
+        return Ok((spec, target_sr, summary));
+    }
+
+    let (audio, target_sr, summary) = load_audio(source, output, audio_options)?;
+
+    // Frequency limits can only be checked once the sample rate is known
+    params.validate_for_sample_rate(target_sr)?;
+
+    let spec = match &audio_options.gpu {
+        Some(gpu) => gpu
+            .compute_spectrogram_with_window(
+                &audio,
+                params.n_fft,
+                params.hop_length,
+                &params.window(),
+                params.center,
+                params.spectrogram_type,
+            )
+            .with_context(|| "Failed to compute spectrogram on the GPU")?,
+        None => {
+            let fft = params.fft()?;
+            // librosa's framing is the STFT of the padded and trimmed signal
+            let framed = match params.frame_alignment {
+                FrameAlignment::Spectrs => Some(Cow::Borrowed(&audio[..])),
+                FrameAlignment::Librosa => librosa_framed_signal(
+                    &audio,
+                    params.n_fft,
+                    params.hop_length,
+                    params.win_length,
+                    params.center,
+                )
+                .map(Cow::Owned),
+            };
+            let center = params.center || params.frame_alignment == FrameAlignment::Librosa;
+            match framed {
+                Some(signal) if parallel => par_compute_spectrogram_cancellable(
+                    &signal,
+                    &*fft,
+                    params.hop_length,
+                    &params.window(),
+                    center,
+                    params.spectrogram_type,
+                    audio_options.cancellation(),
+                )?,
+                Some(signal) => compute_spectrogram_with_fft(
+                    &signal,
+                    &*fft,
+                    params.hop_length,
+                    &params.window(),
+                    center,
+                    params.spectrogram_type,
+                ),
+                None => vec![Vec::new(); params.n_freq_bins()],
+            }
+        }
+    };
+
+    Ok((spec, target_sr, summary))
+}
+
+/// Compute the spectrogram of a source as it is exported (mel-scaled if requested, or the
+/// filter bank features of a compatibility preset), parallelized over frames and mel bands
+/// if requested, with its non-finite values replaced (or rejected under --strict). The feature
+/// statistics are only computed when `features` is set.
+pub(super) fn exported_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    parallel: bool,
+    pool_width: Option<usize>,
+    features: bool,
+) -> Result<(Vec<Vec<f32>>, u32, FileSummary)> {
+    let (mut spec, sample_rate, summary) = cached_spectrogram(
+        source,
+        output,
+        audio_options,
+        params,
+        parallel,
+        pool_width,
+        features,
+    )?;
+    check_finite(&mut spec, source, audio_options.strict)?;
+    Ok((spec, sample_rate, summary))
+}
+
+/// Report the frames of a spectrogram holding NaN or infinite values, then fail under
+/// --strict or replace them with zero
+pub(super) fn check_finite<T: Float>(
+    spec: &mut [Vec<T>],
+    source: &dyn AudioSource,
+    strict: bool,
+) -> Result<()> {
+    let frames = non_finite_frames(spec)?;
+    if frames.is_empty() {
+        return Ok(());
+    }
+    let mut listed: Vec<String> = frames.iter().take(10).map(|t| t.to_string()).collect();
+    if frames.len() > listed.len() {
+        listed.push("...".to_string());
+    }
+    let message = format!(
+        "{} frames of the spectrogram of {} hold NaN or infinite values (frames {})",
+        frames.len(),
+        source.name(),
+        listed.join(", ")
+    );
+    if strict {
+        anyhow::bail!(message);
+    }
+    let replaced = sanitize_non_finite(spec, T::zero())?;
+    tracing::warn!(
+        "{}; {} values replaced with zero (--strict to fail instead)",
+        message,
+        replaced
+    );
+    Ok(())
+}
+
+/// Spectrogram of a source as computed by `compute_exported_spectrogram`. Local files go
+/// through the cache of --cache-dir, unless the decoded audio itself is needed (statistics,
+/// waveform panel, pitch tracks, audio dumps, lenient decoding).
+pub(super) fn cached_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    parallel: bool,
+    pool_width: Option<usize>,
+    features: bool,
+) -> Result<(Vec<Vec<f32>>, u32, FileSummary)> {
+    let cached = match (&audio_options.cache, source.local_path()) {
+        (Some(cache), Some(path))
+            if !features
+                && !audio_options.waveform
+                && !audio_options.dump_resampled
+                && !audio_options.lenient
+                && audio_options.augment.is_empty()
+                && audio_options.pitch.is_none()
+                && audio_options.formants.is_none()
+                && audio_options.bands.is_none()
+                && !audio_options.chroma
+                && audio_options.cpp.is_none() =>
+        {
+            let settings = CacheSettings {
+                params,
+                sr: audio_options.sr,
+                resample_quality: format!("{:?}", audio_options.resample_quality),
+                start: audio_options.start,
+                end: audio_options.end,
+                downmix: format!("{:?}", audio_options.downmix),
+                raw_pcm: audio_options.raw_pcm.map(|raw| format!("{:?}", raw)),
+                gpu: audio_options.gpu.is_some(),
+                pool_width,
+                filter: (!audio_options.filter.is_empty())
+                    .then(|| format!("{:?}", audio_options.filter)),
+                gate: audio_options.gate.map(|gate| format!("{:?}", gate)),
+            };
+            let key = SpectrogramCache::key_for_file(path, &settings)
+                .with_context(|| "Failed to hash audio for the cache")?;
+            Some((cache, key))
+        }
+        _ => None,
+    };
+    let Some((cache, key)) = cached else {
+        return compute_exported_spectrogram(
+            source,
+            output,
+            audio_options,
+            params,
+            parallel,
+            pool_width,
+            features,
+        );
+    };
+
+    match cache.load::<CachedSummary>(&key) {
+        Ok(Some((spec, cached))) => {
+            let summary = FileSummary {
+                duration: cached.duration,
+                peak_db: cached.peak_db,
+                zero_crossing_rate: cached.zero_crossing_rate,
+                features: None,
+                loudness: None,
+                waveform: None,
+                pitch: None,
+                formants: None,
+                chroma: None,
+                cpp: None,
+                bands: None,
+                average: None,
+                thumbnail: None,
+            };
+            return Ok((spec, cached.sample_rate, summary));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Ignoring damaged cache entry of {}: {}", source.name(), e),
+    }
+    let (spec, sample_rate, summary) = compute_exported_spectrogram(
+        source,
+        output,
+        audio_options,
+        params,
+        parallel,
+        pool_width,
+        features,
+    )?;
+    let cached = CachedSummary {
+        sample_rate,
+        duration: summary.duration,
+        peak_db: summary.peak_db,
+        zero_crossing_rate: summary.zero_crossing_rate,
+    };
+    if let Err(e) = cache.store(&key, &spec, &cached) {
+        tracing::warn!(
+            "Failed to cache the spectrogram of {}: {}",
+            source.name(),
+            e
+        );
+    }
+    Ok((spec, sample_rate, summary))
+}
+
+/// Compute the spectrogram of a source as it is exported, without the cache (see
+/// `exported_spectrogram`)
+pub(super) fn compute_exported_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    parallel: bool,
+    pool_width: Option<usize>,
+    features: bool,
+) -> Result<(Vec<Vec<f32>>, u32, FileSummary)> {
+    if params.compat != Compat::Librosa {
+        return preset_spectrogram(source, output, audio_options, params, parallel);
+    }
+
+    // Without statistics the linear spectrogram is not needed: project it on the mel
+    // filters before reading it back from the GPU
+    if let Some(gpu) = &audio_options.gpu
+        && params.n_mels.is_some()
+        && !features
+        && !audio_options.chroma
+        && audio_options.cpp.is_none()
+        && audio_options.gate.is_none()
+    {
+        let (audio, target_sr, summary) = load_audio(source, output, audio_options)?;
+        params.validate_for_sample_rate(target_sr)?;
+        let mut spec = gpu
+            .spectrogram(&audio, target_sr, params)
+            .with_context(|| "Failed to compute spectrogram on the GPU")?;
+        if let Some(mel_floor) = params.mel_floor {
+            floor_spectrogram(&mut spec, mel_floor);
+        }
+        return Ok((spec, target_sr, summary));
+    }
+
+    // Gating needs the energy of every frame, and the LPC envelope the spectrum of every frame,
+    // before pooling
+    let pool_width =
+        pool_width.filter(|_| audio_options.gate.is_none() && params.lpc_order.is_none());
+    let (mut spec, target_sr, mut summary) =
+        linear_spectrogram(source, output, audio_options, params, parallel, pool_width)?;
+    // The cepstrum is that of the unweighted spectrum
+    if let Some(cpp_params) = &audio_options.cpp {
+        let cepstrum = real_cepstrum(&spec, params.spectrogram_type, params.n_fft)?;
+        summary.cpp = Some(cepstral_peak_prominence(&cepstrum, target_sr, cpp_params)?);
+    }
+    if let Some(order) = params.lpc_order {
+        let envelope = if parallel {
+            par_lpc_envelope
+        } else {
+            lpc_envelope
+        };
+        spec = envelope(&spec, params.spectrogram_type, params.n_fft, order)?;
+    }
+    if let Some(gains) =
+        params
+            .weighting
+            .bin_gains(params.n_fft, target_sr, params.spectrogram_type)
+    {
+        if parallel {
+            par_apply_weighting(&mut spec, &gains)
+        } else {
+            apply_weighting(&mut spec, &gains)
+        }
+    }
+    if features {
+        summary.add_features(&spec, target_sr, params);
+    }
+    if audio_options.chroma {
+        summary.chroma = Some(chroma(
+            &spec,
+            params.spectrogram_type,
+            target_sr,
+            params.n_fft,
+        ));
+    }
+    if let Some((threshold_db, mode)) = audio_options.gate {
+        gate_frames(&mut spec, threshold_db, params.spectrogram_type, mode)
+            .with_context(|| "Failed to gate frames")?;
+    }
+
+    // Project on the mel (or gammatone) filters if necessary
+    if let Some(filters) = filter_bank(params, target_sr) {
+        spec = if parallel {
+            par_apply_filter_bank(&spec, &filters)
+        } else {
+            apply_filter_bank(&spec, &filters)
+        };
+        if let Some(mel_floor) = params.mel_floor {
+            floor_spectrogram(&mut spec, mel_floor);
+        }
+    }
+
+    Ok((spec, target_sr, summary))
+}
+
+/// Compute the features of a compatibility preset (Kaldi/HTK filter banks or torchaudio
+/// spectrograms) from the whole audio
+pub(super) fn preset_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    parallel: bool,
+) -> Result<(Vec<Vec<f32>>, u32, FileSummary)> {
+    let (audio, target_sr, summary) = load_audio(source, output, audio_options)?;
+
+    // Compressed in `write_outputs`, after denoising, like the other spectrograms
+    let spec = SpectrogramPipeline::builder()
+        .params(*params)
+        .compression(None)
+        .parallel(parallel)
+        .build()?
+        .process_samples_at(&audio, target_sr)
+        .with_context(|| format!("Failed to compute {:?} features", params.compat))?;
+
+    Ok((spec, target_sr, summary))
+}
@@ -0,0 +1,1195 @@
+//! Command-line arguments of `spectrs compute` and the parsers of their values
+
+use crate::cli::Cli;
+use anyhow::{Context, Result};
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use spectrs::augment::{Augmentation, MaskFill, NoiseColor, SpecAugment};
+use spectrs::features::octave::BandFraction;
+use spectrs::io::audio::{DownmixMode, PcmFormat, RawPcm, ResampleQuality};
+use spectrs::io::export::OutputFormat;
+use spectrs::io::image::Colormap;
+use spectrs::spectrogram::compression::Compression;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::fft::FftBackendType;
+use spectrs::spectrogram::filterbank::{FilterBankType, FrequencyScale};
+use spectrs::spectrogram::gpu::Device;
+use spectrs::spectrogram::mel::MelScale;
+use spectrs::spectrogram::ops::GateMode;
+use spectrs::spectrogram::pooling::{FitMode, PoolMode};
+use spectrs::spectrogram::stft::{FrameAlignment, Precision, SpectrogramType, WindowNorm};
+use spectrs::spectrogram::weighting::Weighting;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Which files of an input directory are processed
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct WalkArgs {
+    /// Only process the files of a directory matching one of these globs, relative to the
+    /// directory (e.g. "**/*.wav", "speaker_*/*.wav"), instead of every file with an audio
+    /// extension. `*` does not cross directories, `**` does
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skip the files (and directories) of a directory matching one of these globs, relative
+    /// to the directory (e.g. "**/backup/**")
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Symbolic links in a directory: `files` processes links to files but does not descend
+    /// into linked directories, `follow` also descends into them, `skip` ignores every link
+    #[arg(long, default_value = "files")]
+    pub symlinks: SymlinkPolicy,
+
+    /// Maximum depth of the files processed in a directory (1: only the files directly inside
+    /// it)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: Option<u32>,
+}
+
+/// How symbolic links are treated when walking a directory
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Process links to files, do not descend into linked directories
+    #[default]
+    Files,
+    /// Process links to files and descend into linked directories
+    Follow,
+    /// Ignore every link
+    Skip,
+}
+
+/// File format of the segment lists of --segments-out
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentFormat {
+    /// JSON array of {"start", "end", "label"}, as read by --annotations
+    Json,
+    /// Audacity label track (.txt)
+    Audacity,
+}
+
+impl SegmentFormat {
+    pub(super) fn extension(&self) -> &'static str {
+        match self {
+            SegmentFormat::Json => "json",
+            SegmentFormat::Audacity => "txt",
+        }
+    }
+}
+
+/// Compute spectrograms of files, directories, URLs or stdin (the default command)
+#[derive(clap::Args)]
+pub struct ComputeArgs {
+    /// Input file, directory or URL (URLs require the `http` feature), zip or tar archive of
+    /// WAV files (requires the `archive` feature), or object store prefix or object
+    /// (s3://bucket/prefix, requires the `object-store` feature). `-` reads a single file from
+    /// stdin and writes the output to stdout
+    #[arg(required_unless_present_any = ["live", "manifest"])]
+    pub input: Option<String>,
+
+    /// Process exactly the inputs listed in this CSV (with a header line) or JSON Lines
+    /// (.jsonl) file instead of an input: a `path` per entry (relative to the manifest), and
+    /// optionally its own `start` and `end` (seconds), `label` (shown in --summary-png) and
+    /// `output` name (relative to --output-dir, or to the manifest)
+    #[arg(long, conflicts_with_all = ["input", "live"])]
+    pub manifest: Option<String>,
+
+    /// Write a JSON Lines manifest mapping every input (with its label and segment) to the files
+    /// generated from it
+    #[arg(long, conflicts_with = "live")]
+    pub manifest_out: Option<String>,
+
+    /// Write a JSON report of the run once every input was attempted: the inputs processed,
+    /// skipped (with why) and failed (with the error), the wall time and the throughput. A
+    /// failing input of a directory or manifest no longer stops the others
+    #[arg(long, conflicts_with = "live")]
+    pub report: Option<String>,
+
+    /// Walk the input and apply the filters and skip logic (--skip-indexed, --checkpoint,
+    /// --max-memory-mb) without computing or writing anything, then print every input with the
+    /// files it would be written to, and the effective parameters
+    #[arg(long, conflicts_with = "live")]
+    pub dry_run: bool,
+
+    /// Write the plan of --dry-run as JSON to this file ("-" for stdout) instead of printing it
+    #[arg(long, requires = "dry_run")]
+    pub plan_out: Option<String>,
+
+    /// Record every input in this SQLite database once it was attempted: its status (with the error
+    /// if it failed), label, segment, outputs, parameters, size and modification time, and its
+    /// duration, peak level and statistics (those of --stats-out in the `file_features` table),
+    /// e.g. to list the failed inputs or those shorter than a second with SQL. Requires the
+    /// `index` feature
+    #[arg(long, conflicts_with = "live")]
+    pub index_db: Option<PathBuf>,
+
+    /// Skip the inputs of a directory or manifest that --index-db records as processed with the
+    /// same parameters, from unchanged files (same size and modification time) into outputs
+    /// that still exist. Statistics, summaries and manifests of the run only cover the inputs
+    /// processed
+    #[arg(
+        long,
+        requires = "index_db",
+        conflicts_with_all = ["dataset_out", "cmvn_out", "global_scale"]
+    )]
+    pub skip_indexed: bool,
+
+    /// Record the inputs of a directory or manifest completed so far in this JSON file, and
+    /// skip those it records on the next run with the same parameters, e.g. to resume a long
+    /// conversion after an interruption. Unlike --skip-indexed, the outputs are not checked, so
+    /// it also works when they are written to remote storage. Failed inputs are retried
+    #[arg(
+        long,
+        conflicts_with_all = ["live", "dataset_out", "cmvn_out", "global_scale"]
+    )]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Seconds between saves of --checkpoint (it is also saved at the end of the run)
+    #[arg(
+        long,
+        default_value = "30.0",
+        value_parser = parse_positive_seconds,
+        requires = "checkpoint"
+    )]
+    pub checkpoint_interval: f32,
+
+    /// Give up on an input of a batch after this long (seconds, e.g. 120 or 120s), reporting it
+    /// as failed instead of letting a malformed file hang the run. A timed-out input is
+    /// cancelled and writes no outputs or dataset rows
+    #[arg(long, value_parser = parse_timeout, conflicts_with = "live")]
+    pub per_file_timeout: Option<f32>,
+
+    /// Skip the inputs of a batch whose processing would need more than this much memory
+    /// (megabytes), estimated from their header before decoding them, and report them as
+    /// failed. Inputs whose length is unknown in advance (URLs, objects in a store, members of
+    /// archives) are not checked
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "live")]
+    pub max_memory_mb: Option<u64>,
+
+    /// Capture from the default input device and analyze it in real time instead of reading an
+    /// input (requires the `live` feature). With --format png, a snapshot of the last
+    /// --live-window seconds is rendered to live.png; with --format npy, every frame is appended
+    /// to live.npy. Both are written in --output-dir (or the working directory)
+    #[arg(long, conflicts_with = "input")]
+    pub live: bool,
+
+    /// Stop the live capture after this duration, as seconds or timestamp (runs until
+    /// interrupted otherwise)
+    #[arg(long, value_parser = parse_time, requires = "live")]
+    pub live_duration: Option<f32>,
+
+    /// Seconds between two live PNG snapshots or NPY header updates
+    #[arg(long, default_value = "1.0", value_parser = parse_positive_seconds)]
+    pub live_interval: f32,
+
+    /// Seconds of audio shown in live PNG snapshots
+    #[arg(long, default_value = "10.0", value_parser = parse_positive_seconds)]
+    pub live_window: f32,
+
+    /// Decode the input as headerless PCM instead of WAV, described as
+    /// sr=<sample rate>,fmt=<u8|s16le|s24le|s32le|f32le>[,channels=<N>]. Applies to stdin,
+    /// to a single file of any extension and to the .raw and .pcm files of a directory
+    #[arg(long, value_parser = parse_raw_pcm)]
+    pub raw_pcm: Option<RawPcm>,
+
+    /// How multichannel audio (WAV or raw PCM) is reduced to mono: `average` (all channels), `first`,
+    /// `channel=N` (channels start from 0) or `itu775` (ITU-R BS.775 downmix of 5.1 audio)
+    #[arg(long, default_value = "average", value_parser = parse_downmix)]
+    pub downmix: DownmixMode,
+
+    /// Band-pass the audio between two frequencies (Hz) before the STFT, e.g. 300:3400 for
+    /// telephone speech (4th-order Butterworth edges)
+    #[arg(long, value_parser = parse_bandpass)]
+    pub bandpass: Option<(f32, f32)>,
+
+    /// Remove narrow bands around these frequencies (Hz, comma-separated) before the STFT,
+    /// e.g. 50,100,150 for mains hum and its harmonics
+    #[arg(long, value_delimiter = ',')]
+    pub notch: Vec<f32>,
+
+    /// Augment the audio before the STFT, with transforms applied in order (comma-separated):
+    /// noise:white:SNR or noise:pink:SNR (dB), gain:MIN:MAX (dB), shift:SECONDS (up to that
+    /// delay or advance) and stretch:MIN:MAX (speed factors, by resampling)
+    #[arg(long, value_delimiter = ',', value_parser = parse_augmentation)]
+    pub augment: Vec<Augmentation>,
+
+    /// Mask the exported features as SpecAugment does, e.g. freq=2:27,time=2:40,fill=mean:
+    /// freq=N:W masks N bands of up to W rows, time=N:W masks N spans of up to W frames, and
+    /// fill is zero (the default), mean or a value. Images are not masked
+    #[arg(long, value_parser = parse_spec_augment)]
+    pub spec_augment: Option<SpecAugment>,
+
+    /// Seed of the random amounts of --augment and of the masks of --spec-augment, combined
+    /// with the name of each input so that inputs are augmented differently but reproducibly
+    #[arg(long, default_value_t = 0)]
+    pub augment_seed: u64,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    /// Ignore the .spectrs.toml files of an input directory. Otherwise, the spectrogram
+    /// parameters set in such a file (e.g. `n_fft = 256` for a folder of telephone recordings)
+    /// override those of the command line for the inputs beneath its directory, a file of a
+    /// subdirectory overriding those of its parents
+    #[arg(long)]
+    pub no_directory_params: bool,
+
+    /// Output directory path (optional). Output files are created inside this directory with the
+    /// same relative structure as inputs. An object store prefix (s3://bucket/prefix, requires
+    /// the `object-store` feature) receives every output as soon as its input is done.
+    #[arg(long)]
+    pub output_dir: Option<String>,
+
+    /// Name outputs after this template instead of the name of their input, e.g.
+    /// "{stem}__{n_mels}mel_{sr}hz.{ext}" to write variants of the same inputs to one
+    /// directory. Variables: {stem} (name of the input, or `output` of its manifest entry),
+    /// {parent} (directory of the input), {ext} (extension of the format), {sr} (sample rate of
+    /// the spectrogram, `native` if unknown before decoding), {n_fft}, {hop}, {win}, {n_mels}
+    /// (0 for linear spectrograms) and {chunk} (index of the segment among the manifest entries
+    /// of its input, 0 otherwise). The template must end with ".{ext}"
+    #[arg(long, conflicts_with = "dataset_out")]
+    pub name_template: Option<String>,
+
+    /// Write every output directly into --output-dir instead of recreating the directories of
+    /// the inputs. Outputs of different directories with the same name are told apart by a
+    /// hash of the path of their input (e.g. a_1f3c9e0b.png), listed in --report
+    #[arg(long, requires = "output_dir", conflicts_with = "dataset_out")]
+    pub flatten: bool,
+
+    /// Target sample rate (optional). If specified, resampling is applied before spectrogram
+    /// creation. Hz, or a preset: speech16k (16000), music22k (22050) or cd44k (44100). Mel
+    /// bands requested above its Nyquist frequency (--f-max) are capped to it
+    #[arg(long, value_parser = parse_sample_rate)]
+    pub sr: Option<u32>,
+
+    /// Resampler quality preset (only used together with --sr)
+    #[arg(long, default_value = "fast")]
+    pub resample_quality: ResampleQuality,
+
+    /// FFT window size
+    #[arg(long, default_value = "2048")]
+    pub n_fft: usize,
+
+    /// Hop length
+    #[arg(long, default_value = "512")]
+    pub hop_length: usize,
+
+    /// Overlap between successive windows as a percentage of win_length (e.g. 75%).
+    /// Alternative to --hop-length
+    #[arg(long, value_parser = parse_overlap, conflicts_with = "hop_length")]
+    pub overlap: Option<f32>,
+
+    /// Window length
+    #[arg(long, default_value = "2048")]
+    pub win_length: usize,
+
+    /// Window length in milliseconds (e.g. 25), resolved against --sr, or against the sample
+    /// rate of each input without it. The FFT size defaults to the next power of two of the
+    /// window and the hop to a quarter of it
+    #[arg(long, value_parser = parse_milliseconds, conflicts_with = "win_length")]
+    pub win_ms: Option<f32>,
+
+    /// Hop length in milliseconds (e.g. 10), resolved against --sr, or against the sample rate
+    /// of each input without it
+    #[arg(long, value_parser = parse_milliseconds, conflicts_with_all = ["hop_length", "overlap"])]
+    pub hop_ms: Option<f32>,
+
+    /// Choose the FFT size, window, hop and mel bands not given explicitly from the sample
+    /// rate (after --sr) and the duration of each input: 25 ms windows every 10 ms and mel bands
+    /// in proportion to the bandwidth, instead of defaults tuned for music at 22-44 kHz. The
+    /// chosen values are printed
+    #[arg(long, conflicts_with_all = ["win_ms", "hop_ms", "overlap", "live"])]
+    pub auto_params: bool,
+
+    /// Compute a spectrogram for every combination of these parameter values, e.g. `--sweep
+    /// n_mels=64,128 --sweep n_fft=1024,2048` for four variants of each input, all computed from
+    /// the same decoded and resampled audio. Parameters: n_fft, hop_length, win_length and
+    /// n_mels, whose variable ({n_fft}, {hop}, {win} or {n_mels}) must be in --name-template
+    #[arg(
+        long,
+        value_parser = parse_sweep,
+        requires = "name_template",
+        conflicts_with_all = ["auto_params", "live", "dataset_out"]
+    )]
+    pub sweep: Vec<Sweep>,
+
+    /// Enable centering in the FFT window
+    #[arg(long, default_value = "true")]
+    pub center: bool,
+
+    /// How frames are laid over the signal: spectrs (frames start every hop from the first
+    /// sample, as many as hold a whole window) or librosa (1 + n/hop frames with --center, the
+    /// signal being padded with n_fft/2 zeros on both sides, 1 + (n - n_fft)/hop without), so
+    /// that frame counts match librosa's exactly
+    #[arg(long, default_value = "spectrs")]
+    pub frame_alignment: FrameAlignment,
+
+    /// Use a periodic Hann window (true, as librosa's and torch's STFTs) or a symmetric one
+    /// (false). Defaults to periodic with --compat torchaudio and --frame-alignment librosa,
+    /// symmetric otherwise
+    #[arg(long)]
+    pub periodic_window: Option<bool>,
+
+    /// Normalize the window, scaling the values: by its sum (scipy.signal.stft's magnitudes),
+    /// by the square root of its energy (torchaudio's Spectrogram with normalized=True) or by
+    /// the square root of n_fft (torch.stft with normalized=True)
+    #[arg(long, default_value = "none")]
+    pub window_norm: WindowNorm,
+
+    /// Spectrogram type
+    #[arg(long, default_value = "power")]
+    pub spec_type: SpectrogramType,
+
+    /// Number of mel bands (optional, for mel spectrograms)
+    #[arg(long)]
+    pub n_mels: Option<usize>,
+
+    /// Minimum frequency (Hz)
+    #[arg(long, default_value = "0.0")]
+    pub f_min: Option<f32>,
+
+    /// Maximum frequency (Hz, optional). In unspecified, it's sr/2 by Nyquist theorem
+    #[arg(long)]
+    pub f_max: Option<f32>,
+
+    /// Mel scale type (only applies to mel spectrograms)
+    #[arg(long, default_value = "slaney")]
+    pub mel_scale: MelScale,
+
+    /// Filter bank of the --n-mels bands: triangular mel filters, or 4th-order gammatone
+    /// filters spaced on the ERB-rate scale (auditory models)
+    #[arg(long = "filterbank", value_enum, default_value_t = FilterBankType::Mel, requires = "n_mels")]
+    pub filter_bank: FilterBankType,
+
+    /// Frequency scale the triangular --n-mels bands are spaced on: mel (see --mel-scale),
+    /// Bark or ERB rate
+    #[arg(long, value_enum, default_value_t = FrequencyScale::Mel, requires = "n_mels")]
+    pub frequency_scale: FrequencyScale,
+
+    /// Replace the spectrum of every frame by the envelope of its all-pole model of this order
+    /// (linear prediction, autocorrelation method), e.g. 18 for the formants of speech at
+    /// 16 kHz (2 + sr/1000): smooth formant peaks without the harmonics, rendered, banded and
+    /// exported like spectra
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "live")]
+    pub lpc_order: Option<u64>,
+
+    /// Weight the frequency bins with the A or C curve of IEC 61672-1 (sound level meters)
+    /// before the --n-mels bands and the feature statistics; Z leaves them unweighted
+    #[arg(long, value_enum, default_value_t = Weighting::Z, ignore_case = true)]
+    pub weighting: Weighting,
+
+    /// Reproduce the features of another toolkit. `kaldi` and `htk` compute log mel filter
+    /// banks matching Kaldi's compute-fbank-feats or HTK's FBANK (25 ms frames every 10 ms;
+    /// --n-mels and --f-max override the preset, the other STFT options are ignored).
+    /// `torchaudio` matches torchaudio's MelSpectrogram, whose defaults replace those of the
+    /// STFT and mel options not given explicitly
+    #[arg(long, default_value = "librosa")]
+    pub compat: Compat,
+
+    /// Standard deviation of the noise added to the samples with --compat kaldi/htk
+    /// (Kaldi's default is 1.0; keep 0 for reproducible features)
+    #[arg(long, default_value = "0.0")]
+    pub dither: f32,
+
+    /// Device computing the STFT and mel projection. `gpu` batches frames through wgpu
+    /// compute shaders (requires the `gpu` feature and a power-of-two --n-fft)
+    #[arg(long, default_value = "cpu")]
+    pub device: Device,
+
+    /// Compute on a single thread in a fixed order (files sorted by path, CMVN statistics
+    /// merged in that order), so that identical inputs give bit-identical outputs from run
+    /// to run. Not available with --device gpu
+    #[arg(long)]
+    pub deterministic: bool,
+
+    /// Floating-point precision of the STFT and the mel projection. f64 values are written
+    /// without rounding (float64 NPY arrays or full-precision CSV), so f64 requires
+    /// --format csv or npy
+    #[arg(long, default_value = "f32")]
+    pub precision: Precision,
+
+    /// Library computing the FFTs of the STFT: rustfft, fftw (requires the `fftw` feature) or
+    /// accelerate (Apple's vDSP, requires macOS and the `accelerate` feature), often 2-3x
+    /// faster on the platforms they are tuned for. Only with --compat librosa and --device cpu
+    #[arg(long, default_value = "rustfft")]
+    pub fft_backend: FftBackendType,
+
+    /// Colormap for visualization
+    #[arg(long, default_value = "viridis")]
+    pub colormap: Colormap,
+
+    /// Level in dB shown with the first colour of the colormap (e.g. -80), instead of the
+    /// minimum of each image. With --vmax, every image of a batch shares the same scale
+    #[arg(long, allow_negative_numbers = true)]
+    pub vmin: Option<f32>,
+
+    /// Level in dB shown with the last colour of the colormap (e.g. 0), instead of the maximum
+    /// of each image
+    #[arg(long, allow_negative_numbers = true)]
+    pub vmax: Option<f32>,
+
+    /// Lower and upper percentiles of the values spanning the colormap (e.g. 2,98), instead of
+    /// the minimum and maximum, so that a few outliers do not wash out the image
+    #[arg(long, value_parser = parse_percentile_clip, conflicts_with_all = ["vmin", "vmax"])]
+    pub percentile_clip: Option<(f32, f32)>,
+
+    /// Gamma correction of the colours: below 1 brightens quiet parts, above 1 darkens them
+    #[arg(long, default_value_t = 1.0)]
+    pub gamma: f32,
+
+    /// Scan every input in a first pass and render all images in dB with the dataset-wide
+    /// minimum and maximum (or --percentile-clip percentiles; --vmin/--vmax still fix their
+    /// bound), so that images of different files are comparable
+    #[arg(long)]
+    pub global_scale: bool,
+
+    /// Lowest frequency (Hz) shown in images (e.g. 0), independently of --f-min: the rows
+    /// below it are cropped before rendering
+    #[arg(long)]
+    pub display_fmin: Option<f32>,
+
+    /// Highest frequency (Hz) shown in images (e.g. 8000 for speech), independently of
+    /// --f-max: the rows above it are cropped before rendering
+    #[arg(long)]
+    pub display_fmax: Option<f32>,
+
+    /// Output formats, comma-separated (e.g. png,npy): every format is written from the same
+    /// computed spectrogram, next to each other with their own extension
+    #[arg(long, default_value = "png", value_delimiter = ',')]
+    pub format: Vec<OutputFormat>,
+
+    /// Compress the exported values to a log scale: log (natural log of the power), db (dB of
+    /// the power), db:N (dB no lower than N below the peak of each output) or log1p. Images are
+    /// still rendered from the linear values
+    #[arg(long, value_parser = parse_compression)]
+    pub compress: Option<Compression>,
+
+    /// Smallest value taken the logarithm of by --compress log or db, so that silent frames
+    /// get a finite level (librosa's amin, 1e-10 by default)
+    #[arg(long, requires = "compress")]
+    pub amin: Option<f32>,
+
+    /// Raise the filter bank bands to at least this value (e.g. 1e-10), so that silent frames
+    /// stay positive in the exported values
+    #[arg(long, requires = "n_mels")]
+    pub mel_floor: Option<f32>,
+
+    /// Bring every output to exactly N frames, so that all files of a dataset share one shape
+    /// (e.g. to stack their NPY files into a batch for an embedding model)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "tile_width")]
+    pub fixed_frames: Option<u32>,
+
+    /// How outputs are brought to --fixed-frames: pad (with silence after the end, or truncate)
+    /// or resample (interpolate along time, keeping the whole file)
+    #[arg(long, default_value = "pad", requires = "fixed_frames")]
+    pub fixed_mode: FitMode,
+
+    /// Pool every N frames of images into one column, by their mean or maximum (e.g. max:16),
+    /// for compact overviews of long files. Numerical outputs keep every frame
+    #[arg(long, value_parser = parse_time_reduce)]
+    pub time_reduce: Option<(PoolMode, usize)>,
+
+    /// Maximum image width in pixels (optional). Longer spectrograms are shrunk by averaging
+    /// adjacent frames
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Maximum image height in pixels (optional). Taller spectrograms are shrunk by averaging
+    /// adjacent frequency bins
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Split each image into tiles of at most this many frames (e.g. 4096), written as
+    /// <name>.tile0000.png, <name>.tile0001.png, ... with a <name>.tiles.json index giving the
+    /// time range of every tile. For recordings too long to fit in a single image
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "width")]
+    pub tile_width: Option<u32>,
+
+    /// Stack the waveform (amplitude over time) of the processed audio beneath the spectrogram
+    /// in the same image
+    #[arg(long, conflicts_with = "tile_width")]
+    pub with_waveform: bool,
+
+    /// Field delimiter for CSV outputs (e.g. ';' for European-locale spreadsheets)
+    #[arg(long, default_value = ",")]
+    pub csv_delimiter: char,
+
+    /// Decimal separator for CSV outputs
+    #[arg(long, default_value = ".")]
+    pub csv_decimal: char,
+
+    /// Number of digits after the decimal separator in CSV outputs (optional, shortest exact
+    /// representation if unspecified)
+    #[arg(long)]
+    pub csv_precision: Option<usize>,
+
+    /// Start of the segment to process, as seconds (e.g. 90.5) or timestamp (e.g. 00:01:30)
+    #[arg(long, value_parser = parse_time)]
+    pub start: Option<f32>,
+
+    /// End of the segment to process, as seconds (e.g. 120) or timestamp (e.g. 00:02:00)
+    #[arg(long, value_parser = parse_time)]
+    pub end: Option<f32>,
+
+    /// Also write the decoded (and resampled, if --sr is given) mono audio next to each
+    /// output, as <name>.resampled.wav, for debugging
+    #[arg(long)]
+    pub dump_resampled: bool,
+
+    /// Write a versioned JSON sidecar (<name>.meta.json) next to each output, describing the
+    /// source, sample rate, parameters and shape of the stored values (of the first format,
+    /// if several are given)
+    #[arg(long)]
+    pub sidecar: bool,
+
+    /// Append every spectrogram (with the path of its input, the label of its directory and
+    /// its sample rate) to this Parquet dataset instead of writing an output per input, e.g.
+    /// features.parquet, or to this Arrow IPC file if it ends in .arrow, .feather or .ipc. The
+    /// values are those of numerical formats (compressed, normalized and masked as requested)
+    /// and the parameters are stored in the metadata of the file. Requires the `dataset`
+    /// feature
+    #[arg(
+        long,
+        conflicts_with_all = ["format", "sidecar", "tile_width", "with_waveform", "pitch_overlay", "formant_overlay", "onset_overlay"]
+    )]
+    pub dataset_out: Option<PathBuf>,
+
+    /// Cache computed spectrograms in this directory, keyed by a hash of the audio content and
+    /// of the settings: later runs with the same settings skip decoding and the STFT (e.g.
+    /// while tweaking how images are rendered)
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Salvage as much audio as possible from damaged WAV files (truncated data, wrong header
+    /// lengths) instead of failing, with a warning reporting the recovered duration
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Fail on spectrograms holding NaN or infinite values (e.g. from corrupted segments of
+    /// audio) instead of replacing them with zero with a warning naming the frames
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Write per-file spectral statistics (centroid, bandwidth, rolloff, flatness, RMS,
+    /// zero-crossing rate) to this CSV file, one row per input, or as JSON records if the file
+    /// ends in .json
+    #[arg(long)]
+    pub stats_out: Option<String>,
+
+    /// Add the loudness of the decoded audio (ITU-R BS.1770) to --stats-out: integrated and
+    /// maximum short-term loudness (LUFS), true peak (dBTP) and sample peak (dBFS), with the
+    /// short-term loudness over time in JSON records
+    #[arg(long, requires = "stats_out")]
+    pub loudness: bool,
+
+    /// Track the pitch (f0) of the processed audio with YIN and write it next to each output
+    /// as <name>.f0.csv or <name>.f0.json (csv or json), one row per frame with an empty f0
+    /// (null in JSON) where unvoiced
+    #[arg(long)]
+    pub pitch_out: Option<OutputFormat>,
+
+    /// Draw the pitch contour of the processed audio over spectrogram images
+    #[arg(long)]
+    pub pitch_overlay: bool,
+
+    /// Pitch range (Hz) searched by --pitch-out and --pitch-overlay, e.g. 80:400 for speech
+    #[arg(long, default_value = "65:2093", value_parser = parse_bandpass)]
+    pub pitch_range: (f32, f32),
+
+    /// Track the formants of the processed audio (Burg LPC over the frames of the STFT, as
+    /// Praat) and write them next to each output as <name>.formants.csv (frequency and
+    /// bandwidth of F1 to F3 per frame, empty where not found) or <name>.formants.json (every
+    /// formant found below --formant-ceiling)
+    #[arg(long)]
+    pub formants_out: Option<OutputFormat>,
+
+    /// Draw the F1 (red), F2 (green) and F3 (azure) tracks over spectrogram images
+    #[arg(long)]
+    pub formant_overlay: bool,
+
+    /// Highest formant searched by --formants-out and --formant-overlay (Hz): 5500 for adult
+    /// female voices, 5000 for male ones
+    #[arg(long, default_value_t = 5500.0)]
+    pub formant_ceiling: f32,
+
+    /// Detect onsets (spectral flux peaks) in every spectrogram and write them next to each
+    /// output as <name>.onsets.csv or <name>.onsets.json (csv or json): frame, time and
+    /// normalized strength of every onset
+    #[arg(long)]
+    pub onsets_out: Option<OutputFormat>,
+
+    /// Mark the detected onsets over spectrogram images with vertical lines
+    #[arg(long)]
+    pub onset_overlay: bool,
+
+    /// Split every input into active and silent segments from the energy of its frames and
+    /// write them next to each output as <name>.segments.json or <name>.segments.txt (an
+    /// Audacity label track), labeled "active" or "silence" and covering the whole input, e.g.
+    /// to trim silence downstream
+    #[arg(long)]
+    pub segments_out: Option<SegmentFormat>,
+
+    /// Energy of the quietest active frames of --segments-out, in dB below the loudest frame
+    #[arg(long, default_value_t = -40.0, allow_negative_numbers = true, value_parser = parse_gate_db, requires = "segments_out")]
+    pub activity_db: f32,
+
+    /// Shortest silence between active segments of --segments-out in milliseconds; shorter
+    /// pauses are part of the surrounding activity
+    #[arg(long, default_value_t = 200.0, value_parser = parse_milliseconds, requires = "segments_out")]
+    pub min_silence_ms: f32,
+
+    /// Shortest active segment of --segments-out in milliseconds; shorter bursts are silence
+    #[arg(long, default_value_t = 100.0, value_parser = parse_milliseconds, requires = "segments_out")]
+    pub min_active_ms: f32,
+
+    /// Write the chroma of every input (energy of the 12 pitch classes, C to B, per frame,
+    /// scaled so that the strongest is 1) next to each output as <name>.chroma.csv, .npy or
+    /// .json, laid out like the spectrogram exports ([pitch class][frame])
+    #[arg(long)]
+    pub chroma_out: Option<OutputFormat>,
+
+    /// Write the tonal centroids of every input (tonnetz: the chroma projected on the circles
+    /// of fifths, minor thirds and major thirds, 6 values per frame) next to each output as
+    /// <name>.tonnetz.csv, .npy or .json, laid out like the spectrogram exports
+    #[arg(long)]
+    pub tonnetz_out: Option<OutputFormat>,
+
+    /// Write the cepstral peak prominence of every frame (CPP: height in dB of the peak of the
+    /// real cepstrum above its trend line, a measure of voice periodicity) and the pitch of the
+    /// peak next to each output as <name>.cpp.csv or <name>.cpp.json (with the mean CPP)
+    #[arg(long)]
+    pub cpp_out: Option<OutputFormat>,
+
+    /// Pitch range (Hz) whose periods --cpp-out searches the cepstral peak at; n_fft must hold
+    /// two periods of the lowest pitch
+    #[arg(long, default_value = "60:330", value_parser = parse_bandpass, requires = "cpp_out")]
+    pub cpp_range: (f32, f32),
+
+    /// Write the octave or third-octave band levels of the processed audio (IEC 61260-1 band
+    /// filters, see --bands) next to each output as <name>.bands.csv (a row per frame and a
+    /// column per band), .json (with the Leq of every band) or .npy ([band][frame]), in dB
+    /// relative to full scale
+    #[arg(long, conflicts_with = "live")]
+    pub band_levels_out: Option<OutputFormat>,
+
+    /// Bands of --band-levels-out: octave (31.5 Hz to 16 kHz) or third (25 Hz to 20 kHz)
+    #[arg(long, default_value = "third", requires = "band_levels_out")]
+    pub bands: BandFraction,
+
+    /// Duration of the frames of --band-levels-out in milliseconds (e.g. 125 for the fast
+    /// time weighting, 1000 for one level per second), the hop length by default
+    #[arg(long, value_parser = parse_milliseconds, requires = "band_levels_out")]
+    pub band_frame_ms: Option<f32>,
+
+    /// Outline labeled time intervals over spectrogram images, e.g. the events of a dataset: an
+    /// Audacity label track (.txt) or a JSON array of {"start", "end", "label"} (seconds from
+    /// the start of the input, optionally with "f_min" and "f_max" in Hz). With a directory, the
+    /// labels of an input a/b.wav are read from a/b.json or a/b.txt in it, if present
+    #[arg(long, conflicts_with = "live")]
+    pub annotations: Option<PathBuf>,
+
+    /// Height above the local mean of the onset strength (normalized to [0, 1]) of the peaks
+    /// kept as onsets: raise it to keep only the sharpest events
+    #[arg(long, default_value_t = 0.07)]
+    pub onset_delta: f32,
+
+    /// Compute per-band mean and variance over all inputs in a first pass, save them to this
+    /// JSON file and normalize every output with them (CMVN)
+    #[arg(long, conflicts_with = "cmvn_in")]
+    pub cmvn_out: Option<String>,
+
+    /// Normalize every output with per-band mean and variance loaded from this JSON file
+    /// (previously written with --cmvn-out), e.g. to process inference data like training data
+    #[arg(long)]
+    pub cmvn_in: Option<String>,
+
+    /// Gate the frames whose energy is more than this many dB below the loudest frame of each
+    /// input (e.g. -60), before the mel projection, so that the noise floor of sparse
+    /// recordings does not clutter images and features
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_gate_db)]
+    pub gate_db: Option<f32>,
+
+    /// Whether gated frames are set to zero (keeping the timeline) or dropped
+    #[arg(long, default_value = "zero", requires = "gate_db")]
+    pub gate_mode: GateMode,
+
+    /// Subtract a noise floor from every spectrogram before it is written (spectral
+    /// subtraction), estimated from the quietest frames of each input or from --noise-wav.
+    /// For recordings over a steady background (hydrophones, wildlife, hum)
+    #[arg(long)]
+    pub denoise: bool,
+
+    /// Estimate the noise floor from this recording of the background alone (processed with
+    /// the same parameters) instead of the quietest frames of each input
+    #[arg(long, requires = "denoise")]
+    pub noise_wav: Option<String>,
+
+    /// Percentage of the quietest frames of each input the noise floor is estimated from
+    #[arg(long, default_value = "10%", value_parser = parse_noise_percent, requires = "denoise", conflicts_with = "noise_wav")]
+    pub noise_percent: f32,
+
+    /// Fraction of their original level values are kept at, at least, after subtracting the
+    /// noise floor (0 for plain subtraction, which leaves isolated peaks in the background)
+    #[arg(long, default_value_t = 0.02, value_parser = parse_fraction, requires = "denoise")]
+    pub denoise_floor: f32,
+
+    /// Render a summary figure of the run (durations, peak levels, files per label directory)
+    #[arg(long)]
+    pub summary_png: Option<String>,
+
+    /// Average the spectrograms of all inputs into a single one written to this path, as an
+    /// image (.png) or an array (.npy), e.g. to characterize a recording setup or a dataset.
+    /// Every spectrogram is first brought to --average-frames frames; the long-term spectrum
+    /// over all frames of all inputs is written next to it as <name>.spectrum.csv
+    #[arg(long, conflicts_with = "live")]
+    pub average_out: Option<PathBuf>,
+
+    /// Number of frames every spectrogram is pooled or stretched to before --average-out
+    /// averages them
+    #[arg(long, default_value_t = 256, value_parser = clap::value_parser!(u32).range(1..), requires = "average_out")]
+    pub average_frames: u32,
+
+    /// Write a single static HTML page embedding a thumbnail of the spectrogram of every input
+    /// with its name, duration, sample rate, peak level and shape, and the inputs that failed,
+    /// e.g. to eyeball a dataset in a browser
+    #[arg(long, conflicts_with = "live")]
+    pub report_html: Option<PathBuf>,
+}
+
+/// Parse a time given either as plain seconds ("90.5") or as a timestamp ("01:30", "00:01:30.5")
+pub(super) fn parse_time(value: &str) -> Result<f32, String> {
+    let invalid = |reason: &str| format!("Invalid time '{}': {}", value, reason);
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid("expected SS, MM:SS or HH:MM:SS"));
+    }
+    let mut seconds = 0.0f64;
+    for (i, part) in parts.iter().enumerate() {
+        let v: f64 = part
+            .trim()
+            .parse()
+            .map_err(|_| invalid("expected SS, MM:SS or HH:MM:SS"))?;
+        if !v.is_finite() || v.is_sign_negative() {
+            return Err(invalid("every field must be a non-negative number"));
+        }
+        // Minutes and seconds below a higher field are less than 60
+        if i > 0 && v >= 60.0 {
+            return Err(invalid("minutes and seconds must be below 60"));
+        }
+        seconds = seconds * 60.0 + v;
+    }
+    Ok(seconds as f32)
+}
+
+/// Parse a strictly positive number of seconds
+pub(super) fn parse_positive_seconds(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(seconds),
+        _ => Err(format!(
+            "Invalid duration '{}': expected a positive number of seconds",
+            value
+        )),
+    }
+}
+
+/// Parse a timeout as a positive number of seconds, with an optional `s` unit ("120s")
+pub(super) fn parse_timeout(value: &str) -> Result<f32, String> {
+    let trimmed = value.trim();
+    parse_positive_seconds(trimmed.strip_suffix('s').unwrap_or(trimmed))
+        .map_err(|_| format!("Invalid timeout '{}': expected seconds, like 120s", value))
+}
+
+/// Parse an overlap percentage ("75%" or "75") into a fraction in [0, 1)
+pub(super) fn parse_overlap(value: &str) -> Result<f32, String> {
+    let percentage: f32 = value.trim().trim_end_matches('%').parse().map_err(|_| {
+        format!(
+            "Invalid overlap '{}': expected a percentage like 75%",
+            value
+        )
+    })?;
+    if !(0.0..100.0).contains(&percentage) {
+        return Err(format!(
+            "Invalid overlap '{}': must be in the range [0%, 100%)",
+            value
+        ));
+    }
+    Ok(percentage / 100.0)
+}
+
+/// Named sample rates of --sr
+pub(super) const SAMPLE_RATE_PRESETS: [(&str, u32); 3] =
+    [("speech16k", 16000), ("music22k", 22050), ("cd44k", 44100)];
+
+/// Parse a sample rate in Hz ("16000") or one of the presets ("speech16k")
+pub(crate) fn parse_sample_rate(value: &str) -> Result<u32, String> {
+    let value = value.trim();
+    if let Some((_, sample_rate)) = SAMPLE_RATE_PRESETS.iter().find(|(name, _)| *name == value) {
+        return Ok(*sample_rate);
+    }
+    match value.parse::<u32>() {
+        Ok(sample_rate) if sample_rate > 0 => Ok(sample_rate),
+        _ => Err(format!(
+            "Invalid sample rate '{}': expected a positive number of Hz or one of {}",
+            value,
+            SAMPLE_RATE_PRESETS.map(|(name, _)| name).join(", ")
+        )),
+    }
+}
+
+/// Parse a positive duration in milliseconds ("25" or "25ms")
+pub(super) fn parse_milliseconds(value: &str) -> Result<f32, String> {
+    match value.trim().trim_end_matches("ms").trim().parse::<f32>() {
+        Ok(ms) if ms > 0.0 && ms.is_finite() => Ok(ms),
+        _ => Err(format!(
+            "Invalid duration '{}': expected a positive number of milliseconds like 25ms",
+            value
+        )),
+    }
+}
+
+/// Parse a pair of percentiles such as "2,98"
+pub(super) fn parse_percentile_clip(value: &str) -> Result<(f32, f32), String> {
+    let invalid = || {
+        format!(
+            "Invalid percentiles '{}': expected LOW,HIGH with 0 <= LOW < HIGH <= 100",
+            value
+        )
+    };
+    let (low, high) = value.split_once(',').ok_or_else(invalid)?;
+    let low: f32 = low.trim().parse().map_err(|_| invalid())?;
+    let high: f32 = high.trim().parse().map_err(|_| invalid())?;
+    if !(0.0 <= low && low < high && high <= 100.0) {
+        return Err(invalid());
+    }
+    Ok((low, high))
+}
+
+/// Parse a pooling of frames such as "max:16"
+pub(super) fn parse_time_reduce(value: &str) -> Result<(PoolMode, usize), String> {
+    let invalid = || {
+        format!(
+            "Invalid time reduction '{}': expected mean:N or max:N with N >= 1",
+            value
+        )
+    };
+    let (mode, factor) = value.split_once(':').ok_or_else(invalid)?;
+    let mode = match mode.trim() {
+        "mean" => PoolMode::Mean,
+        "max" => PoolMode::Max,
+        _ => return Err(invalid()),
+    };
+    match factor.trim().parse::<usize>() {
+        Ok(factor) if factor >= 1 => Ok((mode, factor)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse a compression such as "log1p" or "db:80"
+pub(super) fn parse_compression(value: &str) -> Result<Compression, String> {
+    let invalid = || {
+        format!(
+            "Invalid compression '{}': expected log, log1p, db or db:N with N >= 0",
+            value
+        )
+    };
+    match value.trim().split_once(':') {
+        None => match value.trim() {
+            "log" => Ok(Compression::LogPower),
+            "log1p" => Ok(Compression::Log1p),
+            "db" => Ok(Compression::Db { top_db: None }),
+            _ => Err(invalid()),
+        },
+        Some(("db", top_db)) => match top_db.trim().parse::<f32>() {
+            Ok(top_db) if top_db >= 0.0 && top_db.is_finite() => Ok(Compression::Db {
+                top_db: Some(top_db),
+            }),
+            _ => Err(invalid()),
+        },
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// Parse a band such as "300:3400" (Hz)
+pub(super) fn parse_bandpass(value: &str) -> Result<(f32, f32), String> {
+    let invalid = || {
+        format!(
+            "Invalid band '{}': expected LOW:HIGH in Hz with 0 < LOW < HIGH",
+            value
+        )
+    };
+    let (low, high) = value.split_once(':').ok_or_else(invalid)?;
+    let low: f32 = low.trim().parse().map_err(|_| invalid())?;
+    let high: f32 = high.trim().parse().map_err(|_| invalid())?;
+    if !(0.0 < low && low < high && high.is_finite()) {
+        return Err(invalid());
+    }
+    Ok((low, high))
+}
+
+/// Parameters varied by --sweep (named as in .spectrs.toml files), with their variable in
+/// --name-template
+pub(super) const SWEEP_PARAMS: [(&str, &str); 4] = [
+    ("n_fft", "n_fft"),
+    ("hop_length", "hop"),
+    ("win_length", "win"),
+    ("n_mels", "n_mels"),
+];
+
+/// Values taken by a spectrogram parameter across the variants of --sweep
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    /// Name of the parameter, as in .spectrs.toml files
+    pub param: String,
+    pub values: Vec<usize>,
+}
+
+impl Sweep {
+    /// Variable of --name-template holding the value of the parameter
+    pub(super) fn variable(&self) -> &'static str {
+        SWEEP_PARAMS
+            .iter()
+            .find(|(param, _)| *param == self.param)
+            .map_or("", |(_, variable)| variable)
+    }
+}
+
+/// Parse a sweep such as "n_mels=64,128"
+pub(super) fn parse_sweep(value: &str) -> Result<Sweep, String> {
+    let invalid = |reason: &str| format!("Invalid sweep '{}': {}", value, reason);
+    let (param, values) = value
+        .split_once('=')
+        .ok_or_else(|| invalid("expected PARAMETER=VALUE,VALUE,..."))?;
+    let param = param.trim();
+    if !SWEEP_PARAMS.iter().any(|(name, _)| *name == param) {
+        let names = SWEEP_PARAMS.map(|(name, _)| name).join(", ");
+        return Err(invalid(&format!("the parameters are {}", names)));
+    }
+    let values = values
+        .split(',')
+        .map(|value| {
+            value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&value| value > 0)
+                .ok_or_else(|| invalid(&format!("'{}' is not a positive integer", value)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if (1..values.len()).any(|i| values[..i].contains(&values[i])) {
+        return Err(invalid("values are repeated"));
+    }
+    Ok(Sweep {
+        param: param.to_string(),
+        values,
+    })
+}
+
+/// Parse an augmentation such as "noise:pink:20", "gain:-6:6", "shift:0.5" or "stretch:0.9:1.1"
+pub(super) fn parse_augmentation(value: &str) -> Result<Augmentation, String> {
+    let invalid = |reason: &str| format!("Invalid augmentation '{}': {}", value, reason);
+    let number = |field: &str| {
+        field
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| invalid(&format!("'{}' is not a number", field)))
+    };
+    let fields: Vec<&str> = value.trim().split(':').collect();
+    let augmentation = match fields[..] {
+        ["noise", color, snr] => Augmentation::Noise {
+            color: match color.trim() {
+                "white" => NoiseColor::White,
+                "pink" => NoiseColor::Pink,
+                _ => return Err(invalid("the noise is white or pink")),
+            },
+            snr_db: number(snr)?,
+        },
+        ["gain", min, max] => Augmentation::Gain {
+            min_db: number(min)?,
+            max_db: number(max)?,
+        },
+        ["shift", seconds] => Augmentation::TimeShift {
+            max_seconds: number(seconds)?,
+        },
+        ["stretch", min, max] => Augmentation::TimeStretch {
+            min_rate: number(min)?,
+            max_rate: number(max)?,
+        },
+        _ => {
+            return Err(invalid(
+                "expected noise:white|pink:SNR, gain:MIN:MAX, shift:SECONDS or stretch:MIN:MAX",
+            ));
+        }
+    };
+    augmentation
+        .validate()
+        .map_err(|e| invalid(&e.to_string()))?;
+    Ok(augmentation)
+}
+
+/// Parse SpecAugment masks such as "freq=2:27,time=2:40,fill=mean"
+pub(super) fn parse_spec_augment(value: &str) -> Result<SpecAugment, String> {
+    let invalid = |reason: &str| format!("Invalid --spec-augment '{}': {}", value, reason);
+    let masks = |field: &str| -> Result<(usize, usize), String> {
+        let (count, width) = field
+            .split_once(':')
+            .ok_or_else(|| invalid("masks are given as N:W"))?;
+        match (count.trim().parse(), width.trim().parse()) {
+            (Ok(count), Ok(width)) => Ok((count, width)),
+            _ => Err(invalid("masks are given as N:W with whole numbers")),
+        }
+    };
+    let mut spec_augment = SpecAugment {
+        freq_masks: 0,
+        max_freq_width: 0,
+        time_masks: 0,
+        max_time_width: 0,
+        fill: MaskFill::Zero,
+    };
+    for field in value.split(',') {
+        match field.trim().split_once('=') {
+            Some(("freq", field)) => {
+                (spec_augment.freq_masks, spec_augment.max_freq_width) = masks(field)?
+            }
+            Some(("time", field)) => {
+                (spec_augment.time_masks, spec_augment.max_time_width) = masks(field)?
+            }
+            Some(("fill", "zero")) => spec_augment.fill = MaskFill::Zero,
+            Some(("fill", "mean")) => spec_augment.fill = MaskFill::Mean,
+            Some(("fill", fill)) => {
+                spec_augment.fill = MaskFill::Value(
+                    fill.trim()
+                        .parse()
+                        .map_err(|_| invalid("fill is zero, mean or a number"))?,
+                )
+            }
+            _ => {
+                return Err(invalid(
+                    "expected freq=N:W, time=N:W and fill=zero|mean|VALUE",
+                ));
+            }
+        }
+    }
+    spec_augment
+        .validate()
+        .map_err(|e| invalid(&e.to_string()))?;
+    Ok(spec_augment)
+}
+
+/// Parse a percentage of frames ("10%" or "10") in (0, 100]
+pub(super) fn parse_noise_percent(value: &str) -> Result<f32, String> {
+    let percentage: f32 = value.trim().trim_end_matches('%').parse().map_err(|_| {
+        format!(
+            "Invalid percentage '{}': expected a percentage like 10%",
+            value
+        )
+    })?;
+    if !(percentage > 0.0 && percentage <= 100.0) {
+        return Err(format!(
+            "Invalid percentage '{}': must be in the range (0%, 100%]",
+            value
+        ));
+    }
+    Ok(percentage)
+}
+
+/// Parse a gate threshold in dB relative to the loudest frame (at most 0)
+pub(super) fn parse_gate_db(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(db) if db <= 0.0 => Ok(db),
+        _ => Err(format!(
+            "Invalid gate threshold '{}': expected dB below the loudest frame (e.g. -60)",
+            value
+        )),
+    }
+}
+
+/// Parse a fraction in [0, 1]
+pub(super) fn parse_fraction(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!(
+            "Invalid fraction '{}': expected a number between 0 and 1",
+            value
+        )),
+    }
+}
+
+/// Parse a raw PCM description such as "sr=16000,fmt=s16le" or "sr=8000,fmt=u8,channels=2"
+pub(crate) fn parse_raw_pcm(value: &str) -> Result<RawPcm, String> {
+    let invalid = |reason: String| format!("Invalid raw PCM description '{}': {}", value, reason);
+    let mut sample_rate = None;
+    let mut format = None;
+    let mut channels = 1;
+    for field in value.split(',') {
+        let (key, v) = field
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("expected key=value, got '{}'", field)))?;
+        match key.trim() {
+            "sr" => {
+                sample_rate = Some(
+                    v.trim()
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|&sr| sr > 0)
+                        .ok_or_else(|| invalid(format!("bad sample rate '{}'", v)))?,
+                )
+            }
+            "fmt" => {
+                format = Some(
+                    <PcmFormat as clap::ValueEnum>::from_str(v.trim(), true)
+                        .map_err(|_| invalid(format!("unknown sample format '{}'", v)))?,
+                )
+            }
+            "channels" => {
+                channels = v
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&c| c > 0)
+                    .ok_or_else(|| invalid(format!("channels must be at least 1, got '{}'", v)))?
+            }
+            other => return Err(invalid(format!("unknown key '{}'", other))),
+        }
+    }
+    Ok(RawPcm {
+        sample_rate: sample_rate.ok_or_else(|| invalid("missing sr".to_string()))?,
+        format: format.ok_or_else(|| invalid("missing fmt".to_string()))?,
+        channels,
+    })
+}
+
+/// Parse a downmix strategy such as "average", "first", "channel=2" or "itu775"
+pub(crate) fn parse_downmix(value: &str) -> Result<DownmixMode, String> {
+    match value.trim() {
+        "average" => Ok(DownmixMode::Average),
+        "first" => Ok(DownmixMode::First),
+        "itu775" => Ok(DownmixMode::Itu775),
+        other => other
+            .strip_prefix("channel=")
+            .and_then(|channel| channel.trim().parse().ok())
+            .map(DownmixMode::Channel)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid downmix '{}': expected average, first, channel=N or itu775",
+                    value
+                )
+            }),
+    }
+}
+
+/// Parse the options of `spectrs compute` given without the name of the binary and of the
+/// subcommand, as `spectrs watch` and `spectrs serve` receive them
+pub(crate) fn parse_args(
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<(ComputeArgs, ArgMatches)> {
+    let argv = ["spectrs".into(), "compute".into()].into_iter().chain(args);
+    let matches = Cli::command()
+        .try_get_matches_from(argv)
+        .with_context(|| "Invalid compute options")?;
+    let compute_matches = matches
+        .subcommand_matches("compute")
+        .expect("compute subcommand matched")
+        .clone();
+    let args = ComputeArgs::from_arg_matches(&compute_matches)
+        .with_context(|| "Invalid compute options")?;
+    Ok((args, compute_matches))
+}
@@ -0,0 +1,1709 @@
+//! Batch runs over files, directories, manifests and archives: per-input items, directory and auto
+//! parameters, summaries, reports and plans
+
+use super::args::{ComputeArgs, Sweep};
+use super::live::run_live;
+use super::naming::{apply_name_template, compute_output_path, flatten_outputs, output_files};
+use super::options::{AudioOptions, DatasetSink, OutputOptions, prepare};
+use super::output::save_output;
+use super::sources::{
+    archive_sources, directory_sources, is_single_input, single_source, store_sources,
+};
+use super::upload::Upload;
+use super::{
+    CreateFn, STDIO, accumulate_cmvn, accumulate_scale, create_spectrogram, create_with_variants,
+    is_interrupted, par_create_spectrogram,
+};
+use crate::cli::{InputsFailed, Interrupted, InvalidConfig};
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use rayon::prelude::*;
+use spectrs::cancel::CancellationToken;
+use spectrs::features::FeatureSummary;
+use spectrs::features::cepstrum::CepstralPeak;
+use spectrs::features::formant::FormantTrack;
+use spectrs::features::loudness::Loudness;
+use spectrs::features::octave::BandLevels;
+use spectrs::features::pitch::PitchTrack;
+use spectrs::features::temporal::ZeroCrossingCounter;
+use spectrs::io::checkpoint::Checkpoint;
+use spectrs::io::export::{
+    OutputFormat, save_average_spectrum_csv, save_feature_json, save_feature_table,
+};
+use spectrs::io::image::{BatchSummary, ScaleAccumulator, save_batch_summary_image};
+use spectrs::io::index::{FileIndex, FileStatus, IndexRecord};
+use spectrs::io::manifest::{OutputRecord, load_manifest, save_output_manifest};
+use spectrs::io::metadata::{
+    PlannedInput, ProcessedInput, RenamedOutput, ReportedInput, RunPlan, RunReport, write_run_plan,
+    write_run_report,
+};
+use spectrs::io::report::{HtmlReport, ReportEntry, save_html_report};
+use spectrs::io::source::{
+    AudioSource, FileSource, RawPcmSource, is_archive_path, is_store_url, is_url,
+};
+use spectrs::spectrogram::average::AverageAccumulator;
+use spectrs::spectrogram::cmvn::CmvnAccumulator;
+use spectrs::spectrogram::compression::{Compression, par_compress_spectrogram_with_amin};
+use spectrs::spectrogram::filterbank::band_frequencies;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::{Precision, SpectrogramType};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, mpsc};
+use std::time::{Duration, Instant};
+
+/// Bytes of a megabyte, the unit of --max-memory-mb
+pub(super) const MEGABYTE: u64 = 1024 * 1024;
+
+/// File of a directory overriding the spectrogram parameters of the inputs beneath it
+pub(super) const DIRECTORY_PARAMS: &str = ".spectrs.toml";
+
+/// Figures about a processed file, used for the batch summary and the statistics table
+#[derive(Clone)]
+pub(super) struct FileSummary {
+    /// Duration of the processed audio (seconds)
+    pub(super) duration: f32,
+    /// Peak absolute sample value (dBFS)
+    pub(super) peak_db: f32,
+    /// Fraction of successive samples changing sign
+    pub(super) zero_crossing_rate: f32,
+    /// Spectral descriptors (only computed when requested)
+    pub(super) features: Option<FeatureSummary>,
+    /// Loudness of the decoded audio (only metered when requested), moved to the descriptors
+    pub(super) loudness: Option<Loudness>,
+    /// Processed audio, kept for the waveform panel (--with-waveform)
+    pub(super) waveform: Option<Vec<f32>>,
+    /// Pitch of the processed audio (only tracked when requested)
+    pub(super) pitch: Option<PitchTrack>,
+    /// Formants of the processed audio (only tracked when requested)
+    pub(super) formants: Option<FormantTrack>,
+    /// Chroma of the linear spectrogram ([pitch class][time], only computed when requested)
+    pub(super) chroma: Option<Vec<Vec<f32>>>,
+    /// Cepstral peak of every frame of the linear spectrogram (only computed when requested)
+    pub(super) cpp: Option<Vec<CepstralPeak>>,
+    /// Octave band levels of the processed audio (only analyzed when requested)
+    pub(super) bands: Option<BandLevels>,
+    /// Spectrogram added to the average of the run, with its sample rate (only kept for
+    /// --average-out)
+    pub(super) average: Option<(u32, AverageAccumulator)>,
+    /// Thumbnail of the image (only rendered for --report-html)
+    pub(super) thumbnail: Option<Thumbnail>,
+}
+
+/// Small rendering of the spectrogram of an input for the HTML report, with its sample rate
+/// and the shape of the full spectrogram
+#[derive(Clone)]
+pub(super) struct Thumbnail {
+    pub(super) png: Vec<u8>,
+    pub(super) sample_rate: u32,
+    pub(super) shape: [usize; 2],
+}
+
+/// Largest size (pixels) of the thumbnails of the HTML report
+pub(super) const THUMBNAIL_WIDTH: usize = 320;
+pub(super) const THUMBNAIL_HEIGHT: usize = 128;
+
+/// Everything besides the audio that determines a spectrogram, hashed into its cache key
+#[derive(serde::Serialize)]
+pub(super) struct CacheSettings<'a> {
+    pub(super) params: &'a SpectrogramParams,
+    pub(super) sr: Option<u32>,
+    pub(super) resample_quality: String,
+    pub(super) start: Option<f32>,
+    pub(super) end: Option<f32>,
+    pub(super) downmix: String,
+    pub(super) raw_pcm: Option<String>,
+    pub(super) gpu: bool,
+    pub(super) pool_width: Option<usize>,
+    /// Left out when empty, so that the keys of unfiltered spectrograms do not change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) gate: Option<String>,
+}
+
+/// Sample rate and file summary stored next to a cached spectrogram
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct CachedSummary {
+    pub(super) sample_rate: u32,
+    pub(super) duration: f32,
+    pub(super) peak_db: f32,
+    pub(super) zero_crossing_rate: f32,
+}
+
+impl FileSummary {
+    pub(super) fn from_audio(audio: &[f32], sr: u32) -> Self {
+        let peak = audio.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+        let mut zero_crossings = ZeroCrossingCounter::default();
+        zero_crossings.push(audio);
+        Self {
+            duration: audio.len() as f32 / sr as f32,
+            peak_db: 20.0 * peak.max(1e-10).log10(),
+            zero_crossing_rate: zero_crossings.rate(),
+            features: None,
+            loudness: None,
+            waveform: None,
+            pitch: None,
+            formants: None,
+            chroma: None,
+            cpp: None,
+            bands: None,
+            average: None,
+            thumbnail: None,
+        }
+    }
+
+    /// Compute the spectral descriptors from the linear-frequency spectrogram
+    pub(super) fn add_features(&mut self, spec: &[Vec<f32>], sr: u32, params: &SpectrogramParams) {
+        // The descriptors are defined on magnitudes
+        let magnitude: Vec<Vec<f32>> = match params.spectrogram_type {
+            SpectrogramType::Magnitude => spec.to_vec(),
+            SpectrogramType::Power => spec
+                .iter()
+                .map(|row| row.iter().map(|v| v.sqrt()).collect())
+                .collect(),
+        };
+        self.features = Some(FeatureSummary {
+            loudness: self.loudness.take(),
+            ..FeatureSummary::from_spectrogram(
+                &magnitude,
+                sr,
+                params.n_fft,
+                self.duration,
+                self.zero_crossing_rate,
+            )
+        });
+    }
+}
+
+/// Label of a source, i.e. its parent directory relative to the input ("." at top level)
+pub(super) fn source_label(source: &dyn AudioSource) -> String {
+    source
+        .relative_path()
+        .parent()
+        .map(|p| p.display().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// Record of a processed input in the index (--index-db), with its label (from its manifest
+/// entry or directory), its statistics if computed and its parameters if not those of the run
+pub(super) fn index_record(
+    record: &OutputRecord,
+    label: String,
+    summary: &FileSummary,
+    params: Option<SpectrogramParams>,
+) -> IndexRecord {
+    let mut features = Vec::new();
+    if let Some(descriptors) = &summary.features {
+        features.extend(FeatureSummary::COLUMNS.iter().zip(descriptors.values()));
+        if let Some(loudness) = &descriptors.loudness {
+            features.extend(Loudness::COLUMNS.iter().zip(loudness.values()));
+        }
+    }
+    IndexRecord {
+        input: record.input.clone(),
+        segment: (record.start, record.end),
+        label: Some(label),
+        status: FileStatus::Processed,
+        error: None,
+        outputs: record.outputs.clone(),
+        duration: Some(summary.duration),
+        peak_db: Some(summary.peak_db),
+        zero_crossing_rate: Some(summary.zero_crossing_rate),
+        features: features
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+        params,
+    }
+}
+
+/// Entry of a processed input in the HTML report (--report-html), taking the thumbnail of its
+/// summary. None if no thumbnail was rendered
+pub(super) fn report_entry(
+    record: &OutputRecord,
+    label: String,
+    summary: &mut FileSummary,
+) -> Option<ReportEntry> {
+    let thumbnail = summary.thumbnail.take()?;
+    Some(ReportEntry {
+        input: record.input.clone(),
+        label,
+        outputs: record.outputs.clone(),
+        duration: summary.duration,
+        sample_rate: thumbnail.sample_rate,
+        peak_db: summary.peak_db,
+        zero_crossing_rate: summary.zero_crossing_rate,
+        shape: thumbnail.shape,
+        thumbnail: Some(thumbnail.png),
+    })
+}
+
+/// An input of a batch with where its outputs go, and the settings of its manifest entry
+pub(super) struct BatchItem {
+    /// Shared with the thread processing it under --per-file-timeout
+    pub(super) source: Arc<dyn AudioSource>,
+    /// Output of the primary format
+    pub(super) output: PathBuf,
+    /// Segment replacing --start/--end, if the entry gives one
+    pub(super) segment: Option<(Option<f32>, Option<f32>)>,
+    /// Label replacing the directory of the input in the batch summary
+    pub(super) label: Option<String>,
+    /// Local directory the outputs are written to before they are uploaded (--output-dir in an
+    /// object store)
+    pub(super) staging: Option<PathBuf>,
+    /// Spectrogram parameters replacing those of the run (from the .spectrs.toml files of its
+    /// directory)
+    pub(super) params: Option<SpectrogramParams>,
+    /// Parameters and output of the other spectrograms of the input (--sweep)
+    pub(super) variants: Vec<(SpectrogramParams, PathBuf)>,
+}
+
+impl BatchItem {
+    /// Item of a source processed with the options of the command line
+    pub(super) fn new(
+        source: Box<dyn AudioSource>,
+        output_dir: Option<&str>,
+        format: OutputFormat,
+    ) -> Self {
+        let output = compute_output_path(source.as_ref(), output_dir, format);
+        Self {
+            source: source.into(),
+            output,
+            segment: None,
+            label: None,
+            staging: None,
+            params: None,
+            variants: Vec::new(),
+        }
+    }
+
+    /// Outputs of the primary format of the item and of its variants
+    pub(super) fn outputs(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.output).chain(self.variants.iter().map(|(_, output)| output))
+    }
+
+    /// Mutable outputs of the item and of its variants
+    pub(super) fn outputs_mut(&mut self) -> impl Iterator<Item = &mut PathBuf> {
+        std::iter::once(&mut self.output).chain(self.variants.iter_mut().map(|(_, output)| output))
+    }
+
+    /// Files written for the item and its variants (see `output_files`)
+    pub(super) fn output_files(&self, options: &OutputOptions) -> Vec<String> {
+        self.outputs()
+            .flat_map(|output| output_files(output, options))
+            .collect()
+    }
+
+    /// Spectrogram parameters of the item
+    pub(super) fn params(&self, base: &SpectrogramParams) -> SpectrogramParams {
+        self.params.unwrap_or(*base)
+    }
+
+    /// Sample rate of the spectrogram (after --sr) and duration (seconds) of the segment of the
+    /// item, when the header of the source tells them
+    pub(super) fn signal(&self, audio_options: &AudioOptions) -> Option<(u32, f32)> {
+        let info = self.source.info()?;
+        let options = self.audio_options(audio_options);
+        let end = options
+            .end
+            .map_or(info.duration(), |end| (end as f64).min(info.duration()));
+        let duration = (end - options.start.unwrap_or(0.0) as f64).max(0.0);
+        Some((options.sr.unwrap_or(info.sample_rate), duration as f32))
+    }
+
+    /// Audio options with the segment of the entry, framed by the parameters of the item when
+    /// --win-ms/--hop-ms are resolved for each input
+    pub(super) fn audio_options<'a>(&self, base: &'a AudioOptions) -> Cow<'a, AudioOptions> {
+        let mut options = match (&self.params, &base.durations) {
+            (Some(params), Some(_)) => Cow::Owned(base.framed_by(params)),
+            _ => Cow::Borrowed(base),
+        };
+        if let Some((start, end)) = self.segment {
+            let options = options.to_mut();
+            options.start = start;
+            options.end = end;
+        }
+        options
+    }
+
+    /// Resolve --win-ms/--hop-ms at the sample rate of the item, unless they were resolved for
+    /// the whole run against --sr
+    pub(super) fn resolve_durations(
+        &mut self,
+        base: &SpectrogramParams,
+        audio_options: &AudioOptions,
+    ) -> Result<()> {
+        if let Some(durations) = &audio_options.durations {
+            let params = durations.params_of(self.source.as_ref(), &self.params(base))?;
+            self.params = (params != *base).then_some(params);
+        }
+        Ok(())
+    }
+
+    /// Output options with the segment and the label of the entry
+    pub(super) fn output_options<'a>(&self, base: &'a OutputOptions) -> Cow<'a, OutputOptions> {
+        let labelled = self.label.is_some() && base.dataset.is_some();
+        if self.segment.is_none() && !labelled {
+            return Cow::Borrowed(base);
+        }
+        let mut options = base.clone();
+        if let Some((start, _)) = self.segment {
+            options.time_offset = start.unwrap_or(0.0);
+        }
+        if let Some(dataset) = options.dataset.as_mut() {
+            dataset.label = self.label.clone();
+        }
+        Cow::Owned(options)
+    }
+
+    /// Label of the input in the batch summary
+    pub(super) fn label(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| source_label(self.source.as_ref()))
+    }
+
+    /// Record of the input in the output manifest
+    pub(super) fn record(
+        &self,
+        summary: &FileSummary,
+        audio_options: &AudioOptions,
+        output_options: &OutputOptions,
+    ) -> OutputRecord {
+        let audio_options = self.audio_options(audio_options);
+        OutputRecord::new(
+            self.source.name(),
+            self.label.clone(),
+            (audio_options.start, audio_options.end),
+            summary.duration,
+            self.output_files(output_options),
+        )
+    }
+}
+
+impl BatchItem {
+    /// Memory (bytes) that processing the item would need, estimated from the header of its
+    /// source: the decoded channels, the mono and resampled signals, and the spectrogram with
+    /// its exported copy. None when the length of the source is unknown in advance
+    pub(super) fn estimated_memory(
+        &self,
+        audio_options: &AudioOptions,
+        params: &SpectrogramParams,
+    ) -> Option<u64> {
+        let info = self.source.info()?;
+        let options = self.audio_options(audio_options);
+        let sample_rate = info.sample_rate.max(1) as f64;
+        let start = (options.start.unwrap_or(0.0) as f64 * sample_rate) as u64;
+        let end = options
+            .end
+            .map_or(info.frames, |end| (end as f64 * sample_rate) as u64);
+        let frames = end.min(info.frames).saturating_sub(start);
+
+        let decoded = frames * (info.channels as u64 + 1) * 4;
+        let samples = match options.sr {
+            Some(sr) if sr != info.sample_rate => {
+                (frames as f64 * sr as f64 / sample_rate).ceil() as u64
+            }
+            _ => frames,
+        };
+        let resampled = if samples == frames { 0 } else { samples * 4 };
+        let value = if params.precision == Precision::F64 {
+            8
+        } else {
+            4
+        };
+        let n_frames = samples / params.hop_length.max(1) as u64 + 1;
+        let spectrogram = n_frames * (params.n_fft as u64 / 2 + 1) * value * 2;
+        Some(decoded + resampled + spectrogram)
+    }
+}
+
+/// Report an input of a batch as failed: in the log, the index and the run report
+pub(super) fn record_failure(
+    item: &BatchItem,
+    error: &anyhow::Error,
+    audio_options: &AudioOptions,
+    index: Option<&mut FileIndex>,
+    failed: &mut Vec<ReportedInput>,
+) -> Result<()> {
+    tracing::error!(
+        "Failed to create spectrogram of {}: {:#}",
+        item.source.name(),
+        error
+    );
+    if let Some(index) = index {
+        let options = item.audio_options(audio_options);
+        let record = IndexRecord {
+            params: item.params,
+            ..IndexRecord::failed(
+                item.source.name(),
+                (options.start, options.end),
+                Some(item.label()),
+                format!("{:#}", error),
+            )
+        };
+        index.record(&record, item.source.local_path())?;
+    }
+    failed.push(ReportedInput {
+        input: item.source.name(),
+        reason: format!("{:#}", error),
+    });
+    Ok(())
+}
+
+/// Process an item of a batch on a thread of its own, giving up on it after `timeout`
+/// (--per-file-timeout). The input is then reported as failed and its token cancelled: the
+/// thread stops at the next check of the decoding or the STFT, never writes anything more,
+/// and its outcome is dropped
+pub(super) fn create_spectrogram_with_timeout(
+    item: &BatchItem,
+    timeout: Duration,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<FileSummary> {
+    let cancel = CancellationToken::new();
+    let source = Arc::clone(&item.source);
+    let output = item.output.clone();
+    let variants = item.variants.clone();
+    let audio_options = AudioOptions {
+        cancel: Some(cancel.clone()),
+        ..audio_options.clone()
+    };
+    let params = *params;
+    let output_options = OutputOptions {
+        cancel: Some(cancel.clone()),
+        ..output_options.clone()
+    };
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("spectrs-input".to_string())
+        .spawn(move || {
+            let outcome = create_with_variants(
+                create_spectrogram,
+                source.as_ref(),
+                &output,
+                &audio_options,
+                &params,
+                &output_options,
+                &variants,
+            );
+            // Nobody listens any more if the input timed out
+            let _ = sender.send(outcome);
+        })
+        .with_context(|| "Failed to start a thread")?;
+    match receiver.recv_timeout(timeout) {
+        Ok(outcome) => outcome,
+        Err(RecvTimeoutError::Timeout) => {
+            cancel.cancel();
+            Err(anyhow::anyhow!(
+                "Timed out after {}s (--per-file-timeout)",
+                timeout.as_secs_f32()
+            ))
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(anyhow::anyhow!("Processing panicked, see the log"))
+        }
+    }
+}
+
+/// Items of the entries of a manifest (--manifest). Paths are relative to the manifest, and so
+/// are the output names without --output-dir.
+pub(super) fn manifest_items(
+    manifest: &Path,
+    args: &ComputeArgs,
+    format: OutputFormat,
+) -> Result<Vec<BatchItem>> {
+    let entries = load_manifest(manifest).with_context(|| "Failed to read the manifest")?;
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    let mut items = Vec::with_capacity(entries.len());
+    let mut entry_of_output: HashMap<PathBuf, usize> = HashMap::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let source: Box<dyn AudioSource> = if entry.path == STDIO {
+            anyhow::bail!("stdin cannot be listed in a manifest");
+        } else if is_url(&entry.path) || is_store_url(&entry.path) {
+            if args.raw_pcm.is_some() {
+                anyhow::bail!("--raw-pcm is not available for URL inputs");
+            }
+            single_source(&entry.path, None, args.downmix)?
+        } else {
+            let path = base.join(&entry.path);
+            match args.raw_pcm {
+                Some(layout) => {
+                    Box::new(RawPcmSource::with_base(path, base, layout).with_downmix(args.downmix))
+                }
+                None => Box::new(FileSource::with_base(path, base).with_downmix(args.downmix)),
+            }
+        };
+        let output = match &entry.output {
+            Some(name) => args
+                .output_dir
+                .as_deref()
+                .map_or(base, Path::new)
+                .join(name)
+                .with_extension(format.extension()),
+            None => compute_output_path(source.as_ref(), args.output_dir.as_deref(), format),
+        };
+        // Outputs named after --name-template are checked once renamed
+        let named = args.name_template.is_some();
+        if let Some(previous) = entry_of_output
+            .insert(output.clone(), index)
+            .filter(|_| !named)
+        {
+            anyhow::bail!(
+                "Manifest entries {} and {} would both be written to {}; give them distinct `output` names",
+                previous + 1,
+                index + 1,
+                output.display()
+            );
+        }
+        let segment =
+            (entry.start.is_some() || entry.end.is_some()).then_some((entry.start, entry.end));
+        items.push(BatchItem {
+            source: source.into(),
+            output,
+            segment,
+            label: entry.label,
+            staging: None,
+            params: None,
+            variants: Vec::new(),
+        });
+    }
+    Ok(items)
+}
+
+/// Parameters of every combination of the values of --sweep, replacing those of `base`
+pub(super) fn sweep_params(
+    base: &SpectrogramParams,
+    sweeps: &[Sweep],
+) -> Result<Vec<SpectrogramParams>> {
+    let mut combinations = vec![toml::Table::new()];
+    for sweep in sweeps {
+        combinations = combinations
+            .iter()
+            .flat_map(|overrides| {
+                sweep.values.iter().map(|&value| {
+                    let mut overrides = overrides.clone();
+                    overrides.insert(sweep.param.clone(), toml::Value::Integer(value as i64));
+                    overrides
+                })
+            })
+            .collect();
+    }
+    combinations
+        .iter()
+        .map(|overrides| override_params(base, overrides).with_context(|| "Invalid --sweep"))
+        .collect()
+}
+
+/// Give every item the variants of --sweep: the first combination of values replaces its
+/// parameters and the others are computed from the same decoded audio, written next to its
+/// output under the names of --name-template
+pub(super) fn sweep_items(
+    items: &mut [BatchItem],
+    sweeps: &[Sweep],
+    params: &SpectrogramParams,
+) -> Result<()> {
+    if sweeps.is_empty() {
+        return Ok(());
+    }
+    for item in items {
+        let mut combinations = sweep_params(&item.params(params), sweeps)?.into_iter();
+        item.params = combinations.next();
+        item.variants = combinations
+            .map(|params| (params, item.output.clone()))
+            .collect();
+    }
+    Ok(())
+}
+
+/// Spectrogram parameters of the inputs of a directory: those of the run, overridden by the
+/// `.spectrs.toml` files from the input directory down to the directory of each input (the
+/// deepest file setting a parameter wins)
+pub(super) struct DirectoryParams<'a> {
+    pub(super) root: &'a Path,
+    /// Merged overrides of the directories looked up so far (None without any)
+    pub(super) overrides: HashMap<PathBuf, Option<toml::Table>>,
+}
+
+impl<'a> DirectoryParams<'a> {
+    pub(super) fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Parameters of the inputs of `dir` otherwise computed with `base`, None if no file
+    /// overrides them
+    pub(super) fn of(
+        &mut self,
+        dir: &Path,
+        base: &SpectrogramParams,
+    ) -> Result<Option<SpectrogramParams>> {
+        let Some(overrides) = self.overrides(dir)? else {
+            return Ok(None);
+        };
+        let params = override_params(base, &overrides).with_context(|| {
+            format!(
+                "Invalid parameters for {} in {} files",
+                dir.display(),
+                DIRECTORY_PARAMS
+            )
+        })?;
+        Ok((params != *base).then_some(params))
+    }
+
+    /// Overrides of the files of `dir` and of its parents up to the root
+    pub(super) fn overrides(&mut self, dir: &Path) -> Result<Option<toml::Table>> {
+        if let Some(overrides) = self.overrides.get(dir) {
+            return Ok(overrides.clone());
+        }
+        let mut overrides = match dir.parent() {
+            Some(parent) if dir != self.root && parent.starts_with(self.root) => {
+                self.overrides(parent)?
+            }
+            _ => None,
+        };
+        let path = dir.join(DIRECTORY_PARAMS);
+        if path.is_file() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let table: toml::Table = text
+                .parse()
+                .with_context(|| format!("Invalid {}", path.display()))?;
+            overrides.get_or_insert_with(toml::Table::new).extend(table);
+        }
+        self.overrides.insert(dir.to_path_buf(), overrides.clone());
+        Ok(overrides)
+    }
+}
+
+/// Parameters suggested for each input by --auto-params (see `SpectrogramParams::suggested`)
+/// in place of those of the run not given on the command line
+pub(super) struct AutoParams {
+    pub(super) n_fft: bool,
+    pub(super) win_length: bool,
+    pub(super) hop_length: bool,
+    pub(super) n_mels: bool,
+}
+
+impl AutoParams {
+    pub(super) fn new(matches: &ArgMatches) -> Self {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        Self {
+            n_fft: given("n_fft"),
+            win_length: given("win_length"),
+            hop_length: given("hop_length"),
+            n_mels: given("n_mels"),
+        }
+    }
+
+    /// Parameters of `item`, None if they are those of the run or if its sample rate is not
+    /// known before decoding it
+    pub(super) fn of(
+        &self,
+        item: &BatchItem,
+        base: &SpectrogramParams,
+        audio_options: &AudioOptions,
+    ) -> Result<Option<SpectrogramParams>> {
+        let Some((sample_rate, duration)) = item.signal(audio_options) else {
+            return Ok(None);
+        };
+        let suggested = SpectrogramParams::suggested(sample_rate, duration);
+        let mut params = *base;
+        if !self.win_length {
+            params.win_length = suggested.win_length;
+        }
+        if !self.n_fft {
+            params.n_fft = match self.win_length {
+                true => params.win_length.next_power_of_two(),
+                false => suggested.n_fft,
+            };
+        }
+        params.win_length = params.win_length.min(params.n_fft);
+        if !self.hop_length {
+            params.hop_length = suggested.hop_length;
+        }
+        if !self.n_mels {
+            params.n_mels = suggested
+                .n_mels
+                .map(|n_mels| n_mels.min(params.n_freq_bins()));
+        }
+        params
+            .validate()
+            .with_context(|| format!("Invalid parameters for {}", item.source.name()))?;
+        Ok((params != *base).then_some(params))
+    }
+}
+
+/// Print the parameters chosen by --auto-params, once for the inputs sharing them
+pub(super) fn log_auto_params(
+    items: &[BatchItem],
+    base: &SpectrogramParams,
+    audio_options: &AudioOptions,
+) {
+    let mut chosen: BTreeMap<_, (usize, BTreeSet<u32>)> = BTreeMap::new();
+    let mut unknown = 0;
+    for item in items {
+        let Some((sample_rate, _)) = item.signal(audio_options) else {
+            unknown += 1;
+            continue;
+        };
+        let params = item.params(base);
+        let key = (
+            params.n_fft,
+            params.win_length,
+            params.hop_length,
+            params.n_mels,
+        );
+        let (count, rates) = chosen.entry(key).or_default();
+        *count += 1;
+        rates.insert(sample_rate);
+    }
+    for ((n_fft, win_length, hop_length, n_mels), (count, rates)) in chosen {
+        let rates: Vec<String> = rates.iter().map(u32::to_string).collect();
+        let n_mels = n_mels.map_or("none".to_string(), |n_mels| n_mels.to_string());
+        tracing::info!(
+            "Auto parameters for {} input{} at {} Hz: n_fft={}, win_length={}, hop_length={}, n_mels={}",
+            count,
+            if count == 1 { "" } else { "s" },
+            rates.join(", "),
+            n_fft,
+            win_length,
+            hop_length,
+            n_mels
+        );
+    }
+    if unknown > 0 {
+        tracing::warn!(
+            "--auto-params: the sample rate of {} input{} is not known before decoding, the parameters of the command line are used",
+            unknown,
+            if unknown == 1 { "" } else { "s" }
+        );
+    }
+}
+
+/// Parameters with some of them replaced, named as in their JSON form (e.g. `n_fft`,
+/// `n_mels`, `spectrogram_type = "magnitude"`)
+pub(super) fn override_params(
+    base: &SpectrogramParams,
+    overrides: &toml::Table,
+) -> Result<SpectrogramParams> {
+    let mut value = serde_json::to_value(base)?;
+    let fields = value
+        .as_object_mut()
+        .expect("parameters serialize to an object");
+    for (name, override_value) in overrides {
+        fields.insert(name.clone(), serde_json::to_value(override_value)?);
+    }
+    let params: SpectrogramParams = serde_json::from_value(value)?;
+    // Unknown names are dropped by deserializing: they are missing once serialized again
+    let known = serde_json::to_value(params)?;
+    if let Some(name) = overrides
+        .keys()
+        .find(|name| known.get(name.as_str()).is_none())
+    {
+        anyhow::bail!("Unknown parameter: {}", name);
+    }
+    params.validate()?;
+    Ok(params)
+}
+
+/// Run `spectrs compute`, processing only the files of the input directory listed in `only`
+/// (canonical paths) if given, as `spectrs watch` does with the files that appeared
+pub(crate) fn run_files(
+    mut args: ComputeArgs,
+    matches: &ArgMatches,
+    only: Option<&HashSet<PathBuf>>,
+) -> Result<()> {
+    let started = Instant::now();
+    let (params, audio_options, mut output_options) =
+        prepare(&mut args, matches).map_err(|e| e.context(InvalidConfig))?;
+    let auto = args.auto_params.then(|| AutoParams::new(matches));
+    // Nothing is staged, uploaded or created by a dry run, whose outputs are named after
+    // --output-dir as given
+    let upload = args
+        .output_dir
+        .as_deref()
+        .filter(|dir| is_store_url(dir) && !args.dry_run)
+        .map(Upload::new)
+        .transpose()?;
+    if let Some(upload) = &upload {
+        if args.live {
+            return Err(
+                anyhow::anyhow!("--live cannot write to an object store").context(InvalidConfig)
+            );
+        }
+        args.output_dir = Some(upload.run_dir().display().to_string());
+    }
+
+    if args.live {
+        return run_live(&args, &params, &output_options);
+    }
+    if let Some(dataset_path) = args.dataset_out.as_ref().filter(|_| !args.dry_run) {
+        output_options.dataset = Some(DatasetSink::create(dataset_path, &params)?);
+    }
+    let checkpoint = args
+        .checkpoint
+        .as_deref()
+        .map(|path| Checkpoint::open(path, &params))
+        .transpose()
+        .with_context(|| "Failed to open the checkpoint")?
+        .map(Mutex::new);
+    let checkpoint_interval = Duration::from_secs_f32(args.checkpoint_interval);
+    let per_file_timeout = args.per_file_timeout.map(Duration::from_secs_f32);
+    let mut index = args
+        .index_db
+        .as_deref()
+        .filter(|path| !args.dry_run || path.exists())
+        .map(|path| FileIndex::open(path, &params))
+        .transpose()
+        .with_context(|| "Failed to open the index")?;
+
+    // Figures collected over the run for the optional summary figure and statistics table
+    let mut batch_summary = BatchSummary::default();
+    let mut feature_rows: Vec<(String, FeatureSummary)> = Vec::new();
+    // Merged in the order of the sources, so that the sums do not depend on the scheduling
+    let mut average: Option<(u32, AverageAccumulator)> = None;
+    let mut records: Vec<OutputRecord> = Vec::new();
+    let mut report_entries: Vec<ReportEntry> = Vec::new();
+    // Outcome of every input for the run report
+    let mut processed: Vec<ProcessedInput> = Vec::new();
+    let mut skipped: Vec<ReportedInput> = Vec::new();
+    let mut failed: Vec<ReportedInput> = Vec::new();
+    let mut renamed: Vec<RenamedOutput> = Vec::new();
+    let mut n_interrupted = 0;
+
+    // Remote inputs are always processed as a single source
+    let input_arg = args.input.clone().unwrap_or_default();
+    let input = Path::new(&input_arg);
+
+    let from_stdin = input_arg == STDIO;
+    let remote = is_url(&input_arg) || is_store_url(&input_arg);
+    if args.manifest.is_none() && !remote && !from_stdin && !input.exists() {
+        return Err(
+            anyhow::anyhow!("Input path does not exist: {}", input.display())
+                .context(InvalidConfig),
+        );
+    }
+
+    // Case of single input - use parallel spectrogram computation
+    let single = args.manifest.is_none() && is_single_input(&input_arg, args.raw_pcm);
+    if single {
+        let source = single_source(&input_arg, args.raw_pcm, args.downmix)?;
+        let mut item = BatchItem::new(
+            source,
+            args.output_dir.as_deref(),
+            output_options.primary_format(),
+        );
+        if let Some(auto) = &auto {
+            item.params = auto
+                .of(&item, &params, &audio_options)
+                .map_err(|e| e.context(InvalidConfig))?;
+            log_auto_params(std::slice::from_ref(&item), &params, &audio_options);
+        }
+        item.resolve_durations(&params, &audio_options)
+            .map_err(|e| e.context(InvalidConfig))?;
+        sweep_items(std::slice::from_mut(&mut item), &args.sweep, &params)
+            .map_err(|e| e.context(InvalidConfig))?;
+        if let Some(template) = &args.name_template {
+            apply_name_template(
+                std::slice::from_mut(&mut item),
+                template,
+                &params,
+                &audio_options,
+                output_options.primary_format(),
+            )?;
+        }
+        if let (true, Some(output_dir)) = (args.flatten, &args.output_dir) {
+            renamed = flatten_outputs(std::slice::from_mut(&mut item), Path::new(output_dir))?;
+        }
+        if args.dry_run {
+            let plan = run_plan(
+                &args,
+                &[item],
+                (skipped, failed),
+                &params,
+                &audio_options,
+                &output_options,
+            );
+            return write_plan(&args, &plan);
+        }
+        let outputs = item.output_files(&output_options);
+        let audio_options = item.audio_options(&audio_options).into_owned();
+        let BatchItem {
+            source,
+            output,
+            params: item_params,
+            variants,
+            ..
+        } = item;
+        let params = item_params.unwrap_or(params);
+
+        // First pass: dataset statistics
+        if let Some(cmvn_path) = &args.cmvn_out {
+            let stats = accumulate_cmvn(
+                source.as_ref(),
+                &output,
+                &audio_options,
+                &params,
+                output_options.denoise.as_ref(),
+                !args.deterministic,
+            )
+            .with_context(|| "Failed to compute CMVN statistics")?
+            .finish()?;
+            stats
+                .save(Path::new(cmvn_path))
+                .with_context(|| "Failed to save CMVN statistics")?;
+            output_options.cmvn = Some(stats);
+        }
+        if args.global_scale {
+            output_options.scale = accumulate_scale(
+                source.as_ref(),
+                &output,
+                &audio_options,
+                &params,
+                &output_options,
+                !args.deterministic,
+            )
+            .with_context(|| "Failed to compute the colour scale")?
+            .finish()?;
+        }
+
+        let create: CreateFn = if args.deterministic {
+            create_spectrogram
+        } else {
+            par_create_spectrogram
+        };
+        let mut file_summary = match create_with_variants(
+            create,
+            source.as_ref(),
+            &output,
+            &audio_options,
+            &params,
+            &output_options,
+            &variants,
+        ) {
+            Ok(file_summary) => file_summary,
+            Err(e) if is_interrupted(&e) => {
+                skipped.push(ReportedInput {
+                    input: source.name(),
+                    reason: "Interrupted".to_string(),
+                });
+                save_html(&args, report_entries, &failed)?;
+                save_run_report(&args, started, processed, skipped, failed, renamed)?;
+                return Err(e.context(Interrupted("Interrupted".to_string())));
+            }
+            Err(e) => {
+                if let Some(index) = &mut index {
+                    let record = IndexRecord::failed(
+                        source.name(),
+                        (args.start, args.end),
+                        Some(source_label(source.as_ref())),
+                        format!("{:#}", e),
+                    );
+                    index.record(&record, source.local_path())?;
+                }
+                failed.push(ReportedInput {
+                    input: source.name(),
+                    reason: format!("{:#}", e),
+                });
+                save_html(&args, report_entries, &failed)?;
+                save_run_report(&args, started, processed, skipped, failed, renamed)?;
+                return Err(e.context(InputsFailed("Failed to create spectrogram".to_string())));
+            }
+        };
+
+        batch_summary.add(
+            file_summary.duration,
+            file_summary.peak_db,
+            &source_label(source.as_ref()),
+        );
+        processed.push(ProcessedInput {
+            input: source.name(),
+            duration: file_summary.duration,
+        });
+        let mut outputs = outputs;
+        if let Some(upload) = &upload {
+            let run_dir = upload.run_dir();
+            outputs = outputs
+                .iter()
+                .map(|output| upload.remote(output, &run_dir))
+                .collect();
+        }
+        records.push(OutputRecord::new(
+            source.name(),
+            None,
+            (args.start, args.end),
+            file_summary.duration,
+            outputs,
+        ));
+        if let (Some(index), Some(record)) = (&mut index, records.last()) {
+            let label = source_label(source.as_ref());
+            index.record(
+                &index_record(record, label, &file_summary, item_params),
+                source.local_path(),
+            )?;
+        }
+        if let Some(record) = records.last() {
+            let label = source_label(source.as_ref());
+            report_entries.extend(report_entry(record, label, &mut file_summary));
+        }
+        add_to_average(&mut average, file_summary.average.take())?;
+        if let Some(features) = file_summary.features {
+            feature_rows.push((source.relative_path().display().to_string(), features));
+        }
+    }
+    // Case of input being a directory or a manifest - parallelize over files, sequential
+    // spectrogram
+    else {
+        let mut items = match &args.manifest {
+            Some(manifest) => {
+                let mut items =
+                    manifest_items(Path::new(manifest), &args, output_options.primary_format())?;
+                for item in &mut items {
+                    if let Some(auto) = &auto {
+                        item.params = auto
+                            .of(item, &params, &audio_options)
+                            .map_err(|e| e.context(InvalidConfig))?;
+                    }
+                    item.resolve_durations(&params, &audio_options)
+                        .map_err(|e| e.context(InvalidConfig))?;
+                }
+                items
+            }
+            None => {
+                let mut listing = if is_store_url(&input_arg) {
+                    store_sources(&input_arg, args.downmix, &args.walk)?
+                } else if is_archive_path(input) {
+                    archive_sources(input, args.downmix, &args.walk)?
+                } else {
+                    directory_sources(input, args.raw_pcm, args.downmix, &args.walk)?
+                };
+                if let Some(only) = only {
+                    let listed = |path: &Path| {
+                        std::fs::canonicalize(path).is_ok_and(|path| only.contains(&path))
+                    };
+                    listing
+                        .sources
+                        .retain(|source| source.local_path().is_some_and(&listed));
+                    listing
+                        .skipped
+                        .retain(|skipped| listed(Path::new(&skipped.input)));
+                }
+                skipped = listing.skipped;
+                let mut directory_params = (!args.no_directory_params && input.is_dir())
+                    .then(|| DirectoryParams::new(input));
+                listing
+                    .sources
+                    .into_iter()
+                    .map(|source| {
+                        let mut item = BatchItem::new(
+                            source,
+                            args.output_dir.as_deref(),
+                            output_options.primary_format(),
+                        );
+                        if let Some(auto) = &auto {
+                            item.params = auto.of(&item, &params, &audio_options)?;
+                        }
+                        item.resolve_durations(&params, &audio_options)?;
+                        let dir = item.source.local_path().and_then(Path::parent);
+                        if let (Some(directory_params), Some(dir)) = (&mut directory_params, dir) {
+                            let base = item.params(&params);
+                            if let Some(overridden) = directory_params.of(dir, &base)? {
+                                item.params = Some(overridden);
+                            }
+                        }
+                        Ok(item)
+                    })
+                    .collect::<Result<_>>()
+                    .map_err(|e| e.context(InvalidConfig))?
+            }
+        };
+        if auto.is_some() {
+            log_auto_params(&items, &params, &audio_options);
+        }
+        sweep_items(&mut items, &args.sweep, &params).map_err(|e| e.context(InvalidConfig))?;
+        if let Some(template) = &args.name_template {
+            apply_name_template(
+                &mut items,
+                template,
+                &params,
+                &audio_options,
+                output_options.primary_format(),
+            )
+            .map_err(|e| e.context(InvalidConfig))?;
+        }
+        if let (true, Some(output_dir)) = (args.flatten, &args.output_dir) {
+            renamed = flatten_outputs(&mut items, Path::new(output_dir))
+                .map_err(|e| e.context(InvalidConfig))?;
+        }
+
+        // Inputs whose outputs are up to date
+        let items = match (&index, args.skip_indexed) {
+            (Some(index), true) => {
+                let mut pending = Vec::with_capacity(items.len());
+                for item in items {
+                    let options = item.audio_options(&audio_options);
+                    let segment = (options.start, options.end);
+                    if index.is_up_to_date_with(
+                        &item.source.name(),
+                        segment,
+                        item.source.local_path(),
+                        &item.params(&params),
+                    )? {
+                        skipped.push(ReportedInput {
+                            input: item.source.name(),
+                            reason: "Up to date in the index".to_string(),
+                        });
+                    } else {
+                        pending.push(item);
+                    }
+                }
+                pending
+            }
+            _ => items,
+        };
+        let mut items = match &checkpoint {
+            Some(checkpoint) => {
+                let checkpoint = lock_checkpoint(checkpoint);
+                let (done, pending): (Vec<BatchItem>, Vec<BatchItem>) =
+                    items.into_iter().partition(|item| {
+                        let options = item.audio_options(&audio_options);
+                        checkpoint.is_completed(&item.source.name(), (options.start, options.end))
+                    });
+                if !done.is_empty() {
+                    tracing::info!(
+                        "Resuming: {} inputs completed in the checkpoint",
+                        done.len()
+                    );
+                }
+                skipped.extend(done.iter().map(|item| ReportedInput {
+                    input: item.source.name(),
+                    reason: "Completed in the checkpoint".to_string(),
+                }));
+                pending
+            }
+            None => items,
+        };
+        // Inputs too large for --max-memory-mb, failed before any of them is decoded
+        if let Some(max_memory_mb) = args.max_memory_mb {
+            let mut fitting = Vec::with_capacity(items.len());
+            for item in items {
+                match item.estimated_memory(&audio_options, &item.params(&params)) {
+                    Some(bytes) if bytes > max_memory_mb * MEGABYTE => {
+                        let error = anyhow::anyhow!(
+                            "Needs about {} MB of memory, more than --max-memory-mb {}",
+                            bytes.div_ceil(MEGABYTE),
+                            max_memory_mb
+                        );
+                        record_failure(&item, &error, &audio_options, index.as_mut(), &mut failed)?;
+                    }
+                    _ => fitting.push(item),
+                }
+            }
+            items = fitting;
+        }
+        if args.dry_run {
+            let plan = run_plan(
+                &args,
+                &items,
+                (skipped, failed),
+                &params,
+                &audio_options,
+                &output_options,
+            );
+            return write_plan(&args, &plan);
+        }
+        if let Some(upload) = &upload {
+            for (index, item) in items.iter_mut().enumerate() {
+                upload.stage(item, index);
+            }
+        }
+
+        // First pass: dataset statistics, merged across files in the order of the sources so
+        // that the sums do not depend on the scheduling of the threads
+        if let Some(cmvn_path) = &args.cmvn_out {
+            let stats = items
+                .par_iter()
+                .map(|item| {
+                    accumulate_cmvn(
+                        item.source.as_ref(),
+                        &item.output,
+                        &item.audio_options(&audio_options),
+                        &item.params(&params),
+                        output_options.denoise.as_ref(),
+                        false,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| "Failed to compute CMVN statistics")?
+                .into_iter()
+                .try_fold(CmvnAccumulator::default(), CmvnAccumulator::merge)?
+                .finish()?;
+            stats
+                .save(Path::new(cmvn_path))
+                .with_context(|| "Failed to save CMVN statistics")?;
+            output_options.cmvn = Some(stats);
+        }
+        if args.global_scale {
+            output_options.scale = items
+                .par_iter()
+                .map(|item| {
+                    accumulate_scale(
+                        item.source.as_ref(),
+                        &item.output,
+                        &item.audio_options(&audio_options),
+                        &item.params(&params),
+                        &output_options,
+                        false,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| "Failed to compute the colour scale")?
+                .into_iter()
+                .fold(
+                    ScaleAccumulator::new(&output_options.scale),
+                    ScaleAccumulator::merge,
+                )
+                .finish()?;
+        }
+
+        // Every input is attempted, so that the failures are all reported at once
+        let outcomes: Vec<(&BatchItem, Result<FileSummary>)> = items
+            .par_iter()
+            .map(|item| {
+                let item_started = Instant::now();
+                let item_audio_options = item.audio_options(&audio_options);
+                let item_output_options = item.output_options(&output_options);
+                let item_params = item.params(&params);
+                let outcome = match per_file_timeout {
+                    Some(timeout) => create_spectrogram_with_timeout(
+                        item,
+                        timeout,
+                        &item_audio_options,
+                        &item_params,
+                        &item_output_options,
+                    ),
+                    None => create_with_variants(
+                        create_spectrogram,
+                        item.source.as_ref(),
+                        &item.output,
+                        &item_audio_options,
+                        &item_params,
+                        &item_output_options,
+                        &item.variants,
+                    ),
+                };
+                let outcome = match (&upload, &item.staging) {
+                    (Some(upload), Some(staging)) => upload.finish_item(staging, outcome),
+                    _ => outcome,
+                };
+                tracing::debug!(
+                    input = %item.source.name(),
+                    elapsed = item_started.elapsed().as_secs_f64(),
+                    ok = outcome.is_ok(),
+                    "Processed input"
+                );
+                // Recorded as soon as the outputs are written, so that a crash loses at most
+                // the inputs completed since the last save
+                if let (Some(checkpoint), Ok(_)) = (&checkpoint, &outcome) {
+                    let options = item.audio_options(&audio_options);
+                    let mut checkpoint = lock_checkpoint(checkpoint);
+                    checkpoint.complete(item.source.name(), (options.start, options.end));
+                    if let Err(e) = checkpoint.save_if_due(checkpoint_interval) {
+                        tracing::warn!("Failed to save the checkpoint: {:#}", e);
+                    }
+                }
+                (item, outcome)
+            })
+            .collect();
+
+        for (item, outcome) in outcomes {
+            let mut file_summary = match outcome {
+                Ok(file_summary) => file_summary,
+                // Neither failed nor completed: processed by the next run
+                Err(e) if is_interrupted(&e) => {
+                    skipped.push(ReportedInput {
+                        input: item.source.name(),
+                        reason: "Interrupted".to_string(),
+                    });
+                    n_interrupted += 1;
+                    continue;
+                }
+                Err(e) => {
+                    record_failure(item, &e, &audio_options, index.as_mut(), &mut failed)?;
+                    continue;
+                }
+            };
+            batch_summary.add(file_summary.duration, file_summary.peak_db, &item.label());
+            processed.push(ProcessedInput {
+                input: item.source.name(),
+                duration: file_summary.duration,
+            });
+            let mut record = item.record(&file_summary, &audio_options, &output_options);
+            if let (Some(upload), Some(staging)) = (&upload, &item.staging) {
+                record.outputs = record
+                    .outputs
+                    .iter()
+                    .map(|output| upload.remote(output, staging))
+                    .collect();
+            }
+            records.push(record);
+            if let (Some(index), Some(record)) = (&mut index, records.last()) {
+                index.record(
+                    &index_record(record, item.label(), &file_summary, item.params),
+                    item.source.local_path(),
+                )?;
+            }
+            if let Some(record) = records.last() {
+                report_entries.extend(report_entry(record, item.label(), &mut file_summary));
+            }
+            add_to_average(&mut average, file_summary.average.take())?;
+            if let Some(features) = file_summary.features {
+                let name = item.source.relative_path().display().to_string();
+                feature_rows.push((name, features));
+            }
+        }
+    };
+    // Outputs written outside of the directories of the inputs
+    if let Some(upload) = &upload {
+        upload.upload(&upload.run_dir())?;
+    }
+    if let Some(checkpoint) = checkpoint {
+        checkpoint
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .save()
+            .with_context(|| "Failed to save the checkpoint")?;
+    }
+    // The rows of the inputs that succeeded are kept even if others failed
+    if let Some(dataset) = &output_options.dataset {
+        dataset.finish()?;
+    }
+
+    let (n_processed, n_failed) = (processed.len(), failed.len());
+    tracing::debug!(
+        "Processed {} inputs ({} skipped, {} failed) in {:.2}s",
+        n_processed,
+        skipped.len(),
+        n_failed,
+        started.elapsed().as_secs_f64()
+    );
+    if !single && tracing::enabled!(tracing::Level::INFO) {
+        print_summary(&processed, &skipped, &failed, started.elapsed());
+    }
+    save_html(&args, report_entries, &failed)?;
+    save_run_report(&args, started, processed, skipped, failed, renamed)?;
+    if n_interrupted > 0 {
+        return Err(Interrupted(format!(
+            "Interrupted: {} inputs were not processed",
+            n_interrupted
+        ))
+        .into());
+    }
+    if n_failed > 0 {
+        return Err(InputsFailed(format!(
+            "{} of {} inputs failed",
+            n_failed,
+            n_processed + n_failed
+        ))
+        .into());
+    }
+
+    // One row of statistics per input
+    if let Some(stats_path) = &args.stats_out {
+        let stats_path = Path::new(stats_path);
+        if stats_path.extension().is_some_and(|ext| ext == "json") {
+            save_feature_json(&feature_rows, stats_path)
+        } else {
+            save_feature_table(&feature_rows, stats_path, &output_options.csv)
+        }
+        .with_context(|| "Failed to save statistics")?;
+    }
+
+    // Average spectrogram and spectrum of the whole run
+    if let Some(average_path) = &args.average_out {
+        save_average(average, average_path, &params, &output_options)?;
+    }
+
+    // Dataset overview of the whole run
+    if let Some(summary_path) = &args.summary_png {
+        save_batch_summary_image(&batch_summary, PathBuf::from(summary_path))
+            .with_context(|| "Failed to save summary image")?;
+    }
+
+    // Inputs and the files generated from them
+    if let Some(manifest_path) = &args.manifest_out {
+        save_output_manifest(&records, Path::new(manifest_path))
+            .with_context(|| "Failed to save output manifest")?;
+    }
+
+    Ok(())
+}
+
+/// Add the spectrogram of an input to the average of the run, which requires the sample rate
+/// of the first one (the rows of mel spectrograms would otherwise span other frequencies)
+pub(super) fn add_to_average(
+    average: &mut Option<(u32, AverageAccumulator)>,
+    input: Option<(u32, AverageAccumulator)>,
+) -> Result<()> {
+    let Some((sample_rate, accumulator)) = input else {
+        return Ok(());
+    };
+    *average = Some(match average.take() {
+        None => (sample_rate, accumulator),
+        Some((first_rate, _)) if first_rate != sample_rate => anyhow::bail!(
+            "--average-out needs inputs at the same sample rate, got {} Hz and {} Hz (resample them with --sr)",
+            first_rate,
+            sample_rate
+        ),
+        Some((first_rate, merged)) => (
+            first_rate,
+            merged
+                .merge(accumulator)
+                .with_context(|| "Failed to average spectrograms")?,
+        ),
+    });
+    Ok(())
+}
+
+/// Save the average spectrogram of the run (--average-out) as an image or an array, compressed
+/// like the outputs, and its long-term spectrum next to it (mean and dB of every row)
+pub(super) fn save_average(
+    average: Option<(u32, AverageAccumulator)>,
+    path: &Path,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<()> {
+    let Some((sample_rate, accumulator)) = average else {
+        tracing::warn!("No spectrogram to average, {} not written", path.display());
+        return Ok(());
+    };
+    let average = accumulator.finish()?;
+    let format = if path.extension().is_some_and(|ext| ext == "npy") {
+        OutputFormat::Npy
+    } else {
+        OutputFormat::Png
+    };
+    let mut values = average.spectrogram;
+    if let (OutputFormat::Npy, Some(compression)) = (format, params.compression) {
+        par_compress_spectrogram_with_amin(
+            &mut values,
+            compression,
+            params.spectrogram_type,
+            params.amin(),
+        );
+    }
+    save_output(&values, path, format, output_options, None)
+        .with_context(|| "Failed to save the average spectrogram")?;
+
+    let mut levels: Vec<Vec<f32>> = average.spectrum.iter().map(|&v| vec![v]).collect();
+    par_compress_spectrogram_with_amin(
+        &mut levels,
+        Compression::Db { top_db: None },
+        params.spectrogram_type,
+        params.amin(),
+    );
+    let levels: Vec<f32> = levels.into_iter().map(|row| row[0]).collect();
+    save_average_spectrum_csv(
+        &band_frequencies(params, sample_rate),
+        &average.spectrum,
+        &levels,
+        &path.with_extension("spectrum.csv"),
+        &output_options.csv,
+    )
+    .with_context(|| "Failed to save the average spectrum")
+}
+
+/// Lock the checkpoint of the run. A panic of another input leaves it usable: it only records
+/// the inputs completed
+pub(super) fn lock_checkpoint(checkpoint: &Mutex<Checkpoint>) -> MutexGuard<'_, Checkpoint> {
+    checkpoint.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Most failed inputs listed by the summary of a batch
+pub(super) const SUMMARY_FAILURES: usize = 10;
+
+/// Print the outcome of a batch to stderr as a table, with the inputs that failed
+pub(super) fn print_summary(
+    processed: &[ProcessedInput],
+    skipped: &[ReportedInput],
+    failed: &[ReportedInput],
+    elapsed: Duration,
+) {
+    let audio: f64 = processed.iter().map(|input| input.duration as f64).sum();
+    let rows = [
+        (
+            "processed",
+            processed.len(),
+            format!("{:.1}s of audio", audio),
+        ),
+        ("skipped", skipped.len(), String::new()),
+        ("failed", failed.len(), String::new()),
+    ];
+    let mut table = String::new();
+    for (outcome, count, detail) in rows {
+        let row = format!("  {:<10} {:>8}  {}", outcome, count, detail);
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+    table.push_str(&format!(
+        "  {:<10} {:>8}\n",
+        "wall time",
+        format!("{:.1}s", elapsed.as_secs_f64())
+    ));
+    for input in failed.iter().take(SUMMARY_FAILURES) {
+        table.push_str(&format!("  failed: {}: {}\n", input.input, input.reason));
+    }
+    if failed.len() > SUMMARY_FAILURES {
+        table.push_str(&format!(
+            "  ... and {} more failed inputs (see --report)\n",
+            failed.len() - SUMMARY_FAILURES
+        ));
+    }
+    eprint!("Summary\n{}", table);
+}
+
+/// Save the run report (--report), if requested
+pub(super) fn save_run_report(
+    args: &ComputeArgs,
+    started: Instant,
+    processed: Vec<ProcessedInput>,
+    skipped: Vec<ReportedInput>,
+    failed: Vec<ReportedInput>,
+    renamed: Vec<RenamedOutput>,
+) -> Result<()> {
+    let Some(report_path) = &args.report else {
+        return Ok(());
+    };
+    let mut report = RunReport::new(processed, skipped, failed, started.elapsed().as_secs_f64());
+    report.renamed = renamed;
+    write_run_report(Path::new(report_path), &report).with_context(|| "Failed to save run report")
+}
+
+/// Save the HTML report of the processed and failed inputs (--report-html), if requested
+pub(super) fn save_html(
+    args: &ComputeArgs,
+    entries: Vec<ReportEntry>,
+    failed: &[ReportedInput],
+) -> Result<()> {
+    let Some(path) = &args.report_html else {
+        return Ok(());
+    };
+    let report = HtmlReport {
+        title: format!(
+            "spectrs: {}",
+            args.input
+                .as_deref()
+                .or(args.manifest.as_deref())
+                .unwrap_or_default()
+        ),
+        entries,
+        failed: failed.to_vec(),
+    };
+    save_html_report(&report, path).with_context(|| "Failed to save the HTML report")
+}
+
+/// Plan of the run (--dry-run): the items left to process once the skip logic was applied, with
+/// the files they would be written to
+pub(super) fn run_plan(
+    args: &ComputeArgs,
+    items: &[BatchItem],
+    (skipped, failed): (Vec<ReportedInput>, Vec<ReportedInput>),
+    params: &SpectrogramParams,
+    audio_options: &AudioOptions,
+    output_options: &OutputOptions,
+) -> RunPlan {
+    let inputs = items
+        .iter()
+        .map(|item| {
+            let options = item.audio_options(audio_options);
+            let start = options.start.unwrap_or(0.0);
+            let duration = item.source.info().map(|info| {
+                let total = info.duration() as f32;
+                (options.end.map_or(total, |end| end.min(total)) - start).max(0.0)
+            });
+            // The dataset is not created by a dry run, so its path is not in the options
+            let outputs = match &args.dataset_out {
+                Some(dataset_path) => vec![dataset_path.display().to_string()],
+                None => item.output_files(&item.output_options(output_options)),
+            };
+            PlannedInput {
+                input: item.source.name(),
+                label: item.label.clone(),
+                start: options.start,
+                end: options.end,
+                duration,
+                outputs,
+                params: item.params,
+            }
+        })
+        .collect();
+    RunPlan::new(
+        *params,
+        audio_options.sr,
+        output_options.formats.clone(),
+        inputs,
+        skipped,
+        failed,
+    )
+}
+
+/// Print the plan of --dry-run, or write it as JSON to --plan-out
+pub(super) fn write_plan(args: &ComputeArgs, plan: &RunPlan) -> Result<()> {
+    if let Some(path) = args.plan_out.as_deref().filter(|path| *path != STDIO) {
+        return write_run_plan(Path::new(path), plan).with_context(|| "Failed to save the plan");
+    }
+    let mut stdout = std::io::stdout().lock();
+    if args.plan_out.is_some() {
+        serde_json::to_writer_pretty(&mut stdout, plan)
+            .with_context(|| "Failed to write the plan")?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+    let run_params = serde_json::to_value(plan.params)?;
+    for input in &plan.inputs {
+        write!(stdout, "{} -> {}", input.input, input.outputs.join(", "))?;
+        // Parameters overridden for the directory of the input
+        if let Some(params) = &input.params
+            && let serde_json::Value::Object(fields) = serde_json::to_value(params)?
+        {
+            let changed: Vec<String> = fields
+                .iter()
+                .filter(|(name, value)| run_params.get(name.as_str()) != Some(value))
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            write!(stdout, " ({})", changed.join(", "))?;
+        }
+        writeln!(stdout)?;
+    }
+    for input in &plan.skipped {
+        writeln!(stdout, "skip {}: {}", input.input, input.reason)?;
+    }
+    for input in &plan.failed {
+        writeln!(stdout, "fail {}: {}", input.input, input.reason)?;
+    }
+    writeln!(
+        stdout,
+        "{} inputs to process ({:.1}s of audio), {} skipped, {} failing",
+        plan.inputs.len(),
+        plan.audio_duration(),
+        plan.skipped.len(),
+        plan.failed.len()
+    )?;
+    let params = serde_json::to_string(&plan.params)
+        .with_context(|| "Failed to serialize the parameters")?;
+    match plan.sample_rate {
+        Some(sr) => writeln!(stdout, "Parameters at {} Hz: {}", sr, params)?,
+        None => writeln!(stdout, "Parameters: {}", params)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Live spectrograms of the microphone (--live)
+
+use super::args::ComputeArgs;
+use super::interrupt;
+use super::options::OutputOptions;
+use super::output::save_output;
+use anyhow::{Context, Result};
+use spectrs::filter::AudioFilter;
+use spectrs::io::export::{NpyFrameWriter, OutputFormat};
+use spectrs::io::live::LiveInput;
+use spectrs::spectrogram::filterbank::filter_bank;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::streaming::StreamingStft;
+use spectrs::spectrogram::weighting::weight_filter_bank;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Analyze the default input device in real time (--live). Frames are computed as the audio
+/// arrives; with --format npy they are appended to live.npy, otherwise the last --live-window
+/// seconds are rendered to live.png every --live-interval seconds.
+pub(super) fn run_live(
+    args: &ComputeArgs,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<()> {
+    let input = LiveInput::open_default().with_context(|| "Failed to open the input device")?;
+    let sr = input.sample_rate();
+    params.validate_for_sample_rate(sr)?;
+    tracing::info!("Capturing from {} at {} Hz", input.device_name(), sr);
+
+    let mut biquads = AudioFilter {
+        bandpass: args.bandpass,
+        notches: args.notch.clone(),
+    }
+    .biquads(sr)?;
+    let mut stft = StreamingStft::from_params(params);
+    let gains: Option<Vec<f32>> =
+        params
+            .weighting
+            .bin_gains(params.n_fft, sr, params.spectrogram_type);
+    let mut filters: Option<Vec<Vec<f32>>> = filter_bank(params, sr);
+    if let (Some(filters), Some(gains)) = (&mut filters, &gains) {
+        weight_filter_bank(filters, gains);
+    }
+    let n_rows = filters.as_ref().map_or(stft.n_freq_bins(), Vec::len);
+
+    let output = Path::new(args.output_dir.as_deref().unwrap_or("."))
+        .join("live")
+        .with_extension(output_options.primary_format().extension());
+    let mut npy = match output_options.primary_format() {
+        OutputFormat::Npy => Some(NpyFrameWriter::create(&output, n_rows)?),
+        _ => None,
+    };
+    // Frames shown in PNG snapshots
+    let window_frames = (args.live_window * sr as f32 / params.hop_length as f32).ceil() as usize;
+    let mut recent: VecDeque<Vec<f32>> = VecDeque::with_capacity(window_frames + 1);
+
+    let interval = Duration::from_secs_f32(args.live_interval);
+    let duration = args.live_duration.map(Duration::from_secs_f32);
+    let start = Instant::now();
+    let mut last_output = start;
+    // Ctrl-C ends the capture like --live-duration, with the output written
+    while duration.is_none_or(|d| start.elapsed() < d) && !interrupt().is_cancelled() {
+        if let Some(mut chunk) = input.next_chunk(Duration::from_millis(50))? {
+            for biquad in biquads.iter_mut() {
+                biquad.process(&mut chunk);
+            }
+            let mut result = Ok(());
+            stft.process(&chunk, |frame| {
+                let frame: Vec<f32> = match (&filters, &gains) {
+                    (Some(filters), _) => filters
+                        .iter()
+                        .map(|f| f.iter().zip(frame).map(|(w, v)| w * v).sum::<f32>())
+                        .map(|band| params.mel_floor.map_or(band, |floor| band.max(floor)))
+                        .collect(),
+                    (None, Some(gains)) => frame.iter().zip(gains).map(|(v, g)| v * g).collect(),
+                    (None, None) => frame.to_vec(),
+                };
+                match &mut npy {
+                    Some(npy) if result.is_ok() => result = npy.push(&frame),
+                    Some(_) => {}
+                    None => {
+                        recent.push_back(frame);
+                        if recent.len() > window_frames {
+                            recent.pop_front();
+                        }
+                    }
+                }
+            });
+            result.with_context(|| "Failed to write live frames")?;
+        }
+
+        if last_output.elapsed() >= interval {
+            write_live_output(&output, npy.as_mut(), &recent, output_options)?;
+            last_output = Instant::now();
+        }
+    }
+    write_live_output(&output, npy.as_mut(), &recent, output_options)?;
+    tracing::info!("Captured {} frames", stft.n_frames());
+    Ok(())
+}
+
+/// Update the live output: flush the NPY file, or render the recent frames to the PNG
+/// snapshot (replaced at once, so viewers never see a partial image)
+pub(super) fn write_live_output(
+    output: &Path,
+    npy: Option<&mut NpyFrameWriter>,
+    recent: &VecDeque<Vec<f32>>,
+    output_options: &OutputOptions,
+) -> Result<()> {
+    if let Some(npy) = npy {
+        return npy.flush().with_context(|| "Failed to write live frames");
+    }
+    if recent.is_empty() {
+        return Ok(());
+    }
+    let n_rows = recent[0].len();
+    let spec: Vec<Vec<f32>> = (0..n_rows)
+        .map(|row| recent.iter().map(|frame| frame[row]).collect())
+        .collect();
+    save_output(&spec, output, OutputFormat::Png, output_options, None)
+        .with_context(|| "Failed to update live snapshot")?;
+    Ok(())
+}
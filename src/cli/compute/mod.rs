@@ -0,0 +1,423 @@
+//! `spectrs compute`: spectrogram images or arrays of files, directories, URLs, stdin or the
+//! microphone (the default command)
+
+use super::InvalidConfig;
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+use spectrs::cancel::CancellationToken;
+use spectrs::error::SpectrsError;
+use spectrs::io::export::OutputFormat;
+use spectrs::io::image::{ColorScale, Colormap, ScaleAccumulator};
+use spectrs::io::source::{AudioSource, is_store_url, is_url};
+use spectrs::spectrogram::cmvn::CmvnAccumulator;
+use spectrs::spectrogram::compression::par_compress_spectrogram_with_amin;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::Precision;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+#[cfg(feature = "serve")]
+use std::sync::{Arc, Mutex};
+
+mod analysis;
+mod args;
+mod batch;
+mod live;
+mod naming;
+mod options;
+mod output;
+mod sources;
+mod upload;
+
+use analysis::{check_finite, exported_spectrogram, load_audio, name_hash};
+pub use args::{ComputeArgs, WalkArgs};
+pub(crate) use args::{parse_args, parse_downmix, parse_raw_pcm, parse_sample_rate};
+use batch::FileSummary;
+pub(crate) use batch::run_files;
+use naming::compute_output_path;
+use options::{AudioOptions, Denoise, OutputOptions, SharedAudio, prepare};
+use output::{
+    fit_to_frames, format_path, image_values, save_band_levels, save_formants, save_pitch,
+    save_values, write_outputs, write_sidecar,
+};
+#[cfg(feature = "serve")]
+use sources::buffer_source;
+pub(crate) use sources::{
+    archive_sources, directory_sources, is_single_input, single_source, store_sources,
+};
+
+/// Input and output name standing for stdin and stdout
+pub(crate) const STDIO: &str = "-";
+
+/// Stop before writing once the input timed out (--per-file-timeout): it is already reported
+/// as failed, so its thread must not leave outputs or dataset rows behind
+fn check_timed_out(cancel: Option<&CancellationToken>) -> Result<()> {
+    if let Some(cancel) = cancel {
+        cancel
+            .check()
+            .with_context(|| "Timed out (--per-file-timeout)")?;
+    }
+    Ok(())
+}
+
+/// Compute and save the spectrogram of a source in double precision (--precision f64). The
+/// audio is decoded and resampled in f32 (exact for 16- and 24-bit PCM), then the STFT and the
+/// mel projection run in f64 and the values are written without rounding them back to f32.
+fn create_f64_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+    parallel: bool,
+) -> Result<FileSummary> {
+    let (audio, target_sr, summary) = load_audio(source, output, audio_options)?;
+    params.validate_for_sample_rate(target_sr)?;
+    let audio: Vec<f64> = audio.iter().map(|&s| s as f64).collect();
+
+    let mut spec = SpectrogramPipeline::builder()
+        .params(*params)
+        .parallel(parallel)
+        .build()?
+        .spectrogram(&audio, target_sr);
+    check_finite(&mut spec, source, audio_options.strict)?;
+    if let Some(fixed_frames) = output_options.fixed_frames {
+        let linear = params.compression.is_none() && !params.compat.is_filter_bank();
+        spec = fit_to_frames(spec, fixed_frames, linear);
+    }
+    if let Some(spec_augment) = output_options.spec_augment {
+        spec_augment.apply(
+            &mut spec,
+            output_options.augment_seed ^ name_hash(&source.name()),
+        );
+    }
+
+    let mut shapes = Vec::with_capacity(output_options.formats.len());
+    for &format in &output_options.formats {
+        shapes.push(save_values(
+            &spec,
+            &format_path(output, format),
+            format,
+            output_options,
+        )?);
+    }
+    if output_options.sidecar {
+        check_timed_out(output_options.cancel.as_ref())?;
+        write_sidecar(
+            source,
+            output,
+            output_options.primary_format(),
+            target_sr,
+            params,
+            shapes[0],
+            None,
+        )?;
+    }
+    if let Some(track) = &summary.pitch {
+        save_pitch(track, output, output_options)?;
+    }
+    if let Some(track) = &summary.formants {
+        save_formants(track, output, output_options)?;
+    }
+    if let Some(levels) = &summary.bands {
+        save_band_levels(levels, output, output_options)?;
+    }
+    Ok(summary)
+}
+
+/// Create spectrogram for a single file (uses parallel spectrogram computation)
+fn par_create_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<FileSummary> {
+    if params.precision == Precision::F64 {
+        return create_f64_spectrogram(source, output, audio_options, params, output_options, true);
+    }
+
+    // Create spectrogram (parallelized over frames and mel bands)
+    let (spec, target_sr, mut summary) = exported_spectrogram(
+        source,
+        output,
+        audio_options,
+        params,
+        true,
+        output_options.fused_pool_width(),
+        output_options.stats,
+    )?;
+    write_outputs(
+        source,
+        output,
+        spec,
+        &mut summary,
+        target_sr,
+        params,
+        output_options,
+    )?;
+    summary.waveform = None;
+
+    Ok(summary)
+}
+
+/// Create spectrogram for batch processing (uses sequential spectrogram computation)
+fn create_spectrogram(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<FileSummary> {
+    if params.precision == Precision::F64 {
+        return create_f64_spectrogram(
+            source,
+            output,
+            audio_options,
+            params,
+            output_options,
+            false,
+        );
+    }
+
+    // Create spectrogram (sequential - parallelism is at file level)
+    let (spec, target_sr, mut summary) = exported_spectrogram(
+        source,
+        output,
+        audio_options,
+        params,
+        false,
+        output_options.fused_pool_width(),
+        output_options.stats,
+    )?;
+    write_outputs(
+        source,
+        output,
+        spec,
+        &mut summary,
+        target_sr,
+        params,
+        output_options,
+    )?;
+    summary.waveform = None;
+
+    Ok(summary)
+}
+
+/// Signature of `create_spectrogram` and `par_create_spectrogram`
+type CreateFn = fn(
+    &dyn AudioSource,
+    &Path,
+    &AudioOptions,
+    &SpectrogramParams,
+    &OutputOptions,
+) -> Result<FileSummary>;
+
+/// Create the spectrogram of an input, then those of its variants (--sweep) from the audio
+/// decoded for the first one. Returns the summary of the first spectrogram.
+fn create_with_variants(
+    create: CreateFn,
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+    variants: &[(SpectrogramParams, PathBuf)],
+) -> Result<FileSummary> {
+    if variants.is_empty() {
+        return create(source, output, audio_options, params, output_options);
+    }
+    let audio_options = AudioOptions {
+        shared: Some(SharedAudio::default()),
+        ..audio_options.clone()
+    };
+    let summary = create(source, output, &audio_options, params, output_options)?;
+    for (params, output) in variants {
+        create(source, output, &audio_options, params, output_options)?;
+    }
+    Ok(summary)
+}
+
+/// First pass of CMVN: accumulate the per-band sums of a source without saving anything
+fn accumulate_cmvn(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    denoise: Option<&Denoise>,
+    parallel: bool,
+) -> Result<CmvnAccumulator> {
+    let (mut spec, sample_rate, _) =
+        exported_spectrogram(source, output, audio_options, params, parallel, None, false)?;
+    // Statistics of the values that are actually normalized
+    if let Some(denoise) = denoise {
+        denoise
+            .apply(&mut spec, sample_rate)
+            .with_context(|| "Failed to denoise spectrogram")?;
+    }
+    if let Some(compression) = params.compression {
+        par_compress_spectrogram_with_amin(
+            &mut spec,
+            compression,
+            params.spectrogram_type,
+            params.amin(),
+        );
+    }
+    let mut accumulator = CmvnAccumulator::default();
+    accumulator.add(&spec)?;
+    Ok(accumulator)
+}
+
+/// First pass of --global-scale: accumulate the levels of a source as they would be rendered
+fn accumulate_scale(
+    source: &dyn AudioSource,
+    output: &Path,
+    audio_options: &AudioOptions,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+    parallel: bool,
+) -> Result<ScaleAccumulator> {
+    let (mut spec, sample_rate, _) =
+        exported_spectrogram(source, output, audio_options, params, parallel, None, false)?;
+    if let Some(denoise) = &output_options.denoise {
+        denoise
+            .apply(&mut spec, sample_rate)
+            .with_context(|| "Failed to denoise spectrogram")?;
+    }
+    let reduced = image_values(&spec, sample_rate, params, output_options)?;
+    let mut accumulator = ScaleAccumulator::new(&output_options.scale);
+    accumulator.add(reduced.as_deref().unwrap_or(&spec));
+    Ok(accumulator)
+}
+
+pub fn run(args: ComputeArgs, matches: &ArgMatches) -> Result<()> {
+    install_interrupt_handler();
+    run_files(args, matches, None)
+}
+
+/// Cancelled by Ctrl-C once `install_interrupt_handler` was called
+fn interrupt() -> &'static CancellationToken {
+    static INTERRUPT: OnceLock<CancellationToken> = OnceLock::new();
+    INTERRUPT.get_or_init(CancellationToken::new)
+}
+
+/// Stop the run cleanly on Ctrl-C: the inputs not started yet are skipped, the outputs being
+/// written are completed and the reports are saved. A second Ctrl-C exits immediately
+fn install_interrupt_handler() {
+    let handler = ctrlc::set_handler(|| {
+        if interrupt().is_cancelled() {
+            std::process::exit(super::EXIT_INTERRUPTED.into());
+        }
+        interrupt().cancel();
+        tracing::warn!(
+            "Interrupted: finishing the outputs being written (Ctrl-C again to exit now)"
+        );
+    });
+    if let Err(e) = handler {
+        tracing::warn!("Failed to install the Ctrl-C handler: {}", e);
+    }
+}
+
+/// Whether an input failed because the run was interrupted
+fn is_interrupted(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| matches!(cause.downcast_ref(), Some(SpectrsError::Cancelled(_))))
+}
+
+/// Compute the spectrogram of audio in memory as `spectrs compute -` would from stdin, and
+/// return what it would write to stdout (`args.input` must be `-`), as `spectrs serve` does
+/// with the uploaded audio
+#[cfg(feature = "serve")]
+pub(crate) fn compute_buffer(
+    mut args: ComputeArgs,
+    matches: &ArgMatches,
+    audio: Vec<u8>,
+) -> Result<Vec<u8>> {
+    debug_assert_eq!(args.input.as_deref(), Some(STDIO));
+    let (params, audio_options, mut output_options) = prepare(&mut args, matches)?;
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    output_options.buffer = Some(buffer.clone());
+
+    let source = buffer_source(audio, args.raw_pcm, args.downmix)?;
+    let (params, audio_options) = audio_options.for_source(source.as_ref(), params)?;
+    let output = compute_output_path(source.as_ref(), None, output_options.primary_format());
+    par_create_spectrogram(
+        source.as_ref(),
+        &output,
+        &audio_options,
+        &params,
+        &output_options,
+    )
+    .with_context(|| "Failed to create spectrogram")?;
+    let bytes = std::mem::take(
+        &mut *buffer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output buffer poisoned by a failed write"))?,
+    );
+    Ok(bytes)
+}
+
+/// Spectrogram of an input as `spectrs compute` renders it in images, with the colours it is
+/// rendered with
+pub(crate) struct RenderedSpectrogram {
+    pub values: Vec<Vec<f32>>,
+    /// Sample rate the spectrogram was computed at
+    pub sample_rate: u32,
+    pub colormap: Colormap,
+    pub scale: ColorScale,
+}
+
+/// Compute the spectrogram of the single input of `args` as `spectrs compute` would render it
+/// in its image (denoised, fitted to --fixed-frames, cropped to the display band and reduced
+/// in time, but not shrunk to --width/--height), without writing anything, as `spectrs
+/// compare-image` does with both of its inputs
+pub(crate) fn rendered_spectrogram(
+    mut args: ComputeArgs,
+    matches: &ArgMatches,
+) -> Result<RenderedSpectrogram> {
+    let (params, audio_options, output_options) =
+        prepare(&mut args, matches).map_err(|e| e.context(InvalidConfig))?;
+    let input = args.input.clone().unwrap_or_default();
+    let remote = is_url(&input) || is_store_url(&input);
+    if !remote && !Path::new(&input).exists() {
+        return Err(anyhow::anyhow!("Input path does not exist: {}", input).context(InvalidConfig));
+    }
+    if !is_single_input(&input, args.raw_pcm) {
+        return Err(anyhow::anyhow!("Not an audio file: {}", input).context(InvalidConfig));
+    }
+
+    let source = single_source(&input, args.raw_pcm, args.downmix)?;
+    let (params, audio_options) = audio_options
+        .for_source(source.as_ref(), params)
+        .map_err(|e| e.context(InvalidConfig))?;
+    let output = compute_output_path(source.as_ref(), None, OutputFormat::Png);
+    let (mut spec, sample_rate, _) = exported_spectrogram(
+        source.as_ref(),
+        &output,
+        &audio_options,
+        &params,
+        true,
+        None,
+        false,
+    )
+    .with_context(|| format!("Failed to compute the spectrogram of {}", input))?;
+    if let Some(denoise) = &output_options.denoise {
+        denoise
+            .apply(&mut spec, sample_rate)
+            .with_context(|| "Failed to denoise spectrogram")?;
+    }
+    if let Some(fixed_frames) = output_options.fixed_frames {
+        spec = fit_to_frames(spec, fixed_frames, !params.compat.is_filter_bank());
+    }
+    let values = match image_values(&spec, sample_rate, &params, &output_options)? {
+        Some(values) => values,
+        None => spec,
+    };
+    Ok(RenderedSpectrogram {
+        values,
+        sample_rate,
+        colormap: output_options.colormap,
+        scale: output_options.scale,
+    })
+}
@@ -0,0 +1,213 @@
+//! Output paths: name templates, mirrored directories and --flatten
+
+use super::STDIO;
+use super::batch::BatchItem;
+use super::options::{AudioOptions, OutputOptions};
+use super::output::format_path;
+use anyhow::Result;
+use spectrs::io::export::OutputFormat;
+use spectrs::io::metadata::{RenamedOutput, metadata_path, tile_index_path};
+use spectrs::io::source::AudioSource;
+use spectrs::spectrogram::params::SpectrogramParams;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Files written for an input: the output of every format (or the index of its tiles) and
+/// the sidecar, or the dataset it was appended to
+pub(super) fn output_files(output: &Path, options: &OutputOptions) -> Vec<String> {
+    if let Some(dataset) = &options.dataset {
+        return vec![dataset.path.display().to_string()];
+    }
+    let mut files: Vec<PathBuf> = options
+        .formats
+        .iter()
+        .map(|&format| {
+            let path = format_path(output, format);
+            match (format, options.tile_width) {
+                (OutputFormat::Png, Some(_)) => tile_index_path(&path),
+                _ => path,
+            }
+        })
+        .collect();
+    if options.sidecar {
+        files.push(metadata_path(output));
+    }
+    files
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+/// Compute the output path for a given audio source
+pub(super) fn compute_output_path(
+    source: &dyn AudioSource,
+    output_dir: Option<&str>,
+    format: OutputFormat,
+) -> PathBuf {
+    let extension = format.extension();
+    if source.name() == STDIO {
+        return PathBuf::from(STDIO);
+    }
+    match (output_dir, source.local_path()) {
+        // Preserve the relative structure of the input inside the output directory
+        // Example: file_path="raw/b/sound.wav", base_path="raw/"
+        //   → relative="b/sound.wav" → output="processed/b/sound.png"
+        (Some(out_dir), _) => Path::new(out_dir)
+            .join(source.relative_path())
+            .with_extension(extension),
+        // Default: same directory as input
+        (None, Some(path)) => path.with_extension(extension),
+        // Non-local sources without an output directory end up in the working directory
+        (None, None) => source.relative_path().with_extension(extension),
+    }
+}
+
+/// Variables of --name-template
+pub(super) const NAME_VARIABLES: [&str; 9] = [
+    "stem", "parent", "ext", "sr", "n_fft", "hop", "win", "n_mels", "chunk",
+];
+
+/// File name given by --name-template with the values of its variables
+pub(super) fn render_name_template(
+    template: &str,
+    value: impl Fn(&str) -> String,
+) -> Result<String> {
+    let mut name = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            anyhow::bail!("Unclosed {{ in --name-template {:?}", template);
+        };
+        let variable = &rest[open + 1..close];
+        if !NAME_VARIABLES.contains(&variable) {
+            anyhow::bail!(
+                "Unknown variable {{{}}} in --name-template (available: {})",
+                variable,
+                NAME_VARIABLES
+                    .map(|name| format!("{{{}}}", name))
+                    .join(", ")
+            );
+        }
+        name.push_str(&value(variable));
+        rest = &rest[close + 1..];
+    }
+    if rest.contains('}') {
+        anyhow::bail!("Unopened }} in --name-template {:?}", template);
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// Rename the outputs of the items after --name-template, once their parameters are known.
+/// Outputs to stdout keep their name.
+pub(super) fn apply_name_template(
+    items: &mut [BatchItem],
+    template: &str,
+    params: &SpectrogramParams,
+    audio_options: &AudioOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut chunks: HashMap<String, usize> = HashMap::new();
+    let mut input_of_output: HashMap<PathBuf, String> = HashMap::new();
+    for item in items.iter_mut() {
+        if item.output == Path::new(STDIO) {
+            continue;
+        }
+        let chunk = chunks.entry(item.source.name()).or_insert(0);
+        let sr = item
+            .signal(audio_options)
+            .map(|(sr, _)| sr)
+            .or(audio_options.sr);
+        let parent = item
+            .source
+            .local_path()
+            .map_or_else(|| item.source.relative_path(), Path::to_path_buf)
+            .parent()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned());
+        let stem = item
+            .output
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        let name = |params: &SpectrogramParams| {
+            render_name_template(template, |variable| match variable {
+                "stem" => stem.clone().unwrap_or_default(),
+                "parent" => parent.clone().unwrap_or_default(),
+                "ext" => format.extension().to_string(),
+                "sr" => sr.map_or("native".to_string(), |sr| sr.to_string()),
+                "n_fft" => params.n_fft.to_string(),
+                "hop" => params.hop_length.to_string(),
+                "win" => params.win_length.to_string(),
+                "n_mels" => params.n_mels.unwrap_or(0).to_string(),
+                _ => chunk.to_string(),
+            })
+        };
+        item.output.set_file_name(name(&item.params(params))?);
+        for (variant_params, output) in &mut item.variants {
+            output.set_file_name(name(variant_params)?);
+        }
+        *chunk += 1;
+        for output in item.outputs() {
+            if let Some(previous) = input_of_output.insert(output.clone(), item.source.name()) {
+                anyhow::bail!(
+                    "--name-template gives {} and {} the same output {}; add variables telling them apart (e.g. {{parent}} or {{chunk}})",
+                    previous,
+                    item.source.name(),
+                    output.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move the outputs of the items into the output directory itself (--flatten). Outputs whose
+/// names collide are all suffixed with a hash of their path in the directory tree, so that an
+/// output is named the same whichever other inputs a run has. Returns the renamed outputs.
+pub(super) fn flatten_outputs(
+    items: &mut [BatchItem],
+    output_dir: &Path,
+) -> Result<Vec<RenamedOutput>> {
+    let flat = |output: &Path| output_dir.join(output.file_name().unwrap_or_default());
+    let mut n_outputs: HashMap<PathBuf, usize> = HashMap::new();
+    for output in items.iter().flat_map(BatchItem::outputs) {
+        *n_outputs.entry(flat(output)).or_default() += 1;
+    }
+    let mut renamed = Vec::new();
+    let mut input_of_output: HashMap<PathBuf, String> = HashMap::new();
+    for item in items.iter_mut() {
+        let name = item.source.name();
+        for tree_output in item.outputs_mut() {
+            let mut output = flat(tree_output);
+            if n_outputs[&output] > 1 {
+                let tree_path = tree_output.strip_prefix(output_dir).unwrap_or(tree_output);
+                let hash = xxh3_64(tree_path.to_string_lossy().as_bytes()) >> 32;
+                let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = output.extension().unwrap_or_default().to_string_lossy();
+                output = output.with_file_name(format!("{}_{:08x}.{}", stem, hash, extension));
+                tracing::warn!(
+                    "{} is written to {}: other inputs have outputs named {}",
+                    name,
+                    output.display(),
+                    flat(tree_output).display()
+                );
+                renamed.push(RenamedOutput {
+                    input: name.clone(),
+                    output: output.display().to_string(),
+                });
+            }
+            if let Some(previous) = input_of_output.insert(output.clone(), name.clone()) {
+                anyhow::bail!(
+                    "--flatten gives {} and {} the same output {}",
+                    previous,
+                    name,
+                    output.display()
+                );
+            }
+            *tree_output = output;
+        }
+    }
+    Ok(renamed)
+}
@@ -0,0 +1,1307 @@
+//! Options of a run: how inputs are decoded and outputs written, resolved from the command
+//! line and validated before any input is touched
+
+use super::analysis::exported_spectrogram;
+use super::args::{ComputeArgs, SegmentFormat};
+use super::batch::{FileSummary, source_label};
+use super::naming::render_name_template;
+use super::sources::single_source;
+use super::{STDIO, interrupt};
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use spectrs::augment::{Augmentation, SpecAugment};
+use spectrs::cancel::CancellationToken;
+use spectrs::features::activity::ActivityParams;
+use spectrs::features::cepstrum::CppParams;
+use spectrs::features::formant::FormantParams;
+use spectrs::features::octave::BandFraction;
+use spectrs::features::pitch::YinParams;
+use spectrs::filter::AudioFilter;
+use spectrs::io::annotations::{Annotation, load_annotations};
+use spectrs::io::audio::{DownmixMode, RawPcm, ResampleQuality};
+use spectrs::io::cache::SpectrogramCache;
+use spectrs::io::dataset::DatasetWriter;
+use spectrs::io::export::{CsvOptions, OutputFormat};
+use spectrs::io::image::{ColorScale, Colormap};
+use spectrs::io::source::{AudioSource, is_archive_path, is_store_url, is_url};
+use spectrs::spectrogram::cmvn::CmvnStats;
+use spectrs::spectrogram::denoise::NoiseProfile;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::fft::FftBackendType;
+use spectrs::spectrogram::gpu::{Device, GpuStft};
+use spectrs::spectrogram::ops::GateMode;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pooling::{FitMode, PoolMode};
+use spectrs::spectrogram::stft::{
+    FrameAlignment, Precision, SpectrogramType, hop_length_from_overlap, samples_from_ms,
+};
+use spectrs::spectrogram::torchaudio::torchaudio_defaults;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Options controlling how audio is decoded and prepared before the STFT, and where the
+/// STFT runs
+#[derive(Clone)]
+pub(super) struct AudioOptions {
+    /// Target sample rate (None keeps the original one)
+    pub(super) sr: Option<u32>,
+    /// Resampler preset used when resampling
+    pub(super) resample_quality: ResampleQuality,
+    /// Start of the segment to process (seconds)
+    pub(super) start: Option<f32>,
+    /// End of the segment to process (seconds)
+    pub(super) end: Option<f32>,
+    /// Dump the prepared audio next to the output
+    pub(super) dump_resampled: bool,
+    /// Salvage damaged files instead of failing
+    pub(super) lenient: bool,
+    /// Fail on non-finite values in the spectrogram instead of replacing them
+    pub(super) strict: bool,
+    /// Layout of headerless PCM inputs, decoded as a whole rather than streamed
+    pub(super) raw_pcm: Option<RawPcm>,
+    /// How multichannel audio is reduced to mono
+    pub(super) downmix: DownmixMode,
+    /// Keep the processed audio for the waveform panel
+    pub(super) waveform: bool,
+    /// Meter the loudness of the decoded audio
+    pub(super) loudness: bool,
+    /// Compute the chroma of the linear spectrogram (--chroma-out, --tonnetz-out)
+    pub(super) chroma: bool,
+    /// Compute the cepstral peaks of the linear spectrogram (--cpp-out)
+    pub(super) cpp: Option<CppParams>,
+    /// Analyze the octave bands of the processed audio (--band-levels-out)
+    pub(super) bands: Option<BandOptions>,
+    /// Track the pitch of the processed audio over the frames of the STFT (the frame length is
+    /// raised at each sample rate to hold two periods of the lowest pitch, if needed)
+    pub(super) pitch: Option<YinParams>,
+    /// Track the formants of the processed audio over the frames of the STFT
+    pub(super) formants: Option<FormantParams>,
+    /// Band-pass and notches applied to the audio before the STFT
+    pub(super) filter: AudioFilter,
+    /// Transforms of the audio before the STFT (--augment), held in memory as a whole
+    pub(super) augment: Vec<Augmentation>,
+    /// Seed of the random amounts of the augmentations
+    pub(super) augment_seed: u64,
+    /// Threshold (dB below the loudest frame) and handling of the quiet frames gated before
+    /// the mel projection (--gate-db)
+    pub(super) gate: Option<(f32, GateMode)>,
+    /// GPU context replacing the CPU STFT and mel projection (--device gpu)
+    pub(super) gpu: Option<Arc<GpuStft>>,
+    /// Cache of computed spectrograms (--cache-dir)
+    pub(super) cache: Option<SpectrogramCache>,
+    /// Audio decoded for the first variant of an input, reused by the others (--sweep)
+    pub(super) shared: Option<SharedAudio>,
+    /// Cancelled once the input timed out (--per-file-timeout), None without a timeout
+    pub(super) cancel: Option<CancellationToken>,
+    /// Window and hop in milliseconds resolved at the sample rate of each input (--win-ms and
+    /// --hop-ms without --sr)
+    pub(super) durations: Option<FrameDurations>,
+}
+
+/// Prepared audio of an input with its sample rate and summary, decoded once for all of its
+/// variants
+pub(super) type SharedAudio = Arc<Mutex<Option<(Vec<f32>, u32, FileSummary)>>>;
+
+/// Octave band analysis of the processed audio
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BandOptions {
+    pub(super) fraction: BandFraction,
+    /// Duration of the frames (milliseconds), the hop length if not given
+    pub(super) frame_ms: Option<f32>,
+    pub(super) hop_length: usize,
+}
+
+impl AudioOptions {
+    pub(super) fn from_cli(args: &ComputeArgs) -> Self {
+        Self {
+            sr: args.sr,
+            resample_quality: args.resample_quality,
+            start: args.start,
+            end: args.end,
+            dump_resampled: args.dump_resampled,
+            lenient: args.lenient,
+            strict: args.strict,
+            raw_pcm: args.raw_pcm,
+            downmix: args.downmix,
+            waveform: args.with_waveform,
+            loudness: args.loudness,
+            chroma: args.chroma_out.is_some() || args.tonnetz_out.is_some(),
+            cpp: args.cpp_out.map(|_| CppParams {
+                f0_min: args.cpp_range.0,
+                f0_max: args.cpp_range.1,
+                ..CppParams::default()
+            }),
+            bands: args.band_levels_out.map(|_| BandOptions {
+                fraction: args.bands,
+                frame_ms: args.band_frame_ms,
+                hop_length: args.hop_length,
+            }),
+            pitch: (args.pitch_out.is_some() || args.pitch_overlay).then(|| YinParams {
+                f_min: args.pitch_range.0,
+                f_max: args.pitch_range.1,
+                // Windows of the STFT start every hop, without padding
+                frame_length: args.win_length,
+                hop_length: args.hop_length,
+                center: false,
+                ..YinParams::default()
+            }),
+            formants: (args.formants_out.is_some() || args.formant_overlay).then(|| {
+                FormantParams {
+                    max_formant: args.formant_ceiling,
+                    frame_length: args.win_length,
+                    hop_length: args.hop_length,
+                    center: false,
+                    ..FormantParams::default()
+                }
+            }),
+            filter: AudioFilter {
+                bandpass: args.bandpass,
+                notches: args.notch.clone(),
+            },
+            augment: args.augment.clone(),
+            augment_seed: args.augment_seed,
+            gate: args.gate_db.map(|db| (db, args.gate_mode)),
+            gpu: None,
+            cache: args.cache_dir.as_deref().map(SpectrogramCache::new),
+            shared: None,
+            cancel: None,
+            durations: None,
+        }
+    }
+
+    /// Options of an input computed with parameters of its own: the pitch, formants and band
+    /// levels follow its frames
+    pub(super) fn framed_by(&self, params: &SpectrogramParams) -> Self {
+        let mut options = self.clone();
+        if let Some(pitch) = &mut options.pitch {
+            pitch.frame_length = params.win_length;
+            pitch.hop_length = params.hop_length;
+        }
+        if let Some(formants) = &mut options.formants {
+            formants.frame_length = params.win_length;
+            formants.hop_length = params.hop_length;
+        }
+        if let Some(bands) = &mut options.bands {
+            bands.hop_length = params.hop_length;
+        }
+        options
+    }
+
+    /// Parameters and options of a single source, with --win-ms/--hop-ms resolved at its
+    /// sample rate unless they were for the whole run
+    pub(super) fn for_source(
+        self,
+        source: &dyn AudioSource,
+        params: SpectrogramParams,
+    ) -> Result<(SpectrogramParams, Self)> {
+        match &self.durations {
+            Some(durations) => {
+                let params = durations.params_of(source, &params)?;
+                let options = self.framed_by(&params);
+                Ok((params, options))
+            }
+            None => Ok((params, self)),
+        }
+    }
+
+    /// Token stopping the decoding and the STFT: that of the input with --per-file-timeout,
+    /// the Ctrl-C one otherwise
+    pub(super) fn cancellation(&self) -> &CancellationToken {
+        self.cancel.as_ref().unwrap_or(interrupt())
+    }
+}
+
+/// Window and hop given in milliseconds (--win-ms, --hop-ms), resolved into samples at the
+/// sample rate of the spectrogram: once for the run with --sr, for each input otherwise
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FrameDurations {
+    win_ms: Option<f32>,
+    hop_ms: Option<f32>,
+    /// Overlap of the windows (--overlap), the hop following the resolved window
+    overlap: Option<f32>,
+    /// Whether --n-fft and --hop-length were given rather than derived from the window
+    n_fft_given: bool,
+    hop_length_given: bool,
+    compat: Compat,
+}
+
+impl FrameDurations {
+    /// Durations of the command line, None without --win-ms and --hop-ms
+    fn from_cli(args: &ComputeArgs, given: impl Fn(&str) -> bool) -> Option<Self> {
+        (args.win_ms.is_some() || args.hop_ms.is_some()).then(|| Self {
+            win_ms: args.win_ms,
+            hop_ms: args.hop_ms,
+            overlap: args.overlap,
+            n_fft_given: given("n_fft"),
+            hop_length_given: given("hop_length"),
+            compat: args.compat,
+        })
+    }
+
+    /// FFT size, window and hop at `sample_rate`, replacing those of `lengths` derived from
+    /// the durations
+    fn resolve(&self, lengths: (usize, usize, usize), sample_rate: u32) -> (usize, usize, usize) {
+        let (mut n_fft, mut win_length, mut hop_length) = lengths;
+        if let Some(win_ms) = self.win_ms {
+            win_length = samples_from_ms(win_ms, sample_rate);
+            if !self.n_fft_given {
+                n_fft = win_length.next_power_of_two();
+            }
+            if !self.hop_length_given && self.hop_ms.is_none() {
+                hop_length = match self.compat {
+                    Compat::Torchaudio => win_length / 2,
+                    _ => win_length / 4,
+                }
+                .max(1);
+            }
+        }
+        if let Some(hop_ms) = self.hop_ms {
+            hop_length = samples_from_ms(hop_ms, sample_rate);
+        }
+        if let Some(overlap) = self.overlap {
+            hop_length = hop_length_from_overlap(win_length, overlap);
+        }
+        (n_fft, win_length, hop_length)
+    }
+
+    /// Parameters of a source, resolved at its sample rate, which must be known before
+    /// decoding it
+    pub(super) fn params_of(
+        &self,
+        source: &dyn AudioSource,
+        base: &SpectrogramParams,
+    ) -> Result<SpectrogramParams> {
+        let Some(info) = source.info() else {
+            anyhow::bail!(
+                "The sample rate of {} is not known before decoding it: give --sr to resolve --win-ms/--hop-ms",
+                source.name()
+            );
+        };
+        let (n_fft, win_length, hop_length) = self.resolve(
+            (base.n_fft, base.win_length, base.hop_length),
+            info.sample_rate,
+        );
+        let params = SpectrogramParams {
+            n_fft,
+            win_length,
+            hop_length,
+            ..*base
+        };
+        params
+            .validate()
+            .with_context(|| format!("Invalid parameters for {}", source.name()))?;
+        Ok(params)
+    }
+}
+
+/// Options controlling how the computed spectrogram is written
+#[derive(Clone)]
+pub(super) struct OutputOptions {
+    /// File formats, each written to its own file
+    pub(super) formats: Vec<OutputFormat>,
+    /// Colormap for image outputs
+    pub(super) colormap: Colormap,
+    /// Mapping of the values to the colormap
+    pub(super) scale: ColorScale,
+    /// Frequency band (Hz) images are cropped to
+    pub(super) display_band: (Option<f32>, Option<f32>),
+    /// Pooling of every N frames of images
+    pub(super) time_reduce: Option<(PoolMode, usize)>,
+    /// Maximum image width (frames)
+    pub(super) width: Option<usize>,
+    /// Maximum image height (frequency bins)
+    pub(super) height: Option<usize>,
+    /// Split images into tiles of this many frames
+    pub(super) tile_width: Option<usize>,
+    /// Time of the first frame in the source (seconds), used to index tiles
+    pub(super) time_offset: f32,
+    /// Number formatting for CSV outputs
+    pub(super) csv: CsvOptions,
+    /// Compute the per-file feature statistics
+    pub(super) stats: bool,
+    /// Write a JSON sidecar next to each output
+    pub(super) sidecar: bool,
+    /// Dataset the spectrograms are appended to instead of their own outputs
+    pub(super) dataset: Option<DatasetSink>,
+    /// Buffer the output of `-` is written to instead of stdout (spectrs serve)
+    pub(super) buffer: Option<Arc<Mutex<Vec<u8>>>>,
+    /// Dataset-wide statistics every output is normalized with
+    pub(super) cmvn: Option<CmvnStats>,
+    /// Spectral subtraction applied before normalizing and writing
+    pub(super) denoise: Option<Denoise>,
+    /// Masks of the exported values (--spec-augment)
+    pub(super) spec_augment: Option<SpecAugment>,
+    /// Number of frames every output is brought to, and how
+    pub(super) fixed_frames: Option<(usize, FitMode)>,
+    /// Width of the average of the run the spectrograms are added to (--average-out)
+    pub(super) average_frames: Option<usize>,
+    /// Format of the pitch tracks written next to the outputs
+    pub(super) pitch_out: Option<OutputFormat>,
+    /// Draw the pitch contour over images
+    pub(super) pitch_overlay: bool,
+    /// Format of the formant tracks written next to the outputs
+    pub(super) formants_out: Option<OutputFormat>,
+    /// Draw the formant tracks over images
+    pub(super) formant_overlay: bool,
+    /// Format of the onsets written next to the outputs
+    pub(super) onsets_out: Option<OutputFormat>,
+    /// Mark the onsets over images
+    pub(super) onset_overlay: bool,
+    /// Format of the segment lists written next to the outputs, and how they are split
+    pub(super) segments_out: Option<(SegmentFormat, ActivityParams)>,
+    /// Formats of the chroma and tonal centroids written next to the outputs
+    pub(super) chroma_out: Option<OutputFormat>,
+    pub(super) tonnetz_out: Option<OutputFormat>,
+    /// Format of the cepstral peaks written next to the outputs
+    pub(super) cpp_out: Option<OutputFormat>,
+    /// Format of the band levels written next to the outputs
+    pub(super) band_levels_out: Option<OutputFormat>,
+    /// Labels outlined over images
+    pub(super) annotations: Option<Annotations>,
+    /// Height of the onset strength peaks above their local mean
+    pub(super) onset_delta: f32,
+    /// Seed of the masks, combined with the name of each input
+    pub(super) augment_seed: u64,
+    /// Render a thumbnail of every image for the HTML report
+    pub(super) thumbnails: bool,
+    /// Cancelled once the input timed out (--per-file-timeout), None without a timeout
+    pub(super) cancel: Option<CancellationToken>,
+}
+
+/// Labels outlined over images (--annotations)
+#[derive(Clone)]
+pub(super) enum Annotations {
+    /// Labels of every input
+    Shared(Arc<Vec<Annotation>>),
+    /// Directory of label files named after the inputs
+    Directory(PathBuf),
+}
+
+impl Annotations {
+    pub(super) fn open(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self::Directory(path.to_path_buf()));
+        }
+        let annotations = load_annotations(path).with_context(|| "Failed to read annotations")?;
+        Ok(Self::Shared(Arc::new(annotations)))
+    }
+
+    /// Labels of a source, none if the directory holds no file for it
+    pub(super) fn of(&self, source: &dyn AudioSource) -> Result<Cow<'_, [Annotation]>> {
+        let dir = match self {
+            Self::Shared(annotations) => return Ok(Cow::Borrowed(annotations.as_slice())),
+            Self::Directory(dir) => dir,
+        };
+        let base = dir.join(source.relative_path());
+        for extension in ["json", "txt"] {
+            let path = base.with_extension(extension);
+            if path.is_file() {
+                let annotations =
+                    load_annotations(&path).with_context(|| "Failed to read annotations")?;
+                return Ok(Cow::Owned(annotations));
+            }
+        }
+        Ok(Cow::Borrowed(&[]))
+    }
+}
+
+/// Parquet or Arrow IPC dataset every spectrogram is appended to (--dataset-out)
+#[derive(Clone)]
+pub(super) struct DatasetSink {
+    pub(super) path: PathBuf,
+    /// Shared by the threads of a batch, taken when the dataset is finished
+    pub(super) writer: Arc<Mutex<Option<DatasetWriter>>>,
+    /// Label of the input (from its manifest entry), instead of its directory
+    pub(super) label: Option<String>,
+}
+
+impl DatasetSink {
+    pub(super) fn create(path: &Path, params: &SpectrogramParams) -> Result<Self> {
+        let writer = DatasetWriter::create(path, params)
+            .with_context(|| format!("Failed to create dataset {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Arc::new(Mutex::new(Some(writer))),
+            label: None,
+        })
+    }
+
+    /// Append the spectrogram of a source as a new row
+    pub(super) fn append(
+        &self,
+        source: &dyn AudioSource,
+        sample_rate: u32,
+        spec: &[Vec<f32>],
+    ) -> Result<()> {
+        let label = self.label.clone().unwrap_or_else(|| source_label(source));
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Dataset writer poisoned by a failed input"))?;
+        let Some(writer) = writer.as_mut() else {
+            anyhow::bail!("Dataset {} is already finished", self.path.display());
+        };
+        writer
+            .append(&source.name(), &label, sample_rate, spec)
+            .with_context(|| format!("Failed to append to dataset {}", self.path.display()))
+    }
+
+    /// Write the footer of the dataset, after the last spectrogram
+    pub(super) fn finish(&self) -> Result<()> {
+        let writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Dataset writer poisoned by a failed input"))?
+            .take();
+        match writer {
+            Some(writer) => writer
+                .finish()
+                .with_context(|| format!("Failed to finish dataset {}", self.path.display())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Spectral subtraction of a noise floor (--denoise)
+#[derive(Clone)]
+pub(super) struct Denoise {
+    /// Profile of --noise-wav and the sample rate it was computed at (None to estimate a
+    /// profile from the quietest frames of each input)
+    pub(super) noise: Option<(NoiseProfile, u32)>,
+    /// Percentage of the quietest frames of an input its profile is estimated from
+    pub(super) percent: f32,
+    /// Fraction of their original level values are floored at
+    pub(super) spectral_floor: f32,
+}
+
+impl Denoise {
+    /// Subtract the noise floor from a spectrogram computed at `sample_rate`
+    pub(super) fn apply(&self, spec: &mut [Vec<f32>], sample_rate: u32) -> Result<()> {
+        let estimated;
+        let profile = match &self.noise {
+            Some((profile, noise_sr)) => {
+                if *noise_sr != sample_rate {
+                    anyhow::bail!(
+                        "The noise recording is at {} Hz but the input is at {} Hz \
+                         (resample both with --sr)",
+                        noise_sr,
+                        sample_rate
+                    );
+                }
+                profile
+            }
+            None => {
+                estimated = NoiseProfile::from_quietest_frames(spec, self.percent)?;
+                &estimated
+            }
+        };
+        profile.subtract(spec, self.spectral_floor)?;
+        Ok(())
+    }
+}
+
+impl OutputOptions {
+    pub(super) fn from_cli(args: &ComputeArgs) -> Self {
+        Self {
+            // The dataset holds the values of numerical formats
+            formats: match args.dataset_out {
+                Some(_) => vec![OutputFormat::Npy],
+                None => args.format.iter().fold(Vec::new(), |mut formats, &format| {
+                    if !formats.contains(&format) {
+                        formats.push(format);
+                    }
+                    formats
+                }),
+            },
+            colormap: args.colormap,
+            scale: ColorScale {
+                vmin: args.vmin,
+                vmax: args.vmax,
+                percentile_clip: args.percentile_clip,
+                gamma: args.gamma,
+                magnitude: args.spec_type == SpectrogramType::Magnitude,
+            },
+            display_band: (args.display_fmin, args.display_fmax),
+            time_reduce: args.time_reduce,
+            width: args.width.map(|w| w as usize),
+            height: args.height.map(|h| h as usize),
+            tile_width: args.tile_width.map(|w| w as usize),
+            time_offset: args.start.unwrap_or(0.0),
+            csv: CsvOptions {
+                delimiter: args.csv_delimiter,
+                decimal: args.csv_decimal,
+                precision: args.csv_precision,
+            },
+            stats: args.stats_out.is_some(),
+            sidecar: args.sidecar,
+            dataset: None,
+            buffer: None,
+            cmvn: None,
+            denoise: args.denoise.then_some(Denoise {
+                noise: None,
+                percent: args.noise_percent,
+                spectral_floor: args.denoise_floor,
+            }),
+            spec_augment: args.spec_augment,
+            fixed_frames: args
+                .fixed_frames
+                .map(|n_frames| (n_frames as usize, args.fixed_mode)),
+            average_frames: args
+                .average_out
+                .is_some()
+                .then_some(args.average_frames as usize),
+            pitch_out: args.pitch_out,
+            pitch_overlay: args.pitch_overlay,
+            formants_out: args.formants_out,
+            formant_overlay: args.formant_overlay,
+            onsets_out: args.onsets_out,
+            onset_overlay: args.onset_overlay,
+            segments_out: args.segments_out.map(|format| {
+                (
+                    format,
+                    ActivityParams {
+                        threshold_db: args.activity_db,
+                        min_silence: args.min_silence_ms / 1000.0,
+                        min_active: args.min_active_ms / 1000.0,
+                    },
+                )
+            }),
+            chroma_out: args.chroma_out,
+            tonnetz_out: args.tonnetz_out,
+            cpp_out: args.cpp_out,
+            band_levels_out: args.band_levels_out,
+            annotations: None,
+            onset_delta: args.onset_delta,
+            augment_seed: args.augment_seed,
+            thumbnails: args.report_html.is_some(),
+            cancel: None,
+        }
+    }
+}
+
+impl OutputOptions {
+    /// Width to pool frames to while they are computed. Only possible when the image is the
+    /// only product of the full-resolution spectrogram (no statistics, onsets, segments, chroma,
+    /// cepstrum, average or raw export)
+    /// and is not denoised (subtraction does not commute with pooling) nor reduced by
+    /// --time-reduce or --fixed-frames.
+    pub(super) fn fused_pool_width(&self) -> Option<usize> {
+        match self.formats[..] {
+            [OutputFormat::Png]
+                if !self.stats
+                    && !self.detects_onsets()
+                    && self.segments_out.is_none()
+                    && self.chroma_out.is_none()
+                    && self.tonnetz_out.is_none()
+                    && self.cpp_out.is_none()
+                    && self.average_frames.is_none()
+                    && self.denoise.is_none()
+                    && self.time_reduce.is_none()
+                    && self.fixed_frames.is_none() =>
+            {
+                self.width
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether onsets are detected in the spectrograms (--onsets-out, --onset-overlay)
+    pub(super) fn detects_onsets(&self) -> bool {
+        self.onsets_out.is_some() || self.onset_overlay
+    }
+
+    /// Format of the first output, the one described by the sidecar (and the only one of
+    /// stdout and live outputs)
+    pub(super) fn primary_format(&self) -> OutputFormat {
+        self.formats[0]
+    }
+}
+
+/// Resolve the options depending on each other (presets, milliseconds, overlap), then
+/// validate the spectrogram parameters and the combination of options before touching any
+/// input. Returns the parameters and the audio and output options of the run
+pub(super) fn prepare(
+    args: &mut ComputeArgs,
+    matches: &ArgMatches,
+) -> Result<(SpectrogramParams, AudioOptions, OutputOptions)> {
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    apply_compat_defaults(args, given);
+    let durations = resolve_frame_lengths(args, given);
+    cap_f_max(args);
+
+    let params = spectrogram_params(args)?;
+    let mut audio_options = AudioOptions::from_cli(args);
+    audio_options.durations = durations;
+    let mut output_options = OutputOptions::from_cli(args);
+    output_options
+        .csv
+        .validate()
+        .with_context(|| "Invalid CSV options")?;
+    if let Some(formants) = &audio_options.formants {
+        formants
+            .validate()
+            .with_context(|| "Invalid formant tracking options")?;
+    }
+    if let Some(end) = args.end
+        && end <= args.start.unwrap_or(0.0)
+    {
+        anyhow::bail!(
+            "--end ({}s) must be after --start ({}s)",
+            end,
+            args.start.unwrap_or(0.0)
+        );
+    }
+    let writes_png = output_options.formats.contains(&OutputFormat::Png);
+    validate_features(args)?;
+    validate_compat(args, writes_png)?;
+    validate_side_outputs(args, writes_png)?;
+    validate_image_options(args, &output_options)?;
+    validate_live(args, &output_options)?;
+    validate_naming(args)?;
+    validate_stdio(args, &output_options)?;
+
+    if let Some(path) = &args.annotations {
+        output_options.annotations = Some(Annotations::open(path)?);
+    }
+    if let Some(cmvn_path) = &args.cmvn_in {
+        output_options.cmvn = Some(CmvnStats::load(Path::new(cmvn_path))?);
+    }
+    if args.device == Device::Gpu {
+        // A single context is shared by every file
+        audio_options.gpu = Some(Arc::new(
+            GpuStft::new().with_context(|| "Failed to initialize the GPU")?,
+        ));
+    }
+    if args.deterministic {
+        configure_single_thread()?;
+    }
+    if let Some(denoise) = &mut output_options.denoise {
+        load_noise_profile(args, &params, audio_options.durations, denoise)?;
+    }
+
+    Ok((params, audio_options, output_options))
+}
+
+/// Check that the options needing optional features were compiled in
+fn validate_features(args: &ComputeArgs) -> Result<()> {
+    let missing = [
+        (
+            "--index-db",
+            "index",
+            args.index_db.is_some() && !cfg!(feature = "index"),
+        ),
+        (
+            "--dataset-out",
+            "dataset",
+            args.dataset_out.is_some() && !cfg!(feature = "dataset"),
+        ),
+    ];
+    if let Some((option, feature, _)) = missing.iter().find(|(_, _, missing)| *missing) {
+        anyhow::bail!(
+            "{} requires spectrs to be compiled with the `{}` feature",
+            option,
+            feature
+        );
+    }
+    Ok(())
+}
+
+/// Replace the defaults of the options not given explicitly with those of the torchaudio
+/// preset
+fn apply_compat_defaults(args: &mut ComputeArgs, given: impl Fn(&str) -> bool) {
+    if args.compat != Compat::Torchaudio {
+        return;
+    }
+    let defaults = torchaudio_defaults();
+    if !given("n_fft") {
+        args.n_fft = defaults.n_fft;
+    }
+    if !given("win_length") {
+        args.win_length = args.n_fft;
+    }
+    if !given("hop_length") {
+        args.hop_length = (args.win_length / 2).max(1);
+    }
+    if !given("n_mels") {
+        args.n_mels = defaults.n_mels;
+    }
+    if !given("mel_scale") {
+        args.mel_scale = defaults.mel_scale;
+    }
+}
+
+/// Resolve the window and the hop given in milliseconds or as an overlap into samples.
+/// Without --sr, the milliseconds are returned to be resolved for each input
+fn resolve_frame_lengths(
+    args: &mut ComputeArgs,
+    given: impl Fn(&str) -> bool,
+) -> Option<FrameDurations> {
+    let durations = FrameDurations::from_cli(args, given);
+    match (durations, args.sr) {
+        (Some(durations), Some(sr)) => {
+            (args.n_fft, args.win_length, args.hop_length) =
+                durations.resolve((args.n_fft, args.win_length, args.hop_length), sr);
+            None
+        }
+        (Some(durations), None) => {
+            // The window of the run stays within the FFT until it is resolved for each input
+            args.win_length = args.win_length.min(args.n_fft);
+            if let Some(overlap) = args.overlap {
+                args.hop_length = hop_length_from_overlap(args.win_length, overlap);
+            }
+            Some(durations)
+        }
+        (None, _) => {
+            // Derive the hop length from the overlap percentage if requested
+            if let Some(overlap) = args.overlap {
+                args.hop_length = hop_length_from_overlap(args.win_length, overlap);
+            }
+            None
+        }
+    }
+}
+
+/// Bring --f-max down to the Nyquist frequency of the resampled audio, as bands above it
+/// would be empty
+fn cap_f_max(args: &mut ComputeArgs) {
+    if let (Some(sr), Some(f_max), Some(_)) = (args.sr, args.f_max, args.n_mels)
+        && f_max > sr as f32 / 2.0
+    {
+        let nyquist = sr as f32 / 2.0;
+        tracing::warn!(
+            "--f-max {} Hz is above the Nyquist frequency of --sr {} ({} Hz): the mel bands stop at {} Hz",
+            f_max,
+            sr,
+            nyquist,
+            nyquist
+        );
+        args.f_max = Some(nyquist);
+    }
+}
+
+/// Validate the spectrogram parameters given on the command line
+fn spectrogram_params(args: &ComputeArgs) -> Result<SpectrogramParams> {
+    SpectrogramParams::builder()
+        .n_fft(args.n_fft)
+        .hop_length(args.hop_length)
+        .win_length(args.win_length)
+        .center(args.center)
+        .frame_alignment(args.frame_alignment)
+        .periodic_window(args.periodic_window)
+        .window_norm(args.window_norm)
+        .spectrogram_type(args.spec_type)
+        .n_mels(args.n_mels)
+        .f_min(args.f_min)
+        .f_max(args.f_max)
+        .mel_scale(args.mel_scale)
+        .filter_bank(args.filter_bank)
+        .frequency_scale(args.frequency_scale)
+        .lpc_order(args.lpc_order.map(|order| order as usize))
+        .weighting(args.weighting)
+        .compression(args.compress)
+        .amin(args.amin)
+        .mel_floor(args.mel_floor)
+        .compat(args.compat)
+        .dither(args.dither)
+        .deterministic(args.deterministic)
+        .precision(args.precision)
+        .fft_backend(args.fft_backend)
+        .require_power_of_two(args.device == Device::Gpu)
+        .build()
+        .with_context(|| "Invalid spectrogram parameters")
+}
+
+/// Check the options against the compatibility preset, the precision and the device
+fn validate_compat(args: &ComputeArgs, writes_png: bool) -> Result<()> {
+    // Normalized values and log energies can be negative, which images cannot represent
+    if (args.cmvn_out.is_some() || args.cmvn_in.is_some()) && writes_png {
+        anyhow::bail!("CMVN requires a numerical output format (e.g. --format csv)");
+    }
+    if args.compat.is_filter_bank() && writes_png {
+        anyhow::bail!("Filter bank presets require a numerical output format (e.g. --format csv)");
+    }
+    // The noise floor is subtracted from linear power or magnitude values
+    if args.compat.is_filter_bank() && args.report_html.is_some() {
+        anyhow::bail!("--report-html is not available with filter bank presets (log energies)");
+    }
+    if args.compat.is_filter_bank() && args.denoise {
+        anyhow::bail!("--denoise is not available with filter bank presets (log energies)");
+    }
+    if args.compat != Compat::Librosa && args.gate_db.is_some() {
+        anyhow::bail!("--gate-db is only available with --compat librosa");
+    }
+    // Dropped frames no longer follow the timeline of the audio
+    if args.gate_db.is_some() && args.gate_mode == GateMode::Drop {
+        let unsupported = [
+            (
+                "--pitch-out/--pitch-overlay",
+                args.pitch_out.is_some() || args.pitch_overlay,
+            ),
+            (
+                "--formants-out/--formant-overlay",
+                args.formants_out.is_some() || args.formant_overlay,
+            ),
+            (
+                "--onsets-out/--onset-overlay",
+                args.onsets_out.is_some() || args.onset_overlay,
+            ),
+            ("--segments-out", args.segments_out.is_some()),
+            ("--annotations", args.annotations.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+            anyhow::bail!("{} is not available with --gate-mode drop", flag);
+        }
+    }
+    if args.compat != Compat::Librosa && args.stats_out.is_some() {
+        anyhow::bail!("--stats-out is only available with --compat librosa");
+    }
+    if args.frame_alignment != FrameAlignment::Spectrs && args.device == Device::Gpu {
+        anyhow::bail!("--frame-alignment librosa is not available with --device gpu");
+    }
+    if args.compat != Compat::Librosa && args.device == Device::Gpu {
+        anyhow::bail!("--device gpu is only available with --compat librosa");
+    }
+    if args.fft_backend != FftBackendType::RustFft && args.device == Device::Gpu {
+        anyhow::bail!("--fft-backend is not available with --device gpu");
+    }
+    if args.precision == Precision::F64 {
+        let unsupported = [
+            ("--format png", writes_png),
+            ("--device gpu", args.device == Device::Gpu),
+            ("--denoise", args.denoise),
+            ("--gate-db", args.gate_db.is_some()),
+            ("--stats-out", args.stats_out.is_some()),
+            (
+                "--onsets-out/--onset-overlay",
+                args.onsets_out.is_some() || args.onset_overlay,
+            ),
+            ("--segments-out", args.segments_out.is_some()),
+            ("--cache-dir", args.cache_dir.is_some()),
+            (
+                "--chroma-out/--tonnetz-out",
+                args.chroma_out.is_some() || args.tonnetz_out.is_some(),
+            ),
+            ("--cpp-out", args.cpp_out.is_some()),
+            (
+                "--cmvn-out/--cmvn-in",
+                args.cmvn_out.is_some() || args.cmvn_in.is_some(),
+            ),
+            ("--dataset-out", args.dataset_out.is_some()),
+            ("--report-html", args.report_html.is_some()),
+            ("--average-out", args.average_out.is_some()),
+            ("--live", args.live),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+            anyhow::bail!("{} is not available with --precision f64", flag);
+        }
+    }
+    if args.lpc_order.is_some() && args.device == Device::Gpu {
+        anyhow::bail!("--lpc-order is not available with --device gpu");
+    }
+    if args.deterministic && args.device == Device::Gpu {
+        anyhow::bail!("--deterministic is not available with --device gpu");
+    }
+    Ok(())
+}
+
+/// Check the outputs written next to the spectrograms (tracks, events, tonal features, band
+/// levels, annotations, average and dataset) against the formats
+fn validate_side_outputs(args: &ComputeArgs, writes_png: bool) -> Result<()> {
+    if args.tile_width.is_some() && !writes_png {
+        anyhow::bail!("--tile-width only applies to images (--format png)");
+    }
+    if args.with_waveform && !writes_png {
+        anyhow::bail!("--with-waveform only applies to images (--format png)");
+    }
+    if matches!(args.pitch_out, Some(OutputFormat::Png | OutputFormat::Npy)) {
+        anyhow::bail!("--pitch-out writes CSV or JSON tracks (--pitch-out csv or json)");
+    }
+    if args.pitch_overlay && !writes_png {
+        anyhow::bail!("--pitch-overlay only applies to images (--format png)");
+    }
+    if args.pitch_overlay && args.tile_width.is_some() {
+        anyhow::bail!("--pitch-overlay is not available with --tile-width");
+    }
+    if matches!(
+        args.formants_out,
+        Some(OutputFormat::Png | OutputFormat::Npy)
+    ) {
+        anyhow::bail!("--formants-out writes CSV or JSON tracks (--formants-out csv or json)");
+    }
+    if args.formant_overlay && !writes_png {
+        anyhow::bail!("--formant-overlay only applies to images (--format png)");
+    }
+    if args.formant_overlay && args.tile_width.is_some() {
+        anyhow::bail!("--formant-overlay is not available with --tile-width");
+    }
+    if matches!(args.onsets_out, Some(OutputFormat::Png | OutputFormat::Npy)) {
+        anyhow::bail!("--onsets-out writes CSV or JSON event lists (--onsets-out csv or json)");
+    }
+    if args.onset_overlay && !writes_png {
+        anyhow::bail!("--onset-overlay only applies to images (--format png)");
+    }
+    if args.onset_overlay && args.tile_width.is_some() {
+        anyhow::bail!("--onset-overlay is not available with --tile-width");
+    }
+    for (flag, format) in [
+        ("--chroma-out", args.chroma_out),
+        ("--tonnetz-out", args.tonnetz_out),
+    ] {
+        if format == Some(OutputFormat::Png) {
+            anyhow::bail!(
+                "{} writes CSV, NPY or JSON matrices (e.g. {} npy)",
+                flag,
+                flag
+            );
+        }
+        // The pitch classes are read from the bins of the linear spectrogram
+        if format.is_some() && args.compat != Compat::Librosa {
+            anyhow::bail!("{} is only available with --compat librosa", flag);
+        }
+    }
+    if matches!(args.cpp_out, Some(OutputFormat::Png | OutputFormat::Npy)) {
+        anyhow::bail!("--cpp-out writes CSV or JSON tracks (--cpp-out csv or json)");
+    }
+    // The cepstrum is read from the bins of the linear spectrogram
+    if args.cpp_out.is_some() && args.compat != Compat::Librosa {
+        anyhow::bail!("--cpp-out is only available with --compat librosa");
+    }
+    if args.band_levels_out == Some(OutputFormat::Png) {
+        anyhow::bail!(
+            "--band-levels-out writes CSV, JSON or NPY levels (e.g. --band-levels-out csv)"
+        );
+    }
+    if args.annotations.is_some() && !writes_png {
+        anyhow::bail!("--annotations only applies to images (--format png)");
+    }
+    if args.annotations.is_some() && args.tile_width.is_some() {
+        anyhow::bail!("--annotations is not available with --tile-width");
+    }
+    // The flux is computed on the levels of linear or mel bands
+    if args.compat.is_filter_bank() && (args.onsets_out.is_some() || args.onset_overlay) {
+        anyhow::bail!(
+            "--onsets-out and --onset-overlay are not available with filter bank presets (log energies)"
+        );
+    }
+    if args.compat.is_filter_bank() && args.segments_out.is_some() {
+        anyhow::bail!("--segments-out is not available with filter bank presets (log energies)");
+    }
+    if !args.onset_delta.is_finite() {
+        anyhow::bail!("--onset-delta must be a finite number");
+    }
+    if let Some(average_path) = &args.average_out {
+        if !average_path
+            .extension()
+            .is_some_and(|ext| ext == "png" || ext == "npy")
+        {
+            anyhow::bail!(
+                "--average-out writes an image or an array (e.g. average.png or average.npy)"
+            );
+        }
+        // Averages of log energies, or of spectrograms with different rows, mean nothing
+        if args.compat.is_filter_bank() {
+            anyhow::bail!("--average-out is not available with filter bank presets (log energies)");
+        }
+        if args.auto_params {
+            anyhow::bail!(
+                "--average-out needs the same parameters for every input, not --auto-params"
+            );
+        }
+    }
+    // Writing HDF5 needs the system HDF5 library
+    if let Some(dataset_path) = &args.dataset_out
+        && dataset_path
+            .extension()
+            .is_some_and(|ext| ext == "h5" || ext == "hdf5")
+    {
+        anyhow::bail!(
+            "--dataset-out writes Parquet (e.g. features.parquet) or Arrow IPC (features.arrow) datasets, not HDF5"
+        );
+    }
+    Ok(())
+}
+
+/// Check the options of images (colour scale, display band, time reduction) and of
+/// numerical outputs
+fn validate_image_options(args: &ComputeArgs, options: &OutputOptions) -> Result<()> {
+    let writes_png = options.formats.contains(&OutputFormat::Png);
+    let scaled = args.vmin.is_some()
+        || args.vmax.is_some()
+        || args.percentile_clip.is_some()
+        || args.gamma != 1.0;
+    if (scaled || args.global_scale) && !writes_png {
+        anyhow::bail!(
+            "--vmin, --vmax, --percentile-clip, --gamma and --global-scale only apply to images (--format png)"
+        );
+    }
+    let display_band = args.display_fmin.is_some() || args.display_fmax.is_some();
+    if args.compress.is_some() && options.formats == [OutputFormat::Png] {
+        anyhow::bail!("--compress only applies to numerical output formats (e.g. --format npy)");
+    }
+    if args.spec_augment.is_some() && options.formats == [OutputFormat::Png] {
+        anyhow::bail!(
+            "--spec-augment only applies to numerical output formats (e.g. --format npy)"
+        );
+    }
+    if args.time_reduce.is_some() && !writes_png {
+        anyhow::bail!("--time-reduce only applies to images (--format png)");
+    }
+    if display_band && !writes_png {
+        anyhow::bail!("--display-fmin/--display-fmax only apply to images (--format png)");
+    }
+    if let (Some(f_min), Some(f_max)) = (args.display_fmin, args.display_fmax)
+        && f_min >= f_max
+    {
+        anyhow::bail!(
+            "--display-fmin ({} Hz) must be below --display-fmax ({} Hz)",
+            f_min,
+            f_max
+        );
+    }
+    options
+        .scale
+        .validate()
+        .with_context(|| "Invalid color scale")?;
+    Ok(())
+}
+
+/// Check the options against --live, which computes frames as the audio arrives
+fn validate_live(args: &ComputeArgs, options: &OutputOptions) -> Result<()> {
+    if !args.live {
+        return Ok(());
+    }
+    let display_band = args.display_fmin.is_some() || args.display_fmax.is_some();
+    let unsupported = [
+        ("--sr", args.sr.is_some()),
+        // Milliseconds are resolved against --sr or the header of each input
+        (
+            "--win-ms/--hop-ms",
+            args.win_ms.is_some() || args.hop_ms.is_some(),
+        ),
+        ("--start/--end", args.start.is_some() || args.end.is_some()),
+        ("--raw-pcm", args.raw_pcm.is_some()),
+        ("--downmix", args.downmix != DownmixMode::Average),
+        ("--lenient", args.lenient),
+        ("--dump-resampled", args.dump_resampled),
+        ("--augment", !args.augment.is_empty()),
+        ("--spec-augment", args.spec_augment.is_some()),
+        ("--sidecar", args.sidecar),
+        ("--stats-out", args.stats_out.is_some()),
+        ("--summary-png", args.summary_png.is_some()),
+        ("--tile-width", args.tile_width.is_some()),
+        ("--with-waveform", args.with_waveform),
+        (
+            "--pitch-out/--pitch-overlay",
+            args.pitch_out.is_some() || args.pitch_overlay,
+        ),
+        (
+            "--formants-out/--formant-overlay",
+            args.formants_out.is_some() || args.formant_overlay,
+        ),
+        (
+            "--onsets-out/--onset-overlay",
+            args.onsets_out.is_some() || args.onset_overlay,
+        ),
+        ("--segments-out", args.segments_out.is_some()),
+        ("--denoise", args.denoise),
+        ("--gate-db", args.gate_db.is_some()),
+        (
+            "--frame-alignment librosa",
+            args.frame_alignment != FrameAlignment::Spectrs,
+        ),
+        ("--global-scale", args.global_scale),
+        ("--display-fmin/--display-fmax", display_band),
+        ("--cache-dir", args.cache_dir.is_some()),
+        ("--time-reduce", args.time_reduce.is_some()),
+        ("--fixed-frames", args.fixed_frames.is_some()),
+        ("--dataset-out", args.dataset_out.is_some()),
+        ("--name-template", args.name_template.is_some()),
+        (
+            "--cmvn-out/--cmvn-in",
+            args.cmvn_out.is_some() || args.cmvn_in.is_some(),
+        ),
+        ("--compress", args.compress.is_some()),
+        ("--compat", args.compat != Compat::Librosa),
+        ("--device gpu", args.device == Device::Gpu),
+        ("--fft-backend", args.fft_backend != FftBackendType::RustFft),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+        anyhow::bail!("{} is not available with --live", flag);
+    }
+    if !matches!(
+        options.formats[..],
+        [OutputFormat::Png] | [OutputFormat::Npy]
+    ) {
+        anyhow::bail!("--live writes PNG snapshots or a growing NPY file (--format png or npy)");
+    }
+    Ok(())
+}
+
+/// Check --name-template and the variants of --sweep it has to tell apart
+fn validate_naming(args: &ComputeArgs) -> Result<()> {
+    if let Some(template) = &args.name_template {
+        render_name_template(template, |_| String::new())?;
+        // Outputs of the other formats and side outputs replace the extension
+        if !template.ends_with(".{ext}") {
+            anyhow::bail!(
+                "--name-template must end with \".{{ext}}\", got {:?}",
+                template
+            );
+        }
+    }
+    for (i, sweep) in args.sweep.iter().enumerate() {
+        if args.sweep[..i]
+            .iter()
+            .any(|other| other.param == sweep.param)
+        {
+            anyhow::bail!("--sweep varies {} more than once", sweep.param);
+        }
+        let variable = format!("{{{}}}", sweep.variable());
+        if !args
+            .name_template
+            .as_ref()
+            .is_some_and(|t| t.contains(&variable))
+        {
+            anyhow::bail!(
+                "--name-template must hold {} to tell apart the variants of --sweep {}",
+                variable,
+                sweep.param
+            );
+        }
+    }
+    if !args.sweep.is_empty() {
+        // Statistics of the run and analyses framed by the hop of the command line are those
+        // of a single set of parameters
+        let unsupported = [
+            ("--stats-out", args.stats_out.is_some()),
+            ("--average-out", args.average_out.is_some()),
+            (
+                "--cmvn-out/--cmvn-in",
+                args.cmvn_out.is_some() || args.cmvn_in.is_some(),
+            ),
+            ("--global-scale", args.global_scale),
+            (
+                "--pitch-out/--pitch-overlay",
+                args.pitch_out.is_some() || args.pitch_overlay,
+            ),
+            (
+                "--formants-out/--formant-overlay",
+                args.formants_out.is_some() || args.formant_overlay,
+            ),
+            ("--band-levels-out", args.band_levels_out.is_some()),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+            anyhow::bail!("{} is not available with --sweep", flag);
+        }
+    }
+    Ok(())
+}
+
+/// Check the options against stdin and stdout, and those only available for local files
+fn validate_stdio(args: &ComputeArgs, options: &OutputOptions) -> Result<()> {
+    // Empty with --live, required otherwise
+    let input_arg = args.input.clone().unwrap_or_default();
+    if input_arg == STDIO {
+        if args.output_dir.is_some()
+            || args.name_template.is_some()
+            || args.sidecar
+            || args.dump_resampled
+            || args.tile_width.is_some()
+            || args.with_waveform
+            || args.pitch_out.is_some()
+            || args.pitch_overlay
+            || args.formants_out.is_some()
+            || args.formant_overlay
+            || args.onsets_out.is_some()
+            || args.onset_overlay
+            || args.segments_out.is_some()
+            || args.chroma_out.is_some()
+            || args.tonnetz_out.is_some()
+            || args.band_levels_out.is_some()
+            || args.cpp_out.is_some()
+        {
+            anyhow::bail!(
+                "--output-dir, --name-template, --sidecar, --dump-resampled, --tile-width, --with-waveform, --pitch-out, --pitch-overlay, --formants-out, --formant-overlay, --onsets-out, --onset-overlay, --segments-out, --chroma-out, --tonnetz-out, --band-levels-out and --cpp-out cannot be used when writing to stdout"
+            );
+        }
+        if args.report_html.is_some() {
+            anyhow::bail!("--report-html is not available when writing to stdout");
+        }
+        // stdin cannot be read twice
+        if args.global_scale {
+            anyhow::bail!("--global-scale is not available when reading from stdin");
+        }
+        if options.formats.len() > 1 {
+            anyhow::bail!("Only one --format can be written to stdout");
+        }
+    } else if args.raw_pcm.is_some() && (is_url(&input_arg) || is_store_url(&input_arg)) {
+        anyhow::bail!("--raw-pcm is not available for URL inputs");
+    } else if args.raw_pcm.is_some() && is_archive_path(Path::new(&input_arg)) {
+        anyhow::bail!("--raw-pcm is not available for archive inputs");
+    }
+    Ok(())
+}
+
+/// Run every parallel iterator (files, frames, mel bands, resampling) in order (--deterministic)
+fn configure_single_thread() -> Result<()> {
+    // The global pool can only be built once, while commands such as compare-image
+    // prepare several inputs in the same process
+    static SINGLE_THREADED: OnceLock<std::result::Result<(), String>> = OnceLock::new();
+    SINGLE_THREADED
+        .get_or_init(|| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build_global()
+                .map_err(|e| e.to_string())
+        })
+        .clone()
+        .map_err(anyhow::Error::msg)
+        .with_context(|| "Failed to configure the thread pool")
+}
+
+/// Load the noise profile of --noise-wav, computed from the whole recording prepared like the
+/// inputs (without it, a profile is estimated from each input)
+fn load_noise_profile(
+    args: &ComputeArgs,
+    params: &SpectrogramParams,
+    durations: Option<FrameDurations>,
+    denoise: &mut Denoise,
+) -> Result<()> {
+    let Some(noise_wav) = &args.noise_wav else {
+        return Ok(());
+    };
+    let noise_options = AudioOptions {
+        start: None,
+        end: None,
+        dump_resampled: false,
+        raw_pcm: None,
+        waveform: false,
+        pitch: None,
+        chroma: false,
+        cpp: None,
+        bands: None,
+        gate: None,
+        gpu: None,
+        ..AudioOptions::from_cli(args)
+    };
+    let noise_source = single_source(noise_wav, None, args.downmix)?;
+    // Resolved like the inputs at the sample rate of the recording, the only one it applies to
+    let params = match &durations {
+        Some(durations) => durations.params_of(noise_source.as_ref(), params)?,
+        None => *params,
+    };
+    let (noise, noise_sr, _) = exported_spectrogram(
+        noise_source.as_ref(),
+        Path::new(noise_wav),
+        &noise_options,
+        &params,
+        !args.deterministic,
+        None,
+        false,
+    )
+    .with_context(|| format!("Failed to compute the noise profile of {}", noise_wav))?;
+    denoise.noise = Some((NoiseProfile::from_noise(&noise)?, noise_sr));
+    Ok(())
+}
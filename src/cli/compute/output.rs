@@ -0,0 +1,720 @@
+//! Outputs of an input: spectrogram files in every format, tiles, sidecars and side outputs
+//! (pitch, formants, onsets, tonal features, cepstral peaks, band levels)
+
+use super::analysis::name_hash;
+use super::batch::{FileSummary, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH, Thumbnail};
+use super::options::OutputOptions;
+use super::{STDIO, check_timed_out, interrupt};
+use anyhow::{Context, Result};
+use spectrs::features::activity::segment_activity;
+use spectrs::features::cepstrum::CepstralPeak;
+use spectrs::features::formant::{FormantTrack, TRACKED_FORMANTS};
+use spectrs::features::octave::BandLevels;
+use spectrs::features::onset::{Onset, PeakPick, detect_onsets};
+use spectrs::features::pitch::PitchTrack;
+use spectrs::features::tonal::tonnetz;
+use spectrs::io::annotations::{Annotation, save_annotations};
+use spectrs::io::export::{
+    NpyFloat, OutputFormat, save_band_levels_csv, save_band_levels_json, save_cpp_csv,
+    save_cpp_json, save_formants_csv, save_formants_json, save_onsets_csv, save_onsets_json,
+    save_pitch_csv, save_pitch_json, save_spectrogram_csv, save_spectrogram_json,
+    save_spectrogram_npy, write_spectrogram_csv, write_spectrogram_json, write_spectrogram_npy,
+};
+use spectrs::io::image::{
+    ImageScale, LabeledRegion, crop_frequency_band, encode_scaled_spectrogram_png, image_scale,
+    overlay_annotations, overlay_formant_tracks, overlay_onset_markers, overlay_pitch_contour,
+    save_scaled_spectrogram_image, save_spectrogram_tiles, save_waveform_image,
+};
+use spectrs::io::metadata::{
+    SpectrogramMetadata, TileIndex, metadata_path, tile_index_path, write_metadata,
+    write_tile_index,
+};
+use spectrs::io::source::AudioSource;
+use spectrs::spectrogram::average::AverageAccumulator;
+use spectrs::spectrogram::compression::par_compress_spectrogram_with_amin;
+use spectrs::spectrogram::fbank::FbankOptions;
+use spectrs::spectrogram::filterbank::band_frequencies;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pooling::{FitMode, fit_frames, pool_freq, pool_time, reduce_time};
+use spectrs::spectrogram::stft::Float;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Crop the rows of an image to the frequency band of --display-fmin/--display-fmax (None if
+/// no band is given)
+pub(super) fn crop_display_band(
+    spec: &[Vec<f32>],
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Result<Option<Vec<Vec<f32>>>> {
+    let (f_min, f_max) = options.display_band;
+    if f_min.is_none() && f_max.is_none() {
+        return Ok(None);
+    }
+    let frequencies = band_frequencies(params, sample_rate);
+    let cropped = crop_frequency_band(spec, &frequencies, f_min, f_max)
+        .with_context(|| "Failed to crop the spectrogram to --display-fmin/--display-fmax")?;
+    Ok(Some(cropped))
+}
+
+/// Values rendered in images: the spectrogram cropped to the display band and reduced in
+/// time (None if neither is requested)
+pub(super) fn image_values(
+    spec: &[Vec<f32>],
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Result<Option<Vec<Vec<f32>>>> {
+    let cropped = crop_display_band(spec, sample_rate, params, options)?;
+    let Some((mode, factor)) = options.time_reduce else {
+        return Ok(cropped);
+    };
+    let spec = cropped.as_deref().unwrap_or(spec);
+    Ok(Some(reduce_time(spec, factor, mode)))
+}
+
+/// Path of the output in `format`, next to the `output` of the primary format (stdout stays
+/// stdout)
+pub(super) fn format_path(output: &Path, format: OutputFormat) -> PathBuf {
+    if output == Path::new(STDIO) {
+        return output.to_path_buf();
+    }
+    output.with_extension(format.extension())
+}
+
+/// Write the spectrogram in `format` (to stdout if `output` is `-`), with the `waveform`
+/// panel beneath images if given, returning the shape of the stored values
+pub(super) fn save_output(
+    spec: &[Vec<f32>],
+    output: &Path,
+    format: OutputFormat,
+    options: &OutputOptions,
+    waveform: Option<&[f32]>,
+) -> Result<[usize; 2]> {
+    if format != OutputFormat::Png {
+        return save_values(spec, output, format, options);
+    }
+    check_timed_out(options.cancel.as_ref())?;
+
+    // Shrink images to the requested size (no-op if already pooled while streaming)
+    let pooled;
+    let spec = match (options.width, options.height) {
+        (None, None) => spec,
+        (width, height) => {
+            let time_pooled = pool_time(spec, width.unwrap_or(0));
+            pooled = pool_freq(&time_pooled, height.unwrap_or(0));
+            &pooled
+        }
+    };
+
+    if output == Path::new(STDIO) {
+        let png = encode_scaled_spectrogram_png(spec, options.colormap, &options.scale)?;
+        write_stdout(options, |writer| Ok(writer.write_all(&png)?))
+            .with_context(|| "Failed to write spectrogram to stdout")?;
+        return Ok(shape_of(spec));
+    }
+    match waveform {
+        Some(audio) => save_waveform_image(
+            spec,
+            audio,
+            output.to_path_buf(),
+            options.colormap,
+            &options.scale,
+        ),
+        None => save_scaled_spectrogram_image(
+            spec,
+            output.to_path_buf(),
+            options.colormap,
+            &options.scale,
+        ),
+    }
+    .with_context(|| "Failed to save spectogram")?;
+    Ok(shape_of(spec))
+}
+
+/// Write an output to stdout, or to the buffer of the options if they have one
+pub(super) fn write_stdout(
+    options: &OutputOptions,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    if let Some(buffer) = &options.buffer {
+        let mut buffer = buffer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output buffer poisoned by a failed write"))?;
+        return write(&mut *buffer);
+    }
+    let mut stdout = std::io::stdout().lock();
+    write(&mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Save the values in a numerical output format (CSV, NPY or JSON), in their own precision
+pub(super) fn save_values<T: NpyFloat + serde::Serialize>(
+    spec: &[Vec<T>],
+    output: &Path,
+    format: OutputFormat,
+    options: &OutputOptions,
+) -> Result<[usize; 2]> {
+    check_timed_out(options.cancel.as_ref())?;
+    if output == Path::new(STDIO) {
+        write_stdout(options, |writer| {
+            Ok(match format {
+                OutputFormat::Npy => write_spectrogram_npy(spec, writer),
+                OutputFormat::Json => write_spectrogram_json(spec, writer),
+                _ => write_spectrogram_csv(spec, writer, &options.csv),
+            }?)
+        })
+        .with_context(|| "Failed to write spectrogram to stdout")?;
+    } else {
+        match format {
+            OutputFormat::Npy => save_spectrogram_npy(spec, output),
+            OutputFormat::Json => save_spectrogram_json(spec, output),
+            _ => save_spectrogram_csv(spec, output, &options.csv),
+        }
+        .with_context(|| "Failed to save spectogram")?;
+    }
+    Ok(shape_of(spec))
+}
+
+/// Number of rows and columns of a [freq][time] spectrogram
+pub(super) fn shape_of<T>(spec: &[Vec<T>]) -> [usize; 2] {
+    [spec.len(), spec.first().map_or(0, |row| row.len())]
+}
+
+/// Write the versioned JSON sidecar describing an output
+pub(super) fn write_sidecar(
+    source: &dyn AudioSource,
+    output: &Path,
+    format: OutputFormat,
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    shape: [usize; 2],
+    image_scale: Option<ImageScale>,
+) -> Result<()> {
+    let mut metadata = SpectrogramMetadata::new(source.name(), format, sample_rate, *params, shape);
+    metadata.image = image_scale;
+    write_metadata(&metadata_path(output), &metadata).with_context(|| "Failed to save sidecar")
+}
+
+/// Compress and normalize (if requested) and save the spectrogram in every requested format
+/// (with the waveform panel and the pitch contour of the summary on the image, if kept, and the
+/// onsets detected before denoising marked over it), then write its sidecar, pitch track and
+/// onsets. Active and silent segments are also split before denoising. Images are rendered
+/// from the linear values, and so is the thumbnail of the HTML report, kept in the summary.
+/// With --dataset-out, the values are appended to the dataset instead.
+pub(super) fn write_outputs(
+    source: &dyn AudioSource,
+    output: &Path,
+    mut spec: Vec<Vec<f32>>,
+    summary: &mut FileSummary,
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    output_options: &OutputOptions,
+) -> Result<()> {
+    // Outputs are either written completely or not started once interrupted, and not written
+    // at all once timed out
+    interrupt().check()?;
+    check_timed_out(output_options.cancel.as_ref())?;
+    let onsets = output_options
+        .detects_onsets()
+        .then(|| spectrogram_onsets(&spec, sample_rate, params, output_options))
+        .transpose()?;
+    if let Some((format, activity)) = &output_options.segments_out {
+        let segments = segment_activity(
+            &spec,
+            params.spectrogram_type,
+            sample_rate as f32 / params.hop_length as f32,
+            params.first_frame_center() / sample_rate as f32,
+            summary.duration,
+            activity,
+        )
+        .with_context(|| "Failed to segment activity")?;
+        let annotations: Vec<Annotation> = segments.into_iter().map(Annotation::from).collect();
+        let path = output.with_extension(format!("segments.{}", format.extension()));
+        check_timed_out(output_options.cancel.as_ref())?;
+        save_annotations(&annotations, &path).with_context(|| "Failed to save segments")?;
+    }
+    if let Some(denoise) = &output_options.denoise {
+        denoise
+            .apply(&mut spec, sample_rate)
+            .with_context(|| "Failed to denoise spectrogram")?;
+    }
+    if let Some(width) = output_options.average_frames {
+        let mut average = AverageAccumulator::new(width);
+        average
+            .add(&spec)
+            .with_context(|| "Failed to average spectrogram")?;
+        summary.average = Some((sample_rate, average));
+    }
+    if let Some(fixed_frames) = output_options.fixed_frames {
+        // Presets export log energies
+        spec = fit_to_frames(spec, fixed_frames, !params.compat.is_filter_bank());
+    }
+    // Numerical outputs carry the compressed and masked values, images the linear ones
+    let writes_png = output_options.formats.contains(&OutputFormat::Png);
+    let renders = writes_png || output_options.thumbnails;
+    let transformed = params.compression.is_some() || output_options.spec_augment.is_some();
+    let mut exported = transformed.then(|| {
+        if renders {
+            spec.clone()
+        } else {
+            std::mem::take(&mut spec)
+        }
+    });
+    if let (Some(values), Some(compression)) = (exported.as_mut(), params.compression) {
+        par_compress_spectrogram_with_amin(
+            values,
+            compression,
+            params.spectrogram_type,
+            params.amin(),
+        );
+    }
+    if let Some(cmvn) = &output_options.cmvn {
+        cmvn.apply(exported.as_mut().unwrap_or(&mut spec))
+            .with_context(|| "Failed to apply CMVN statistics")?;
+    }
+    if let (Some(values), Some(spec_augment)) = (exported.as_mut(), output_options.spec_augment) {
+        let seed = output_options.augment_seed ^ name_hash(&source.name());
+        spec_augment.apply(values, seed);
+    }
+    let values = exported.as_deref().unwrap_or(&spec);
+
+    // Only images are cropped to the display band and reduced in time
+    let reduced = if renders {
+        image_values(&spec, sample_rate, params, output_options)?
+    } else {
+        None
+    };
+    let image = reduced.as_deref().unwrap_or(&spec);
+    if output_options.thumbnails {
+        let thumbnail = pool_freq(&pool_time(image, THUMBNAIL_WIDTH), THUMBNAIL_HEIGHT);
+        summary.thumbnail = Some(Thumbnail {
+            png: encode_scaled_spectrogram_png(
+                &thumbnail,
+                output_options.colormap,
+                &output_options.scale,
+            )
+            .with_context(|| "Failed to render the thumbnail")?,
+            sample_rate,
+            shape: shape_of(values),
+        });
+    }
+    if let Some(dataset) = &output_options.dataset {
+        check_timed_out(output_options.cancel.as_ref())?;
+        dataset.append(source, sample_rate, values)?;
+        if let Some(track) = &summary.pitch {
+            save_pitch(track, output, output_options)?;
+        }
+        if let Some(track) = &summary.formants {
+            save_formants(track, output, output_options)?;
+        }
+        if let Some(levels) = &summary.bands {
+            save_band_levels(levels, output, output_options)?;
+        }
+        if let Some(onsets) = &onsets {
+            save_onsets(onsets, output, output_options)?;
+        }
+        if let Some(chroma) = &summary.chroma {
+            save_tonal(chroma, output, output_options)?;
+        }
+        if let Some(peaks) = &summary.cpp {
+            save_cpp(peaks, output, sample_rate, params, output_options)?;
+        }
+        return Ok(());
+    }
+
+    let waveform = summary.waveform.as_deref();
+
+    let mut shapes = Vec::with_capacity(output_options.formats.len());
+    for &format in &output_options.formats {
+        let path = format_path(output, format);
+        let shape = match (format, output_options.tile_width) {
+            (OutputFormat::Png, Some(tile_width)) => save_tiles(
+                source,
+                image,
+                &path,
+                sample_rate,
+                params,
+                tile_width,
+                output_options,
+            )?,
+            (OutputFormat::Png, None) => {
+                save_output(image, &path, format, output_options, waveform)?
+            }
+            _ => save_output(values, &path, format, output_options, waveform)?,
+        };
+        if format == OutputFormat::Png
+            && output_options.pitch_overlay
+            && let Some(track) = &summary.pitch
+        {
+            let positions = contour_positions(&track.f0, sample_rate, params, output_options);
+            overlay_pitch_contour(&path, &positions, shape[0] as u32)
+                .with_context(|| "Failed to draw the pitch contour")?;
+        }
+        if format == OutputFormat::Png
+            && output_options.formant_overlay
+            && let Some(track) = &summary.formants
+        {
+            let tracks: Vec<Vec<Option<f32>>> = (0..TRACKED_FORMANTS)
+                .map(|i| {
+                    contour_positions(&track.frequencies(i), sample_rate, params, output_options)
+                })
+                .collect();
+            overlay_formant_tracks(&path, &tracks, shape[0] as u32)
+                .with_context(|| "Failed to draw the formant tracks")?;
+        }
+        if format == OutputFormat::Png
+            && output_options.onset_overlay
+            && let Some(onsets) = &onsets
+        {
+            let frames: Vec<usize> = onsets.iter().map(|onset| onset.frame).collect();
+            overlay_onset_markers(&path, &frames, shape_of(&spec)[1], shape[0] as u32)
+                .with_context(|| "Failed to mark the onsets")?;
+        }
+        if format == OutputFormat::Png
+            && let Some(annotations) = &output_options.annotations
+        {
+            let regions = annotation_regions(
+                &annotations.of(source)?,
+                summary.duration,
+                sample_rate,
+                params,
+                output_options,
+            );
+            overlay_annotations(&path, &regions, shape[0] as u32)
+                .with_context(|| "Failed to draw the annotations")?;
+        }
+        shapes.push(shape);
+    }
+    if let Some(track) = &summary.pitch {
+        save_pitch(track, output, output_options)?;
+    }
+    if let Some(track) = &summary.formants {
+        save_formants(track, output, output_options)?;
+    }
+    if let Some(levels) = &summary.bands {
+        save_band_levels(levels, output, output_options)?;
+    }
+    if let Some(onsets) = &onsets {
+        save_onsets(onsets, output, output_options)?;
+    }
+    if let Some(chroma) = &summary.chroma {
+        save_tonal(chroma, output, output_options)?;
+    }
+    if let Some(peaks) = &summary.cpp {
+        save_cpp(peaks, output, sample_rate, params, output_options)?;
+    }
+    if output_options.sidecar {
+        // Images can be read back into values with the scale they were rendered with
+        let image_scale = match output_options.primary_format() {
+            OutputFormat::Png => Some(rendered_image_scale(image, output_options)?),
+            _ => None,
+        };
+        check_timed_out(output_options.cancel.as_ref())?;
+        write_sidecar(
+            source,
+            output,
+            output_options.primary_format(),
+            sample_rate,
+            params,
+            shapes[0],
+            image_scale,
+        )?;
+    }
+    Ok(())
+}
+
+/// Height of a frequency contour (pitch or formant, one value per frame) along the frequency
+/// axis of images (0 at the lowest row, 1 at the highest), interpolated between the frequencies
+/// of the rows shown in the display band. None where missing or outside the rows shown.
+pub(super) fn contour_positions(
+    contour: &[Option<f32>],
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Vec<Option<f32>> {
+    let frequencies = displayed_frequencies(sample_rate, params, options);
+    let last = frequencies.len().saturating_sub(1) as f32;
+    contour
+        .iter()
+        .map(|frequency| {
+            let frequency = (*frequency)?;
+            let row = frequencies
+                .windows(2)
+                .position(|pair| pair[1] >= frequency)?;
+            let (low, high) = (frequencies[row], frequencies[row + 1]);
+            if frequency < low {
+                return None;
+            }
+            Some((row as f32 + (frequency - low) / (high - low)) / last)
+        })
+        .collect()
+}
+
+/// Frequencies of the rows of images, from the bottom one: the bands within the display band
+pub(super) fn displayed_frequencies(
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Vec<f32> {
+    let (f_min, f_max) = options.display_band;
+    let band = f_min.unwrap_or(f32::NEG_INFINITY)..=f_max.unwrap_or(f32::INFINITY);
+    band_frequencies(params, sample_rate)
+        .into_iter()
+        .filter(|frequency| band.contains(frequency))
+        .collect()
+}
+
+/// Regions of labels on the image of a source: their intervals (relative to the processed
+/// segment) along the `duration` of the processed audio, and their frequency ranges along the
+/// rows of the image, clamped to the frequencies shown
+pub(super) fn annotation_regions(
+    annotations: &[Annotation],
+    duration: f32,
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Vec<LabeledRegion> {
+    if duration <= 0.0 {
+        return Vec::new();
+    }
+    let frequencies = displayed_frequencies(sample_rate, params, options);
+    let last = frequencies.len().saturating_sub(1).max(1) as f32;
+    let height = |frequency: f32| match frequencies.windows(2).position(|pair| pair[1] >= frequency)
+    {
+        Some(row) => {
+            let (low, high) = (frequencies[row], frequencies[row + 1]);
+            (row as f32 + ((frequency - low) / (high - low)).clamp(0.0, 1.0)) / last
+        }
+        None => 1.0,
+    };
+    let time = |seconds: f32| (seconds - options.time_offset) / duration;
+    annotations
+        .iter()
+        .map(|annotation| LabeledRegion {
+            time: (time(annotation.start), time(annotation.end)),
+            frequency: (
+                annotation.f_min.map_or(0.0, height),
+                annotation.f_max.map_or(1.0, height),
+            ),
+            label: annotation.label.clone(),
+        })
+        .collect()
+}
+
+/// Write the pitch track of a source next to its output, in the format of --pitch-out
+pub(super) fn save_pitch(track: &PitchTrack, output: &Path, options: &OutputOptions) -> Result<()> {
+    let Some(format) = options.pitch_out else {
+        return Ok(());
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let path = output.with_extension(format!("f0.{}", format.extension()));
+    match format {
+        OutputFormat::Json => save_pitch_json(track, &path),
+        _ => save_pitch_csv(track, &path, &options.csv),
+    }
+    .with_context(|| "Failed to save pitch track")
+}
+
+/// Write the formant tracks of a source next to its output, in the format of --formants-out
+pub(super) fn save_formants(
+    track: &FormantTrack,
+    output: &Path,
+    options: &OutputOptions,
+) -> Result<()> {
+    let Some(format) = options.formants_out else {
+        return Ok(());
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let path = output.with_extension(format!("formants.{}", format.extension()));
+    match format {
+        OutputFormat::Json => save_formants_json(track, &path),
+        _ => save_formants_csv(track, &path, &options.csv),
+    }
+    .with_context(|| "Failed to save formant tracks")
+}
+
+/// Write the chroma of a source and its tonal centroids next to its output, in the formats of
+/// --chroma-out and --tonnetz-out
+pub(super) fn save_tonal(
+    chroma: &[Vec<f32>],
+    output: &Path,
+    options: &OutputOptions,
+) -> Result<()> {
+    if let Some(format) = options.chroma_out {
+        let path = output.with_extension(format!("chroma.{}", format.extension()));
+        save_values(chroma, &path, format, options).with_context(|| "Failed to save chroma")?;
+    }
+    if let Some(format) = options.tonnetz_out {
+        let path = output.with_extension(format!("tonnetz.{}", format.extension()));
+        save_values(&tonnetz(chroma), &path, format, options)
+            .with_context(|| "Failed to save tonal centroids")?;
+    }
+    Ok(())
+}
+
+/// Write the cepstral peaks of a source next to its output, in the format of --cpp-out, each
+/// at the centre of its frame
+pub(super) fn save_cpp(
+    peaks: &[CepstralPeak],
+    output: &Path,
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Result<()> {
+    let Some(format) = options.cpp_out else {
+        return Ok(());
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let times: Vec<f32> = (0..peaks.len())
+        .map(|t| {
+            (params.first_frame_center() + (t * params.hop_length) as f32) / sample_rate as f32
+        })
+        .collect();
+    let path = output.with_extension(format!("cpp.{}", format.extension()));
+    match format {
+        OutputFormat::Json => save_cpp_json(&times, peaks, &path),
+        _ => save_cpp_csv(&times, peaks, &path, &options.csv),
+    }
+    .with_context(|| "Failed to save cepstral peaks")
+}
+
+/// Write the band levels of a source next to its output, in the format of --band-levels-out
+pub(super) fn save_band_levels(
+    levels: &BandLevels,
+    output: &Path,
+    options: &OutputOptions,
+) -> Result<()> {
+    let Some(format) = options.band_levels_out else {
+        return Ok(());
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let path = output.with_extension(format!("bands.{}", format.extension()));
+    match format {
+        OutputFormat::Json => save_band_levels_json(levels, &path),
+        OutputFormat::Npy => save_spectrogram_npy(&levels.levels_db(), &path),
+        _ => save_band_levels_csv(levels, &path, &options.csv),
+    }
+    .with_context(|| "Failed to save band levels")
+}
+
+/// Bring a spectrogram to a fixed number of frames (--fixed-frames). Linear values are padded
+/// with silence, log values with their lowest level.
+pub(super) fn fit_to_frames<T: Float>(
+    spec: Vec<Vec<T>>,
+    (n_frames, mode): (usize, FitMode),
+    linear: bool,
+) -> Vec<Vec<T>> {
+    let pad = if linear {
+        T::zero()
+    } else {
+        let lowest = spec.iter().flatten().copied().fold(T::infinity(), T::min);
+        if lowest.is_finite() {
+            lowest
+        } else {
+            T::zero()
+        }
+    };
+    fit_frames(&spec, n_frames, mode, pad)
+}
+
+/// Onsets of a spectrogram computed at `sample_rate`, with frames timed at the center of their
+/// window
+pub(super) fn spectrogram_onsets(
+    spec: &[Vec<f32>],
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    options: &OutputOptions,
+) -> Result<Vec<Onset>> {
+    let frame_rate = sample_rate as f32 / params.hop_length as f32;
+    let peak_pick = PeakPick {
+        delta: options.onset_delta,
+        ..PeakPick::for_frame_rate(frame_rate)
+    };
+    let time_offset = params.first_frame_center() / sample_rate as f32;
+    detect_onsets(
+        spec,
+        params.spectrogram_type,
+        frame_rate,
+        time_offset,
+        &peak_pick,
+    )
+    .with_context(|| "Failed to detect onsets")
+}
+
+/// Write the onsets of a source next to its output, in the format of --onsets-out
+pub(super) fn save_onsets(onsets: &[Onset], output: &Path, options: &OutputOptions) -> Result<()> {
+    let Some(format) = options.onsets_out else {
+        return Ok(());
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let path = output.with_extension(format!("onsets.{}", format.extension()));
+    match format {
+        OutputFormat::Json => save_onsets_json(onsets, &path),
+        _ => save_onsets_csv(onsets, &path, &options.csv),
+    }
+    .with_context(|| "Failed to save onsets")
+}
+
+/// Mapping of the values of an image to its colours, computed on the values as they are
+/// rendered (shrunk to --width/--height, except tiles which are only shrunk in frequency)
+pub(super) fn rendered_image_scale(
+    image: &[Vec<f32>],
+    options: &OutputOptions,
+) -> Result<ImageScale> {
+    let width = match options.tile_width {
+        Some(_) => None,
+        None => options.width,
+    };
+    let pooled;
+    let image = match (width, options.height) {
+        (None, None) => image,
+        (width, height) => {
+            pooled = pool_freq(&pool_time(image, width.unwrap_or(0)), height.unwrap_or(0));
+            &pooled
+        }
+    };
+    image_scale(image, options.colormap, &options.scale)
+        .with_context(|| "Failed to compute the colour scale of the image")
+}
+
+/// Save the spectrogram image as tiles of `tile_width` frames with their index (--tile-width),
+/// returning the shape of the whole image
+pub(super) fn save_tiles(
+    source: &dyn AudioSource,
+    spec: &[Vec<f32>],
+    output: &Path,
+    sample_rate: u32,
+    params: &SpectrogramParams,
+    tile_width: usize,
+    options: &OutputOptions,
+) -> Result<[usize; 2]> {
+    let pooled;
+    let spec = match options.height {
+        Some(height) => {
+            pooled = pool_freq(spec, height);
+            &pooled
+        }
+        None => spec,
+    };
+    check_timed_out(options.cancel.as_ref())?;
+    let tiles = save_spectrogram_tiles(spec, output, tile_width, options.colormap, &options.scale)
+        .with_context(|| "Failed to save spectrogram tiles")?;
+
+    // Presets have their own frame shift, and every column of a reduced image spans several
+    let frame_shift = FbankOptions::from_params(params, sample_rate)
+        .map_or(params.hop_length, |fbank| fbank.frame_shift)
+        * options.time_reduce.map_or(1, |(_, factor)| factor);
+    let index = TileIndex::new(
+        source.name(),
+        sample_rate,
+        frame_shift,
+        options.time_offset as f64,
+        &tiles,
+    );
+    write_tile_index(&tile_index_path(output), &index)
+        .with_context(|| "Failed to save tile index")?;
+    Ok(shape_of(spec))
+}
@@ -0,0 +1,324 @@
+//! Input sources: single inputs, directory walks, archives and object store listings, stdin
+
+use super::STDIO;
+use super::args::{SymlinkPolicy, WalkArgs};
+use super::batch::DIRECTORY_PARAMS;
+#[cfg(not(all(feature = "archive", feature = "http", feature = "object-store")))]
+use crate::cli::InvalidConfig;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use spectrs::io::audio::{DownmixMode, RawPcm, read_raw_pcm_from_reader};
+use spectrs::io::metadata::ReportedInput;
+use spectrs::io::source::{
+    AudioSource, BufferSource, FileSource, RawPcmSource, SamplesSource, is_store_url, is_url,
+};
+use std::io::Read;
+use std::path::Path;
+#[cfg(any(feature = "archive", feature = "object-store"))]
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// Whether an input is processed as a single source rather than walked as a directory
+pub(crate) fn is_single_input(input: &str, raw_pcm: Option<RawPcm>) -> bool {
+    let path = Path::new(input);
+    is_url(input)
+        || (is_store_url(input) && path.extension().and_then(|ext| ext.to_str()) == Some("wav"))
+        || input == STDIO
+        || (path.is_file()
+            && (raw_pcm.is_some() || path.extension().and_then(|ext| ext.to_str()) == Some("wav")))
+}
+
+/// Compile globs matched against paths relative to the input directory
+pub(super) fn glob_set(globs: &[String], flag: &str) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid {} glob '{}'", flag, glob))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Files of an input directory, split into the audio sources to process and the files left out
+pub(crate) struct DirectoryListing {
+    pub sources: Vec<Box<dyn AudioSource>>,
+    /// Files left out, with why
+    pub skipped: Vec<ReportedInput>,
+}
+
+/// Audio sources of the files of a directory: WAV files, or .raw and .pcm files when decoding
+/// headerless PCM (or the files matching the --include globs), minus the --exclude globs.
+/// Files are listed in the order of their paths, whatever the file system.
+pub(crate) fn directory_sources(
+    input: &Path,
+    raw_pcm: Option<RawPcm>,
+    downmix: DownmixMode,
+    walk: &WalkArgs,
+) -> Result<DirectoryListing> {
+    let extensions: &[&str] = match raw_pcm {
+        Some(_) => &["raw", "pcm"],
+        None => &["wav"],
+    };
+    let include = glob_set(&walk.include, "--include")?;
+    let exclude = glob_set(&walk.exclude, "--exclude")?;
+    let relative = |path: &Path| path.strip_prefix(input).unwrap_or(path).to_path_buf();
+
+    let mut walker = WalkDir::new(input)
+        .sort_by_file_name()
+        .follow_links(walk.symlinks == SymlinkPolicy::Follow);
+    if let Some(max_depth) = walk.max_depth {
+        walker = walker.max_depth(max_depth as usize);
+    }
+    let mut sources: Vec<Box<dyn AudioSource>> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skip = |path: &Path, reason: &str| {
+        skipped.push(ReportedInput {
+            input: path.display().to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    let mut entries = walker.into_iter();
+    while let Some(entry) = entries.next() {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        if entry.depth() > 0 && exclude.is_match(relative(path)) {
+            // Excluded directories are not walked at all
+            if entry.file_type().is_dir() {
+                entries.skip_current_dir();
+            }
+            skip(path, "matches --exclude");
+            continue;
+        }
+        if walk.symlinks == SymlinkPolicy::Skip && entry.path_is_symlink() {
+            skip(path, "symbolic link");
+            continue;
+        }
+        if entry.file_type().is_dir() || !path.is_file() {
+            continue;
+        }
+        // Parameters of the directory, not an input
+        if entry.file_name() == DIRECTORY_PARAMS {
+            continue;
+        }
+        if walk.include.is_empty() {
+            let is_audio = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext));
+            if !is_audio {
+                skip(path, "not an audio file");
+                continue;
+            }
+        } else if !include.is_match(relative(path)) {
+            skip(path, "does not match --include");
+            continue;
+        }
+        sources.push(match raw_pcm {
+            Some(layout) => {
+                Box::new(RawPcmSource::with_base(path, input, layout).with_downmix(downmix))
+            }
+            None => Box::new(FileSource::with_base(path, input).with_downmix(downmix)),
+        });
+    }
+    Ok(DirectoryListing { sources, skipped })
+}
+
+/// Files of a listing without directories (objects of a store, members of an archive) matched
+/// against the walk options like those of a directory (see `directory_sources`)
+#[cfg(any(feature = "object-store", feature = "archive"))]
+pub(super) struct FlatListing {
+    pub(super) include: GlobSet,
+    pub(super) exclude: GlobSet,
+    pub(super) include_all: bool,
+    pub(super) max_depth: Option<u32>,
+}
+
+#[cfg(any(feature = "object-store", feature = "archive"))]
+impl FlatListing {
+    pub(super) fn new(walk: &WalkArgs) -> Result<Self> {
+        Ok(Self {
+            include: glob_set(&walk.include, "--include")?,
+            exclude: glob_set(&walk.exclude, "--exclude")?,
+            include_all: walk.include.is_empty(),
+            max_depth: walk.max_depth,
+        })
+    }
+
+    /// Whether a file is within --max-depth
+    pub(super) fn within_depth(&self, relative: &Path) -> bool {
+        self.max_depth
+            .is_none_or(|depth| relative.components().count() <= depth as usize)
+    }
+
+    /// Why a file is left out, None if it is an input
+    pub(super) fn reason(&self, relative: &Path) -> Option<&'static str> {
+        // There are no directories to exclude: those of the paths are matched instead
+        let excluded = relative
+            .ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+            .any(|path| self.exclude.is_match(path));
+        if excluded {
+            Some("matches --exclude")
+        } else if self.include_all {
+            let is_audio = relative.extension().and_then(|ext| ext.to_str()) == Some("wav");
+            (!is_audio).then_some("not an audio file")
+        } else {
+            (!self.include.is_match(relative)).then_some("does not match --include")
+        }
+    }
+}
+
+/// Audio sources of the objects under a prefix of an object store, left out like the files of
+/// a directory (see `directory_sources`)
+#[cfg(feature = "object-store")]
+pub(crate) fn store_sources(
+    url: &str,
+    downmix: DownmixMode,
+    walk: &WalkArgs,
+) -> Result<DirectoryListing> {
+    use spectrs::io::store::{ObjectSource, StoreLocation};
+
+    let listing = FlatListing::new(walk)?;
+    let location = Arc::new(StoreLocation::parse(url)?);
+    let mut sources: Vec<Box<dyn AudioSource>> = Vec::new();
+    let mut skipped = Vec::new();
+    for relative in location.list()? {
+        if !listing.within_depth(&relative) {
+            continue;
+        }
+        match listing.reason(&relative) {
+            Some(reason) => skipped.push(ReportedInput {
+                input: location.url_of(&relative),
+                reason: reason.to_string(),
+            }),
+            None => sources.push(Box::new(
+                ObjectSource::new(location.clone(), relative).with_downmix(downmix),
+            )),
+        }
+    }
+    Ok(DirectoryListing { sources, skipped })
+}
+
+/// Audio sources of the WAV files of an archive, left out like the files of a directory (see
+/// `directory_sources`)
+#[cfg(feature = "archive")]
+pub(crate) fn archive_sources(
+    path: &Path,
+    downmix: DownmixMode,
+    walk: &WalkArgs,
+) -> Result<DirectoryListing> {
+    use spectrs::io::archive::{Archive, ArchiveSource};
+
+    let listing = FlatListing::new(walk)?;
+    let archive = Arc::new(Archive::open(path)?);
+    let mut sources: Vec<Box<dyn AudioSource>> = Vec::new();
+    let mut skipped = Vec::new();
+    for member in archive.members() {
+        if !listing.within_depth(&member.path) {
+            continue;
+        }
+        match listing.reason(&member.path) {
+            Some(reason) => skipped.push(ReportedInput {
+                input: format!("{}/{}", path.display(), member.path.display()),
+                reason: reason.to_string(),
+            }),
+            None => sources.push(Box::new(
+                ArchiveSource::new(archive.clone(), member.clone()).with_downmix(downmix),
+            )),
+        }
+    }
+    Ok(DirectoryListing { sources, skipped })
+}
+
+#[cfg(not(feature = "archive"))]
+pub(crate) fn archive_sources(
+    _path: &Path,
+    _downmix: DownmixMode,
+    _walk: &WalkArgs,
+) -> Result<DirectoryListing> {
+    Err(
+        anyhow::anyhow!("Archive inputs require spectrs to be compiled with the `archive` feature")
+            .context(InvalidConfig),
+    )
+}
+
+#[cfg(not(feature = "object-store"))]
+pub(crate) fn store_sources(
+    _url: &str,
+    _downmix: DownmixMode,
+    _walk: &WalkArgs,
+) -> Result<DirectoryListing> {
+    Err(anyhow::anyhow!(
+        "Object store inputs require spectrs to be compiled with the `object-store` feature"
+    )
+    .context(InvalidConfig))
+}
+
+/// Build the audio source for a single (non-directory) input
+pub(crate) fn single_source(
+    input: &str,
+    raw_pcm: Option<RawPcm>,
+    downmix: DownmixMode,
+) -> Result<Box<dyn AudioSource>> {
+    if input == STDIO {
+        return stdin_source(raw_pcm, downmix);
+    }
+    if is_url(input) {
+        #[cfg(feature = "http")]
+        return Ok(Box::new(
+            spectrs::io::source::HttpSource::new(input).with_downmix(downmix),
+        ));
+        #[cfg(not(feature = "http"))]
+        return Err(anyhow::anyhow!(
+            "URL inputs require spectrs to be compiled with the `http` feature"
+        )
+        .context(InvalidConfig));
+    }
+    if is_store_url(input) {
+        #[cfg(feature = "object-store")]
+        return Ok(Box::new(
+            spectrs::io::store::ObjectSource::parse(input)?.with_downmix(downmix),
+        ));
+        #[cfg(not(feature = "object-store"))]
+        return Err(anyhow::anyhow!(
+            "Object store inputs require spectrs to be compiled with the `object-store` feature"
+        )
+        .context(InvalidConfig));
+    }
+    Ok(match raw_pcm {
+        Some(layout) => Box::new(RawPcmSource::new(input, layout).with_downmix(downmix)),
+        None => Box::new(FileSource::new(input).with_downmix(downmix)),
+    })
+}
+
+/// Read the whole of stdin, as WAV or as headerless PCM
+pub(super) fn stdin_source(
+    raw_pcm: Option<RawPcm>,
+    downmix: DownmixMode,
+) -> Result<Box<dyn AudioSource>> {
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .with_context(|| "Failed to read stdin")?;
+    buffer_source(bytes, raw_pcm, downmix)
+}
+
+/// Source standing for stdin from audio already in memory, as WAV or as headerless PCM
+pub(super) fn buffer_source(
+    bytes: Vec<u8>,
+    raw_pcm: Option<RawPcm>,
+    downmix: DownmixMode,
+) -> Result<Box<dyn AudioSource>> {
+    Ok(match raw_pcm {
+        Some(raw) => {
+            let samples =
+                read_raw_pcm_from_reader(bytes.as_slice(), raw.format, raw.channels, downmix)
+                    .with_context(|| "Failed to read raw PCM")?;
+            Box::new(SamplesSource::new(STDIO, samples, raw.sample_rate))
+        }
+        None => Box::new(BufferSource::new(STDIO, bytes).with_downmix(downmix)),
+    })
+}
@@ -0,0 +1,99 @@
+//! Uploads of the outputs written to a temporary directory to an object store (--output s3://...)
+
+use super::batch::{BatchItem, FileSummary};
+#[cfg(not(feature = "object-store"))]
+use crate::cli::InvalidConfig;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Output directory in an object store (--output-dir s3://...): outputs are written to a local
+/// staging directory, then uploaded and deleted as soon as their input is done
+pub(super) struct Upload {
+    #[cfg(feature = "object-store")]
+    pub(super) location: spectrs::io::store::StoreLocation,
+    /// URL of the output directory
+    pub(super) url: String,
+    pub(super) staging: PathBuf,
+}
+
+impl Upload {
+    #[cfg(feature = "object-store")]
+    pub(super) fn new(url: &str) -> Result<Self> {
+        let location = spectrs::io::store::StoreLocation::parse(url)?;
+        Ok(Self {
+            url: location.url().to_string(),
+            location,
+            staging: std::env::temp_dir().join(format!("spectrs-upload-{}", std::process::id())),
+        })
+    }
+
+    #[cfg(not(feature = "object-store"))]
+    pub(super) fn new(_url: &str) -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "Object store outputs require spectrs to be compiled with the `object-store` feature"
+        )
+        .context(InvalidConfig))
+    }
+
+    /// Local stand-in for the output directory, whose files are uploaded at the end of the run
+    pub(super) fn run_dir(&self) -> PathBuf {
+        self.staging.join("run")
+    }
+
+    /// Write the outputs of a batch item to a staging directory of its own
+    pub(super) fn stage(&self, item: &mut BatchItem, index: usize) {
+        let staging = self.staging.join("items").join(index.to_string());
+        let run_dir = self.run_dir();
+        for output in item.outputs_mut() {
+            if let Ok(relative) = output.strip_prefix(&run_dir) {
+                *output = staging.join(relative);
+            }
+        }
+        item.staging = Some(staging);
+    }
+
+    /// Upload the files of a staging directory, then delete them
+    pub(super) fn upload(&self, dir: &Path) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        #[cfg(feature = "object-store")]
+        self.location
+            .upload_dir(dir)
+            .with_context(|| format!("Failed to upload the outputs to {}", self.url))?;
+        std::fs::remove_dir_all(dir).with_context(|| format!("Failed to delete {}", dir.display()))
+    }
+
+    /// Upload the outputs of an input that succeeded, and delete them either way
+    pub(super) fn finish_item(
+        &self,
+        dir: &Path,
+        outcome: Result<FileSummary>,
+    ) -> Result<FileSummary> {
+        match outcome {
+            Ok(summary) => self.upload(dir).map(|_| summary),
+            Err(e) => {
+                std::fs::remove_dir_all(dir).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// URL an output written in a staging directory is uploaded to
+    pub(super) fn remote(&self, local: &str, dir: &Path) -> String {
+        let relative = Path::new(local)
+            .strip_prefix(dir)
+            .unwrap_or(Path::new(local));
+        let parts: Vec<_> = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect();
+        format!("{}/{}", self.url, parts.join("/"))
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.staging).ok();
+    }
+}
@@ -0,0 +1,178 @@
+//! Subcommands of the `spectrs` binary. Each module holds the arguments and the `run` function
+//! of one subcommand.
+
+pub mod compare;
+pub mod compare_image;
+pub mod compute;
+pub mod reconstruct;
+pub mod serve;
+pub mod stats;
+pub mod view;
+pub mod watch;
+
+use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+#[command(
+    after_help = "Without a subcommand, `compute` is assumed: `spectrs audio.wav` is \
+                        `spectrs compute audio.wav`."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Most verbose level of the messages written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogLevel::Info)]
+    pub log_level: LogLevel,
+
+    /// Write the messages to stderr as JSON objects, one per line (e.g. for log collectors)
+    #[arg(long, global = true)]
+    pub log_json: bool,
+}
+
+/// Verbosity of the messages written to stderr
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    /// Also a message per processed input
+    Debug,
+    Trace,
+}
+
+/// Send the `tracing` events of the run to stderr, as text or as JSON Lines
+pub fn init_logging(level: LogLevel, json: bool) {
+    use std::io::IsTerminal;
+    use tracing_subscriber::filter::LevelFilter;
+
+    let level = match level {
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        // Reporting that stderr is closed would panic (e.g. in the requests of spectrs serve)
+        .log_internal_errors(false);
+    if json {
+        builder.json().init();
+    } else {
+        builder
+            .without_time()
+            .with_target(false)
+            .with_ansi(std::io::stderr().is_terminal())
+            .init();
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    // Boxed: the compute arguments outweigh all the others
+    Compute(Box<compute::ComputeArgs>),
+    Stats(stats::StatsArgs),
+    View(view::ViewArgs),
+    Reconstruct(reconstruct::ReconstructArgs),
+    Compare(compare::CompareArgs),
+    CompareImage(compare_image::CompareImageArgs),
+    Watch(watch::WatchArgs),
+    Serve(serve::ServeArgs),
+}
+
+/// Insert `compute` in the arguments of the bare invocation (`spectrs audio.wav --n-mels 128`,
+/// which predates subcommands), so it keeps working
+pub fn with_default_subcommand(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    use clap::CommandFactory;
+
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    let Some(first) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return args;
+    };
+    let command = Cli::command();
+    let is_subcommand = first == "help" || command.find_subcommand(first).is_some();
+    let is_top_level_flag = ["-h", "--help", "-V", "--version"].contains(&first);
+    if !is_subcommand && !is_top_level_flag {
+        args.insert(1, OsString::from("compute"));
+    }
+    args
+}
+
+/// Exit code of a run where some inputs failed to be processed (the others were)
+pub const EXIT_FAILED_INPUTS: u8 = 2;
+/// Exit code of a run refused for its configuration (invalid or conflicting options,
+/// parameters or inputs), before anything was processed
+pub const EXIT_CONFIG: u8 = 3;
+/// Exit code of any other error
+pub const EXIT_ERROR: u8 = 1;
+/// Exit code of a run stopped by Ctrl-C (128 + SIGINT, as shells report it)
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Marks an error as the failure of inputs (`EXIT_FAILED_INPUTS`), keeping its message
+#[derive(Debug)]
+pub struct InputsFailed(pub String);
+
+impl std::fmt::Display for InputsFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InputsFailed {}
+
+/// Marks an error as the interruption of the run by Ctrl-C (`EXIT_INTERRUPTED`), keeping its
+/// message
+#[derive(Debug)]
+pub struct Interrupted(pub String);
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Marks an error as a configuration error (`EXIT_CONFIG`)
+#[derive(Debug)]
+pub struct InvalidConfig;
+
+impl std::fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Invalid configuration")
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+/// Exit code of the binary for an error, for wrapper scripts and schedulers to branch on:
+/// interruption, failed inputs, configuration errors (including invalid spectrogram parameters
+/// and arguments rejected by the parser) or anything else
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    let interrupted = error.downcast_ref::<Interrupted>().is_some()
+        || error.chain().any(|cause| {
+            matches!(
+                cause.downcast_ref::<spectrs::error::SpectrsError>(),
+                Some(spectrs::error::SpectrsError::Cancelled(_))
+            )
+        });
+    if interrupted {
+        return EXIT_INTERRUPTED;
+    }
+    if error.downcast_ref::<InputsFailed>().is_some() {
+        return EXIT_FAILED_INPUTS;
+    }
+    let invalid = error.downcast_ref::<InvalidConfig>().is_some()
+        || error.chain().any(|cause| {
+            cause.is::<clap::Error>()
+                || matches!(
+                    cause.downcast_ref::<spectrs::error::SpectrsError>(),
+                    Some(spectrs::error::SpectrsError::InvalidParams(_))
+                )
+        });
+    if invalid { EXIT_CONFIG } else { EXIT_ERROR }
+}
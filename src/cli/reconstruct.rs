@@ -0,0 +1,143 @@
+//! `spectrs reconstruct`: audio from a linear or mel spectrogram, with phases estimated by
+//! Griffin-Lim
+
+use anyhow::{Context, Result};
+use spectrs::io::audio::{WavFormat, write_audio_file};
+use spectrs::io::export::{CsvOptions, load_spectrogram};
+use spectrs::io::metadata::{metadata_path, read_metadata};
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::filterbank::filter_bank;
+use spectrs::spectrogram::inverse::{
+    DEFAULT_GRIFFIN_LIM_ITERATIONS, DEFAULT_NNLS_ITERATIONS, MelInversion, griffin_lim,
+    mel_to_linear,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::SpectrogramType;
+use spectrs::spectrogram::weighting::weight_filter_bank;
+use std::path::PathBuf;
+
+/// Reconstruct audio from a linear spectrogram saved as NPY (or CSV, JSON, or PNG with its
+/// sidecar), estimating the phases with Griffin-Lim. The STFT parameters and sample rate are
+/// read from the sidecar written by `spectrs compute --sidecar`; options given explicitly
+/// override them. Mel spectrograms (and the other filter banks of --n-mels) are first brought
+/// back to linear frequencies, approximately.
+#[derive(clap::Args)]
+pub struct ReconstructArgs {
+    /// Spectrogram (.npy, .csv, .json or .png, by extension)
+    pub input: PathBuf,
+
+    /// Output audio file (default: <input>.reconstructed.wav, or .opus with --format opus)
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Sample encoding of the output WAV file, or opus for a compressed Ogg Opus file (with
+    /// the `opus` feature)
+    #[arg(long, default_value = "int16")]
+    pub format: WavFormat,
+
+    /// Number of Griffin-Lim iterations
+    #[arg(long, default_value_t = DEFAULT_GRIFFIN_LIM_ITERATIONS)]
+    pub n_iter: usize,
+
+    /// Sample rate of the spectrogram (required without a sidecar)
+    #[arg(long)]
+    pub sr: Option<u32>,
+
+    /// FFT window size (default: sidecar, or 2048)
+    #[arg(long)]
+    pub n_fft: Option<usize>,
+
+    /// Hop length (default: sidecar, or 512)
+    #[arg(long)]
+    pub hop_length: Option<usize>,
+
+    /// Window length (default: sidecar, or 2048)
+    #[arg(long)]
+    pub win_length: Option<usize>,
+
+    /// Spectrogram type (default: sidecar, or power)
+    #[arg(long)]
+    pub spec_type: Option<SpectrogramType>,
+
+    /// How the bands of a mel spectrogram are brought back to linear frequencies: nnls
+    /// (non-negative least squares, as librosa's mel_to_stft) or transpose (faster, smoother)
+    #[arg(long, value_enum, default_value_t = MelInversion::Nnls)]
+    pub mel_inversion: MelInversion,
+}
+
+/// Run `spectrs reconstruct`
+pub fn run(args: ReconstructArgs) -> Result<()> {
+    let sidecar = metadata_path(&args.input);
+    let metadata = sidecar
+        .exists()
+        .then(|| read_metadata(&sidecar))
+        .transpose()?;
+    let mut params = metadata
+        .as_ref()
+        .map_or_else(SpectrogramParams::default, |m| m.params);
+    let sr = args
+        .sr
+        .or(metadata.as_ref().map(|m| m.sample_rate))
+        .with_context(|| {
+            format!(
+                "No sidecar found at {}: give the sample rate with --sr",
+                sidecar.display()
+            )
+        })?;
+
+    if params.compat != Compat::Librosa {
+        anyhow::bail!(
+            "Only librosa-style spectrograms can be reconstructed (found --compat {:?})",
+            params.compat
+        );
+    }
+    if params.compression.is_some() {
+        anyhow::bail!("Compressed spectrograms (--compress) cannot be reconstructed");
+    }
+    params.n_fft = args.n_fft.unwrap_or(params.n_fft);
+    params.hop_length = args.hop_length.unwrap_or(params.hop_length);
+    params.win_length = args.win_length.unwrap_or(params.win_length);
+    params.spectrogram_type = args.spec_type.unwrap_or(params.spectrogram_type);
+
+    let mut spec = load_spectrogram(&args.input, &CsvOptions::default())?;
+    // The weighting of the bins is part of the filters the bands were projected on
+    if let Some(mut filters) = filter_bank::<f32>(&params, sr) {
+        if let Some(gains) = params
+            .weighting
+            .bin_gains(params.n_fft, sr, params.spectrogram_type)
+        {
+            weight_filter_bank(&mut filters, &gains);
+        }
+        spec = mel_to_linear(&spec, &filters, args.mel_inversion, DEFAULT_NNLS_ITERATIONS)
+            .with_context(|| format!("Failed to invert the bands of {}", args.input.display()))?;
+    }
+    // Griffin-Lim works on magnitudes, of the unnormalized window
+    let scale = params.win_scale() as f32;
+    let magnitude: Vec<Vec<f32>> = match params.spectrogram_type {
+        SpectrogramType::Magnitude => spec
+            .into_iter()
+            .map(|row| row.into_iter().map(|v| v / scale).collect())
+            .collect(),
+        SpectrogramType::Power => spec
+            .into_iter()
+            .map(|row| row.into_iter().map(|v| v.max(0.0).sqrt() / scale).collect())
+            .collect(),
+    };
+    let audio = griffin_lim(
+        &magnitude,
+        params.n_fft,
+        params.hop_length,
+        params.win_length,
+        params.center,
+        args.n_iter,
+    )
+    .with_context(|| format!("Failed to reconstruct {}", args.input.display()))?;
+
+    let output = args.output.unwrap_or_else(|| {
+        args.input
+            .with_extension(format!("reconstructed.{}", args.format.extension()))
+    });
+    write_audio_file(&output, &audio, sr, args.format)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! `spectrs serve`: HTTP service computing the spectrograms of uploaded audio, so that other
+//! services can use spectrs without shelling out (requires the `serve` feature)
+//!
+//! `POST /spectrogram` takes the audio as the body of the request (WAV, or headerless PCM with
+//! `raw_pcm`) and answers with its spectrogram, computed as `spectrs compute -` would from
+//! stdin. The options of `spectrs compute` go in the query string under the name of their
+//! flag, with `-` or `_` (`?format=npy&n_mels=128&f_max=8000`); flags without a value are given
+//! alone or as `true` (`?lenient`). Options reading or writing files on the server are
+//! rejected. `GET /health` answers `ok`.
+//!
+//! Requests with options that cannot be parsed get `400 Bad Request`, audio that cannot be
+//! processed with the options `422 Unprocessable Entity`, both with the error as text.
+
+use anyhow::Result;
+use std::net::IpAddr;
+
+/// Serve the spectrograms of the audio posted to `/spectrogram` over HTTP (requires the
+/// `serve` feature)
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Port to listen on (0 for any free port, logged when listening)
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to listen on (e.g. 0.0.0.0 to accept other hosts)
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Largest audio accepted in a request (MiB)
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_upload_mb: u32,
+}
+
+pub fn run(args: ServeArgs) -> Result<()> {
+    serve(&args)
+}
+
+/// Arguments of `spectrs compute` reading from stdin with the options of a query string
+#[cfg(feature = "serve")]
+fn compute_args(
+    query: &[(String, String)],
+) -> Result<(super::compute::ComputeArgs, clap::ArgMatches)> {
+    use clap::CommandFactory;
+
+    let cli = super::Cli::command();
+    let compute = cli
+        .find_subcommand("compute")
+        .expect("compute subcommand exists");
+    let mut argv = vec![super::compute::STDIO.to_string()];
+    for (key, value) in query {
+        let flag = key.replace('_', "-");
+        let takes_value = compute
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(flag.as_str()))
+            .is_none_or(|arg| arg.get_action().takes_values());
+        match (takes_value, value.as_str()) {
+            (false, "" | "true") => argv.push(format!("--{}", flag)),
+            (false, "false") => {}
+            // Attached, so that values starting with `-` are not taken for flags
+            _ => argv.push(format!("--{}={}", flag, value)),
+        }
+    }
+    let (args, matches) = super::compute::parse_args(argv.into_iter().map(Into::into))?;
+
+    // Every request is a run of its own, whose only output is the response
+    let unsupported = [
+        ("live", args.live),
+        ("manifest_out", args.manifest_out.is_some()),
+        ("report", args.report.is_some()),
+        ("index_db", args.index_db.is_some()),
+        ("checkpoint", args.checkpoint.is_some()),
+        ("dry_run", args.dry_run),
+        ("annotations", args.annotations.is_some()),
+        ("dataset_out", args.dataset_out.is_some()),
+        ("cache_dir", args.cache_dir.is_some()),
+        ("stats_out", args.stats_out.is_some()),
+        ("summary_png", args.summary_png.is_some()),
+        ("report_html", args.report_html.is_some()),
+        ("cmvn_out", args.cmvn_out.is_some()),
+        ("cmvn_in", args.cmvn_in.is_some()),
+        ("noise_wav", args.noise_wav.is_some()),
+        ("deterministic", args.deterministic),
+    ];
+    if let Some((option, _)) = unsupported.iter().find(|(_, given)| *given) {
+        anyhow::bail!("{} is not available with spectrs serve", option);
+    }
+    Ok((args, matches))
+}
+
+/// Response to a failed request, with the error as text
+#[cfg(feature = "serve")]
+fn error_response(
+    status: axum::http::StatusCode,
+    error: anyhow::Error,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    tracing::warn!("{}: {:#}", status, error);
+    (status, format!("{:#}\n", error)).into_response()
+}
+
+/// Handler of `POST /spectrogram`
+#[cfg(feature = "serve")]
+async fn spectrogram(
+    axum::extract::Query(query): axum::extract::Query<Vec<(String, String)>>,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    use axum::http::{StatusCode, header};
+    use axum::response::IntoResponse;
+    use spectrs::io::export::OutputFormat;
+
+    let (args, matches) = match compute_args(&query) {
+        Ok(parsed) => parsed,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    let content_type = match args.format.first() {
+        Some(OutputFormat::Png) => "image/png",
+        Some(OutputFormat::Npy) => "application/octet-stream",
+        Some(OutputFormat::Json) => "application/json",
+        Some(OutputFormat::Csv) => "text/csv",
+        None => "application/octet-stream",
+    };
+    // The computation runs on the threads of rayon, not on those serving requests
+    let computed = tokio::task::spawn_blocking(move || {
+        super::compute::compute_buffer(args, &matches, body.to_vec())
+    })
+    .await;
+    match computed {
+        Ok(Ok(bytes)) => ([(header::CONTENT_TYPE, content_type)], bytes).into_response(),
+        Ok(Err(e)) => error_response(StatusCode::UNPROCESSABLE_ENTITY, e),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.into()),
+    }
+}
+
+#[cfg(feature = "serve")]
+fn serve(args: &ServeArgs) -> Result<()> {
+    use anyhow::Context;
+    use axum::Router;
+    use axum::extract::DefaultBodyLimit;
+    use axum::routing::{get, post};
+
+    let app = Router::new()
+        .route("/spectrogram", post(spectrogram))
+        .route("/health", get(|| async { "ok" }))
+        .layer(DefaultBodyLimit::max(
+            args.max_upload_mb as usize * 1024 * 1024,
+        ));
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .with_context(|| "Failed to start the runtime")?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind((args.host, args.port))
+            .await
+            .with_context(|| format!("Failed to listen on {}:{}", args.host, args.port))?;
+        tracing::info!("Listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                tokio::signal::ctrl_c().await.ok();
+            })
+            .await
+            .with_context(|| "Server failed")
+    })
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve(_args: &ServeArgs) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Serve feature not enabled. Compile with --features serve to use this command."
+    )
+    .context(super::InvalidConfig))
+}
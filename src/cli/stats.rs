@@ -0,0 +1,185 @@
+//! `spectrs stats`: per-file spectral and temporal statistics (and loudness) as a CSV table or
+//! JSON records
+
+use crate::cli::compute::{
+    STDIO, WalkArgs, archive_sources, directory_sources, is_single_input, parse_downmix,
+    parse_raw_pcm, parse_sample_rate, single_source, store_sources,
+};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use spectrs::features::FeatureSummary;
+use spectrs::features::loudness::Loudness;
+use spectrs::features::temporal::ZeroCrossingCounter;
+use spectrs::io::audio::{DownmixMode, RawPcm, ResampleQuality, resample_with_quality};
+use spectrs::io::export::{
+    CsvOptions, save_feature_json, save_feature_table, write_feature_json, write_feature_table,
+};
+use spectrs::io::source::{AudioSource, is_archive_path, is_store_url, is_url};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use std::path::Path;
+
+/// Print per-file statistics (duration, RMS, zero-crossing rate, spectral centroid, bandwidth,
+/// rolloff and flatness, and optionally loudness) of files, directories, URLs or stdin as CSV
+/// or JSON
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    /// Input file, directory or URL (URLs require the `http` feature). `-` reads stdin
+    pub input: String,
+
+    /// Write the table to this file instead of stdout
+    #[arg(long, default_value = "-")]
+    pub output: String,
+
+    /// Write JSON records instead of CSV, with the short-term loudness over time of --loudness
+    #[arg(long)]
+    pub json: bool,
+
+    /// Meter the loudness of the decoded audio (ITU-R BS.1770): integrated and maximum
+    /// short-term loudness (LUFS), true peak (dBTP) and sample peak (dBFS)
+    #[arg(long)]
+    pub loudness: bool,
+
+    /// Decode the input as headerless PCM instead of WAV (see `spectrs compute --help`)
+    #[arg(long, value_parser = parse_raw_pcm)]
+    pub raw_pcm: Option<RawPcm>,
+
+    /// How multichannel audio is reduced to mono (see `spectrs compute --help`)
+    #[arg(long, default_value = "average", value_parser = parse_downmix)]
+    pub downmix: DownmixMode,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    /// Target sample rate (optional). If specified, audio is resampled first. Hz, or a preset:
+    /// speech16k, music22k or cd44k
+    #[arg(long, value_parser = parse_sample_rate)]
+    pub sr: Option<u32>,
+
+    /// Resampler quality preset (only used together with --sr)
+    #[arg(long, default_value = "fast")]
+    pub resample_quality: ResampleQuality,
+
+    /// FFT window size
+    #[arg(long, default_value = "2048")]
+    pub n_fft: usize,
+
+    /// Hop length
+    #[arg(long, default_value = "512")]
+    pub hop_length: usize,
+
+    /// Window length
+    #[arg(long, default_value = "2048")]
+    pub win_length: usize,
+
+    /// Field delimiter
+    #[arg(long, default_value = ",")]
+    pub csv_delimiter: char,
+
+    /// Decimal separator
+    #[arg(long, default_value = ".")]
+    pub csv_decimal: char,
+
+    /// Number of digits after the decimal separator (optional, shortest exact representation
+    /// if unspecified)
+    #[arg(long)]
+    pub csv_precision: Option<usize>,
+}
+
+/// Run `spectrs stats`
+pub fn run(args: StatsArgs) -> Result<()> {
+    let params = SpectrogramParams::builder()
+        .n_fft(args.n_fft)
+        .hop_length(args.hop_length)
+        .win_length(args.win_length)
+        .spectrogram_type(SpectrogramType::Magnitude)
+        .build()
+        .with_context(|| "Invalid spectrogram parameters")?;
+    let csv = CsvOptions {
+        delimiter: args.csv_delimiter,
+        decimal: args.csv_decimal,
+        precision: args.csv_precision,
+    };
+    csv.validate().with_context(|| "Invalid CSV options")?;
+
+    let input = Path::new(&args.input);
+    let remote = is_url(&args.input) || is_store_url(&args.input);
+    if !remote && args.input != STDIO && !input.exists() {
+        anyhow::bail!("Input path does not exist: {}", input.display());
+    }
+    if remote && args.raw_pcm.is_some() {
+        anyhow::bail!("--raw-pcm is not available for URL inputs");
+    }
+    if is_archive_path(input) && args.raw_pcm.is_some() {
+        anyhow::bail!("--raw-pcm is not available for archive inputs");
+    }
+    let sources = if is_single_input(&args.input, args.raw_pcm) {
+        vec![single_source(&args.input, args.raw_pcm, args.downmix)?]
+    } else if is_store_url(&args.input) {
+        store_sources(&args.input, args.downmix, &args.walk)?.sources
+    } else if is_archive_path(input) {
+        archive_sources(input, args.downmix, &args.walk)?.sources
+    } else {
+        directory_sources(input, args.raw_pcm, args.downmix, &args.walk)?.sources
+    };
+
+    let rows = sources
+        .par_iter()
+        .map(|source| {
+            summarize(source.as_ref(), &args, &params)
+                .with_context(|| format!("Failed to compute statistics of {}", source.name()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match (args.output == STDIO, args.json) {
+        (true, true) => write_feature_json(&rows, std::io::stdout().lock()),
+        (true, false) => write_feature_table(&rows, std::io::stdout().lock(), &csv),
+        (false, true) => save_feature_json(&rows, Path::new(&args.output)),
+        (false, false) => save_feature_table(&rows, Path::new(&args.output), &csv),
+    }
+    .with_context(|| "Failed to save statistics")
+}
+
+/// Statistics of one source, labeled with its path relative to the input
+fn summarize(
+    source: &dyn AudioSource,
+    args: &StatsArgs,
+    params: &SpectrogramParams,
+) -> Result<(String, FeatureSummary)> {
+    let (audio, original_sr) = source.read_mono()?;
+    let mut zero_crossings = ZeroCrossingCounter::default();
+    zero_crossings.push(&audio);
+    let duration = audio.len() as f32 / original_sr as f32;
+    let loudness = args
+        .loudness
+        .then(|| Loudness::from_samples(&audio, original_sr));
+
+    let (audio, sr) = match args.sr {
+        Some(sr) if sr != original_sr => (
+            resample_with_quality(audio, original_sr, sr, args.resample_quality)?,
+            sr,
+        ),
+        _ => (audio, original_sr),
+    };
+    params.validate_for_sample_rate(sr)?;
+
+    let magnitude = compute_spectrogram(
+        &audio,
+        params.n_fft,
+        params.hop_length,
+        params.win_length,
+        params.center,
+        params.spectrogram_type,
+    );
+    let summary = FeatureSummary {
+        loudness,
+        ..FeatureSummary::from_spectrogram(
+            &magnitude,
+            sr,
+            params.n_fft,
+            duration,
+            zero_crossings.rate(),
+        )
+    };
+    Ok((source.relative_path().display().to_string(), summary))
+}
@@ -0,0 +1,259 @@
+//! `spectrs view`: interactive spectrogram preview in the terminal, drawn with colored
+//! half blocks (requires the `tui` feature)
+
+use anyhow::{Context, Result};
+use spectrs::io::image::Colormap;
+use std::path::PathBuf;
+
+/// Preview the spectrogram of a WAV file in the terminal (requires the `tui` feature)
+#[derive(clap::Args)]
+pub struct ViewArgs {
+    /// WAV file to preview
+    pub input: PathBuf,
+
+    /// FFT window size (also used as window length)
+    #[arg(long, default_value = "2048")]
+    pub n_fft: usize,
+
+    /// Hop length
+    #[arg(long, default_value = "512")]
+    pub hop_length: usize,
+
+    /// Number of mel bands (optional, linear frequencies if unspecified)
+    #[arg(long)]
+    pub n_mels: Option<usize>,
+
+    /// Colormap
+    #[arg(long, default_value = "viridis")]
+    pub colormap: Colormap,
+}
+
+/// Compute the spectrogram and open the viewer
+pub fn run(args: ViewArgs) -> Result<()> {
+    use spectrs::io::audio::read_audio_file_mono;
+    use spectrs::spectrogram::mel::par_convert_to_mel;
+    use spectrs::spectrogram::params::SpectrogramParams;
+    use spectrs::spectrogram::stft::par_compute_spectrogram;
+
+    let params = SpectrogramParams::builder()
+        .n_fft(args.n_fft)
+        .win_length(args.n_fft)
+        .hop_length(args.hop_length)
+        .n_mels(args.n_mels)
+        .build()
+        .with_context(|| "Invalid spectrogram parameters")?;
+    let (audio, sr) = read_audio_file_mono(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    params.validate_for_sample_rate(sr)?;
+
+    let mut spec = par_compute_spectrogram(
+        &audio,
+        params.n_fft,
+        params.hop_length,
+        params.win_length,
+        params.center,
+        params.spectrogram_type,
+    );
+    if let Some(n_mels) = params.n_mels {
+        spec = par_convert_to_mel(
+            &spec,
+            sr,
+            params.n_fft,
+            n_mels,
+            params.f_min,
+            params.f_max,
+            params.mel_scale,
+        );
+    }
+
+    let seconds_per_frame = params.hop_length as f32 / sr as f32;
+    show(
+        spec,
+        args.input.display().to_string(),
+        seconds_per_frame,
+        args.colormap,
+    )
+}
+
+#[cfg(feature = "tui")]
+fn show(
+    spec: Vec<Vec<f32>>,
+    name: String,
+    seconds_per_frame: f32,
+    colormap: Colormap,
+) -> Result<()> {
+    let mut viewer = Viewer::new(spec, name, seconds_per_frame, colormap);
+    let mut terminal = ratatui::init();
+    let result = viewer.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[cfg(not(feature = "tui"))]
+fn show(
+    _spec: Vec<Vec<f32>>,
+    _name: String,
+    _seconds_per_frame: f32,
+    _colormap: Colormap,
+) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "TUI feature not enabled. Compile with --features tui to use this command."
+    )
+    .context(super::InvalidConfig))
+}
+
+/// State of the viewer: which frames are shown and at which zoom level
+#[cfg(feature = "tui")]
+struct Viewer {
+    spec: Vec<Vec<f32>>,
+    name: String,
+    seconds_per_frame: f32,
+    colormap: Colormap,
+    /// Log range of the whole spectrogram, so colors do not change while scrolling
+    range: (f32, f32),
+    /// First frame shown
+    offset: usize,
+    /// Frames averaged into one column (a power of two)
+    zoom: usize,
+    /// Columns available at the last draw
+    columns: usize,
+}
+
+#[cfg(feature = "tui")]
+impl Viewer {
+    fn new(spec: Vec<Vec<f32>>, name: String, seconds_per_frame: f32, colormap: Colormap) -> Self {
+        let range = spectrs::io::terminal::log_range(&spec);
+        Self {
+            spec,
+            name,
+            seconds_per_frame,
+            colormap,
+            range,
+            offset: 0,
+            zoom: 1,
+            columns: 1,
+        }
+    }
+
+    fn n_frames(&self) -> usize {
+        self.spec.first().map_or(0, Vec::len)
+    }
+
+    /// Number of frames shown at the current zoom level
+    fn span(&self) -> usize {
+        self.columns * self.zoom
+    }
+
+    /// Largest useful zoom: the whole spectrogram fits the screen
+    fn max_zoom(&self) -> usize {
+        self.n_frames()
+            .div_ceil(self.columns.max(1))
+            .max(1)
+            .next_power_of_two()
+    }
+
+    fn scroll_to(&mut self, offset: isize) {
+        let last = self.n_frames().saturating_sub(self.span()) as isize;
+        self.offset = offset.clamp(0, last.max(0)) as usize;
+    }
+
+    /// Change the zoom level keeping the center of the view in place
+    fn zoom_to(&mut self, zoom: usize) {
+        let center = self.offset + self.span() / 2;
+        self.zoom = zoom.clamp(1, self.max_zoom());
+        self.scroll_to(center as isize - (self.span() / 2) as isize);
+    }
+
+    fn run(&mut self, terminal: &mut ratatui::DefaultTerminal) -> Result<()> {
+        use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let step = (self.span() / 8).max(1) as isize;
+            let offset = self.offset as isize;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Left | KeyCode::Char('h') => self.scroll_to(offset - step),
+                KeyCode::Right | KeyCode::Char('l') => self.scroll_to(offset + step),
+                KeyCode::PageUp => self.scroll_to(offset - self.span() as isize),
+                KeyCode::PageDown => self.scroll_to(offset + self.span() as isize),
+                KeyCode::Home => self.scroll_to(0),
+                KeyCode::End => self.scroll_to(isize::MAX),
+                KeyCode::Char('+') | KeyCode::Char('=') => self.zoom_to(self.zoom / 2),
+                KeyCode::Char('-') => self.zoom_to(self.zoom * 2),
+                KeyCode::Char('0') => self.zoom_to(self.max_zoom()),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        use ratatui::layout::{Constraint, Layout};
+        use ratatui::style::{Color, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::Paragraph;
+        use spectrs::io::terminal::{HALF_BLOCK, render_half_blocks};
+        use spectrs::spectrogram::pooling::pool_time;
+
+        let [plot, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        // The terminal may have been resized: keep the view valid
+        self.columns = plot.width.max(1) as usize;
+        self.zoom = self.zoom.min(self.max_zoom());
+        self.scroll_to(self.offset as isize);
+
+        // Average `zoom` frames into every column
+        let end = (self.offset + self.span()).min(self.n_frames());
+        let visible: Vec<Vec<f32>> = self
+            .spec
+            .iter()
+            .map(|row| row[self.offset..end].to_vec())
+            .collect();
+        let visible = pool_time(
+            &visible,
+            end.saturating_sub(self.offset).div_ceil(self.zoom),
+        );
+
+        let lines: Vec<Line> =
+            render_half_blocks(&visible, self.colormap, self.range, plot.height as usize)
+                .into_iter()
+                .map(|cells| {
+                    Line::from(
+                        cells
+                            .into_iter()
+                            .map(|cell| {
+                                let [r, g, b] = cell.top;
+                                let [br, bg, bb] = cell.bottom;
+                                Span::styled(
+                                    HALF_BLOCK.to_string(),
+                                    Style::default()
+                                        .fg(Color::Rgb(r, g, b))
+                                        .bg(Color::Rgb(br, bg, bb)),
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect();
+        frame.render_widget(Paragraph::new(lines), plot);
+
+        let text = format!(
+            " {}  {:.2}s-{:.2}s of {:.2}s  zoom 1/{}  ←/→ scroll  +/- zoom  0 fit  q quit",
+            self.name,
+            self.offset as f32 * self.seconds_per_frame,
+            end as f32 * self.seconds_per_frame,
+            self.n_frames() as f32 * self.seconds_per_frame,
+            self.zoom
+        );
+        frame.render_widget(
+            Paragraph::new(text).style(Style::default().fg(Color::Black).bg(Color::Gray)),
+            status,
+        );
+    }
+}
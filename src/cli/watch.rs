@@ -0,0 +1,243 @@
+//! `spectrs watch`: process the audio files dropped into a directory as they appear, e.g. by
+//! recorders in a lab (requires the `watch` feature)
+//!
+//! Changes in the directory are received from the file system (inotify, FSEvents, ...). A file
+//! is processed once it has settled: no event about it for the settling time, and the same size
+//! and modification time as when its last event arrived, so that files still being written are
+//! left alone. The files that settle together are processed as one batch of `spectrs compute`
+//! over the directory, keeping its relative structure, labels and walk options.
+
+use super::compute::ComputeArgs;
+#[cfg(feature = "watch")]
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::ffi::OsString;
+use std::path::PathBuf;
+#[cfg(feature = "watch")]
+use std::{collections::HashSet, path::Path};
+
+/// Process the audio files appearing in a directory as `spectrs compute` would (requires the
+/// `watch` feature)
+#[derive(clap::Args)]
+pub struct WatchArgs {
+    /// Directory to watch, with its subdirectories
+    pub dir: PathBuf,
+
+    /// Seconds a file must stay unchanged (no event, same size and modification time) before
+    /// it is processed
+    #[arg(long, default_value_t = 2.0)]
+    pub settle: f32,
+
+    /// Also process the files already in the directory when the watch starts
+    #[arg(long)]
+    pub existing: bool,
+
+    /// Stop after processing this many files (runs until interrupted otherwise)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_files: Option<u32>,
+
+    /// Options of `spectrs compute` applied to every file, after `--` (e.g. -- --n-mels 128
+    /// --output-dir spectrograms/)
+    #[arg(last = true, allow_hyphen_values = true)]
+    pub compute: Vec<OsString>,
+}
+
+impl WatchArgs {
+    /// Compute arguments and matches of a run over the watched directory
+    fn compute_args(&self) -> Result<(ComputeArgs, ArgMatches)> {
+        let dir = std::iter::once(self.dir.clone().into_os_string());
+        super::compute::parse_args(dir.chain(self.compute.iter().cloned()))
+    }
+}
+
+/// Check the options, then watch the directory
+pub fn run(args: WatchArgs) -> Result<()> {
+    if !args.dir.is_dir() {
+        anyhow::bail!("Watched path is not a directory: {}", args.dir.display());
+    }
+    if !(args.settle.is_finite() && args.settle >= 0.0) {
+        anyhow::bail!("--settle must be a non-negative number of seconds");
+    }
+    let (compute, _) = args.compute_args()?;
+    // Every batch is a run of its own
+    let unsupported = [
+        ("--live", compute.live),
+        ("--manifest", compute.manifest.is_some()),
+        ("--dataset-out", compute.dataset_out.is_some()),
+        ("--cmvn-out", compute.cmvn_out.is_some()),
+        ("--global-scale", compute.global_scale),
+        ("--deterministic", compute.deterministic),
+        ("--dry-run", compute.dry_run),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, given)| *given) {
+        anyhow::bail!("{} is not available with spectrs watch", flag);
+    }
+    if compute.dump_resampled && compute.output_dir.is_none() {
+        // The dumps would appear in the watched directory
+        anyhow::bail!("--dump-resampled requires --output-dir with spectrs watch");
+    }
+    watch(&args, &compute)
+}
+
+/// Whether a path may be an input of the run: a file with an audio extension (or any file with
+/// --include), outside the output directory
+#[cfg(feature = "watch")]
+fn is_candidate(path: &Path, compute: &ComputeArgs, output_dir: Option<&Path>) -> bool {
+    if output_dir.is_some_and(|dir| path.starts_with(dir)) {
+        return false;
+    }
+    if !compute.walk.include.is_empty() {
+        return true;
+    }
+    let extensions: &[&str] = match compute.raw_pcm {
+        Some(_) => &["raw", "pcm"],
+        None => &["wav"],
+    };
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext))
+}
+
+/// Process a batch of settled files (canonical paths), logging failures instead of stopping
+#[cfg(feature = "watch")]
+fn process(args: &WatchArgs, files: HashSet<PathBuf>) -> Result<()> {
+    tracing::info!("Processing {} new file(s)", files.len());
+    let (compute, matches) = args.compute_args()?;
+    if let Err(e) = super::compute::run_files(compute, &matches, Some(&files)) {
+        tracing::error!("{:#}", e);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+fn watch(args: &WatchArgs, compute: &ComputeArgs) -> Result<()> {
+    use notify::event::ModifyKind;
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use spectrs::io::source::is_store_url;
+    use std::collections::HashMap;
+    use std::sync::mpsc::{RecvTimeoutError, channel};
+    use std::time::{Duration, Instant, SystemTime};
+
+    /// Interval at which pending files are checked
+    const TICK: Duration = Duration::from_millis(100);
+
+    // Size and modification time of a file, None once it is gone
+    let stamp = |path: &Path| -> Option<(u64, SystemTime)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        Some((metadata.len(), metadata.modified().ok()?))
+    };
+
+    let dir = std::fs::canonicalize(&args.dir)
+        .with_context(|| format!("Failed to resolve {}", args.dir.display()))?;
+    // Outputs uploaded to an object store cannot appear in the watched directory
+    let output_dir = compute
+        .output_dir
+        .as_deref()
+        .filter(|dir| !is_store_url(dir))
+        .map(|output_dir| {
+            // Created by the first batch otherwise
+            std::fs::create_dir_all(output_dir).ok();
+            std::fs::canonicalize(output_dir).unwrap_or_else(|_| PathBuf::from(output_dir))
+        });
+    let settle = Duration::from_secs_f32(args.settle);
+
+    let (sender, receiver) = channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).with_context(|| "Failed to start watching")?;
+    watcher
+        .watch(&dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", dir.display()))?;
+    tracing::info!("Watching {}", dir.display());
+
+    // Files waiting to settle: their stamp when their last event arrived, and when it arrived
+    let mut pending: HashMap<PathBuf, (Option<(u64, SystemTime)>, Instant)> = HashMap::new();
+    if args.existing {
+        let existing = walkdir::WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.into_path())
+            .filter(|path| path.is_file() && is_candidate(path, compute, output_dir.as_deref()));
+        for path in existing {
+            pending.insert(path.clone(), (stamp(&path), Instant::now()));
+        }
+    }
+
+    let mut n_processed = 0u32;
+    loop {
+        match receiver.recv_timeout(TICK) {
+            // Reading the files (e.g. to process them) or changing their metadata is no news
+            Ok(Ok(event))
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(
+                            ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any
+                        )
+                ) =>
+            {
+                for path in event.paths {
+                    if is_candidate(&path, compute, output_dir.as_deref()) {
+                        let path_stamp = stamp(&path);
+                        pending.insert(path, (path_stamp, Instant::now()));
+                    }
+                }
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::warn!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut settled = HashSet::new();
+        pending.retain(|path, (last_stamp, since)| {
+            let current = stamp(path);
+            if current.is_none() {
+                // Deleted or renamed away
+                return false;
+            }
+            if current != *last_stamp {
+                // Still being written
+                *last_stamp = current;
+                *since = Instant::now();
+                return true;
+            }
+            if since.elapsed() < settle {
+                return true;
+            }
+            settled.insert(path.clone());
+            false
+        });
+        if settled.is_empty() {
+            continue;
+        }
+        if let Some(max_files) = args.max_files {
+            let remaining = (max_files - n_processed) as usize;
+            if settled.len() > remaining {
+                let mut files: Vec<PathBuf> = settled.into_iter().collect();
+                files.sort();
+                settled = files.into_iter().take(remaining).collect();
+            }
+        }
+        n_processed += settled.len() as u32;
+        process(args, settled)?;
+        if args
+            .max_files
+            .is_some_and(|max_files| n_processed >= max_files)
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "watch"))]
+fn watch(_args: &WatchArgs, _compute: &ComputeArgs) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Watch feature not enabled. Compile with --features watch to use this command."
+    )
+    .context(super::InvalidConfig))
+}
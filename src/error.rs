@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt;
+
+/// Underlying error of a `SpectrsError`, e.g. a `hound::Error` or a `std::io::Error`
+pub type BoxedError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Result type of the library
+pub type Result<T, E = SpectrsError> = std::result::Result<T, E>;
+
+/// Errors returned by the library. The message describes what failed; the underlying error,
+/// if any, is available through `Error::source`.
+#[derive(Debug)]
+pub enum SpectrsError {
+    /// The input is not in a format spectrs can read (e.g. not a PCM WAV file)
+    UnsupportedFormat(String),
+    /// Invalid parameter or combination of parameters
+    InvalidParams(String),
+    /// Audio could not be decoded
+    DecodeError {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// Audio could not be resampled
+    ResampleError {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// An image could not be created or written
+    ImageError {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// A file or directory could not be read or written
+    Io {
+        message: String,
+        source: std::io::Error,
+    },
+    /// A JSON document (sidecar, CMVN statistics) is malformed or from an unsupported version
+    InvalidData {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// The GPU backend could not be initialized or failed
+    Gpu {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// A Parquet dataset or an Arrow record batch or file could not be written
+    Dataset {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// The SQLite index of processed files could not be read or written
+    Index {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// Audio could not be encoded (e.g. to Opus)
+    Encode {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// The computation was stopped by its `CancellationToken`
+    Cancelled(String),
+}
+
+impl SpectrsError {
+    pub(crate) fn decode(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::DecodeError {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub(crate) fn resample(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::ResampleError {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub(crate) fn io(message: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            message: message.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn invalid_data(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::InvalidData {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    pub(crate) fn gpu(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::Gpu {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    #[cfg(feature = "arrow")]
+    pub(crate) fn dataset(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::Dataset {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    #[cfg(feature = "index")]
+    pub(crate) fn index(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::Index {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    #[cfg(feature = "opus")]
+    pub(crate) fn encode(message: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::Encode {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Classify a WAV reading or writing error: unsupported encodings and IO failures get
+    /// their own kind
+    pub(crate) fn wav(message: impl Into<String>, error: hound::Error) -> Self {
+        match error {
+            hound::Error::IoError(source) => Self::io(message, source),
+            hound::Error::Unsupported => {
+                Self::UnsupportedFormat(format!("{}: unsupported WAV encoding", message.into()))
+            }
+            error => Self::decode(message, error),
+        }
+    }
+}
+
+impl SpectrsError {
+    /// Prefix the message (e.g. with the file being processed), keeping the kind and source
+    pub fn context(mut self, context: impl fmt::Display) -> Self {
+        let message = match &mut self {
+            Self::UnsupportedFormat(message)
+            | Self::InvalidParams(message)
+            | Self::Cancelled(message) => message,
+            Self::DecodeError { message, .. }
+            | Self::ResampleError { message, .. }
+            | Self::ImageError { message, .. }
+            | Self::Io { message, .. }
+            | Self::InvalidData { message, .. }
+            | Self::Gpu { message, .. }
+            | Self::Dataset { message, .. }
+            | Self::Index { message, .. }
+            | Self::Encode { message, .. } => message,
+        };
+        *message = format!("{}: {}", context, message);
+        self
+    }
+}
+
+impl fmt::Display for SpectrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(message)
+            | Self::InvalidParams(message)
+            | Self::Cancelled(message) => f.write_str(message),
+            Self::DecodeError { message, .. }
+            | Self::ResampleError { message, .. }
+            | Self::ImageError { message, .. }
+            | Self::Io { message, .. }
+            | Self::InvalidData { message, .. }
+            | Self::Gpu { message, .. }
+            | Self::Dataset { message, .. }
+            | Self::Index { message, .. }
+            | Self::Encode { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl Error for SpectrsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::UnsupportedFormat(_) | Self::InvalidParams(_) | Self::Cancelled(_) => None,
+            Self::Io { source, .. } => Some(source),
+            Self::DecodeError { source, .. }
+            | Self::ResampleError { source, .. }
+            | Self::ImageError { source, .. }
+            | Self::InvalidData { source, .. }
+            | Self::Gpu { source, .. }
+            | Self::Dataset { source, .. }
+            | Self::Index { source, .. }
+            | Self::Encode { source, .. } => source.as_deref().map(|e| e as &(dyn Error + 'static)),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// Activity segmentation from the frame energies of a spectrogram: frames whose energy is within
+// `threshold_db` of the loudest frame are active, silent gaps shorter than `min_silence` are
+// bridged and active runs shorter than `min_active` are dropped. The segments alternate between
+// active and silent and cover the whole recording, each frame owning the time closer to its
+// center than to its neighbours', so that they can be used to trim audio without re-analysis.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::annotations::Annotation;
+use crate::spectrogram::ops::frame_energy_db;
+use crate::spectrogram::stft::{Float, SpectrogramType};
+
+/// Label of active segments once exported as annotations
+pub const ACTIVE_LABEL: &str = "active";
+/// Label of silent segments once exported as annotations
+pub const SILENCE_LABEL: &str = "silence";
+
+/// Settings of the segmentation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActivityParams {
+    /// Energy (dB relative to the loudest frame, at most 0) of the quietest active frames
+    pub threshold_db: f32,
+    /// Shortest silence (seconds) between two active segments; shorter gaps are bridged
+    pub min_silence: f32,
+    /// Shortest active segment (seconds); shorter ones are silence
+    pub min_active: f32,
+}
+
+impl Default for ActivityParams {
+    /// Frames within 40 dB of the loudest, pauses of 200 ms and events of 100 ms
+    fn default() -> Self {
+        Self {
+            threshold_db: -40.0,
+            min_silence: 0.2,
+            min_active: 0.1,
+        }
+    }
+}
+
+impl ActivityParams {
+    pub fn validate(&self) -> Result<()> {
+        if self.threshold_db.is_nan() || self.threshold_db > 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The activity threshold must be at most 0 dB (relative to the loudest frame), got {}",
+                self.threshold_db
+            )));
+        }
+        let valid = |seconds: f32| seconds >= 0.0 && seconds.is_finite();
+        if !valid(self.min_silence) || !valid(self.min_active) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The shortest silence and activity must be non-negative durations, got {}s and {}s",
+                self.min_silence, self.min_active
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Interval of a recording that is either active or silent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    /// Start of the interval (seconds from the start of the recording)
+    pub start: f32,
+    /// End of the interval (seconds)
+    pub end: f32,
+    /// Whether the frames of the interval are active
+    pub active: bool,
+}
+
+impl From<Segment> for Annotation {
+    /// Label of the segment, `active` or `silence`
+    fn from(segment: Segment) -> Self {
+        let label = if segment.active {
+            ACTIVE_LABEL
+        } else {
+            SILENCE_LABEL
+        };
+        Annotation {
+            start: segment.start,
+            end: segment.end,
+            label: label.to_string(),
+            f_min: None,
+            f_max: None,
+        }
+    }
+}
+
+/// Active and silent segments of a recording of `duration` seconds from the linear (or mel)
+/// [freq][time] spectrogram whose frame `t` is centered at `time_offset + t / frame_rate`
+/// seconds. A silent recording is a single silent segment.
+pub fn segment_activity<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+    frame_rate: f32,
+    time_offset: f32,
+    duration: f32,
+    params: &ActivityParams,
+) -> Result<Vec<Segment>> {
+    params.validate()?;
+    let energies = frame_energy_db(spectrogram, spectrogram_type)?;
+    let mut active: Vec<bool> = energies
+        .iter()
+        .map(|&energy| energy >= params.threshold_db)
+        .collect();
+    let frames = |seconds: f32| (seconds * frame_rate).round() as usize;
+
+    // Bridge short pauses between active runs, then drop short active runs
+    let runs = runs_of(&active);
+    for (i, &(start, end, is_active)) in runs.iter().enumerate() {
+        let inner = i > 0 && i + 1 < runs.len();
+        if !is_active && inner && end - start < frames(params.min_silence) {
+            active[start..end].fill(true);
+        }
+    }
+    for (start, end, is_active) in runs_of(&active) {
+        if is_active && end - start < frames(params.min_active) {
+            active[start..end].fill(false);
+        }
+    }
+
+    // Frames own the time up to halfway to their neighbours
+    let boundary = |t: usize| {
+        let time = time_offset + (t as f32 - 0.5) / frame_rate;
+        time.clamp(0.0, duration)
+    };
+    let mut segments: Vec<Segment> = runs_of(&active)
+        .into_iter()
+        .map(|(start, end, active)| Segment {
+            start: boundary(start),
+            end: boundary(end),
+            active,
+        })
+        .collect();
+    if let Some(first) = segments.first_mut() {
+        first.start = 0.0;
+    }
+    match segments.last_mut() {
+        Some(last) => last.end = duration,
+        None => segments.push(Segment {
+            start: 0.0,
+            end: duration,
+            active: false,
+        }),
+    }
+    // Runs of frames centered beyond the end of the audio
+    segments.retain(|segment| segment.end > segment.start);
+    Ok(segments)
+}
+
+/// Runs of equal values as (first frame, frame after the last, value)
+fn runs_of(values: &[bool]) -> Vec<(usize, usize, bool)> {
+    let mut runs: Vec<(usize, usize, bool)> = Vec::new();
+    for (t, &value) in values.iter().enumerate() {
+        match runs.last_mut() {
+            Some((_, end, last)) if *last == value => *end = t + 1,
+            _ => runs.push((t, t + 1, value)),
+        }
+    }
+    runs
+}
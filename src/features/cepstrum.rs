@@ -0,0 +1,161 @@
+// Cepstral analysis computed frame by frame from a linear-frequency spectrogram in [freq][time]
+// format: the real cepstrum (inverse FFT of the log magnitude spectrum), and the cepstral peak
+// prominence (CPP, Hillenbrand et al. 1994) used to rate the periodicity of voices, i.e. the
+// height of the cepstral peak in the pitch range above the trend line of the cepstrum.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::stft::SpectrogramType;
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+/// Floor of the magnitudes taken the logarithm of
+const MIN_MAGNITUDE: f64 = 1e-10;
+
+/// Real cepstrum of every frame ([quefrency][time]) of a linear-frequency spectrogram computed
+/// with `n_fft`: the inverse FFT of the natural logarithm of its magnitude spectrum. Quefrency
+/// q is a delay of q samples; the n_fft/2 + 1 first ones are kept, the others mirroring them.
+pub fn real_cepstrum(
+    spectrogram: &[Vec<f32>],
+    spectrogram_type: SpectrogramType,
+    n_fft: usize,
+) -> Result<Vec<Vec<f32>>> {
+    let n_bins = n_fft / 2 + 1;
+    if n_fft < 2 || spectrogram.len() != n_bins {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The cepstrum needs the {} bins of a linear spectrogram with n_fft={}, got {}",
+            n_bins,
+            n_fft,
+            spectrogram.len()
+        )));
+    }
+    let fft = FftPlanner::<f64>::new().plan_fft_forward(n_fft);
+    let n_frames = spectrogram[0].len();
+    let mut cepstrum = vec![vec![0.0f32; n_frames]; n_bins];
+    let mut buffer = vec![Complex::new(0.0, 0.0); n_fft];
+    for t in 0..n_frames {
+        for (k, row) in spectrogram.iter().enumerate() {
+            let log_magnitude = match spectrogram_type {
+                SpectrogramType::Magnitude => (row[t] as f64).max(MIN_MAGNITUDE).ln(),
+                SpectrogramType::Power => {
+                    0.5 * (row[t] as f64).max(MIN_MAGNITUDE * MIN_MAGNITUDE).ln()
+                }
+            };
+            // The log spectrum of a real signal is even: the inverse FFT is the forward one
+            // divided by its length
+            buffer[k] = Complex::new(log_magnitude, 0.0);
+            if k > 0 && k < n_fft - k {
+                buffer[n_fft - k] = buffer[k];
+            }
+        }
+        fft.process(&mut buffer);
+        for (row, value) in cepstrum.iter_mut().zip(&buffer) {
+            row[t] = (value.re / n_fft as f64) as f32;
+        }
+    }
+    Ok(cepstrum)
+}
+
+/// Settings of the cepstral peak prominence, Praat's defaults
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CppParams {
+    /// Lowest pitch (Hz) whose period the cepstral peak is searched at
+    pub f0_min: f32,
+    /// Highest pitch (Hz) whose period the cepstral peak is searched at
+    pub f0_max: f32,
+    /// Quefrency (seconds) the trend line is fitted from, up to the last one
+    pub trend_from: f32,
+}
+
+impl Default for CppParams {
+    fn default() -> Self {
+        Self {
+            f0_min: 60.0,
+            f0_max: 330.0,
+            trend_from: 0.001,
+        }
+    }
+}
+
+impl CppParams {
+    /// Check that the pitch range is valid and that the cepstrum of an FFT of `n_fft` points at
+    /// `sr` reaches the period of the lowest pitch
+    pub fn validate(&self, sr: u32, n_fft: usize) -> Result<()> {
+        if !(0.0 < self.f0_min && self.f0_min < self.f0_max) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The pitch range of the cepstral peak {} Hz to {} Hz must satisfy 0 < low < high",
+                self.f0_min, self.f0_max
+            )));
+        }
+        if !(0.0..1.0 / self.f0_max).contains(&self.trend_from) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The trend line of the cepstrum must start before the period of {} Hz",
+                self.f0_max
+            )));
+        }
+        let longest_period = (sr as f32 / self.f0_min).ceil() as usize;
+        if longest_period > n_fft / 2 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The cepstral peak of pitches down to {} Hz needs n_fft of at least {} at {} Hz",
+                self.f0_min,
+                2 * longest_period,
+                sr
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Cepstral peak of a frame
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct CepstralPeak {
+    /// Quefrency of the peak (seconds), the period of the pitch
+    pub quefrency: f32,
+    /// Pitch of the peak (Hz)
+    pub f0: f32,
+    /// Height of the peak above the trend line of the cepstrum (dB)
+    pub cpp_db: f32,
+}
+
+/// Cepstral peak of every frame of a real cepstrum ([quefrency][time], see `real_cepstrum`) of
+/// audio at `sr`: the highest quefrency bin within the periods of the pitch range, and its
+/// height above the least-squares line fitted to the cepstrum in dB (20 log10 |c|) from
+/// `trend_from` on. Silent frames have a prominence of 0.
+pub fn cepstral_peak_prominence(
+    cepstrum: &[Vec<f32>],
+    sr: u32,
+    params: &CppParams,
+) -> Result<Vec<CepstralPeak>> {
+    let n_fft = 2 * cepstrum.len().saturating_sub(1);
+    params.validate(sr, n_fft)?;
+    let shortest_period = ((sr as f32 / params.f0_max).floor() as usize).max(1);
+    let longest_period = (sr as f32 / params.f0_min).ceil() as usize;
+    let trend_start = ((params.trend_from * sr as f32).round() as usize).max(1);
+
+    // Sums of the least-squares fit over the quefrencies of the trend line
+    let quefrencies: Vec<f64> = (trend_start..cepstrum.len()).map(|q| q as f64).collect();
+    let n = quefrencies.len() as f64;
+    let mean_q = quefrencies.iter().sum::<f64>() / n;
+    let variance_q: f64 = quefrencies.iter().map(|q| (q - mean_q).powi(2)).sum();
+
+    let n_frames = cepstrum.first().map_or(0, |row| row.len());
+    Ok((0..n_frames)
+        .map(|t| {
+            let db = |q: usize| 20.0 * (cepstrum[q][t].abs() as f64).max(MIN_MAGNITUDE).log10();
+            let mean_db = quefrencies.iter().map(|&q| db(q as usize)).sum::<f64>() / n;
+            let covariance: f64 = quefrencies
+                .iter()
+                .map(|&q| (q - mean_q) * (db(q as usize) - mean_db))
+                .sum();
+            let slope = covariance / variance_q;
+            let peak = (shortest_period..=longest_period)
+                .max_by(|&a, &b| db(a).total_cmp(&db(b)))
+                .unwrap_or(shortest_period);
+            let trend = mean_db + slope * (peak as f64 - mean_q);
+            CepstralPeak {
+                quefrency: peak as f32 / sr as f32,
+                f0: sr as f32 / peak as f32,
+                cpp_db: (db(peak) - trend) as f32,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,287 @@
+// Formant tracking following Praat's "To Formant (burg)": the audio is resampled to twice the
+// formant ceiling and pre-emphasized from 50 Hz, then every Hann-windowed frame is modelled by
+// Burg's method with two poles per formant searched. The roots of the prediction error filter
+// above the real axis are the formants: their angle gives the frequency and their distance to
+// the unit circle the bandwidth. Frames are laid out like those of an STFT, so that tracks line
+// up with spectrograms.
+
+use crate::error::{Result, SpectrsError};
+use crate::features::lpc::{LpcMethod, lpc};
+use crate::io::audio::resample;
+use crate::spectrogram::stft::create_hann_window;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustfft::num_complex::Complex64;
+use std::f64::consts::PI;
+
+/// Number of formants exported and drawn (F1 to F3)
+pub const TRACKED_FORMANTS: usize = 3;
+
+/// Frequency (Hz) the pre-emphasis starts from
+const PRE_EMPHASIS_HZ: f64 = 50.0;
+/// Roots closer than this (Hz) to 0 Hz or to the Nyquist frequency are not formants
+const EDGE_HZ: f64 = 50.0;
+/// Iterations of the root finder, which stops earlier once the roots settle
+const MAX_ITERATIONS: usize = 500;
+
+/// Settings of the formant tracker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormantParams {
+    /// Highest formant searched (Hz): 5500 for adult female voices, 5000 for male ones
+    pub max_formant: f32,
+    /// Number of formants searched below the ceiling (two poles each)
+    pub n_formants: usize,
+    /// Widest bandwidth (Hz) of a formant: wider poles shape the slope of the spectrum rather
+    /// than resonances
+    pub max_bandwidth: f32,
+    /// Samples (at the rate of the audio) in each frame
+    pub frame_length: usize,
+    /// Stride between successive frames (samples at the rate of the audio)
+    pub hop_length: usize,
+    /// Center frames on their time, padding the audio with zeros (as the STFT and librosa do)
+    pub center: bool,
+}
+
+impl Default for FormantParams {
+    /// Praat's defaults for the ceiling and number of formants, bandwidths up to 400 Hz, frames
+    /// of 2048 samples every 512
+    fn default() -> Self {
+        Self {
+            max_formant: 5500.0,
+            n_formants: 5,
+            max_bandwidth: 400.0,
+            frame_length: 2048,
+            hop_length: 512,
+            center: true,
+        }
+    }
+}
+
+impl FormantParams {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.max_formant > 2.0 * EDGE_HZ as f32 && self.max_formant.is_finite()) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The formant ceiling must be above {} Hz, got {}",
+                2.0 * EDGE_HZ,
+                self.max_formant
+            )));
+        }
+        if self.max_bandwidth.is_nan() || self.max_bandwidth <= 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The widest formant bandwidth must be positive, got {}",
+                self.max_bandwidth
+            )));
+        }
+        if self.n_formants == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "At least one formant must be searched".to_string(),
+            ));
+        }
+        if self.frame_length == 0 || self.hop_length == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "frame_length and hop_length must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Resonance of the vocal tract
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Formant {
+    /// Centre frequency (Hz)
+    pub frequency: f32,
+    /// Bandwidth at -3 dB (Hz)
+    pub bandwidth: f32,
+}
+
+/// Formants of every frame
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FormantTrack {
+    /// Time of the center of every frame (seconds)
+    pub times: Vec<f32>,
+    /// Formants of every frame from the lowest, up to the number searched (none in silence)
+    pub formants: Vec<Vec<Formant>>,
+}
+
+impl FormantTrack {
+    /// Frequency of the formant `index` (0 for F1) in every frame, None where not found
+    pub fn frequencies(&self, index: usize) -> Vec<Option<f32>> {
+        self.formants
+            .iter()
+            .map(|frame| frame.get(index).map(|formant| formant.frequency))
+            .collect()
+    }
+}
+
+/// Track the formants of mono audio at `sr` (single-threaded)
+pub fn track_formants(audio: &[f32], sr: u32, params: &FormantParams) -> Result<FormantTrack> {
+    let analysis = Analysis::new(audio, sr, params)?;
+    let formants = (0..analysis.times.len())
+        .map(|i| analysis.frame_formants(i))
+        .collect::<Result<_>>()?;
+    Ok(FormantTrack {
+        times: analysis.times,
+        formants,
+    })
+}
+
+/// Track the formants of mono audio at `sr` (parallelized over frames)
+#[cfg(feature = "parallel")]
+pub fn par_track_formants(audio: &[f32], sr: u32, params: &FormantParams) -> Result<FormantTrack> {
+    let analysis = Analysis::new(audio, sr, params)?;
+    let formants = (0..analysis.times.len())
+        .into_par_iter()
+        .map(|i| analysis.frame_formants(i))
+        .collect::<Result<_>>()?;
+    Ok(FormantTrack {
+        times: analysis.times,
+        formants,
+    })
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the frames are processed sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_track_formants(audio: &[f32], sr: u32, params: &FormantParams) -> Result<FormantTrack> {
+    track_formants(audio, sr, params)
+}
+
+/// Pre-emphasized audio at the rate of the analysis and the frames laid over it
+struct Analysis {
+    audio: Vec<f32>,
+    sr: u32,
+    /// Time of the center of every frame (seconds)
+    times: Vec<f32>,
+    window: Vec<f32>,
+    order: usize,
+    max_bandwidth: f32,
+}
+
+impl Analysis {
+    fn new(audio: &[f32], sr: u32, params: &FormantParams) -> Result<Self> {
+        params.validate()?;
+        // Twice the ceiling, or the rate of the audio if lower
+        let analysis_sr = ((2.0 * params.max_formant).round() as u32).min(sr);
+        let mut resampled = if analysis_sr < sr {
+            resample(audio.to_vec(), sr, analysis_sr)?
+        } else {
+            audio.to_vec()
+        };
+        let alpha = (-2.0 * PI * PRE_EMPHASIS_HZ / analysis_sr as f64).exp() as f32;
+        for i in (1..resampled.len()).rev() {
+            resampled[i] -= alpha * resampled[i - 1];
+        }
+
+        // Frames of the STFT of the audio, whatever the rate of the analysis
+        let n_frames = if params.center {
+            audio.len() / params.hop_length + 1
+        } else if audio.len() < params.frame_length {
+            0
+        } else {
+            (audio.len() - params.frame_length) / params.hop_length + 1
+        };
+        let offset = if params.center {
+            0
+        } else {
+            params.frame_length / 2
+        };
+        let times = (0..n_frames)
+            .map(|i| (i * params.hop_length + offset) as f32 / sr as f32)
+            .collect();
+        let frame_length = ((params.frame_length as f64 * analysis_sr as f64 / sr as f64).round()
+            as usize)
+            .max(2 * params.n_formants + 1);
+        Ok(Self {
+            audio: resampled,
+            sr: analysis_sr,
+            times,
+            window: create_hann_window(frame_length, false),
+            order: 2 * params.n_formants,
+            max_bandwidth: params.max_bandwidth,
+        })
+    }
+
+    /// Formants of frame `i`, from the lowest
+    fn frame_formants(&self, i: usize) -> Result<Vec<Formant>> {
+        let length = self.window.len();
+        let center = (self.times[i] as f64 * self.sr as f64).round() as isize;
+        let start = center - (length / 2) as isize;
+        let frame: Vec<f32> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(j, &w)| {
+                let index = start + j as isize;
+                let sample = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| self.audio.get(index));
+                sample.map_or(0.0, |&s| s * w)
+            })
+            .collect();
+        let model = lpc(&frame, self.order, LpcMethod::Burg)?;
+        if model.error <= 0.0 {
+            return Ok(Vec::new());
+        }
+        let mut formants = formants(&model.coefficients, self.sr);
+        formants.retain(|formant| formant.bandwidth <= self.max_bandwidth);
+        Ok(formants)
+    }
+}
+
+/// Formants of the prediction error filter `coefficients` (1, a1, ..., ap) at `sr`, from the
+/// lowest: its roots above the real axis away from 0 Hz and the Nyquist frequency
+pub fn formants(coefficients: &[f32], sr: u32) -> Vec<Formant> {
+    let coefficients: Vec<f64> = coefficients.iter().map(|&a| a as f64).collect();
+    let nyquist = sr as f64 / 2.0;
+    let mut formants: Vec<Formant> = polynomial_roots(&coefficients)
+        .into_iter()
+        .filter(|root| root.im > 0.0)
+        .map(|root| (root.arg() * sr as f64 / (2.0 * PI), root.norm()))
+        .filter(|&(frequency, _)| frequency > EDGE_HZ && frequency < nyquist - EDGE_HZ)
+        .map(|(frequency, radius)| Formant {
+            frequency: frequency as f32,
+            bandwidth: (-radius.ln() * sr as f64 / PI) as f32,
+        })
+        .collect();
+    formants.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    formants
+}
+
+/// Roots of the monic polynomial z^p + c1 z^(p-1) + ... + cp given as (1, c1, ..., cp), by the
+/// Durand-Kerner iteration
+fn polynomial_roots(coefficients: &[f64]) -> Vec<Complex64> {
+    let degree = coefficients.len().saturating_sub(1);
+    let evaluate = |z: Complex64| {
+        coefficients
+            .iter()
+            .fold(Complex64::new(0.0, 0.0), |value, &c| value * z + c)
+    };
+    // Starting points spread inside the unit circle, where the roots of stable filters lie,
+    // off the real axis and off any symmetry
+    let mut roots: Vec<Complex64> = (0..degree)
+        .map(|k| Complex64::from_polar(0.9, 2.0 * PI * k as f64 / degree as f64 + 0.4))
+        .collect();
+    for _ in 0..MAX_ITERATIONS {
+        let mut largest_step: f64 = 0.0;
+        for i in 0..degree {
+            let z = roots[i];
+            let denominator = roots
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(Complex64::new(1.0, 0.0), |product, (_, &root)| {
+                    product * (z - root)
+                });
+            if denominator.norm_sqr() == 0.0 {
+                continue;
+            }
+            let step = evaluate(z) / denominator;
+            roots[i] = z - step;
+            largest_step = largest_step.max(step.norm());
+        }
+        if largest_step < 1e-12 {
+            break;
+        }
+    }
+    roots
+}
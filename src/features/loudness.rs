@@ -0,0 +1,245 @@
+// Loudness and level metering following ITU-R BS.1770-4 (EBU R 128): integrated loudness of the
+// K-weighted audio gated over 400 ms blocks, short-term loudness over 3 s windows, and the true
+// peak of the audio oversampled 4 times. Block energies are gathered in 100 ms steps, so that
+// audio can be metered chunk by chunk (e.g. while streaming a file). Audio is metered as a
+// single channel of weight 1, i.e. after the downmix to mono.
+
+use crate::filter::Biquad;
+use std::f64::consts::PI;
+
+/// Step between successive gating blocks and short-term windows (seconds)
+pub const STEP_SECONDS: f64 = 0.1;
+/// Steps in a gating block (400 ms)
+const BLOCK_STEPS: usize = 4;
+/// Steps in a short-term window (3 s)
+const SHORT_TERM_STEPS: usize = 30;
+/// Blocks quieter than this are left out of the integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks more than this below the loudness of the blocks above the absolute gate are left out
+const RELATIVE_GATE_LU: f64 = 10.0;
+/// Oversampling factor of the true peak
+const OVERSAMPLING: usize = 4;
+/// Taps of each phase of the interpolation filter (48 in total, as in BS.1770-4 Annex 2)
+const TAPS_PER_PHASE: usize = 12;
+
+/// Loudness and peak levels of an audio file
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Loudness {
+    /// Gated loudness of the whole audio (LUFS, -inf if every block is below the absolute gate)
+    pub integrated_lufs: f32,
+    /// Loudness of the 3 s windows starting every 100 ms (LUFS, empty for shorter audio)
+    pub short_term_lufs: Vec<f32>,
+    /// Peak of the audio oversampled 4 times (dBTP)
+    pub true_peak_dbtp: f32,
+    /// Peak absolute sample value (dBFS)
+    pub sample_peak_dbfs: f32,
+}
+
+impl Loudness {
+    /// Names of the values returned by `values`, in order
+    pub const COLUMNS: [&'static str; 4] = [
+        "integrated_lufs",
+        "short_term_max_lufs",
+        "true_peak_dbtp",
+        "sample_peak_dbfs",
+    ];
+
+    /// Meter mono audio at `sr` as a whole
+    pub fn from_samples(samples: &[f32], sr: u32) -> Self {
+        let mut meter = LoudnessMeter::new(sr);
+        meter.push(samples);
+        meter.finish()
+    }
+
+    /// Loudest short-term window (LUFS, -inf for audio shorter than 3 s)
+    pub fn short_term_max_lufs(&self) -> f32 {
+        self.short_term_lufs
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    /// Values in the order of `COLUMNS`
+    pub fn values(&self) -> [f32; 4] {
+        [
+            self.integrated_lufs,
+            self.short_term_max_lufs(),
+            self.true_peak_dbtp,
+            self.sample_peak_dbfs,
+        ]
+    }
+}
+
+/// Running loudness meter of mono audio delivered in chunks
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    k_weighting: [Biquad; 2],
+    /// Samples in a step
+    step: usize,
+    /// Samples and energy of the step being filled
+    filled: usize,
+    energy: f64,
+    /// Mean square of the K-weighted samples of every complete step
+    steps: Vec<f64>,
+    true_peak: TruePeak,
+    sample_peak: f32,
+}
+
+impl LoudnessMeter {
+    pub fn new(sr: u32) -> Self {
+        Self {
+            k_weighting: k_weighting(sr),
+            step: ((sr as f64 * STEP_SECONDS).round() as usize).max(1),
+            filled: 0,
+            energy: 0.0,
+            steps: Vec::new(),
+            true_peak: TruePeak::new(),
+            sample_peak: 0.0,
+        }
+    }
+
+    /// Account for the next chunk of audio
+    pub fn push(&mut self, chunk: &[f32]) {
+        self.sample_peak = chunk
+            .iter()
+            .fold(self.sample_peak, |peak, s| peak.max(s.abs()));
+        self.true_peak.push(chunk);
+
+        let mut weighted = chunk.to_vec();
+        for biquad in self.k_weighting.iter_mut() {
+            biquad.process(&mut weighted);
+        }
+        for s in weighted {
+            self.energy += s as f64 * s as f64;
+            self.filled += 1;
+            if self.filled == self.step {
+                self.steps.push(self.energy / self.step as f64);
+                self.filled = 0;
+                self.energy = 0.0;
+            }
+        }
+    }
+
+    /// Levels of the audio pushed so far (samples of an incomplete last step are not gated)
+    pub fn finish(&self) -> Loudness {
+        let blocks = window_means(&self.steps, BLOCK_STEPS);
+        let above_absolute: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&z| loudness(z) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        let integrated_lufs = if above_absolute.is_empty() {
+            f64::NEG_INFINITY
+        } else {
+            let relative_gate = loudness(mean(&above_absolute)) - RELATIVE_GATE_LU;
+            let gated: Vec<f64> = above_absolute
+                .into_iter()
+                .filter(|&z| loudness(z) > relative_gate)
+                .collect();
+            loudness(mean(&gated))
+        };
+        Loudness {
+            integrated_lufs: integrated_lufs as f32,
+            short_term_lufs: window_means(&self.steps, SHORT_TERM_STEPS)
+                .into_iter()
+                .map(|z| loudness(z) as f32)
+                .collect(),
+            true_peak_dbtp: 20.0 * self.true_peak.peak.max(self.sample_peak).log10(),
+            sample_peak_dbfs: 20.0 * self.sample_peak.log10(),
+        }
+    }
+}
+
+/// Loudness (LUFS) of a mean square of K-weighted samples
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Mean square of every run of `n` successive steps
+fn window_means(steps: &[f64], n: usize) -> Vec<f64> {
+    steps.windows(n).map(mean).collect()
+}
+
+/// High-shelf pre-filter and RLB high-pass of the K-weighting at `sr`, from the analogue
+/// prototypes of BS.1770 (coefficients as derived by libebur128)
+fn k_weighting(sr: u32) -> [Biquad; 2] {
+    let k = |f0: f64| (PI * f0 / sr as f64).tan();
+
+    let (k1, q1, gain_db) = (
+        k(1_681.974_450_955_533),
+        0.707_175_236_955_419_6,
+        3.999_843_853_973_347,
+    );
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let shelf = Biquad::new(
+        [
+            vh + vb * k1 / q1 + k1 * k1,
+            2.0 * (k1 * k1 - vh),
+            vh - vb * k1 / q1 + k1 * k1,
+        ],
+        [
+            1.0 + k1 / q1 + k1 * k1,
+            2.0 * (k1 * k1 - 1.0),
+            1.0 - k1 / q1 + k1 * k1,
+        ],
+    );
+
+    let (k2, q2) = (k(38.135_470_876_024_44), 0.500_327_037_323_877_3);
+    let a0 = 1.0 + k2 / q2 + k2 * k2;
+    let highpass = Biquad::new(
+        [a0, -2.0 * a0, a0],
+        [a0, 2.0 * (k2 * k2 - 1.0), 1.0 - k2 / q2 + k2 * k2],
+    );
+    [shelf, highpass]
+}
+
+/// Peak of audio interpolated `OVERSAMPLING` times by a polyphase windowed-sinc filter
+#[derive(Debug, Clone)]
+struct TruePeak {
+    /// Taps of every phase, applied to the most recent samples first
+    phases: Vec<[f32; TAPS_PER_PHASE]>,
+    /// Most recent samples, newest first
+    history: [f32; TAPS_PER_PHASE],
+    peak: f32,
+}
+
+impl TruePeak {
+    fn new() -> Self {
+        let n_taps = OVERSAMPLING * TAPS_PER_PHASE;
+        let center = (n_taps - 1) as f64 / 2.0;
+        let tap = |n: usize| {
+            let x = (n as f64 - center) / OVERSAMPLING as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let hann = 0.5 - 0.5 * (2.0 * PI * (n + 1) as f64 / (n_taps + 1) as f64).cos();
+            (sinc * hann) as f32
+        };
+        let phases = (0..OVERSAMPLING)
+            .map(|p| std::array::from_fn(|k| tap(k * OVERSAMPLING + p)))
+            .collect();
+        Self {
+            phases,
+            history: [0.0; TAPS_PER_PHASE],
+            peak: 0.0,
+        }
+    }
+
+    fn push(&mut self, chunk: &[f32]) {
+        for &s in chunk {
+            self.history.copy_within(..TAPS_PER_PHASE - 1, 1);
+            self.history[0] = s;
+            for taps in &self.phases {
+                let value: f32 = taps.iter().zip(&self.history).map(|(t, x)| t * x).sum();
+                self.peak = self.peak.max(value.abs());
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Linear predictive coding (LPC): every frame is modelled by an all-pole filter predicting each
+// sample from the previous ones, whose response is a smooth envelope of the spectrum peaking at
+// the formants. The coefficients are estimated with the autocorrelation method (Levinson-Durbin
+// recursion on the autocorrelation of the frame) or with Burg's method (minimizing the forward
+// and backward prediction errors, sharper on short frames). The envelope of a whole spectrogram
+// is computed from its power spectra, whose inverse FFT is the autocorrelation of the frames.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::stft::{Float, SpectrogramType};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+/// Estimator of the LPC coefficients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum LpcMethod {
+    /// Levinson-Durbin recursion on the autocorrelation of the frame (always stable)
+    #[default]
+    Autocorrelation,
+    /// Burg's method, minimizing the forward and backward prediction errors
+    Burg,
+}
+
+/// All-pole model of a frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lpc {
+    /// Coefficients of the prediction error filter A(z) = 1 + a1 z^-1 + ... + ap z^-p, starting
+    /// with 1
+    pub coefficients: Vec<f32>,
+    /// Mean square of the prediction error per sample
+    pub error: f32,
+}
+
+impl Lpc {
+    /// Order of the model
+    pub fn order(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// Power spectrum of the model, error / |A(e^jw)|², at the n_fft/2 + 1 bins of an FFT of
+    /// `n_fft` points
+    pub fn envelope(&self, n_fft: usize) -> Vec<f32> {
+        let coefficients: Vec<f64> = self.coefficients.iter().map(|&a| a as f64).collect();
+        let fft = FftPlanner::<f64>::new().plan_fft_forward(n_fft);
+        model_spectrum(&coefficients, self.error as f64, &*fft)
+            .into_iter()
+            .map(|p| p as f32)
+            .collect()
+    }
+}
+
+/// Model of order `order` of a frame of samples (windowed by the caller, e.g. with a Hann
+/// window)
+pub fn lpc(frame: &[f32], order: usize, method: LpcMethod) -> Result<Lpc> {
+    if order == 0 || order >= frame.len() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The LPC order must be between 1 and the frame length minus one ({}), got {}",
+            frame.len().saturating_sub(1),
+            order
+        )));
+    }
+    let frame: Vec<f64> = frame.iter().map(|&s| s as f64).collect();
+    let (coefficients, error) = match method {
+        LpcMethod::Autocorrelation => {
+            let n = frame.len() as f64;
+            let autocorrelation: Vec<f64> = (0..=order)
+                .map(|lag| {
+                    frame
+                        .iter()
+                        .zip(&frame[lag..])
+                        .map(|(a, b)| a * b)
+                        .sum::<f64>()
+                        / n
+                })
+                .collect();
+            levinson(&autocorrelation, order)
+        }
+        LpcMethod::Burg => burg(&frame, order),
+    };
+    Ok(Lpc {
+        coefficients: coefficients.into_iter().map(|a| a as f32).collect(),
+        error: error as f32,
+    })
+}
+
+/// Prediction error filter and error of the autocorrelation `r` (lags 0 to `order`) by the
+/// Levinson-Durbin recursion. Silence gives A(z) = 1 and no error.
+fn levinson(r: &[f64], order: usize) -> (Vec<f64>, f64) {
+    let mut a = vec![0.0; order + 1];
+    a[0] = 1.0;
+    let mut error = r[0];
+    for i in 1..=order {
+        if error <= 0.0 {
+            break;
+        }
+        let reflection = -(r[i] + (1..i).map(|j| a[j] * r[i - j]).sum::<f64>()) / error;
+        let previous = a.clone();
+        for j in 1..i {
+            a[j] = previous[j] + reflection * previous[i - j];
+        }
+        a[i] = reflection;
+        error *= 1.0 - reflection * reflection;
+    }
+    (a, error.max(0.0))
+}
+
+/// Prediction error filter and error of a frame by Burg's method
+fn burg(frame: &[f64], order: usize) -> (Vec<f64>, f64) {
+    let n = frame.len();
+    let mut a = vec![0.0; order + 1];
+    a[0] = 1.0;
+    let mut error = frame.iter().map(|s| s * s).sum::<f64>() / n as f64;
+    // Forward and backward prediction errors
+    let mut forward = frame.to_vec();
+    let mut backward = frame.to_vec();
+    for m in 0..order {
+        let (mut numerator, mut denominator) = (0.0, 0.0);
+        for i in m + 1..n {
+            numerator += forward[i] * backward[i - 1];
+            denominator += forward[i] * forward[i] + backward[i - 1] * backward[i - 1];
+        }
+        if denominator <= 0.0 {
+            break;
+        }
+        let reflection = -2.0 * numerator / denominator;
+        // From the end, so that the backward error of the previous sample is still the old one
+        for i in (m + 1..n).rev() {
+            let f = forward[i];
+            forward[i] = f + reflection * backward[i - 1];
+            backward[i] = backward[i - 1] + reflection * f;
+        }
+        let previous = a.clone();
+        for j in 1..=m + 1 {
+            a[j] = previous[j] + reflection * previous[m + 1 - j];
+        }
+        error *= 1.0 - reflection * reflection;
+    }
+    (a, error.max(0.0))
+}
+
+/// error / |A(e^jw)|² at the n/2 + 1 bins of the FFT `fft` of n points
+fn model_spectrum(coefficients: &[f64], error: f64, fft: &dyn Fft<f64>) -> Vec<f64> {
+    let n_fft = fft.len();
+    let mut buffer = vec![Complex::new(0.0, 0.0); n_fft];
+    for (value, &a) in buffer.iter_mut().zip(coefficients) {
+        *value = Complex::new(a, 0.0);
+    }
+    fft.process(&mut buffer);
+    buffer[..n_fft / 2 + 1]
+        .iter()
+        .map(|a| error / a.norm_sqr().max(f64::MIN_POSITIVE))
+        .collect()
+}
+
+/// Forward FFT of `n_fft` points, used both ways: the spectra are real and even
+fn plan(n_fft: usize, n_bins: usize, order: usize) -> Result<Arc<dyn Fft<f64>>> {
+    if n_bins != n_fft / 2 + 1 {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The LPC envelope needs the {} bins of a linear spectrogram with n_fft={}, got {}",
+            n_fft / 2 + 1,
+            n_fft,
+            n_bins
+        )));
+    }
+    if order == 0 || order >= n_fft / 2 {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The LPC order must be between 1 and n_fft/2 - 1 ({}), got {}",
+            (n_fft / 2).saturating_sub(1),
+            order
+        )));
+    }
+    Ok(FftPlanner::<f64>::new().plan_fft_forward(n_fft))
+}
+
+/// LPC envelope of frame `t` of a linear-frequency spectrogram, in its type
+fn frame_envelope<T: Float>(
+    spectrogram: &[Vec<T>],
+    t: usize,
+    spectrogram_type: SpectrogramType,
+    order: usize,
+    fft: &dyn Fft<f64>,
+) -> Vec<T> {
+    let n_fft = fft.len();
+    // Autocorrelation of the frame: the inverse FFT of its power spectrum
+    let mut buffer = vec![Complex::new(0.0, 0.0); n_fft];
+    for (k, row) in spectrogram.iter().enumerate() {
+        let value = row[t].to_f64().unwrap_or(0.0);
+        let power = match spectrogram_type {
+            SpectrogramType::Power => value,
+            SpectrogramType::Magnitude => value * value,
+        };
+        buffer[k] = Complex::new(power, 0.0);
+        if k > 0 && k < n_fft - k {
+            buffer[n_fft - k] = buffer[k];
+        }
+    }
+    fft.process(&mut buffer);
+    let autocorrelation: Vec<f64> = buffer[..=order]
+        .iter()
+        .map(|value| value.re / n_fft as f64)
+        .collect();
+    let (coefficients, error) = levinson(&autocorrelation, order);
+    model_spectrum(&coefficients, error, fft)
+        .into_iter()
+        .map(|power| {
+            let value = match spectrogram_type {
+                SpectrogramType::Power => power,
+                SpectrogramType::Magnitude => power.sqrt(),
+            };
+            T::from_f64(value).unwrap_or_else(T::zero)
+        })
+        .collect()
+}
+
+/// Replace every frame of a linear-frequency [freq][time] spectrogram computed with `n_fft` by
+/// the spectrum of its all-pole model of order `order` (autocorrelation method), in the same
+/// type and scale: formants stand out as smooth peaks without the harmonics. The lags of the
+/// autocorrelation wrap around when n_fft is below twice the window, which is negligible for
+/// tapered windows and orders far below the window length.
+pub fn lpc_envelope<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+    n_fft: usize,
+    order: usize,
+) -> Result<Vec<Vec<T>>> {
+    let fft = plan(n_fft, spectrogram.len(), order)?;
+    let n_frames = spectrogram.first().map_or(0, |row| row.len());
+    let frames: Vec<Vec<T>> = (0..n_frames)
+        .map(|t| frame_envelope(spectrogram, t, spectrogram_type, order, &*fft))
+        .collect();
+    Ok(transpose(frames, spectrogram.len()))
+}
+
+/// LPC envelope of a spectrogram (parallelized over frames), see `lpc_envelope`
+#[cfg(feature = "parallel")]
+pub fn par_lpc_envelope<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+    n_fft: usize,
+    order: usize,
+) -> Result<Vec<Vec<T>>> {
+    let fft = plan(n_fft, spectrogram.len(), order)?;
+    let n_frames = spectrogram.first().map_or(0, |row| row.len());
+    let frames: Vec<Vec<T>> = (0..n_frames)
+        .into_par_iter()
+        .map(|t| frame_envelope(spectrogram, t, spectrogram_type, order, &*fft))
+        .collect();
+    Ok(transpose(frames, spectrogram.len()))
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the frames are processed sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_lpc_envelope<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+    n_fft: usize,
+    order: usize,
+) -> Result<Vec<Vec<T>>> {
+    lpc_envelope(spectrogram, spectrogram_type, n_fft, order)
+}
+
+/// [time][freq] frames to a [freq][time] spectrogram of `n_bins` rows
+fn transpose<T: Float>(frames: Vec<Vec<T>>, n_bins: usize) -> Vec<Vec<T>> {
+    let mut spectrogram = vec![Vec::with_capacity(frames.len()); n_bins];
+    for frame in frames {
+        for (row, value) in spectrogram.iter_mut().zip(frame) {
+            row.push(value);
+        }
+    }
+    spectrogram
+}
@@ -0,0 +1,125 @@
+pub mod activity;
+pub mod cepstrum;
+pub mod formant;
+pub mod loudness;
+pub mod lpc;
+pub mod octave;
+pub mod onset;
+pub mod pitch;
+pub mod spectral;
+pub mod temporal;
+pub mod tonal;
+
+use loudness::Loudness;
+use spectral::{
+    rms_from_spectrogram, spectral_bandwidth, spectral_centroid, spectral_flatness,
+    spectral_rolloff,
+};
+
+/// Fraction of the magnitude below the roll-off frequency
+pub const DEFAULT_ROLL_PERCENT: f32 = 0.85;
+
+/// Mean and standard deviation of a frame-wise descriptor over a file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl Stats {
+    pub fn from_values(values: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let n = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+        Self {
+            mean,
+            std: variance.sqrt(),
+        }
+    }
+}
+
+/// Per-file descriptors for quick dataset exploration
+#[derive(Debug, Clone, Default)]
+pub struct FeatureSummary {
+    /// Duration of the audio (seconds)
+    pub duration: f32,
+    /// Root-mean-square energy
+    pub rms: Stats,
+    /// Fraction of successive sample pairs changing sign
+    pub zero_crossing_rate: f32,
+    /// Spectral centroid (Hz)
+    pub centroid: Stats,
+    /// Spectral bandwidth (Hz)
+    pub bandwidth: Stats,
+    /// Roll-off frequency (Hz)
+    pub rolloff: Stats,
+    /// Spectral flatness
+    pub flatness: Stats,
+    /// Loudness and peak levels (only metered when requested)
+    pub loudness: Option<Loudness>,
+}
+
+impl FeatureSummary {
+    /// Names of the values returned by `values`, in order
+    pub const COLUMNS: [&'static str; 12] = [
+        "duration_s",
+        "rms_mean",
+        "rms_std",
+        "zero_crossing_rate",
+        "centroid_mean_hz",
+        "centroid_std_hz",
+        "bandwidth_mean_hz",
+        "bandwidth_std_hz",
+        "rolloff_mean_hz",
+        "rolloff_std_hz",
+        "flatness_mean",
+        "flatness_std",
+    ];
+
+    /// Summarize a linear-frequency magnitude spectrogram ([freq][time]) computed with `n_fft`
+    /// at sample rate `sr`. Duration and zero-crossing rate come from the waveform.
+    pub fn from_spectrogram(
+        magnitude: &[Vec<f32>],
+        sr: u32,
+        n_fft: usize,
+        duration: f32,
+        zero_crossing_rate: f32,
+    ) -> Self {
+        Self {
+            duration,
+            rms: Stats::from_values(&rms_from_spectrogram(magnitude, n_fft)),
+            zero_crossing_rate,
+            centroid: Stats::from_values(&spectral_centroid(magnitude, sr, n_fft)),
+            bandwidth: Stats::from_values(&spectral_bandwidth(magnitude, sr, n_fft)),
+            rolloff: Stats::from_values(&spectral_rolloff(
+                magnitude,
+                sr,
+                n_fft,
+                DEFAULT_ROLL_PERCENT,
+            )),
+            flatness: Stats::from_values(&spectral_flatness(magnitude)),
+            loudness: None,
+        }
+    }
+
+    /// Values in the order of `COLUMNS`
+    pub fn values(&self) -> [f32; 12] {
+        [
+            self.duration,
+            self.rms.mean,
+            self.rms.std,
+            self.zero_crossing_rate,
+            self.centroid.mean,
+            self.centroid.std,
+            self.bandwidth.mean,
+            self.bandwidth.std,
+            self.rolloff.mean,
+            self.rolloff.std,
+            self.flatness.mean,
+            self.flatness.std,
+        ]
+    }
+}
@@ -0,0 +1,250 @@
+// Octave and third-octave band levels following IEC 61260-1: base-ten midband frequencies
+// (1000 Hz · 10^(3x/10b) for b bands per octave), 6th-order Butterworth band-pass filters
+// (class 1 shape) and the mean square of each band over successive frames. The analysis is
+// multirate: the audio is low-passed and decimated by two for every octave below the top one,
+// and each band is filtered at the lowest rate leaving it well below the Nyquist frequency, so
+// that the narrow low bands keep accurate filters at any sample rate.
+
+use crate::error::{Result, SpectrsError};
+use crate::filter::Biquad;
+use rustfft::num_complex::Complex64;
+use std::f64::consts::PI;
+
+/// Octave ratio of the base-ten system, 10^(3/10)
+const OCTAVE_RATIO: f64 = 1.995_262_314_968_879_6;
+/// Reference midband frequency (Hz)
+const REFERENCE_HZ: f64 = 1000.0;
+/// Order of the low-pass prototype of the band-pass filters (6th order once transformed)
+const BANDPASS_ORDER: usize = 3;
+/// Lowest nominal midband frequency analyzed: 25 Hz for third-octave bands, 31.5 Hz for
+/// octave bands
+const LOWEST_BAND: i32 = -16;
+/// A band is filtered at the lowest rate at least four times its upper edge, i.e. with a
+/// Nyquist frequency at least twice the edge
+const HEADROOM: f64 = 0.25;
+/// Cut-off of the anti-aliasing filter before each decimation, relative to the decimated rate
+const ANTI_ALIASING_CUTOFF: f64 = 0.3;
+/// Nominal midband frequencies of a decade of third-octave bands (ISO 266)
+const NOMINAL_DECADE: [f64; 10] = [1.0, 1.25, 1.6, 2.0, 2.5, 3.15, 4.0, 5.0, 6.3, 8.0];
+
+/// Width of the bands of `band_levels`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum BandFraction {
+    /// Octave bands (31.5 Hz, 63 Hz, 125 Hz, ...)
+    Octave,
+    /// Third-octave bands (25 Hz, 31.5 Hz, 40 Hz, ...)
+    #[default]
+    Third,
+}
+
+impl BandFraction {
+    /// Number of bands per octave
+    pub fn bands_per_octave(self) -> i32 {
+        match self {
+            BandFraction::Octave => 1,
+            BandFraction::Third => 3,
+        }
+    }
+
+    /// Bands below the Nyquist frequency at `sr`, as the index of each band in third-octave
+    /// steps from 1 kHz
+    fn band_indices(self, sr: u32) -> Vec<i32> {
+        let step = 3 / self.bands_per_octave();
+        // Octave bands are every third one, 1 kHz included
+        let first = -((-LOWEST_BAND).div_euclid(step) * step);
+        (first..)
+            .step_by(step as usize)
+            .take_while(|&x| band_edges(x, self).1 < sr as f64 / 2.0)
+            .collect()
+    }
+}
+
+/// Exact midband frequency (Hz) of the band `x` third-octave steps from 1 kHz
+fn midband_frequency(x: i32) -> f64 {
+    REFERENCE_HZ * OCTAVE_RATIO.powf(x as f64 / 3.0)
+}
+
+/// Nominal midband frequency (Hz) of the band `x` third-octave steps from 1 kHz, e.g. 31.5
+fn nominal_frequency(x: i32) -> f32 {
+    let decade = x.div_euclid(10);
+    (NOMINAL_DECADE[x.rem_euclid(10) as usize] * 10f64.powi(decade + 3)) as f32
+}
+
+/// Lower and upper edges (Hz) of the band `x` third-octave steps from 1 kHz
+fn band_edges(x: i32, fraction: BandFraction) -> (f64, f64) {
+    let half_band = OCTAVE_RATIO.powf(0.5 / fraction.bands_per_octave() as f64);
+    let midband = midband_frequency(x);
+    (midband / half_band, midband * half_band)
+}
+
+/// Butterworth band-pass filter from `low` to `high` (Hz) at `sr`, of order twice that of its
+/// low-pass prototype, as biquad sections
+fn butterworth_bandpass(sr: u32, low: f64, high: f64, order: usize) -> Vec<Biquad> {
+    // Edges prewarped for the bilinear transform
+    let k = 2.0 * sr as f64;
+    let (w1, w2) = (
+        k * (PI * low / sr as f64).tan(),
+        k * (PI * high / sr as f64).tan(),
+    );
+    let (center_squared, bandwidth) = (w1 * w2, w2 - w1);
+    (1..=order)
+        .flat_map(|i| {
+            // Each pole of the prototype becomes two poles of the band-pass filter
+            let pole =
+                Complex64::from_polar(1.0, PI * (2 * i + order - 1) as f64 / (2 * order) as f64);
+            let root = (pole * pole * bandwidth * bandwidth - 4.0 * center_squared).sqrt();
+            [
+                (pole * bandwidth + root) / 2.0,
+                (pole * bandwidth - root) / 2.0,
+            ]
+        })
+        // One of each pair of complex conjugates, the section holding both
+        .filter(|pole| pole.im > 0.0)
+        .map(|pole| {
+            let (a1, a0) = (-2.0 * pole.re, pole.norm_sqr());
+            let gain = bandwidth * k;
+            Biquad::new(
+                [gain, 0.0, -gain],
+                [
+                    k * k + a1 * k + a0,
+                    2.0 * a0 - 2.0 * k * k,
+                    k * k - a1 * k + a0,
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Halve the rate of `samples` at `sr` after an 8th-order Butterworth low-pass
+fn decimate(samples: &[f32], sr: u32) -> Vec<f32> {
+    let mut filtered = samples.to_vec();
+    let cutoff = ANTI_ALIASING_CUTOFF * sr as f64 / 2.0;
+    for section in 0..4 {
+        let q = 1.0 / (2.0 * (PI * (2 * section + 1) as f64 / 16.0).cos());
+        Biquad::lowpass(sr, cutoff, q).process(&mut filtered);
+    }
+    filtered.into_iter().step_by(2).collect()
+}
+
+/// Band levels of an audio file over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandLevels {
+    /// Width of the bands
+    pub fraction: BandFraction,
+    /// Nominal midband frequency of each band (Hz), e.g. 31.5, 63, 125
+    pub nominal_hz: Vec<f32>,
+    /// Exact (base-ten) midband frequency of each band (Hz)
+    pub center_hz: Vec<f32>,
+    /// Start of each frame (seconds)
+    pub times: Vec<f32>,
+    /// Mean square of the filtered audio in each band and frame ([band][frame])
+    pub mean_square: Vec<Vec<f32>>,
+}
+
+impl BandLevels {
+    /// Level of each band in each frame ([band][frame], dB relative to full scale: 0 dB for a
+    /// square wave of amplitude 1, -3 dB for a sine)
+    pub fn levels_db(&self) -> Vec<Vec<f32>> {
+        self.mean_square
+            .iter()
+            .map(|band| band.iter().map(|&ms| level_db(ms as f64)).collect())
+            .collect()
+    }
+
+    /// Equivalent continuous level of each band, the mean square of its frames (dB)
+    pub fn leq_db(&self) -> Vec<f32> {
+        let n_frames = self.times.len().max(1) as f64;
+        self.mean_square
+            .iter()
+            .map(|band| level_db(band.iter().map(|&ms| ms as f64).sum::<f64>() / n_frames))
+            .collect()
+    }
+}
+
+/// Level (dB) of a mean square, floored at -200 dB
+fn level_db(mean_square: f64) -> f32 {
+    (10.0 * mean_square.max(1e-20).log10()) as f32
+}
+
+/// Octave or third-octave band levels of mono audio at `sr` over successive frames of
+/// `frame_length` samples (the last one possibly shorter), from 25 Hz (31.5 Hz for octaves)
+/// to the highest band below the Nyquist frequency
+pub fn band_levels(
+    samples: &[f32],
+    sr: u32,
+    fraction: BandFraction,
+    frame_length: usize,
+) -> Result<BandLevels> {
+    if frame_length == 0 {
+        return Err(SpectrsError::InvalidParams(
+            "The frames of band levels must hold at least one sample".to_string(),
+        ));
+    }
+    let bands = fraction.band_indices(sr);
+    if bands.is_empty() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "No {:?} band fits below the Nyquist frequency at {} Hz",
+            fraction, sr
+        )));
+    }
+    let n_frames = samples.len().div_ceil(frame_length);
+
+    // Decimation stage of every band: the lowest rate keeping it within the headroom
+    let stage_of = |x: i32| {
+        let high = band_edges(x, fraction).1;
+        let mut stage = 0;
+        while high <= HEADROOM * (sr >> (stage + 1)) as f64 {
+            stage += 1;
+        }
+        stage
+    };
+    let n_stages = bands.iter().map(|&x| stage_of(x)).max().unwrap_or(0) + 1;
+
+    let mut mean_square = vec![Vec::new(); bands.len()];
+    let mut signal = samples.to_vec();
+    for stage in 0..n_stages {
+        if stage > 0 {
+            signal = decimate(&signal, sr >> (stage - 1));
+        }
+        let rate = sr >> stage;
+        // Frame boundaries in the samples of the stage
+        let factor = (1usize << stage) as f64;
+        let bounds: Vec<usize> = (0..=n_frames)
+            .map(|j| ((j * frame_length) as f64 / factor).ceil() as usize)
+            .map(|bound| bound.min(signal.len()))
+            .collect();
+        for (band, &x) in bands.iter().enumerate() {
+            if stage_of(x) != stage {
+                continue;
+            }
+            let (low, high) = band_edges(x, fraction);
+            let mut filtered = signal.clone();
+            for mut section in butterworth_bandpass(rate, low, high, BANDPASS_ORDER) {
+                section.process(&mut filtered);
+            }
+            mean_square[band] = bounds
+                .windows(2)
+                .map(|frame| {
+                    let values = &filtered[frame[0]..frame[1]];
+                    if values.is_empty() {
+                        return 0.0;
+                    }
+                    (values.iter().map(|&v| (v as f64).powi(2)).sum::<f64>() / values.len() as f64)
+                        as f32
+                })
+                .collect();
+        }
+    }
+
+    Ok(BandLevels {
+        fraction,
+        nominal_hz: bands.iter().map(|&x| nominal_frequency(x)).collect(),
+        center_hz: bands.iter().map(|&x| midband_frequency(x) as f32).collect(),
+        times: (0..n_frames)
+            .map(|j| (j * frame_length) as f32 / sr as f32)
+            .collect(),
+        mean_square,
+    })
+}
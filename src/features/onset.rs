@@ -0,0 +1,160 @@
+// Onset detection by spectral flux, following librosa's `onset_strength` and `onset_detect`:
+// the onset strength of a frame is the mean increase of the dB levels of the bands over the
+// previous frame, and onsets are the peaks of the strength envelope (normalized to [0, 1]) that
+// are local maxima, stand `delta` above the local mean and follow the previous onset by more
+// than `wait` frames. Works on linear or mel spectrograms, power or magnitude.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::compression::{Compression, compress_spectrogram};
+use crate::spectrogram::stft::{Float, SpectrogramType};
+
+/// Dynamic range (dB below the peak) of the levels the flux is computed on
+const TOP_DB: f32 = 80.0;
+
+/// Settings of the peak picking, in frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeakPick {
+    /// Frames before a peak it must be the maximum of
+    pub pre_max: usize,
+    /// Frames after a peak (itself included) it must be the maximum of
+    pub post_max: usize,
+    /// Frames before a peak averaged into the local mean
+    pub pre_avg: usize,
+    /// Frames after a peak (itself included) averaged into the local mean
+    pub post_avg: usize,
+    /// Height of a peak above the local mean (the envelope spans [0, 1])
+    pub delta: f32,
+    /// Frames after an onset without another one
+    pub wait: usize,
+}
+
+impl PeakPick {
+    /// librosa's defaults at `frame_rate` frames per second: maxima over the previous 30 ms,
+    /// means over ±100 ms, delta 0.07 and 30 ms between onsets
+    pub fn for_frame_rate(frame_rate: f32) -> Self {
+        let frames = |seconds: f32| (seconds * frame_rate) as usize;
+        Self {
+            pre_max: frames(0.03),
+            post_max: 1,
+            pre_avg: frames(0.1),
+            post_avg: frames(0.1) + 1,
+            delta: 0.07,
+            wait: frames(0.03),
+        }
+    }
+
+    /// Check that the windows hold the peak and that delta is finite
+    pub fn validate(&self) -> Result<()> {
+        if self.post_max == 0 || self.post_avg == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "post_max and post_avg must be at least 1 (the peak itself)".to_string(),
+            ));
+        }
+        if !self.delta.is_finite() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Onset delta must be finite, got {}",
+                self.delta
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Onset found in a spectrogram
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Onset {
+    /// Frame of the spectrogram
+    pub frame: usize,
+    /// Time of the frame (seconds)
+    pub time: f32,
+    /// Normalized onset strength of the frame, in [0, 1]
+    pub strength: f32,
+}
+
+/// Onset strength of every frame of a [freq][time] spectrogram: the mean over bands of the
+/// increase of their dB level over the previous frame (0 for the first frame)
+pub fn onset_strength<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+) -> Vec<f32> {
+    let n_frames = spectrogram.first().map_or(0, Vec::len);
+    if spectrogram.is_empty() || n_frames == 0 {
+        return Vec::new();
+    }
+    let mut levels = spectrogram.to_vec();
+    compress_spectrogram(
+        &mut levels,
+        Compression::Db {
+            top_db: Some(TOP_DB),
+        },
+        spectrogram_type,
+    );
+
+    let mut strength = vec![0.0f32; n_frames];
+    for row in &levels {
+        for (t, pair) in row.windows(2).enumerate() {
+            let increase = (pair[1] - pair[0]).max(T::zero());
+            strength[t + 1] += increase.to_f32().unwrap_or(0.0);
+        }
+    }
+    let n_bands = levels.len() as f32;
+    strength.iter_mut().for_each(|s| *s /= n_bands);
+    strength
+}
+
+/// Frames of the peaks of an onset strength envelope, normalized to [0, 1] first
+pub fn pick_peaks(envelope: &[f32], params: &PeakPick) -> Result<Vec<usize>> {
+    params.validate()?;
+    let normalized = normalize(envelope);
+    let n = normalized.len();
+    let window = |t: usize, before: usize, after: usize| {
+        &normalized[t.saturating_sub(before)..(t + after).min(n)]
+    };
+
+    let mut peaks: Vec<usize> = Vec::new();
+    for (t, &value) in normalized.iter().enumerate() {
+        let local_max = window(t, params.pre_max, params.post_max)
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let neighbours = window(t, params.pre_avg, params.post_avg);
+        let local_mean = neighbours.iter().sum::<f32>() / neighbours.len() as f32;
+        let waited = peaks.last().is_none_or(|&last| t > last + params.wait);
+        if value == local_max && value >= local_mean + params.delta && waited {
+            peaks.push(t);
+        }
+    }
+    Ok(peaks)
+}
+
+/// Onsets of a [freq][time] spectrogram whose frame `t` is at `time_offset + t / frame_rate`
+/// seconds
+pub fn detect_onsets<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+    frame_rate: f32,
+    time_offset: f32,
+    params: &PeakPick,
+) -> Result<Vec<Onset>> {
+    let envelope = onset_strength(spectrogram, spectrogram_type);
+    let normalized = normalize(&envelope);
+    Ok(pick_peaks(&envelope, params)?
+        .into_iter()
+        .map(|frame| Onset {
+            frame,
+            time: time_offset + frame as f32 / frame_rate,
+            strength: normalized[frame],
+        })
+        .collect())
+}
+
+/// Envelope shifted and scaled to span [0, 1] (all zeros if flat)
+fn normalize(envelope: &[f32]) -> Vec<f32> {
+    let min = envelope.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = envelope.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    envelope
+        .iter()
+        .map(|&v| if range > 0.0 { (v - min) / range } else { 0.0 })
+        .collect()
+}
@@ -0,0 +1,197 @@
+// Fundamental frequency (f0) tracking with YIN (de Cheveigné & Kawahara, 2002): for every frame,
+// the cumulative mean normalized difference of the signal with delayed copies of itself dips at
+// the period of the pitch. The first dip below the threshold (refined to its local minimum and
+// by parabolic interpolation) gives the period; frames without such a dip are unvoiced. Frames
+// are laid out like those of an STFT (centered as in librosa, or starting every hop), so that
+// tracks line up with spectrograms.
+
+use crate::error::{Result, SpectrsError};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Settings of the YIN estimator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YinParams {
+    /// Lowest pitch searched (Hz)
+    pub f_min: f32,
+    /// Highest pitch searched (Hz)
+    pub f_max: f32,
+    /// Samples in each frame: the difference function is integrated over its first half and
+    /// delays span the second half
+    pub frame_length: usize,
+    /// Stride between successive frames
+    pub hop_length: usize,
+    /// Center frames on their time, padding the audio with zeros (as the STFT and librosa do)
+    pub center: bool,
+    /// Dip of the normalized difference below which a period is accepted
+    pub threshold: f32,
+}
+
+impl Default for YinParams {
+    /// librosa's defaults: C2 to C7, frames of 2048 samples every 512, threshold 0.1
+    fn default() -> Self {
+        Self {
+            f_min: 65.0,
+            f_max: 2093.0,
+            frame_length: 2048,
+            hop_length: 512,
+            center: true,
+            threshold: 0.1,
+        }
+    }
+}
+
+impl YinParams {
+    /// Shortest frame holding two periods of `f_min` at `sr`
+    pub fn min_frame_length(f_min: f32, sr: u32) -> usize {
+        2 * ((sr as f32 / f_min).ceil() as usize + 1)
+    }
+
+    /// Check the search range against the Nyquist frequency and the frame length
+    pub fn validate_for_sample_rate(&self, sr: u32) -> Result<()> {
+        let nyquist = sr as f32 / 2.0;
+        if !(0.0 < self.f_min && self.f_min < self.f_max && self.f_max < nyquist) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Pitch range {} Hz to {} Hz must satisfy 0 < f_min < f_max < {} Hz (Nyquist)",
+                self.f_min, self.f_max, nyquist
+            )));
+        }
+        if self.hop_length == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "hop_length must be greater than 0".to_string(),
+            ));
+        }
+        if !(self.threshold > 0.0 && self.threshold <= 1.0) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "YIN threshold must be in (0, 1], got {}",
+                self.threshold
+            )));
+        }
+        let min_frame_length = Self::min_frame_length(self.f_min, sr);
+        if self.frame_length < min_frame_length {
+            return Err(SpectrsError::InvalidParams(format!(
+                "frame_length ({}) must hold two periods of {} Hz ({} samples at {} Hz)",
+                self.frame_length, self.f_min, min_frame_length, sr
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fundamental frequency of every frame
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PitchTrack {
+    /// Time of the center of every frame (seconds)
+    pub times: Vec<f32>,
+    /// Pitch of every frame (Hz), None where unvoiced
+    pub f0: Vec<Option<f32>>,
+}
+
+/// Track the pitch of mono audio at `sr` (single-threaded)
+pub fn yin(audio: &[f32], sr: u32, params: &YinParams) -> Result<PitchTrack> {
+    let (padded, starts) = frames(audio, sr, params)?;
+    let f0 = starts
+        .iter()
+        .map(|&start| frame_pitch(&padded[start..start + params.frame_length], sr, params))
+        .collect();
+    Ok(track(f0, sr, params))
+}
+
+/// Track the pitch of mono audio at `sr` (parallelized over frames)
+#[cfg(feature = "parallel")]
+pub fn par_yin(audio: &[f32], sr: u32, params: &YinParams) -> Result<PitchTrack> {
+    let (padded, starts) = frames(audio, sr, params)?;
+    let f0 = starts
+        .par_iter()
+        .map(|&start| frame_pitch(&padded[start..start + params.frame_length], sr, params))
+        .collect();
+    Ok(track(f0, sr, params))
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the frames are processed sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_yin(audio: &[f32], sr: u32, params: &YinParams) -> Result<PitchTrack> {
+    yin(audio, sr, params)
+}
+
+/// Audio padded as the frames require, and the start of every frame in it
+fn frames(audio: &[f32], sr: u32, params: &YinParams) -> Result<(Vec<f32>, Vec<usize>)> {
+    params.validate_for_sample_rate(sr)?;
+    let pad = if params.center {
+        params.frame_length / 2
+    } else {
+        0
+    };
+    let mut padded = vec![0.0; pad];
+    padded.extend_from_slice(audio);
+    padded.resize(audio.len() + 2 * pad, 0.0);
+    let n_frames = if padded.len() < params.frame_length {
+        0
+    } else {
+        (padded.len() - params.frame_length) / params.hop_length + 1
+    };
+    let starts = (0..n_frames).map(|i| i * params.hop_length).collect();
+    Ok((padded, starts))
+}
+
+/// Track with the times of the frames
+fn track(f0: Vec<Option<f32>>, sr: u32, params: &YinParams) -> PitchTrack {
+    let offset = if params.center {
+        0
+    } else {
+        params.frame_length / 2
+    };
+    let times = (0..f0.len())
+        .map(|i| (i * params.hop_length + offset) as f32 / sr as f32)
+        .collect();
+    PitchTrack { times, f0 }
+}
+
+/// Pitch of one frame (None if unvoiced)
+fn frame_pitch(frame: &[f32], sr: u32, params: &YinParams) -> Option<f32> {
+    let window = params.frame_length / 2;
+    let min_period = ((sr as f32 / params.f_max).floor() as usize).max(1);
+    let max_period = ((sr as f32 / params.f_min).ceil() as usize).min(frame.len() - window);
+
+    // Difference function d(τ), then its cumulative mean normalized form d'(τ)
+    let difference: Vec<f64> = (0..=max_period)
+        .map(|lag| {
+            frame[..window]
+                .iter()
+                .zip(&frame[lag..lag + window])
+                .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+                .sum()
+        })
+        .collect();
+    let mut normalized = vec![1.0; max_period + 1];
+    let mut cumulative = 0.0;
+    for lag in 1..=max_period {
+        cumulative += difference[lag];
+        if cumulative == 0.0 {
+            // Silence has no period
+            return None;
+        }
+        normalized[lag] = difference[lag] * lag as f64 / cumulative;
+    }
+
+    // First dip below the threshold, followed down to its minimum
+    let threshold = params.threshold as f64;
+    let mut lag = (min_period..=max_period).find(|&lag| normalized[lag] < threshold)?;
+    while lag < max_period && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    // Parabola through the minimum and its neighbours
+    let shift = if lag > 1 && lag < max_period {
+        let (a, b, c) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+        let curvature = a - 2.0 * b + c;
+        if curvature > 0.0 {
+            (a - c) / (2.0 * curvature)
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+    Some((sr as f64 / (lag as f64 + shift)) as f32)
+}
@@ -0,0 +1,120 @@
+// Spectral descriptors computed frame by frame from a linear-frequency magnitude spectrogram
+// in [freq][time] format, following librosa's definitions.
+
+/// Centre frequency (Hz) of each FFT bin
+pub fn fft_frequencies(sr: u32, n_fft: usize) -> Vec<f32> {
+    (0..n_fft / 2 + 1)
+        .map(|k| k as f32 * sr as f32 / n_fft as f32)
+        .collect()
+}
+
+/// Number of frames of a [freq][time] spectrogram
+fn n_frames(spectrogram: &[Vec<f32>]) -> usize {
+    spectrogram.first().map_or(0, |row| row.len())
+}
+
+/// Spectral centroid (Hz) of each frame, i.e. the magnitude-weighted mean frequency.
+/// Silent frames have a centroid of 0.
+pub fn spectral_centroid(spectrogram: &[Vec<f32>], sr: u32, n_fft: usize) -> Vec<f32> {
+    let freqs = fft_frequencies(sr, n_fft);
+    (0..n_frames(spectrogram))
+        .map(|t| {
+            let (weighted, total) = spectrogram
+                .iter()
+                .zip(&freqs)
+                .fold((0.0f32, 0.0f32), |(weighted, total), (row, &f)| {
+                    (weighted + f * row[t], total + row[t])
+                });
+            if total > 0.0 { weighted / total } else { 0.0 }
+        })
+        .collect()
+}
+
+/// Spectral bandwidth (Hz) of each frame, i.e. the magnitude-weighted standard deviation of the
+/// frequency around the centroid (librosa's default p=2)
+pub fn spectral_bandwidth(spectrogram: &[Vec<f32>], sr: u32, n_fft: usize) -> Vec<f32> {
+    let freqs = fft_frequencies(sr, n_fft);
+    let centroid = spectral_centroid(spectrogram, sr, n_fft);
+    centroid
+        .iter()
+        .enumerate()
+        .map(|(t, &c)| {
+            let total: f32 = spectrogram.iter().map(|row| row[t]).sum();
+            if total <= 0.0 {
+                return 0.0;
+            }
+            spectrogram
+                .iter()
+                .zip(&freqs)
+                .map(|(row, &f)| row[t] / total * (f - c).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        })
+        .collect()
+}
+
+/// Roll-off frequency (Hz) of each frame: the lowest bin frequency below which `roll_percent`
+/// (e.g. 0.85) of the frame magnitude is concentrated
+pub fn spectral_rolloff(
+    spectrogram: &[Vec<f32>],
+    sr: u32,
+    n_fft: usize,
+    roll_percent: f32,
+) -> Vec<f32> {
+    let freqs = fft_frequencies(sr, n_fft);
+    (0..n_frames(spectrogram))
+        .map(|t| {
+            let total: f32 = spectrogram.iter().map(|row| row[t]).sum();
+            let threshold = roll_percent * total;
+            let mut cumulative = 0.0f32;
+            for (row, &f) in spectrogram.iter().zip(&freqs) {
+                cumulative += row[t];
+                if cumulative >= threshold {
+                    return f;
+                }
+            }
+            freqs.last().copied().unwrap_or(0.0)
+        })
+        .collect()
+}
+
+/// Spectral flatness of each frame: geometric over arithmetic mean of the power spectrum
+/// (1 for white noise, close to 0 for pure tones)
+pub fn spectral_flatness(spectrogram: &[Vec<f32>]) -> Vec<f32> {
+    // Power floor avoiding log(0)
+    let amin = 1e-10f32;
+    let n_bins = spectrogram.len() as f32;
+    (0..n_frames(spectrogram))
+        .map(|t| {
+            let (log_sum, sum) = spectrogram.iter().fold((0.0f32, 0.0f32), |(l, s), row| {
+                let power = (row[t] * row[t]).max(amin);
+                (l + power.ln(), s + power)
+            });
+            (log_sum / n_bins).exp() / (sum / n_bins)
+        })
+        .collect()
+}
+
+/// Root-mean-square energy of each frame, recovered from the magnitude spectrogram through
+/// Parseval's theorem (like librosa's `rms(S=...)`)
+pub fn rms_from_spectrogram(spectrogram: &[Vec<f32>], n_fft: usize) -> Vec<f32> {
+    let last_bin = spectrogram.len().saturating_sub(1);
+    (0..n_frames(spectrogram))
+        .map(|t| {
+            let energy: f32 = spectrogram
+                .iter()
+                .enumerate()
+                .map(|(k, row)| {
+                    let power = row[t] * row[t];
+                    // DC and (for even n_fft) Nyquist bins are not mirrored
+                    if k == 0 || (k == last_bin && n_fft.is_multiple_of(2)) {
+                        0.5 * power
+                    } else {
+                        power
+                    }
+                })
+                .sum();
+            (2.0 * energy / (n_fft * n_fft) as f32).sqrt()
+        })
+        .collect()
+}
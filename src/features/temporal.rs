@@ -0,0 +1,57 @@
+// Descriptors computed directly on the waveform
+
+/// Whether two successive samples lie on different sides of zero (zero counts as positive)
+fn is_crossing(previous: f32, current: f32) -> bool {
+    (previous >= 0.0) != (current >= 0.0)
+}
+
+/// Zero-crossing rate of each frame of `frame_length` samples, `hop_length` apart: the fraction
+/// of successive sample pairs changing sign
+pub fn zero_crossing_rate(audio: &[f32], frame_length: usize, hop_length: usize) -> Vec<f32> {
+    let n_frames = audio.len().saturating_sub(frame_length) / hop_length + 1;
+    (0..n_frames)
+        .map(|i| {
+            let start = i * hop_length;
+            let frame = &audio[start..(start + frame_length).min(audio.len())];
+            let crossings = frame.windows(2).filter(|w| is_crossing(w[0], w[1])).count();
+            crossings as f32 / frame_length as f32
+        })
+        .collect()
+}
+
+/// Running zero-crossing count over audio delivered in chunks (e.g. while streaming a file)
+#[derive(Debug, Clone, Default)]
+pub struct ZeroCrossingCounter {
+    last: Option<f32>,
+    crossings: usize,
+    n_samples: usize,
+}
+
+impl ZeroCrossingCounter {
+    /// Account for the next chunk of audio
+    pub fn push(&mut self, chunk: &[f32]) {
+        for &sample in chunk {
+            if let Some(last) = self.last
+                && is_crossing(last, sample)
+            {
+                self.crossings += 1;
+            }
+            self.last = Some(sample);
+        }
+        self.n_samples += chunk.len();
+    }
+
+    /// Number of sign changes seen so far
+    pub fn crossings(&self) -> usize {
+        self.crossings
+    }
+
+    /// Fraction of successive sample pairs changing sign over all the audio seen so far
+    pub fn rate(&self) -> f32 {
+        if self.n_samples < 2 {
+            0.0
+        } else {
+            self.crossings as f32 / (self.n_samples - 1) as f32
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Pitch-class and tonal centroid features computed frame by frame from a linear-frequency
+// spectrogram in [freq][time] format, following librosa's `chroma_stft` (without tuning
+// estimation) and `tonnetz`.
+
+use crate::spectrogram::stft::SpectrogramType;
+use std::f64::consts::PI;
+
+/// Number of pitch classes (C, C#, ..., B)
+pub const N_CHROMA: usize = 12;
+
+/// Number of tonal centroid dimensions: fifths, minor thirds and major thirds, each as the x
+/// and y coordinates of a circle
+pub const N_TONNETZ: usize = 6;
+
+/// Octave (in octaves above C0 = 16.35 Hz) the chroma filters are centred on, and their
+/// spread: bins far below or above the musical range weigh less
+const CENTER_OCTAVE: f64 = 5.0;
+const OCTAVE_WIDTH: f64 = 2.0;
+
+/// Chroma filter bank ([pitch class][frequency bin]) for an FFT of `n_fft` points at `sr`:
+/// Gaussian bumps around every pitch class of every octave, normalized per bin, weighted by
+/// a Gaussian over the octaves, starting from C
+pub fn chroma_filter_bank(sr: u32, n_fft: usize) -> Vec<Vec<f32>> {
+    let n_chroma = N_CHROMA as f64;
+    // Position of every bin in pitch classes above A0/16, the first (DC) one set an octave
+    // and a half below the second
+    let mut bins: Vec<f64> = (1..n_fft)
+        .map(|k| {
+            let frequency = k as f64 * sr as f64 / n_fft as f64;
+            n_chroma * (frequency / (440.0 / 16.0)).log2()
+        })
+        .collect();
+    let first = bins.first().copied().unwrap_or(0.0) - 1.5 * n_chroma;
+    bins.insert(0, first);
+    let widths: Vec<f64> = bins
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(1.0))
+        .chain(std::iter::once(1.0))
+        .collect();
+
+    let half = (n_chroma / 2.0).round();
+    let mut weights = vec![vec![0.0f64; n_fft]; N_CHROMA];
+    for (k, (&bin, &width)) in bins.iter().zip(&widths).enumerate() {
+        for (c, row) in weights.iter_mut().enumerate() {
+            let distance = (bin - c as f64 + half + 10.0 * n_chroma).rem_euclid(n_chroma) - half;
+            row[k] = (-0.5 * (2.0 * distance / width).powi(2)).exp();
+        }
+        let norm = weights.iter().map(|row| row[k].powi(2)).sum::<f64>().sqrt();
+        let octave = (-0.5 * ((bin / n_chroma - CENTER_OCTAVE) / OCTAVE_WIDTH).powi(2)).exp();
+        for row in weights.iter_mut() {
+            row[k] = if norm > 0.0 { row[k] / norm } else { 0.0 } * octave;
+        }
+    }
+    // Rows from A to rows from C
+    weights.rotate_left(3);
+    weights
+        .iter()
+        .map(|row| row[..n_fft / 2 + 1].iter().map(|&w| w as f32).collect())
+        .collect()
+}
+
+/// Chroma ([pitch class][time]) of a linear-frequency spectrogram computed with `n_fft` at
+/// `sr`: the energy of the frame under each chroma filter, scaled so that the strongest pitch
+/// class of the frame is 1. Silent frames stay 0.
+pub fn chroma(
+    spectrogram: &[Vec<f32>],
+    spectrogram_type: SpectrogramType,
+    sr: u32,
+    n_fft: usize,
+) -> Vec<Vec<f32>> {
+    let filters = chroma_filter_bank(sr, n_fft);
+    let n_frames = spectrogram.first().map_or(0, |row| row.len());
+    let mut chroma = vec![vec![0.0f32; n_frames]; N_CHROMA];
+    for (filter, out) in filters.iter().zip(chroma.iter_mut()) {
+        for (&weight, row) in filter.iter().zip(spectrogram) {
+            if weight == 0.0 {
+                continue;
+            }
+            for (value, &s) in out.iter_mut().zip(row) {
+                // The filters weigh powers
+                let power = match spectrogram_type {
+                    SpectrogramType::Power => s,
+                    SpectrogramType::Magnitude => s * s,
+                };
+                *value += weight * power;
+            }
+        }
+    }
+    for t in 0..n_frames {
+        let peak = chroma
+            .iter()
+            .fold(0.0f32, |peak, row| peak.max(row[t].abs()));
+        if peak > f32::MIN_POSITIVE {
+            for row in chroma.iter_mut() {
+                row[t] /= peak;
+            }
+        }
+    }
+    chroma
+}
+
+/// Tonal centroids ([dimension][time], Harte et al. 2006) of a chromagram: every frame, as a
+/// distribution over the pitch classes, projected on the circles of fifths, minor thirds
+/// and major thirds (radii 1, 1 and 0.5). Silent frames stay 0.
+pub fn tonnetz(chroma: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    // Angle of a pitch class on each circle (in half turns), and the radius of the circle
+    const STEPS: [f64; N_TONNETZ] = [
+        7.0 / 6.0,
+        7.0 / 6.0,
+        3.0 / 2.0,
+        3.0 / 2.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+    ];
+    const RADII: [f64; N_TONNETZ] = [1.0, 1.0, 1.0, 1.0, 0.5, 0.5];
+    let n_chroma = chroma.len();
+    let basis: Vec<Vec<f64>> = (0..N_TONNETZ)
+        .map(|d| {
+            (0..n_chroma)
+                .map(|c| {
+                    let position = c as f64 * N_CHROMA as f64 / n_chroma as f64;
+                    // Even dimensions are the sines (x), odd ones the cosines (y)
+                    let phase = if d % 2 == 0 { 0.5 } else { 0.0 };
+                    RADII[d] * (PI * (STEPS[d] * position - phase)).cos()
+                })
+                .collect()
+        })
+        .collect();
+
+    let n_frames = chroma.first().map_or(0, |row| row.len());
+    let mut tonnetz = vec![vec![0.0f32; n_frames]; N_TONNETZ];
+    for t in 0..n_frames {
+        let total: f64 = chroma.iter().map(|row| row[t].abs() as f64).sum();
+        if total <= f32::MIN_POSITIVE as f64 {
+            continue;
+        }
+        for (row, weights) in tonnetz.iter_mut().zip(&basis) {
+            row[t] = (weights
+                .iter()
+                .zip(chroma)
+                .map(|(w, pitch)| w * pitch[t] as f64)
+                .sum::<f64>()
+                / total) as f32;
+        }
+    }
+    tonnetz
+}
@@ -0,0 +1,144 @@
+// Filtering of the waveform before the STFT, to suppress mains hum and out-of-band noise in the
+// spectrograms: a band-pass (4th-order Butterworth high-pass and low-pass sections) and notches
+// at given frequencies, all built as biquads from the formulas of R. Bristow-Johnson's "Audio
+// EQ Cookbook". Biquads keep their state from one call to the next, so audio can be filtered
+// chunk by chunk as it is captured.
+
+use crate::error::{Result, SpectrsError};
+use std::f64::consts::PI;
+
+/// Quality factor of the notches: a -3 dB width of 1/30 of the notch frequency
+pub const NOTCH_Q: f64 = 30.0;
+
+/// Quality factors of the two sections of a 4th-order Butterworth filter, 1 / (2 cos(π/8))
+/// and 1 / (2 cos(3π/8))
+const BUTTERWORTH_Q: [f64; 2] = [0.541_196_100_146_197, 1.306_562_964_876_376_5];
+
+/// Second-order IIR filter section (transposed direct form II)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    /// Section from cookbook coefficients, normalized by a0
+    pub(crate) fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+            state: [0.0; 2],
+        }
+    }
+
+    /// Angular frequency and alpha of the cookbook formulas
+    fn omega(sr: u32, hz: f64, q: f64) -> (f64, f64) {
+        let w0 = 2.0 * PI * hz / sr as f64;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    /// Low-pass section with cut-off `hz`
+    pub fn lowpass(sr: u32, hz: f64, q: f64) -> Self {
+        let (cos, alpha) = Self::omega(sr, hz, q);
+        let b1 = 1.0 - cos;
+        Self::new(
+            [b1 / 2.0, b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// High-pass section with cut-off `hz`
+    pub fn highpass(sr: u32, hz: f64, q: f64) -> Self {
+        let (cos, alpha) = Self::omega(sr, hz, q);
+        let b1 = 1.0 + cos;
+        Self::new(
+            [b1 / 2.0, -b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Notch section removing `hz`
+    pub fn notch(sr: u32, hz: f64, q: f64) -> Self {
+        let (cos, alpha) = Self::omega(sr, hz, q);
+        Self::new(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Filter samples in place, continuing from the previous call
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let ([b0, b1, b2], [a1, a2]) = (self.b, self.a);
+        for s in samples.iter_mut() {
+            let x = *s as f64;
+            let y = b0 * x + self.state[0];
+            self.state[0] = b1 * x - a1 * y + self.state[1];
+            self.state[1] = b2 * x - a2 * y;
+            *s = y as f32;
+        }
+    }
+}
+
+/// Band-pass and notches applied to the waveform
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioFilter {
+    /// Lower and upper cut-off frequencies (Hz) of the band-pass
+    pub bandpass: Option<(f32, f32)>,
+    /// Frequencies (Hz) removed by notches, e.g. 50 Hz mains hum and its harmonics
+    pub notches: Vec<f32>,
+}
+
+impl AudioFilter {
+    /// Whether the filter leaves the audio unchanged
+    pub fn is_empty(&self) -> bool {
+        self.bandpass.is_none() && self.notches.is_empty()
+    }
+
+    /// Check that every frequency is below the Nyquist frequency at `sr`
+    pub fn validate_for_sample_rate(&self, sr: u32) -> Result<()> {
+        let nyquist = sr as f32 / 2.0;
+        if let Some((low, high)) = self.bandpass
+            && !(0.0 < low && low < high && high < nyquist)
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Band-pass {} Hz to {} Hz must satisfy 0 < low < high < {} Hz (Nyquist)",
+                low, high, nyquist
+            )));
+        }
+        if let Some(&hz) = self.notches.iter().find(|&&hz| !(0.0 < hz && hz < nyquist)) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Notch frequency {} Hz must be between 0 and {} Hz (Nyquist)",
+                hz, nyquist
+            )));
+        }
+        Ok(())
+    }
+
+    /// Biquad sections of the filter at `sr`, applied in order
+    pub fn biquads(&self, sr: u32) -> Result<Vec<Biquad>> {
+        self.validate_for_sample_rate(sr)?;
+        let mut biquads = Vec::new();
+        if let Some((low, high)) = self.bandpass {
+            // 24 dB per octave beyond each edge
+            for q in BUTTERWORTH_Q {
+                biquads.push(Biquad::highpass(sr, low as f64, q));
+                biquads.push(Biquad::lowpass(sr, high as f64, q));
+            }
+        }
+        biquads.extend(
+            self.notches
+                .iter()
+                .map(|&hz| Biquad::notch(sr, hz as f64, NOTCH_Q)),
+        );
+        Ok(biquads)
+    }
+
+    /// Filter mono audio at `sr` in place
+    pub fn apply(&self, samples: &mut [f32], sr: u32) -> Result<()> {
+        for mut biquad in self.biquads(sr)? {
+            biquad.process(samples);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Time-interval labels of a recording (e.g. the events of a dataset), read from the label
+//! tracks exported by Audacity or from JSON, to be drawn over spectrogram images.
+//!
+//! Audacity label tracks are tab-separated lines of start, end (seconds) and label, each
+//! optionally followed by a line starting with a backslash giving the frequency range of the
+//! label (fields shown here separated by spaces are separated by tabs):
+//!
+//! ```text
+//! 1.250000    3.500000    dog bark
+//! \           500.000000  4000.000000
+//! ```
+//!
+//! JSON files hold an array of labels (`begin` is accepted for `start`, `value` and `text` for
+//! `label`, so that exports of other tools such as ELAN only need light reshaping):
+//!
+//! ```json
+//! [{"start": 1.25, "end": 3.5, "label": "dog bark", "f_min": 500, "f_max": 4000}]
+//! ```
+
+use crate::error::{Result, SpectrsError};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Label of a time interval of a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Start of the interval (seconds from the start of the recording)
+    #[serde(alias = "begin")]
+    pub start: f32,
+    /// End of the interval (seconds)
+    pub end: f32,
+    #[serde(default, alias = "value", alias = "text")]
+    pub label: String,
+    /// Frequency range of the label (Hz), if it does not span every frequency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f_min: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f_max: Option<f32>,
+}
+
+impl Annotation {
+    /// Check that the interval and the frequency range are ordered and non-negative
+    pub fn validate(&self) -> Result<()> {
+        let valid_time = self.start.is_finite()
+            && self.end.is_finite()
+            && self.start >= 0.0
+            && self.end >= self.start;
+        if !valid_time {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid interval [{}s, {}s] of label '{}'",
+                self.start, self.end, self.label
+            )));
+        }
+        let valid_frequency = match (self.f_min, self.f_max) {
+            (Some(f_min), Some(f_max)) => f_min >= 0.0 && f_max >= f_min,
+            (f_min, f_max) => f_min.or(f_max).is_none_or(|f| f >= 0.0),
+        };
+        if !valid_frequency {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid frequency range of label '{}'",
+                self.label
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a JSON array of labels
+pub fn parse_annotations_json(text: &str) -> Result<Vec<Annotation>> {
+    let annotations: Vec<Annotation> = serde_json::from_str(text)
+        .map_err(|e| SpectrsError::invalid_data("Invalid JSON annotations", e))?;
+    for annotation in &annotations {
+        annotation.validate()?;
+    }
+    Ok(annotations)
+}
+
+/// Parse an Audacity label track (tab-separated start, end and label per line)
+pub fn parse_audacity_labels(text: &str) -> Result<Vec<Annotation>> {
+    let invalid = |number: usize, line: &str| SpectrsError::InvalidData {
+        message: format!("Invalid label on line {}: '{}'", number + 1, line),
+        source: None,
+    };
+    let mut annotations: Vec<Annotation> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let first = fields.next().unwrap_or_default();
+        let mut number_field = || {
+            fields
+                .next()
+                .and_then(|field| field.trim().parse::<f32>().ok())
+                .ok_or_else(|| invalid(number, line))
+        };
+        if first.trim() == "\\" {
+            // Frequency range of the previous label
+            let (f_min, f_max) = (number_field()?, number_field()?);
+            let annotation = annotations
+                .last_mut()
+                .ok_or_else(|| invalid(number, line))?;
+            annotation.f_min = Some(f_min);
+            annotation.f_max = Some(f_max);
+            continue;
+        }
+        let start = first
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| invalid(number, line))?;
+        let end = number_field()?;
+        let label = fields.collect::<Vec<_>>().join("\t");
+        annotations.push(Annotation {
+            start,
+            end,
+            label,
+            f_min: None,
+            f_max: None,
+        });
+    }
+    for annotation in &annotations {
+        annotation.validate()?;
+    }
+    Ok(annotations)
+}
+
+/// Read labels from a file: JSON (.json) or an Audacity label track (any other extension,
+/// usually .txt)
+#[cfg(feature = "fs")]
+pub fn load_annotations(path: &Path) -> Result<Vec<Annotation>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| SpectrsError::io(format!("Failed to read {}", path.display()), e))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let annotations = if is_json {
+        parse_annotations_json(&text)
+    } else {
+        parse_audacity_labels(&text)
+    };
+    annotations.map_err(|e| e.context(format!("In {}", path.display())))
+}
+
+/// Format labels as an Audacity label track, with a frequency range line after the labels that
+/// have both bounds
+pub fn format_audacity_labels(annotations: &[Annotation]) -> String {
+    let mut text = String::new();
+    for annotation in annotations {
+        text.push_str(&format!(
+            "{:.6}\t{:.6}\t{}\n",
+            annotation.start, annotation.end, annotation.label
+        ));
+        if let (Some(f_min), Some(f_max)) = (annotation.f_min, annotation.f_max) {
+            text.push_str(&format!("\\\t{:.6}\t{:.6}\n", f_min, f_max));
+        }
+    }
+    text
+}
+
+/// Write labels to a file readable by `load_annotations`: JSON (.json) or an Audacity label
+/// track (any other extension, usually .txt)
+#[cfg(feature = "fs")]
+pub fn save_annotations(annotations: &[Annotation], path: &Path) -> Result<()> {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let text = if is_json {
+        serde_json::to_string(annotations)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?
+    } else {
+        format_audacity_labels(annotations)
+    };
+    crate::io::atomic::write_atomically(path, |partial| {
+        std::fs::write(partial, text)
+            .map_err(|e| SpectrsError::io(format!("Failed to write {}", path.display()), e))
+    })
+}
@@ -0,0 +1,312 @@
+//! Zip and tar archives of audio files as inputs (enabled with the `archive` feature), since
+//! large corpora are usually distributed that way.
+//!
+//! Members are read into memory one at a time, without extracting the archive to disk. Zip
+//! archives and plain tar files are read at the offset of each member. Gzipped tar files
+//! (.tar.gz, .tgz) cannot be read at an offset: they are decompressed from the start by
+//! streams that only move forward, kept open between reads so that members read in order (as
+//! the threads of a batch do) decompress the archive about once per thread.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::audio::{
+    DownmixMode, SalvageReport, read_audio_mono_from_reader, read_audio_mono_lenient_from_reader,
+    read_audio_segment_from_reader,
+};
+use crate::io::source::AudioSource;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Format of an archive, by its extension (see `io::source::is_archive_path`)
+fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else {
+        None
+    }
+}
+
+/// File of an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveMember {
+    /// Path of the file inside the archive
+    pub path: PathBuf,
+    /// Size of the file (bytes, uncompressed)
+    pub size: u64,
+    /// Index of the file in a zip archive, offset of its data in a (decompressed) tar file
+    location: u64,
+}
+
+/// Decompressed stream of a gzipped tar file, and its position
+struct TarStream {
+    reader: MultiGzDecoder<BufReader<File>>,
+    position: u64,
+}
+
+/// Archive of audio files, whose members can be read from several threads at once
+pub struct Archive {
+    path: PathBuf,
+    format: ArchiveFormat,
+    members: Vec<ArchiveMember>,
+    /// Opened zip archives not in use
+    zips: Mutex<Vec<zip::ZipArchive<BufReader<File>>>>,
+    /// Decompressed streams not in use
+    streams: Mutex<Vec<TarStream>>,
+}
+
+impl std::fmt::Debug for Archive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archive")
+            .field("path", &self.path)
+            .field("members", &self.members.len())
+            .finish()
+    }
+}
+
+impl Archive {
+    /// Open the archive at `path` and list its files, in the order of their paths. Directories,
+    /// links, members with unsafe paths (absolute or with `..`) and the resource forks of macOS
+    /// (__MACOSX/) are left out
+    pub fn open(path: &Path) -> Result<Self> {
+        let format = archive_format(path).ok_or_else(|| {
+            SpectrsError::UnsupportedFormat(format!(
+                "{} is not a .zip, .tar, .tar.gz or .tgz archive",
+                path.display()
+            ))
+        })?;
+        let open_error = |e| SpectrsError::io(format!("Failed to open {}", path.display()), e);
+        let file = BufReader::new(File::open(path).map_err(open_error)?);
+        let list_error = |e: std::io::Error| {
+            SpectrsError::invalid_data(format!("Failed to list archive {}", path.display()), e)
+        };
+
+        let mut members = Vec::new();
+        match format {
+            ArchiveFormat::Zip => {
+                let mut archive = zip::ZipArchive::new(file).map_err(|e| {
+                    SpectrsError::invalid_data(format!("Invalid zip archive {}", path.display()), e)
+                })?;
+                for index in 0..archive.len() {
+                    let file = archive.by_index_raw(index).map_err(|e| {
+                        SpectrsError::invalid_data(
+                            format!("Invalid zip archive {}", path.display()),
+                            e,
+                        )
+                    })?;
+                    if let (true, Some(member_path)) = (file.is_file(), file.enclosed_name()) {
+                        members.push(ArchiveMember {
+                            path: member_path,
+                            size: file.size(),
+                            location: index as u64,
+                        });
+                    }
+                }
+            }
+            ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+                let reader: Box<dyn Read> = match format {
+                    ArchiveFormat::TarGz => Box::new(MultiGzDecoder::new(file)),
+                    _ => Box::new(file),
+                };
+                let mut archive = tar::Archive::new(reader);
+                for entry in archive.entries().map_err(list_error)? {
+                    let entry = entry.map_err(list_error)?;
+                    if !entry.header().entry_type().is_file() {
+                        continue;
+                    }
+                    let member_path = entry.path().map_err(list_error)?;
+                    if let Some(member_path) = safe_path(&member_path) {
+                        members.push(ArchiveMember {
+                            path: member_path,
+                            size: entry.size(),
+                            location: entry.raw_file_position(),
+                        });
+                    }
+                }
+            }
+        }
+        members.retain(|member| !member.path.starts_with("__MACOSX"));
+        members.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            members,
+            zips: Mutex::new(Vec::new()),
+            streams: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Path of the archive
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Files of the archive, in the order of their paths
+    pub fn members(&self) -> &[ArchiveMember] {
+        &self.members
+    }
+
+    /// Read a file of the archive into memory
+    pub fn read(&self, member: &ArchiveMember) -> Result<Vec<u8>> {
+        let name = format!("{}/{}", self.path.display(), member.path.display());
+        let read_error = |e| SpectrsError::io(format!("Failed to read {}", name), e);
+        let mut bytes = Vec::with_capacity(member.size as usize);
+        match self.format {
+            ArchiveFormat::Zip => {
+                let reused = self
+                    .zips
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .pop();
+                let mut archive = match reused {
+                    Some(archive) => archive,
+                    None => {
+                        let file = File::open(&self.path).map_err(read_error)?;
+                        zip::ZipArchive::new(BufReader::new(file)).map_err(|e| {
+                            SpectrsError::invalid_data(format!("Failed to read {}", name), e)
+                        })?
+                    }
+                };
+                archive
+                    .by_index(member.location as usize)
+                    .map_err(|e| SpectrsError::invalid_data(format!("Failed to read {}", name), e))?
+                    .read_to_end(&mut bytes)
+                    .map_err(read_error)?;
+                self.zips
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(archive);
+            }
+            ArchiveFormat::Tar => {
+                let mut file = File::open(&self.path).map_err(read_error)?;
+                file.seek(SeekFrom::Start(member.location))
+                    .map_err(read_error)?;
+                file.take(member.size)
+                    .read_to_end(&mut bytes)
+                    .map_err(read_error)?;
+            }
+            ArchiveFormat::TarGz => {
+                let mut stream = match self.take_stream(member.location) {
+                    Some(stream) => stream,
+                    None => TarStream {
+                        reader: MultiGzDecoder::new(BufReader::new(
+                            File::open(&self.path).map_err(read_error)?,
+                        )),
+                        position: 0,
+                    },
+                };
+                let skip = member.location - stream.position;
+                std::io::copy(&mut (&mut stream.reader).take(skip), &mut std::io::sink())
+                    .map_err(read_error)?;
+                (&mut stream.reader)
+                    .take(member.size)
+                    .read_to_end(&mut bytes)
+                    .map_err(read_error)?;
+                stream.position = member.location + bytes.len() as u64;
+                self.streams
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(stream);
+            }
+        }
+        if (bytes.len() as u64) < member.size {
+            return Err(SpectrsError::InvalidData {
+                message: format!("{} is truncated", name),
+                source: None,
+            });
+        }
+        Ok(bytes)
+    }
+
+    /// Stream closest to `offset` without being past it
+    fn take_stream(&self, offset: u64) -> Option<TarStream> {
+        let mut streams = self.streams.lock().unwrap_or_else(PoisonError::into_inner);
+        let (index, _) = streams
+            .iter()
+            .enumerate()
+            .filter(|(_, stream)| stream.position <= offset)
+            .max_by_key(|(_, stream)| stream.position)?;
+        Some(streams.swap_remove(index))
+    }
+}
+
+/// Path of a member made of normal components only, None if it could escape the archive
+fn safe_path(path: &Path) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!safe.as_os_str().is_empty()).then_some(safe)
+}
+
+/// WAV file inside an archive. The file is read into memory and decoded like a buffer.
+#[derive(Debug, Clone)]
+pub struct ArchiveSource {
+    pub archive: Arc<Archive>,
+    pub member: ArchiveMember,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+}
+
+impl ArchiveSource {
+    pub fn new(archive: Arc<Archive>, member: ArchiveMember) -> Self {
+        Self {
+            archive,
+            member,
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// Downmix the channels of the file this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+}
+
+impl AudioSource for ArchiveSource {
+    fn name(&self) -> String {
+        format!(
+            "{}/{}",
+            self.archive.path.display(),
+            self.member.path.display()
+        )
+    }
+
+    /// Path of the file inside the archive, so that outputs keep the structure of the archive
+    fn relative_path(&self) -> PathBuf {
+        self.member.path.clone()
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        let bytes = self.archive.read(&self.member)?;
+        read_audio_mono_from_reader(Cursor::new(bytes), self.downmix)
+    }
+
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        let bytes = self.archive.read(&self.member)?;
+        read_audio_segment_from_reader(Cursor::new(bytes), start, end, self.downmix)
+    }
+
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        let bytes = self.archive.read(&self.member)?;
+        read_audio_mono_lenient_from_reader(Cursor::new(bytes), self.downmix)
+    }
+}
@@ -0,0 +1,290 @@
+//! Spectrograms as Arrow record batches (enabled with the `arrow` feature), so that Rust and
+//! Python pipelines (arrow-rs, DataFusion, pyarrow, polars) can consume them without
+//! serializing them or going through intermediate files.
+//!
+//! Every spectrogram is one row, with the columns of the Parquet datasets of `io::dataset`:
+//! the path and label of its source, its sample rate, its shape and its values flattened row
+//! by row ([freq][time], so value `t` of band `k` is at `k * n_frames + t`) in a single Arrow
+//! buffer. `IpcWriter` streams the rows to an Arrow IPC file (Feather v2), which readers can
+//! memory-map instead of decoding it, with the parameters as JSON in its metadata under
+//! `spectrs.params`.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::dataset::{Dataset, DatasetEntry, PARAMS_KEY, VERSION_KEY};
+use crate::spectrogram::params::SpectrogramParams;
+use arrow_array::builder::{
+    ArrayBuilder, Float32Builder, ListBuilder, StringBuilder, UInt32Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Rows buffered by `IpcWriter` before they are written as a record batch
+const BATCH_SIZE: usize = 64;
+
+/// Extensions of Arrow IPC files
+pub const IPC_EXTENSIONS: [&str; 3] = ["arrow", "feather", "ipc"];
+
+/// Whether `path` names an Arrow IPC file (.arrow, .feather or .ipc)
+pub fn is_ipc_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IPC_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Columns of the record batches of spectrograms
+pub fn spectrogram_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("label", DataType::Utf8, false),
+        Field::new("sample_rate", DataType::UInt32, false),
+        Field::new("n_bins", DataType::UInt32, false),
+        Field::new("n_frames", DataType::UInt32, false),
+        Field::new(
+            "values",
+            DataType::List(Arc::new(Field::new_list_field(DataType::Float32, true))),
+            false,
+        ),
+    ])
+}
+
+/// Builder of a record batch of spectrograms, one row per spectrogram
+pub struct SpectrogramBatchBuilder {
+    paths: StringBuilder,
+    labels: StringBuilder,
+    sample_rates: UInt32Builder,
+    n_bins: UInt32Builder,
+    n_frames: UInt32Builder,
+    values: ListBuilder<Float32Builder>,
+}
+
+impl Default for SpectrogramBatchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectrogramBatchBuilder {
+    pub fn new() -> Self {
+        Self {
+            paths: StringBuilder::new(),
+            labels: StringBuilder::new(),
+            sample_rates: UInt32Builder::new(),
+            n_bins: UInt32Builder::new(),
+            n_frames: UInt32Builder::new(),
+            values: ListBuilder::new(Float32Builder::new()),
+        }
+    }
+
+    /// Add the [freq][time] spectrogram of a source as a new row
+    pub fn append(
+        &mut self,
+        path: &str,
+        label: &str,
+        sample_rate: u32,
+        spectrogram: &[Vec<f32>],
+    ) -> Result<()> {
+        let n_frames = spectrogram.first().map_or(0, Vec::len);
+        if let Some(row) = spectrogram.iter().find(|row| row.len() != n_frames) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram rows must all have {} frames, got one of {}",
+                n_frames,
+                row.len()
+            )));
+        }
+        self.paths.append_value(path);
+        self.labels.append_value(label);
+        self.sample_rates.append_value(sample_rate);
+        self.n_bins.append_value(spectrogram.len() as u32);
+        self.n_frames.append_value(n_frames as u32);
+        for row in spectrogram {
+            self.values.values().append_slice(row);
+        }
+        self.values.append(true);
+        Ok(())
+    }
+
+    /// Number of rows added since the last batch
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the rows added so far as a record batch, leaving the builder empty
+    pub fn finish(&mut self) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.paths.finish()),
+            Arc::new(self.labels.finish()),
+            Arc::new(self.sample_rates.finish()),
+            Arc::new(self.n_bins.finish()),
+            Arc::new(self.n_frames.finish()),
+            Arc::new(self.values.finish()),
+        ];
+        RecordBatch::try_new(Arc::new(spectrogram_schema()), columns)
+            .map_err(|e| SpectrsError::dataset("Failed to build a record batch", e))
+    }
+}
+
+/// Read the spectrograms of a record batch with the columns of `spectrogram_schema`
+pub fn batch_entries(batch: &RecordBatch) -> Result<Vec<DatasetEntry>> {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, UInt32Type};
+
+    let missing = |name: &str| SpectrsError::InvalidData {
+        message: format!("Record batch has no valid '{}' column", name),
+        source: None,
+    };
+    let strings = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|column| column.as_string_opt::<i32>())
+            .ok_or_else(|| missing(name))
+    };
+    let numbers = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|column| column.as_primitive_opt::<UInt32Type>())
+            .ok_or_else(|| missing(name))
+    };
+    let (paths, labels) = (strings("path")?, strings("label")?);
+    let (sample_rates, n_frames) = (numbers("sample_rate")?, numbers("n_frames")?);
+    let values = batch
+        .column_by_name("values")
+        .and_then(|column| column.as_list_opt::<i32>())
+        .ok_or_else(|| missing("values"))?;
+
+    let mut entries = Vec::with_capacity(batch.num_rows());
+    for i in 0..batch.num_rows() {
+        let flat = values.value(i);
+        let flat = flat
+            .as_primitive_opt::<Float32Type>()
+            .ok_or_else(|| missing("values"))?
+            .values();
+        let width = n_frames.value(i) as usize;
+        let spectrogram = if width == 0 {
+            Vec::new()
+        } else {
+            flat.chunks(width).map(<[f32]>::to_vec).collect()
+        };
+        entries.push(DatasetEntry {
+            path: paths.value(i).to_string(),
+            label: labels.value(i).to_string(),
+            sample_rate: sample_rates.value(i),
+            spectrogram,
+        });
+    }
+    Ok(entries)
+}
+
+/// Writer appending spectrograms to an Arrow IPC file
+pub struct IpcWriter<W: Write> {
+    writer: arrow_ipc::writer::FileWriter<W>,
+    /// Rows not written yet
+    pending: SpectrogramBatchBuilder,
+}
+
+impl IpcWriter<std::io::BufWriter<std::fs::File>> {
+    /// Create (or truncate) the Arrow IPC file at `path`, recording `params` in its metadata
+    pub fn create(path: &Path, params: &SpectrogramParams) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| SpectrsError::io(format!("Failed to create {}", path.display()), e))?;
+        Self::new(std::io::BufWriter::new(file), params)
+    }
+}
+
+impl<W: Write> IpcWriter<W> {
+    /// Start an Arrow IPC file in `writer`, recording `params` in its metadata
+    pub fn new(writer: W, params: &SpectrogramParams) -> Result<Self> {
+        let params = serde_json::to_string(params)
+            .map_err(|e| SpectrsError::dataset("Failed to serialize parameters", e))?;
+        let mut writer = arrow_ipc::writer::FileWriter::try_new(writer, &spectrogram_schema())
+            .map_err(|e| SpectrsError::dataset("Failed to start the Arrow file", e))?;
+        writer.write_metadata(PARAMS_KEY, params);
+        writer.write_metadata(VERSION_KEY, env!("CARGO_PKG_VERSION"));
+        Ok(Self {
+            writer,
+            pending: SpectrogramBatchBuilder::new(),
+        })
+    }
+
+    /// Append the [freq][time] spectrogram of a source as a new row
+    pub fn append(
+        &mut self,
+        path: &str,
+        label: &str,
+        sample_rate: u32,
+        spectrogram: &[Vec<f32>],
+    ) -> Result<()> {
+        self.pending.append(path, label, sample_rate, spectrogram)?;
+        if self.pending.len() >= BATCH_SIZE {
+            self.write_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Write a record batch built with `SpectrogramBatchBuilder`, after the rows appended so far
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        if batch.schema().fields() != spectrogram_schema().fields() {
+            return Err(SpectrsError::InvalidParams(
+                "Record batch does not have the columns of spectrogram_schema".to_string(),
+            ));
+        }
+        self.write_pending()?;
+        self.writer
+            .write(batch)
+            .map_err(|e| SpectrsError::dataset("Failed to write to the Arrow file", e))
+    }
+
+    fn write_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = self.pending.finish()?;
+        self.writer
+            .write(&batch)
+            .map_err(|e| SpectrsError::dataset("Failed to write to the Arrow file", e))
+    }
+
+    /// Write the remaining rows and the footer of the file, returning the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.write_pending()?;
+        self.writer
+            .finish()
+            .map_err(|e| SpectrsError::dataset("Failed to finish the Arrow file", e))?;
+        self.writer
+            .into_inner()
+            .map_err(|e| SpectrsError::dataset("Failed to finish the Arrow file", e))
+    }
+}
+
+/// Read a whole Arrow IPC file written by `IpcWriter`
+pub fn load_ipc(path: &Path) -> Result<Dataset> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", path.display()), e))?;
+    read_ipc(std::io::BufReader::new(file))
+        .map_err(|e| e.context(format!("Arrow file {}", path.display())))
+}
+
+/// Read a whole Arrow IPC file written by `IpcWriter` from a reader
+pub fn read_ipc<R: Read + Seek>(reader: R) -> Result<Dataset> {
+    let reader = arrow_ipc::reader::FileReader::try_new(reader, None)
+        .map_err(|e| SpectrsError::invalid_data("Failed to read the Arrow file", e))?;
+    let params = reader
+        .custom_metadata()
+        .get(PARAMS_KEY)
+        .map(|params| serde_json::from_str(params))
+        .transpose()
+        .map_err(|e| SpectrsError::invalid_data("Invalid parameters in the Arrow file", e))?;
+    let mut entries = Vec::new();
+    for batch in reader {
+        let batch =
+            batch.map_err(|e| SpectrsError::invalid_data("Failed to read the Arrow file", e))?;
+        entries.extend(batch_entries(&batch)?);
+    }
+    Ok(Dataset { params, entries })
+}
@@ -0,0 +1,49 @@
+//! Outputs written through a temporary file renamed over them once complete, so that a run
+//! stopped while writing (Ctrl-C, crash, full disk) leaves the previous output or none, never a
+//! truncated file that a later run would take for a complete one.
+//!
+//! The temporary file is hidden in the directory of the output, so that the rename does not
+//! cross file systems, and named after the process and a counter, so that concurrent writers of
+//! the same output do not share it.
+
+use crate::error::{Result, SpectrsError};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of temporary files created so far by this process
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary path of the output `path`: `dir/.name.<pid>-<n>.partial`
+pub fn partial_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(
+        ".{}.{}-{}.partial",
+        name,
+        std::process::id(),
+        WRITTEN.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Create the file at `path` by calling `write` with a temporary path, then renaming it over
+/// `path`. The directory of the output is created if needed; the temporary file is removed if
+/// `write` fails.
+pub fn write_atomically<T>(path: &Path, write: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            SpectrsError::io(
+                format!("Failed to create directory: {}", parent.display()),
+                e,
+            )
+        })?;
+    }
+    let partial = partial_path(path);
+    let written = write(&partial).and_then(|value| {
+        std::fs::rename(&partial, path)
+            .map_err(|e| SpectrsError::io(format!("Failed to write {}", path.display()), e))?;
+        Ok(value)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    written
+}
@@ -1,78 +1,1515 @@
-use anyhow::{Context, Result};
-use hound::WavReader;
-use rubato::{FftFixedIn, Resampler};
+use crate::cancel::CancellationToken;
+use crate::error::{Result, SpectrsError};
+use hound::{SampleFormat, WavReader};
+#[cfg(feature = "fs")]
+use hound::{WavSpec, WavWriter};
+use rubato::{
+    FftFixedIn, Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    WindowFunction,
+};
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufReader;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+#[cfg(feature = "fs")]
 use std::path::Path;
 
-/// Read audio file from file path and convert to mono by averaging left and right channel
+/// How multichannel audio is reduced to the mono signal spectrograms are computed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownmixMode {
+    /// Average of all channels
+    #[default]
+    Average,
+    /// First channel only (the left channel of stereo audio)
+    First,
+    /// A single channel, by index (starting from 0)
+    Channel(usize),
+    /// ITU-R BS.775 downmix of 5.1 audio (L, R, C, LFE, Ls, Rs in WAV order): average of the
+    /// stereo downmix Lo = L + C/√2 + Ls/√2 and Ro = R + C/√2 + Rs/√2. The LFE is dropped.
+    Itu775,
+}
+
+impl DownmixMode {
+    /// Check that audio with the given number of channels can be downmixed this way
+    pub fn check(self, channels: usize) -> Result<()> {
+        if channels == 0 {
+            return Err(SpectrsError::UnsupportedFormat(
+                "Audio has no channels".to_string(),
+            ));
+        }
+        match self {
+            DownmixMode::Channel(channel) if channel >= channels => {
+                Err(SpectrsError::InvalidParams(format!(
+                    "Cannot select channel {} of audio with {} channels (channels start from 0)",
+                    channel, channels
+                )))
+            }
+            DownmixMode::Itu775 if channels != 6 => Err(SpectrsError::InvalidParams(format!(
+                "The ITU-R BS.775 downmix requires 5.1 audio (6 channels), got {} channels",
+                channels
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Downmix one frame (one sample per channel, already checked with `check`)
+    fn mix<T: Copy + Into<f64>>(self, frame: &[T]) -> f64 {
+        let sample = |channel: usize| frame[channel].into();
+        match self {
+            DownmixMode::Average => {
+                frame.iter().map(|&s| s.into()).sum::<f64>() / frame.len() as f64
+            }
+            DownmixMode::First => sample(0),
+            DownmixMode::Channel(channel) => sample(channel),
+            DownmixMode::Itu775 => {
+                let surround = std::f64::consts::FRAC_1_SQRT_2;
+                (sample(0) + sample(1)) / 2.0
+                    + surround * sample(2)
+                    + surround * (sample(4) + sample(5)) / 2.0
+            }
+        }
+    }
+}
+
+/// Read audio file from file path and convert to mono by averaging all channels
+#[cfg(feature = "fs")]
 pub fn read_audio_file_mono(audio_file_path: &Path) -> Result<(Vec<f32>, u32)> {
     // Open the WAV file
+    let file = File::open(audio_file_path)
+        .map_err(|e| SpectrsError::io("Failed to open audio file", e))?;
     let mut reader =
-        WavReader::open(audio_file_path).with_context(|| "Failed to open audio file")?;
+        WavStream::new(BufReader::new(file)).map_err(|e| e.context("Failed to open audio file"))?;
 
     // Extract info from file
-    let spec = reader.spec();
-    let sr = spec.sample_rate;
-    let channels = spec.channels as usize;
-    let bits_per_sample = spec.bits_per_sample;
+    let sr = reader.sample_rate();
+
+    // Read every sample in the file
+    let samples = reader.read_mono(None, DownmixMode::Average)?;
+
+    Ok((samples, sr))
+}
+
+/// Read only the [start, end) portion (in seconds) of an audio file and convert to mono.
+/// The reader seeks straight to `start` so the preceding audio is never decoded.
+/// If `end` is None (or past the end of the file) the segment extends to the end of the file.
+#[cfg(feature = "fs")]
+pub fn read_audio_segment(
+    audio_file_path: &Path,
+    start: f32,
+    end: Option<f32>,
+) -> Result<(Vec<f32>, u32)> {
+    // Open the WAV file
+    let reader = BufReader::new(
+        File::open(audio_file_path)
+            .map_err(|e| SpectrsError::io("Failed to open audio file", e))?,
+    );
+
+    read_audio_segment_from_reader(reader, start, end, DownmixMode::Average)
+}
+
+/// Same as `read_audio_segment`, but decoding WAV data from any seekable reader
+/// (e.g. an in-memory `Cursor<Vec<u8>>`) instead of a file path, downmixed as requested
+pub fn read_audio_segment_from_reader<R: Read + Seek>(
+    reader: R,
+    start: f32,
+    end: Option<f32>,
+    downmix: DownmixMode,
+) -> Result<(Vec<f32>, u32)> {
+    let mut reader = AudioReader::new(reader)?.with_downmix(downmix)?;
+
+    // Extract info from file
+    let sr = reader.sample_rate();
+    let (start_frame, end_frame) = segment_frames(sr, reader.n_frames() as u32, start, end)?;
+
+    // Jump to the first requested frame without decoding what comes before
+    reader.seek_frame(start_frame)?;
+
+    let samples = reader.read_frames((end_frame - start_frame) as usize)?;
+
+    Ok((samples, sr))
+}
+
+/// Validate a [start, end) segment (in seconds) and convert it to frame indices, clamping the
+/// end to the duration of the file
+fn segment_frames(
+    sr: u32,
+    duration_frames: u32,
+    start: f32,
+    end: Option<f32>,
+) -> Result<(u32, u32)> {
+    if start < 0.0 {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Start time must be non-negative, got {}s",
+            start
+        )));
+    }
+    if let Some(end) = end
+        && end <= start
+    {
+        return Err(SpectrsError::InvalidParams(format!(
+            "End time ({}s) must be after start time ({}s)",
+            end, start
+        )));
+    }
+
+    // Convert times to frame indices
+    // In f64: f32 products lose whole samples past a few minutes
+    let start_frame = (start as f64 * sr as f64).round() as u32;
+    if start_frame >= duration_frames {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Start time {}s is beyond the end of the file ({}s)",
+            start,
+            duration_frames as f32 / sr as f32
+        )));
+    }
+    let end_frame = end
+        .map(|e| ((e as f64 * sr as f64).round() as u32).min(duration_frames))
+        .unwrap_or(duration_frames);
+
+    Ok((start_frame, end_frame))
+}
+
+/// Read WAV data from any reader (e.g. stdin or a network stream) and convert to mono
+pub fn read_audio_mono_from_reader<R: Read>(
+    reader: R,
+    downmix: DownmixMode,
+) -> Result<(Vec<f32>, u32)> {
+    let mut reader = WavStream::from_stream(reader)?;
+    let sr = reader.sample_rate();
+    let samples = reader.read_mono(None, downmix)?;
+    Ok((samples, sr))
+}
+
+/// Sample encodings of headerless (raw) PCM audio. Samples are interleaved little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PcmFormat {
+    /// 8-bit unsigned integer
+    U8,
+    /// 16-bit signed integer
+    S16le,
+    /// 24-bit signed integer (packed in 3 bytes)
+    S24le,
+    /// 32-bit signed integer
+    S32le,
+    /// 32-bit IEEE float
+    F32le,
+}
+
+impl PcmFormat {
+    /// Number of bytes of a single sample
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::U8 => 1,
+            PcmFormat::S16le => 2,
+            PcmFormat::S24le => 3,
+            PcmFormat::S32le | PcmFormat::F32le => 4,
+        }
+    }
+}
+
+/// Layout of headerless PCM audio, which has to be known in advance since no header
+/// describes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawPcm {
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Sample encoding
+    pub format: PcmFormat,
+    /// Number of interleaved channels
+    pub channels: usize,
+}
+
+/// Read a headerless PCM file (e.g. a telephony or embedded capture) and downmix it to mono.
+/// Returns the samples and the given sample rate, like `read_audio_file_mono`.
+#[cfg(feature = "fs")]
+pub fn read_raw_pcm(
+    audio_file_path: &Path,
+    sample_rate: u32,
+    format: PcmFormat,
+    channels: usize,
+    downmix: DownmixMode,
+) -> Result<(Vec<f32>, u32)> {
+    if sample_rate == 0 {
+        return Err(SpectrsError::InvalidParams(
+            "Sample rate of raw PCM audio must be greater than 0".to_string(),
+        ));
+    }
+    let reader = BufReader::new(
+        File::open(audio_file_path)
+            .map_err(|e| SpectrsError::io("Failed to open audio file", e))?,
+    );
+    let samples = read_raw_pcm_from_reader(reader, format, channels, downmix)?;
+    Ok((samples, sample_rate))
+}
+
+/// Read headerless PCM audio from any reader (e.g. stdin) and downmix it to mono. An
+/// incomplete trailing frame is dropped.
+pub fn read_raw_pcm_from_reader<R: Read>(
+    mut reader: R,
+    format: PcmFormat,
+    channels: usize,
+    downmix: DownmixMode,
+) -> Result<Vec<f32>> {
+    downmix.check(channels)?;
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| SpectrsError::io("Failed to read raw PCM data", e))?;
+
+    let frame_bytes = format.bytes_per_sample() * channels;
+    bytes.truncate(bytes.len() - bytes.len() % frame_bytes);
+    let samples = bytes.chunks_exact(format.bytes_per_sample());
+
+    Ok(match format {
+        PcmFormat::F32le => {
+            let interleaved: Vec<f32> = samples
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            float_interleaved_to_mono(&interleaved, channels, downmix)
+        }
+        _ => {
+            let interleaved: Vec<i32> = samples
+                .map(|b| match format {
+                    PcmFormat::U8 => b[0] as i32 - 128,
+                    PcmFormat::S16le => i16::from_le_bytes([b[0], b[1]]) as i32,
+                    // Sign-extend by placing the 3 bytes at the top of an i32
+                    PcmFormat::S24le => i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8,
+                    _ => i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                })
+                .collect();
+            interleaved_to_mono(
+                &interleaved,
+                channels,
+                (format.bytes_per_sample() * 8) as u16,
+                downmix,
+            )
+        }
+    })
+}
+
+/// Cut the [start, end) portion (in seconds) out of already decoded samples.
+/// If `end` is None (or past the end of the audio) the segment extends to the end.
+pub fn slice_segment(samples: &[f32], sr: u32, start: f32, end: Option<f32>) -> Result<Vec<f32>> {
+    let start_idx = (start as f64 * sr as f64).round() as usize;
+    let end_idx = end
+        .map(|e| ((e as f64 * sr as f64).round() as usize).min(samples.len()))
+        .unwrap_or(samples.len());
+    if start < 0.0 || start_idx >= samples.len() || start_idx >= end_idx {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Invalid segment [{}s, {:?}s) for audio of {}s",
+            start,
+            end,
+            samples.len() as f32 / sr as f32
+        )));
+    }
+    Ok(samples[start_idx..end_idx].to_vec())
+}
+
+/// Random-access mono decoder of a WAV stream: the sample rate and duration are known as soon
+/// as the header is parsed, and only the frames asked for are decoded
+pub struct AudioReader<R: Read> {
+    stream: WavStream<R>,
+    downmix: DownmixMode,
+    /// Index of the next frame to decode
+    position: u32,
+}
+
+#[cfg(feature = "fs")]
+impl AudioReader<BufReader<File>> {
+    /// Parse the header of a WAV file, without decoding any sample
+    pub fn open(audio_file_path: &Path) -> Result<Self> {
+        let reader = BufReader::new(
+            File::open(audio_file_path)
+                .map_err(|e| SpectrsError::io("Failed to open audio file", e))?,
+        );
+        Self::new(reader)
+    }
+}
+
+impl<R: Read + Seek> AudioReader<R> {
+    /// Parse the header of WAV data from any seekable reader. Channels are averaged unless
+    /// another downmix is set with `with_downmix`.
+    pub fn new(reader: R) -> Result<Self> {
+        let stream = WavStream::new(reader)?;
+        DownmixMode::Average.check(stream.channels())?;
+        Ok(Self {
+            stream,
+            downmix: DownmixMode::Average,
+            position: 0,
+        })
+    }
+
+    /// Move to the frame closest to `seconds` from the start, without decoding what comes
+    /// before. Seeking past the end leaves nothing to read.
+    pub fn seek(&mut self, seconds: f32) -> Result<()> {
+        if seconds < 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Seek position must be non-negative, got {}s",
+                seconds
+            )));
+        }
+        // In f64: f32 products lose whole samples past a few minutes
+        self.seek_frame((seconds as f64 * self.sample_rate() as f64).round() as u32)
+    }
+
+    /// Move to the given frame (clamped to the end of the stream)
+    fn seek_frame(&mut self, frame: u32) -> Result<()> {
+        let frame = frame.min(self.stream.duration());
+        self.stream.seek(frame)?;
+        self.position = frame;
+        Ok(())
+    }
+}
+
+impl<R: Read> AudioReader<R> {
+    /// Downmix the channels this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Result<Self> {
+        downmix.check(self.stream.channels())?;
+        self.downmix = downmix;
+        Ok(self)
+    }
+
+    /// Sample rate of the decoded audio
+    pub fn sample_rate(&self) -> u32 {
+        self.stream.sample_rate()
+    }
+
+    /// Number of channels of the stream (before downmixing)
+    pub fn channels(&self) -> usize {
+        self.stream.channels()
+    }
+
+    /// Number of frames announced by the header
+    pub fn n_frames(&self) -> usize {
+        self.stream.duration() as usize
+    }
+
+    /// Duration of the stream (seconds)
+    pub fn duration(&self) -> f32 {
+        self.stream.duration() as f32 / self.sample_rate() as f32
+    }
+
+    /// Time of the next frame to decode (seconds)
+    pub fn position(&self) -> f32 {
+        self.position as f32 / self.sample_rate() as f32
+    }
+
+    /// Number of frames left to decode, according to the header
+    pub fn remaining_frames(&self) -> usize {
+        (self.stream.duration() - self.position) as usize
+    }
+
+    /// Decode the next (at most `n`) frames to mono samples. An empty result marks the end.
+    pub fn read_frames(&mut self, n: usize) -> Result<Vec<f32>> {
+        let n = n.min(self.remaining_frames());
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let samples = self.stream.read_mono(Some(n), self.downmix)?;
+        self.position += samples.len() as u32;
+        Ok(samples)
+    }
+}
+
+/// Incremental mono decoder: yields the audio of a WAV stream a chunk at a time, so that
+/// arbitrarily long files can be processed with bounded memory
+pub struct MonoChunks<R: Read> {
+    reader: AudioReader<R>,
+    /// Frames left to decode
+    remaining_frames: usize,
+    /// Token stopping the decoding between two chunks
+    cancel: Option<CancellationToken>,
+}
+
+#[cfg(feature = "fs")]
+impl MonoChunks<BufReader<File>> {
+    /// Decode the whole file
+    pub fn open(audio_file_path: &Path) -> Result<Self> {
+        let reader = BufReader::new(
+            File::open(audio_file_path)
+                .map_err(|e| SpectrsError::io("Failed to open audio file", e))?,
+        );
+        Self::new(reader)
+    }
+
+    /// Decode only the [start, end) portion (in seconds) of the file, seeking to `start`
+    pub fn open_segment(audio_file_path: &Path, start: f32, end: Option<f32>) -> Result<Self> {
+        let mut chunks = Self::open(audio_file_path)?;
+        let sr = chunks.sample_rate();
+        let duration = chunks.reader.n_frames() as u32;
+        let (start_frame, end_frame) = segment_frames(sr, duration, start, end)?;
+        chunks.reader.seek_frame(start_frame)?;
+        chunks.remaining_frames = (end_frame - start_frame) as usize;
+        Ok(chunks)
+    }
+}
+
+impl<R: Read + Seek> MonoChunks<R> {
+    /// Decode WAV data from any seekable reader, averaging all channels
+    pub fn new(reader: R) -> Result<Self> {
+        let reader = AudioReader::new(reader)?;
+        let remaining_frames = reader.remaining_frames();
+        Ok(Self {
+            reader,
+            remaining_frames,
+            cancel: None,
+        })
+    }
+}
+
+impl<R: Read> MonoChunks<R> {
+    /// Downmix the channels this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Result<Self> {
+        self.reader = self.reader.with_downmix(downmix)?;
+        Ok(self)
+    }
+
+    /// Sample rate of the decoded audio
+    pub fn sample_rate(&self) -> u32 {
+        self.reader.sample_rate()
+    }
+
+    /// Number of frames left to decode, according to the header
+    pub fn remaining_frames(&self) -> usize {
+        self.remaining_frames
+    }
+
+    /// Fail with `SpectrsError::Cancelled` at the next chunk once the token is cancelled, so
+    /// that computations streaming the audio stop early
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Decode the next (at most `max_frames`) mono samples. An empty chunk marks the end.
+    pub fn next_chunk(&mut self, max_frames: usize) -> Result<Vec<f32>> {
+        if let Some(cancel) = &self.cancel {
+            cancel.check()?;
+        }
+        let n_frames = max_frames.min(self.remaining_frames);
+        if n_frames == 0 {
+            return Ok(Vec::new());
+        }
+        let samples = self.reader.read_frames(n_frames)?;
+        self.remaining_frames = if samples.is_empty() {
+            0
+        } else {
+            self.remaining_frames - samples.len()
+        };
+        Ok(samples)
+    }
+}
+
+/// WAVE format tag of integer PCM samples
+const WAVE_FORMAT_PCM: u16 = 1;
+/// WAVE format tag of IEEE float samples
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+/// Largest fmt chunk read from a header (the real ones hold 16, 18 or 40 bytes): its length
+/// comes from the file, and must not size an allocation unchecked
+const MAX_FMT_CHUNK_LEN: u32 = 1024;
+
+/// WAV decoder: hound for the common layouts, or a plain reader of the data chunk for the
+/// layouts hound does not support (see `FmtChunk::needs_raw_decoding`)
+enum WavStream<R: Read> {
+    Hound(WavReader<R>),
+    Raw(RawData<R>),
+}
+
+/// Data chunk of a WAV file, read frame by frame
+struct RawData<R> {
+    reader: R,
+    format: FmtChunk,
+    /// Frames announced by the header (u32::MAX when it leaves the length open)
+    n_frames: u32,
+    /// Index of the next frame to read
+    position: u32,
+    /// Offset of the data chunk from the start of the stream
+    data_start: u64,
+    /// Whether the data runs to the end of the stream rather than to the declared length
+    to_eof: bool,
+}
+
+impl<R: Read + Seek> WavStream<R> {
+    /// Decode WAV data from a seekable reader, rewinding it once the format is known
+    fn new(mut reader: R) -> Result<Self> {
+        let start = reader
+            .stream_position()
+            .map_err(|e| SpectrsError::io("Failed to read WAV data", e))?;
+        if let Ok((format, data_len)) = read_wav_header(&mut reader)
+            && format.needs_raw_decoding()
+        {
+            let data_start = reader
+                .stream_position()
+                .map_err(|e| SpectrsError::io("Failed to read WAV data", e))?;
+            return Ok(Self::raw(reader, format, Some(data_len), data_start));
+        }
+        reader
+            .seek(SeekFrom::Start(start))
+            .map_err(|e| SpectrsError::io("Failed to rewind WAV data", e))?;
+        let reader =
+            WavReader::new(reader).map_err(|e| SpectrsError::wav("Failed to parse WAV data", e))?;
+        Ok(Self::Hound(reader))
+    }
+
+    /// Move to `frame` without decoding what comes before
+    fn seek(&mut self, frame: u32) -> Result<()> {
+        let seek_error = |e| SpectrsError::io("Failed to seek in audio file", e);
+        match self {
+            Self::Hound(reader) => reader.seek(frame).map_err(seek_error),
+            Self::Raw(data) => {
+                let frame = frame.min(data.n_frames);
+                let offset = data.data_start + frame as u64 * data.format.frame_len() as u64;
+                data.reader
+                    .seek(SeekFrom::Start(offset))
+                    .map_err(seek_error)?;
+                data.position = frame;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<R: Read> WavStream<std::io::Chain<Cursor<Vec<u8>>, R>> {
+    /// Decode WAV data from a reader that cannot seek (e.g. stdin): the header is parsed
+    /// while keeping its bytes, which are replayed to hound for the formats it supports
+    fn from_stream(reader: R) -> Result<Self> {
+        let mut recorder = Recorder {
+            reader,
+            bytes: Vec::new(),
+        };
+        let header = read_wav_header(&mut recorder);
+        let data_start = recorder.bytes.len() as u64;
+        let Recorder { reader, bytes } = recorder;
+        match header {
+            Ok((format, data_len)) if format.needs_raw_decoding() => Ok(Self::raw(
+                Cursor::new(Vec::new()).chain(reader),
+                format,
+                Some(data_len),
+                data_start,
+            )),
+            // Writers streaming to a pipe (e.g. `ffmpeg -f wav -`) cannot go back to fill in the
+            // length, and leave 0xFFFFFFFF or 0 instead, which hound refuses: the data runs to
+            // the end of the stream
+            Ok((format, 0 | u32::MAX)) if format.is_raw_decodable() => Ok(Self::raw(
+                Cursor::new(Vec::new()).chain(reader),
+                format,
+                None,
+                data_start,
+            )),
+            _ => {
+                let reader = WavReader::new(Cursor::new(bytes).chain(reader))
+                    .map_err(|e| SpectrsError::wav("Failed to parse WAV data", e))?;
+                Ok(Self::Hound(reader))
+            }
+        }
+    }
+}
+
+impl<R: Read> WavStream<R> {
+    /// Raw decoder of the data chunk, of `data_len` bytes or up to the end of the stream
+    fn raw(reader: R, format: FmtChunk, data_len: Option<u32>, data_start: u64) -> Self {
+        Self::Raw(RawData {
+            reader,
+            format,
+            n_frames: data_len.map_or(u32::MAX, |len| {
+                (len as usize / format.frame_len().max(1)) as u32
+            }),
+            position: 0,
+            data_start,
+            to_eof: data_len.is_none(),
+        })
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Self::Hound(reader) => reader.spec().sample_rate,
+            Self::Raw(data) => data.format.sample_rate,
+        }
+    }
+
+    fn channels(&self) -> usize {
+        match self {
+            Self::Hound(reader) => reader.spec().channels as usize,
+            Self::Raw(data) => data.format.channels,
+        }
+    }
+
+    /// Number of frames announced by the header
+    fn duration(&self) -> u32 {
+        match self {
+            Self::Hound(reader) => reader.duration(),
+            Self::Raw(data) => data.n_frames,
+        }
+    }
+
+    /// Read (at most `max_frames`) frames from the current position and convert to mono
+    fn read_mono(&mut self, max_frames: Option<usize>, downmix: DownmixMode) -> Result<Vec<f32>> {
+        let channels = self.channels();
+        downmix.check(channels)?;
 
-    // Exit if if more than 2 channels
-    if channels > 2 {
-        return Err(anyhow::anyhow!(
-            "Unsupported number of channels: {}. Only mono and stereo are supported.",
-            channels
+        // Number of interleaved samples to read
+        let max_samples = max_frames.map_or(usize::MAX, |n| n * channels);
+
+        match self {
+            Self::Hound(reader) => {
+                let spec = reader.spec();
+                match spec.sample_format {
+                    SampleFormat::Int => {
+                        let interleaved = reader
+                            .samples::<i32>()
+                            .take(max_samples)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|e| SpectrsError::wav("Couldn't read samples", e))?;
+                        Ok(interleaved_to_mono(
+                            &interleaved,
+                            channels,
+                            spec.bits_per_sample,
+                            downmix,
+                        ))
+                    }
+                    SampleFormat::Float => {
+                        let interleaved = reader
+                            .samples::<f32>()
+                            .take(max_samples)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|e| SpectrsError::wav("Couldn't read samples", e))?;
+                        Ok(float_interleaved_to_mono(&interleaved, channels, downmix))
+                    }
+                }
+            }
+            Self::Raw(data) => {
+                let n_frames = ((data.n_frames - data.position) as usize)
+                    .min(max_frames.unwrap_or(usize::MAX));
+                let frame_len = data.format.frame_len();
+                let len = n_frames * frame_len;
+                // The length comes from the header: the buffer grows with the bytes actually
+                // read instead of being sized after it, so a truncated file fails at its end
+                let mut bytes = Vec::new();
+                (&mut data.reader)
+                    .take(len as u64)
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| SpectrsError::io("Couldn't read samples", e))?;
+                if bytes.len() < len && !data.to_eof {
+                    return Err(SpectrsError::io(
+                        "Couldn't read samples",
+                        std::io::ErrorKind::UnexpectedEof.into(),
+                    ));
+                }
+                let n_read = bytes.len().checked_div(frame_len).unwrap_or(n_frames);
+                bytes.truncate(n_read * frame_len);
+                data.position += n_read as u32;
+                Ok(decode_raw_frames(&bytes, &data.format, downmix))
+            }
+        }
+    }
+}
+
+/// Reader keeping a copy of every byte it reads
+struct Recorder<R> {
+    reader: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Sample layout announced by the `fmt ` chunk of a WAV file
+#[derive(Debug, Clone, Copy)]
+struct FmtChunk {
+    /// Format tag (the sub-format for WAVE_FORMAT_EXTENSIBLE)
+    tag: u16,
+    channels: usize,
+    sample_rate: u32,
+    /// Size of the sample container in bits
+    bits_per_sample: u16,
+    /// Number of meaningful bits in each container (WAVE_FORMAT_EXTENSIBLE only; equal to
+    /// `bits_per_sample` otherwise)
+    valid_bits: u16,
+}
+
+impl FmtChunk {
+    /// Parse the body of a `fmt ` chunk (at least 16 bytes)
+    fn parse(body: &[u8]) -> Self {
+        let read_u16 = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+        let mut tag = read_u16(0);
+        let bits_per_sample = read_u16(14);
+        let mut valid_bits = bits_per_sample;
+        // WAVE_FORMAT_EXTENSIBLE stores the actual format in the sub-format GUID
+        if tag == 0xFFFE && body.len() >= 26 {
+            tag = read_u16(24);
+            valid_bits = match read_u16(18) {
+                0 => bits_per_sample,
+                bits => bits.min(bits_per_sample),
+            };
+        }
+        Self {
+            tag,
+            channels: read_u16(2) as usize,
+            sample_rate: u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+            bits_per_sample,
+            valid_bits,
+        }
+    }
+
+    /// Bytes taken by one frame (one container per channel)
+    fn frame_len(&self) -> usize {
+        self.bits_per_sample as usize / 8 * self.channels
+    }
+
+    /// Whether the samples can be decoded by hand (see `decode_raw_frames`)
+    fn is_raw_decodable(&self) -> bool {
+        let supported = match self.tag {
+            WAVE_FORMAT_PCM => matches!(self.bits_per_sample, 8 | 16 | 24 | 32),
+            WAVE_FORMAT_IEEE_FLOAT => matches!(self.bits_per_sample, 32 | 64),
+            _ => false,
+        };
+        supported && self.channels > 0
+    }
+
+    /// hound refuses 64-bit float samples and reads integer samples narrower than their
+    /// container (e.g. 24 bits in 4 bytes) from the low bits, while WAVE_FORMAT_EXTENSIBLE
+    /// left-justifies them: both layouts are decoded by hand
+    fn needs_raw_decoding(&self) -> bool {
+        match self.tag {
+            WAVE_FORMAT_PCM => self.valid_bits < self.bits_per_sample,
+            WAVE_FORMAT_IEEE_FLOAT => self.bits_per_sample == 64,
+            _ => false,
+        }
+    }
+}
+
+/// Read the chunks of a RIFF/WAVE stream up to the start of its data chunk. Returns the
+/// format and the declared length of the data.
+fn read_wav_header<R: Read>(reader: &mut R) -> Result<(FmtChunk, u32)> {
+    let read_error = |e| SpectrsError::io("Failed to read WAV header", e);
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff).map_err(read_error)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(SpectrsError::UnsupportedFormat(
+            "Not a RIFF/WAVE file".to_string(),
         ));
     }
 
-    // Init samples vec
-    let mut samples: Vec<f32> = Vec::new();
+    let mut format = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        reader.read_exact(&mut chunk).map_err(read_error)?;
+        let chunk_len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        if &chunk[0..4] == b"data" {
+            let format = format.ok_or_else(|| SpectrsError::DecodeError {
+                message: "Data chunk found before fmt chunk".to_string(),
+                source: None,
+            })?;
+            return Ok((format, chunk_len));
+        }
+
+        // Chunks are padded to an even number of bytes
+        let padded_len = chunk_len as u64 + chunk_len as u64 % 2;
+        if &chunk[0..4] == b"fmt " {
+            if chunk_len < 16 {
+                return Err(SpectrsError::DecodeError {
+                    message: "Truncated fmt chunk".to_string(),
+                    source: None,
+                });
+            }
+            if chunk_len > MAX_FMT_CHUNK_LEN {
+                return Err(SpectrsError::DecodeError {
+                    message: format!("Oversized fmt chunk ({} bytes)", chunk_len),
+                    source: None,
+                });
+            }
+            let mut body = vec![0u8; padded_len as usize];
+            reader.read_exact(&mut body).map_err(read_error)?;
+            format = Some(FmtChunk::parse(&body[..chunk_len as usize]));
+        } else {
+            let skipped = std::io::copy(&mut reader.take(padded_len), &mut std::io::sink())
+                .map_err(read_error)?;
+            if skipped < padded_len {
+                return Err(read_error(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+}
 
+/// Normalize interleaved integer samples to [-1, 1) and downmix the channels. Samples must be
+/// sign-extended to i32 (as hound returns them, e.g. 24-bit values in [-2^23, 2^23)).
+fn interleaved_to_mono(
+    interleaved: &[i32],
+    channels: usize,
+    bits_per_sample: u16,
+    downmix: DownmixMode,
+) -> Vec<f32> {
     // Calculate the maximum value based on bits_per_sample
     let max_value = 2_f64.powi(bits_per_sample as i32 - 1);
 
-    // Define accumulator to compute average in case of stereo (using i64 to prevent overflow)
-    let mut acc = 0_i64;
+    interleaved
+        .chunks_exact(channels.max(1))
+        .map(|frame| match downmix {
+            DownmixMode::Average => {
+                // Average (using i64 to prevent overflow) and normalize by dividing by max_value
+                let acc: i64 = frame.iter().map(|&sample| sample as i64).sum();
+                acc as f32 / frame.len() as f32 / max_value as f32
+            }
+            _ => (downmix.mix(frame) / max_value) as f32,
+        })
+        .collect()
+}
 
-    // Read into samples vec
+/// Downmix interleaved float samples (already in [-1, 1]) to mono
+fn float_interleaved_to_mono<T: Copy + Into<f64>>(
+    interleaved: &[T],
+    channels: usize,
+    downmix: DownmixMode,
+) -> Vec<f32> {
+    interleaved
+        .chunks_exact(channels.max(1))
+        .map(|frame| downmix.mix(frame) as f32)
+        .collect()
+}
+
+/// Details about a damaged file whose samples were (partially) recovered in lenient mode
+#[derive(Debug, Clone)]
+pub struct SalvageReport {
+    /// Why the strict decoder rejected the file
+    pub reason: String,
+    /// Number of frames recovered
+    pub recovered_frames: usize,
+    /// Number of frames announced by the header, if it could be parsed
+    pub declared_frames: Option<u32>,
+    /// Sample rate of the recovered audio
+    pub sample_rate: u32,
+}
+
+impl SalvageReport {
+    /// Duration of the recovered audio (seconds)
+    pub fn recovered_duration(&self) -> f32 {
+        self.recovered_frames as f32 / self.sample_rate as f32
+    }
+}
+
+/// Lenient variant of `read_audio_file_mono`: damaged files (truncated data chunk, wrong header
+/// lengths) are decoded as far as possible instead of failing. The report is `Some` whenever
+/// the file was not read cleanly.
+#[cfg(feature = "fs")]
+pub fn read_audio_file_mono_lenient(
+    audio_file_path: &Path,
+) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+    let reader = BufReader::new(
+        File::open(audio_file_path)
+            .map_err(|e| SpectrsError::io("Failed to open audio file", e))?,
+    );
+    read_audio_mono_lenient_from_reader(reader, DownmixMode::Average)
+}
+
+/// Same as `read_audio_file_mono_lenient`, but decoding WAV data from any seekable reader,
+/// downmixed as requested
+pub fn read_audio_mono_lenient_from_reader<R: Read + Seek>(
+    mut reader: R,
+    downmix: DownmixMode,
+) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+    // Layouts hound does not support are decoded by hand below
+    let raw = read_wav_header(&mut reader).is_ok_and(|(format, _)| format.needs_raw_decoding());
     reader
-        .samples::<i32>()
-        .map(|s| s.with_context(|| "Couldn't read samples"))
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        .enumerate()
-        .for_each(|(i, &sample)| {
-            if channels == 2 {
-                acc += sample as i64;
-                if i % 2 != 0 {
-                    // Average and normalize by dividing by max_value
-                    samples.push(acc as f32 / 2.0 / max_value as f32);
-                    acc = 0_i64;
+        .rewind()
+        .map_err(|e| SpectrsError::io("Failed to rewind WAV data", e))?;
+
+    let hound = (!raw).then(|| WavReader::new(&mut reader));
+    let wav_reader = match hound {
+        Some(Ok(wav_reader)) => wav_reader,
+        // The header itself is inconsistent, or the layout is not supported by hound: parse
+        // the chunks by hand
+        hound => {
+            let reason = match hound {
+                Some(Err(error)) => error.to_string(),
+                _ => "Data chunk shorter than declared".to_string(),
+            };
+            reader
+                .rewind()
+                .map_err(|e| SpectrsError::io("Failed to rewind WAV data", e))?;
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|e| SpectrsError::io("Failed to read WAV data", e))?;
+            let (samples, sr, declared_frames) = salvage_raw_wav(&bytes, downmix).map_err(|e| {
+                SpectrsError::decode(format!("Failed to parse WAV data ({})", reason), e)
+            })?;
+            // Complete files in a layout hound does not support are not damaged
+            if raw && declared_frames == Some(samples.len() as u32) {
+                return Ok((samples, sr, None));
+            }
+            let report = SalvageReport {
+                reason,
+                recovered_frames: samples.len(),
+                declared_frames,
+                sample_rate: sr,
+            };
+            return Ok((samples, sr, Some(report)));
+        }
+    };
+
+    let spec = wav_reader.spec();
+    let channels = spec.channels as usize;
+    downmix.check(channels)?;
+    let declared_frames = wav_reader.duration();
+
+    // Keep every sample up to the first decoding error
+    let mut failure = None;
+    let samples = match spec.sample_format {
+        SampleFormat::Int => {
+            let mut interleaved =
+                samples_until_error(wav_reader.into_samples::<i32>(), &mut failure);
+            // Drop an incomplete trailing frame
+            interleaved.truncate(interleaved.len() - interleaved.len() % channels);
+            interleaved_to_mono(&interleaved, channels, spec.bits_per_sample, downmix)
+        }
+        SampleFormat::Float => {
+            let interleaved = samples_until_error(wav_reader.into_samples::<f32>(), &mut failure);
+            float_interleaved_to_mono(&interleaved, channels, downmix)
+        }
+    };
+
+    let report = failure.map(|reason| SalvageReport {
+        reason,
+        recovered_frames: samples.len(),
+        declared_frames: Some(declared_frames),
+        sample_rate: spec.sample_rate,
+    });
+
+    Ok((samples, spec.sample_rate, report))
+}
+
+/// Collect samples up to the first decoding error, which is stored in `failure`
+fn samples_until_error<T>(
+    samples: impl Iterator<Item = hound::Result<T>>,
+    failure: &mut Option<String>,
+) -> Vec<T> {
+    let mut collected = Vec::new();
+    for sample in samples {
+        match sample {
+            Ok(sample) => collected.push(sample),
+            Err(error) => {
+                *failure = Some(error.to_string());
+                break;
+            }
+        }
+    }
+    collected
+}
+
+/// Decode whole frames of little-endian samples laid out as described by `format` and average
+/// them to mono. Integer samples are left-justified in their container, so normalizing by
+/// the container width also covers containers wider than the valid bits.
+fn decode_raw_frames(data: &[u8], format: &FmtChunk, downmix: DownmixMode) -> Vec<f32> {
+    let channels = format.channels;
+    let bytes_per_sample = format.bits_per_sample as usize / 8;
+    match (format.tag, bytes_per_sample) {
+        (WAVE_FORMAT_IEEE_FLOAT, 4) => {
+            let interleaved: Vec<f32> = data
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            float_interleaved_to_mono(&interleaved, channels, downmix)
+        }
+        (WAVE_FORMAT_IEEE_FLOAT, _) => {
+            let interleaved: Vec<f64> = data
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            float_interleaved_to_mono(&interleaved, channels, downmix)
+        }
+        _ => {
+            let interleaved: Vec<i32> = data
+                .chunks_exact(bytes_per_sample)
+                .map(|b| match bytes_per_sample {
+                    // 8-bit PCM is unsigned
+                    1 => b[0] as i32 - 128,
+                    2 => i16::from_le_bytes([b[0], b[1]]) as i32,
+                    3 => i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8,
+                    _ => i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                })
+                .collect();
+            interleaved_to_mono(&interleaved, channels, format.bits_per_sample, downmix)
+        }
+    }
+}
+
+/// Decode integer or float PCM from raw RIFF/WAVE bytes whose header hound refuses, trusting the
+/// `fmt ` chunk and reading the data chunk up to the end of the buffer when its declared
+/// length is missing or too large. Returns the mono samples, the sample rate and the
+/// number of frames the header announced.
+fn salvage_raw_wav(bytes: &[u8], downmix: DownmixMode) -> Result<(Vec<f32>, u32, Option<u32>)> {
+    let read_u32 =
+        |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(SpectrsError::UnsupportedFormat(
+            "Not a RIFF/WAVE file".to_string(),
+        ));
+    }
+
+    let mut format = None;
+    let mut position = 12;
+    while position + 8 <= bytes.len() {
+        let chunk_id = &bytes[position..position + 4];
+        let chunk_len = read_u32(position + 4) as usize;
+        let body = position + 8;
+
+        if chunk_id == b"fmt " {
+            if chunk_len < 16 || body + 16 > bytes.len() {
+                return Err(SpectrsError::DecodeError {
+                    message: "Truncated fmt chunk".to_string(),
+                    source: None,
+                });
+            }
+            format = Some(FmtChunk::parse(
+                &bytes[body..(body + chunk_len).min(bytes.len())],
+            ));
+        } else if chunk_id == b"data" {
+            let format: FmtChunk = format.ok_or_else(|| SpectrsError::DecodeError {
+                message: "Data chunk found before fmt chunk".to_string(),
+                source: None,
+            })?;
+            let (channels, bits) = (format.channels, format.bits_per_sample);
+            let supported = match format.tag {
+                WAVE_FORMAT_PCM => matches!(bits, 8 | 16 | 24 | 32),
+                WAVE_FORMAT_IEEE_FLOAT => matches!(bits, 32 | 64),
+                tag => {
+                    return Err(SpectrsError::UnsupportedFormat(format!(
+                        "Unsupported sample format (format tag {})",
+                        tag
+                    )));
                 }
-            } else if channels == 1 {
-                // Normalize by dividing by max_value
-                samples.push(sample as f32 / max_value as f32);
+            };
+            downmix.check(channels)?;
+            if !supported {
+                return Err(SpectrsError::UnsupportedFormat(format!(
+                    "Unsupported bits per sample: {}",
+                    bits
+                )));
             }
-        });
 
-    Ok((samples, sr))
+            // Trust the declared length only if it is plausible
+            let available = bytes.len() - body;
+            let data_len = if chunk_len == 0 || chunk_len > available {
+                available
+            } else {
+                chunk_len
+            };
+
+            let frame_len = format.frame_len();
+            let declared_frames = (chunk_len / frame_len) as u32;
+            let data = &bytes[body..body + data_len - data_len % frame_len];
+
+            let samples = decode_raw_frames(data, &format, downmix);
+
+            return Ok((samples, format.sample_rate, Some(declared_frames)));
+        }
+
+        // Chunks are padded to an even number of bytes
+        position = body.saturating_add(chunk_len).saturating_add(chunk_len % 2);
+    }
+
+    Err(SpectrsError::DecodeError {
+        message: "No data chunk found".to_string(),
+        source: None,
+    })
 }
 
-/// Resample audio file to target sample rate
+/// Number of input frames fed to the sinc resamplers at a time
+const RESAMPLE_CHUNK_SIZE: usize = 1024;
+
+/// Resampler quality presets, trading speed for anti-aliasing performance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ResampleQuality {
+    /// Single FFT over the whole signal: fastest, but memory grows with the file length
+    #[default]
+    Fast,
+    /// Windowed sinc (128 taps, linear interpolation, Blackman^2 window), chunked
+    Medium,
+    /// Windowed sinc (256 taps, cubic interpolation, Blackman-Harris^2 window), chunked
+    Best,
+}
+
+impl ResampleQuality {
+    /// Sinc interpolation parameters of the preset (None for the FFT resampler)
+    fn sinc_parameters(self) -> Option<SincInterpolationParameters> {
+        match self {
+            ResampleQuality::Fast => None,
+            ResampleQuality::Medium => Some(SincInterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.925,
+                oversampling_factor: 128,
+                interpolation: SincInterpolationType::Linear,
+                window: WindowFunction::Blackman2,
+            }),
+            ResampleQuality::Best => Some(SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                oversampling_factor: 256,
+                interpolation: SincInterpolationType::Cubic,
+                window: WindowFunction::BlackmanHarris2,
+            }),
+        }
+    }
+}
+
+/// Resample audio file to target sample rate (fast preset)
 pub fn resample(samples: Vec<f32>, original_sr: u32, target_sr: u32) -> Result<Vec<f32>> {
-    // Initialize the resampler
-    let mut resampler = FftFixedIn::<f32>::new(
-        original_sr as usize,
-        target_sr as usize,
-        samples.len(), // Number of frames per channel (1 channel)
-        1024,
-        1, // Always mono by construction
-    )
-    .with_context(|| "Can't initiate resampler")?;
-
-    // Perform the resampling
-    let mut resampled = resampler
-        .process(&[samples], None)
-        .with_context(|| "Can't resample file")?;
-
-    // Take ownership of the first channel, avoiding cloning
-    Ok(resampled.swap_remove(0))
+    resample_with_quality(samples, original_sr, target_sr, ResampleQuality::Fast)
+}
+
+/// Resample audio file to target sample rate with the chosen quality preset.
+/// The sinc presets process audio in fixed-size chunks so the resampler state does not scale
+/// with the length of the file.
+pub fn resample_with_quality(
+    samples: Vec<f32>,
+    original_sr: u32,
+    target_sr: u32,
+    quality: ResampleQuality,
+) -> Result<Vec<f32>> {
+    match quality.sinc_parameters() {
+        None => {
+            // Initialize the resampler
+            let mut resampler = FftFixedIn::<f32>::new(
+                original_sr as usize,
+                target_sr as usize,
+                samples.len(), // Number of frames per channel (1 channel)
+                1024,
+                1, // Always mono by construction
+            )
+            .map_err(|e| SpectrsError::resample("Can't initiate resampler", e))?;
+
+            // Perform the resampling
+            let mut resampled = resampler
+                .process(&[samples], None)
+                .map_err(|e| SpectrsError::resample("Can't resample file", e))?;
+
+            // Take ownership of the first channel, avoiding cloning
+            Ok(resampled.swap_remove(0))
+        }
+        Some(_) => {
+            let mut resampler = StreamingResampler::new(original_sr, target_sr, quality)?;
+            let mut output = Vec::with_capacity(
+                (samples.len() as f64 * target_sr as f64 / original_sr as f64).ceil() as usize,
+            );
+            resampler.process(&samples, &mut output)?;
+            resampler.finish(&mut output)?;
+            Ok(output)
+        }
+    }
+}
+
+/// Number of samples obtained by resampling `n_samples` samples from `original_sr` to `target_sr`
+pub fn resampled_len(n_samples: usize, original_sr: u32, target_sr: u32) -> usize {
+    (n_samples as f64 * target_sr as f64 / original_sr as f64).round() as usize
+}
+
+/// Resampler engine behind `StreamingResampler`
+enum StreamingEngine {
+    /// Same input and output rates
+    Passthrough,
+    /// Chunked FFT resampler (fast preset)
+    Fft(FftFixedIn<f32>),
+    /// Chunked windowed sinc resampler (medium and best presets)
+    Sinc(SincFixedIn<f32>),
+}
+
+/// Incremental resampler: feed the signal in pieces of any size and collect the output as it
+/// is produced, so the whole resampled signal never has to be held in memory.
+/// Once `finish` has been called the output has exactly len * target_sr / original_sr samples,
+/// like the output of `resample_with_quality`.
+pub struct StreamingResampler {
+    engine: StreamingEngine,
+    /// Input samples waiting for a full chunk
+    pending: Vec<f32>,
+    /// Scratch output buffer of the resampler
+    buffer: Vec<Vec<f32>>,
+    /// target_sr / original_sr
+    ratio: f64,
+    /// Number of input samples received so far
+    n_in: usize,
+    /// Number of output samples delivered so far
+    n_out: usize,
+    /// Leading output samples still to be dropped to compensate the resampler delay
+    skip: usize,
+}
+
+impl StreamingResampler {
+    pub fn new(original_sr: u32, target_sr: u32, quality: ResampleQuality) -> Result<Self> {
+        let (engine, buffer, skip) = if original_sr == target_sr {
+            (StreamingEngine::Passthrough, Vec::new(), 0)
+        } else {
+            match quality.sinc_parameters() {
+                None => {
+                    let resampler = FftFixedIn::<f32>::new(
+                        original_sr as usize,
+                        target_sr as usize,
+                        RESAMPLE_CHUNK_SIZE,
+                        2,
+                        1, // Always mono by construction
+                    )
+                    .map_err(|e| SpectrsError::resample("Can't initiate resampler", e))?;
+                    let buffer = resampler.output_buffer_allocate(true);
+                    let skip = resampler.output_delay();
+                    (StreamingEngine::Fft(resampler), buffer, skip)
+                }
+                Some(parameters) => {
+                    let resampler = SincFixedIn::<f32>::new(
+                        target_sr as f64 / original_sr as f64,
+                        1.0, // Fixed ratio
+                        parameters,
+                        RESAMPLE_CHUNK_SIZE,
+                        1, // Always mono by construction
+                    )
+                    .map_err(|e| SpectrsError::resample("Can't initiate resampler", e))?;
+                    let buffer = resampler.output_buffer_allocate(true);
+                    // The sinc interpolator starts half a filter length before the first input
+                    // sample, so its output is already aligned with the input
+                    (StreamingEngine::Sinc(resampler), buffer, 0)
+                }
+            }
+        };
+
+        Ok(Self {
+            engine,
+            pending: Vec::new(),
+            buffer,
+            ratio: target_sr as f64 / original_sr as f64,
+            n_in: 0,
+            n_out: 0,
+            skip,
+        })
+    }
+
+    /// Feed the next piece of the signal, appending the samples that became available to `output`
+    pub fn process(&mut self, samples: &[f32], output: &mut Vec<f32>) -> Result<()> {
+        self.n_in += samples.len();
+
+        let mut produced = Vec::new();
+        match &mut self.engine {
+            StreamingEngine::Passthrough => produced.extend_from_slice(samples),
+            StreamingEngine::Fft(resampler) => {
+                self.pending.extend_from_slice(samples);
+                process_full_chunks(
+                    resampler,
+                    &mut self.pending,
+                    &mut self.buffer,
+                    &mut produced,
+                )?
+            }
+            StreamingEngine::Sinc(resampler) => {
+                self.pending.extend_from_slice(samples);
+                process_full_chunks(
+                    resampler,
+                    &mut self.pending,
+                    &mut self.buffer,
+                    &mut produced,
+                )?
+            }
+        }
+        self.deliver(&produced, output);
+
+        Ok(())
+    }
+
+    /// Process the last partial chunk and flush the samples still held back by the resampler,
+    /// appending them to `output`
+    pub fn finish(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        let expected_len = (self.n_in as f64 * self.ratio).round() as usize;
+
+        let mut produced = Vec::new();
+        let n_missing = expected_len.saturating_sub(self.n_out) + self.skip;
+        match &mut self.engine {
+            StreamingEngine::Passthrough => {}
+            StreamingEngine::Fft(resampler) => flush(
+                resampler,
+                &mut self.pending,
+                &mut self.buffer,
+                n_missing,
+                &mut produced,
+            )?,
+            StreamingEngine::Sinc(resampler) => flush(
+                resampler,
+                &mut self.pending,
+                &mut self.buffer,
+                n_missing,
+                &mut produced,
+            )?,
+        }
+        let appended_from = output.len();
+        self.deliver(&produced, output);
+
+        // Drop the zero padding tail
+        if self.n_out > expected_len {
+            let excess = (self.n_out - expected_len).min(output.len() - appended_from);
+            output.truncate(output.len() - excess);
+            self.n_out -= excess;
+        }
+
+        Ok(())
+    }
+
+    /// Hand resampled samples to the caller, skipping the leading delay
+    fn deliver(&mut self, produced: &[f32], output: &mut Vec<f32>) {
+        let skip = self.skip.min(produced.len());
+        self.skip -= skip;
+        output.extend_from_slice(&produced[skip..]);
+        self.n_out += produced.len() - skip;
+    }
+}
+
+/// Run every full chunk available in `pending` through the resampler
+fn process_full_chunks<R: Resampler<f32>>(
+    resampler: &mut R,
+    pending: &mut Vec<f32>,
+    buffer: &mut [Vec<f32>],
+    produced: &mut Vec<f32>,
+) -> Result<()> {
+    let mut position = 0;
+    while pending.len() - position >= resampler.input_frames_next() {
+        let n_frames = resampler.input_frames_next();
+        let (_, n_out) = resampler
+            .process_into_buffer(&[&pending[position..position + n_frames]], buffer, None)
+            .map_err(|e| SpectrsError::resample("Can't resample file", e))?;
+        produced.extend_from_slice(&buffer[0][..n_out]);
+        position += n_frames;
+    }
+    pending.drain(..position);
+    Ok(())
+}
+
+/// Process the last, partial chunk (zero padded by the resampler), then keep feeding silence
+/// until `n_missing` more samples have been produced
+fn flush<R: Resampler<f32>>(
+    resampler: &mut R,
+    pending: &mut Vec<f32>,
+    buffer: &mut [Vec<f32>],
+    n_missing: usize,
+    produced: &mut Vec<f32>,
+) -> Result<()> {
+    if !pending.is_empty() {
+        let (_, n_out) = resampler
+            .process_partial_into_buffer(Some(&[&pending[..]]), buffer, None)
+            .map_err(|e| SpectrsError::resample("Can't resample file", e))?;
+        produced.extend_from_slice(&buffer[0][..n_out]);
+        pending.clear();
+    }
+
+    while produced.len() < n_missing {
+        let (_, n_out) = resampler
+            .process_partial_into_buffer(None::<&[&[f32]]>, buffer, None)
+            .map_err(|e| SpectrsError::resample("Can't resample file", e))?;
+        if n_out == 0 {
+            break;
+        }
+        produced.extend_from_slice(&buffer[0][..n_out]);
+    }
+
+    Ok(())
+}
+
+/// Sample encodings supported when writing WAV files, and compressed alternatives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum WavFormat {
+    /// 16-bit signed integer PCM
+    #[default]
+    Int16,
+    /// 24-bit signed integer PCM
+    Int24,
+    /// 32-bit signed integer PCM
+    Int32,
+    /// 32-bit IEEE float
+    Float32,
+    /// Opus in an Ogg container (lossy, resampled to 48 kHz), with the `opus` feature
+    Opus,
+}
+
+impl WavFormat {
+    /// Extension of the files written in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            WavFormat::Opus => "opus",
+            _ => "wav",
+        }
+    }
+}
+
+/// Write mono f32 samples (nominally in [-1, 1]) to a WAV file with the given encoding.
+/// Integer formats clip samples outside [-1, 1]; the float format stores them untouched.
+/// `WavFormat::Opus` writes an Ogg Opus file instead (see `io::opus`).
+#[cfg(feature = "fs")]
+pub fn write_audio_file(
+    audio_file_path: &Path,
+    samples: &[f32],
+    sr: u32,
+    format: WavFormat,
+) -> Result<()> {
+    let (bits_per_sample, sample_format) = match format {
+        WavFormat::Int16 => (16, SampleFormat::Int),
+        WavFormat::Int24 => (24, SampleFormat::Int),
+        WavFormat::Int32 => (32, SampleFormat::Int),
+        WavFormat::Float32 => (32, SampleFormat::Float),
+        #[cfg(feature = "opus")]
+        WavFormat::Opus => {
+            return crate::io::opus::write_opus_file(
+                audio_file_path,
+                samples,
+                sr,
+                crate::io::opus::DEFAULT_OPUS_BITRATE,
+            );
+        }
+        #[cfg(not(feature = "opus"))]
+        WavFormat::Opus => {
+            return Err(SpectrsError::UnsupportedFormat(
+                "Opus output requires the `opus` feature (compile with --features opus)"
+                    .to_string(),
+            ));
+        }
+    };
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: sr,
+        bits_per_sample,
+        sample_format,
+    };
+
+    crate::io::atomic::write_atomically(audio_file_path, |partial| {
+        let mut writer = WavWriter::create(partial, spec)
+            .map_err(|e| SpectrsError::wav("Failed to create WAV file", e))?;
+
+        match format {
+            WavFormat::Float32 => {
+                for &sample in samples {
+                    writer
+                        .write_sample(sample)
+                        .map_err(|e| SpectrsError::wav("Couldn't write samples", e))?;
+                }
+            }
+            _ => {
+                // Scale to the integer range, mirroring the normalization applied when reading
+                let max_value = 2_f64.powi(bits_per_sample as i32 - 1);
+                for &sample in samples {
+                    let scaled = (sample.clamp(-1.0, 1.0) as f64 * max_value)
+                        .round()
+                        .clamp(-max_value, max_value - 1.0);
+                    writer
+                        .write_sample(scaled as i32)
+                        .map_err(|e| SpectrsError::wav("Couldn't write samples", e))?;
+                }
+            }
+        }
+
+        writer
+            .finalize()
+            .map_err(|e| SpectrsError::wav("Failed to finalize WAV file", e))
+    })
 }
@@ -0,0 +1,125 @@
+//! On-disk cache of computed spectrograms, so that repeated runs with the same settings skip the
+//! decoding and the STFT. An entry is named after a key hashing the content of the audio together
+//! with everything that determines its spectrogram (the settings, serialized as JSON, and the
+//! version of spectrs), so that changing either simply misses the cache. Every entry is an NPY
+//! file holding the values and a JSON file holding caller-defined metadata (e.g. the sample
+//! rate). The NPY file is renamed into place last, so an entry is complete once it exists.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::atomic::write_atomically;
+use crate::io::export::{load_spectrogram_npy, save_spectrogram_npy};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Directory of cached spectrograms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpectrogramCache {
+    dir: PathBuf,
+}
+
+impl SpectrogramCache {
+    /// Cache stored in `dir` (created when the first entry is stored)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory the entries are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Key of the spectrogram of `audio` (the encoded bytes, e.g. a WAV file) computed with
+    /// `settings`
+    pub fn key<S: Serialize>(audio: &[u8], settings: &S) -> Result<String> {
+        let mut hasher = Xxh3::new();
+        hasher.update(&(audio.len() as u64).to_le_bytes());
+        hasher.update(audio);
+        finish_key(hasher, settings)
+    }
+
+    /// Key of the spectrogram of the audio file at `path` computed with `settings`, hashing
+    /// the file as it is read
+    pub fn key_for_file<S: Serialize>(path: &Path, settings: &S) -> Result<String> {
+        let open_error = |e| SpectrsError::io(format!("Failed to open {}", path.display()), e);
+        let mut file = File::open(path).map_err(open_error)?;
+        let length = file.metadata().map_err(open_error)?.len();
+        let mut hasher = Xxh3::new();
+        hasher.update(&length.to_le_bytes());
+        let mut buffer = vec![0u8; 1 << 16];
+        loop {
+            let n = file
+                .read(&mut buffer)
+                .map_err(|e| SpectrsError::io(format!("Failed to read {}", path.display()), e))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+        finish_key(hasher, settings)
+    }
+
+    /// Spectrogram and metadata stored under `key`, or None if there is no such entry
+    pub fn load<M: DeserializeOwned>(&self, key: &str) -> Result<Option<(Vec<Vec<f32>>, M)>> {
+        let (values_path, metadata_path) = self.entry_paths(key);
+        if !values_path.is_file() {
+            return Ok(None);
+        }
+        let metadata = std::fs::read_to_string(&metadata_path).map_err(|e| {
+            SpectrsError::io(format!("Failed to read {}", metadata_path.display()), e)
+        })?;
+        let metadata = serde_json::from_str(&metadata).map_err(|e| {
+            SpectrsError::invalid_data(
+                format!("Invalid cache metadata in {}", metadata_path.display()),
+                e,
+            )
+        })?;
+        Ok(Some((load_spectrogram_npy(&values_path)?, metadata)))
+    }
+
+    /// Store a spectrogram and its metadata under `key`, replacing any previous entry
+    pub fn store<M: Serialize>(
+        &self,
+        key: &str,
+        spectrogram: &[Vec<f32>],
+        metadata: &M,
+    ) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            SpectrsError::io(
+                format!("Failed to create directory: {}", self.dir.display()),
+                e,
+            )
+        })?;
+        let (values_path, metadata_path) = self.entry_paths(key);
+        let metadata = serde_json::to_string(metadata)
+            .map_err(|e| SpectrsError::invalid_data("Failed to serialize cache metadata", e))?;
+        write_atomically(&metadata_path, |partial| {
+            std::fs::write(partial, metadata).map_err(|e| {
+                SpectrsError::io(format!("Failed to write {}", metadata_path.display()), e)
+            })
+        })?;
+
+        // Renamed into place by `save_spectrogram_npy` as well
+        save_spectrogram_npy(spectrogram, &values_path)
+    }
+
+    /// Paths of the values and of the metadata of an entry
+    fn entry_paths(&self, key: &str) -> (PathBuf, PathBuf) {
+        (
+            self.dir.join(format!("{}.npy", key)),
+            self.dir.join(format!("{}.json", key)),
+        )
+    }
+}
+
+/// Mix the settings and the version of spectrs into the hash of the audio
+fn finish_key<S: Serialize>(mut hasher: Xxh3, settings: &S) -> Result<String> {
+    let settings = serde_json::to_vec(settings)
+        .map_err(|e| SpectrsError::invalid_data("Failed to serialize cache settings", e))?;
+    hasher.update(&settings);
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    Ok(format!("{:032x}", hasher.digest128()))
+}
@@ -0,0 +1,202 @@
+//! Checkpoint of a batch run: the inputs completed so far, saved to a JSON file as the run
+//! progresses so that an interrupted run can resume where it stopped.
+//!
+//! Unlike the index of `io::index`, a checkpoint does not look at the outputs: an input it
+//! records is done, even if its outputs went to remote storage whose files cannot be checked.
+//! A checkpoint belongs to one set of parameters; opening it with others is an error rather
+//! than a silent restart.
+//!
+//! ```json
+//! {"schema_version": 1, "spectrs_version": "0.3.0", "params": {...},
+//!  "completed": [{"input": "audio/a.wav", "start": null, "end": null}]}
+//! ```
+
+use crate::error::{Result, SpectrsError};
+use crate::io::metadata::SCHEMA_VERSION;
+use crate::spectrogram::params::SpectrogramParams;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Input recorded as completed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompletedInput {
+    /// Name of the input (its path or URL)
+    pub input: String,
+    /// Segment of the input processed (seconds, None for its start or end)
+    pub start: Option<f32>,
+    pub end: Option<f32>,
+}
+
+impl CompletedInput {
+    /// Key of the input in the checkpoint (segments compared by their bits)
+    fn key(&self) -> (String, Option<u32>, Option<u32>) {
+        (
+            self.input.clone(),
+            self.start.map(f32::to_bits),
+            self.end.map(f32::to_bits),
+        )
+    }
+}
+
+/// Contents of a checkpoint file
+#[derive(Serialize, Deserialize)]
+struct CheckpointFile {
+    schema_version: u32,
+    spectrs_version: String,
+    params: serde_json::Value,
+    completed: Vec<CompletedInput>,
+}
+
+/// Inputs completed by a run, saved to a JSON file
+pub struct Checkpoint {
+    path: PathBuf,
+    /// Parameters of the run, as JSON
+    params: serde_json::Value,
+    completed: Vec<CompletedInput>,
+    keys: HashSet<(String, Option<u32>, Option<u32>)>,
+    /// Inputs completed since the last save
+    unsaved: usize,
+    last_saved: Instant,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint at `path` for a run with `params`, resuming it if the file exists.
+    /// Fails if the file was written with other parameters
+    pub fn open(path: &Path, params: &SpectrogramParams) -> Result<Self> {
+        // Through text, so that the values compare equal to those read back from the file
+        let params = serde_json::to_string(params)
+            .and_then(|params| serde_json::from_str(&params))
+            .map_err(|e| SpectrsError::invalid_data("Failed to serialize parameters", e))?;
+        let mut checkpoint = Self {
+            path: path.to_path_buf(),
+            params,
+            completed: Vec::new(),
+            keys: HashSet::new(),
+            unsaved: 0,
+            last_saved: Instant::now(),
+        };
+        if !path.exists() {
+            return Ok(checkpoint);
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            SpectrsError::io(format!("Failed to read checkpoint {}", path.display()), e)
+        })?;
+        let file: CheckpointFile = serde_json::from_str(&content).map_err(|e| {
+            SpectrsError::invalid_data(format!("Invalid checkpoint {}", path.display()), e)
+        })?;
+        if file.schema_version > SCHEMA_VERSION {
+            return Err(SpectrsError::InvalidData {
+                message: format!(
+                    "Checkpoint {} was written by a newer version of spectrs ({})",
+                    path.display(),
+                    file.spectrs_version
+                ),
+                source: None,
+            });
+        }
+        if file.params != checkpoint.params {
+            return Err(SpectrsError::InvalidData {
+                message: format!(
+                    "Checkpoint {} was written with other parameters; delete it to start over",
+                    path.display()
+                ),
+                source: None,
+            });
+        }
+        for input in file.completed {
+            checkpoint.insert(input);
+        }
+        Ok(checkpoint)
+    }
+
+    /// Whether the segment of an input was completed
+    pub fn is_completed(&self, input: &str, segment: (Option<f32>, Option<f32>)) -> bool {
+        let key = (
+            input.to_string(),
+            segment.0.map(f32::to_bits),
+            segment.1.map(f32::to_bits),
+        );
+        self.keys.contains(&key)
+    }
+
+    /// Record the segment of an input as completed (saved by the next `save`)
+    pub fn complete(&mut self, input: impl Into<String>, segment: (Option<f32>, Option<f32>)) {
+        let input = CompletedInput {
+            input: input.into(),
+            start: segment.0,
+            end: segment.1,
+        };
+        if self.insert(input) {
+            self.unsaved += 1;
+        }
+    }
+
+    fn insert(&mut self, input: CompletedInput) -> bool {
+        let inserted = self.keys.insert(input.key());
+        if inserted {
+            self.completed.push(input);
+        }
+        inserted
+    }
+
+    /// Inputs completed, in the order they were recorded
+    pub fn completed(&self) -> &[CompletedInput] {
+        &self.completed
+    }
+
+    pub fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+    }
+
+    /// Write the checkpoint file. It is replaced at once, so that an interruption never leaves
+    /// it half written
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                SpectrsError::io(
+                    format!("Failed to create directory: {}", parent.display()),
+                    e,
+                )
+            })?;
+        }
+        let file = CheckpointFile {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            params: self.params.clone(),
+            completed: self.completed.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| SpectrsError::invalid_data("Failed to serialize the checkpoint", e))?;
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        std::fs::write(&temporary, json + "\n")
+            .and_then(|_| std::fs::rename(&temporary, &self.path))
+            .map_err(|e| {
+                SpectrsError::io(
+                    format!("Failed to write checkpoint {}", self.path.display()),
+                    e,
+                )
+            })?;
+        self.unsaved = 0;
+        self.last_saved = Instant::now();
+        Ok(())
+    }
+
+    /// Save the checkpoint if inputs were completed since the last save and `interval` has
+    /// elapsed since then. Returns whether it was saved
+    pub fn save_if_due(&mut self, interval: Duration) -> Result<bool> {
+        if self.unsaved == 0 || self.last_saved.elapsed() < interval {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,207 @@
+//! Dataset of spectrograms in a single Parquet file (enabled with the `dataset` feature), so
+//! that a whole corpus can be loaded with pandas, polars or Arrow in one read rather than from
+//! thousands of small files.
+//!
+//! Every spectrogram is one row: the path and label of its source, its sample rate, its shape
+//! and its values flattened row by row ([freq][time], so value `t` of band `k` is at
+//! `k * n_frames + t`). The parameters shared by every row are stored as JSON in the metadata
+//! of the file, under `spectrs.params`. Rows are written in row groups as they are appended,
+//! so the dataset never has to be held in memory. Paths ending in .arrow, .feather or .ipc
+//! hold the same rows as an Arrow IPC file instead (see `io::arrow`).
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::params::SpectrogramParams;
+use std::path::Path;
+
+/// Key of the parameters in the metadata of the file
+pub const PARAMS_KEY: &str = "spectrs.params";
+/// Key of the version of spectrs that wrote the file
+pub const VERSION_KEY: &str = "spectrs.version";
+/// Rows buffered before they are written as a row group
+#[cfg(feature = "dataset")]
+const ROW_GROUP_SIZE: usize = 64;
+
+/// Spectrogram read back from a dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetEntry {
+    /// Path (or name) of the source
+    pub path: String,
+    /// Label of the source, e.g. the directory it was found in
+    pub label: String,
+    pub sample_rate: u32,
+    /// Values in [freq][time] format
+    pub spectrogram: Vec<Vec<f32>>,
+}
+
+/// Contents of a dataset file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    /// Parameters the spectrograms were computed with (None if the file does not record them)
+    pub params: Option<SpectrogramParams>,
+    pub entries: Vec<DatasetEntry>,
+}
+
+/// Writer appending spectrograms to a Parquet (or Arrow IPC) dataset
+#[cfg(feature = "dataset")]
+pub struct DatasetWriter {
+    format: DatasetFormat,
+}
+
+#[cfg(feature = "dataset")]
+// Boxed: the writers are large, and of very different sizes
+enum DatasetFormat {
+    Parquet(Box<parquet::arrow::ArrowWriter<std::fs::File>>),
+    Ipc(Box<crate::io::arrow::IpcWriter<std::io::BufWriter<std::fs::File>>>),
+}
+
+#[cfg(feature = "dataset")]
+impl DatasetWriter {
+    /// Create (or truncate) the dataset at `path`, recording `params` in its metadata. Paths
+    /// ending in .arrow, .feather or .ipc are written as Arrow IPC files
+    pub fn create(path: &Path, params: &SpectrogramParams) -> Result<Self> {
+        use crate::io::arrow::{IpcWriter, is_ipc_path, spectrogram_schema};
+        use parquet::file::metadata::KeyValue;
+        use parquet::file::properties::WriterProperties;
+
+        if is_ipc_path(path) {
+            return Ok(Self {
+                format: DatasetFormat::Ipc(Box::new(IpcWriter::create(path, params)?)),
+            });
+        }
+        let params = serde_json::to_string(params)
+            .map_err(|e| SpectrsError::dataset("Failed to serialize parameters", e))?;
+        let properties = WriterProperties::builder()
+            .set_compression(parquet::basic::Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .set_key_value_metadata(Some(vec![
+                KeyValue::new(PARAMS_KEY.to_string(), params),
+                KeyValue::new(
+                    VERSION_KEY.to_string(),
+                    env!("CARGO_PKG_VERSION").to_string(),
+                ),
+            ]))
+            .build();
+        let file = std::fs::File::create(path)
+            .map_err(|e| SpectrsError::io(format!("Failed to create {}", path.display()), e))?;
+        let writer = parquet::arrow::ArrowWriter::try_new(
+            file,
+            std::sync::Arc::new(spectrogram_schema()),
+            Some(properties),
+        )
+        .map_err(|e| SpectrsError::dataset("Failed to start the dataset", e))?;
+        Ok(Self {
+            format: DatasetFormat::Parquet(Box::new(writer)),
+        })
+    }
+
+    /// Append the [freq][time] spectrogram of a source as a new row
+    pub fn append(
+        &mut self,
+        path: &str,
+        label: &str,
+        sample_rate: u32,
+        spectrogram: &[Vec<f32>],
+    ) -> Result<()> {
+        use crate::io::arrow::SpectrogramBatchBuilder;
+
+        match &mut self.format {
+            DatasetFormat::Parquet(writer) => {
+                let mut row = SpectrogramBatchBuilder::new();
+                row.append(path, label, sample_rate, spectrogram)?;
+                writer
+                    .write(&row.finish()?)
+                    .map_err(|e| SpectrsError::dataset("Failed to write to the dataset", e))
+            }
+            DatasetFormat::Ipc(writer) => writer.append(path, label, sample_rate, spectrogram),
+        }
+    }
+
+    /// Write the remaining rows and the footer of the file
+    pub fn finish(self) -> Result<()> {
+        match self.format {
+            DatasetFormat::Parquet(writer) => writer
+                .close()
+                .map(|_| ())
+                .map_err(|e| SpectrsError::dataset("Failed to finish the dataset", e)),
+            DatasetFormat::Ipc(writer) => writer.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Read a whole dataset written by `DatasetWriter`
+#[cfg(feature = "dataset")]
+pub fn load_dataset(path: &Path) -> Result<Dataset> {
+    use crate::io::arrow::{batch_entries, is_ipc_path, load_ipc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    if is_ipc_path(path) {
+        return load_ipc(path);
+    }
+    let file = std::fs::File::open(path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", path.display()), e))?;
+    let read_error = |e: parquet::errors::ParquetError| {
+        SpectrsError::invalid_data(format!("Failed to read dataset {}", path.display()), e)
+    };
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(read_error)?;
+    let params = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == PARAMS_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| SpectrsError::invalid_data("Invalid parameters in dataset", e))?;
+
+    let mut entries = Vec::new();
+    for batch in builder.build().map_err(read_error)? {
+        let batch = batch.map_err(|e| {
+            SpectrsError::invalid_data(format!("Failed to read dataset {}", path.display()), e)
+        })?;
+        entries.extend(
+            batch_entries(&batch).map_err(|e| e.context(format!("Dataset {}", path.display())))?,
+        );
+    }
+    Ok(Dataset { params, entries })
+}
+
+#[cfg(not(feature = "dataset"))]
+pub struct DatasetWriter {
+    _private: (),
+}
+
+#[cfg(not(feature = "dataset"))]
+impl DatasetWriter {
+    pub fn create(_path: &Path, _params: &SpectrogramParams) -> Result<Self> {
+        Err(not_enabled())
+    }
+
+    pub fn append(
+        &mut self,
+        _path: &str,
+        _label: &str,
+        _sample_rate: u32,
+        _spectrogram: &[Vec<f32>],
+    ) -> Result<()> {
+        Err(not_enabled())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        Err(not_enabled())
+    }
+}
+
+#[cfg(not(feature = "dataset"))]
+pub fn load_dataset(_path: &Path) -> Result<Dataset> {
+    Err(not_enabled())
+}
+
+#[cfg(not(feature = "dataset"))]
+fn not_enabled() -> SpectrsError {
+    SpectrsError::Dataset {
+        message:
+            "Dataset feature not enabled. Compile with --features dataset to use this function."
+                .to_string(),
+        source: None,
+    }
+}
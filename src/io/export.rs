@@ -0,0 +1,988 @@
+use crate::error::{Result, SpectrsError};
+#[cfg(feature = "fs")]
+use crate::features::FeatureSummary;
+#[cfg(feature = "fs")]
+use crate::features::cepstrum::CepstralPeak;
+#[cfg(feature = "fs")]
+use crate::features::formant::{FormantTrack, TRACKED_FORMANTS};
+#[cfg(feature = "fs")]
+use crate::features::loudness::Loudness;
+#[cfg(feature = "fs")]
+use crate::features::octave::BandLevels;
+#[cfg(feature = "fs")]
+use crate::features::onset::Onset;
+#[cfg(feature = "fs")]
+use crate::features::pitch::PitchTrack;
+#[cfg(feature = "fs")]
+use crate::io::atomic::write_atomically;
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::{BufRead, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Formatting options for CSV exports.
+/// Numbers are always formatted independently of the system locale; the decimal separator and
+/// field delimiter are chosen explicitly so files import cleanly in e.g. European-locale
+/// spreadsheets (`;` delimiter with `,` decimal separator).
+#[derive(Debug, Clone, Copy)]
+pub struct CsvOptions {
+    /// Field delimiter
+    pub delimiter: char,
+    /// Decimal separator
+    pub decimal: char,
+    /// Number of digits after the decimal separator (None for the shortest exact representation)
+    pub precision: Option<usize>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            decimal: '.',
+            precision: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Check that the options produce unambiguous files
+    pub fn validate(&self) -> Result<()> {
+        if self.delimiter == self.decimal {
+            return Err(SpectrsError::InvalidParams(format!(
+                "CSV delimiter and decimal separator must differ (both are '{}')",
+                self.delimiter
+            )));
+        }
+        if self.decimal.is_ascii_digit() || self.decimal == '-' {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid decimal separator '{}'",
+                self.decimal
+            )));
+        }
+        if self.delimiter.is_ascii_digit() || self.delimiter == '-' || self.delimiter == '\n' {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid CSV delimiter '{}'",
+                self.delimiter
+            )));
+        }
+        Ok(())
+    }
+
+    /// Format a single number according to the options
+    pub fn format_value<T: fmt::Display>(&self, value: T) -> String {
+        let formatted = match self.precision {
+            Some(precision) => format!("{:.*}", precision, value),
+            None => value.to_string(),
+        };
+        if self.decimal == '.' {
+            formatted
+        } else {
+            formatted.replace('.', &self.decimal.to_string())
+        }
+    }
+
+    /// Quote a text field if it contains the delimiter, quotes or line breaks
+    pub fn quote(&self, field: &str) -> String {
+        if field.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    /// Join already formatted fields with the delimiter
+    pub fn join<S: AsRef<str>>(&self, fields: &[S]) -> String {
+        let delimiter = self.delimiter.to_string();
+        fields
+            .iter()
+            .map(|f| f.as_ref())
+            .collect::<Vec<_>>()
+            .join(&delimiter)
+    }
+}
+
+/// Save a spectrogram as CSV: one line per frequency bin (lowest frequency first) and one
+/// column per time frame
+#[cfg(feature = "fs")]
+pub fn save_spectrogram_csv<T: fmt::Display>(
+    spectrogram: &[Vec<T>],
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        write_spectrogram_csv(spectrogram, &mut writer, options)
+    })
+}
+
+/// Write a spectrogram as CSV (same layout as `save_spectrogram_csv`) to any writer, e.g.
+/// stdout
+pub fn write_spectrogram_csv<T: fmt::Display, W: Write>(
+    spectrogram: &[Vec<T>],
+    mut writer: W,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+
+    for row in spectrogram {
+        let fields: Vec<String> = row.iter().map(|v| options.format_value(v)).collect();
+        writeln!(writer, "{}", options.join(&fields))
+            .map_err(|e| SpectrsError::io("Failed to write CSV", e))?;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| SpectrsError::io("Failed to write CSV", e))?;
+
+    Ok(())
+}
+
+/// Load a spectrogram saved with `save_spectrogram_csv` with the same `options` (the
+/// precision is irrelevant)
+#[cfg(feature = "fs")]
+pub fn load_spectrogram_csv(input_path: &Path, options: &CsvOptions) -> Result<Vec<Vec<f32>>> {
+    let file = File::open(input_path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", input_path.display()), e))?;
+    read_spectrogram_csv(std::io::BufReader::new(file), options)
+        .map_err(|e| e.context(input_path.display()))
+}
+
+/// Read a spectrogram in CSV (see `load_spectrogram_csv`) from any reader, e.g. stdin
+pub fn read_spectrogram_csv<R: BufRead>(reader: R, options: &CsvOptions) -> Result<Vec<Vec<f32>>> {
+    options.validate()?;
+
+    let mut spectrogram: Vec<Vec<f32>> = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| SpectrsError::io("Failed to read CSV", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = line
+            .split(options.delimiter)
+            .map(|field| {
+                let field = field.trim();
+                let normalized = match options.decimal {
+                    '.' => field.to_string(),
+                    decimal => field.replace(decimal, "."),
+                };
+                normalized
+                    .parse::<f32>()
+                    .map_err(|e| SpectrsError::InvalidData {
+                        message: format!("Invalid value {:?} on line {}", field, index + 1),
+                        source: Some(Box::new(e)),
+                    })
+            })
+            .collect::<Result<Vec<f32>>>()?;
+        if spectrogram
+            .first()
+            .is_some_and(|first| first.len() != row.len())
+        {
+            return Err(SpectrsError::InvalidData {
+                message: format!(
+                    "Line {} has {} values, expected {}",
+                    index + 1,
+                    row.len(),
+                    spectrogram[0].len()
+                ),
+                source: None,
+            });
+        }
+        spectrogram.push(row);
+    }
+
+    Ok(spectrogram)
+}
+
+/// Save a spectrogram as JSON: an object with the shape (n_freq_bins, n_frames) and the rows
+/// (lowest frequency first) under `data`, readable with `load_spectrogram_json`
+#[cfg(feature = "fs")]
+pub fn save_spectrogram_json<T: serde::Serialize>(
+    spectrogram: &[Vec<T>],
+    output_path: &Path,
+) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        write_spectrogram_json(spectrogram, &mut writer)
+    })
+}
+
+/// Write a spectrogram as JSON (same layout as `save_spectrogram_json`) to any writer, e.g.
+/// stdout
+pub fn write_spectrogram_json<T: serde::Serialize, W: Write>(
+    spectrogram: &[Vec<T>],
+    mut writer: W,
+) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Document<'a, T> {
+        shape: [usize; 2],
+        data: &'a [Vec<T>],
+    }
+
+    let n_cols = spectrogram.first().map_or(0, Vec::len);
+    if spectrogram.iter().any(|row| row.len() != n_cols) {
+        return Err(SpectrsError::InvalidParams(
+            "All rows of the spectrogram must have the same length".to_string(),
+        ));
+    }
+    let document = Document {
+        shape: [spectrogram.len(), n_cols],
+        data: spectrogram,
+    };
+    serde_json::to_writer(&mut writer, &document)
+        .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+    writer
+        .flush()
+        .map_err(|e| SpectrsError::io("Failed to write JSON", e))?;
+
+    Ok(())
+}
+
+/// Load a spectrogram stored as JSON, either a bare array of rows or an object with the rows
+/// under `data` (`{"data": [[...], ...], "shape": [n_rows, n_frames]}`, the layout of the
+/// librosa and torchaudio comparison scripts)
+#[cfg(feature = "fs")]
+pub fn load_spectrogram_json(input_path: &Path) -> Result<Vec<Vec<f32>>> {
+    let file = File::open(input_path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", input_path.display()), e))?;
+    read_spectrogram_json(std::io::BufReader::new(file))
+        .map_err(|e| e.context(input_path.display()))
+}
+
+/// Read a spectrogram stored as JSON (see `load_spectrogram_json`) from any reader
+pub fn read_spectrogram_json<R: Read>(reader: R) -> Result<Vec<Vec<f32>>> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Document {
+        Rows(Vec<Vec<f32>>),
+        Object { data: Vec<Vec<f32>> },
+    }
+
+    let document: Document =
+        serde_json::from_reader(reader).map_err(|e| SpectrsError::InvalidData {
+            message: "Invalid spectrogram JSON".to_string(),
+            source: Some(Box::new(e)),
+        })?;
+    let spectrogram = match document {
+        Document::Rows(rows) | Document::Object { data: rows } => rows,
+    };
+    let n_cols = spectrogram.first().map_or(0, Vec::len);
+    if spectrogram.iter().any(|row| row.len() != n_cols) {
+        return Err(SpectrsError::InvalidData {
+            message: "All rows of the spectrogram must have the same length".to_string(),
+            source: None,
+        });
+    }
+    Ok(spectrogram)
+}
+
+/// Load a spectrogram from a `.npy`, `.csv`, `.json` or `.png` file (with its sidecar, see
+/// `load_spectrogram_image`), chosen by extension. `options` describe the number formatting of
+/// CSV files.
+#[cfg(feature = "fs")]
+pub fn load_spectrogram(input_path: &Path, options: &CsvOptions) -> Result<Vec<Vec<f32>>> {
+    match input_path.extension().and_then(|ext| ext.to_str()) {
+        Some("npy") => load_spectrogram_npy(input_path),
+        Some("csv") => load_spectrogram_csv(input_path, options),
+        Some("json") => load_spectrogram_json(input_path),
+        Some("png") => crate::io::image::load_spectrogram_image(input_path),
+        _ => Err(SpectrsError::UnsupportedFormat(format!(
+            "{}: expected a .npy, .csv, .json or .png spectrogram",
+            input_path.display()
+        ))),
+    }
+}
+
+/// Floating-point type of exported values: `f32` (written as float32) or `f64` (float64)
+pub trait NpyFloat: Copy + fmt::Display {
+    /// Data type of the `.npy` header
+    const DESCR: &'static str;
+
+    /// Append the little-endian bytes of the value
+    fn extend_le_bytes(self, bytes: &mut Vec<u8>);
+}
+
+impl NpyFloat for f32 {
+    const DESCR: &'static str = "<f4";
+
+    fn extend_le_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyFloat for f64 {
+    const DESCR: &'static str = "<f8";
+
+    fn extend_le_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// Save a spectrogram as a NumPy `.npy` file: a float32 (or, for `f64` values, float64) array
+/// of shape (n_freq_bins, n_frames), readable with `numpy.load`
+#[cfg(feature = "fs")]
+pub fn save_spectrogram_npy<T: NpyFloat>(spectrogram: &[Vec<T>], output_path: &Path) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "NPY")?);
+        write_spectrogram_npy(spectrogram, &mut writer)
+    })
+}
+
+/// Write a spectrogram in the `.npy` format (same layout as `save_spectrogram_npy`) to any
+/// writer, e.g. stdout
+pub fn write_spectrogram_npy<T: NpyFloat, W: Write>(
+    spectrogram: &[Vec<T>],
+    mut writer: W,
+) -> Result<()> {
+    let n_rows = spectrogram.len();
+    let n_cols = spectrogram.first().map_or(0, Vec::len);
+    if spectrogram.iter().any(|row| row.len() != n_cols) {
+        return Err(SpectrsError::InvalidParams(
+            "All rows of the spectrogram must have the same length".to_string(),
+        ));
+    }
+
+    // Version 1.0 header: magic, version, header length, then a Python dict literal padded
+    // with spaces so that the data starts on a 64-byte boundary
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        T::DESCR,
+        n_rows,
+        n_cols
+    );
+    let unpadded = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let write_error = |e| SpectrsError::io("Failed to write NPY", e);
+    writer.write_all(NPY_MAGIC).map_err(write_error)?;
+    writer.write_all(&[1, 0]).map_err(write_error)?;
+    writer
+        .write_all(&(header.len() as u16).to_le_bytes())
+        .map_err(write_error)?;
+    writer.write_all(header.as_bytes()).map_err(write_error)?;
+    let mut bytes = Vec::new();
+    for row in spectrogram {
+        bytes.clear();
+        row.iter().for_each(|v| v.extend_le_bytes(&mut bytes));
+        writer.write_all(&bytes).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)?;
+
+    Ok(())
+}
+
+/// Load a spectrogram saved with `save_spectrogram_npy` or `NpyFrameWriter` (or any 2-D
+/// little-endian float32 or float64 `.npy` array, in C or Fortran order) as [row][column].
+/// float64 values are rounded to float32.
+#[cfg(feature = "fs")]
+pub fn load_spectrogram_npy(input_path: &Path) -> Result<Vec<Vec<f32>>> {
+    let file = File::open(input_path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", input_path.display()), e))?;
+    read_spectrogram_npy(std::io::BufReader::new(file)).map_err(|e| e.context(input_path.display()))
+}
+
+/// Read a spectrogram in the `.npy` format (see `load_spectrogram_npy`) from any reader, e.g.
+/// stdin
+pub fn read_spectrogram_npy<R: Read>(mut reader: R) -> Result<Vec<Vec<f32>>> {
+    let read_error = |e| SpectrsError::io("Failed to read NPY", e);
+    let invalid_header = |what: &str| SpectrsError::InvalidData {
+        message: format!("Invalid NPY header: {}", what),
+        source: None,
+    };
+
+    let mut prefix = [0u8; 8];
+    reader.read_exact(&mut prefix).map_err(read_error)?;
+    if &prefix[..NPY_MAGIC.len()] != NPY_MAGIC {
+        return Err(SpectrsError::UnsupportedFormat(
+            "Not a NPY file".to_string(),
+        ));
+    }
+    // Version 1 stores the header length on 2 bytes, later versions on 4
+    let header_len = match prefix[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len).map_err(read_error)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len).map_err(read_error)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => {
+            return Err(SpectrsError::UnsupportedFormat(format!(
+                "Unsupported NPY version {}",
+                version
+            )));
+        }
+    };
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header).map_err(read_error)?;
+    let header = String::from_utf8_lossy(&header);
+
+    // The header is a Python dict literal, e.g.
+    // {'descr': '<f4', 'fortran_order': False, 'shape': (128, 431), }
+    let field = |key: &str| {
+        header
+            .split_once(&format!("'{}':", key))
+            .map(|(_, rest)| rest.trim_start())
+    };
+    let descr = field("descr").ok_or_else(|| invalid_header("missing descr"))?;
+    let item_size = if descr.starts_with("'<f4'") {
+        4
+    } else if descr.starts_with("'<f8'") {
+        8
+    } else {
+        return Err(SpectrsError::UnsupportedFormat(format!(
+            "Unsupported NPY data type {} (expected little-endian float32 or float64)",
+            descr.split(',').next().unwrap_or(descr)
+        )));
+    };
+    let fortran_order = field("fortran_order")
+        .ok_or_else(|| invalid_header("missing fortran_order"))?
+        .starts_with("True");
+    let shape: Vec<usize> = field("shape")
+        .and_then(|rest| rest.strip_prefix('('))
+        .and_then(|rest| rest.split_once(')'))
+        .ok_or_else(|| invalid_header("missing shape"))?
+        .0
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().map_err(|_| invalid_header("invalid shape")))
+        .collect::<Result<_>>()?;
+    let [n_rows, n_cols] = shape[..] else {
+        return Err(SpectrsError::UnsupportedFormat(format!(
+            "Expected a 2-D array, got {} dimensions",
+            shape.len()
+        )));
+    };
+
+    let mut bytes = vec![0u8; n_rows * n_cols * item_size];
+    reader.read_exact(&mut bytes).map_err(read_error)?;
+    let value = |i: usize| {
+        let item = &bytes[item_size * i..item_size * (i + 1)];
+        match item_size {
+            4 => f32::from_le_bytes(item.try_into().unwrap()),
+            _ => f64::from_le_bytes(item.try_into().unwrap()) as f32,
+        }
+    };
+    Ok((0..n_rows)
+        .map(|row| {
+            (0..n_cols)
+                .map(|col| match fortran_order {
+                    true => value(col * n_rows + row),
+                    false => value(row * n_cols + col),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Magic string opening every `.npy` file
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Size of the `NpyFrameWriter` header, large enough for any shape so that it can be
+/// rewritten in place
+#[cfg(feature = "fs")]
+const NPY_GROWING_HEADER_LEN: usize = 128;
+
+/// `.npy` file growing one frame at a time, e.g. while audio is being captured. The array has
+/// the same (n_freq_bins, n_frames) shape as `save_spectrogram_npy`, stored in Fortran
+/// (column-major) order so that every frame is appended at the end of the file. The header is
+/// updated by `flush`, after which the file is a valid array of the frames pushed so far.
+#[cfg(feature = "fs")]
+pub struct NpyFrameWriter {
+    writer: BufWriter<File>,
+    n_rows: usize,
+    n_frames: usize,
+}
+
+#[cfg(feature = "fs")]
+impl NpyFrameWriter {
+    /// Create the file for frames of `n_rows` values
+    pub fn create(output_path: &Path, n_rows: usize) -> Result<Self> {
+        let mut npy = Self {
+            writer: BufWriter::new(create_output_file(output_path, "NPY")?),
+            n_rows,
+            n_frames: 0,
+        };
+        npy.write_header()?;
+        Ok(npy)
+    }
+
+    /// Number of frames pushed so far
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    /// Append a frame of `n_rows` values
+    pub fn push(&mut self, frame: &[f32]) -> Result<()> {
+        if frame.len() != self.n_rows {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Frame has {} values, expected {}",
+                frame.len(),
+                self.n_rows
+            )));
+        }
+        let bytes: Vec<u8> = frame.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.writer
+            .write_all(&bytes)
+            .map_err(|e| SpectrsError::io("Failed to write NPY", e))?;
+        self.n_frames += 1;
+        Ok(())
+    }
+
+    /// Write the buffered frames and update the shape in the header
+    pub fn flush(&mut self) -> Result<()> {
+        use std::io::{Seek, SeekFrom};
+
+        let write_error = |e| SpectrsError::io("Failed to write NPY", e);
+        self.writer.flush().map_err(write_error)?;
+        self.writer.seek(SeekFrom::Start(0)).map_err(write_error)?;
+        self.write_header()?;
+        self.writer.flush().map_err(write_error)?;
+        self.writer.seek(SeekFrom::End(0)).map_err(write_error)?;
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let mut header = format!(
+            "{{'descr': '<f4', 'fortran_order': True, 'shape': ({}, {}), }}",
+            self.n_rows, self.n_frames
+        );
+        let prefix = NPY_MAGIC.len() + 2 + 2;
+        header.push_str(&" ".repeat(NPY_GROWING_HEADER_LEN - prefix - header.len() - 1));
+        header.push('\n');
+
+        let write_error = |e| SpectrsError::io("Failed to write NPY", e);
+        self.writer.write_all(NPY_MAGIC).map_err(write_error)?;
+        self.writer.write_all(&[1, 0]).map_err(write_error)?;
+        self.writer
+            .write_all(&(header.len() as u16).to_le_bytes())
+            .map_err(write_error)?;
+        self.writer
+            .write_all(header.as_bytes())
+            .map_err(write_error)?;
+        Ok(())
+    }
+}
+
+/// Create an output file, and its parent directories if necessary
+#[cfg(feature = "fs")]
+fn create_output_file(output_path: &Path, kind: &str) -> Result<File> {
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            SpectrsError::io(
+                format!("Failed to create directory: {}", parent.display()),
+                e,
+            )
+        })?;
+    }
+    File::create(output_path)
+        .map_err(|e| SpectrsError::io(format!("Failed to create {} file", kind), e))
+}
+
+/// Save per-file feature summaries as CSV: a header line, then one line per file. The loudness
+/// columns follow the descriptors when any file was metered (empty for the others).
+#[cfg(feature = "fs")]
+pub fn save_feature_table(
+    rows: &[(String, FeatureSummary)],
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        write_feature_table(rows, writer, options)
+    })
+}
+
+/// Write per-file feature summaries as CSV (same layout as `save_feature_table`) to any writer,
+/// e.g. stdout
+#[cfg(feature = "fs")]
+pub fn write_feature_table<W: Write>(
+    rows: &[(String, FeatureSummary)],
+    mut writer: W,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+
+    let loudness = rows.iter().any(|(_, summary)| summary.loudness.is_some());
+    let header: Vec<&str> = std::iter::once("file")
+        .chain(FeatureSummary::COLUMNS)
+        .chain(Loudness::COLUMNS.into_iter().filter(|_| loudness))
+        .collect();
+    writeln!(writer, "{}", options.join(&header))
+        .map_err(|e| SpectrsError::io("Failed to write CSV", e))?;
+
+    for (name, summary) in rows {
+        let levels = match &summary.loudness {
+            Some(levels) => levels.values().map(|v| options.format_value(v)),
+            None => Default::default(),
+        };
+        let fields: Vec<String> = std::iter::once(options.quote(name))
+            .chain(summary.values().iter().map(|&v| options.format_value(v)))
+            .chain(levels.into_iter().filter(|_| loudness))
+            .collect();
+        writeln!(writer, "{}", options.join(&fields))
+            .map_err(|e| SpectrsError::io("Failed to write CSV", e))?;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| SpectrsError::io("Failed to write CSV", e))?;
+
+    Ok(())
+}
+
+/// Save per-file feature summaries as a JSON array of records: the file, the columns of the
+/// CSV table, and the loudness of metered files with their short-term loudness over time.
+/// Non-finite levels (e.g. the loudness of silence) are written as null.
+#[cfg(feature = "fs")]
+pub fn save_feature_json(rows: &[(String, FeatureSummary)], output_path: &Path) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        write_feature_json(rows, writer)
+    })
+}
+
+/// Write per-file feature summaries as JSON (same layout as `save_feature_json`) to any
+/// writer, e.g. stdout
+#[cfg(feature = "fs")]
+pub fn write_feature_json<W: Write>(
+    rows: &[(String, FeatureSummary)],
+    mut writer: W,
+) -> Result<()> {
+    let records: Vec<FeatureRecord> = rows
+        .iter()
+        .map(|(name, summary)| FeatureRecord { name, summary })
+        .collect();
+    serde_json::to_writer(&mut writer, &records)
+        .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+    writer
+        .flush()
+        .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+}
+
+/// Record of one file in `write_feature_json`
+#[cfg(feature = "fs")]
+struct FeatureRecord<'a> {
+    name: &'a str,
+    summary: &'a FeatureSummary,
+}
+
+#[cfg(feature = "fs")]
+impl serde::Serialize for FeatureRecord<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("file", self.name)?;
+        for (column, value) in FeatureSummary::COLUMNS.iter().zip(self.summary.values()) {
+            map.serialize_entry(column, &value)?;
+        }
+        if let Some(loudness) = &self.summary.loudness {
+            map.serialize_entry("loudness", loudness)?;
+        }
+        map.end()
+    }
+}
+
+/// Save a pitch track as CSV: a `time_s,f0_hz` header, then one line per frame (with an empty
+/// pitch where unvoiced)
+#[cfg(feature = "fs")]
+pub fn save_pitch_csv(track: &PitchTrack, output_path: &Path, options: &CsvOptions) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        writeln!(writer, "{}", options.join(&["time_s", "f0_hz"])).map_err(write_error)?;
+        for (&time, f0) in track.times.iter().zip(&track.f0) {
+            let f0 = f0.map(|f0| options.format_value(f0)).unwrap_or_default();
+            writeln!(
+                writer,
+                "{}",
+                options.join(&[options.format_value(time), f0])
+            )
+            .map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// Save a pitch track as JSON: `{"times": [...], "f0": [...]}`, with null pitches where
+/// unvoiced
+#[cfg(feature = "fs")]
+pub fn save_pitch_json(track: &PitchTrack, output_path: &Path) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        serde_json::to_writer(&mut writer, track)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+    })
+}
+
+/// Save a formant track as CSV: a `time_s,f1_hz,b1_hz,f2_hz,b2_hz,f3_hz,b3_hz` header, then
+/// one line per frame with the frequency and bandwidth of F1 to F3, empty where not found
+#[cfg(feature = "fs")]
+pub fn save_formants_csv(
+    track: &FormantTrack,
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        let header: Vec<String> = std::iter::once("time_s".to_string())
+            .chain((1..=TRACKED_FORMANTS).flat_map(|i| [format!("f{i}_hz"), format!("b{i}_hz")]))
+            .collect();
+        writeln!(writer, "{}", options.join(&header)).map_err(write_error)?;
+        for (&time, formants) in track.times.iter().zip(&track.formants) {
+            let row: Vec<String> = std::iter::once(options.format_value(time))
+                .chain((0..TRACKED_FORMANTS).flat_map(|i| match formants.get(i) {
+                    Some(formant) => [
+                        options.format_value(formant.frequency),
+                        options.format_value(formant.bandwidth),
+                    ],
+                    None => [String::new(), String::new()],
+                }))
+                .collect();
+            writeln!(writer, "{}", options.join(&row)).map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// Save a formant track as JSON: `{"times": [...], "formants": [[{"frequency", "bandwidth"},
+/// ...], ...]}`, with every formant found in each frame
+#[cfg(feature = "fs")]
+pub fn save_formants_json(track: &FormantTrack, output_path: &Path) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        serde_json::to_writer(&mut writer, track)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+    })
+}
+
+/// Save onsets as CSV: a `frame,time_s,strength` header, then one line per onset
+#[cfg(feature = "fs")]
+pub fn save_onsets_csv(onsets: &[Onset], output_path: &Path, options: &CsvOptions) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        writeln!(writer, "{}", options.join(&["frame", "time_s", "strength"]))
+            .map_err(write_error)?;
+        for onset in onsets {
+            writeln!(
+                writer,
+                "{}",
+                options.join(&[
+                    onset.frame.to_string(),
+                    options.format_value(onset.time),
+                    options.format_value(onset.strength),
+                ])
+            )
+            .map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// Save onsets as JSON: `[{"frame": ..., "time": ..., "strength": ...}, ...]`
+#[cfg(feature = "fs")]
+pub fn save_onsets_json(onsets: &[Onset], output_path: &Path) -> Result<()> {
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        serde_json::to_writer(&mut writer, onsets)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+    })
+}
+
+/// Save band levels as CSV: a header of `time_s` and the nominal midband frequency of every
+/// band (e.g. `31.5_hz`), then one line per frame with the level of every band (dB)
+#[cfg(feature = "fs")]
+pub fn save_band_levels_csv(
+    levels: &BandLevels,
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        let header: Vec<String> = std::iter::once("time_s".to_string())
+            .chain(levels.nominal_hz.iter().map(|hz| format!("{}_hz", hz)))
+            .collect();
+        writeln!(writer, "{}", options.join(&header)).map_err(write_error)?;
+        let db = levels.levels_db();
+        for (frame, &time) in levels.times.iter().enumerate() {
+            let row: Vec<String> = std::iter::once(options.format_value(time))
+                .chain(db.iter().map(|band| options.format_value(band[frame])))
+                .collect();
+            writeln!(writer, "{}", options.join(&row)).map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// Save band levels as JSON: `{"fraction", "bands_hz", "center_hz", "times", "levels_db",
+/// "leq_db"}`, the levels in [band][frame] layout
+#[cfg(feature = "fs")]
+pub fn save_band_levels_json(levels: &BandLevels, output_path: &Path) -> Result<()> {
+    let value = serde_json::json!({
+        "fraction": levels.fraction,
+        "bands_hz": levels.nominal_hz,
+        "center_hz": levels.center_hz,
+        "times": levels.times,
+        "levels_db": levels.levels_db(),
+        "leq_db": levels.leq_db(),
+    });
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        serde_json::to_writer(&mut writer, &value)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+    })
+}
+
+/// Save cepstral peaks as CSV: a `time_s,f0_hz,cpp_db` header, then one line per frame at the
+/// matching time of `times`
+#[cfg(feature = "fs")]
+pub fn save_cpp_csv(
+    times: &[f32],
+    peaks: &[CepstralPeak],
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        writeln!(writer, "{}", options.join(&["time_s", "f0_hz", "cpp_db"]))
+            .map_err(write_error)?;
+        for (&time, peak) in times.iter().zip(peaks) {
+            writeln!(
+                writer,
+                "{}",
+                options.join(&[
+                    options.format_value(time),
+                    options.format_value(peak.f0),
+                    options.format_value(peak.cpp_db),
+                ])
+            )
+            .map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// Save cepstral peaks as JSON: `{"times", "f0", "cpp_db", "cpp_mean_db"}`, the last being the
+/// mean prominence over the frames
+#[cfg(feature = "fs")]
+pub fn save_cpp_json(times: &[f32], peaks: &[CepstralPeak], output_path: &Path) -> Result<()> {
+    let cpp_db: Vec<f32> = peaks.iter().map(|peak| peak.cpp_db).collect();
+    let value = serde_json::json!({
+        "times": times,
+        "f0": peaks.iter().map(|peak| peak.f0).collect::<Vec<f32>>(),
+        "cpp_mean_db": cpp_db.iter().sum::<f32>() / cpp_db.len().max(1) as f32,
+        "cpp_db": cpp_db,
+    });
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "JSON")?);
+        serde_json::to_writer(&mut writer, &value)
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e.into()))?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io("Failed to write JSON", e))
+    })
+}
+
+/// Save an average spectrum as CSV: a `frequency_hz,mean,mean_db` header, then one line per
+/// band (or bin) at the matching frequency of `frequencies`
+#[cfg(feature = "fs")]
+pub fn save_average_spectrum_csv(
+    frequencies: &[f32],
+    mean: &[f32],
+    mean_db: &[f32],
+    output_path: &Path,
+    options: &CsvOptions,
+) -> Result<()> {
+    options.validate()?;
+    write_atomically(output_path, |partial| {
+        let mut writer = BufWriter::new(create_output_file(partial, "CSV")?);
+        let write_error = |e| SpectrsError::io("Failed to write CSV", e);
+
+        writeln!(
+            writer,
+            "{}",
+            options.join(&["frequency_hz", "mean", "mean_db"])
+        )
+        .map_err(write_error)?;
+        for ((&frequency, &value), &db) in frequencies.iter().zip(mean).zip(mean_db) {
+            writeln!(
+                writer,
+                "{}",
+                options.join(&[
+                    options.format_value(frequency),
+                    options.format_value(value),
+                    options.format_value(db),
+                ])
+            )
+            .map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)
+    })
+}
+
+/// File format of the computed spectrogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Colormapped image
+    #[default]
+    Png,
+    /// Raw values as delimited text
+    Csv,
+    /// Raw values as a float32 NumPy array of shape (n_freq_bins, n_frames)
+    Npy,
+    /// Raw values as JSON rows, with their shape
+    Json,
+}
+
+impl OutputFormat {
+    /// File extension used for outputs in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Npy => "npy",
+            OutputFormat::Json => "json",
+        }
+    }
+}
@@ -1,11 +1,14 @@
+use crate::error::{Result, SpectrsError};
 #[cfg(feature = "image")]
-use anyhow::Context;
-use anyhow::Result;
-use std::path::PathBuf;
+use crate::io::atomic::write_atomically;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 /// Available colormaps for spectrogram visualization
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
 pub enum Colormap {
     /// Perceptually uniform, great for spectrograms (matplotlib/librosa default)
     #[default]
@@ -1083,7 +1086,7 @@ fn apply_colormap_kernel(value: f32, colormap_data: [[f32; 3]; 256]) -> [u8; 3]
 
 /// Apply a given colormap to a normalized value (0.0 to 1.0)
 /// Returns RGB values as (r, g, b) in 0-255 range
-fn apply_colormap(value: f32, colormap: Colormap) -> [u8; 3] {
+pub fn apply_colormap(value: f32, colormap: Colormap) -> [u8; 3] {
     match colormap {
         Colormap::Viridis => apply_colormap_kernel(value, VIRIDIS_DATA),
         Colormap::Magma => apply_colormap_kernel(value, MAGMA_DATA),
@@ -1096,6 +1099,381 @@ fn apply_colormap(value: f32, colormap: Colormap) -> [u8; 3] {
     }
 }
 
+/// How spectrogram values are mapped to the colormap. By default, values are log1p scaled and
+/// the minimum and maximum of every image span the whole colormap, so that colours are not
+/// comparable from one image to the next; fixing the range in dB (`vmin`/`vmax`) gives every
+/// image of a batch the same scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorScale {
+    /// Level (dB) shown with the first colour of the colormap, lower levels are clipped. If
+    /// only `vmax` is given, the minimum level of the image.
+    pub vmin: Option<f32>,
+    /// Level (dB) shown with the last colour of the colormap, higher levels are clipped. If
+    /// only `vmin` is given, the maximum level of the image.
+    pub vmax: Option<f32>,
+    /// Lower and upper percentiles (in [0, 100]) of the (log1p or dB scaled) values spanning
+    /// the colormap, instead of the minimum and maximum, so that a few outliers do not wash
+    /// out the rest of the image
+    pub percentile_clip: Option<(f32, f32)>,
+    /// Exponent applied to the normalized values: below 1 brightens quiet parts, above 1
+    /// darkens them
+    pub gamma: f32,
+    /// Values are magnitudes (20 log10 in dB) rather than powers (10 log10)
+    pub magnitude: bool,
+}
+
+impl Default for ColorScale {
+    fn default() -> Self {
+        Self {
+            vmin: None,
+            vmax: None,
+            percentile_clip: None,
+            gamma: 1.0,
+            magnitude: false,
+        }
+    }
+}
+
+impl ColorScale {
+    /// Check that the range, percentiles and gamma make sense
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(vmin), Some(vmax)) = (self.vmin, self.vmax)
+            && vmin >= vmax
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "vmin ({} dB) must be lower than vmax ({} dB)",
+                vmin, vmax
+            )));
+        }
+        if let Some((low, high)) = self.percentile_clip
+            && !(0.0 <= low && low < high && high <= 100.0)
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Percentiles must satisfy 0 <= low < high <= 100, got {} and {}",
+                low, high
+            )));
+        }
+        if !(self.gamma > 0.0 && self.gamma.is_finite()) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Gamma must be positive, got {}",
+                self.gamma
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether values are shown in dB rather than log1p scaled
+    #[cfg(feature = "image")]
+    fn in_db(&self) -> bool {
+        self.vmin.is_some() || self.vmax.is_some()
+    }
+
+    /// Scaled value (dB or log1p) of a spectrogram value
+    #[cfg(feature = "image")]
+    fn scale(&self, value: f32) -> f32 {
+        if self.in_db() {
+            self.level_db(value)
+        } else {
+            (value + 1.0).ln()
+        }
+    }
+
+    /// Level of a spectrogram value in dB
+    fn level_db(&self, value: f32) -> f32 {
+        let factor = if self.magnitude { 20.0 } else { 10.0 };
+        factor * value.max(1e-10).log10()
+    }
+}
+
+/// How the values of a spectrogram were mapped to the colours of its image, recorded in its
+/// sidecar so that `load_spectrogram_image` can turn the colours back into values (to the
+/// resolution of the colormap, and with the values outside [min, max] clipped)
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImageScale {
+    /// Colormap of the image
+    pub colormap: Colormap,
+    /// Values were shown in dB rather than log1p scaled
+    pub db: bool,
+    /// Values are magnitudes (20 log10 in dB) rather than powers (10 log10)
+    pub magnitude: bool,
+    /// Scaled (dB or log1p) value shown with the first colour of the colormap
+    pub min: f32,
+    /// Scaled (dB or log1p) value shown with the last colour of the colormap
+    pub max: f32,
+    /// Exponent applied to the normalized values
+    pub gamma: f32,
+}
+
+impl ImageScale {
+    /// Spectrogram value shown at a position (in [0, 1]) along the colormap
+    pub fn value(&self, position: f32) -> f32 {
+        let normalized = if self.gamma == 1.0 {
+            position
+        } else {
+            position.powf(1.0 / self.gamma)
+        };
+        let scaled = if self.max > self.min {
+            self.min + normalized * (self.max - self.min)
+        } else {
+            self.min
+        };
+        if self.db {
+            let factor = if self.magnitude { 20.0 } else { 10.0 };
+            10f32.powf(scaled / factor)
+        } else {
+            scaled.exp_m1()
+        }
+    }
+}
+
+/// Width of the histogram bins of `ScaleAccumulator` (dB)
+const SCALE_BIN_DB: f32 = 0.01;
+
+/// Dataset-wide range of the levels of many spectrograms, so that their images can share a
+/// single colour scale. Levels are collected in dB into a histogram of `SCALE_BIN_DB` bins, so
+/// percentiles are exact to the bin width whatever the size of the dataset.
+#[derive(Debug, Clone)]
+pub struct ScaleAccumulator {
+    scale: ColorScale,
+    min: f32,
+    max: f32,
+    histogram: BTreeMap<i64, u64>,
+    count: u64,
+}
+
+impl ScaleAccumulator {
+    /// Accumulator completing `scale`: the bounds it leaves open (`vmin`, `vmax`) are taken
+    /// from the dataset, at its `percentile_clip` percentiles if given
+    pub fn new(scale: &ColorScale) -> Self {
+        Self {
+            scale: *scale,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            histogram: BTreeMap::new(),
+            count: 0,
+        }
+    }
+
+    /// Add every value of a spectrogram
+    pub fn add(&mut self, spectrogram: &[Vec<f32>]) {
+        for &value in spectrogram.iter().flatten() {
+            let level = self.scale.level_db(value);
+            if level.is_nan() {
+                continue;
+            }
+            self.min = self.min.min(level);
+            self.max = self.max.max(level);
+            *self
+                .histogram
+                .entry((level / SCALE_BIN_DB).floor() as i64)
+                .or_insert(0) += 1;
+            self.count += 1;
+        }
+    }
+
+    /// Combine with the levels accumulated on another part of the dataset
+    pub fn merge(mut self, other: ScaleAccumulator) -> Self {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        for (bin, count) in other.histogram {
+            *self.histogram.entry(bin).or_insert(0) += count;
+        }
+        self.count += other.count;
+        self
+    }
+
+    /// Level (dB) of the `p`-th percentile (nearest rank) of everything added so far
+    fn percentile(&self, p: f32) -> f32 {
+        let rank = ((p as f64 / 100.0) * (self.count - 1) as f64).round() as u64;
+        if rank == 0 {
+            return self.min;
+        }
+        if rank == self.count - 1 {
+            return self.max;
+        }
+        let mut seen = 0;
+        for (&bin, &count) in &self.histogram {
+            seen += count;
+            if seen > rank {
+                return ((bin as f32 + 0.5) * SCALE_BIN_DB).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Colour scale with a fixed dB range shared by every spectrogram of the dataset
+    pub fn finish(self) -> Result<ColorScale> {
+        if self.count == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "No values to compute the colour scale from".to_string(),
+            ));
+        }
+        let (low, high) = self.scale.percentile_clip.unwrap_or((0.0, 100.0));
+        let scale = ColorScale {
+            vmin: Some(self.scale.vmin.unwrap_or_else(|| self.percentile(low))),
+            vmax: Some(self.scale.vmax.unwrap_or_else(|| self.percentile(high))),
+            percentile_clip: None,
+            ..self.scale
+        };
+        scale.validate().map_err(|_| {
+            SpectrsError::InvalidParams(format!(
+                "Empty dataset-wide range: from {} dB to {} dB",
+                scale.vmin.unwrap_or_default(),
+                scale.vmax.unwrap_or_default()
+            ))
+        })?;
+        Ok(scale)
+    }
+}
+
+/// Maps the values of a spectrogram to positions in [0, 1] along the colormap
+#[cfg(feature = "image")]
+struct Normalizer {
+    scale: ColorScale,
+    min: f32,
+    max: f32,
+}
+
+#[cfg(feature = "image")]
+impl Normalizer {
+    /// Range of the colormap for a whole spectrogram
+    fn new(spectrogram: &[Vec<f32>], scale: &ColorScale) -> Result<Self> {
+        Self::spanning(&[spectrogram], scale)
+    }
+
+    /// Range of the colormap shared by several spectrograms, so that their colours compare
+    fn spanning(spectrograms: &[&[Vec<f32>]], scale: &ColorScale) -> Result<Self> {
+        scale.validate()?;
+        let scaled = spectrograms
+            .iter()
+            .flat_map(|spectrogram| spectrogram.iter().flatten())
+            .map(|&v| scale.scale(v));
+        let (mut min, mut max) = match scale.percentile_clip {
+            Some((low, high)) => {
+                let mut values: Vec<f32> = scaled.collect();
+                (percentile(&mut values, low), percentile(&mut values, high))
+            }
+            None => scaled.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            }),
+        };
+        if let Some(vmin) = scale.vmin {
+            min = vmin;
+        }
+        if let Some(vmax) = scale.vmax {
+            max = vmax;
+        }
+        Ok(Self {
+            scale: *scale,
+            min,
+            max,
+        })
+    }
+
+    /// Mapping of the values to the colormap, as recorded in sidecars
+    fn image_scale(&self, colormap: Colormap) -> ImageScale {
+        ImageScale {
+            colormap,
+            db: self.scale.in_db(),
+            magnitude: self.scale.magnitude,
+            min: self.min,
+            max: self.max,
+            gamma: self.scale.gamma,
+        }
+    }
+
+    /// Position of a spectrogram value along the colormap
+    fn normalize(&self, value: f32) -> f32 {
+        let range = self.max - self.min;
+        let normalized = if range > 0.0 {
+            ((self.scale.scale(value) - self.min) / range).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        if self.scale.gamma == 1.0 {
+            normalized
+        } else {
+            normalized.powf(self.scale.gamma)
+        }
+    }
+}
+
+/// `p`-th percentile (in [0, 100], nearest rank) of the values, reordered in place
+#[cfg(feature = "image")]
+fn percentile(values: &mut [f32], p: f32) -> f32 {
+    if values.is_empty() {
+        return f32::NAN;
+    }
+    let rank = ((p / 100.0) * (values.len() - 1) as f32).round() as usize;
+    *values
+        .select_nth_unstable_by(rank.min(values.len() - 1), f32::total_cmp)
+        .1
+}
+
+/// Colormapped image of a spectrogram (low frequencies at the bottom)
+#[cfg(feature = "image")]
+fn render_spectrogram(
+    spectrogram: &[Vec<f32>],
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<image::RgbImage> {
+    let n_frames = spectrogram[0].len();
+    let normalizer = Normalizer::new(spectrogram, scale)?;
+    Ok(render_frames(
+        spectrogram,
+        0..n_frames,
+        &normalizer,
+        colormap,
+    ))
+}
+
+/// Colormapped image of the given frames of a spectrogram, normalized with the range of the
+/// whole spectrogram so that images of different frames share the same colors
+#[cfg(feature = "image")]
+fn render_frames(
+    spectrogram: &[Vec<f32>],
+    frames: Range<usize>,
+    normalizer: &Normalizer,
+    colormap: Colormap,
+) -> image::RgbImage {
+    use image::{ImageBuffer, Rgb};
+
+    let n_freq_bins = spectrogram.len();
+
+    // Create image buffer (width = time, height = frequency)
+    let mut img = ImageBuffer::new(frames.len() as u32, n_freq_bins as u32);
+
+    // Fill the image (flip vertically so low frequencies are at bottom)
+    for (freq_idx, row) in spectrogram.iter().enumerate() {
+        for (time_idx, &value) in row[frames.clone()].iter().enumerate() {
+            // Normalize to 0.0-1.0 and apply colormap
+            let rgb = apply_colormap(normalizer.normalize(value), colormap);
+
+            // Flip vertically: y = height - 1 - freq_idx
+            let y = (n_freq_bins - 1 - freq_idx) as u32;
+            let x = time_idx as u32;
+
+            img.put_pixel(x, y, Rgb(rgb));
+        }
+    }
+
+    img
+}
+
+/// Save an image in the format of the extension of `output_path`, through a temporary file
+/// (see `io::atomic`) so that an interrupted save never leaves a truncated image
+#[cfg(feature = "image")]
+fn save_image(img: &image::RgbImage, output_path: &Path, message: &str) -> Result<()> {
+    let image_error = |e: image::ImageError| SpectrsError::ImageError {
+        message: message.to_string(),
+        source: Some(e.into()),
+    };
+    let format = image::ImageFormat::from_path(output_path).map_err(image_error)?;
+    write_atomically(output_path, |partial| {
+        img.save_with_format(partial, format).map_err(image_error)
+    })
+}
+
 /// Save a spectrogram as an image file with colormap support
 /// This function applies log scaling (log1p) to better visualize the spectrogram dynamics.
 /// The image is oriented with frequency on the Y-axis (bottom to top) and time on the X-axis.
@@ -1105,65 +1483,817 @@ pub fn save_spectrogram_image(
     output_path: PathBuf,
     colormap: Colormap,
 ) -> Result<()> {
-    use image::{ImageBuffer, Rgb};
+    save_scaled_spectrogram_image(spectrogram, output_path, colormap, &ColorScale::default())
+}
 
-    let n_freq_bins = spectrogram.len();
-    let n_frames = spectrogram[0].len();
+/// Save a spectrogram as an image file like `save_spectrogram_image`, with the values mapped
+/// to the colormap as described by `scale` (e.g. a fixed dB range shared by a batch)
+#[cfg(feature = "image")]
+pub fn save_scaled_spectrogram_image(
+    spectrogram: &[Vec<f32>],
+    output_path: PathBuf,
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<()> {
+    let img = render_spectrogram(spectrogram, colormap, scale)?;
+    save_image(&img, &output_path, "Failed to save image")
+}
+
+/// Colormapped image of a spectrogram with an amplitude-over-time panel of `audio` beneath it
+/// (a quarter of the spectrogram height, at least `WAVEFORM_MIN_HEIGHT` pixels). Every column
+/// of the panel spans the samples of the matching column of the spectrogram and shows their
+/// minimum and maximum, in the brightest colour of the colormap over its darkest one.
+#[cfg(feature = "image")]
+fn render_waveform_image(
+    spectrogram: &[Vec<f32>],
+    audio: &[f32],
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<image::RgbImage> {
+    use image::{GenericImage, ImageBuffer, Rgb};
+
+    let spec_img = render_spectrogram(spectrogram, colormap, scale)?;
+    let (width, spec_height) = spec_img.dimensions();
+    let panel_height = (spec_height / 4).max(WAVEFORM_MIN_HEIGHT);
+    let background = Rgb(apply_colormap(0.0, colormap));
+    let foreground = Rgb(apply_colormap(1.0, colormap));
+
+    let mut img = ImageBuffer::from_pixel(width, spec_height + panel_height, background);
+    img.copy_from(&spec_img, 0, 0)
+        .expect("spectrogram fits in the image");
+
+    // Amplitudes in [-1, 1] map to the rows of the panel, +1 at the top
+    let half = (panel_height - 1) as f32 / 2.0;
+    let row =
+        |amplitude: f32| spec_height + (half * (1.0 - amplitude.clamp(-1.0, 1.0))).round() as u32;
+    for x in 0..width {
+        let start = x as usize * audio.len() / width as usize;
+        let end = ((x as usize + 1) * audio.len() / width as usize).max(start + 1);
+        let Some(samples) = audio.get(start..end.min(audio.len())) else {
+            continue;
+        };
+        let (min, max) = samples
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &s| {
+                (min.min(s), max.max(s))
+            });
+        if min > max {
+            continue;
+        }
+        for y in row(max)..=row(min) {
+            img.put_pixel(x, y, foreground);
+        }
+    }
+
+    Ok(img)
+}
+
+/// Minimum height of the waveform panel of `save_waveform_image` (pixels)
+#[cfg(feature = "image")]
+const WAVEFORM_MIN_HEIGHT: u32 = 32;
+
+/// Save a spectrogram image (rendered like `save_scaled_spectrogram_image`) with the waveform
+/// of the audio it was computed from stacked beneath it, on the same time axis, as audio
+/// editors present recordings. `audio` is mono in [-1, 1]; its sample rate does not matter,
+/// since the waveform is stretched over the width of the spectrogram.
+#[cfg(feature = "image")]
+pub fn save_waveform_image(
+    spectrogram: &[Vec<f32>],
+    audio: &[f32],
+    output_path: PathBuf,
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<()> {
+    let img = render_waveform_image(spectrogram, audio, colormap, scale)?;
+    save_image(&img, &output_path, "Failed to save image")
+}
+
+/// Rows of black between the panels of `save_comparison_image`
+#[cfg(feature = "image")]
+const COMPARISON_GAP: u32 = 2;
+
+/// Colour of a difference in [-1, 1] on the diverging scale of comparison images: blue for
+/// negative differences, white for none, red for positive ones
+#[cfg(feature = "image")]
+fn diverging_color(difference: f32) -> [u8; 3] {
+    let fade = ((1.0 - difference.abs().min(1.0)) * 255.0).round() as u8;
+    if difference < 0.0 {
+        [fade, fade, 255]
+    } else {
+        [255, fade, fade]
+    }
+}
+
+/// Save a before/after comparison of two spectrograms of the same shape (e.g. of a recording
+/// and of its denoised or encoded version) as one image: `before` over `after`, rendered with
+/// a colour scale spanning both so that their colours compare, over a panel of the difference
+/// of their levels (`after` - `before`, in dB) in blue where `after` is quieter and red where
+/// it is louder. Differences of `diff_range_db` dB or more get the full colours; by default
+/// the range is the 99th percentile of the absolute differences, so that a few outliers do not
+/// wash out the rest. Every panel is labelled, the difference with its range.
+#[cfg(feature = "image")]
+pub fn save_comparison_image(
+    before: &[Vec<f32>],
+    after: &[Vec<f32>],
+    output_path: PathBuf,
+    colormap: Colormap,
+    scale: &ColorScale,
+    diff_range_db: Option<f32>,
+) -> Result<()> {
+    use crate::io::font::{GLYPH_HEIGHT, draw_text, text_width};
+    use crate::spectrogram::compare::{DiffMode, TimeAlignment, diff_spectrograms};
+    use image::{GenericImage, ImageBuffer, Rgb};
+
+    let shape = |spec: &[Vec<f32>]| (spec.len(), spec.first().map_or(0, Vec::len));
+    if shape(before) != shape(after) {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Spectrograms of different shapes cannot be compared: {}x{} and {}x{}",
+            shape(before).0,
+            shape(before).1,
+            shape(after).0,
+            shape(after).1
+        )));
+    }
+    let (n_rows, n_frames) = shape(before);
+    if n_rows == 0 || n_frames == 0 {
+        return Err(SpectrsError::InvalidParams(
+            "Cannot compare empty spectrograms".to_string(),
+        ));
+    }
+    if let Some(range) = diff_range_db
+        && !(range > 0.0 && range.is_finite())
+    {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Difference range must be positive, got {} dB",
+            range
+        )));
+    }
+
+    let normalizer = Normalizer::spanning(&[before, after], scale)?;
+    let mut differences =
+        diff_spectrograms(after, before, DiffMode::LogRatio, TimeAlignment::Crop)?;
+    if scale.magnitude {
+        differences.iter_mut().flatten().for_each(|d| *d *= 2.0);
+    }
+    let range = diff_range_db.unwrap_or_else(|| {
+        let mut magnitudes: Vec<f32> = differences.iter().flatten().map(|d| d.abs()).collect();
+        let range = percentile(&mut magnitudes, 99.0);
+        if range > 0.0 { range } else { 1.0 }
+    });
+
+    let (width, height) = (n_frames as u32, n_rows as u32);
+    let panel_y = |panel: u32| panel * (height + COMPARISON_GAP);
+    let mut img = ImageBuffer::from_pixel(width, panel_y(3) - COMPARISON_GAP, Rgb([0, 0, 0]));
+    for (panel, spec) in [before, after].into_iter().enumerate() {
+        let rendered = render_frames(spec, 0..n_frames, &normalizer, colormap);
+        img.copy_from(&rendered, 0, panel_y(panel as u32))
+            .expect("panel fits in the image");
+    }
+    // Flip vertically so low frequencies are at the bottom, as in the other panels
+    for (row, values) in differences.iter().enumerate() {
+        let y = panel_y(2) + height - 1 - row as u32;
+        for (x, &difference) in values.iter().enumerate() {
+            img.put_pixel(x as u32, y, Rgb(diverging_color(difference / range)));
+        }
+    }
+
+    let labels = [
+        "before".to_string(),
+        "after".to_string(),
+        format!("after - before: +/-{} dB", format_axis_value(range)),
+    ];
+    for (panel, label) in labels.iter().enumerate() {
+        let (x, y) = (2, panel_y(panel as u32) + 2);
+        fill_rect(
+            &mut img,
+            x - 1,
+            y - 1,
+            x + text_width(label, 1) + 1,
+            (y + GLYPH_HEIGHT + 1).min(panel_y(panel as u32) + height),
+            [0, 0, 0],
+        );
+        draw_text(&mut img, x as i64, y as i64, label, [255, 255, 255], 1);
+    }
+
+    save_image(&img, &output_path, "Failed to save image")
+}
 
-    // Find min and max values after log scaling for normalization
-    let log_values: Vec<Vec<f32>> = spectrogram
+/// Keep the rows of a spectrogram whose frequency (`frequencies`, one per row, e.g. from
+/// `fft_frequencies` or `mel_band_frequencies`) lies in [`f_min`, `f_max`], to render a band
+/// of interest (e.g. 0-8 kHz for speech) without the empty bins around it
+pub fn crop_frequency_band(
+    spectrogram: &[Vec<f32>],
+    frequencies: &[f32],
+    f_min: Option<f32>,
+    f_max: Option<f32>,
+) -> Result<Vec<Vec<f32>>> {
+    if frequencies.len() != spectrogram.len() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Spectrogram has {} rows but {} frequencies were given",
+            spectrogram.len(),
+            frequencies.len()
+        )));
+    }
+    let f_min = f_min.unwrap_or(f32::NEG_INFINITY);
+    let f_max = f_max.unwrap_or(f32::INFINITY);
+    let cropped: Vec<Vec<f32>> = spectrogram
         .iter()
-        .map(|row| row.iter().map(|&v| (v + 1.0).ln()).collect())
+        .zip(frequencies)
+        .filter(|&(_, &frequency)| (f_min..=f_max).contains(&frequency))
+        .map(|(row, _)| row.clone())
         .collect();
+    if cropped.is_empty() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "No rows between {} Hz and {} Hz",
+            f_min, f_max
+        )));
+    }
+    Ok(cropped)
+}
 
-    let min_val = log_values
-        .iter()
-        .flatten()
-        .copied()
-        .fold(f32::INFINITY, f32::min);
-    let max_val = log_values
-        .iter()
-        .flatten()
-        .copied()
-        .fold(f32::NEG_INFINITY, f32::max);
+/// One of the images a spectrogram was split into by `save_spectrogram_tiles`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageTile {
+    /// Path of the image
+    pub path: PathBuf,
+    /// Frames (columns of the spectrogram) shown in the image
+    pub frames: Range<usize>,
+}
 
-    let range = max_val - min_val;
+/// Path of the `index`-th tile of an image, e.g. `a/b.png` → `a/b.tile0002.png`
+pub fn tile_path(output_path: &Path, index: usize) -> PathBuf {
+    output_path.with_extension(format!("tile{:04}.png", index))
+}
 
-    // Create image buffer (width = time, height = frequency)
-    let mut img = ImageBuffer::new(n_frames as u32, n_freq_bins as u32);
+/// Save a spectrogram as horizontally tiled images of at most `tile_width` frames each (see
+/// `tile_path`), for recordings too long to fit in a single image. Tiles are rendered like
+/// `save_scaled_spectrogram_image`, but normalized over the whole spectrogram so that their
+/// colors match when they are laid side by side.
+#[cfg(feature = "image")]
+pub fn save_spectrogram_tiles(
+    spectrogram: &[Vec<f32>],
+    output_path: &Path,
+    tile_width: usize,
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<Vec<ImageTile>> {
+    if tile_width == 0 {
+        return Err(SpectrsError::InvalidParams(
+            "Tile width must be greater than 0".to_string(),
+        ));
+    }
 
-    // Fill the image (flip vertically so low frequencies are at bottom)
-    for (freq_idx, row) in log_values.iter().enumerate() {
-        for (time_idx, &value) in row.iter().enumerate() {
-            // Normalize to 0.0-1.0
-            let normalized = if range > 0.0 {
-                (value - min_val) / range
-            } else {
-                0.5
+    let n_frames = spectrogram.first().map_or(0, |row| row.len());
+    let normalizer = Normalizer::new(spectrogram, scale)?;
+    (0..n_frames)
+        .step_by(tile_width)
+        .enumerate()
+        .map(|(index, start)| {
+            let frames = start..(start + tile_width).min(n_frames);
+            let path = tile_path(output_path, index);
+            let tile = render_frames(spectrogram, frames.clone(), &normalizer, colormap);
+            save_image(
+                &tile,
+                &path,
+                &format!("Failed to save image tile {}", path.display()),
+            )?;
+            Ok(ImageTile { path, frames })
+        })
+        .collect()
+}
+
+/// Encode a spectrogram as PNG in memory (same rendering as `save_spectrogram_image`), e.g.
+/// to write it to stdout or send it over the network
+#[cfg(feature = "image")]
+pub fn encode_spectrogram_png(spectrogram: &[Vec<f32>], colormap: Colormap) -> Result<Vec<u8>> {
+    encode_scaled_spectrogram_png(spectrogram, colormap, &ColorScale::default())
+}
+
+/// Encode a spectrogram as PNG in memory, rendered like `save_scaled_spectrogram_image`
+#[cfg(feature = "image")]
+pub fn encode_scaled_spectrogram_png(
+    spectrogram: &[Vec<f32>],
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<Vec<u8>> {
+    let img = render_spectrogram(spectrogram, colormap, scale)?;
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageFormat::Png)
+        .map_err(|e| SpectrsError::ImageError {
+            message: "Failed to encode image".to_string(),
+            source: Some(e.into()),
+        })?;
+    Ok(bytes.into_inner())
+}
+
+/// Mapping of the values of a spectrogram to the colours of the image rendered with
+/// `colormap` and `scale` (e.g. by `save_scaled_spectrogram_image`)
+#[cfg(feature = "image")]
+pub fn image_scale(
+    spectrogram: &[Vec<f32>],
+    colormap: Colormap,
+    scale: &ColorScale,
+) -> Result<ImageScale> {
+    Ok(Normalizer::new(spectrogram, scale)?.image_scale(colormap))
+}
+
+/// Number of positions along the colormap told apart when reading colours back, finer than
+/// the 256 colours of the colormaps since `apply_colormap` interpolates between them
+#[cfg(feature = "image")]
+const COLORMAP_STEPS: usize = 1021;
+
+/// Load the values of a spectrogram image written by spectrs with a sidecar (`--sidecar`),
+/// which records how they were mapped to colours. The waveform panel of `--with-waveform`
+/// images is left out.
+#[cfg(feature = "image")]
+pub fn load_spectrogram_image(input_path: &Path) -> Result<Vec<Vec<f32>>> {
+    use crate::io::metadata::{metadata_path, read_metadata};
+
+    let metadata = read_metadata(&metadata_path(input_path))
+        .map_err(|e| e.context(format!("Sidecar of {}", input_path.display())))?;
+    let scale = metadata.image.ok_or_else(|| SpectrsError::InvalidData {
+        message: format!(
+            "The sidecar of {} does not record the colour scale of the image",
+            input_path.display()
+        ),
+        source: None,
+    })?;
+    let file = std::fs::File::open(input_path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", input_path.display()), e))?;
+    read_spectrogram_image(std::io::BufReader::new(file), &scale, metadata.shape[0])
+}
+
+/// Read the values of a spectrogram image of `n_rows` rows (the top rows of the image) mapped
+/// to colours as described by `scale`, in [freq][time] layout
+#[cfg(feature = "image")]
+pub fn read_spectrogram_image<R: std::io::BufRead + std::io::Seek>(
+    reader: R,
+    scale: &ImageScale,
+    n_rows: usize,
+) -> Result<Vec<Vec<f32>>> {
+    let image_error = |e: image::ImageError| SpectrsError::ImageError {
+        message: "Failed to read image".to_string(),
+        source: Some(e.into()),
+    };
+    let img = image::ImageReader::new(reader)
+        .with_guessed_format()
+        .map_err(|e| SpectrsError::io("Failed to read image", e))?
+        .decode()
+        .map_err(image_error)?
+        .to_rgb8();
+    if n_rows > img.height() as usize {
+        return Err(SpectrsError::InvalidData {
+            message: format!(
+                "Expected {} rows, but the image is {} pixels high",
+                n_rows,
+                img.height()
+            ),
+            source: None,
+        });
+    }
+
+    // Every colour is looked up once along the colormap
+    let positions: Vec<(f32, [u8; 3])> = (0..COLORMAP_STEPS)
+        .map(|i| {
+            let position = i as f32 / (COLORMAP_STEPS - 1) as f32;
+            (position, apply_colormap(position, scale.colormap))
+        })
+        .collect();
+    let mut values = std::collections::HashMap::new();
+    let mut value_of = |rgb: [u8; 3]| {
+        *values.entry(rgb).or_insert_with(|| {
+            let distance = |color: &[u8; 3]| {
+                color
+                    .iter()
+                    .zip(rgb)
+                    .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+                    .sum::<i32>()
             };
+            let (position, _) = positions
+                .iter()
+                .min_by_key(|(_, color)| distance(color))
+                .expect("the colormap has colours");
+            scale.value(*position)
+        })
+    };
 
-            // Apply colormap
-            let rgb = apply_colormap(normalized, colormap);
+    // Low frequencies are at the bottom of the spectrogram
+    Ok((0..n_rows)
+        .map(|row| {
+            let y = (n_rows - 1 - row) as u32;
+            (0..img.width())
+                .map(|x| value_of(img.get_pixel(x, y).0))
+                .collect()
+        })
+        .collect())
+}
 
-            // Flip vertically: y = height - 1 - freq_idx
-            let y = (n_freq_bins - 1 - freq_idx) as u32;
-            let x = time_idx as u32;
+/// Colour of the pitch contours of `overlay_pitch_contour`, absent from every colormap
+#[cfg(feature = "image")]
+const PITCH_COLOR: [u8; 3] = [0, 255, 255];
 
-            img.put_pixel(x, y, Rgb(rgb));
+/// Colours of the F1, F2 and F3 tracks of `overlay_formant_tracks` (red, green and azure),
+/// absent from every colormap
+#[cfg(feature = "image")]
+const FORMANT_COLORS: [[u8; 3]; 3] = [[255, 0, 0], [0, 255, 0], [0, 128, 255]];
+
+/// Colour of the markers of `overlay_onset_markers`, absent from every colormap
+#[cfg(feature = "image")]
+const ONSET_COLOR: [u8; 3] = [255, 0, 255];
+
+/// Colour of the regions of `overlay_annotations`, labels being written on a black background
+#[cfg(feature = "image")]
+const ANNOTATION_COLOR: [u8; 3] = [0, 255, 0];
+
+/// Draw over a saved image, then save it again
+#[cfg(feature = "image")]
+fn edit_saved_image(path: &Path, draw: impl FnOnce(&mut image::RgbImage)) -> Result<()> {
+    let image_error = |message: &str, e: image::ImageError| SpectrsError::ImageError {
+        message: message.to_string(),
+        source: Some(e.into()),
+    };
+    let mut img = image::open(path)
+        .map_err(|e| image_error("Failed to read image", e))?
+        .to_rgb8();
+    draw(&mut img);
+    save_image(&img, path, "Failed to save image")
+}
+
+/// Draw a pitch contour over a saved spectrogram image. `positions` holds one entry per frame
+/// of the contour, spread evenly over the width of the image: the height of the pitch along
+/// the frequency axis of the spectrogram, which fills the top `n_rows` rows of the image (0 at
+/// the bottom row, 1 at the top row), or None where unvoiced. Successive voiced frames are
+/// joined by lines.
+#[cfg(feature = "image")]
+pub fn overlay_pitch_contour(path: &Path, positions: &[Option<f32>], n_rows: u32) -> Result<()> {
+    edit_saved_image(path, |img| {
+        draw_contour(img, positions, n_rows, PITCH_COLOR)
+    })
+}
+
+/// Draw formant tracks over a saved spectrogram image: `tracks` holds the positions of F1, F2
+/// and F3 in every frame (as those of `overlay_pitch_contour`), each in its own colour
+#[cfg(feature = "image")]
+pub fn overlay_formant_tracks(path: &Path, tracks: &[Vec<Option<f32>>], n_rows: u32) -> Result<()> {
+    edit_saved_image(path, |img| {
+        for (positions, &color) in tracks.iter().zip(FORMANT_COLORS.iter().cycle()) {
+            draw_contour(img, positions, n_rows, color);
+        }
+    })
+}
+
+/// Draw a contour in `color` through the positions of the frames spread evenly over the width
+/// of the image, joining successive ones by lines
+#[cfg(feature = "image")]
+fn draw_contour(img: &mut image::RgbImage, positions: &[Option<f32>], n_rows: u32, color: [u8; 3]) {
+    let (width, height) = img.dimensions();
+    let n_rows = n_rows.min(height);
+    if width == 0 || n_rows == 0 || positions.is_empty() {
+        return;
+    }
+
+    let point = |i: usize, position: f32| {
+        let x = (i as f32 + 0.5) * width as f32 / positions.len() as f32 - 0.5;
+        let y = (n_rows - 1) as f32 * (1.0 - position.clamp(0.0, 1.0));
+        (x.clamp(0.0, (width - 1) as f32), y)
+    };
+    let mut previous: Option<(f32, f32)> = None;
+    for (i, position) in positions.iter().enumerate() {
+        let Some(position) = position else {
+            previous = None;
+            continue;
+        };
+        let (x1, y1) = point(i, *position);
+        let (x0, y0) = previous.unwrap_or((x1, y1));
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = (x0 + t * (x1 - x0), y0 + t * (y1 - y0));
+            img.put_pixel(x.round() as u32, y.round() as u32, image::Rgb(color));
+        }
+        previous = Some((x1, y1));
+    }
+}
+
+/// Mark onsets over a saved spectrogram image with vertical lines across the spectrogram,
+/// which fills the top `n_rows` rows of the image. `frames` are the frames of the onsets among
+/// the `n_frames` frames spread evenly over the width of the image.
+#[cfg(feature = "image")]
+pub fn overlay_onset_markers(
+    path: &Path,
+    frames: &[usize],
+    n_frames: usize,
+    n_rows: u32,
+) -> Result<()> {
+    edit_saved_image(path, |img| {
+        let (width, height) = img.dimensions();
+        if width == 0 || n_frames == 0 {
+            return;
+        }
+        for &frame in frames {
+            let x = (frame as f32 + 0.5) * width as f32 / n_frames as f32 - 0.5;
+            let x = (x.round().max(0.0) as u32).min(width - 1);
+            for y in 0..n_rows.min(height) {
+                img.put_pixel(x, y, image::Rgb(ONSET_COLOR));
+            }
+        }
+    })
+}
+
+/// Labeled region of a spectrogram drawn by `overlay_annotations`, in fractions of the
+/// spectrogram: `time` along its width (0 at the left edge, 1 at the right one) and `frequency`
+/// along its height (0 at the bottom, 1 at the top)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledRegion {
+    pub time: (f32, f32),
+    pub frequency: (f32, f32),
+    pub label: String,
+}
+
+/// Outline labeled regions over a saved spectrogram image, which fills the top `n_rows` rows
+/// of the image, and write every label in the top-left corner of its region. Regions are
+/// clipped to the spectrogram; those entirely outside are skipped.
+#[cfg(feature = "image")]
+pub fn overlay_annotations(path: &Path, regions: &[LabeledRegion], n_rows: u32) -> Result<()> {
+    use crate::io::font::{GLYPH_HEIGHT, draw_text, text_width};
+
+    edit_saved_image(path, |img| {
+        let width = img.width();
+        let n_rows = n_rows.min(img.height());
+        if width == 0 || n_rows == 0 {
+            return;
+        }
+        for region in regions {
+            let (t0, t1) = region.time;
+            let (f0, f1) = region.frequency;
+            if t1 < 0.0 || t0 > 1.0 || f1 < 0.0 || f0 > 1.0 {
+                continue;
+            }
+            let column = |t: f32| ((t.clamp(0.0, 1.0) * width as f32) as u32).min(width - 1);
+            let row = |f: f32| (((1.0 - f.clamp(0.0, 1.0)) * n_rows as f32) as u32).min(n_rows - 1);
+            let (x0, x1) = (column(t0), column(t1));
+            let (y0, y1) = (row(f1), row(f0));
+            for x in x0..=x1 {
+                img.put_pixel(x, y0, image::Rgb(ANNOTATION_COLOR));
+                img.put_pixel(x, y1, image::Rgb(ANNOTATION_COLOR));
+            }
+            for y in y0..=y1 {
+                img.put_pixel(x0, y, image::Rgb(ANNOTATION_COLOR));
+                img.put_pixel(x1, y, image::Rgb(ANNOTATION_COLOR));
+            }
+            if region.label.is_empty() {
+                continue;
+            }
+            let (text_x, text_y) = (x0 + 2, y0 + 2);
+            fill_rect(
+                img,
+                text_x - 1,
+                text_y - 1,
+                text_x + text_width(&region.label, 1) + 1,
+                (text_y + GLYPH_HEIGHT + 1).min(n_rows),
+                [0, 0, 0],
+            );
+            draw_text(
+                img,
+                text_x as i64,
+                text_y as i64,
+                &region.label,
+                ANNOTATION_COLOR,
+                1,
+            );
+        }
+    })
+}
+
+/// Dataset-level figures collected over a batch run, rendered by `save_batch_summary_image`
+#[derive(Debug, Clone, Default)]
+pub struct BatchSummary {
+    /// Duration of every processed file (seconds)
+    pub durations: Vec<f32>,
+    /// Peak level of every processed file (dBFS)
+    pub peak_db: Vec<f32>,
+    /// Number of processed files per label directory
+    pub label_counts: BTreeMap<String, usize>,
+}
+
+impl BatchSummary {
+    /// Record one processed file
+    pub fn add(&mut self, duration: f32, peak_db: f32, label: &str) {
+        self.durations.push(duration);
+        self.peak_db.push(peak_db);
+        *self.label_counts.entry(label.to_string()).or_insert(0) += 1;
+    }
+}
+
+#[cfg(feature = "image")]
+const SUMMARY_PANEL_WIDTH: u32 = 360;
+#[cfg(feature = "image")]
+const SUMMARY_PANEL_HEIGHT: u32 = 300;
+#[cfg(feature = "image")]
+const SUMMARY_HISTOGRAM_BINS: usize = 20;
+#[cfg(feature = "image")]
+const SUMMARY_MAX_LABELS: usize = 24;
+
+/// Fill the rectangle [x0, x1) x [y0, y1) with a solid colour
+#[cfg(feature = "image")]
+fn fill_rect(img: &mut image::RgbImage, x0: u32, y0: u32, x1: u32, y1: u32, color: [u8; 3]) {
+    for y in y0..y1.min(img.height()) {
+        for x in x0..x1.min(img.width()) {
+            img.put_pixel(x, y, image::Rgb(color));
+        }
+    }
+}
+
+/// Format an axis value compactly (few decimals for small ranges, none for large ones)
+#[cfg(feature = "image")]
+fn format_axis_value(value: f32) -> String {
+    if value.abs() >= 100.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Draw a bar chart inside the panel starting at horizontal offset `panel_x`.
+/// `bars` are (height, optional label under the bar) pairs.
+#[cfg(feature = "image")]
+fn draw_bar_panel(
+    img: &mut image::RgbImage,
+    panel_x: u32,
+    title: &str,
+    bars: &[(usize, Option<String>)],
+    x_axis: Option<(f32, f32)>,
+) {
+    use crate::io::font::{GLYPH_ADVANCE, GLYPH_HEIGHT, draw_text, text_width};
+
+    let black = [0, 0, 0];
+    let bar_color = apply_colormap(0.35, Colormap::Viridis);
+
+    // Plot area inside the panel
+    let left = panel_x + 30;
+    let right = panel_x + SUMMARY_PANEL_WIDTH - 15;
+    let top = 40;
+    let bottom = SUMMARY_PANEL_HEIGHT - 45;
+
+    // Title centred above the plot
+    let title_x = panel_x + (SUMMARY_PANEL_WIDTH.saturating_sub(text_width(title, 2))) / 2;
+    draw_text(img, title_x as i64, 12, title, black, 2);
+
+    // Axes
+    fill_rect(img, left, top, left + 1, bottom + 1, black);
+    fill_rect(img, left, bottom, right, bottom + 1, black);
+
+    let max_count = bars.iter().map(|(c, _)| *c).max().unwrap_or(0);
+    draw_text(
+        img,
+        panel_x as i64 + 2,
+        top as i64,
+        &max_count.to_string(),
+        black,
+        1,
+    );
+    draw_text(img, panel_x as i64 + 2, bottom as i64 - 6, "0", black, 1);
+
+    if bars.is_empty() || max_count == 0 {
+        draw_text(
+            img,
+            left as i64 + 10,
+            (top + bottom) as i64 / 2,
+            "NO DATA",
+            black,
+            1,
+        );
+        return;
+    }
+
+    // Bars
+    let plot_width = right - left - 2;
+    let bar_width = (plot_width / bars.len() as u32).max(1);
+    for (i, (count, label)) in bars.iter().enumerate() {
+        let x0 = left + 2 + i as u32 * bar_width;
+        let height = ((bottom - top) as f32 * *count as f32 / max_count as f32).round() as u32;
+        fill_rect(
+            img,
+            x0,
+            bottom - height,
+            x0 + bar_width.saturating_sub(1),
+            bottom,
+            bar_color,
+        );
+
+        if let Some(label) = label {
+            // Truncate the label to the space available under its bar
+            let max_chars = ((bar_width + 1) / GLYPH_ADVANCE).max(1) as usize;
+            let text: String = label.chars().take(max_chars).collect();
+            draw_text(img, x0 as i64, bottom as i64 + 5, &text, black, 1);
+            let count_text = count.to_string();
+            if text_width(&count_text, 1) <= bar_width {
+                draw_text(
+                    img,
+                    x0 as i64,
+                    (bottom - height) as i64 - GLYPH_HEIGHT as i64 - 2,
+                    &count_text,
+                    black,
+                    1,
+                );
+            }
         }
     }
 
-    // Ensure parent directory exists
-    if let Some(parent) = output_path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    // Range of the histogram along the x axis
+    if let Some((min, max)) = x_axis {
+        let min_text = format_axis_value(min);
+        let max_text = format_axis_value(max);
+        draw_text(img, left as i64, bottom as i64 + 5, &min_text, black, 1);
+        draw_text(
+            img,
+            (right - text_width(&max_text, 1)) as i64,
+            bottom as i64 + 5,
+            &max_text,
+            black,
+            1,
+        );
+    }
+}
+
+/// Bin values into a fixed number of equally wide bins, returning counts and the value range
+#[cfg(feature = "image")]
+fn histogram(values: &[f32], n_bins: usize) -> (Vec<usize>, f32, f32) {
+    let finite: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if finite.is_empty() {
+        return (Vec::new(), 0.0, 0.0);
+    }
+    let min = finite.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = finite.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+
+    let mut counts = vec![0usize; n_bins];
+    for v in finite {
+        let idx = if range > 0.0 {
+            (((v - min) / range) * n_bins as f32) as usize
+        } else {
+            n_bins / 2
+        };
+        counts[idx.min(n_bins - 1)] += 1;
     }
+    (counts, min, max)
+}
+
+/// Save a dataset health overview for a batch run: histogram of durations, histogram of
+/// peak levels and number of files per label directory, side by side in a single PNG
+#[cfg(feature = "image")]
+pub fn save_batch_summary_image(summary: &BatchSummary, output_path: PathBuf) -> Result<()> {
+    use image::{ImageBuffer, Rgb};
+
+    let mut img = ImageBuffer::from_pixel(
+        SUMMARY_PANEL_WIDTH * 3,
+        SUMMARY_PANEL_HEIGHT,
+        Rgb([255u8, 255, 255]),
+    );
+
+    // Durations
+    let (counts, min, max) = histogram(&summary.durations, SUMMARY_HISTOGRAM_BINS);
+    let bars: Vec<_> = counts.into_iter().map(|c| (c, None)).collect();
+    draw_bar_panel(&mut img, 0, "DURATION (S)", &bars, Some((min, max)));
 
-    // Save the image
-    img.save(output_path)
-        .with_context(|| "Failed to save image")?;
+    // Peak levels
+    let (counts, min, max) = histogram(&summary.peak_db, SUMMARY_HISTOGRAM_BINS);
+    let bars: Vec<_> = counts.into_iter().map(|c| (c, None)).collect();
+    draw_bar_panel(
+        &mut img,
+        SUMMARY_PANEL_WIDTH,
+        "PEAK (DBFS)",
+        &bars,
+        Some((min, max)),
+    );
 
-    Ok(())
+    // Files per label (keep the most populated labels if there are too many)
+    let mut labels: Vec<(&String, &usize)> = summary.label_counts.iter().collect();
+    labels.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    labels.truncate(SUMMARY_MAX_LABELS);
+    labels.sort_by(|a, b| a.0.cmp(b.0));
+    let bars: Vec<_> = labels
+        .into_iter()
+        .map(|(label, &count)| (count, Some(label.clone())))
+        .collect();
+    draw_bar_panel(
+        &mut img,
+        SUMMARY_PANEL_WIDTH * 2,
+        "FILES PER LABEL",
+        &bars,
+        None,
+    );
+
+    save_image(&img, &output_path, "Failed to save summary image")
+}
+
+#[cfg(not(feature = "image"))]
+pub fn save_batch_summary_image(_summary: &BatchSummary, _output_path: PathBuf) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
 }
 
 #[cfg(not(feature = "image"))]
@@ -1172,5 +2302,158 @@ pub fn save_spectrogram_image(
     _output_path: PathBuf,
     _colormap: Colormap,
 ) -> Result<()> {
-    anyhow::bail!("Image feature not enabled. Compile with --features image to use this function.")
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn save_scaled_spectrogram_image(
+    _spectrogram: &[Vec<f32>],
+    _output_path: PathBuf,
+    _colormap: Colormap,
+    _scale: &ColorScale,
+) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn save_comparison_image(
+    _before: &[Vec<f32>],
+    _after: &[Vec<f32>],
+    _output_path: PathBuf,
+    _colormap: Colormap,
+    _scale: &ColorScale,
+    _diff_range_db: Option<f32>,
+) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn save_waveform_image(
+    _spectrogram: &[Vec<f32>],
+    _audio: &[f32],
+    _output_path: PathBuf,
+    _colormap: Colormap,
+    _scale: &ColorScale,
+) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn save_spectrogram_tiles(
+    _spectrogram: &[Vec<f32>],
+    _output_path: &Path,
+    _tile_width: usize,
+    _colormap: Colormap,
+    _scale: &ColorScale,
+) -> Result<Vec<ImageTile>> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn encode_spectrogram_png(_spectrogram: &[Vec<f32>], _colormap: Colormap) -> Result<Vec<u8>> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn encode_scaled_spectrogram_png(
+    _spectrogram: &[Vec<f32>],
+    _colormap: Colormap,
+    _scale: &ColorScale,
+) -> Result<Vec<u8>> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn image_scale(
+    _spectrogram: &[Vec<f32>],
+    _colormap: Colormap,
+    _scale: &ColorScale,
+) -> Result<ImageScale> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn load_spectrogram_image(_input_path: &Path) -> Result<Vec<Vec<f32>>> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn overlay_onset_markers(
+    _path: &Path,
+    _frames: &[usize],
+    _n_frames: usize,
+    _n_rows: u32,
+) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn overlay_annotations(_path: &Path, _regions: &[LabeledRegion], _n_rows: u32) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn overlay_formant_tracks(
+    _path: &Path,
+    _tracks: &[Vec<Option<f32>>],
+    _n_rows: u32,
+) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
+}
+
+#[cfg(not(feature = "image"))]
+pub fn overlay_pitch_contour(_path: &Path, _positions: &[Option<f32>], _n_rows: u32) -> Result<()> {
+    Err(SpectrsError::ImageError {
+        message: "Image feature not enabled. Compile with --features image to use this function."
+            .to_string(),
+        source: None,
+    })
 }
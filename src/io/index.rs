@@ -0,0 +1,334 @@
+//! SQLite index of processed files (enabled with the `index` feature).
+//!
+//! Every input of a run is recorded in the `files` table with its status (`processed` or
+//! `failed`, with the error), label, outputs, parameters, size and modification time, and its
+//! duration, peak level and zero-crossing rate. Optional statistics (e.g. spectral centroid or
+//! loudness) go to the `file_features` table, one row per value. Inputs are keyed by name and
+//! segment, so the index holds the latest outcome of every input and can be queried with any
+//! SQLite client:
+//!
+//! ```sql
+//! SELECT input, error FROM files WHERE status = 'failed';
+//! SELECT input FROM files WHERE duration_s < 1.0;
+//! ```
+//!
+//! The index also tells whether an input is up to date, so that later runs can skip it.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::params::SpectrogramParams;
+use std::path::Path;
+
+/// Outcome of an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Processed,
+    Failed,
+}
+
+impl FileStatus {
+    /// Value of the `status` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Processed => "processed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Outcome of an input, as recorded in the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexRecord {
+    /// Name of the input (its path or URL)
+    pub input: String,
+    /// Segment of the input processed (seconds, None for its start or end)
+    pub segment: (Option<f32>, Option<f32>),
+    pub label: Option<String>,
+    pub status: FileStatus,
+    /// Reason of the failure
+    pub error: Option<String>,
+    /// Files written for the input
+    pub outputs: Vec<String>,
+    /// Duration of the processed audio (seconds)
+    pub duration: Option<f32>,
+    /// Peak absolute sample value (dBFS)
+    pub peak_db: Option<f32>,
+    pub zero_crossing_rate: Option<f32>,
+    /// Further statistics, by name
+    pub features: Vec<(String, f32)>,
+    /// Parameters the input was processed with, when not those of the run (e.g. overridden for
+    /// its directory)
+    pub params: Option<SpectrogramParams>,
+}
+
+impl IndexRecord {
+    /// Record of an input that failed
+    pub fn failed(
+        input: impl Into<String>,
+        segment: (Option<f32>, Option<f32>),
+        label: Option<String>,
+        error: impl Into<String>,
+    ) -> Self {
+        Self {
+            input: input.into(),
+            segment,
+            label,
+            status: FileStatus::Failed,
+            error: Some(error.into()),
+            outputs: Vec::new(),
+            duration: None,
+            peak_db: None,
+            zero_crossing_rate: None,
+            features: Vec::new(),
+            params: None,
+        }
+    }
+}
+
+/// Key of a segment in the index ("" for whole inputs)
+#[cfg(feature = "index")]
+fn segment_key((start, end): (Option<f32>, Option<f32>)) -> String {
+    match (start, end) {
+        (None, None) => String::new(),
+        _ => format!(
+            "{}:{}",
+            start.map(|s| s.to_string()).unwrap_or_default(),
+            end.map(|s| s.to_string()).unwrap_or_default()
+        ),
+    }
+}
+
+/// Size (bytes) and modification time (seconds since the Unix epoch) of a local file
+pub fn file_stamp(path: &Path) -> Option<(u64, i64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+    Some((metadata.len(), modified.as_secs() as i64))
+}
+
+/// Index of the inputs processed into a SQLite database
+#[cfg(feature = "index")]
+pub struct FileIndex {
+    connection: rusqlite::Connection,
+    /// Parameters of the current run, as JSON
+    params: String,
+}
+
+#[cfg(feature = "index")]
+impl FileIndex {
+    /// Open (or create) the index at `path` for a run with `params`
+    pub fn open(path: &Path, params: &SpectrogramParams) -> Result<Self> {
+        let connection = rusqlite::Connection::open(path).map_err(|e| {
+            SpectrsError::index(format!("Failed to open index {}", path.display()), e)
+        })?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS files (
+                     input TEXT NOT NULL,
+                     segment TEXT NOT NULL,
+                     start_s REAL,
+                     end_s REAL,
+                     label TEXT,
+                     status TEXT NOT NULL,
+                     error TEXT,
+                     outputs TEXT NOT NULL,
+                     params TEXT NOT NULL,
+                     size INTEGER,
+                     modified INTEGER,
+                     duration_s REAL,
+                     peak_dbfs REAL,
+                     zero_crossing_rate REAL,
+                     processed_at INTEGER NOT NULL,
+                     PRIMARY KEY (input, segment)
+                 );
+                 CREATE TABLE IF NOT EXISTS file_features (
+                     input TEXT NOT NULL,
+                     segment TEXT NOT NULL,
+                     name TEXT NOT NULL,
+                     value REAL,
+                     PRIMARY KEY (input, segment, name)
+                 );",
+            )
+            .map_err(|e| SpectrsError::index("Failed to create the index tables", e))?;
+        let params = serde_json::to_string(params)
+            .map_err(|e| SpectrsError::index("Failed to serialize parameters", e))?;
+        Ok(Self { connection, params })
+    }
+
+    /// Record the outcome of an input with its parameters (those of the run unless the record
+    /// has its own), replacing any previous outcome. `file` is the local file of the input,
+    /// whose size and modification time are recorded
+    pub fn record(&mut self, record: &IndexRecord, file: Option<&Path>) -> Result<()> {
+        let stamp = file.and_then(file_stamp);
+        let params = record
+            .params
+            .map(|params| serde_json::to_string(&params))
+            .transpose()
+            .map_err(|e| SpectrsError::index("Failed to serialize parameters", e))?;
+        let outputs = serde_json::to_string(&record.outputs)
+            .map_err(|e| SpectrsError::index("Failed to serialize outputs", e))?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        let segment = segment_key(record.segment);
+        let error = |e| SpectrsError::index(format!("Failed to index {}", record.input), e);
+
+        let transaction = self.connection.transaction().map_err(error)?;
+        transaction
+            .execute(
+                "DELETE FROM file_features WHERE input = ?1 AND segment = ?2",
+                [&record.input, &segment],
+            )
+            .map_err(error)?;
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO files (input, segment, start_s, end_s, label, status, error,
+                     outputs, params, size, modified, duration_s, peak_dbfs, zero_crossing_rate,
+                     processed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                rusqlite::params![
+                    record.input,
+                    segment,
+                    record.segment.0,
+                    record.segment.1,
+                    record.label,
+                    record.status.as_str(),
+                    record.error,
+                    outputs,
+                    params.as_ref().unwrap_or(&self.params),
+                    stamp.map(|(size, _)| size as i64),
+                    stamp.map(|(_, modified)| modified),
+                    record.duration,
+                    record.peak_db,
+                    record.zero_crossing_rate,
+                    now,
+                ],
+            )
+            .map_err(error)?;
+        for (name, value) in &record.features {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO file_features (input, segment, name, value)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![record.input, segment, name, value],
+                )
+                .map_err(error)?;
+        }
+        transaction.commit().map_err(error)
+    }
+
+    /// Whether the segment of `input` was processed with the parameters of the run, from a file
+    /// that has not changed since (same size and modification time, if `file` is given), into
+    /// outputs that all still exist
+    pub fn is_up_to_date(
+        &self,
+        input: &str,
+        segment: (Option<f32>, Option<f32>),
+        file: Option<&Path>,
+    ) -> Result<bool> {
+        self.is_processed_with(input, segment, file, &self.params)
+    }
+
+    /// Like `is_up_to_date`, for an input processed with its own parameters
+    pub fn is_up_to_date_with(
+        &self,
+        input: &str,
+        segment: (Option<f32>, Option<f32>),
+        file: Option<&Path>,
+        params: &SpectrogramParams,
+    ) -> Result<bool> {
+        let params = serde_json::to_string(params)
+            .map_err(|e| SpectrsError::index("Failed to serialize parameters", e))?;
+        self.is_processed_with(input, segment, file, &params)
+    }
+
+    fn is_processed_with(
+        &self,
+        input: &str,
+        segment: (Option<f32>, Option<f32>),
+        file: Option<&Path>,
+        expected_params: &str,
+    ) -> Result<bool> {
+        use rusqlite::OptionalExtension;
+
+        let row = self
+            .connection
+            .query_row(
+                "SELECT status, params, size, modified, outputs FROM files
+                 WHERE input = ?1 AND segment = ?2",
+                [input, &segment_key(segment)],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<i64>>(2)?,
+                        row.get::<_, Option<i64>>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| SpectrsError::index(format!("Failed to look up {}", input), e))?;
+        let Some((status, params, size, modified, outputs)) = row else {
+            return Ok(false);
+        };
+        if status != FileStatus::Processed.as_str() || params != expected_params {
+            return Ok(false);
+        }
+        if let Some(path) = file
+            && file_stamp(path).map(|(size, modified)| (Some(size as i64), Some(modified)))
+                != Some((size, modified))
+        {
+            return Ok(false);
+        }
+        let outputs: Vec<String> = serde_json::from_str(&outputs)
+            .map_err(|e| SpectrsError::index(format!("Invalid outputs of {}", input), e))?;
+        Ok(outputs.iter().all(|output| Path::new(output).exists()))
+    }
+}
+
+#[cfg(not(feature = "index"))]
+pub struct FileIndex {
+    _private: (),
+}
+
+#[cfg(not(feature = "index"))]
+impl FileIndex {
+    pub fn open(_path: &Path, _params: &SpectrogramParams) -> Result<Self> {
+        Err(not_enabled())
+    }
+
+    pub fn record(&mut self, _record: &IndexRecord, _file: Option<&Path>) -> Result<()> {
+        Err(not_enabled())
+    }
+
+    pub fn is_up_to_date(
+        &self,
+        _input: &str,
+        _segment: (Option<f32>, Option<f32>),
+        _file: Option<&Path>,
+    ) -> Result<bool> {
+        Err(not_enabled())
+    }
+
+    pub fn is_up_to_date_with(
+        &self,
+        _input: &str,
+        _segment: (Option<f32>, Option<f32>),
+        _file: Option<&Path>,
+        _params: &SpectrogramParams,
+    ) -> Result<bool> {
+        Err(not_enabled())
+    }
+}
+
+#[cfg(not(feature = "index"))]
+fn not_enabled() -> SpectrsError {
+    SpectrsError::Index {
+        message: "Index feature not enabled. Compile with --features index to use this function."
+            .to_string(),
+        source: None,
+    }
+}
@@ -0,0 +1,177 @@
+//! Audio capture from the default input device (enabled with the `live` feature).
+//!
+//! The driver delivers audio on its own thread; the samples are downmixed to mono there and
+//! handed over through a channel, so the caller can run the STFT at its own pace. Errors of
+//! the driver are handed over the same way.
+
+use crate::error::{Result, SpectrsError};
+use std::time::Duration;
+
+/// Chunk of mono samples, or error of the driver, handed over by the capture thread
+#[cfg(feature = "live")]
+type Captured = std::result::Result<Vec<f32>, cpal::StreamError>;
+
+/// Mono audio captured from an input device. Capture starts when the input is opened and
+/// stops when it is dropped.
+#[cfg(feature = "live")]
+pub struct LiveInput {
+    // Kept alive for the capture to continue
+    _stream: cpal::Stream,
+    receiver: std::sync::mpsc::Receiver<Captured>,
+    sample_rate: u32,
+    device_name: String,
+}
+
+#[cfg(feature = "live")]
+impl LiveInput {
+    /// Start capturing from the default input device with its default configuration
+    pub fn open_default() -> Result<Self> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host().default_input_device().ok_or_else(|| {
+            device_error(
+                "No input device found",
+                std::io::Error::from(std::io::ErrorKind::NotFound),
+            )
+        })?;
+        let device_name = device
+            .name()
+            .unwrap_or_else(|_| "unknown device".to_string());
+        let config = device
+            .default_input_config()
+            .map_err(|e| device_error(format!("Failed to configure {}", device_name), e))?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let on_error = {
+            let sender = sender.clone();
+            move |e| {
+                // The reader has gone away: the stream is about to be dropped
+                let _ = sender.send(Err(e));
+            }
+        };
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &_| send_mono(data, channels, &sender),
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::I32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i32], _: &_| send_mono(data, channels, &sender),
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::U16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[u16], _: &_| send_mono(data, channels, &sender),
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &_| send_mono(data, channels, &sender),
+                on_error,
+                None,
+            ),
+            format => {
+                return Err(SpectrsError::UnsupportedFormat(format!(
+                    "Unsupported sample format {} of {}",
+                    format, device_name
+                )));
+            }
+        }
+        .map_err(|e| device_error(format!("Failed to open {}", device_name), e))?;
+        stream
+            .play()
+            .map_err(|e| device_error(format!("Failed to start {}", device_name), e))?;
+
+        Ok(Self {
+            _stream: stream,
+            receiver,
+            sample_rate,
+            device_name,
+        })
+    }
+
+    /// Sample rate of the captured audio
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Name of the capturing device
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Wait at most `timeout` for the next chunk of mono samples (None if nothing arrived).
+    /// Errors reported by the driver while capturing are returned in order with the chunks.
+    pub fn next_chunk(&self, timeout: Duration) -> Result<Option<Vec<f32>>> {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        match self.receiver.recv_timeout(timeout) {
+            Ok(Ok(chunk)) => Ok(Some(chunk)),
+            Ok(Err(e)) => Err(device_error(
+                format!("Capture from {} failed", self.device_name),
+                e,
+            )),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(device_error(
+                format!("Capture from {} stopped", self.device_name),
+                std::io::Error::from(std::io::ErrorKind::BrokenPipe),
+            )),
+        }
+    }
+}
+
+/// Average the interleaved channels of a driver buffer and send them to the reader
+#[cfg(feature = "live")]
+fn send_mono<T>(data: &[T], channels: usize, sender: &std::sync::mpsc::Sender<Captured>)
+where
+    T: cpal::Sample,
+    f32: cpal::FromSample<T>,
+{
+    let mono = data
+        .chunks_exact(channels.max(1))
+        .map(|frame| frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32)
+        .collect();
+    // The reader has gone away: the stream is about to be dropped
+    let _ = sender.send(Ok(mono));
+}
+
+#[cfg(feature = "live")]
+fn device_error(
+    message: impl Into<String>,
+    source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> SpectrsError {
+    SpectrsError::io(message, std::io::Error::other(source))
+}
+
+#[cfg(not(feature = "live"))]
+pub struct LiveInput {
+    _private: (),
+}
+
+#[cfg(not(feature = "live"))]
+impl LiveInput {
+    pub fn open_default() -> Result<Self> {
+        Err(SpectrsError::io(
+            "Live feature not enabled. Compile with --features live to use this function.",
+            std::io::Error::from(std::io::ErrorKind::Unsupported),
+        ))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        0
+    }
+
+    pub fn device_name(&self) -> &str {
+        ""
+    }
+
+    pub fn next_chunk(&self, _timeout: Duration) -> Result<Option<Vec<f32>>> {
+        Ok(None)
+    }
+}
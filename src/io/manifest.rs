@@ -0,0 +1,246 @@
+//! Manifests: the lists of inputs dataset builders hand to a batch run, and the records a run
+//! writes back mapping every input to the files generated from it. Input manifests are CSV files
+//! with a header line or JSON Lines files, one entry per input; only the `path` column is
+//! required. Output manifests are JSON Lines files, one versioned record per input.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::metadata::SCHEMA_VERSION;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{BufRead, Write};
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// An input listed in a manifest, with its own settings
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Audio file or URL. Relative paths are relative to the manifest
+    pub path: String,
+    /// Start of the segment to process (seconds)
+    #[serde(default)]
+    pub start: Option<f32>,
+    /// End of the segment to process (seconds)
+    #[serde(default)]
+    pub end: Option<f32>,
+    /// Label of the input (e.g. its class)
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Name of the output, used instead of the name of the input (the extension is replaced
+    /// by the one of the output format)
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Check that the segment is well formed
+    fn validate(&self) -> Result<()> {
+        if self.path.is_empty() {
+            return Err(SpectrsError::InvalidData {
+                message: "Empty path".to_string(),
+                source: None,
+            });
+        }
+        let start = self.start.unwrap_or(0.0);
+        if !(start >= 0.0 && self.end.is_none_or(|end| end > start)) {
+            return Err(SpectrsError::InvalidData {
+                message: format!(
+                    "Invalid segment of {}: start {:?}, end {:?}",
+                    self.path, self.start, self.end
+                ),
+                source: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Read a manifest in CSV: a header line naming the columns (`path`, and optionally `start`,
+/// `end`, `label` and `output`; other columns are ignored), then one line per entry. Empty
+/// fields are left unset; fields containing commas or quotes are double-quoted.
+pub fn read_manifest_csv<R: BufRead>(reader: R) -> Result<Vec<ManifestEntry>> {
+    let mut lines = reader.lines().enumerate();
+    let header = loop {
+        match lines.next() {
+            Some((_, line)) => {
+                let line = line.map_err(|e| SpectrsError::io("Failed to read manifest", e))?;
+                if !line.trim().is_empty() {
+                    break split_csv_line(&line).map_err(|e| e.context("Line 1"))?;
+                }
+            }
+            None => return Ok(Vec::new()),
+        }
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let path_column = column("path").ok_or_else(|| SpectrsError::InvalidData {
+        message: "The manifest has no `path` column".to_string(),
+        source: None,
+    })?;
+    let (start_column, end_column) = (column("start"), column("end"));
+    let (label_column, output_column) = (column("label"), column("output"));
+
+    let mut entries = Vec::new();
+    for (index, line) in lines {
+        let line = line.map_err(|e| SpectrsError::io("Failed to read manifest", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let context = format!("Line {}", index + 1);
+        let fields = split_csv_line(&line).map_err(|e| e.context(&context))?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+        let seconds = |column: Option<usize>| -> Result<Option<f32>> {
+            field(column)
+                .map(|f| {
+                    f.parse::<f32>().map_err(|e| SpectrsError::InvalidData {
+                        message: format!("{}: invalid time {:?}", context, f),
+                        source: Some(Box::new(e)),
+                    })
+                })
+                .transpose()
+        };
+        let entry = ManifestEntry {
+            path: field(Some(path_column)).unwrap_or_default().to_string(),
+            start: seconds(start_column)?,
+            end: seconds(end_column)?,
+            label: field(label_column).map(str::to_string),
+            output: field(output_column).map(str::to_string),
+        };
+        entry.validate().map_err(|e| e.context(&context))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Read a manifest in JSON Lines: one object per line with the fields of `ManifestEntry`
+pub fn read_manifest_jsonl<R: BufRead>(reader: R) -> Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| SpectrsError::io("Failed to read manifest", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let context = format!("Line {}", index + 1);
+        let entry: ManifestEntry =
+            serde_json::from_str(&line).map_err(|e| SpectrsError::InvalidData {
+                message: format!("{}: invalid manifest entry", context),
+                source: Some(Box::new(e)),
+            })?;
+        entry.validate().map_err(|e| e.context(&context))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Load a manifest, in JSON Lines if its extension is `.jsonl` or `.json`, in CSV otherwise
+#[cfg(feature = "fs")]
+pub fn load_manifest(path: &Path) -> Result<Vec<ManifestEntry>> {
+    let file = File::open(path)
+        .map_err(|e| SpectrsError::io(format!("Failed to open {}", path.display()), e))?;
+    let reader = BufReader::new(file);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("jsonl" | "json") => read_manifest_jsonl(reader),
+        _ => read_manifest_csv(reader),
+    }
+    .map_err(|e| e.context(path.display()))
+}
+
+/// Split a CSV line into its fields, unquoting double-quoted fields
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(SpectrsError::InvalidData {
+            message: "Unterminated quoted field".to_string(),
+            source: None,
+        });
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Record of an output manifest: an input and the files generated from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputRecord {
+    /// Version of this schema
+    pub schema_version: u32,
+    /// Input the outputs were computed from
+    pub input: String,
+    /// Label of the input, if given
+    pub label: Option<String>,
+    /// Start of the processed segment (seconds), if not the start of the input
+    pub start: Option<f32>,
+    /// End of the processed segment (seconds), if not the end of the input
+    pub end: Option<f32>,
+    /// Duration of the processed audio (seconds)
+    pub duration: f32,
+    /// Files written for the input (outputs of every format, tile indexes, sidecars)
+    pub outputs: Vec<String>,
+}
+
+impl OutputRecord {
+    /// Stamp a record with the current schema version
+    pub fn new(
+        input: impl Into<String>,
+        label: Option<String>,
+        segment: (Option<f32>, Option<f32>),
+        duration: f32,
+        outputs: Vec<String>,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            input: input.into(),
+            label,
+            start: segment.0,
+            end: segment.1,
+            duration,
+            outputs,
+        }
+    }
+}
+
+/// Write an output manifest as JSON Lines, one record per line
+pub fn write_output_manifest<W: Write>(records: &[OutputRecord], mut writer: W) -> Result<()> {
+    let write_error = |e| SpectrsError::io("Failed to write manifest", e);
+    for record in records {
+        let line = serde_json::to_string(record).map_err(|e| SpectrsError::InvalidData {
+            message: "Failed to serialize manifest record".to_string(),
+            source: Some(Box::new(e)),
+        })?;
+        writeln!(writer, "{}", line).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Save an output manifest (see `write_output_manifest`), creating its directory if needed
+#[cfg(feature = "fs")]
+pub fn save_output_manifest(records: &[OutputRecord], path: &Path) -> Result<()> {
+    crate::io::atomic::write_atomically(path, |partial| {
+        let file = File::create(partial)
+            .map_err(|e| SpectrsError::io(format!("Failed to create {}", path.display()), e))?;
+        write_output_manifest(records, BufWriter::new(file))
+    })
+}
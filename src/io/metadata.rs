@@ -0,0 +1,434 @@
+//! JSON sidecars describing how an output was produced. Every JSON document written by spectrs
+//! carries a `schema_version`; readers upgrade documents written by older releases through the
+//! migrations below and refuse documents written by newer ones, so long-lived dataset pipelines
+//! can rely on these artifacts across crate upgrades.
+
+use crate::error::{Result, SpectrsError};
+#[cfg(feature = "fs")]
+use crate::io::atomic::write_atomically;
+use crate::io::export::OutputFormat;
+use crate::io::image::{ImageScale, ImageTile};
+use crate::spectrogram::params::SpectrogramParams;
+#[cfg(feature = "fs")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Current version of the JSON output schema. Bump it (and add a migration) whenever a field is
+/// renamed, removed or changes meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrade step turning a JSON document of one schema version into the next version
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Upgrade steps: `MIGRATIONS[v]` turns a version `v` document into a version `v + 1` one
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Version 0 designates documents written before outputs were versioned, which lacked the
+/// spectrs version stamp
+fn migrate_v0_to_v1(document: &mut Map<String, Value>) -> Result<()> {
+    document
+        .entry("spectrs_version")
+        .or_insert_with(|| Value::String("unknown".to_string()));
+    Ok(())
+}
+
+/// Check the schema version of a JSON document written by spectrs and upgrade it to the current
+/// schema. Documents without a version are considered version 0.
+pub fn migrate(document: Value) -> Result<Value> {
+    let Value::Object(mut document) = document else {
+        return Err(SpectrsError::InvalidData {
+            message: "Expected a JSON object".to_string(),
+            source: None,
+        });
+    };
+
+    let version = match document.get("schema_version") {
+        None => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| SpectrsError::InvalidData {
+                message: format!("Invalid schema_version: {}", value),
+                source: None,
+            })?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(SpectrsError::InvalidData {
+            message: format!(
+                "Schema version {} is newer than the supported version {} (written by a newer spectrs?)",
+                version, SCHEMA_VERSION
+            ),
+            source: None,
+        });
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut document)
+            .map_err(|e| e.context(format!("Failed to migrate schema version {}", from)))?;
+    }
+    document.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+
+    Ok(Value::Object(document))
+}
+
+/// Sidecar metadata written next to a spectrogram output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectrogramMetadata {
+    /// Version of this schema
+    pub schema_version: u32,
+    /// Version of spectrs that produced the output
+    pub spectrs_version: String,
+    /// Input the spectrogram was computed from
+    pub source: String,
+    /// Format of the output
+    pub format: OutputFormat,
+    /// Sample rate the spectrogram was computed at (Hz)
+    pub sample_rate: u32,
+    /// Spectrogram parameters
+    pub params: SpectrogramParams,
+    /// Number of rows (frequency bins or mel bands) and columns (frames) of the stored values
+    pub shape: [usize; 2],
+    /// Mapping of the values to the colours of an image output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageScale>,
+}
+
+impl SpectrogramMetadata {
+    /// Stamp metadata with the current schema and crate versions
+    pub fn new(
+        source: impl Into<String>,
+        format: OutputFormat,
+        sample_rate: u32,
+        params: SpectrogramParams,
+        shape: [usize; 2],
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.into(),
+            format,
+            sample_rate,
+            params,
+            shape,
+            image: None,
+        }
+    }
+}
+
+/// Path of the sidecar of an output file, e.g. `a/b.png` → `a/b.meta.json`
+pub fn metadata_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("meta.json")
+}
+
+/// Write sidecar metadata as pretty-printed JSON
+#[cfg(feature = "fs")]
+pub fn write_metadata(path: &Path, metadata: &SpectrogramMetadata) -> Result<()> {
+    write_json(path, metadata, "metadata")
+}
+
+/// Read sidecar metadata, upgrading documents written by older versions of spectrs
+#[cfg(feature = "fs")]
+pub fn read_metadata(path: &Path) -> Result<SpectrogramMetadata> {
+    read_json(path, "metadata")
+}
+
+/// Index of the tiles a spectrogram image was split into, linking every tile to the time range
+/// it shows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileIndex {
+    /// Version of this schema
+    pub schema_version: u32,
+    /// Version of spectrs that produced the tiles
+    pub spectrs_version: String,
+    /// Input the spectrogram was computed from
+    pub source: String,
+    /// Sample rate the spectrogram was computed at (Hz)
+    pub sample_rate: u32,
+    /// Seconds between the starts of successive frames
+    pub frame_period: f64,
+    /// Total number of frames over all tiles
+    pub n_frames: usize,
+    /// Tiles, from left to right
+    pub tiles: Vec<TileEntry>,
+}
+
+/// A tile of a `TileIndex`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileEntry {
+    /// File name of the image, relative to the index
+    pub file: String,
+    /// First frame shown in the tile
+    pub start_frame: usize,
+    /// Frame following the last one shown in the tile
+    pub end_frame: usize,
+    /// Time of `start_frame` in the source (seconds)
+    pub start_time: f64,
+    /// Time of `end_frame` in the source (seconds)
+    pub end_time: f64,
+}
+
+impl TileIndex {
+    /// Index the tiles of a spectrogram whose frames start every `frame_shift` samples. Frame t
+    /// is placed at `offset + t * frame_shift / sample_rate` seconds, `offset` being the time
+    /// of the first frame in the source (e.g. the start of a processed segment).
+    pub fn new(
+        source: impl Into<String>,
+        sample_rate: u32,
+        frame_shift: usize,
+        offset: f64,
+        tiles: &[ImageTile],
+    ) -> Self {
+        let frame_period = frame_shift as f64 / sample_rate as f64;
+        let time_of = |frame: usize| offset + frame as f64 * frame_period;
+        Self {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.into(),
+            sample_rate,
+            frame_period,
+            n_frames: tiles.last().map_or(0, |tile| tile.frames.end),
+            tiles: tiles
+                .iter()
+                .map(|tile| TileEntry {
+                    file: tile
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    start_frame: tile.frames.start,
+                    end_frame: tile.frames.end,
+                    start_time: time_of(tile.frames.start),
+                    end_time: time_of(tile.frames.end),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Path of the tile index of an image output, e.g. `a/b.png` → `a/b.tiles.json`
+pub fn tile_index_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("tiles.json")
+}
+
+/// Write a tile index as pretty-printed JSON
+#[cfg(feature = "fs")]
+pub fn write_tile_index(path: &Path, index: &TileIndex) -> Result<()> {
+    write_json(path, index, "tile index")
+}
+
+/// Read a tile index, upgrading documents written by older versions of spectrs
+#[cfg(feature = "fs")]
+pub fn read_tile_index(path: &Path) -> Result<TileIndex> {
+    read_json(path, "tile index")
+}
+
+/// Report of a batch run, for monitoring jobs programmatically: which inputs were processed,
+/// skipped or failed, and how fast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Version of this schema
+    pub schema_version: u32,
+    /// Version of spectrs that ran
+    pub spectrs_version: String,
+    /// Inputs processed successfully
+    pub processed: Vec<ProcessedInput>,
+    /// Files of the input directory left out, with why
+    pub skipped: Vec<ReportedInput>,
+    /// Inputs that could not be processed, with the error
+    pub failed: Vec<ReportedInput>,
+    /// Duration of the run (seconds)
+    pub wall_time: f64,
+    /// Total duration of the processed audio (seconds)
+    pub audio_duration: f64,
+    /// Inputs processed per second of the run
+    pub files_per_second: f64,
+    /// Seconds of audio processed per second of the run
+    pub audio_seconds_per_second: f64,
+    /// Outputs given another name than that of their input, so as not to overwrite each other
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed: Vec<RenamedOutput>,
+}
+
+/// An input processed by a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessedInput {
+    /// Name of the input
+    pub input: String,
+    /// Duration of the processed audio (seconds)
+    pub duration: f32,
+}
+
+/// An input skipped by a run or failing, with the reason
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportedInput {
+    /// Name of the input
+    pub input: String,
+    /// Why it was skipped, or the error it failed with
+    pub reason: String,
+}
+
+/// An output renamed because another input of the run would have been written to the same path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenamedOutput {
+    /// Name of the input
+    pub input: String,
+    /// Path the output was written to
+    pub output: String,
+}
+
+impl RunReport {
+    /// Report of a run that took `wall_time` seconds, deriving the throughput
+    pub fn new(
+        processed: Vec<ProcessedInput>,
+        skipped: Vec<ReportedInput>,
+        failed: Vec<ReportedInput>,
+        wall_time: f64,
+    ) -> Self {
+        let audio_duration: f64 = processed.iter().map(|p| p.duration as f64).sum();
+        let per_second = |amount: f64| {
+            if wall_time > 0.0 {
+                amount / wall_time
+            } else {
+                0.0
+            }
+        };
+        Self {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            files_per_second: per_second(processed.len() as f64),
+            audio_seconds_per_second: per_second(audio_duration),
+            processed,
+            skipped,
+            failed,
+            wall_time,
+            audio_duration,
+            renamed: Vec::new(),
+        }
+    }
+}
+
+/// Write a run report as pretty-printed JSON
+#[cfg(feature = "fs")]
+pub fn write_run_report(path: &Path, report: &RunReport) -> Result<()> {
+    write_json(path, report, "run report")
+}
+
+/// Read a run report, upgrading documents written by older versions of spectrs
+#[cfg(feature = "fs")]
+pub fn read_run_report(path: &Path) -> Result<RunReport> {
+    read_json(path, "run report")
+}
+
+/// Plan of a batch run made without computing anything (`--dry-run`): the inputs that would be
+/// processed with the files they would be written to, and the effective parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunPlan {
+    /// Version of this schema
+    pub schema_version: u32,
+    /// Version of spectrs that made the plan
+    pub spectrs_version: String,
+    /// Spectrogram parameters, once presets and options depending on each other are resolved
+    pub params: SpectrogramParams,
+    /// Sample rate the inputs would be resampled to (Hz), None to keep theirs
+    pub sample_rate: Option<u32>,
+    /// Formats of the outputs
+    pub formats: Vec<OutputFormat>,
+    /// Inputs that would be processed
+    pub inputs: Vec<PlannedInput>,
+    /// Files of the input directory left out, with why
+    pub skipped: Vec<ReportedInput>,
+    /// Inputs that would fail before being decoded (e.g. over --max-memory-mb), with the error
+    pub failed: Vec<ReportedInput>,
+}
+
+/// An input a run would process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedInput {
+    /// Name of the input
+    pub input: String,
+    /// Label of the input, if given
+    pub label: Option<String>,
+    /// Start of the segment to process (seconds), if not the start of the input
+    pub start: Option<f32>,
+    /// End of the segment to process (seconds), if not the end of the input
+    pub end: Option<f32>,
+    /// Duration of the audio to process (seconds), when the header of the input announces it
+    pub duration: Option<f32>,
+    /// Files that would be written for the input
+    pub outputs: Vec<String>,
+    /// Parameters of the input when not those of the run (overridden for its directory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<SpectrogramParams>,
+}
+
+impl RunPlan {
+    /// Stamp a plan with the current schema and crate versions
+    pub fn new(
+        params: SpectrogramParams,
+        sample_rate: Option<u32>,
+        formats: Vec<OutputFormat>,
+        inputs: Vec<PlannedInput>,
+        skipped: Vec<ReportedInput>,
+        failed: Vec<ReportedInput>,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            params,
+            sample_rate,
+            formats,
+            inputs,
+            skipped,
+            failed,
+        }
+    }
+
+    /// Total duration of the audio to process (seconds), over the inputs whose duration is known
+    pub fn audio_duration(&self) -> f64 {
+        self.inputs
+            .iter()
+            .filter_map(|input| input.duration)
+            .map(f64::from)
+            .sum()
+    }
+}
+
+/// Write a run plan as pretty-printed JSON
+#[cfg(feature = "fs")]
+pub fn write_run_plan(path: &Path, plan: &RunPlan) -> Result<()> {
+    write_json(path, plan, "run plan")
+}
+
+/// Read a run plan, upgrading documents written by older versions of spectrs
+#[cfg(feature = "fs")]
+pub fn read_run_plan(path: &Path) -> Result<RunPlan> {
+    read_json(path, "run plan")
+}
+
+/// Write a versioned document as pretty-printed JSON, creating its directory if needed. The
+/// document is replaced at once (see `io::atomic`), never left half-written
+#[cfg(feature = "fs")]
+fn write_json<T: Serialize>(path: &Path, document: &T, what: &str) -> Result<()> {
+    let json = serde_json::to_string_pretty(document)
+        .map_err(|e| SpectrsError::invalid_data(format!("Failed to serialize {}", what), e))?;
+    write_atomically(path, |partial| {
+        std::fs::write(partial, json + "\n")
+            .map_err(|e| SpectrsError::io(format!("Failed to write {}", what), e))
+    })
+}
+
+/// Read a versioned JSON document, upgrading documents written by older versions of spectrs
+#[cfg(feature = "fs")]
+fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| SpectrsError::io(format!("Failed to read {}: {}", what, path.display()), e))?;
+    let document: Value = serde_json::from_str(&content).map_err(|e| {
+        SpectrsError::invalid_data(format!("Invalid JSON in {}", path.display()), e)
+    })?;
+    let document = migrate(document).map_err(|e| e.context(format!("In {}", path.display())))?;
+    serde_json::from_value(document).map_err(|e| {
+        SpectrsError::invalid_data(format!("Invalid {} in {}", what, path.display()), e)
+    })
+}
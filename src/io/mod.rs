@@ -1,2 +1,30 @@
+pub mod annotations;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "fs")]
+pub mod atomic;
 pub mod audio;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "fs")]
+pub mod checkpoint;
+#[cfg(feature = "fs")]
+pub mod dataset;
+pub mod export;
+#[cfg(feature = "image")]
+pub(crate) mod font;
 pub mod image;
+#[cfg(feature = "fs")]
+pub mod index;
+pub mod live;
+pub mod manifest;
+pub mod metadata;
+#[cfg(feature = "opus")]
+pub mod opus;
+pub mod report;
+pub mod source;
+#[cfg(feature = "object-store")]
+pub mod store;
+pub mod terminal;
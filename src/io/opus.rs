@@ -0,0 +1,114 @@
+// Opus encoding of mono audio in an Ogg container (RFC 7845), to audition reconstructions as
+// small files. Opus always runs at 48 kHz: other sample rates are resampled, the original one
+// being recorded in the header for players that resample back.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::atomic::write_atomically;
+use crate::io::audio::resample;
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::io::Write;
+use std::path::Path;
+
+/// Sample rate of the Opus stream and unit of its granule positions
+const OPUS_SR: u32 = 48000;
+/// Samples per packet (20 ms)
+const FRAME_SIZE: usize = 960;
+/// Largest packet recommended by the Opus documentation
+const MAX_PACKET_SIZE: usize = 4000;
+/// Serial number of the (single) logical stream
+const STREAM_SERIAL: u32 = 0x5350_4543;
+/// Bitrate of the encoded stream (bits per second), transparent for most mono material
+pub const DEFAULT_OPUS_BITRATE: i32 = 64_000;
+
+/// Encode mono f32 samples (nominally in [-1, 1]) at `sr` as an Ogg Opus stream
+pub fn encode_opus<W: Write>(writer: W, samples: &[f32], sr: u32, bitrate: i32) -> Result<()> {
+    let samples = match sr {
+        OPUS_SR => samples.to_vec(),
+        _ => resample(samples.to_vec(), sr, OPUS_SR)?,
+    };
+
+    let mut encoder = Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Audio)
+        .map_err(|e| SpectrsError::encode("Failed to create the Opus encoder", e))?;
+    encoder
+        .set_bitrate(Bitrate::BitsPerSecond(bitrate))
+        .map_err(|e| SpectrsError::encode(format!("Invalid Opus bitrate {}", bitrate), e))?;
+    // Decoders drop the first `pre_skip` samples, the delay of the encoder
+    let pre_skip = encoder
+        .lookahead()
+        .map_err(|e| SpectrsError::encode("Failed to query the Opus encoder", e))?
+        as usize;
+
+    let mut packets = PacketWriter::new(writer);
+    let write = |packets: &mut PacketWriter<W>, packet: Vec<u8>, end, granule| {
+        packets
+            .write_packet(packet.into_boxed_slice(), STREAM_SERIAL, end, granule)
+            .map_err(|e| SpectrsError::io("Failed to write the Ogg stream", e))
+    };
+    write(
+        &mut packets,
+        opus_head(pre_skip as u16, sr),
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    write(&mut packets, opus_tags(), PacketWriteEndInfo::EndPage, 0)?;
+
+    // Zeros flush the delay of the encoder; the granule position of the last packet trims
+    // them from the decoded signal
+    let end = pre_skip + samples.len();
+    let n_packets = end.div_ceil(FRAME_SIZE).max(1);
+    let mut padded = samples;
+    padded.resize(n_packets * FRAME_SIZE, 0.0);
+
+    let mut packet = vec![0u8; MAX_PACKET_SIZE];
+    for (i, frame) in padded.chunks(FRAME_SIZE).enumerate() {
+        let size = encoder
+            .encode_float(frame, &mut packet)
+            .map_err(|e| SpectrsError::encode("Failed to encode Opus packet", e))?;
+        let last = i + 1 == n_packets;
+        let granule = ((i + 1) * FRAME_SIZE).min(end) as u64;
+        let info = match last {
+            true => PacketWriteEndInfo::EndStream,
+            false => PacketWriteEndInfo::NormalPacket,
+        };
+        write(&mut packets, packet[..size].to_vec(), info, granule)?;
+    }
+    Ok(())
+}
+
+/// Write mono f32 samples at `sr` to an Ogg Opus file (e.g. `.opus`)
+pub fn write_opus_file(path: &Path, samples: &[f32], sr: u32, bitrate: i32) -> Result<()> {
+    write_atomically(path, |partial| {
+        let file = std::fs::File::create(partial)
+            .map_err(|e| SpectrsError::io(format!("Failed to create {}", path.display()), e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        encode_opus(&mut writer, samples, sr, bitrate)?;
+        writer
+            .flush()
+            .map_err(|e| SpectrsError::io(format!("Failed to write {}", path.display()), e))
+    })
+}
+
+/// Identification header: version 1, mono, pre-skip, original sample rate, no gain, mapping
+/// family 0
+fn opus_head(pre_skip: u16, input_sr: u32) -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(1);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_sr.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+/// Comment header naming the encoder, without user comments
+fn opus_tags() -> Vec<u8> {
+    let vendor = format!("spectrs {}", env!("CARGO_PKG_VERSION"));
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
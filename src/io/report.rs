@@ -0,0 +1,156 @@
+//! Static HTML reports of a batch run: a single page embedding a thumbnail of the spectrogram of
+//! every input with its name, duration and a few statistics, to eyeball a dataset in a browser
+//! without opening every image. The thumbnails are inlined as base64 data URIs so that the page
+//! can be moved or mailed on its own.
+
+#[cfg(feature = "fs")]
+use crate::error::{Result, SpectrsError};
+use crate::io::metadata::ReportedInput;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// An input shown in the report
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReportEntry {
+    /// Name of the input
+    pub input: String,
+    /// Label of the input (its directory, or the label of its manifest entry)
+    pub label: String,
+    /// Files generated from the input
+    pub outputs: Vec<String>,
+    /// Duration of the processed audio (seconds)
+    pub duration: f32,
+    /// Sample rate the spectrogram was computed at (Hz)
+    pub sample_rate: u32,
+    /// Peak absolute sample value (dBFS)
+    pub peak_db: f32,
+    /// Fraction of successive samples changing sign
+    pub zero_crossing_rate: f32,
+    /// Number of rows (frequency bands) and columns (frames) of the spectrogram
+    pub shape: [usize; 2],
+    /// Thumbnail of the spectrogram image, encoded as PNG (None to show its name only)
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+/// Report of a run: the inputs processed, in order, and those that failed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HtmlReport {
+    /// Title of the page
+    pub title: String,
+    pub entries: Vec<ReportEntry>,
+    pub failed: Vec<ReportedInput>,
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1.5em;background:#fafafa;color:#222}\
+h1{font-size:1.4em}.summary{color:#555}\
+.grid{display:grid;grid-template-columns:repeat(auto-fill,minmax(280px,1fr));gap:1em}\
+figure{margin:0;padding:.6em;background:#fff;border:1px solid #ddd;border-radius:4px}\
+figure img{width:100%;image-rendering:pixelated;display:block}\
+figcaption{font-size:.8em;margin-top:.4em;word-break:break-all}\
+figcaption .name{font-weight:bold}figcaption .stats{color:#555}\
+.failed li{font-size:.85em;color:#a00}";
+
+impl HtmlReport {
+    /// Render the report as a self-contained HTML page
+    pub fn to_html(&self) -> String {
+        let total: f64 = self.entries.iter().map(|entry| entry.duration as f64).sum();
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+        html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+        html.push_str(&format!("<h1>{}</h1>\n", escape_html(&self.title)));
+        html.push_str(&format!(
+            "<p class=\"summary\">{} inputs, {:.1}s of audio, {} failed (spectrs {})</p>\n",
+            self.entries.len(),
+            total,
+            self.failed.len(),
+            env!("CARGO_PKG_VERSION")
+        ));
+
+        html.push_str("<div class=\"grid\">\n");
+        for entry in &self.entries {
+            html.push_str("<figure>");
+            if let Some(png) = &entry.thumbnail {
+                html.push_str(&format!(
+                    "<img src=\"data:image/png;base64,{}\" alt=\"{}\" loading=\"lazy\">",
+                    base64(png),
+                    escape_html(&entry.input)
+                ));
+            }
+            html.push_str(&format!(
+                "<figcaption><div class=\"name\">{}</div>\
+                 <div class=\"stats\">{} &middot; {:.2}s &middot; {} Hz &middot; peak {:.1} dBFS \
+                 &middot; ZCR {:.3} &middot; {}&times;{}</div><div>{}</div></figcaption>",
+                escape_html(&entry.input),
+                escape_html(&entry.label),
+                entry.duration,
+                entry.sample_rate,
+                entry.peak_db,
+                entry.zero_crossing_rate,
+                entry.shape[0],
+                entry.shape[1],
+                escape_html(&entry.outputs.join(", "))
+            ));
+            html.push_str("</figure>\n");
+        }
+        html.push_str("</div>\n");
+
+        if !self.failed.is_empty() {
+            html.push_str("<h2>Failed inputs</h2>\n<ul class=\"failed\">\n");
+            for input in &self.failed {
+                html.push_str(&format!(
+                    "<li>{}: {}</li>\n",
+                    escape_html(&input.input),
+                    escape_html(&input.reason)
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// Write the report as a single HTML page
+#[cfg(feature = "fs")]
+pub fn save_html_report(report: &HtmlReport, path: &Path) -> Result<()> {
+    crate::io::atomic::write_atomically(path, |partial| {
+        std::fs::write(partial, report.to_html())
+            .map_err(|e| SpectrsError::io(format!("Failed to write {}", path.display()), e))
+    })
+}
+
+/// Escape the characters of text with a meaning in HTML
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Standard base64 encoding (with padding) of bytes, for data URIs
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,459 @@
+use crate::error::Result;
+#[cfg(any(feature = "fs", feature = "http"))]
+use crate::error::SpectrsError;
+use crate::io::audio::{
+    AudioReader, DownmixMode, SalvageReport, read_audio_mono_from_reader,
+    read_audio_mono_lenient_from_reader, read_audio_segment_from_reader, slice_segment,
+};
+#[cfg(feature = "fs")]
+use crate::io::audio::{RawPcm, read_raw_pcm};
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufReader;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Layout and length of audio, as announced before decoding it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInfo {
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Number of channels (before downmixing)
+    pub channels: usize,
+    /// Number of frames (samples per channel)
+    pub frames: u64,
+}
+
+impl AudioInfo {
+    /// Duration of the audio (seconds)
+    pub fn duration(&self) -> f64 {
+        self.frames as f64 / self.sample_rate as f64
+    }
+}
+
+/// Anything spectrs can pull mono audio from: local files, in-memory buffers, URLs, devices...
+/// The CLI and the processing pipeline only talk to this trait, so a new input kind only needs
+/// a new implementation rather than a bespoke code path.
+pub trait AudioSource: Send + Sync {
+    /// Human readable identifier of the source (path, URL, device name, ...)
+    fn name(&self) -> String;
+
+    /// Relative path used to derive output file names for this source
+    fn relative_path(&self) -> PathBuf;
+
+    /// Location on the local filesystem, if the source lives there
+    fn local_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Layout and length of the source when they are known without decoding it (e.g. from the
+    /// header of a file), to size the work before doing it. None when they are not, or when the
+    /// header cannot be read (decoding then reports why)
+    fn info(&self) -> Option<AudioInfo> {
+        None
+    }
+
+    /// Decode the whole source into mono samples, returning them with their sample rate
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)>;
+
+    /// Decode only the [start, end) portion (in seconds) of the source
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        // Generic fallback: decode everything and slice
+        let (samples, sr) = self.read_mono()?;
+        let segment =
+            slice_segment(&samples, sr, start, end).map_err(|e| e.context(self.name()))?;
+        Ok((segment, sr))
+    }
+
+    /// Decode the whole source, salvaging as much audio as possible from damaged data.
+    /// The report is `Some` when the data was damaged. Sources that cannot be damaged
+    /// (e.g. already decoded samples) simply decode normally.
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        let (samples, sr) = self.read_mono()?;
+        Ok((samples, sr, None))
+    }
+}
+
+/// WAV file on the local filesystem
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    pub path: PathBuf,
+    /// Root directory the file was discovered in (used to preserve directory structure)
+    pub base: Option<PathBuf>,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+}
+
+#[cfg(feature = "fs")]
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            base: None,
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// File discovered while walking `base`, whose relative structure should be preserved
+    pub fn with_base(path: impl Into<PathBuf>, base: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            base: Some(base.into()),
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// Downmix the channels of the file this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+
+    fn open(&self) -> Result<BufReader<File>> {
+        File::open(&self.path)
+            .map(BufReader::new)
+            .map_err(|e| SpectrsError::io("Failed to open audio file", e))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl AudioSource for FileSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        relative_to_base(&self.path, self.base.as_deref())
+    }
+
+    fn local_path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn info(&self) -> Option<AudioInfo> {
+        wav_info(self.open().ok()?)
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        read_audio_mono_from_reader(self.open()?, self.downmix)
+    }
+
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        read_audio_segment_from_reader(self.open()?, start, end, self.downmix)
+    }
+
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        read_audio_mono_lenient_from_reader(self.open()?, self.downmix)
+    }
+}
+
+/// Layout and length announced by the header of WAV data
+fn wav_info<R: std::io::Read + std::io::Seek>(reader: R) -> Option<AudioInfo> {
+    let reader = AudioReader::new(reader).ok()?;
+    Some(AudioInfo {
+        sample_rate: reader.sample_rate(),
+        channels: reader.channels(),
+        frames: reader.n_frames() as u64,
+    })
+}
+
+/// Path of a file relative to the directory it was discovered in (just the file name for
+/// single files)
+#[cfg(feature = "fs")]
+fn relative_to_base(path: &Path, base: Option<&Path>) -> PathBuf {
+    match base {
+        // Directory case - preserve subdirectory structure
+        Some(base) if base != path => path
+            .strip_prefix(base)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| path.to_path_buf()),
+        // Single file case - use just the filename
+        _ => path
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| path.to_path_buf()),
+    }
+}
+
+/// Headerless PCM file on the local filesystem, decoded with a layout given in advance
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct RawPcmSource {
+    pub path: PathBuf,
+    /// Root directory the file was discovered in (used to preserve directory structure)
+    pub base: Option<PathBuf>,
+    pub layout: RawPcm,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+}
+
+#[cfg(feature = "fs")]
+impl RawPcmSource {
+    pub fn new(path: impl Into<PathBuf>, layout: RawPcm) -> Self {
+        Self {
+            path: path.into(),
+            base: None,
+            layout,
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// File discovered while walking `base`, whose relative structure should be preserved
+    pub fn with_base(path: impl Into<PathBuf>, base: impl Into<PathBuf>, layout: RawPcm) -> Self {
+        Self {
+            path: path.into(),
+            base: Some(base.into()),
+            layout,
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// Downmix the channels of the file this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+}
+
+#[cfg(feature = "fs")]
+impl AudioSource for RawPcmSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        relative_to_base(&self.path, self.base.as_deref())
+    }
+
+    fn local_path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    /// Layout given in advance, length from the size of the file
+    fn info(&self) -> Option<AudioInfo> {
+        let bytes = std::fs::metadata(&self.path).ok()?.len();
+        let frame = (self.layout.format.bytes_per_sample() * self.layout.channels) as u64;
+        Some(AudioInfo {
+            sample_rate: self.layout.sample_rate,
+            channels: self.layout.channels,
+            frames: bytes / frame.max(1),
+        })
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        read_raw_pcm(
+            &self.path,
+            self.layout.sample_rate,
+            self.layout.format,
+            self.layout.channels,
+            self.downmix,
+        )
+    }
+}
+
+/// WAV-encoded bytes held in memory (e.g. an upload or an archive member)
+#[derive(Debug, Clone)]
+pub struct BufferSource {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+}
+
+impl BufferSource {
+    pub fn new(name: impl Into<String>, bytes: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            bytes,
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// Downmix the channels of the buffer this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+}
+
+impl AudioSource for BufferSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+
+    fn info(&self) -> Option<AudioInfo> {
+        wav_info(Cursor::new(&self.bytes))
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        read_audio_mono_from_reader(Cursor::new(&self.bytes), self.downmix)
+    }
+
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        read_audio_segment_from_reader(Cursor::new(&self.bytes), start, end, self.downmix)
+    }
+
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        read_audio_mono_lenient_from_reader(Cursor::new(&self.bytes), self.downmix)
+    }
+}
+
+/// Already decoded mono samples (e.g. produced by a capture device or another library)
+#[derive(Debug, Clone)]
+pub struct SamplesSource {
+    pub name: String,
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl SamplesSource {
+    pub fn new(name: impl Into<String>, samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            name: name.into(),
+            samples,
+            sample_rate,
+        }
+    }
+}
+
+impl AudioSource for SamplesSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+
+    fn info(&self) -> Option<AudioInfo> {
+        Some(AudioInfo {
+            sample_rate: self.sample_rate,
+            channels: 1,
+            frames: self.samples.len() as u64,
+        })
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        Ok((self.samples.clone(), self.sample_rate))
+    }
+}
+
+/// Default largest body downloaded by `HttpSource`: the largest a RIFF/WAVE file can be (its
+/// 32-bit length and the 8 bytes before it)
+#[cfg(feature = "http")]
+pub const MAX_DOWNLOAD_SIZE: u64 = u32::MAX as u64 + 8;
+
+/// WAV file served over HTTP(S). The body is downloaded in memory and decoded like a buffer.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    pub url: String,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+    /// Largest body downloaded (bytes): longer ones fail instead of filling memory
+    pub max_size: u64,
+}
+
+#[cfg(feature = "http")]
+impl HttpSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            downmix: DownmixMode::default(),
+            max_size: MAX_DOWNLOAD_SIZE,
+        }
+    }
+
+    /// Refuse bodies longer than `max_size` bytes instead of `MAX_DOWNLOAD_SIZE`
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Downmix the channels of the download this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+
+    /// Download the raw body of the URL
+    fn fetch(&self) -> Result<Vec<u8>> {
+        ureq::get(&self.url)
+            .call()
+            .map_err(|e| {
+                SpectrsError::io(
+                    format!("Failed to fetch {}", self.url),
+                    std::io::Error::other(e),
+                )
+            })?
+            .body_mut()
+            .with_config()
+            // ureq stops at 10 MB by default, about a minute of CD-quality stereo
+            .limit(self.max_size)
+            .read_to_vec()
+            .map_err(|e| {
+                SpectrsError::io(
+                    format!("Failed to download {}", self.url),
+                    std::io::Error::other(e),
+                )
+            })
+    }
+}
+
+#[cfg(feature = "http")]
+impl AudioSource for HttpSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        // Last path segment of the URL, ignoring query string and fragment
+        let path = self.url.split(['?', '#']).next().unwrap_or(&self.url);
+        let file_name = path
+            .rsplit('/')
+            .find(|segment| !segment.is_empty())
+            .unwrap_or("download");
+        PathBuf::from(file_name)
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        read_audio_mono_from_reader(Cursor::new(self.fetch()?), self.downmix)
+    }
+
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        read_audio_segment_from_reader(Cursor::new(self.fetch()?), start, end, self.downmix)
+    }
+
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        read_audio_mono_lenient_from_reader(Cursor::new(self.fetch()?), self.downmix)
+    }
+}
+
+/// Check whether an input string designates a remote (HTTP/HTTPS) source
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Check whether a path names an archive of audio files (.zip, .tar, .tar.gz or .tgz, see
+/// `io::archive`)
+pub fn is_archive_path(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = name.to_ascii_lowercase();
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+/// Check whether an input string designates a location in an object store (`s3://` or
+/// `file://`, see `io::store`)
+pub fn is_store_url(input: &str) -> bool {
+    input.starts_with("s3://") || input.starts_with("file://")
+}
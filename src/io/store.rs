@@ -0,0 +1,251 @@
+//! Object stores as inputs and output directories (enabled with the `object-store` feature),
+//! for datasets that never touch the local disk.
+//!
+//! A location is a URL naming a bucket and a prefix, such as `s3://bucket/recordings`. S3
+//! credentials, region and endpoint (e.g. of MinIO) come from the usual `AWS_*` environment
+//! variables. `file://` URLs designate local directories through the same code path, e.g. to try
+//! a pipeline before pointing it at a bucket.
+//!
+//! Audio objects are downloaded into memory as they are decoded, and outputs are uploaded one by
+//! one, so nothing is kept on disk. The object store API is asynchronous: calls block on a
+//! runtime shared by the process, so they must not be made from an asynchronous context.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::audio::{
+    DownmixMode, SalvageReport, read_audio_mono_from_reader, read_audio_mono_lenient_from_reader,
+    read_audio_segment_from_reader,
+};
+use crate::io::source::AudioSource;
+use object_store::ObjectStore;
+use object_store::path::{Path as ObjectPath, PathPart};
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+/// Runtime running the requests to object stores
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the object store runtime")
+    })
+}
+
+fn store_error(message: String, error: object_store::Error) -> SpectrsError {
+    SpectrsError::io(message, std::io::Error::other(error))
+}
+
+/// Prefix of a bucket (or of a local directory) objects are read from or written to
+#[derive(Debug, Clone)]
+pub struct StoreLocation {
+    store: Arc<dyn ObjectStore>,
+    /// URL of the prefix, without trailing slash
+    url: String,
+    prefix: ObjectPath,
+}
+
+impl StoreLocation {
+    /// Location of a URL: `s3://bucket/prefix` (configured from the `AWS_*` environment
+    /// variables) or `file:///absolute/directory`
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            SpectrsError::InvalidParams(format!("Invalid object store URL '{}': {}", url, reason))
+        };
+        let (scheme, rest) = url.split_once("://").ok_or_else(|| invalid("no scheme"))?;
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let prefix = ObjectPath::from_url_path(path).map_err(|_| invalid("invalid path"))?;
+        let store: Arc<dyn ObjectStore> = match scheme {
+            "s3" => {
+                if host.is_empty() {
+                    return Err(invalid("no bucket"));
+                }
+                let store = object_store::aws::AmazonS3Builder::from_env()
+                    .with_bucket_name(host)
+                    .build()
+                    .map_err(|e| store_error(format!("Failed to connect to {}", url), e))?;
+                Arc::new(store)
+            }
+            "file" => {
+                if !host.is_empty() && host != "localhost" {
+                    return Err(invalid("only local files are supported"));
+                }
+                Arc::new(object_store::local::LocalFileSystem::new())
+            }
+            _ => return Err(invalid("only s3:// and file:// are supported")),
+        };
+        Ok(Self::new(store, url.trim_end_matches('/'), prefix))
+    }
+
+    /// Location of `prefix` in any object store (e.g. an in-memory one), designated by `url`
+    pub fn new(store: Arc<dyn ObjectStore>, url: impl Into<String>, prefix: ObjectPath) -> Self {
+        Self {
+            store,
+            url: url.into(),
+            prefix,
+        }
+    }
+
+    /// URL of the location
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// URL of an object, by its path relative to the location
+    pub fn url_of(&self, relative: &Path) -> String {
+        let relative = relative_parts(relative)
+            .map(|part| part.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("{}/{}", self.url, relative)
+    }
+
+    fn object_path(&self, relative: &Path) -> ObjectPath {
+        self.prefix
+            .parts()
+            .chain(relative_parts(relative))
+            .collect()
+    }
+
+    /// Paths (relative to the location) of every object under it, in order
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        use futures::TryStreamExt;
+
+        let prefix = (!self.prefix.as_ref().is_empty()).then_some(&self.prefix);
+        let objects: Vec<object_store::ObjectMeta> = runtime()
+            .block_on(self.store.list(prefix).try_collect())
+            .map_err(|e| store_error(format!("Failed to list {}", self.url), e))?;
+        let mut paths: Vec<PathBuf> = objects
+            .iter()
+            .filter_map(|object| object.location.prefix_match(&self.prefix))
+            .map(|parts| parts.map(|part| part.as_ref().to_string()).collect())
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Download an object, by its path relative to the location
+    pub fn get(&self, relative: &Path) -> Result<Vec<u8>> {
+        let url = self.url_of(relative);
+        let location = self.object_path(relative);
+        runtime()
+            .block_on(async {
+                let object = self.store.get(&location).await?;
+                object.bytes().await
+            })
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| store_error(format!("Failed to download {}", url), e))
+    }
+
+    /// Upload an object, by its path relative to the location
+    pub fn put(&self, relative: &Path, bytes: Vec<u8>) -> Result<()> {
+        let location = self.object_path(relative);
+        runtime()
+            .block_on(self.store.put(&location, bytes.into()))
+            .map(|_| ())
+            .map_err(|e| store_error(format!("Failed to upload {}", self.url_of(relative)), e))
+    }
+
+    /// Upload the files of a local directory (recursively) under the location, keeping their
+    /// paths relative to it. Returns the URLs of the uploaded objects, in order
+    pub fn upload_dir(&self, dir: &Path) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)?;
+        files.sort();
+        let mut urls = Vec::with_capacity(files.len());
+        for file in files {
+            let relative = file.strip_prefix(dir).unwrap_or(&file);
+            let bytes = std::fs::read(&file)
+                .map_err(|e| SpectrsError::io(format!("Failed to read {}", file.display()), e))?;
+            self.put(relative, bytes)?;
+            urls.push(self.url_of(relative));
+        }
+        Ok(urls)
+    }
+}
+
+/// Components of a relative path as parts of an object path
+fn relative_parts(relative: &Path) -> impl Iterator<Item = PathPart<'_>> {
+    relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(PathPart::from(part.to_string_lossy().into_owned())),
+            _ => None,
+        })
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| SpectrsError::io(format!("Failed to read {}", dir.display()), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| SpectrsError::io(format!("Failed to read {}", dir.display()), e))?
+            .path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// WAV object in an object store. The object is downloaded in memory and decoded like a buffer.
+#[derive(Debug, Clone)]
+pub struct ObjectSource {
+    pub location: Arc<StoreLocation>,
+    /// Path of the object relative to the location
+    pub relative: PathBuf,
+    /// How multichannel audio is reduced to mono
+    pub downmix: DownmixMode,
+}
+
+impl ObjectSource {
+    pub fn new(location: Arc<StoreLocation>, relative: impl Into<PathBuf>) -> Self {
+        Self {
+            location,
+            relative: relative.into(),
+            downmix: DownmixMode::default(),
+        }
+    }
+
+    /// Source of the object at a URL such as `s3://bucket/recordings/a.wav`
+    pub fn parse(url: &str) -> Result<Self> {
+        let (parent, name) = url.trim_end_matches('/').rsplit_once('/').ok_or_else(|| {
+            SpectrsError::InvalidParams(format!("Invalid object store URL '{}'", url))
+        })?;
+        Ok(Self::new(Arc::new(StoreLocation::parse(parent)?), name))
+    }
+
+    /// Downmix the channels of the object this way instead of averaging them
+    pub fn with_downmix(mut self, downmix: DownmixMode) -> Self {
+        self.downmix = downmix;
+        self
+    }
+}
+
+impl AudioSource for ObjectSource {
+    fn name(&self) -> String {
+        self.location.url_of(&self.relative)
+    }
+
+    fn relative_path(&self) -> PathBuf {
+        self.relative.clone()
+    }
+
+    fn read_mono(&self) -> Result<(Vec<f32>, u32)> {
+        let bytes = self.location.get(&self.relative)?;
+        read_audio_mono_from_reader(Cursor::new(bytes), self.downmix)
+    }
+
+    fn read_segment(&self, start: f32, end: Option<f32>) -> Result<(Vec<f32>, u32)> {
+        let bytes = self.location.get(&self.relative)?;
+        read_audio_segment_from_reader(Cursor::new(bytes), start, end, self.downmix)
+    }
+
+    fn read_mono_lenient(&self) -> Result<(Vec<f32>, u32, Option<SalvageReport>)> {
+        let bytes = self.location.get(&self.relative)?;
+        read_audio_mono_lenient_from_reader(Cursor::new(bytes), self.downmix)
+    }
+}
@@ -0,0 +1,76 @@
+//! Spectrograms drawn with colored Unicode half blocks, for previews in a terminal.
+//!
+//! Every cell shows two pixels stacked vertically: the upper half block `▀` is drawn with the
+//! color of the upper pixel as foreground and the color of the lower pixel as background.
+
+use crate::io::image::{Colormap, apply_colormap};
+
+/// Character drawn in every cell
+pub const HALF_BLOCK: char = '▀';
+
+/// Colors of one terminal cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    /// Color of the upper pixel (foreground)
+    pub top: [u8; 3],
+    /// Color of the lower pixel (background)
+    pub bottom: [u8; 3],
+}
+
+/// Range of the log1p-scaled values of a spectrogram (the scaling of `save_spectrogram_image`).
+/// Computed once over the whole spectrogram so colors do not change while scrolling.
+pub fn log_range(spectrogram: &[Vec<f32>]) -> (f32, f32) {
+    spectrogram
+        .iter()
+        .flatten()
+        .map(|&v| (v + 1.0).ln())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        })
+}
+
+/// Render a [freq][time] spectrogram into `rows` lines of cells, one column per frame and the
+/// highest frequencies on the first line. Frequency bins are averaged (or repeated) to fit the
+/// 2 * `rows` pixels of each column; colors are normalized with `range` (see `log_range`).
+pub fn render_half_blocks(
+    spectrogram: &[Vec<f32>],
+    colormap: Colormap,
+    range: (f32, f32),
+    rows: usize,
+) -> Vec<Vec<Cell>> {
+    let n_bins = spectrogram.len();
+    let n_frames = spectrogram.first().map_or(0, Vec::len);
+    let n_pixels = 2 * rows;
+    if n_bins == 0 || n_frames == 0 {
+        return vec![Vec::new(); rows];
+    }
+
+    // Color of pixel `y` (counted from the bottom) of frame `t`
+    let pixel = |y: usize, t: usize| {
+        let first = y * n_bins / n_pixels;
+        let last = ((y + 1) * n_bins / n_pixels).max(first + 1);
+        let mean = spectrogram[first..last]
+            .iter()
+            .map(|row| (row[t] + 1.0).ln())
+            .sum::<f32>()
+            / (last - first) as f32;
+        let normalized = if range.1 > range.0 {
+            (mean - range.0) / (range.1 - range.0)
+        } else {
+            0.5
+        };
+        apply_colormap(normalized, colormap)
+    };
+
+    (0..rows)
+        .map(|line| {
+            let top = n_pixels - 1 - 2 * line;
+            (0..n_frames)
+                .map(|t| Cell {
+                    top: pixel(top, t),
+                    bottom: pixel(top - 1, t),
+                })
+                .collect()
+        })
+        .collect()
+}
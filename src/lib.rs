@@ -1,2 +1,9 @@
+pub mod augment;
+pub mod cancel;
+pub mod error;
+pub mod features;
+pub mod filter;
 pub mod io;
 pub mod spectrogram;
+#[cfg(feature = "wasm")]
+pub mod wasm;
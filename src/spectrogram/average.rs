@@ -0,0 +1,142 @@
+// Average of spectrograms across files, e.g. to characterize a recording setup or a dataset.
+// The average spectrum weighs every frame of every file equally (the long-term spectrum of the
+// whole collection), while the average spectrogram first brings each file to the same number
+// of frames, so that the start, middle and end of every file line up whatever its duration.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::pooling::{FitMode, fit_frames, pool_time};
+
+/// Running sums over spectrograms of the same height
+#[derive(Debug, Clone)]
+pub struct AverageAccumulator {
+    /// Frames every spectrogram is brought to before it is added to the average spectrogram
+    width: usize,
+    /// Number of spectrograms added
+    n_inputs: u64,
+    /// Number of frames added to the spectrum
+    n_frames: u64,
+    /// Per-row sum of every frame
+    spectrum: Vec<f64>,
+    /// Sum of the time-normalized spectrograms ([freq][time])
+    spectrogram: Vec<Vec<f64>>,
+}
+
+impl AverageAccumulator {
+    /// Empty average whose spectrogram is `width` frames wide
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            n_inputs: 0,
+            n_frames: 0,
+            spectrum: Vec::new(),
+            spectrogram: Vec::new(),
+        }
+    }
+
+    /// Add a [freq][time] spectrogram. Long ones are average-pooled to the width of the
+    /// average and short ones interpolated along time; spectrograms without frames are skipped.
+    pub fn add(&mut self, spectrogram: &[Vec<f32>]) -> Result<()> {
+        let n_frames = spectrogram.first().map_or(0, |row| row.len());
+        if n_frames == 0 || self.width == 0 {
+            return Ok(());
+        }
+        if self.n_inputs == 0 {
+            self.spectrum = vec![0.0; spectrogram.len()];
+            self.spectrogram = vec![vec![0.0; self.width]; spectrogram.len()];
+        } else if self.spectrum.len() != spectrogram.len() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram has {} rows but the average has {} (were they computed with different parameters?)",
+                spectrogram.len(),
+                self.spectrum.len()
+            )));
+        }
+
+        let normalized = if n_frames > self.width {
+            pool_time(spectrogram, self.width)
+        } else {
+            fit_frames(spectrogram, self.width, FitMode::Resample, 0.0)
+        };
+        for ((sum, row), (sums, normalized_row)) in self
+            .spectrum
+            .iter_mut()
+            .zip(spectrogram)
+            .zip(self.spectrogram.iter_mut().zip(&normalized))
+        {
+            *sum += row.iter().map(|&v| v as f64).sum::<f64>();
+            for (sum, &v) in sums.iter_mut().zip(normalized_row) {
+                *sum += v as f64;
+            }
+        }
+        self.n_inputs += 1;
+        self.n_frames += n_frames as u64;
+        Ok(())
+    }
+
+    /// Combine with the sums accumulated on another part of the dataset
+    pub fn merge(mut self, other: AverageAccumulator) -> Result<Self> {
+        if other.n_inputs == 0 {
+            return Ok(self);
+        }
+        if self.n_inputs == 0 {
+            return Ok(other);
+        }
+        if self.spectrum.len() != other.spectrum.len() || self.width != other.width {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Cannot merge averages of {}x{} and {}x{} spectrograms",
+                self.spectrum.len(),
+                self.width,
+                other.spectrum.len(),
+                other.width
+            )));
+        }
+        for (a, b) in self.spectrum.iter_mut().zip(other.spectrum) {
+            *a += b;
+        }
+        for (row, other_row) in self.spectrogram.iter_mut().zip(other.spectrogram) {
+            for (a, b) in row.iter_mut().zip(other_row) {
+                *a += b;
+            }
+        }
+        self.n_inputs += other.n_inputs;
+        self.n_frames += other.n_frames;
+        Ok(self)
+    }
+
+    /// Average spectrum and spectrogram of everything added so far
+    pub fn finish(self) -> Result<AverageSpectrogram> {
+        if self.n_inputs == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "No frames to average".to_string(),
+            ));
+        }
+        let n_frames = self.n_frames as f64;
+        let n_inputs = self.n_inputs as f64;
+        Ok(AverageSpectrogram {
+            n_inputs: self.n_inputs,
+            n_frames: self.n_frames,
+            spectrum: self
+                .spectrum
+                .iter()
+                .map(|sum| (sum / n_frames) as f32)
+                .collect(),
+            spectrogram: self
+                .spectrogram
+                .iter()
+                .map(|row| row.iter().map(|sum| (sum / n_inputs) as f32).collect())
+                .collect(),
+        })
+    }
+}
+
+/// Average of spectrograms across files, in their type (power or magnitude) and scale
+#[derive(Debug, Clone, PartialEq)]
+pub struct AverageSpectrogram {
+    /// Number of spectrograms averaged
+    pub n_inputs: u64,
+    /// Number of frames the spectrum was averaged over
+    pub n_frames: u64,
+    /// Mean of every row over all frames
+    pub spectrum: Vec<f32>,
+    /// Mean of the time-normalized spectrograms ([freq][time])
+    pub spectrogram: Vec<Vec<f32>>,
+}
@@ -0,0 +1,171 @@
+// Cepstral mean and variance normalization (CMVN) of spectrogram rows (frequency bins or mel
+// bands) using dataset-wide statistics. Statistics are accumulated over any number of
+// spectrograms, saved to a versioned JSON file and loaded back at inference time so that
+// training and inference features are normalized identically.
+
+use crate::error::{Result, SpectrsError};
+use crate::io::metadata::SCHEMA_VERSION;
+#[cfg(feature = "fs")]
+use crate::io::metadata::migrate;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Floor added to the variance before dividing, avoiding blow-ups on constant rows
+const VARIANCE_FLOOR: f64 = 1e-10;
+
+/// Running per-row sums over spectrograms of the same height
+#[derive(Debug, Clone, Default)]
+pub struct CmvnAccumulator {
+    n_frames: u64,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl CmvnAccumulator {
+    /// Add every frame of a [freq][time] spectrogram
+    pub fn add(&mut self, spectrogram: &[Vec<f32>]) -> Result<()> {
+        if self.sum.is_empty() {
+            self.sum = vec![0.0; spectrogram.len()];
+            self.sum_sq = vec![0.0; spectrogram.len()];
+        } else if self.sum.len() != spectrogram.len() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram has {} rows but the statistics have {}",
+                spectrogram.len(),
+                self.sum.len()
+            )));
+        }
+
+        for ((sum, sum_sq), row) in self.sum.iter_mut().zip(&mut self.sum_sq).zip(spectrogram) {
+            for &v in row {
+                *sum += v as f64;
+                *sum_sq += v as f64 * v as f64;
+            }
+        }
+        self.n_frames += spectrogram.first().map_or(0, |row| row.len()) as u64;
+        Ok(())
+    }
+
+    /// Combine with the sums accumulated on another part of the dataset
+    pub fn merge(mut self, other: CmvnAccumulator) -> Result<Self> {
+        if other.n_frames == 0 {
+            return Ok(self);
+        }
+        if self.n_frames == 0 {
+            return Ok(other);
+        }
+        if self.sum.len() != other.sum.len() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Cannot merge statistics with {} and {} rows",
+                self.sum.len(),
+                other.sum.len()
+            )));
+        }
+        for (a, b) in self.sum.iter_mut().zip(other.sum) {
+            *a += b;
+        }
+        for (a, b) in self.sum_sq.iter_mut().zip(other.sum_sq) {
+            *a += b;
+        }
+        self.n_frames += other.n_frames;
+        Ok(self)
+    }
+
+    /// Per-row mean and variance of everything added so far
+    pub fn finish(self) -> Result<CmvnStats> {
+        if self.n_frames == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "No frames to compute normalization statistics from".to_string(),
+            ));
+        }
+        let n = self.n_frames as f64;
+        let mean: Vec<f64> = self.sum.iter().map(|s| s / n).collect();
+        let variance = self
+            .sum_sq
+            .iter()
+            .zip(&mean)
+            .map(|(s, m)| ((s / n) - m * m).max(0.0) as f32)
+            .collect();
+        Ok(CmvnStats {
+            schema_version: SCHEMA_VERSION,
+            spectrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            n_frames: self.n_frames,
+            mean: mean.into_iter().map(|m| m as f32).collect(),
+            variance,
+        })
+    }
+}
+
+/// Dataset-wide per-row statistics, persisted as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CmvnStats {
+    /// Version of the JSON schema
+    pub schema_version: u32,
+    /// Version of spectrs that computed the statistics
+    pub spectrs_version: String,
+    /// Number of frames the statistics were computed over
+    pub n_frames: u64,
+    /// Mean of each row
+    pub mean: Vec<f32>,
+    /// Variance of each row
+    pub variance: Vec<f32>,
+}
+
+impl CmvnStats {
+    /// Normalize every row of a [freq][time] spectrogram to zero mean and unit variance
+    pub fn apply(&self, spectrogram: &mut [Vec<f32>]) -> Result<()> {
+        if spectrogram.len() != self.mean.len() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram has {} rows but the normalization statistics have {} \
+                 (were they computed with different parameters?)",
+                spectrogram.len(),
+                self.mean.len()
+            )));
+        }
+        for ((row, &mean), &variance) in spectrogram.iter_mut().zip(&self.mean).zip(&self.variance)
+        {
+            let scale = 1.0 / (variance as f64 + VARIANCE_FLOOR).sqrt();
+            for v in row.iter_mut() {
+                *v = ((*v as f64 - mean as f64) * scale) as f32;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the statistics as pretty-printed JSON
+    #[cfg(feature = "fs")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| SpectrsError::invalid_data("Failed to serialize CMVN statistics", e))?;
+        crate::io::atomic::write_atomically(path, |partial| {
+            std::fs::write(partial, json + "\n")
+                .map_err(|e| SpectrsError::io("Failed to write CMVN statistics", e))
+        })
+    }
+
+    /// Read statistics saved by `save`, upgrading files written by older versions of spectrs
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            SpectrsError::io(
+                format!("Failed to read CMVN statistics: {}", path.display()),
+                e,
+            )
+        })?;
+        let document = serde_json::from_str(&content).map_err(|e| {
+            SpectrsError::invalid_data(format!("Invalid JSON in {}", path.display()), e)
+        })?;
+        let document =
+            migrate(document).map_err(|e| e.context(format!("In {}", path.display())))?;
+        let stats: CmvnStats = serde_json::from_value(document).map_err(|e| {
+            SpectrsError::invalid_data(format!("Invalid CMVN statistics in {}", path.display()), e)
+        })?;
+        if stats.mean.len() != stats.variance.len() {
+            return Err(SpectrsError::InvalidData {
+                message: format!("Mean and variance lengths differ in {}", path.display()),
+                source: None,
+            });
+        }
+        Ok(stats)
+    }
+}
@@ -0,0 +1,194 @@
+//! Similarity metrics between two spectrograms, e.g. spectrs output against a librosa or
+//! torchaudio reference. Spectrograms of different shapes are compared over their common
+//! region (the lowest rows and first frames of both), as frame counts of different toolkits
+//! often differ by one or two at the edges.
+//!
+//! `diff_spectrograms` maps where two spectrograms differ, bin by bin, e.g. to check that a
+//! codec or a denoiser only touched the expected bands.
+
+use crate::error::{Result, SpectrsError};
+
+/// Similarity metric between a spectrogram and a reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Metric {
+    /// Pearson correlation coefficient of the values
+    Correlation,
+    /// Mean squared error
+    Mse,
+    /// Root mean squared error
+    Rmse,
+    /// Root mean squared error divided by the mean of the reference
+    NormalizedRmse,
+    /// Mean relative error on the bins of the reference above 1% of its maximum
+    RelativeError,
+    /// Largest absolute difference
+    MaxAbsError,
+}
+
+impl Metric {
+    /// Every metric, in the order of the enum
+    pub const ALL: [Metric; 6] = [
+        Metric::Correlation,
+        Metric::Mse,
+        Metric::Rmse,
+        Metric::NormalizedRmse,
+        Metric::RelativeError,
+        Metric::MaxAbsError,
+    ];
+
+    /// Snake-case name of the metric, e.g. `relative_error`
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Correlation => "correlation",
+            Metric::Mse => "mse",
+            Metric::Rmse => "rmse",
+            Metric::NormalizedRmse => "normalized_rmse",
+            Metric::RelativeError => "relative_error",
+            Metric::MaxAbsError => "max_abs_error",
+        }
+    }
+
+    /// Value of the metric for `spectrogram` against `reference` (both [freq][time]), over
+    /// their common shape. Accumulated in f64; NaN when undefined (e.g. the correlation of a
+    /// constant spectrogram or metrics of empty spectrograms).
+    pub fn compute(self, spectrogram: &[Vec<f32>], reference: &[Vec<f32>]) -> f64 {
+        let [n_rows, n_cols] = common_shape(spectrogram, reference);
+        let pairs: Vec<(f64, f64)> = spectrogram[..n_rows]
+            .iter()
+            .zip(&reference[..n_rows])
+            .flat_map(|(row, reference_row)| {
+                row[..n_cols]
+                    .iter()
+                    .zip(&reference_row[..n_cols])
+                    .map(|(&x, &y)| (x as f64, y as f64))
+            })
+            .collect();
+        if pairs.is_empty() {
+            return f64::NAN;
+        }
+        let n = pairs.len() as f64;
+        let mse = || pairs.iter().map(|(x, y)| (x - y).powi(2)).sum::<f64>() / n;
+
+        match self {
+            Metric::Correlation => {
+                let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+                let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+                let (mut covariance, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+                for (x, y) in &pairs {
+                    covariance += (x - mean_x) * (y - mean_y);
+                    var_x += (x - mean_x).powi(2);
+                    var_y += (y - mean_y).powi(2);
+                }
+                covariance / (var_x * var_y).sqrt()
+            }
+            Metric::Mse => mse(),
+            Metric::Rmse => mse().sqrt(),
+            Metric::NormalizedRmse => {
+                let mean_reference = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+                if mean_reference > 0.0 {
+                    mse().sqrt() / mean_reference
+                } else {
+                    f64::INFINITY
+                }
+            }
+            Metric::RelativeError => {
+                // Near-silent bins would dominate the mean: only keep significant ones
+                let max_reference = pairs.iter().map(|(_, y)| *y).fold(f64::MIN, f64::max);
+                let threshold = 0.01 * max_reference;
+                let significant: Vec<f64> = pairs
+                    .iter()
+                    .filter(|(_, y)| *y > threshold)
+                    .map(|(x, y)| (x - y).abs() / y)
+                    .collect();
+                if significant.is_empty() {
+                    pairs
+                        .iter()
+                        .map(|(x, y)| (x - y).abs() / (y.abs() + 1e-8))
+                        .sum::<f64>()
+                        / n
+                } else {
+                    significant.iter().sum::<f64>() / significant.len() as f64
+                }
+            }
+            Metric::MaxAbsError => pairs.iter().map(|(x, y)| (x - y).abs()).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Shape ([rows, frames]) of the region shared by two spectrograms
+pub fn common_shape(a: &[Vec<f32>], b: &[Vec<f32>]) -> [usize; 2] {
+    let n_cols = |spec: &[Vec<f32>]| spec.iter().map(Vec::len).min().unwrap_or(0);
+    [a.len().min(b.len()), n_cols(a).min(n_cols(b))]
+}
+
+/// Values below this floor are raised to it before taking logarithms or ratios, so that
+/// silent bins give finite levels (-100 dB)
+const DIFF_FLOOR: f32 = 1e-10;
+
+/// Kind of difference map computed by `diff_spectrograms`. Values are taken as powers (10
+/// log10 in dB): double the dB of magnitude spectrograms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DiffMode {
+    /// Absolute difference of the values, |spectrogram - reference|
+    #[default]
+    Absolute,
+    /// Level of the spectrogram relative to the reference (dB), positive where it is louder
+    LogRatio,
+    /// Signal-to-noise ratio of every bin (dB): level of the reference over the level of the
+    /// difference, high where the spectrogram is faithful
+    Snr,
+}
+
+/// How spectrograms with different numbers of frames are aligned by `diff_spectrograms`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TimeAlignment {
+    /// Keep the frames both spectrograms have
+    #[default]
+    Crop,
+    /// Pad the shorter spectrogram with silence (zeros) to the length of the longer
+    Pad,
+}
+
+/// Difference map of `spectrogram` against `reference` (both [freq][time] with the same number
+/// of rows), bin by bin, as described by `mode`. Spectrograms of different lengths are
+/// aligned along time as described by `alignment`.
+pub fn diff_spectrograms(
+    spectrogram: &[Vec<f32>],
+    reference: &[Vec<f32>],
+    mode: DiffMode,
+    alignment: TimeAlignment,
+) -> Result<Vec<Vec<f32>>> {
+    if spectrogram.len() != reference.len() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Spectrograms with different numbers of rows cannot be differenced: {} and {}",
+            spectrogram.len(),
+            reference.len()
+        )));
+    }
+    let n_frames = |spec: &[Vec<f32>]| spec.iter().map(Vec::len).min().unwrap_or(0);
+    let n_frames = match alignment {
+        TimeAlignment::Crop => n_frames(spectrogram).min(n_frames(reference)),
+        TimeAlignment::Pad => n_frames(spectrogram).max(n_frames(reference)),
+    };
+    let level = |value: f32| 10.0 * value.max(DIFF_FLOOR).log10();
+    let diff = |value: f32, reference: f32| match mode {
+        DiffMode::Absolute => (value - reference).abs(),
+        DiffMode::LogRatio => level(value) - level(reference),
+        DiffMode::Snr => level(reference) - level((value - reference).abs()),
+    };
+    Ok(spectrogram
+        .iter()
+        .zip(reference)
+        .map(|(row, reference_row)| {
+            (0..n_frames)
+                .map(|t| {
+                    let at = |row: &[f32]| row.get(t).copied().unwrap_or(0.0);
+                    diff(at(row), at(reference_row))
+                })
+                .collect()
+        })
+        .collect())
+}
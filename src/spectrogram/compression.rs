@@ -0,0 +1,160 @@
+// Compression of the dynamic range of power (or magnitude) spectrograms, the last stage before
+// values are exported: natural log power, decibels (optionally clipped `top_db` below the peak,
+// as librosa's `power_to_db`) or log1p. Values are floored at `amin` (librosa's 1e-10 unless
+// given) before taking logarithms, so that silent frames do not become -inf.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::stft::{Float, SpectrogramType, constant};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Smallest power taken the logarithm of, unless the parameters give their own (librosa's
+/// `amin`)
+pub const DEFAULT_AMIN: f32 = 1e-10;
+
+/// Compressed scale of the exported values
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type")]
+pub enum Compression {
+    /// Natural logarithm of the power
+    LogPower,
+    /// Decibels of the power (10 log10, relative to 1), no lower than `top_db` below the peak
+    /// of the spectrogram if given
+    Db { top_db: Option<f32> },
+    /// ln(1 + value), of the values as they are (powers or magnitudes)
+    Log1p,
+}
+
+impl Compression {
+    /// Check that `top_db` is a level difference
+    pub fn validate(&self) -> Result<()> {
+        if let Compression::Db {
+            top_db: Some(top_db),
+        } = *self
+            && !(top_db >= 0.0 && top_db.is_finite())
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "top_db must be a non-negative number of dB, got {}",
+                top_db
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compressed value of a power (or, for `Magnitude` spectrograms, magnitude) value
+    fn compress<T: Float>(self, value: T, spectrogram_type: SpectrogramType, amin: T) -> T {
+        // Powers of magnitudes are their squares: the log doubles
+        let power_factor: T = match spectrogram_type {
+            SpectrogramType::Power => T::one(),
+            SpectrogramType::Magnitude => constant(2.0),
+        };
+        match self {
+            Compression::LogPower => power_factor * value.max(amin).ln(),
+            Compression::Db { .. } => constant::<T>(10.0) * power_factor * value.max(amin).log10(),
+            Compression::Log1p => value.max(T::zero()).ln_1p(),
+        }
+    }
+
+    /// Lowest level kept in a dB spectrogram whose highest level is `peak`
+    fn floor<T: Float>(self, peak: T) -> Option<T> {
+        match self {
+            Compression::Db {
+                top_db: Some(top_db),
+            } => Some(peak - constant(top_db as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// Compress the values of a [freq][time] spectrogram in place (single-threaded)
+pub fn compress_spectrogram<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    compression: Compression,
+    spectrogram_type: SpectrogramType,
+) {
+    compress_spectrogram_with_amin(spectrogram, compression, spectrogram_type, DEFAULT_AMIN)
+}
+
+/// Compress the values of a [freq][time] spectrogram in place (single-threaded), flooring
+/// them at `amin` before taking logarithms
+pub fn compress_spectrogram_with_amin<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    compression: Compression,
+    spectrogram_type: SpectrogramType,
+    amin: f32,
+) {
+    let amin: T = constant(amin as f64);
+    for row in spectrogram.iter_mut() {
+        for v in row.iter_mut() {
+            *v = compression.compress(*v, spectrogram_type, amin);
+        }
+    }
+    let peak = peak(spectrogram.iter());
+    if let Some(floor) = peak.and_then(|peak| compression.floor(peak)) {
+        for v in spectrogram.iter_mut().flatten() {
+            *v = v.max(floor);
+        }
+    }
+}
+
+/// Compress the values of a [freq][time] spectrogram in place (parallelized over rows)
+pub fn par_compress_spectrogram<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    compression: Compression,
+    spectrogram_type: SpectrogramType,
+) {
+    par_compress_spectrogram_with_amin(spectrogram, compression, spectrogram_type, DEFAULT_AMIN)
+}
+
+/// Compress the values of a [freq][time] spectrogram in place (parallelized over rows),
+/// flooring them at `amin` before taking logarithms
+#[cfg(feature = "parallel")]
+pub fn par_compress_spectrogram_with_amin<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    compression: Compression,
+    spectrogram_type: SpectrogramType,
+    amin: f32,
+) {
+    let amin: T = constant(amin as f64);
+    spectrogram.par_iter_mut().for_each(|row| {
+        for v in row.iter_mut() {
+            *v = compression.compress(*v, spectrogram_type, amin);
+        }
+    });
+    let peak = spectrogram
+        .par_iter()
+        .filter_map(|row| peak(std::iter::once(row)))
+        .reduce_with(T::max);
+    if let Some(floor) = peak.and_then(|peak| compression.floor(peak)) {
+        spectrogram.par_iter_mut().for_each(|row| {
+            for v in row.iter_mut() {
+                *v = v.max(floor);
+            }
+        });
+    }
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the values are compressed sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_compress_spectrogram_with_amin<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    compression: Compression,
+    spectrogram_type: SpectrogramType,
+    amin: f32,
+) {
+    compress_spectrogram_with_amin(spectrogram, compression, spectrogram_type, amin)
+}
+
+/// Raise the values of a [freq][time] spectrogram below `floor` to it (NaN values included),
+/// e.g. to keep the filter bank energies of silent frames positive (the mel floor)
+pub fn floor_spectrogram<T: Float>(spectrogram: &mut [Vec<T>], floor: f32) {
+    let floor: T = constant(floor as f64);
+    for v in spectrogram.iter_mut().flatten() {
+        *v = v.max(floor);
+    }
+}
+
+/// Highest value of some rows (None if they are empty)
+fn peak<'a, T: Float>(rows: impl Iterator<Item = &'a Vec<T>>) -> Option<T> {
+    rows.flatten().copied().reduce(T::max)
+}
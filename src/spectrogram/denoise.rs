@@ -0,0 +1,85 @@
+// Spectral subtraction. A per-row noise floor (the noise profile) is estimated either from a
+// recording of the noise alone or from the quietest frames of the signal itself, then
+// subtracted from every frame of a (linear power or magnitude) spectrogram. Values are floored
+// at a fraction of their original level rather than at zero, which avoids the isolated
+// spectral peaks ("musical noise") left by plain subtraction.
+
+use crate::error::{Result, SpectrsError};
+
+/// Per-row (frequency bin or mel band) noise floor of a [freq][time] spectrogram
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseProfile {
+    /// Noise level of each row
+    pub floor: Vec<f32>,
+}
+
+impl NoiseProfile {
+    /// Average every row of a spectrogram of the noise alone (e.g. a recording of the
+    /// background without the sounds of interest)
+    pub fn from_noise(noise: &[Vec<f32>]) -> Result<Self> {
+        let n_frames = noise.first().map_or(0, |row| row.len());
+        Self::from_frames(noise, &(0..n_frames).collect::<Vec<_>>())
+    }
+
+    /// Average every row over the `percent` percent quietest frames (by total energy) of the
+    /// spectrogram, assumed to hold only the background noise. At least one frame is used.
+    pub fn from_quietest_frames(spectrogram: &[Vec<f32>], percent: f32) -> Result<Self> {
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Percentage of quietest frames must be in (0, 100], got {}",
+                percent
+            )));
+        }
+        let n_frames = spectrogram.first().map_or(0, |row| row.len());
+        let energy: Vec<f64> = (0..n_frames)
+            .map(|t| spectrogram.iter().map(|row| row[t] as f64).sum())
+            .collect();
+        let mut frames: Vec<usize> = (0..n_frames).collect();
+        frames.sort_by(|&a, &b| energy[a].total_cmp(&energy[b]).then(a.cmp(&b)));
+        let n_quiet = ((n_frames as f64 * percent as f64 / 100.0).ceil() as usize).max(1);
+        frames.truncate(n_quiet);
+        Self::from_frames(spectrogram, &frames)
+    }
+
+    /// Average every row over the given frames
+    fn from_frames(spectrogram: &[Vec<f32>], frames: &[usize]) -> Result<Self> {
+        if frames.is_empty() {
+            return Err(SpectrsError::InvalidParams(
+                "No frames to estimate the noise profile from".to_string(),
+            ));
+        }
+        let floor = spectrogram
+            .iter()
+            .map(|row| {
+                let sum: f64 = frames.iter().map(|&t| row[t] as f64).sum();
+                (sum / frames.len() as f64) as f32
+            })
+            .collect();
+        Ok(Self { floor })
+    }
+
+    /// Subtract the noise floor from every frame of a spectrogram with the same rows. Values
+    /// are kept at no less than `spectral_floor` (in [0, 1]) times their original level.
+    pub fn subtract(&self, spectrogram: &mut [Vec<f32>], spectral_floor: f32) -> Result<()> {
+        if spectrogram.len() != self.floor.len() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram has {} rows but the noise profile has {} \
+                 (was it computed with different parameters?)",
+                spectrogram.len(),
+                self.floor.len()
+            )));
+        }
+        if !(0.0..=1.0).contains(&spectral_floor) {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectral floor must be in [0, 1], got {}",
+                spectral_floor
+            )));
+        }
+        for (row, &noise) in spectrogram.iter_mut().zip(&self.floor) {
+            for v in row.iter_mut() {
+                *v = (*v - noise).max(spectral_floor * *v);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+// Log mel filter bank ("fbank") features following the conventions of speech toolkits rather
+// than librosa: frames snipped at the edges, DC removal, pre-emphasis, povey/hamming windows,
+// triangular filters defined on the HTK mel scale and a log floor. With the Kaldi preset the
+// output matches `compute-fbank-feats` (with --dither=0) up to floating-point rounding.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::params::SpectrogramParams;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Toolkit whose feature definition is reproduced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Compat {
+    /// librosa-style STFT and mel spectrograms (the regular spectrs pipeline)
+    #[default]
+    Librosa,
+    /// Kaldi `compute-fbank-feats` defaults
+    Kaldi,
+    /// HTK `HCopy` FBANK defaults
+    Htk,
+    /// `torchaudio.transforms.MelSpectrogram` defaults (reflect padding, periodic Hann window,
+    /// unnormalized HTK filters)
+    Torchaudio,
+}
+
+impl Compat {
+    /// Whether the preset computes log mel filter banks (see `FbankOptions`)
+    pub fn is_filter_bank(self) -> bool {
+        matches!(self, Compat::Kaldi | Compat::Htk)
+    }
+}
+
+/// Window function applied to each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FbankWindow {
+    Hann,
+    Hamming,
+    /// Kaldi's default: a Hann window raised to the power 0.85
+    Povey,
+    Rectangular,
+}
+
+impl FbankWindow {
+    /// Window coefficients over `length` samples (symmetric, as in Kaldi)
+    pub fn coefficients(self, length: usize) -> Vec<f32> {
+        let a = 2.0 * PI / (length.max(2) - 1) as f32;
+        (0..length)
+            .map(|i| {
+                let i = i as f32;
+                match self {
+                    FbankWindow::Hann => 0.5 - 0.5 * (a * i).cos(),
+                    FbankWindow::Hamming => 0.54 - 0.46 * (a * i).cos(),
+                    FbankWindow::Povey => (0.5 - 0.5 * (a * i).cos()).powf(0.85),
+                    FbankWindow::Rectangular => 1.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Full set of options of the fbank computation (named after their Kaldi counterparts)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FbankOptions {
+    /// Sample rate of the audio (Hz)
+    pub sample_rate: u32,
+    /// Samples per frame
+    pub frame_length: usize,
+    /// Samples between successive frames
+    pub frame_shift: usize,
+    /// Only output frames that fit completely inside the signal. Otherwise, the number of
+    /// frames depends only on the frame shift and the signal is reflected at the edges
+    pub snip_edges: bool,
+    /// Standard deviation of the Gaussian noise added to each sample (0 disables dithering)
+    pub dither: f32,
+    /// Subtract the mean of each frame before further processing
+    pub remove_dc_offset: bool,
+    /// Pre-emphasis coefficient (0 disables pre-emphasis)
+    pub preemphasis: f32,
+    /// Window applied after pre-emphasis
+    pub window: FbankWindow,
+    /// Zero-pad frames to the next power of two before the FFT
+    pub round_to_power_of_two: bool,
+    /// Use the power spectrum (true) or the magnitude spectrum (false)
+    pub use_power: bool,
+    /// Number of triangular mel filters
+    pub num_mel_bins: usize,
+    /// Lower edge of the first filter (Hz)
+    pub low_freq: f32,
+    /// Upper edge of the last filter (Hz); zero or negative values are offsets from Nyquist
+    pub high_freq: f32,
+    /// Filter bank energies are floored at this value before taking the log
+    pub log_floor: f32,
+}
+
+impl FbankOptions {
+    /// Defaults of Kaldi's `compute-fbank-feats` (25 ms frames every 10 ms, povey window,
+    /// 23 filters from 20 Hz), except for dithering which is disabled for reproducibility
+    pub fn kaldi(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            frame_length: ms_to_samples(sample_rate, 25.0),
+            frame_shift: ms_to_samples(sample_rate, 10.0),
+            snip_edges: true,
+            dither: 0.0,
+            remove_dc_offset: true,
+            preemphasis: 0.97,
+            window: FbankWindow::Povey,
+            round_to_power_of_two: true,
+            use_power: true,
+            num_mel_bins: 23,
+            low_freq: 20.0,
+            high_freq: 0.0,
+            log_floor: f32::EPSILON,
+        }
+    }
+
+    /// Common HTK FBANK configuration (25 ms hamming frames every 10 ms, magnitude spectrum,
+    /// 20 filters over the full band, energies floored at 1 before the log)
+    pub fn htk(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            frame_length: ms_to_samples(sample_rate, 25.0),
+            frame_shift: ms_to_samples(sample_rate, 10.0),
+            snip_edges: true,
+            dither: 0.0,
+            remove_dc_offset: false,
+            preemphasis: 0.97,
+            window: FbankWindow::Hamming,
+            round_to_power_of_two: true,
+            use_power: false,
+            num_mel_bins: 20,
+            low_freq: 0.0,
+            high_freq: 0.0,
+            log_floor: 1.0,
+        }
+    }
+
+    /// Options of a filter bank preset (None for the other presets).
+    /// The number of mel bands, the upper frequency and the dither of `params` override the
+    /// preset; frame sizes and the lower frequency always follow the preset.
+    pub fn from_params(params: &SpectrogramParams, sample_rate: u32) -> Option<Self> {
+        let mut options = match params.compat {
+            Compat::Librosa | Compat::Torchaudio => return None,
+            Compat::Kaldi => Self::kaldi(sample_rate),
+            Compat::Htk => Self::htk(sample_rate),
+        };
+        if let Some(n_mels) = params.n_mels {
+            options.num_mel_bins = n_mels;
+        }
+        if let Some(f_max) = params.f_max {
+            options.high_freq = f_max;
+        }
+        options.dither = params.dither;
+        Some(options)
+    }
+
+    /// Number of samples each frame is padded to before the FFT
+    pub fn padded_frame_length(&self) -> usize {
+        if self.round_to_power_of_two {
+            self.frame_length.next_power_of_two()
+        } else {
+            self.frame_length
+        }
+    }
+
+    /// Upper edge of the filter bank in Hz
+    pub fn high_freq_hz(&self) -> f32 {
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if self.high_freq <= 0.0 {
+            nyquist + self.high_freq
+        } else {
+            self.high_freq
+        }
+    }
+
+    /// Check that the options describe a valid filter bank
+    pub fn validate(&self) -> Result<()> {
+        if self.frame_length == 0 || self.frame_shift == 0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Frame length and shift must be at least one sample at {} Hz",
+                self.sample_rate
+            )));
+        }
+        if self.num_mel_bins < 3 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "At least 3 mel bins are required (got {})",
+                self.num_mel_bins
+            )));
+        }
+        let nyquist = self.sample_rate as f32 / 2.0;
+        let high_freq = self.high_freq_hz();
+        if self.low_freq < 0.0 || high_freq > nyquist || self.low_freq >= high_freq {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid filter bank range {} Hz - {} Hz for sample rate {} Hz",
+                self.low_freq, high_freq, self.sample_rate
+            )));
+        }
+        if self.dither < 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "dither ({}) must be non-negative",
+                self.dither
+            )));
+        }
+        if self.log_floor <= 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "log floor ({}) must be positive",
+                self.log_floor
+            )));
+        }
+        Ok(())
+    }
+
+    /// Number of frames produced for a signal of `n_samples` samples
+    pub fn num_frames(&self, n_samples: usize) -> usize {
+        if self.snip_edges {
+            if n_samples < self.frame_length {
+                0
+            } else {
+                1 + (n_samples - self.frame_length) / self.frame_shift
+            }
+        } else {
+            (n_samples + self.frame_shift / 2) / self.frame_shift
+        }
+    }
+}
+
+/// Convert a duration in milliseconds to samples, truncating like Kaldi
+fn ms_to_samples(sample_rate: u32, ms: f32) -> usize {
+    (sample_rate as f32 * 0.001 * ms) as usize
+}
+
+/// HTK mel scale as written by Kaldi (natural logarithm)
+fn mel_scale(freq: f32) -> f32 {
+    1127.0 * (1.0 + freq / 700.0).ln()
+}
+
+/// Triangular filters in [mel][fft bin] layout, with `padded_frame_length / 2 + 1` columns.
+/// The filters are triangles on the mel axis (not on the frequency axis as in librosa), are
+/// not normalized and never cover the Nyquist bin, as in Kaldi.
+pub fn fbank_mel_banks(options: &FbankOptions) -> Vec<Vec<f32>> {
+    let padded = options.padded_frame_length();
+    let fft_bin_width = options.sample_rate as f32 / padded as f32;
+
+    let mel_low = mel_scale(options.low_freq);
+    let mel_high = mel_scale(options.high_freq_hz());
+    let mel_delta = (mel_high - mel_low) / (options.num_mel_bins + 1) as f32;
+
+    (0..options.num_mel_bins)
+        .map(|bin| {
+            let left = mel_low + bin as f32 * mel_delta;
+            let center = mel_low + (bin + 1) as f32 * mel_delta;
+            let right = mel_low + (bin + 2) as f32 * mel_delta;
+
+            let mut weights = vec![0.0f32; padded / 2 + 1];
+            for (i, weight) in weights.iter_mut().take(padded / 2).enumerate() {
+                let mel = mel_scale(fft_bin_width * i as f32);
+                if mel > left && mel < right {
+                    *weight = if mel <= center {
+                        (mel - left) / (center - left)
+                    } else {
+                        (right - mel) / (right - center)
+                    };
+                }
+            }
+            weights
+        })
+        .collect()
+}
+
+/// Everything needed to turn a frame index into its log filter bank energies
+struct FbankComputer<'a> {
+    audio: &'a [f32],
+    options: FbankOptions,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    mel_banks: Vec<Vec<f32>>,
+}
+
+impl<'a> FbankComputer<'a> {
+    fn new(audio: &'a [f32], options: &FbankOptions) -> Result<Self> {
+        options.validate()?;
+        let mut planner = FftPlanner::<f32>::new();
+        Ok(Self {
+            audio,
+            options: *options,
+            fft: planner.plan_fft_forward(options.padded_frame_length()),
+            window: options.window.coefficients(options.frame_length),
+            mel_banks: fbank_mel_banks(options),
+        })
+    }
+
+    /// Log mel energies of one frame
+    fn frame(&self, frame_idx: usize) -> Vec<f32> {
+        let options = &self.options;
+        let n = self.audio.len() as i64;
+
+        // Extract the frame, reflecting the signal at the edges if frames are not snipped
+        let start = if options.snip_edges {
+            (frame_idx * options.frame_shift) as i64
+        } else {
+            (frame_idx * options.frame_shift + options.frame_shift / 2) as i64
+                - (options.frame_length / 2) as i64
+        };
+        let mut frame: Vec<f32> = (0..options.frame_length as i64)
+            .map(|i| {
+                let mut s = start + i;
+                while s < 0 || s >= n {
+                    s = if s < 0 { -s - 1 } else { 2 * n - 1 - s };
+                }
+                self.audio[s as usize]
+            })
+            .collect();
+
+        // Dither with reproducible noise (seeded by the frame index)
+        if options.dither > 0.0 {
+            let mut noise = GaussianNoise::new(frame_idx as u64);
+            for s in frame.iter_mut() {
+                *s += options.dither * noise.sample();
+            }
+        }
+
+        if options.remove_dc_offset {
+            let mean = frame.iter().sum::<f32>() / frame.len() as f32;
+            for s in frame.iter_mut() {
+                *s -= mean;
+            }
+        }
+
+        // Pre-emphasis (the first sample is emphasized against itself)
+        if options.preemphasis != 0.0 {
+            for i in (1..frame.len()).rev() {
+                frame[i] -= options.preemphasis * frame[i - 1];
+            }
+            frame[0] -= options.preemphasis * frame[0];
+        }
+
+        // Window, zero-pad and transform
+        let mut buffer = vec![Complex::<f32>::new(0.0, 0.0); options.padded_frame_length()];
+        for (dst, (&s, &w)) in buffer.iter_mut().zip(frame.iter().zip(&self.window)) {
+            dst.re = s * w;
+        }
+        self.fft.process(&mut buffer);
+
+        let spectrum: Vec<f32> = buffer
+            .iter()
+            .take(buffer.len() / 2 + 1)
+            .map(|c| {
+                if options.use_power {
+                    c.norm_sqr()
+                } else {
+                    c.norm()
+                }
+            })
+            .collect();
+
+        // Filter bank energies, floored and log-compressed
+        self.mel_banks
+            .iter()
+            .map(|weights| {
+                let energy: f32 = weights.iter().zip(&spectrum).map(|(w, s)| w * s).sum();
+                energy.max(options.log_floor).ln()
+            })
+            .collect()
+    }
+
+    /// Transpose frame-major rows into [mel][time]
+    fn to_mel_major(&self, frames: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        let mut fbank = vec![vec![0.0f32; frames.len()]; self.options.num_mel_bins];
+        for (t, frame) in frames.into_iter().enumerate() {
+            for (row, v) in fbank.iter_mut().zip(frame) {
+                row[t] = v;
+            }
+        }
+        fbank
+    }
+}
+
+/// Compute log mel filter bank features (single-threaded).
+/// Returns the features in [mel][time] format; audio shorter than one frame yields no frames
+/// when edges are snipped, as in Kaldi.
+pub fn compute_fbank(audio: &[f32], options: &FbankOptions) -> Result<Vec<Vec<f32>>> {
+    let computer = FbankComputer::new(audio, options)?;
+    let n_frames = if audio.is_empty() {
+        0
+    } else {
+        options.num_frames(audio.len())
+    };
+    let frames = (0..n_frames).map(|i| computer.frame(i)).collect();
+    Ok(computer.to_mel_major(frames))
+}
+
+/// Compute log mel filter bank features (parallelized over frames with rayon)
+#[cfg(feature = "parallel")]
+pub fn par_compute_fbank(audio: &[f32], options: &FbankOptions) -> Result<Vec<Vec<f32>>> {
+    let computer = FbankComputer::new(audio, options)?;
+    let n_frames = if audio.is_empty() {
+        0
+    } else {
+        options.num_frames(audio.len())
+    };
+    let frames = (0..n_frames)
+        .into_par_iter()
+        .map(|i| computer.frame(i))
+        .collect();
+    Ok(computer.to_mel_major(frames))
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the features are computed sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_compute_fbank(audio: &[f32], options: &FbankOptions) -> Result<Vec<Vec<f32>>> {
+    compute_fbank(audio, options)
+}
+
+/// Small deterministic Gaussian generator (xorshift + Box-Muller) for dithering
+pub(crate) struct GaussianNoise {
+    state: u64,
+}
+
+impl GaussianNoise {
+    pub(crate) fn new(seed: u64) -> Self {
+        // Avoid the all-zero state of xorshift
+        Self {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub(crate) fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        // Uniform in (0, 1]
+        ((self.state >> 40) as f32 + 1.0) / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn sample(&mut self) -> f32 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+}
@@ -0,0 +1,404 @@
+// FFT backends of the STFT. RustFFT is always available; FFTW (`fftw` feature) and Apple's
+// Accelerate framework (`accelerate` feature, macOS only) are often 2-3x faster on the
+// platforms they are tuned for. A backend plans forward transforms of a given length, which
+// the STFT then applies to blocks of frames laid out one after the other in a buffer.
+// Backends give the same values up to floating-point rounding.
+
+use crate::error::{Result, SpectrsError};
+/// Complex values transformed by the backends
+pub use rustfft::num_complex::Complex;
+use std::sync::Arc;
+
+/// Library computing the FFTs of the STFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum FftBackendType {
+    /// RustFFT, in pure Rust
+    #[default]
+    #[cfg_attr(feature = "cli", value(name = "rustfft"))]
+    RustFft,
+    /// FFTW (requires the `fftw` feature)
+    Fftw,
+    /// vDSP of Apple's Accelerate framework (requires the `accelerate` feature, macOS only)
+    Accelerate,
+}
+
+impl FftBackendType {
+    /// The backend, or an error if it is not compiled in
+    pub fn backend(self) -> Result<Arc<dyn FftBackend>> {
+        match self {
+            FftBackendType::RustFft => Ok(Arc::new(RustFftBackend)),
+            #[cfg(feature = "fftw")]
+            FftBackendType::Fftw => Ok(Arc::new(fftw_backend::FftwBackend)),
+            #[cfg(not(feature = "fftw"))]
+            FftBackendType::Fftw => Err(SpectrsError::UnsupportedFormat(
+                "The FFTW backend requires the `fftw` feature (compile with --features fftw)"
+                    .to_string(),
+            )),
+            #[cfg(all(feature = "accelerate", target_os = "macos"))]
+            FftBackendType::Accelerate => Ok(Arc::new(accelerate_backend::AccelerateBackend)),
+            #[cfg(not(all(feature = "accelerate", target_os = "macos")))]
+            FftBackendType::Accelerate => Err(SpectrsError::UnsupportedFormat(
+                "The Accelerate backend requires macOS and the `accelerate` feature (compile \
+                 with --features accelerate)"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Forward FFT of a fixed length, planned by an `FftBackend`
+pub trait ForwardFft<T>: Send + Sync {
+    /// Number of points of the transform
+    fn len(&self) -> usize;
+
+    /// Whether the transform has no points
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Transform in place every chunk of `len()` values of the buffer (whose length is a
+    /// multiple of `len()`), without normalization
+    fn process(&self, buffer: &mut [Complex<T>]);
+}
+
+/// Library planning the forward FFTs of the STFT, in single and double precision. Implement it
+/// to compute the spectrograms with another FFT (see `compute_spectrogram_with_fft`).
+pub trait FftBackend: Send + Sync {
+    /// Name of the backend, e.g. for logs
+    fn name(&self) -> &'static str;
+
+    /// Plan a single-precision forward FFT of `n` points
+    fn plan_f32(&self, n: usize) -> Result<Arc<dyn ForwardFft<f32>>>;
+
+    /// Plan a double-precision forward FFT of `n` points
+    fn plan_f64(&self, n: usize) -> Result<Arc<dyn ForwardFft<f64>>>;
+}
+
+/// RustFFT, the default backend
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustFftBackend;
+
+impl RustFftBackend {
+    fn plan<T: rustfft::FftNum>(n: usize) -> Arc<dyn ForwardFft<T>> {
+        Arc::new(RustFft(rustfft::FftPlanner::<T>::new().plan_fft_forward(n)))
+    }
+}
+
+impl FftBackend for RustFftBackend {
+    fn name(&self) -> &'static str {
+        "rustfft"
+    }
+
+    fn plan_f32(&self, n: usize) -> Result<Arc<dyn ForwardFft<f32>>> {
+        Ok(Self::plan(n))
+    }
+
+    fn plan_f64(&self, n: usize) -> Result<Arc<dyn ForwardFft<f64>>> {
+        Ok(Self::plan(n))
+    }
+}
+
+/// Transform planned by RustFFT, which processes consecutive chunks in a single call
+struct RustFft<T>(Arc<dyn rustfft::Fft<T>>);
+
+impl<T: rustfft::FftNum> ForwardFft<T> for RustFft<T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn process(&self, buffer: &mut [Complex<T>]) {
+        if !buffer.is_empty() {
+            self.0.process(buffer);
+        }
+    }
+}
+
+#[cfg(feature = "fftw")]
+mod fftw_backend {
+    use super::{FftBackend, ForwardFft};
+    use crate::error::{Result, SpectrsError};
+    use fftw::array::AlignedVec;
+    use fftw::plan::{C2CPlan, C2CPlan32, C2CPlan64};
+    use fftw::types::{Flag, Sign};
+    use rustfft::num_complex::Complex;
+    use std::sync::{Arc, Mutex};
+
+    /// FFTW, with plans estimated rather than measured so that planning is quick
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct FftwBackend;
+
+    impl FftBackend for FftwBackend {
+        fn name(&self) -> &'static str {
+            "fftw"
+        }
+
+        fn plan_f32(&self, n: usize) -> Result<Arc<dyn ForwardFft<f32>>> {
+            Ok(Arc::new(FftwFft::<C2CPlan32>::new(n)?))
+        }
+
+        fn plan_f64(&self, n: usize) -> Result<Arc<dyn ForwardFft<f64>>> {
+            Ok(Arc::new(FftwFft::<C2CPlan64>::new(n)?))
+        }
+    }
+
+    /// Plan with the aligned buffers it was planned for
+    struct Worker<P: C2CPlan> {
+        plan: P,
+        input: AlignedVec<P::Complex>,
+        output: AlignedVec<P::Complex>,
+    }
+
+    impl<P: C2CPlan> Worker<P> {
+        fn new(n: usize) -> Result<Self> {
+            let plan = P::aligned(&[n], Sign::Forward, Flag::ESTIMATE).map_err(|e| {
+                SpectrsError::InvalidParams(format!(
+                    "Failed to plan an FFTW FFT of {} points: {}",
+                    n, e
+                ))
+            })?;
+            Ok(Self {
+                plan,
+                input: AlignedVec::new(n),
+                output: AlignedVec::new(n),
+            })
+        }
+    }
+
+    /// FFTW plans cannot run on two threads at once: every thread takes an idle plan from the
+    /// pool, or plans a new one
+    struct FftwFft<P: C2CPlan> {
+        n: usize,
+        idle: Mutex<Vec<Worker<P>>>,
+    }
+
+    impl<P: C2CPlan> FftwFft<P> {
+        fn new(n: usize) -> Result<Self> {
+            let worker = Worker::new(n)?;
+            Ok(Self {
+                n,
+                idle: Mutex::new(vec![worker]),
+            })
+        }
+    }
+
+    impl<T, P> ForwardFft<T> for FftwFft<P>
+    where
+        T: Copy + Send,
+        P: C2CPlan<Complex = Complex<T>> + Send,
+    {
+        fn len(&self) -> usize {
+            self.n
+        }
+
+        fn process(&self, buffer: &mut [Complex<T>]) {
+            let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            let mut worker = match idle {
+                Some(worker) => worker,
+                None => Worker::new(self.n).expect("FFTW plan of a size planned before"),
+            };
+            for chunk in buffer.chunks_exact_mut(self.n) {
+                worker.input.copy_from_slice(chunk);
+                let Worker {
+                    plan,
+                    input,
+                    output,
+                } = &mut worker;
+                plan.c2c(input, output)
+                    .expect("aligned buffers of the planned size");
+                chunk.copy_from_slice(output);
+            }
+            self.idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(worker);
+        }
+    }
+}
+
+#[cfg(all(feature = "accelerate", target_os = "macos"))]
+mod accelerate_backend {
+    use super::{FftBackend, ForwardFft};
+    use crate::error::{Result, SpectrsError};
+    use rustfft::num_complex::Complex;
+    use std::ffi::c_void;
+    use std::sync::Arc;
+
+    /// vDSP_DFT_FORWARD
+    const FORWARD: i32 = 1;
+
+    #[link(name = "Accelerate", kind = "framework")]
+    unsafe extern "C" {
+        fn vDSP_DFT_zop_CreateSetup(
+            previous: *mut c_void,
+            length: usize,
+            direction: i32,
+        ) -> *mut c_void;
+        fn vDSP_DFT_zop_CreateSetupD(
+            previous: *mut c_void,
+            length: usize,
+            direction: i32,
+        ) -> *mut c_void;
+        fn vDSP_DFT_Execute(
+            setup: *const c_void,
+            ir: *const f32,
+            ii: *const f32,
+            or: *mut f32,
+            oi: *mut f32,
+        );
+        fn vDSP_DFT_ExecuteD(
+            setup: *const c_void,
+            ir: *const f64,
+            ii: *const f64,
+            or: *mut f64,
+            oi: *mut f64,
+        );
+        fn vDSP_DFT_DestroySetup(setup: *mut c_void);
+        fn vDSP_DFT_DestroySetupD(setup: *mut c_void);
+    }
+
+    /// vDSP functions of one precision
+    trait VdspFloat: Copy + Default + Send + Sync + 'static {
+        unsafe fn create_setup(length: usize) -> *mut c_void;
+        unsafe fn execute(
+            setup: *const c_void,
+            ir: &[Self],
+            ii: &[Self],
+            or: &mut [Self],
+            oi: &mut [Self],
+        );
+        unsafe fn destroy_setup(setup: *mut c_void);
+    }
+
+    impl VdspFloat for f32 {
+        unsafe fn create_setup(length: usize) -> *mut c_void {
+            unsafe { vDSP_DFT_zop_CreateSetup(std::ptr::null_mut(), length, FORWARD) }
+        }
+        unsafe fn execute(
+            setup: *const c_void,
+            ir: &[f32],
+            ii: &[f32],
+            or: &mut [f32],
+            oi: &mut [f32],
+        ) {
+            unsafe {
+                vDSP_DFT_Execute(
+                    setup,
+                    ir.as_ptr(),
+                    ii.as_ptr(),
+                    or.as_mut_ptr(),
+                    oi.as_mut_ptr(),
+                )
+            }
+        }
+        unsafe fn destroy_setup(setup: *mut c_void) {
+            unsafe { vDSP_DFT_DestroySetup(setup) }
+        }
+    }
+
+    impl VdspFloat for f64 {
+        unsafe fn create_setup(length: usize) -> *mut c_void {
+            unsafe { vDSP_DFT_zop_CreateSetupD(std::ptr::null_mut(), length, FORWARD) }
+        }
+        unsafe fn execute(
+            setup: *const c_void,
+            ir: &[f64],
+            ii: &[f64],
+            or: &mut [f64],
+            oi: &mut [f64],
+        ) {
+            unsafe {
+                vDSP_DFT_ExecuteD(
+                    setup,
+                    ir.as_ptr(),
+                    ii.as_ptr(),
+                    or.as_mut_ptr(),
+                    oi.as_mut_ptr(),
+                )
+            }
+        }
+        unsafe fn destroy_setup(setup: *mut c_void) {
+            unsafe { vDSP_DFT_DestroySetupD(setup) }
+        }
+    }
+
+    /// vDSP's complex DFT, for lengths f * 2^n with f in {1, 3, 5, 15} and n >= 3
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct AccelerateBackend;
+
+    impl AccelerateBackend {
+        fn plan<T: VdspFloat>(n: usize) -> Result<Arc<dyn ForwardFft<T>>> {
+            // Safety: a null previous setup creates an independent one
+            let setup = unsafe { T::create_setup(n) };
+            if setup.is_null() {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The Accelerate backend does not support FFTs of {} points (only f * 2^n \
+                     points with f = 1, 3, 5 or 15 and n >= 3)",
+                    n
+                )));
+            }
+            Ok(Arc::new(VdspFft::<T> {
+                setup,
+                n,
+                phantom: std::marker::PhantomData,
+            }))
+        }
+    }
+
+    impl FftBackend for AccelerateBackend {
+        fn name(&self) -> &'static str {
+            "accelerate"
+        }
+
+        fn plan_f32(&self, n: usize) -> Result<Arc<dyn ForwardFft<f32>>> {
+            Self::plan(n)
+        }
+
+        fn plan_f64(&self, n: usize) -> Result<Arc<dyn ForwardFft<f64>>> {
+            Self::plan(n)
+        }
+    }
+
+    struct VdspFft<T: VdspFloat> {
+        setup: *mut c_void,
+        n: usize,
+        phantom: std::marker::PhantomData<T>,
+    }
+
+    // Safety: the setup is read-only once created and vDSP_DFT_Execute may be called from
+    // several threads with the same setup
+    unsafe impl<T: VdspFloat> Send for VdspFft<T> {}
+    unsafe impl<T: VdspFloat> Sync for VdspFft<T> {}
+
+    impl<T: VdspFloat> Drop for VdspFft<T> {
+        fn drop(&mut self) {
+            // Safety: the setup was created by create_setup of the same precision
+            unsafe { T::destroy_setup(self.setup) }
+        }
+    }
+
+    impl<T: VdspFloat> ForwardFft<T> for VdspFft<T> {
+        fn len(&self) -> usize {
+            self.n
+        }
+
+        fn process(&self, buffer: &mut [Complex<T>]) {
+            // vDSP works on split complex arrays
+            let mut split = vec![T::default(); 4 * self.n];
+            let (input, output) = split.split_at_mut(2 * self.n);
+            let (ir, ii) = input.split_at_mut(self.n);
+            let (or, oi) = output.split_at_mut(self.n);
+            for chunk in buffer.chunks_exact_mut(self.n) {
+                for (c, (re, im)) in chunk.iter().zip(ir.iter_mut().zip(ii.iter_mut())) {
+                    (*re, *im) = (c.re, c.im);
+                }
+                // Safety: the four arrays hold the n values of the setup
+                unsafe { T::execute(self.setup, ir, ii, or, oi) };
+                for (c, (&re, &im)) in chunk.iter_mut().zip(or.iter().zip(oi.iter())) {
+                    *c = Complex::new(re, im);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Choice of the filter bank a linear spectrogram is projected on: triangular filters spaced on
+// a frequency scale (the mel filter bank of librosa, or the Bark and ERB-rate scales of
+// psychoacoustics) or an auditory gammatone filter bank. All are built in [band][freq] layout,
+// so they apply with `apply_filter_bank` and anywhere else the mel filters do (GPU, live
+// capture). The mel scale goes through `mel_filter_bank` unchanged, keeping librosa parity.
+
+use crate::features::spectral::fft_frequencies;
+use crate::spectrogram::gammatone::{
+    erb_rate_to_hz, gammatone_band_frequencies, gammatone_filter_bank, hz_to_erb_rate,
+};
+use crate::spectrogram::mel::{
+    MelScale, hz_to_mel, mel_band_frequencies, mel_filter_bank, mel_to_hz, triangular_filter_bank,
+};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::stft::{Float, constant};
+
+/// Filter bank a spectrogram is projected on when a number of bands is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum FilterBankType {
+    /// Triangular filters (Slaney-normalized, as librosa) spaced on the `FrequencyScale`, the
+    /// mel scale by default
+    #[default]
+    Mel,
+    /// 4th-order gammatone filters spaced on the ERB-rate scale
+    Gammatone,
+}
+
+/// Frequency scale the triangular filters are spaced on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum FrequencyScale {
+    /// Mel scale, in the definition of `MelScale`
+    #[default]
+    Mel,
+    /// Bark scale (Traunmüller, 1990)
+    Bark,
+    /// ERB-rate scale (Glasberg & Moore, 1990)
+    Erb,
+}
+
+impl FrequencyScale {
+    /// Position of `hz` on the scale (mel scales in the definition of `mel_scale`)
+    pub fn from_hz(self, hz: f64, mel_scale: MelScale) -> f64 {
+        match self {
+            FrequencyScale::Mel => hz_to_mel(hz, mel_scale),
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+            FrequencyScale::Erb => hz_to_erb_rate(hz),
+        }
+    }
+
+    /// Frequency (Hz) at a position of the scale (inverse of `from_hz`)
+    pub fn to_hz(self, value: f64, mel_scale: MelScale) -> f64 {
+        match self {
+            FrequencyScale::Mel => mel_to_hz(value, mel_scale),
+            FrequencyScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            FrequencyScale::Erb => erb_rate_to_hz(value),
+        }
+    }
+}
+
+/// Edges of the triangular filters of the bands described by `params`: n_mels + 2 frequencies
+/// (Hz) equally spaced on their scale from f_min to f_max
+fn band_edges(params: &SpectrogramParams, n_bands: usize, sr: u32) -> Vec<f64> {
+    let (scale, mel_scale) = (params.frequency_scale, params.mel_scale);
+    let low = scale.from_hz(params.f_min.unwrap_or(0.0) as f64, mel_scale);
+    let high = scale.from_hz(
+        params.f_max.map_or(sr as f64 / 2.0, |f| f as f64),
+        mel_scale,
+    );
+    let step = (high - low) / (n_bands + 1) as f64;
+    (0..n_bands + 2)
+        .map(|i| scale.to_hz(low + i as f64 * step, mel_scale))
+        .collect()
+}
+
+/// Filters of the bank described by `params` for audio at `sr`, in [band][freq] layout (None
+/// for a linear-frequency spectrogram)
+pub fn filter_bank<T: Float>(params: &SpectrogramParams, sr: u32) -> Option<Vec<Vec<T>>> {
+    let n_bands = params.n_mels?;
+    Some(match (params.filter_bank, params.frequency_scale) {
+        (FilterBankType::Mel, FrequencyScale::Mel) => mel_filter_bank(
+            sr,
+            params.n_fft,
+            n_bands,
+            params.f_min,
+            params.f_max,
+            params.mel_scale,
+            true,
+        ),
+        (FilterBankType::Mel, _) => {
+            let edges: Vec<T> = band_edges(params, n_bands, sr)
+                .into_iter()
+                .map(constant)
+                .collect();
+            triangular_filter_bank(sr, params.n_fft, &edges, true)
+        }
+        (FilterBankType::Gammatone, _) => {
+            gammatone_filter_bank(sr, params.n_fft, n_bands, params.f_min, params.f_max)
+        }
+    })
+}
+
+/// Centre frequency (Hz) of every row of the spectrogram described by `params` for audio at
+/// `sr`: the bands of its filter bank, or the FFT bins
+pub fn band_frequencies(params: &SpectrogramParams, sr: u32) -> Vec<f32> {
+    match (params.n_mels, params.filter_bank) {
+        (Some(n_bands), FilterBankType::Mel) => match params.frequency_scale {
+            FrequencyScale::Mel => {
+                mel_band_frequencies(sr, n_bands, params.f_min, params.f_max, params.mel_scale)
+            }
+            _ => band_edges(params, n_bands, sr)[1..=n_bands]
+                .iter()
+                .map(|&f| f as f32)
+                .collect(),
+        },
+        (Some(n_bands), FilterBankType::Gammatone) => {
+            gammatone_band_frequencies(sr, n_bands, params.f_min, params.f_max)
+        }
+        (None, _) => fft_frequencies(sr, params.n_fft),
+    }
+}
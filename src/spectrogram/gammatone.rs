@@ -0,0 +1,72 @@
+// Gammatone filter banks, the usual model of the auditory filters of the cochlea. Centre
+// frequencies are equally spaced on the ERB-rate scale of Glasberg & Moore (1990), and every
+// filter weighs the FFT bins of a power spectrogram with the power response of a 4th-order
+// gammatone filter whose bandwidth is 1.019 ERB (the approximation of Ellis's `fft2gammatonemx`).
+
+use crate::spectrogram::stft::{Float, constant};
+
+/// Order of the gammatone filters
+const ORDER: i32 = 4;
+
+/// Equivalent rectangular bandwidth (Hz) of the auditory filter centred at `hz`
+pub fn erb(hz: f64) -> f64 {
+    24.7 * (4.37 * hz / 1000.0 + 1.0)
+}
+
+/// Number of ERBs below `hz` (the ERB-rate scale)
+pub fn hz_to_erb_rate(hz: f64) -> f64 {
+    21.4 * (1.0 + 0.00437 * hz).log10()
+}
+
+/// Frequency (Hz) at a given ERB rate (inverse of `hz_to_erb_rate`)
+pub fn erb_rate_to_hz(erb_rate: f64) -> f64 {
+    (10f64.powf(erb_rate / 21.4) - 1.0) / 0.00437
+}
+
+/// Centre frequency (Hz) of every band of the gammatone filter bank built with the same
+/// arguments: `n_bands` points equally spaced in ERB rate strictly between `f_min` (default 0)
+/// and `f_max` (default Nyquist)
+pub fn gammatone_band_frequencies(
+    sr: u32,
+    n_bands: usize,
+    f_min: Option<f32>,
+    f_max: Option<f32>,
+) -> Vec<f32> {
+    center_frequencies(sr, n_bands, f_min, f_max)
+        .into_iter()
+        .map(|f| f as f32)
+        .collect()
+}
+
+fn center_frequencies(sr: u32, n_bands: usize, f_min: Option<f32>, f_max: Option<f32>) -> Vec<f64> {
+    let low = hz_to_erb_rate(f_min.unwrap_or(0.0) as f64);
+    let high = hz_to_erb_rate(f_max.map_or(sr as f64 / 2.0, |f| f as f64));
+    let step = (high - low) / (n_bands + 1) as f64;
+    (1..=n_bands)
+        .map(|i| erb_rate_to_hz(low + i as f64 * step))
+        .collect()
+}
+
+/// Create a gammatone filter bank in [band][freq] layout for a spectrogram of `n_fft` samples.
+/// Every filter has unit gain at its centre frequency.
+pub fn gammatone_filter_bank<T: Float>(
+    sr: u32,
+    n_fft: usize,
+    n_bands: usize,
+    f_min: Option<f32>,
+    f_max: Option<f32>,
+) -> Vec<Vec<T>> {
+    let bin_width = sr as f64 / n_fft as f64;
+    center_frequencies(sr, n_bands, f_min, f_max)
+        .into_iter()
+        .map(|center| {
+            let bandwidth = 1.019 * erb(center);
+            (0..n_fft / 2 + 1)
+                .map(|k| {
+                    let x = (k as f64 * bin_width - center) / bandwidth;
+                    constant((1.0 + x * x).powi(-ORDER))
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,626 @@
+//! STFT and mel projection on the GPU (enabled with the `gpu` feature).
+//!
+//! Frames are processed in batches: each batch is windowed, transformed with a radix-2
+//! Stockham FFT and projected on the mel filters by compute shaders, then read back once.
+//! Only power-of-two FFT sizes are supported.
+
+use crate::error::{Result, SpectrsError};
+#[cfg(feature = "gpu")]
+use crate::spectrogram::filterbank::filter_bank;
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::stft::SpectrogramType;
+#[cfg(feature = "gpu")]
+use crate::spectrogram::stft::create_hann_window;
+#[cfg(feature = "gpu")]
+use crate::spectrogram::weighting::{apply_weighting, weight_filter_bank};
+
+/// Device the spectrograms are computed on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Device {
+    /// rustfft on the CPU (parallelized with rayon)
+    #[default]
+    Cpu,
+    /// wgpu compute shaders (Vulkan, Metal, DX12 or OpenGL)
+    Gpu,
+}
+
+/// Upper bound on the size of the complex FFT buffers of a batch
+#[cfg(feature = "gpu")]
+const MAX_BATCH_BYTES: u64 = 64 << 20;
+
+#[cfg(feature = "gpu")]
+const WORKGROUP_SIZE: u32 = 64;
+
+/// GPU context holding the device and the compiled kernels. Create it once and reuse it for
+/// every file: it can be shared between threads.
+#[cfg(feature = "gpu")]
+pub struct GpuStft {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    adapter_name: String,
+    limits: wgpu::Limits,
+    frame_audio: wgpu::ComputePipeline,
+    butterfly: wgpu::ComputePipeline,
+    power_spectrum: wgpu::ComputePipeline,
+    mel_project: wgpu::ComputePipeline,
+}
+
+/// Uniforms shared by the kernels (see `Params` in gpu.wgsl)
+#[cfg(feature = "gpu")]
+#[derive(Debug, Clone, Copy, Default)]
+struct KernelParams {
+    n_fft: u32,
+    hop_length: u32,
+    win_length: u32,
+    offset: u32,
+    n_samples: u32,
+    n_frames: u32,
+    n_bins: u32,
+    n_mels: u32,
+    stage: u32,
+    power: u32,
+}
+
+#[cfg(feature = "gpu")]
+impl KernelParams {
+    fn to_words(self) -> [u32; 12] {
+        [
+            self.n_fft,
+            self.hop_length,
+            self.win_length,
+            self.offset,
+            self.n_samples,
+            self.n_frames,
+            self.n_bins,
+            self.n_mels,
+            self.stage,
+            self.power,
+            0,
+            0,
+        ]
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl GpuStft {
+    /// Open the default high-performance adapter and compile the kernels
+    pub fn new() -> Result<Self> {
+        pollster::block_on(Self::new_async())
+    }
+
+    async fn new_async() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .map_err(|e| SpectrsError::gpu("No GPU adapter found", e))?;
+        let adapter_name = adapter.get_info().name;
+        let limits = adapter.limits();
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("spectrs"),
+                required_features: wgpu::Features::empty(),
+                required_limits: limits.clone(),
+                memory_hints: wgpu::MemoryHints::Performance,
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .map_err(|e| {
+                SpectrsError::gpu(format!("Failed to open GPU device {}", adapter_name), e)
+            })?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("spectrs kernels"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Self {
+            frame_audio: pipeline("frame_audio"),
+            butterfly: pipeline("butterfly"),
+            power_spectrum: pipeline("power_spectrum"),
+            mel_project: pipeline("mel_project"),
+            device,
+            queue,
+            adapter_name,
+            limits,
+        })
+    }
+
+    /// Name of the adapter the kernels run on
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// GPU equivalent of `compute_spectrogram`, in [freq][time] layout
+    pub fn compute_spectrogram(
+        &self,
+        audio: &[f32],
+        n_fft: usize,
+        hop_length: usize,
+        win_length: usize,
+        center: bool,
+        spectrogram_type: SpectrogramType,
+    ) -> Result<Vec<Vec<f32>>> {
+        self.run(
+            audio,
+            n_fft,
+            hop_length,
+            &create_hann_window(win_length, false),
+            center,
+            spectrogram_type,
+            None,
+        )
+    }
+
+    /// GPU equivalent of `compute_spectrogram_with_window`, in [freq][time] layout
+    pub fn compute_spectrogram_with_window(
+        &self,
+        audio: &[f32],
+        n_fft: usize,
+        hop_length: usize,
+        window: &[f32],
+        center: bool,
+        spectrogram_type: SpectrogramType,
+    ) -> Result<Vec<Vec<f32>>> {
+        self.run(
+            audio,
+            n_fft,
+            hop_length,
+            window,
+            center,
+            spectrogram_type,
+            None,
+        )
+    }
+
+    /// Spectrogram described by `params` (weighted, and mel-scaled if `n_mels` is set, with the
+    /// same filters as `convert_to_mel`), in [freq][time] layout.
+    /// Deterministic parameters are rejected: the results depend on the GPU and its driver.
+    pub fn spectrogram(
+        &self,
+        audio: &[f32],
+        sr: u32,
+        params: &SpectrogramParams,
+    ) -> Result<Vec<Vec<f32>>> {
+        if params.deterministic {
+            return Err(SpectrsError::InvalidParams(
+                "Deterministic computation is not available on the GPU".to_string(),
+            ));
+        }
+        if params.lpc_order.is_some() {
+            return Err(SpectrsError::InvalidParams(
+                "The LPC envelope is not available on the GPU".to_string(),
+            ));
+        }
+        let gains: Option<Vec<f32>> =
+            params
+                .weighting
+                .bin_gains(params.n_fft, sr, params.spectrogram_type);
+        let mut filters: Option<Vec<Vec<f32>>> = filter_bank(params, sr);
+        if let (Some(filters), Some(gains)) = (&mut filters, &gains) {
+            weight_filter_bank(filters, gains);
+        }
+        let mut spec = self.run(
+            audio,
+            params.n_fft,
+            params.hop_length,
+            &params.window(),
+            params.center,
+            params.spectrogram_type,
+            filters.as_deref(),
+        )?;
+        if filters.is_none()
+            && let Some(gains) = &gains
+        {
+            apply_weighting(&mut spec, gains);
+        }
+        Ok(spec)
+    }
+
+    /// Number of frames processed per batch, bounded by the buffer and dispatch limits
+    fn batch_frames(&self, n_fft: usize) -> Result<usize> {
+        let frame_bytes = n_fft as u64 * 8;
+        let max_bytes = MAX_BATCH_BYTES
+            .min(self.limits.max_storage_buffer_binding_size as u64)
+            .min(self.limits.max_buffer_size);
+        let max_invocations =
+            self.limits.max_compute_workgroups_per_dimension as u64 * WORKGROUP_SIZE as u64;
+        let frames = (max_bytes / frame_bytes).min(max_invocations / n_fft as u64);
+        if frames == 0 {
+            return Err(SpectrsError::Gpu {
+                message: format!(
+                    "n_fft ({}) is too large for the limits of {}",
+                    n_fft, self.adapter_name
+                ),
+                source: None,
+            });
+        }
+        Ok(frames as usize)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run(
+        &self,
+        audio: &[f32],
+        n_fft: usize,
+        hop_length: usize,
+        window: &[f32],
+        center: bool,
+        spectrogram_type: SpectrogramType,
+        filters: Option<&[Vec<f32>]>,
+    ) -> Result<Vec<Vec<f32>>> {
+        let win_length = window.len();
+        if !n_fft.is_power_of_two() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The GPU backend requires n_fft ({}) to be a power of two (e.g. {})",
+                n_fft,
+                n_fft.next_power_of_two()
+            )));
+        }
+        if win_length == 0 || win_length > n_fft || hop_length == 0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Invalid STFT layout: n_fft={}, win_length={}, hop_length={}",
+                n_fft, win_length, hop_length
+            )));
+        }
+
+        // Same framing as compute_spectrogram
+        let n_frames = (audio.len().saturating_sub(win_length)) / hop_length + 1;
+        let n_bins = n_fft / 2 + 1;
+        let n_mels = filters.map_or(0, <[Vec<f32>]>::len);
+        let n_rows = if filters.is_some() { n_mels } else { n_bins };
+        let n_stages = n_fft.trailing_zeros() as usize;
+        let batch = self.batch_frames(n_fft)?.min(n_frames);
+
+        // Buffers sized for a full batch, reused by every batch
+        let storage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+        let audio_len = (batch - 1) * hop_length + win_length;
+        let audio_buffer = self.buffer("audio", (audio_len * 4) as u64, storage);
+        let window_buffer = self.upload("window", window);
+        let twiddles: Vec<f32> = (0..(n_fft / 2).max(1))
+            .flat_map(|t| {
+                let angle = -2.0 * std::f64::consts::PI * t as f64 / n_fft as f64;
+                [angle.cos() as f32, angle.sin() as f32]
+            })
+            .collect();
+        let twiddle_buffer = self.upload("twiddles", &twiddles);
+        let fft_bytes = (batch * n_fft * 8) as u64;
+        let fft_a = self.buffer("fft a", fft_bytes, wgpu::BufferUsages::STORAGE);
+        let fft_b = self.buffer("fft b", fft_bytes, wgpu::BufferUsages::STORAGE);
+        let spectrum_buffer = self.buffer(
+            "spectrum",
+            (batch * n_bins * 4) as u64,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        );
+        let mel_buffers = filters.map(|filters| {
+            let flat: Vec<f32> = filters.concat();
+            (
+                self.upload("filters", &flat),
+                self.buffer(
+                    "mel",
+                    (batch * n_mels.max(1) * 4) as u64,
+                    wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                ),
+            )
+        });
+        let output_bytes = (batch * n_rows.max(1) * 4) as u64;
+        let staging = self.buffer(
+            "staging",
+            output_bytes,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        );
+
+        // One uniform buffer per FFT stage (they only differ by `stage`) plus one for the rest
+        let uniform = wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
+        let params_buffer = self.buffer("params", 48, uniform);
+        let stage_buffers: Vec<wgpu::Buffer> = (0..n_stages)
+            .map(|_| self.buffer("stage params", 48, uniform))
+            .collect();
+
+        // Bind groups: the FFT stages ping-pong between fft_a and fft_b
+        let frame_group = self.bind_group(
+            &self.frame_audio,
+            &[
+                (0, &params_buffer),
+                (1, &audio_buffer),
+                (2, &window_buffer),
+                (4, &fft_a),
+            ],
+        );
+        let stage_groups: Vec<wgpu::BindGroup> = stage_buffers
+            .iter()
+            .enumerate()
+            .map(|(stage, stage_buffer)| {
+                let (src, dst) = if stage.is_multiple_of(2) {
+                    (&fft_a, &fft_b)
+                } else {
+                    (&fft_b, &fft_a)
+                };
+                self.bind_group(
+                    &self.butterfly,
+                    &[(0, stage_buffer), (3, src), (4, dst), (5, &twiddle_buffer)],
+                )
+            })
+            .collect();
+        let fft_result = if n_stages.is_multiple_of(2) {
+            &fft_a
+        } else {
+            &fft_b
+        };
+        let spectrum_group = self.bind_group(
+            &self.power_spectrum,
+            &[(0, &params_buffer), (3, fft_result), (6, &spectrum_buffer)],
+        );
+        let mel_group = mel_buffers.as_ref().map(|(filter_buffer, mel_buffer)| {
+            self.bind_group(
+                &self.mel_project,
+                &[
+                    (0, &params_buffer),
+                    (6, &spectrum_buffer),
+                    (7, filter_buffer),
+                    (8, mel_buffer),
+                ],
+            )
+        });
+        let output_buffer = mel_buffers
+            .as_ref()
+            .map_or(&spectrum_buffer, |(_, mel_buffer)| mel_buffer);
+
+        let mut spectrogram = vec![vec![0.0f32; n_frames]; n_rows];
+        for first_frame in (0..n_frames).step_by(batch) {
+            let batch_frames = batch.min(n_frames - first_frame);
+
+            // Upload only the samples covered by the frames of this batch
+            let start = (first_frame * hop_length).min(audio.len());
+            let end = ((first_frame + batch_frames - 1) * hop_length + win_length).min(audio.len());
+            if end > start {
+                self.queue
+                    .write_buffer(&audio_buffer, 0, bytemuck::cast_slice(&audio[start..end]));
+            }
+
+            let mut kernel_params = KernelParams {
+                n_fft: n_fft as u32,
+                hop_length: hop_length as u32,
+                win_length: win_length as u32,
+                offset: if center {
+                    ((n_fft - win_length) / 2) as u32
+                } else {
+                    0
+                },
+                n_samples: (end - start) as u32,
+                n_frames: batch_frames as u32,
+                n_bins: n_bins as u32,
+                n_mels: n_mels as u32,
+                stage: 1,
+                power: (spectrogram_type == SpectrogramType::Power) as u32,
+            };
+            self.queue.write_buffer(
+                &params_buffer,
+                0,
+                bytemuck::cast_slice(&kernel_params.to_words()),
+            );
+            for (stage, stage_buffer) in stage_buffers.iter().enumerate() {
+                kernel_params.stage = 1 << stage;
+                self.queue.write_buffer(
+                    stage_buffer,
+                    0,
+                    bytemuck::cast_slice(&kernel_params.to_words()),
+                );
+            }
+
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("stft"),
+                    timestamp_writes: None,
+                });
+                dispatch(
+                    &mut pass,
+                    &self.frame_audio,
+                    &frame_group,
+                    batch_frames * n_fft,
+                );
+                for stage_group in &stage_groups {
+                    dispatch(
+                        &mut pass,
+                        &self.butterfly,
+                        stage_group,
+                        batch_frames * n_fft / 2,
+                    );
+                }
+                dispatch(
+                    &mut pass,
+                    &self.power_spectrum,
+                    &spectrum_group,
+                    batch_frames * n_bins,
+                );
+                if let Some(mel_group) = &mel_group {
+                    dispatch(
+                        &mut pass,
+                        &self.mel_project,
+                        mel_group,
+                        batch_frames * n_mels,
+                    );
+                }
+            }
+            let batch_bytes = (batch_frames * n_rows * 4) as u64;
+            if batch_bytes > 0 {
+                encoder.copy_buffer_to_buffer(output_buffer, 0, &staging, 0, batch_bytes);
+            }
+            self.queue.submit(Some(encoder.finish()));
+            if batch_bytes == 0 {
+                continue;
+            }
+
+            // Read the [frame][row] batch back and scatter it into [row][frame]
+            let values = self.read_back(&staging, batch_bytes)?;
+            for (frame, frame_values) in values.chunks_exact(n_rows).enumerate() {
+                for (row, &value) in spectrogram.iter_mut().zip(frame_values) {
+                    row[first_frame + frame] = value;
+                }
+            }
+        }
+
+        Ok(spectrogram)
+    }
+
+    fn buffer(&self, label: &str, size: u64, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            // Empty bindings are not allowed
+            size: size.max(4),
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn upload(&self, label: &str, values: &[f32]) -> wgpu::Buffer {
+        let buffer = self.buffer(
+            label,
+            (values.len() * 4) as u64,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        if !values.is_empty() {
+            self.queue
+                .write_buffer(&buffer, 0, bytemuck::cast_slice(values));
+        }
+        buffer
+    }
+
+    fn bind_group(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        entries: &[(u32, &wgpu::Buffer)],
+    ) -> wgpu::BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = entries
+            .iter()
+            .map(|&(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        })
+    }
+
+    /// Wait for the submitted work and copy the first `size` bytes of `staging`
+    fn read_back(&self, staging: &wgpu::Buffer, size: u64) -> Result<Vec<f32>> {
+        let slice = staging.slice(..size);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| SpectrsError::gpu("GPU device lost", e))?;
+        receiver
+            .recv()
+            .map_err(|e| SpectrsError::gpu("GPU read-back was cancelled", e))?
+            .map_err(|e| SpectrsError::gpu("Failed to map GPU buffer", e))?;
+
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        staging.unmap();
+        Ok(values)
+    }
+}
+
+/// Dispatch one invocation per output value
+#[cfg(feature = "gpu")]
+fn dispatch(
+    pass: &mut wgpu::ComputePass,
+    pipeline: &wgpu::ComputePipeline,
+    bind_group: &wgpu::BindGroup,
+    invocations: usize,
+) {
+    if invocations == 0 {
+        return;
+    }
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.dispatch_workgroups((invocations as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+}
+
+#[cfg(not(feature = "gpu"))]
+pub struct GpuStft {
+    _private: (),
+}
+
+#[cfg(not(feature = "gpu"))]
+impl GpuStft {
+    pub fn new() -> Result<Self> {
+        Err(SpectrsError::Gpu {
+            message: "GPU feature not enabled. Compile with --features gpu to use this function."
+                .to_string(),
+            source: None,
+        })
+    }
+
+    pub fn adapter_name(&self) -> &str {
+        ""
+    }
+
+    pub fn compute_spectrogram(
+        &self,
+        _audio: &[f32],
+        _n_fft: usize,
+        _hop_length: usize,
+        _win_length: usize,
+        _center: bool,
+        _spectrogram_type: SpectrogramType,
+    ) -> Result<Vec<Vec<f32>>> {
+        Err(SpectrsError::Gpu {
+            message: "GPU feature not enabled. Compile with --features gpu to use this function."
+                .to_string(),
+            source: None,
+        })
+    }
+
+    pub fn compute_spectrogram_with_window(
+        &self,
+        _audio: &[f32],
+        _n_fft: usize,
+        _hop_length: usize,
+        _window: &[f32],
+        _center: bool,
+        _spectrogram_type: SpectrogramType,
+    ) -> Result<Vec<Vec<f32>>> {
+        Err(SpectrsError::Gpu {
+            message: "GPU feature not enabled. Compile with --features gpu to use this function."
+                .to_string(),
+            source: None,
+        })
+    }
+
+    pub fn spectrogram(
+        &self,
+        _audio: &[f32],
+        _sr: u32,
+        _params: &SpectrogramParams,
+    ) -> Result<Vec<Vec<f32>>> {
+        Err(SpectrsError::Gpu {
+            message: "GPU feature not enabled. Compile with --features gpu to use this function."
+                .to_string(),
+            source: None,
+        })
+    }
+}
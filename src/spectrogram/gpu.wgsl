@@ -0,0 +1,109 @@
+// Batched STFT and mel projection. Every kernel processes `n_frames` frames of one batch,
+// one invocation per output value. The FFT is a radix-2 Stockham transform (one dispatch per
+// stage, ping-ponging between two buffers) producing bins in natural order.
+
+struct Params {
+    n_fft: u32,
+    hop_length: u32,
+    win_length: u32,
+    // Position of the window inside the FFT frame (centering)
+    offset: u32,
+    // Samples uploaded for this batch
+    n_samples: u32,
+    n_frames: u32,
+    n_bins: u32,
+    n_mels: u32,
+    // Size of the sub-transforms merged by the current FFT stage
+    stage: u32,
+    // 1 for a power spectrogram, 0 for magnitude
+    power: u32,
+    _pad0: u32,
+    _pad1: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> audio: array<f32>;
+@group(0) @binding(2) var<storage, read> window: array<f32>;
+@group(0) @binding(3) var<storage, read> fft_in: array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> fft_out: array<vec2<f32>>;
+@group(0) @binding(5) var<storage, read> twiddles: array<vec2<f32>>;
+@group(0) @binding(6) var<storage, read_write> spectrum: array<f32>;
+@group(0) @binding(7) var<storage, read> filters: array<f32>;
+@group(0) @binding(8) var<storage, read_write> mel: array<f32>;
+
+// Window the audio of each frame into a zero-padded complex buffer
+@compute @workgroup_size(64)
+fn frame_audio(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= params.n_frames * params.n_fft) {
+        return;
+    }
+    let frame = i / params.n_fft;
+    let k = i % params.n_fft;
+
+    var value = 0.0;
+    if (k >= params.offset) {
+        let j = k - params.offset;
+        let sample = frame * params.hop_length + j;
+        if (j < params.win_length && sample < params.n_samples) {
+            value = audio[sample] * window[j];
+        }
+    }
+    fft_out[i] = vec2<f32>(value, 0.0);
+}
+
+// One radix-2 butterfly of the current Stockham stage
+@compute @workgroup_size(64)
+fn butterfly(@builtin(global_invocation_id) id: vec3<u32>) {
+    let half = params.n_fft / 2u;
+    let i = id.x;
+    if (i >= params.n_frames * half) {
+        return;
+    }
+    let base = (i / half) * params.n_fft;
+    let j = i % half;
+    let ns = params.stage;
+    let r = j % ns;
+
+    // exp(-2πi r / (2 ns)), looked up in the table of exp(-2πi t / n_fft)
+    let w = twiddles[r * (half / ns)];
+    let a = fft_in[base + j];
+    let b = fft_in[base + j + half];
+    let wb = vec2<f32>(b.x * w.x - b.y * w.y, b.x * w.y + b.y * w.x);
+
+    let out = base + (j / ns) * ns * 2u + r;
+    fft_out[out] = a + wb;
+    fft_out[out + ns] = a - wb;
+}
+
+// Magnitude or power of the positive frequencies, in [frame][bin] layout
+@compute @workgroup_size(64)
+fn power_spectrum(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= params.n_frames * params.n_bins) {
+        return;
+    }
+    let c = fft_in[(i / params.n_bins) * params.n_fft + i % params.n_bins];
+    var value = dot(c, c);
+    if (params.power == 0u) {
+        value = sqrt(value);
+    }
+    spectrum[i] = value;
+}
+
+// Mel projection: mel[frame][m] = sum_k filters[m][k] * spectrum[frame][k]
+@compute @workgroup_size(64)
+fn mel_project(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= params.n_frames * params.n_mels) {
+        return;
+    }
+    let frame = i / params.n_mels;
+    let m = i % params.n_mels;
+
+    var acc = 0.0;
+    for (var k = 0u; k < params.n_bins; k++) {
+        acc += filters[m * params.n_bins + k] * spectrum[frame * params.n_bins + k];
+    }
+    mel[i] = acc;
+}
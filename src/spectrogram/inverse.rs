@@ -0,0 +1,350 @@
+//! Inverse STFT and Griffin-Lim phase reconstruction, turning linear spectrograms back into
+//! audio, and the approximate inversion of filter banks turning mel spectrograms back into linear
+//! ones. Frames follow the layout of `stft::compute_spectrogram`: frame `t` windows samples
+//! `[t * hop_length, t * hop_length + win_length)`, placed in the middle of the FFT buffer when
+//! `center` is set.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::fbank::GaussianNoise;
+use crate::spectrogram::stft::create_hann_window;
+use rustfft::{FftPlanner, num_complex::Complex};
+use std::f32::consts::PI;
+
+/// Default number of Griffin-Lim iterations (as librosa)
+pub const DEFAULT_GRIFFIN_LIM_ITERATIONS: usize = 32;
+
+/// Momentum of the "fast" Griffin-Lim update (as librosa)
+const MOMENTUM: f32 = 0.99;
+
+/// Default number of iterations of the non-negative least squares of `mel_to_linear`
+pub const DEFAULT_NNLS_ITERATIONS: usize = 200;
+
+/// How `mel_to_linear` inverts a filter bank, which has fewer bands than frequency bins
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MelInversion {
+    /// Non-negative least squares: the non-negative spectrogram whose projection on the filters
+    /// is closest to the bands, as librosa.feature.inverse.mel_to_stft
+    #[default]
+    Nnls,
+    /// Transpose of the filter bank, normalized so that flat spectra are recovered exactly:
+    /// every bin gets the mean level of the bands covering it, weighted by their filters. Fast,
+    /// but smoother than NNLS
+    Transpose,
+}
+
+/// Complex STFT in [freq][time] layout (positive frequencies only), framed like
+/// `compute_spectrogram`
+pub fn compute_complex_stft(
+    audio: &[f32],
+    n_fft: usize,
+    hop_length: usize,
+    win_length: usize,
+    center: bool,
+) -> Vec<Vec<Complex<f32>>> {
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(n_fft);
+    let window: Vec<f32> = create_hann_window(win_length, false);
+    let offset = if center { (n_fft - win_length) / 2 } else { 0 };
+
+    let n_frames = audio.len().saturating_sub(win_length) / hop_length + 1;
+    let n_bins = n_fft / 2 + 1;
+    let mut stft = vec![vec![Complex::new(0.0, 0.0); n_frames]; n_bins];
+
+    let mut frame = vec![Complex::new(0.0, 0.0); n_fft];
+    for t in 0..n_frames {
+        let start = (t * hop_length).min(audio.len());
+        let end = (start + win_length).min(audio.len());
+        frame.fill(Complex::new(0.0, 0.0));
+        for (dst, (&s, &w)) in frame[offset..]
+            .iter_mut()
+            .zip(audio[start..end].iter().zip(&window))
+        {
+            dst.re = s * w;
+        }
+        fft.process(&mut frame);
+        for (row, c) in stft.iter_mut().zip(&frame) {
+            row[t] = *c;
+        }
+    }
+
+    stft
+}
+
+/// Inverse of `compute_complex_stft`: the inverse FFTs of the frames are windowed again and
+/// overlap-added, normalized by the summed squared window. Returns
+/// `(n_frames - 1) * hop_length + win_length` samples.
+pub fn istft(
+    stft: &[Vec<Complex<f32>>],
+    n_fft: usize,
+    hop_length: usize,
+    win_length: usize,
+    center: bool,
+) -> Result<Vec<f32>> {
+    validate(stft.len(), n_fft, hop_length, win_length)?;
+    let n_frames = stft.first().map_or(0, Vec::len);
+    if n_frames == 0 {
+        return Ok(Vec::new());
+    }
+
+    let ifft = FftPlanner::<f32>::new().plan_fft_inverse(n_fft);
+    let window: Vec<f32> = create_hann_window(win_length, false);
+    let offset = if center { (n_fft - win_length) / 2 } else { 0 };
+    let n_bins = stft.len();
+
+    let n_samples = (n_frames - 1) * hop_length + win_length;
+    let mut audio = vec![0.0f32; n_samples];
+    let mut window_sum = vec![0.0f32; n_samples];
+    let mut frame = vec![Complex::new(0.0, 0.0); n_fft];
+    for t in 0..n_frames {
+        let (positive, negative) = frame.split_at_mut(n_bins);
+        for (value, row) in positive.iter_mut().zip(stft) {
+            *value = row[t];
+        }
+        // Negative frequencies of a real signal are the conjugates of the positive ones
+        for (k, value) in (n_bins..n_fft).zip(negative.iter_mut()) {
+            *value = positive[n_fft - k].conj();
+        }
+        ifft.process(&mut frame);
+
+        let start = t * hop_length;
+        for (j, &w) in window.iter().enumerate() {
+            // rustfft does not normalize the inverse transform
+            audio[start + j] += w * frame[offset + j].re / n_fft as f32;
+            window_sum[start + j] += w * w;
+        }
+    }
+
+    for (sample, &sum) in audio.iter_mut().zip(&window_sum) {
+        if sum > f32::MIN_POSITIVE {
+            *sample /= sum;
+        }
+    }
+    Ok(audio)
+}
+
+/// Estimate audio whose magnitude spectrogram ([freq][time], as computed by
+/// `compute_spectrogram` with `SpectrogramType::Magnitude`) is `magnitude`, with the fast
+/// Griffin-Lim algorithm: starting from random phases, alternate between the inverse STFT and
+/// the phases of the STFT of the result. The initial phases are seeded, so the result is
+/// reproducible.
+pub fn griffin_lim(
+    magnitude: &[Vec<f32>],
+    n_fft: usize,
+    hop_length: usize,
+    win_length: usize,
+    center: bool,
+    n_iter: usize,
+) -> Result<Vec<f32>> {
+    validate(magnitude.len(), n_fft, hop_length, win_length)?;
+
+    let mut noise = GaussianNoise::new(0);
+    let mut angles: Vec<Vec<Complex<f32>>> = magnitude
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|_| Complex::from_polar(1.0, 2.0 * PI * noise.uniform()))
+                .collect()
+        })
+        .collect();
+    let with_magnitude = |angles: &[Vec<Complex<f32>>]| -> Vec<Vec<Complex<f32>>> {
+        magnitude
+            .iter()
+            .zip(angles)
+            .map(|(m, a)| m.iter().zip(a).map(|(&m, &a)| a * m).collect())
+            .collect()
+    };
+
+    let mut previous =
+        vec![vec![Complex::new(0.0, 0.0); angles.first().map_or(0, Vec::len)]; angles.len()];
+    for _ in 0..n_iter {
+        let audio = istft(
+            &with_magnitude(&angles),
+            n_fft,
+            hop_length,
+            win_length,
+            center,
+        )?;
+        let rebuilt = compute_complex_stft(&audio, n_fft, hop_length, win_length, center);
+
+        // Push the phases further in the direction they moved at the previous iteration
+        for ((angle_row, rebuilt_row), previous_row) in
+            angles.iter_mut().zip(&rebuilt).zip(previous.iter_mut())
+        {
+            for ((angle, &value), previous) in angle_row
+                .iter_mut()
+                .zip(rebuilt_row)
+                .zip(previous_row.iter_mut())
+            {
+                let accelerated = value - *previous * (MOMENTUM / (1.0 + MOMENTUM));
+                *angle = accelerated / (accelerated.norm() + 1e-16);
+                *previous = value;
+            }
+        }
+    }
+
+    istft(
+        &with_magnitude(&angles),
+        n_fft,
+        hop_length,
+        win_length,
+        center,
+    )
+}
+
+/// Approximately invert a filter bank ([band][freq] layout, e.g. from `mel_filter_bank` or
+/// `filter_bank`): the linear spectrogram ([freq][time], n_fft / 2 + 1 bins) whose projection on
+/// the filters is closest to `bands` ([band][time]), e.g. to reconstruct audio from a mel
+/// spectrogram with `griffin_lim`. The values keep the type of the bands (power or magnitude);
+/// bins outside of every filter are zero. NNLS runs `n_iter` accelerated projected gradient
+/// steps from the transpose estimate.
+pub fn mel_to_linear(
+    bands: &[Vec<f32>],
+    filters: &[Vec<f32>],
+    method: MelInversion,
+    n_iter: usize,
+) -> Result<Vec<Vec<f32>>> {
+    if filters.is_empty() || bands.len() != filters.len() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Expected as many bands as filters, got {} bands and {} filters",
+            bands.len(),
+            filters.len()
+        )));
+    }
+    let n_bins = filters[0].len();
+    if filters.iter().any(|filter| filter.len() != n_bins) {
+        return Err(SpectrsError::InvalidParams(
+            "The filters of the filter bank have different lengths".to_string(),
+        ));
+    }
+    let n_frames = bands[0].len();
+    if bands.iter().any(|row| row.len() != n_frames) {
+        return Err(SpectrsError::InvalidParams(
+            "The bands of the spectrogram have different numbers of frames".to_string(),
+        ));
+    }
+
+    let filters: Vec<SparseFilter> = filters.iter().map(|f| SparseFilter::new(f)).collect();
+    // Weight of every bin over the filters, and area of every filter
+    let mut coverage = vec![0.0f32; n_bins];
+    for filter in &filters {
+        for (c, &w) in coverage[filter.start..].iter_mut().zip(&filter.weights) {
+            *c += w;
+        }
+    }
+    let areas: Vec<f32> = filters.iter().map(|f| f.weights.iter().sum()).collect();
+    // Step of the projected gradient: the inverse of a bound of the largest eigenvalue of
+    // MᵀM, ‖M‖₁‖M‖∞
+    let max_coverage = coverage.iter().fold(0.0f32, |acc, &c| acc.max(c.abs()));
+    let max_area = filters
+        .iter()
+        .map(|f| f.weights.iter().map(|w| w.abs()).sum::<f32>())
+        .fold(0.0f32, f32::max);
+    let lipschitz = max_coverage * max_area;
+
+    let mut linear = vec![vec![0.0f32; n_frames]; n_bins];
+    let mut target = vec![0.0f32; filters.len()];
+    let mut estimate = vec![0.0f32; n_bins];
+    for t in 0..n_frames {
+        for (value, row) in target.iter_mut().zip(bands) {
+            *value = row[t];
+        }
+
+        // Transpose estimate: the level of every band spread over its filter
+        estimate.fill(0.0);
+        for ((filter, &value), &area) in filters.iter().zip(&target).zip(&areas) {
+            if area > 0.0 {
+                filter.add_transposed(value / area, &mut estimate);
+            }
+        }
+        for (x, &c) in estimate.iter_mut().zip(&coverage) {
+            *x = if c > 0.0 { (*x / c).max(0.0) } else { 0.0 };
+        }
+        if method == MelInversion::Nnls && lipschitz > 0.0 {
+            nnls(&filters, &target, &mut estimate, lipschitz, n_iter);
+        }
+
+        for (row, &x) in linear.iter_mut().zip(&estimate) {
+            row[t] = x;
+        }
+    }
+    Ok(linear)
+}
+
+/// Non-zero span of a filter
+struct SparseFilter {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+impl SparseFilter {
+    fn new(filter: &[f32]) -> Self {
+        let start = filter.iter().position(|&w| w != 0.0).unwrap_or(0);
+        let end = filter
+            .iter()
+            .rposition(|&w| w != 0.0)
+            .map_or(0, |end| end + 1);
+        Self {
+            start,
+            weights: filter[start..end.max(start)].to_vec(),
+        }
+    }
+
+    /// Projection of a spectrum on the filter
+    fn apply(&self, spectrum: &[f32]) -> f32 {
+        self.weights
+            .iter()
+            .zip(&spectrum[self.start..])
+            .map(|(w, x)| w * x)
+            .sum()
+    }
+
+    /// Add `value` spread over the bins by the filter (the transpose of `apply`)
+    fn add_transposed(&self, value: f32, spectrum: &mut [f32]) {
+        for (x, &w) in spectrum[self.start..].iter_mut().zip(&self.weights) {
+            *x += w * value;
+        }
+    }
+}
+
+/// Minimize ‖Mx − target‖² over x ≥ 0 by accelerated projected gradient (FISTA), starting from
+/// `x`
+fn nnls(filters: &[SparseFilter], target: &[f32], x: &mut [f32], lipschitz: f32, n_iter: usize) {
+    let mut momentum = x.to_vec();
+    let mut gradient = vec![0.0f32; x.len()];
+    let mut previous = x.to_vec();
+    let mut step = 1.0f32;
+    for _ in 0..n_iter {
+        gradient.fill(0.0);
+        for (filter, &value) in filters.iter().zip(target) {
+            filter.add_transposed(filter.apply(&momentum) - value, &mut gradient);
+        }
+        for ((x, &y), &g) in x.iter_mut().zip(&momentum).zip(&gradient) {
+            *x = (y - g / lipschitz).max(0.0);
+        }
+        let next_step = (1.0 + (1.0 + 4.0 * step * step).sqrt()) / 2.0;
+        let weight = (step - 1.0) / next_step;
+        for ((y, &x), p) in momentum.iter_mut().zip(x.iter()).zip(previous.iter_mut()) {
+            *y = x + weight * (x - *p);
+            *p = x;
+        }
+        step = next_step;
+    }
+}
+
+fn validate(n_bins: usize, n_fft: usize, hop_length: usize, win_length: usize) -> Result<()> {
+    if hop_length == 0 || win_length == 0 || win_length > n_fft {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Invalid STFT parameters (n_fft={}, hop_length={}, win_length={})",
+            n_fft, hop_length, win_length
+        )));
+    }
+    if n_bins != n_fft / 2 + 1 {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Expected {} frequency bins for n_fft={}, got {}",
+            n_fft / 2 + 1,
+            n_fft,
+            n_bins
+        )));
+    }
+    Ok(())
+}
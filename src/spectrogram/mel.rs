@@ -1,37 +1,43 @@
 //use clap::ValueEnum;
+use crate::spectrogram::stft::{Float, constant};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 // Different sconversions to mel scale
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
 pub enum MelScale {
     HTK,
     Slaney,
 }
 
 /// Convert frequency in Hz to mel scale
-fn hz_to_mel(hz: f32, mel_scale: MelScale) -> f32 {
+pub(crate) fn hz_to_mel<T: Float>(hz: T, mel_scale: MelScale) -> T {
     match mel_scale {
-        MelScale::HTK => 2595.0 * (1.0 + hz / 700.0).log10(),
+        MelScale::HTK => constant::<T>(2595.0) * (T::one() + hz / constant(700.0)).log10(),
         MelScale::Slaney => {
-            if hz < 1000.0 {
-                3.0 * hz / 200.0
+            if hz < constant(1000.0) {
+                constant::<T>(3.0) * hz / constant(200.0)
             } else {
-                15.0 + 27.0 * (hz / 1000.0).log(6.4)
+                constant::<T>(15.0)
+                    + constant::<T>(27.0) * (hz / constant(1000.0)).log(constant(6.4))
             }
         }
     }
 }
 
 /// Convert mel scale back to Hz (inverse formula of the above)
-fn mel_to_hz(mel: f32, mel_scale: MelScale) -> f32 {
+pub(crate) fn mel_to_hz<T: Float>(mel: T, mel_scale: MelScale) -> T {
     match mel_scale {
-        MelScale::HTK => 700.0 * (10.0f32.powf(mel / 2595.0) - 1.0),
+        MelScale::HTK => {
+            constant::<T>(700.0) * (constant::<T>(10.0).powf(mel / constant(2595.0)) - T::one())
+        }
         MelScale::Slaney => {
-            if mel < 15.0 {
-                200.0 * mel / 3.0
+            if mel < constant(15.0) {
+                constant::<T>(200.0) * mel / constant(3.0)
             } else {
-                6.4f32.powf((mel - 15.0) / 27.0) * 1000.0
+                constant::<T>(6.4).powf((mel - constant(15.0)) / constant(27.0)) * constant(1000.0)
             }
         }
     }
@@ -40,7 +46,12 @@ fn mel_to_hz(mel: f32, mel_scale: MelScale) -> f32 {
 /// Compute an array of acoustic frequencies tuned to the mel scale
 /// Because of psycho-acoustic there are two definitions, see (see e.g. https://en.wikipedia.org/wiki/Mel_scale)
 /// for additional information.
-fn create_mel_frequencies(f_min: f32, f_max: f32, n_mels: usize, mel_scale: MelScale) -> Vec<f32> {
+fn create_mel_frequencies<T: Float>(
+    f_min: T,
+    f_max: T,
+    n_mels: usize,
+    mel_scale: MelScale,
+) -> Vec<T> {
     // Convert to mel scale
     let mel_min = hz_to_mel(f_min, mel_scale);
     let mel_max = hz_to_mel(f_max, mel_scale);
@@ -58,30 +69,53 @@ fn create_mel_frequencies(f_min: f32, f_max: f32, n_mels: usize, mel_scale: MelS
     //          4188.417,   4573.636,   4994.285,   5453.621,
     //          5955.205,   6502.92 ,   7101.009,   7754.107,
     //          8467.272,   9246.028,  10096.408,  11025.   ])
-    let mel_freqs: Vec<f32> = (0..=n_mels - 1)
-        .map(|i| mel_min + (mel_max - mel_min) * i as f32 / (n_mels - 1) as f32)
+    let mel_freqs: Vec<T> = (0..=n_mels - 1)
+        .map(|i| mel_min + (mel_max - mel_min) * constant(i as f64) / constant((n_mels - 1) as f64))
         .map(|f| mel_to_hz(f, mel_scale))
         .collect();
 
     mel_freqs
 }
 
-fn create_mel_filter_bank(
+fn create_mel_filter_bank<T: Float>(
     sr: u32,
     n_fft: usize,
     n_mels: usize,
     f_min: Option<f32>, // Lower cut-off frequency
     f_max: Option<f32>, // Upper cut-off frequency
     mel_scale: MelScale,
-) -> Vec<Vec<f32>> {
+    slaney_norm: bool,
+) -> Vec<Vec<T>> {
     // Use provided values or defaults
-    let f_min = f_min.unwrap_or(0.0);
-    let f_max = f_max.unwrap_or(sr as f32 / 2.0); // (Nyquist theorem)
+    let f_min: T = constant(f_min.unwrap_or(0.0) as f64);
+    let f_max: T = match f_max {
+        Some(f_max) => constant(f_max as f64),
+        None => constant::<T>(sr as f64) / constant(2.0), // (Nyquist theorem)
+    };
+
+    // Extract mel frequencies
+    // Equivalent to Librosa mel_f = mel_frequencies(n_mels + 2, fmin=fmin, fmax=fmax, htk=htk)
+    let mel_freqs: Vec<T> = create_mel_frequencies(f_min, f_max, n_mels + 2, mel_scale);
+
+    triangular_filter_bank(sr, n_fft, &mel_freqs, slaney_norm)
+}
+
+/// Create triangular filters in [band][freq] layout, band i rising from `edges[i]` to a peak
+/// at `edges[i + 1]` and falling to `edges[i + 2]` (Hz), the way librosa builds mel filters.
+/// With `slaney_norm`, every filter is scaled to unit area instead of a unit peak.
+pub(crate) fn triangular_filter_bank<T: Float>(
+    sr: u32,
+    n_fft: usize,
+    edges: &[T],
+    slaney_norm: bool,
+) -> Vec<Vec<T>> {
+    let n_mels = edges.len().saturating_sub(2);
+    let mel_freqs = edges;
 
     // Create weights
     // Equivalent to weights = np.zeros((n_mels, int(1 + n_fft // 2)), dtype=dtype)
     let n_freq_bins = 1 + n_fft / 2;
-    let mut weights: Vec<Vec<f32>> = vec![vec![0.0f32; n_freq_bins]; n_mels];
+    let mut weights: Vec<Vec<T>> = vec![vec![T::zero(); n_freq_bins]; n_mels];
 
     // Compute fft frequencies.
     // From librosa official doc
@@ -89,21 +123,17 @@ fn create_mel_filter_bank(
     // array([     0.   ,   1378.125,   2756.25 ,   4134.375,
     //          5512.5  ,   6890.625,   8268.75 ,   9646.875,  11025.   ])
     // In Python would be [i * sr/n_fft for i in range(0, n_fft//2 + 1)]
-    let fft_freqs: Vec<f32> = (0..=n_fft / 2_usize)
-        .map(|i| i as f32 * sr as f32 / n_fft as f32)
+    let fft_freqs: Vec<T> = (0..=n_fft / 2_usize)
+        .map(|i| constant::<T>(i as f64) * constant(sr as f64) / constant(n_fft as f64))
         .collect();
 
-    // Extract mel frequencies
-    // Equivalent to Librosa mel_f = mel_frequencies(n_mels + 2, fmin=fmin, fmax=fmax, htk=htk)
-    let mel_freqs: Vec<f32> = create_mel_frequencies(f_min, f_max, n_mels + 2, mel_scale);
-
     // Compute differences between subsequent mel frequencies
     // Equivalent to fdiff = np.diff(mel_f) in Librosa implementation
-    let mel_freqs_diffs: Vec<f32> = mel_freqs.windows(2).map(|w| w[1] - w[0]).collect();
+    let mel_freqs_diffs: Vec<T> = mel_freqs.windows(2).map(|w| w[1] - w[0]).collect();
 
     // Create ramps matrix: ramps[i][j] = mel_freqs[i] - fft_freqs[j]
     // Equivalent to ramps = np.subtract.outer(mel_f, fftfreqs) in Librosa
-    let ramps: Vec<Vec<f32>> = mel_freqs
+    let ramps: Vec<Vec<T>> = mel_freqs
         .iter()
         .map(|&mel_freq| {
             fft_freqs
@@ -117,9 +147,9 @@ fn create_mel_filter_bank(
     // to perceptually-motivated mel-scale representations.
     for i in 0..n_mels {
         // Lower and upper slopes for all bins
-        let lower: Vec<f32> = ramps[i].iter().map(|&r| -r / mel_freqs_diffs[i]).collect();
+        let lower: Vec<T> = ramps[i].iter().map(|&r| -r / mel_freqs_diffs[i]).collect();
 
-        let upper: Vec<f32> = ramps[i + 2]
+        let upper: Vec<T> = ramps[i + 2]
             .iter()
             .map(|&r| r / mel_freqs_diffs[i + 1])
             .collect();
@@ -128,44 +158,84 @@ fn create_mel_filter_bank(
         weights[i] = lower
             .iter()
             .zip(upper.iter())
-            .map(|(&l, &u)| 0.0f32.max(l.min(u)))
+            .map(|(&l, &u)| T::zero().max(l.min(u)))
             .collect();
     }
 
+    // Without normalization every filter peaks at 1 (torchaudio's default)
+    if !slaney_norm {
+        return weights;
+    }
+
     // Apply Slaney normalization (librosa's default, regardless of choice for mel scale)
     // Compute normalization factors: 2.0 / (mel_f[2:n_mels+2] - mel_f[0:n_mels])
-    let enorm: Vec<f32> = (0..n_mels)
-        .map(|i| 2.0 / (mel_freqs[i + 2] - mel_freqs[i]))
+    let enorm: Vec<T> = (0..n_mels)
+        .map(|i| constant::<T>(2.0) / (mel_freqs[i + 2] - mel_freqs[i]))
         .collect();
 
     // Apply normalization to each filter
-    for i in 0..n_mels {
-        for j in 0..n_freq_bins {
-            weights[i][j] *= enorm[i];
+    for (filter, &norm) in weights.iter_mut().zip(enorm.iter()) {
+        for w in filter.iter_mut() {
+            *w *= norm;
         }
     }
 
     weights
 }
 
+/// Centre frequency (Hz) of every band of the mel filter bank built with the same arguments,
+/// i.e. the peaks of its triangular filters
+pub fn mel_band_frequencies(
+    sr: u32,
+    n_mels: usize,
+    f_min: Option<f32>,
+    f_max: Option<f32>,
+    mel_scale: MelScale,
+) -> Vec<f32> {
+    let f_min = f_min.unwrap_or(0.0) as f64;
+    let f_max = f_max.map_or(sr as f64 / 2.0, |f_max| f_max as f64);
+    let mel_freqs: Vec<f64> = create_mel_frequencies(f_min, f_max, n_mels + 2, mel_scale);
+    mel_freqs[1..=n_mels].iter().map(|&f| f as f32).collect()
+}
+
 /// Apply Mel filters to an already created spectrogram (sequential version)
-pub fn convert_to_mel(
-    spectrogram: &[Vec<f32>],
+pub fn convert_to_mel<T: Float>(
+    spectrogram: &[Vec<T>],
     sr: u32,
     n_fft: usize,
     n_mels: usize,
     f_min: Option<f32>, // Lower cut-off frequency
     f_max: Option<f32>, // Upper cut-off frequency
     mel_scale: MelScale,
-) -> Vec<Vec<f32>> {
+) -> Vec<Vec<T>> {
     // Create mel filter bank matrix
-    let mel_filters = create_mel_filter_bank(sr, n_fft, n_mels, f_min, f_max, mel_scale);
+    let mel_filters = create_mel_filter_bank(sr, n_fft, n_mels, f_min, f_max, mel_scale, true);
 
+    apply_filter_bank(spectrogram, &mel_filters)
+}
+
+/// Create a mel filter bank in [mel][freq] layout, with or without Slaney normalization
+/// (librosa normalizes by default, torchaudio does not)
+pub fn mel_filter_bank<T: Float>(
+    sr: u32,
+    n_fft: usize,
+    n_mels: usize,
+    f_min: Option<f32>, // Lower cut-off frequency
+    f_max: Option<f32>, // Upper cut-off frequency
+    mel_scale: MelScale,
+    slaney_norm: bool,
+) -> Vec<Vec<T>> {
+    create_mel_filter_bank(sr, n_fft, n_mels, f_min, f_max, mel_scale, slaney_norm)
+}
+
+/// Apply a filter bank to a spectrogram (sequential version)
+pub fn apply_filter_bank<T: Float>(spectrogram: &[Vec<T>], filters: &[Vec<T>]) -> Vec<Vec<T>> {
     // Apply filters: mel_spec[mel_bin][time] = sum(spec[freq][time] * filter[mel_bin][freq])
-    let mut mel_spec = vec![vec![0.0; spectrogram[0].len()]; n_mels];
+    let n_time_frames = spectrogram.first().map_or(0, |row| row.len());
+    let mut mel_spec = vec![vec![T::zero(); n_time_frames]; filters.len()];
 
-    for (mel_idx, filter) in mel_filters.iter().enumerate() {
-        for time_idx in 0..spectrogram[0].len() {
+    for (mel_idx, filter) in filters.iter().enumerate() {
+        for time_idx in 0..n_time_frames {
             mel_spec[mel_idx][time_idx] = spectrogram
                 .iter()
                 .zip(filter.iter())
@@ -178,31 +248,35 @@ pub fn convert_to_mel(
 }
 
 /// Create mel filter bank (parallelized version)
-fn par_create_mel_filter_bank(
+#[cfg(feature = "parallel")]
+fn par_create_mel_filter_bank<T: Float>(
     sr: u32,
     n_fft: usize,
     n_mels: usize,
     f_min: Option<f32>, // Lower cut-off frequency
     f_max: Option<f32>, // Upper cut-off frequency
     mel_scale: MelScale,
-) -> Vec<Vec<f32>> {
+) -> Vec<Vec<T>> {
     // Use provided values or defaults
-    let f_min = f_min.unwrap_or(0.0);
-    let f_max = f_max.unwrap_or(sr as f32 / 2.0); // (Nyquist theorem)
+    let f_min: T = constant(f_min.unwrap_or(0.0) as f64);
+    let f_max: T = match f_max {
+        Some(f_max) => constant(f_max as f64),
+        None => constant::<T>(sr as f64) / constant(2.0), // (Nyquist theorem)
+    };
 
     // Compute fft frequencies
-    let fft_freqs: Vec<f32> = (0..=n_fft / 2_usize)
-        .map(|i| i as f32 * sr as f32 / n_fft as f32)
+    let fft_freqs: Vec<T> = (0..=n_fft / 2_usize)
+        .map(|i| constant::<T>(i as f64) * constant(sr as f64) / constant(n_fft as f64))
         .collect();
 
     // Extract mel frequencies
-    let mel_freqs: Vec<f32> = create_mel_frequencies(f_min, f_max, n_mels + 2, mel_scale);
+    let mel_freqs: Vec<T> = create_mel_frequencies(f_min, f_max, n_mels + 2, mel_scale);
 
     // Compute differences between subsequent mel frequencies
-    let mel_freqs_diffs: Vec<f32> = mel_freqs.windows(2).map(|w| w[1] - w[0]).collect();
+    let mel_freqs_diffs: Vec<T> = mel_freqs.windows(2).map(|w| w[1] - w[0]).collect();
 
     // Create ramps matrix in parallel
-    let ramps: Vec<Vec<f32>> = mel_freqs
+    let ramps: Vec<Vec<T>> = mel_freqs
         .par_iter()
         .map(|&mel_freq| {
             fft_freqs
@@ -213,8 +287,8 @@ fn par_create_mel_filter_bank(
         .collect();
 
     // Apply Slaney normalization factors
-    let enorm: Vec<f32> = (0..n_mels)
-        .map(|i| 2.0 / (mel_freqs[i + 2] - mel_freqs[i]))
+    let enorm: Vec<T> = (0..n_mels)
+        .map(|i| constant::<T>(2.0) / (mel_freqs[i + 2] - mel_freqs[i]))
         .collect();
 
     // Create triangular mel filter banks in parallel
@@ -222,9 +296,9 @@ fn par_create_mel_filter_bank(
         .into_par_iter()
         .map(|i| {
             // Lower and upper slopes for all bins
-            let lower: Vec<f32> = ramps[i].iter().map(|&r| -r / mel_freqs_diffs[i]).collect();
+            let lower: Vec<T> = ramps[i].iter().map(|&r| -r / mel_freqs_diffs[i]).collect();
 
-            let upper: Vec<f32> = ramps[i + 2]
+            let upper: Vec<T> = ramps[i + 2]
                 .iter()
                 .map(|&r| r / mel_freqs_diffs[i + 1])
                 .collect();
@@ -233,32 +307,39 @@ fn par_create_mel_filter_bank(
             lower
                 .iter()
                 .zip(upper.iter())
-                .map(|(&l, &u)| 0.0f32.max(l.min(u)) * enorm[i])
+                .map(|(&l, &u)| T::zero().max(l.min(u)) * enorm[i])
                 .collect()
         })
         .collect()
 }
 
 /// Apply Mel filters to an already created spectrogram (parallelized version)
-pub fn par_convert_to_mel(
-    spectrogram: &[Vec<f32>],
+#[cfg(feature = "parallel")]
+pub fn par_convert_to_mel<T: Float>(
+    spectrogram: &[Vec<T>],
     sr: u32,
     n_fft: usize,
     n_mels: usize,
     f_min: Option<f32>, // Lower cut-off frequency
     f_max: Option<f32>, // Upper cut-off frequency
     mel_scale: MelScale,
-) -> Vec<Vec<f32>> {
+) -> Vec<Vec<T>> {
     // Create mel filter bank matrix (using parallelized version)
     let mel_filters = par_create_mel_filter_bank(sr, n_fft, n_mels, f_min, f_max, mel_scale);
 
+    par_apply_filter_bank(spectrogram, &mel_filters)
+}
+
+/// Apply a filter bank to a spectrogram (parallelized over filters)
+#[cfg(feature = "parallel")]
+pub fn par_apply_filter_bank<T: Float>(spectrogram: &[Vec<T>], filters: &[Vec<T>]) -> Vec<Vec<T>> {
     // Apply filters in parallel: mel_spec[mel_bin][time] = sum(spec[freq][time] * filter[mel_bin][freq])
-    let n_time_frames = spectrogram[0].len();
+    let n_time_frames = spectrogram.first().map_or(0, |row| row.len());
 
-    mel_filters
+    filters
         .par_iter()
         .map(|filter| {
-            let mut mel_row = vec![0.0; n_time_frames];
+            let mut mel_row = vec![T::zero(); n_time_frames];
             for time_idx in 0..n_time_frames {
                 mel_row[time_idx] = spectrogram
                     .iter()
@@ -270,3 +351,23 @@ pub fn par_convert_to_mel(
         })
         .collect()
 }
+
+/// Without the `parallel` feature (e.g. on wasm32) the filters are applied sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_convert_to_mel<T: Float>(
+    spectrogram: &[Vec<T>],
+    sr: u32,
+    n_fft: usize,
+    n_mels: usize,
+    f_min: Option<f32>, // Lower cut-off frequency
+    f_max: Option<f32>, // Upper cut-off frequency
+    mel_scale: MelScale,
+) -> Vec<Vec<T>> {
+    convert_to_mel(spectrogram, sr, n_fft, n_mels, f_min, f_max, mel_scale)
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the filters are applied sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_apply_filter_bank<T: Float>(spectrogram: &[Vec<T>], filters: &[Vec<T>]) -> Vec<Vec<T>> {
+    apply_filter_bank(spectrogram, filters)
+}
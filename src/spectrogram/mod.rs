@@ -1,2 +1,20 @@
+pub mod average;
+pub mod cmvn;
+pub mod compare;
+pub mod compression;
+pub mod denoise;
+pub mod fbank;
+pub mod fft;
+pub mod filterbank;
+pub mod gammatone;
+pub mod gpu;
+pub mod inverse;
 pub mod mel;
+pub mod ops;
+pub mod params;
+pub mod pipeline;
+pub mod pooling;
 pub mod stft;
+pub mod streaming;
+pub mod torchaudio;
+pub mod weighting;
@@ -0,0 +1,278 @@
+// Arithmetic on whole spectrograms in [freq][time] format: cropping a range of frames or bins,
+// padding, joining spectrograms along time, bilinear resizing, gating quiet frames and
+// finding or replacing non-finite values, so that callers do not re-implement the slicing of
+// nested vectors (and its off-by-one errors) themselves. All functions reject ragged
+// spectrograms, whose rows have different numbers of frames.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::stft::{Float, SpectrogramType, constant};
+use std::ops::Range;
+
+/// Number of rows (frequency bins) and columns (frames) of the [freq][time] spectrogram
+pub fn shape<T>(spectrogram: &[Vec<T>]) -> Result<(usize, usize)> {
+    let n_frames = spectrogram.first().map_or(0, Vec::len);
+    if spectrogram.iter().any(|row| row.len() != n_frames) {
+        return Err(SpectrsError::InvalidParams(
+            "All rows of the spectrogram must have the same length".to_string(),
+        ));
+    }
+    Ok((spectrogram.len(), n_frames))
+}
+
+/// Keep the frames of `frames` (e.g. `10..20`) of the [freq][time] spectrogram
+pub fn crop_time<T: Float>(spectrogram: &[Vec<T>], frames: Range<usize>) -> Result<Vec<Vec<T>>> {
+    let (_, n_frames) = shape(spectrogram)?;
+    check_range(&frames, n_frames, "frames")?;
+    Ok(spectrogram
+        .iter()
+        .map(|row| row[frames.clone()].to_vec())
+        .collect())
+}
+
+/// Keep the frequency bins of `bins` (e.g. `0..64` for the lowest 64) of the [freq][time]
+/// spectrogram
+pub fn crop_freq<T: Float>(spectrogram: &[Vec<T>], bins: Range<usize>) -> Result<Vec<Vec<T>>> {
+    let (n_bins, _) = shape(spectrogram)?;
+    check_range(&bins, n_bins, "bins")?;
+    Ok(spectrogram[bins].to_vec())
+}
+
+/// Amount of padding added on each side of a spectrogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Padding {
+    /// Frames added before the first frame
+    pub start: usize,
+    /// Frames added after the last frame
+    pub end: usize,
+    /// Bins added below the lowest bin
+    pub low: usize,
+    /// Bins added above the highest bin
+    pub high: usize,
+}
+
+impl Padding {
+    /// Padding along time only
+    pub fn time(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+            ..Self::default()
+        }
+    }
+
+    /// Padding along frequency only
+    pub fn freq(low: usize, high: usize) -> Self {
+        Self {
+            low,
+            high,
+            ..Self::default()
+        }
+    }
+}
+
+/// Surround the [freq][time] spectrogram with `value` (e.g. zero for linear values, or the
+/// floor of a dB spectrogram)
+pub fn pad<T: Float>(spectrogram: &[Vec<T>], padding: Padding, value: T) -> Result<Vec<Vec<T>>> {
+    let (_, n_frames) = shape(spectrogram)?;
+    let width = padding.start + n_frames + padding.end;
+    let mut padded = Vec::with_capacity(padding.low + spectrogram.len() + padding.high);
+    padded.extend((0..padding.low).map(|_| vec![value; width]));
+    padded.extend(spectrogram.iter().map(|row| {
+        let mut padded_row = Vec::with_capacity(width);
+        padded_row.resize(padding.start, value);
+        padded_row.extend_from_slice(row);
+        padded_row.resize(width, value);
+        padded_row
+    }));
+    padded.extend((0..padding.high).map(|_| vec![value; width]));
+    Ok(padded)
+}
+
+/// Join [freq][time] spectrograms with the same number of bins one after the other along time
+pub fn concat_time<T: Float, S: AsRef<[Vec<T>]>>(spectrograms: &[S]) -> Result<Vec<Vec<T>>> {
+    let Some(first) = spectrograms.first() else {
+        return Ok(Vec::new());
+    };
+    let n_bins = first.as_ref().len();
+    let mut n_frames = 0;
+    for (i, spectrogram) in spectrograms.iter().enumerate() {
+        let (bins, frames) = shape(spectrogram.as_ref())?;
+        if bins != n_bins {
+            return Err(SpectrsError::InvalidParams(format!(
+                "Spectrogram {} has {} bins, expected {} like the first one",
+                i, bins, n_bins
+            )));
+        }
+        n_frames += frames;
+    }
+
+    let mut joined: Vec<Vec<T>> = (0..n_bins).map(|_| Vec::with_capacity(n_frames)).collect();
+    for spectrogram in spectrograms {
+        for (row, part) in joined.iter_mut().zip(spectrogram.as_ref()) {
+            row.extend_from_slice(part);
+        }
+    }
+    Ok(joined)
+}
+
+/// Resize the [freq][time] spectrogram to `n_bins` × `n_frames` by bilinear interpolation. The
+/// corners are kept (as with `FitMode::Resample` along time), so that the first and last bins
+/// and frames are unchanged.
+pub fn resize<T: Float>(
+    spectrogram: &[Vec<T>],
+    n_bins: usize,
+    n_frames: usize,
+) -> Result<Vec<Vec<T>>> {
+    let (rows, cols) = shape(spectrogram)?;
+    if (rows == 0 && n_bins > 0) || (cols == 0 && n_frames > 0) {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Cannot resize an empty spectrogram ({}x{}) to {}x{}",
+            rows, cols, n_bins, n_frames
+        )));
+    }
+    let row_positions: Vec<(usize, usize, T)> =
+        (0..n_bins).map(|k| sample(k, n_bins, rows)).collect();
+    let col_positions: Vec<(usize, usize, T)> =
+        (0..n_frames).map(|t| sample(t, n_frames, cols)).collect();
+
+    Ok(row_positions
+        .iter()
+        .map(|&(low, high, v)| {
+            let (low, high) = (&spectrogram[low], &spectrogram[high]);
+            col_positions
+                .iter()
+                .map(|&(left, right, u)| {
+                    let below = low[left] + u * (low[right] - low[left]);
+                    let above = high[left] + u * (high[right] - high[left]);
+                    below + v * (above - below)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Neighbours of output index `i` of `n_out` in an axis of `n_in` values, and the weight of
+/// the second one
+fn sample<T: Float>(i: usize, n_out: usize, n_in: usize) -> (usize, usize, T) {
+    let position = if n_out > 1 {
+        i as f64 * (n_in - 1) as f64 / (n_out - 1) as f64
+    } else {
+        (n_in - 1) as f64 / 2.0
+    };
+    let left = position.floor() as usize;
+    let right = (left + 1).min(n_in - 1);
+    (left, right, constant(position - left as f64))
+}
+
+fn check_range(range: &Range<usize>, len: usize, what: &str) -> Result<()> {
+    if range.start > range.end || range.end > len {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Invalid range of {} {}..{} for a spectrogram with {} {}",
+            what, range.start, range.end, len, what
+        )));
+    }
+    Ok(())
+}
+
+/// What becomes of the frames below the threshold of `gate_frames`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum GateMode {
+    /// Set their values to zero (silence), keeping the timeline
+    #[default]
+    Zero,
+    /// Remove them, joining the frames around them
+    Drop,
+}
+
+/// Energy of every frame of the linear [freq][time] spectrogram (the sum of the power of its
+/// bins), in dB relative to the loudest frame. Silent frames are at minus infinity, and so are
+/// all frames of a silent spectrogram.
+pub fn frame_energy_db<T: Float>(
+    spectrogram: &[Vec<T>],
+    spectrogram_type: SpectrogramType,
+) -> Result<Vec<f32>> {
+    let (_, n_frames) = shape(spectrogram)?;
+    let mut energies = vec![0.0f64; n_frames];
+    for row in spectrogram {
+        for (energy, &value) in energies.iter_mut().zip(row) {
+            let value = value.to_f64().unwrap_or(0.0);
+            *energy += match spectrogram_type {
+                SpectrogramType::Magnitude => value * value,
+                SpectrogramType::Power => value,
+            };
+        }
+    }
+    let loudest = energies.iter().copied().fold(0.0f64, f64::max);
+    Ok(energies
+        .iter()
+        .map(|&energy| match energy > 0.0 {
+            true => (10.0 * (energy / loudest).log10()) as f32,
+            false => f32::NEG_INFINITY,
+        })
+        .collect())
+}
+
+/// Zero or drop the frames of the linear [freq][time] spectrogram whose energy is more than
+/// `threshold_db` (e.g. -60) below the loudest frame, so that the noise floor of sparse
+/// recordings does not clutter images and features. Returns the number of frames gated; a
+/// silent spectrogram is left unchanged.
+pub fn gate_frames<T: Float>(
+    spectrogram: &mut [Vec<T>],
+    threshold_db: f32,
+    spectrogram_type: SpectrogramType,
+    mode: GateMode,
+) -> Result<usize> {
+    if threshold_db.is_nan() || threshold_db > 0.0 {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The gate threshold must be at most 0 dB (relative to the loudest frame), got {}",
+            threshold_db
+        )));
+    }
+    let energies = frame_energy_db(spectrogram, spectrogram_type)?;
+    if energies.iter().all(|energy| energy.is_infinite()) {
+        return Ok(0);
+    }
+    let gated: Vec<bool> = energies.iter().map(|&e| e < threshold_db).collect();
+    let n_gated = gated.iter().filter(|&&g| g).count();
+    if n_gated == 0 {
+        return Ok(0);
+    }
+    for row in spectrogram.iter_mut() {
+        match mode {
+            GateMode::Zero => {
+                for (value, _) in row.iter_mut().zip(&gated).filter(|(_, g)| **g) {
+                    *value = T::zero();
+                }
+            }
+            GateMode::Drop => {
+                let mut frames = gated.iter();
+                row.retain(|_| !frames.next().copied().unwrap_or(false));
+            }
+        }
+    }
+    Ok(n_gated)
+}
+
+/// Frames of the [freq][time] spectrogram holding NaN or infinite values (e.g. computed from a
+/// corrupted segment of audio), in order
+pub fn non_finite_frames<T: Float>(spectrogram: &[Vec<T>]) -> Result<Vec<usize>> {
+    let (_, n_frames) = shape(spectrogram)?;
+    Ok((0..n_frames)
+        .filter(|&t| spectrogram.iter().any(|row| !row[t].is_finite()))
+        .collect())
+}
+
+/// Replace the NaN and infinite values of the [freq][time] spectrogram by `value` (e.g. zero,
+/// silence, for linear values), returning the number of values replaced
+pub fn sanitize_non_finite<T: Float>(spectrogram: &mut [Vec<T>], value: T) -> Result<usize> {
+    shape(spectrogram)?;
+    let mut replaced = 0;
+    for v in spectrogram.iter_mut().flatten() {
+        if !v.is_finite() {
+            *v = value;
+            replaced += 1;
+        }
+    }
+    Ok(replaced)
+}
@@ -0,0 +1,603 @@
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::compression::{Compression, DEFAULT_AMIN};
+use crate::spectrogram::fbank::Compat;
+use crate::spectrogram::fft::{FftBackendType, ForwardFft};
+use crate::spectrogram::filterbank::{FilterBankType, FrequencyScale};
+use crate::spectrogram::mel::MelScale;
+use crate::spectrogram::stft::{
+    Float, FrameAlignment, Precision, SpectrogramType, WindowNorm, constant, create_hann_window,
+    librosa_n_frames, samples_from_ms,
+};
+use crate::spectrogram::weighting::Weighting;
+
+/// Full set of parameters describing how a spectrogram is computed.
+/// Build it through `SpectrogramParams::builder()` so that invalid combinations are rejected
+/// with a descriptive error instead of panicking (or silently producing wrong shapes)
+/// deep inside the FFT loop.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SpectrogramParams {
+    /// Number of samples in each FFT window
+    pub n_fft: usize,
+    /// Stride between successive FFT frames
+    pub hop_length: usize,
+    /// Number of samples in the window function applied before FFT
+    pub win_length: usize,
+    /// Center the window inside the FFT frame (with `FrameAlignment::Librosa`, pad the signal
+    /// so that frames are centered on multiples of the hop, as librosa's `center`)
+    pub center: bool,
+    /// How frames are laid over the signal
+    #[serde(default)]
+    pub frame_alignment: FrameAlignment,
+    /// Periodic (`true`, as librosa and torchaudio) or symmetric Hann window (None for the
+    /// default of the preset, see `periodic_window()`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub periodic_window: Option<bool>,
+    /// Normalization of the window, scaling the values of the spectrogram
+    #[serde(default)]
+    pub window_norm: WindowNorm,
+    /// Magnitude or power spectrogram
+    pub spectrogram_type: SpectrogramType,
+    /// Number of mel bands, or of bands of `filter_bank` (None for a linear-frequency
+    /// spectrogram)
+    pub n_mels: Option<usize>,
+    /// Lower cut-off frequency of the mel filter bank (Hz)
+    pub f_min: Option<f32>,
+    /// Upper cut-off frequency of the mel filter bank (Hz, defaults to Nyquist)
+    pub f_max: Option<f32>,
+    /// Mel scale definition
+    pub mel_scale: MelScale,
+    /// Filter bank the bands are computed with
+    #[serde(default)]
+    pub filter_bank: FilterBankType,
+    /// Frequency scale the triangular filters are spaced on (`mel_scale` refines the mel one)
+    #[serde(default)]
+    pub frequency_scale: FrequencyScale,
+    /// Order of the all-pole (LPC) model replacing every frame of the STFT by its envelope
+    /// before the weighting and the filter bank (None to keep the spectra)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lpc_order: Option<usize>,
+    /// Frequency weighting of the FFT bins, applied before the filter bank
+    #[serde(default)]
+    pub weighting: Weighting,
+    /// Compressed scale of the values (None for linear powers or magnitudes)
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    /// Smallest value taken the logarithm of by the log and dB compressions (None for
+    /// librosa's 1e-10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amin: Option<f32>,
+    /// Values of the filter bank bands are raised to at least this floor (None to keep them
+    /// as computed, down to zero for silent frames)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mel_floor: Option<f32>,
+    /// Toolkit whose features are reproduced (see `Compat`)
+    #[serde(default)]
+    pub compat: Compat,
+    /// Standard deviation of the noise added to the samples by the fbank presets
+    #[serde(default)]
+    pub dither: f32,
+    /// Compute on the calling thread, summing in a fixed order, so that identical inputs give
+    /// bit-identical spectrograms from run to run whatever the number of threads
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Floating-point precision of the STFT and the mel projection
+    #[serde(default)]
+    pub precision: Precision,
+    /// Library computing the FFTs of the STFT
+    #[serde(default)]
+    pub fft_backend: FftBackendType,
+}
+
+impl Default for SpectrogramParams {
+    fn default() -> Self {
+        Self {
+            n_fft: 2048,
+            hop_length: 512,
+            win_length: 2048,
+            center: true,
+            frame_alignment: FrameAlignment::Spectrs,
+            periodic_window: None,
+            window_norm: WindowNorm::None,
+            spectrogram_type: SpectrogramType::Power,
+            n_mels: None,
+            f_min: None,
+            f_max: None,
+            mel_scale: MelScale::Slaney,
+            filter_bank: FilterBankType::Mel,
+            frequency_scale: FrequencyScale::Mel,
+            lpc_order: None,
+            weighting: Weighting::Z,
+            compression: None,
+            amin: None,
+            mel_floor: None,
+            compat: Compat::Librosa,
+            dither: 0.0,
+            deterministic: false,
+            precision: Precision::F32,
+            fft_backend: FftBackendType::RustFft,
+        }
+    }
+}
+
+impl SpectrogramParams {
+    /// Start building parameters from the defaults (n_fft=2048, hop_length=512, win_length=2048)
+    pub fn builder() -> SpectrogramParamsBuilder {
+        SpectrogramParamsBuilder::default()
+    }
+
+    /// Parameters suited to audio at `sample_rate` Hz lasting `duration` seconds, starting from
+    /// the defaults: 25 ms windows every 10 ms, the FFT length being the next power of two of
+    /// the window, and mel bands in proportion to the bandwidth (40 up to 8 kHz, 64 up to
+    /// 16 kHz, 80 up to 24 kHz, 128 above). Clips shorter than a window get the largest window
+    /// of a power of two samples they hold.
+    pub fn suggested(sample_rate: u32, duration: f32) -> Self {
+        let mut win_length = samples_from_ms(25.0, sample_rate);
+        let mut hop_length = samples_from_ms(10.0, sample_rate);
+        let n_samples = (duration.max(0.0) as f64 * sample_rate as f64).round() as usize;
+        if n_samples < win_length {
+            win_length = (n_samples + 1).next_power_of_two().max(32) / 2;
+            hop_length = win_length / 4;
+        }
+        let n_fft = win_length.next_power_of_two();
+        let n_mels = match sample_rate {
+            0..=8000 => 40,
+            8001..=16000 => 64,
+            16001..=24000 => 80,
+            _ => 128,
+        };
+        Self {
+            n_fft,
+            hop_length,
+            win_length,
+            n_mels: Some(n_mels.min(n_fft / 2 + 1)),
+            ..Self::default()
+        }
+    }
+
+    /// Smallest value taken the logarithm of by the compression
+    pub fn amin(&self) -> f32 {
+        self.amin.unwrap_or(DEFAULT_AMIN)
+    }
+
+    /// Whether the Hann window is periodic: by default with the torchaudio preset and librosa's
+    /// frame alignment, which reproduce reference implementations, and symmetric otherwise
+    pub fn periodic_window(&self) -> bool {
+        self.periodic_window.unwrap_or(
+            self.compat == Compat::Torchaudio || self.frame_alignment == FrameAlignment::Librosa,
+        )
+    }
+
+    /// Hann window of `win_length` samples applied to every frame, normalized by `win_scale()`
+    pub fn window<T: Float>(&self) -> Vec<T> {
+        let window: Vec<T> = create_hann_window(self.win_length, self.periodic_window());
+        match self.window_norm {
+            WindowNorm::None => window,
+            _ => {
+                let scale: T = constant(self.win_scale());
+                window.into_iter().map(|w| w * scale).collect()
+            }
+        }
+    }
+
+    /// Factor the Hann window is multiplied by, and so the magnitudes of the STFT (the powers
+    /// by its square), following `window_norm`
+    pub fn win_scale(&self) -> f64 {
+        let window: Vec<f64> = create_hann_window(self.win_length, self.periodic_window());
+        self.window_norm.scale(&window, self.n_fft)
+    }
+
+    /// Forward FFT of `n_fft` points planned by `fft_backend`
+    pub fn fft<T: Float>(&self) -> Result<std::sync::Arc<dyn ForwardFft<T>>> {
+        T::plan_fft(&*self.fft_backend.backend()?, self.n_fft)
+    }
+
+    /// Number of frames of the spectrogram of `n_samples` samples
+    pub fn n_frames(&self, n_samples: usize) -> usize {
+        match self.frame_alignment {
+            FrameAlignment::Spectrs => {
+                n_samples.saturating_sub(self.win_length) / self.hop_length + 1
+            }
+            FrameAlignment::Librosa => {
+                librosa_n_frames(n_samples, self.n_fft, self.hop_length, self.center)
+            }
+        }
+    }
+
+    /// Position (samples) of the center of the first frame in the signal, the next ones
+    /// following every hop
+    pub fn first_frame_center(&self) -> f32 {
+        match (self.frame_alignment, self.center) {
+            (FrameAlignment::Spectrs, _) => self.win_length as f32 / 2.0,
+            (FrameAlignment::Librosa, true) => 0.0,
+            (FrameAlignment::Librosa, false) => self.n_fft as f32 / 2.0,
+        }
+    }
+
+    /// Number of frequency bins produced by the STFT
+    pub fn n_freq_bins(&self) -> usize {
+        self.n_fft / 2 + 1
+    }
+
+    /// Check the parameters that do not depend on the sample rate
+    pub fn validate(&self) -> Result<()> {
+        if self.n_fft == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "n_fft must be greater than 0".to_string(),
+            ));
+        }
+        if self.win_length == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "win_length must be greater than 0".to_string(),
+            ));
+        }
+        if self.win_length > self.n_fft {
+            return Err(SpectrsError::InvalidParams(format!(
+                "win_length ({}) must be less than or equal to n_fft ({})",
+                self.win_length, self.n_fft
+            )));
+        }
+        if self.hop_length == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "hop_length must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(f_min) = self.f_min
+            && f_min < 0.0
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "f_min ({} Hz) must be non-negative",
+                f_min
+            )));
+        }
+        if let (Some(f_min), Some(f_max)) = (self.f_min, self.f_max)
+            && f_min >= f_max
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "f_min ({} Hz) must be below f_max ({} Hz)",
+                f_min, f_max
+            )));
+        }
+        if self.filter_bank != FilterBankType::Mel || self.frequency_scale != FrequencyScale::Mel {
+            let name = match self.filter_bank {
+                FilterBankType::Mel => format!("{:?}-scale", self.frequency_scale),
+                FilterBankType::Gammatone => "Gammatone".to_string(),
+            };
+            if self.n_mels.is_none() {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The {} filter bank needs a number of bands (n_mels)",
+                    name
+                )));
+            }
+            if self.compat != Compat::Librosa {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The {} filter bank is not available with the {:?} preset",
+                    name, self.compat
+                )));
+            }
+            if self.filter_bank == FilterBankType::Gammatone
+                && self.frequency_scale != FrequencyScale::Mel
+            {
+                return Err(SpectrsError::InvalidParams(
+                    "Gammatone filters are always spaced on the ERB-rate scale; the frequency \
+                     scale only applies to triangular filters"
+                        .to_string(),
+                ));
+            }
+        }
+        if self.frame_alignment != FrameAlignment::Spectrs && self.compat != Compat::Librosa {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The {:?} preset frames the signal its own way; the frame alignment does not apply",
+                self.compat
+            )));
+        }
+        if self.window_norm != WindowNorm::None && self.compat.is_filter_bank() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The {:?} preset applies its own window; the window normalization does not apply",
+                self.compat
+            )));
+        }
+        if self.periodic_window.is_some() && self.compat.is_filter_bank() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "The {:?} preset applies its own window; the window periodicity does not apply",
+                self.compat
+            )));
+        }
+        if let Some(order) = self.lpc_order {
+            if order == 0 || order >= self.n_fft / 2 {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The LPC order must be between 1 and n_fft/2 - 1 ({}), got {}",
+                    (self.n_fft / 2).saturating_sub(1),
+                    order
+                )));
+            }
+            if self.compat != Compat::Librosa {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The LPC envelope is not available with the {:?} preset",
+                    self.compat
+                )));
+            }
+        }
+        if self.weighting != Weighting::Z && self.compat != Compat::Librosa {
+            return Err(SpectrsError::InvalidParams(format!(
+                "{:?}-weighting is not available with the {:?} preset",
+                self.weighting, self.compat
+            )));
+        }
+        if let Some(compression) = self.compression {
+            compression.validate()?;
+            if self.compat.is_filter_bank() {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The {:?} preset already computes log filter banks; compression does not apply",
+                    self.compat
+                )));
+            }
+        }
+        if let Some(amin) = self.amin
+            && !(amin > 0.0 && amin.is_finite())
+        {
+            return Err(SpectrsError::InvalidParams(format!(
+                "amin must be a positive number, got {}",
+                amin
+            )));
+        }
+        if let Some(mel_floor) = self.mel_floor {
+            if !(mel_floor > 0.0 && mel_floor.is_finite()) {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "mel_floor must be a positive number, got {}",
+                    mel_floor
+                )));
+            }
+            if self.n_mels.is_none() {
+                return Err(SpectrsError::InvalidParams(
+                    "mel_floor only applies to filter bank bands (n_mels)".to_string(),
+                ));
+            }
+            if self.compat.is_filter_bank() {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The {:?} preset floors its filter banks with its own log floor",
+                    self.compat
+                )));
+            }
+        }
+        if self.dither < 0.0 {
+            return Err(SpectrsError::InvalidParams(format!(
+                "dither ({}) must be non-negative",
+                self.dither
+            )));
+        }
+        if self.fft_backend != FftBackendType::RustFft {
+            if self.compat != Compat::Librosa {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "The {:?} preset computes its own FFTs; the FFT backend does not apply",
+                    self.compat
+                )));
+            }
+            // Unavailable backends and FFT sizes the backend cannot plan
+            self.fft::<f32>()?;
+        }
+        // Presets reproduce toolkits computing in single precision
+        if self.precision == Precision::F64 && self.compat != Compat::Librosa {
+            return Err(SpectrsError::InvalidParams(format!(
+                "f64 precision is not available with the {:?} preset",
+                self.compat
+            )));
+        }
+        if let Some(n_mels) = self.n_mels {
+            if n_mels == 0 {
+                return Err(SpectrsError::InvalidParams(
+                    "n_mels must be greater than 0".to_string(),
+                ));
+            }
+            // Filter bank presets use their own frame sizes
+            if !self.compat.is_filter_bank() && n_mels > self.n_freq_bins() {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "n_mels ({}) exceeds the number of frequency bins ({}) for n_fft={}",
+                    n_mels,
+                    self.n_freq_bins(),
+                    self.n_fft
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the parameters that depend on the sample rate of the audio being processed
+    /// (frequency limits against Nyquist, mel bands against the bins actually in range)
+    pub fn validate_for_sample_rate(&self, sr: u32) -> Result<()> {
+        self.validate()?;
+
+        let nyquist = sr as f32 / 2.0;
+        let f_min = self.f_min.unwrap_or(0.0);
+        let f_max = self.f_max.unwrap_or(nyquist);
+
+        if f_max > nyquist {
+            return Err(SpectrsError::InvalidParams(format!(
+                "f_max ({} Hz) exceeds the Nyquist frequency ({} Hz) for sample rate {} Hz",
+                f_max, nyquist, sr
+            )));
+        }
+        if f_min >= f_max {
+            return Err(SpectrsError::InvalidParams(format!(
+                "f_min ({} Hz) must be below f_max ({} Hz)",
+                f_min, f_max
+            )));
+        }
+
+        if let Some(n_mels) = self.n_mels
+            && !self.compat.is_filter_bank()
+        {
+            // FFT bins falling inside [f_min, f_max]
+            let bin_width = sr as f32 / self.n_fft as f32;
+            let usable_bins = (0..self.n_freq_bins())
+                .map(|i| i as f32 * bin_width)
+                .filter(|&f| f >= f_min && f <= f_max)
+                .count();
+            if n_mels > usable_bins {
+                return Err(SpectrsError::InvalidParams(format!(
+                    "n_mels ({}) exceeds the {} frequency bins between {} Hz and {} Hz; \
+                     some mel bands would be empty (increase n_fft or reduce n_mels)",
+                    n_mels, usable_bins, f_min, f_max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for `SpectrogramParams`, validating the combination on `build()`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpectrogramParamsBuilder {
+    params: SpectrogramParams,
+    require_power_of_two: bool,
+    sample_rate: Option<u32>,
+}
+
+impl SpectrogramParamsBuilder {
+    pub fn n_fft(mut self, n_fft: usize) -> Self {
+        self.params.n_fft = n_fft;
+        self
+    }
+
+    pub fn hop_length(mut self, hop_length: usize) -> Self {
+        self.params.hop_length = hop_length;
+        self
+    }
+
+    pub fn win_length(mut self, win_length: usize) -> Self {
+        self.params.win_length = win_length;
+        self
+    }
+
+    pub fn center(mut self, center: bool) -> Self {
+        self.params.center = center;
+        self
+    }
+
+    pub fn frame_alignment(mut self, frame_alignment: FrameAlignment) -> Self {
+        self.params.frame_alignment = frame_alignment;
+        self
+    }
+
+    pub fn periodic_window(mut self, periodic: Option<bool>) -> Self {
+        self.params.periodic_window = periodic;
+        self
+    }
+
+    pub fn window_norm(mut self, window_norm: WindowNorm) -> Self {
+        self.params.window_norm = window_norm;
+        self
+    }
+
+    pub fn spectrogram_type(mut self, spectrogram_type: SpectrogramType) -> Self {
+        self.params.spectrogram_type = spectrogram_type;
+        self
+    }
+
+    pub fn n_mels(mut self, n_mels: Option<usize>) -> Self {
+        self.params.n_mels = n_mels;
+        self
+    }
+
+    pub fn f_min(mut self, f_min: Option<f32>) -> Self {
+        self.params.f_min = f_min;
+        self
+    }
+
+    pub fn f_max(mut self, f_max: Option<f32>) -> Self {
+        self.params.f_max = f_max;
+        self
+    }
+
+    pub fn mel_scale(mut self, mel_scale: MelScale) -> Self {
+        self.params.mel_scale = mel_scale;
+        self
+    }
+
+    pub fn filter_bank(mut self, filter_bank: FilterBankType) -> Self {
+        self.params.filter_bank = filter_bank;
+        self
+    }
+
+    pub fn frequency_scale(mut self, frequency_scale: FrequencyScale) -> Self {
+        self.params.frequency_scale = frequency_scale;
+        self
+    }
+
+    pub fn lpc_order(mut self, lpc_order: Option<usize>) -> Self {
+        self.params.lpc_order = lpc_order;
+        self
+    }
+
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.params.weighting = weighting;
+        self
+    }
+
+    pub fn compression(mut self, compression: Option<Compression>) -> Self {
+        self.params.compression = compression;
+        self
+    }
+
+    pub fn amin(mut self, amin: Option<f32>) -> Self {
+        self.params.amin = amin;
+        self
+    }
+
+    pub fn mel_floor(mut self, mel_floor: Option<f32>) -> Self {
+        self.params.mel_floor = mel_floor;
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.params.compat = compat;
+        self
+    }
+
+    pub fn dither(mut self, dither: f32) -> Self {
+        self.params.dither = dither;
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.params.deterministic = deterministic;
+        self
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.params.precision = precision;
+        self
+    }
+
+    pub fn fft_backend(mut self, fft_backend: FftBackendType) -> Self {
+        self.params.fft_backend = fft_backend;
+        self
+    }
+
+    /// Reject FFT sizes that are not powers of two (the fastest sizes for the FFT)
+    pub fn require_power_of_two(mut self, require: bool) -> Self {
+        self.require_power_of_two = require;
+        self
+    }
+
+    /// Sample rate of the audio, if already known, enabling the Nyquist-dependent checks
+    pub fn sample_rate(mut self, sr: u32) -> Self {
+        self.sample_rate = Some(sr);
+        self
+    }
+
+    /// Validate and return the parameters
+    pub fn build(self) -> Result<SpectrogramParams> {
+        if self.require_power_of_two && !self.params.n_fft.is_power_of_two() {
+            return Err(SpectrsError::InvalidParams(format!(
+                "n_fft ({}) must be a power of two (e.g. {})",
+                self.params.n_fft,
+                self.params.n_fft.next_power_of_two()
+            )));
+        }
+        match self.sample_rate {
+            Some(sr) => self.params.validate_for_sample_rate(sr)?,
+            None => self.params.validate()?,
+        }
+        Ok(self.params)
+    }
+}
@@ -0,0 +1,414 @@
+// Reusable read → resample → STFT → LPC envelope → weighting → mel → compression pipeline. The builder gathers the settings once
+// (filling in librosa's defaults for the window and the hop from the FFT size), validates them
+// on `build()`, and the pipeline then turns any number of files or sample buffers into
+// spectrograms, dispatching to the Kaldi/HTK filter banks or the torchaudio spectrogram for
+// the compatibility presets and computing in double precision when asked to.
+
+use crate::error::{Result, SpectrsError};
+use crate::features::lpc::{lpc_envelope, par_lpc_envelope};
+#[cfg(feature = "fs")]
+use crate::io::audio::read_audio_file_mono;
+use crate::io::audio::{ResampleQuality, resample_with_quality};
+use crate::spectrogram::compression::{
+    Compression, compress_spectrogram_with_amin, floor_spectrogram,
+    par_compress_spectrogram_with_amin,
+};
+use crate::spectrogram::fbank::{Compat, FbankOptions, compute_fbank, par_compute_fbank};
+use crate::spectrogram::fft::FftBackendType;
+use crate::spectrogram::filterbank::{FilterBankType, FrequencyScale, filter_bank};
+use crate::spectrogram::mel::{MelScale, apply_filter_bank, par_apply_filter_bank};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::stft::{
+    Float, FrameAlignment, Precision, SpectrogramType, WindowNorm, compute_spectrogram_with_fft,
+    librosa_framed_signal, par_compute_spectrogram_with_fft,
+};
+use crate::spectrogram::torchaudio::{par_torchaudio_spectrogram, torchaudio_spectrogram};
+use crate::spectrogram::weighting::{Weighting, apply_weighting, par_apply_weighting};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Spectrograms of files or samples computed with fixed settings, e.g.
+/// `SpectrogramPipeline::builder().sample_rate(16000).n_fft(512).hop_length(160).mel(80).db(true).build()?`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrogramPipeline {
+    params: SpectrogramParams,
+    sample_rate: Option<u32>,
+    resample_quality: ResampleQuality,
+    parallel: bool,
+}
+
+impl SpectrogramPipeline {
+    /// Start building a pipeline from the default parameters (see `SpectrogramParams`)
+    pub fn builder() -> SpectrogramPipelineBuilder {
+        SpectrogramPipelineBuilder::default()
+    }
+
+    /// Parameters of the spectrograms
+    pub fn params(&self) -> &SpectrogramParams {
+        &self.params
+    }
+
+    /// Sample rate the audio is resampled to, if any
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    /// Spectrogram of an audio file, read as mono and resampled to the sample rate of the
+    /// pipeline (if set)
+    #[cfg(feature = "fs")]
+    pub fn process_file(&self, path: &Path) -> Result<Vec<Vec<f32>>> {
+        let (samples, sr) = read_audio_file_mono(path)?;
+        self.process_samples_at(&samples, sr)
+            .map_err(|e| e.context(path.display()))
+    }
+
+    /// Spectrogram of mono samples at the sample rate of the pipeline, which must be set
+    pub fn process_samples(&self, samples: &[f32]) -> Result<Vec<Vec<f32>>> {
+        let sr = self.sample_rate.ok_or_else(|| {
+            SpectrsError::InvalidParams(
+                "The pipeline has no sample rate: give the one of the samples with \
+                 process_samples_at"
+                    .to_string(),
+            )
+        })?;
+        self.process_samples_at(samples, sr)
+    }
+
+    /// Spectrogram of mono samples at `sr`, resampled to the sample rate of the pipeline (if
+    /// set and different)
+    pub fn process_samples_at(&self, samples: &[f32], sr: u32) -> Result<Vec<Vec<f32>>> {
+        if sr == 0 {
+            return Err(SpectrsError::InvalidParams(
+                "Sample rate must be greater than 0".to_string(),
+            ));
+        }
+        let resampled;
+        let (samples, sr) = match self.sample_rate {
+            Some(target_sr) if target_sr != sr => {
+                resampled =
+                    resample_with_quality(samples.to_vec(), sr, target_sr, self.resample_quality)?;
+                (resampled.as_slice(), target_sr)
+            }
+            _ => (samples, sr),
+        };
+        self.params.validate_for_sample_rate(sr)?;
+
+        let spec = match FbankOptions::from_params(&self.params, sr) {
+            Some(options) if self.parallel => par_compute_fbank(samples, &options)?,
+            Some(options) => compute_fbank(samples, &options)?,
+            None if self.params.compat != Compat::Librosa => {
+                let mut spec = if self.parallel {
+                    par_torchaudio_spectrogram(samples, sr, &self.params)?
+                } else {
+                    torchaudio_spectrogram(samples, sr, &self.params)?
+                };
+                self.compress(&mut spec);
+                spec
+            }
+            None => match self.params.precision {
+                Precision::F32 => self.spectrogram(samples, sr),
+                Precision::F64 => {
+                    let samples: Vec<f64> = samples.iter().map(|&s| s as f64).collect();
+                    self.spectrogram(&samples, sr)
+                        .iter()
+                        .map(|row| row.iter().map(|&v| v as f32).collect())
+                        .collect()
+                }
+            },
+        };
+        Ok(spec)
+    }
+
+    /// Linear, mel or gammatone spectrogram of samples at `sr` (already at the sample rate of the
+    /// pipeline), weighted and compressed if requested, in the precision of the samples. The compatibility
+    /// presets are not applied.
+    pub fn spectrogram<T: Float>(&self, samples: &[T], sr: u32) -> Vec<Vec<T>> {
+        let params = &self.params;
+        let compute = if self.parallel {
+            par_compute_spectrogram_with_fft
+        } else {
+            compute_spectrogram_with_fft
+        };
+        let fft = params
+            .fft::<T>()
+            .expect("FFT backend checked when the pipeline was built");
+        let window = params.window();
+        let mut spec = match params.frame_alignment {
+            FrameAlignment::Spectrs => compute(
+                samples,
+                &*fft,
+                params.hop_length,
+                &window,
+                params.center,
+                params.spectrogram_type,
+            ),
+            FrameAlignment::Librosa => match librosa_framed_signal(
+                samples,
+                params.n_fft,
+                params.hop_length,
+                params.win_length,
+                params.center,
+            ) {
+                Some(signal) => compute(
+                    &signal,
+                    &*fft,
+                    params.hop_length,
+                    &window,
+                    true,
+                    params.spectrogram_type,
+                ),
+                None => vec![Vec::new(); params.n_freq_bins()],
+            },
+        };
+        if let Some(order) = params.lpc_order {
+            let envelope = if self.parallel {
+                par_lpc_envelope
+            } else {
+                lpc_envelope
+            };
+            spec = envelope(&spec, params.spectrogram_type, params.n_fft, order)
+                .expect("LPC order checked when the pipeline was built");
+        }
+        if let Some(gains) = params
+            .weighting
+            .bin_gains(params.n_fft, sr, params.spectrogram_type)
+        {
+            if self.parallel {
+                par_apply_weighting(&mut spec, &gains)
+            } else {
+                apply_weighting(&mut spec, &gains)
+            }
+        }
+        if let Some(filters) = filter_bank(params, sr) {
+            spec = if self.parallel {
+                par_apply_filter_bank(&spec, &filters)
+            } else {
+                apply_filter_bank(&spec, &filters)
+            };
+            if let Some(mel_floor) = params.mel_floor {
+                floor_spectrogram(&mut spec, mel_floor);
+            }
+        }
+        self.compress(&mut spec);
+        spec
+    }
+
+    /// Apply the compression of the parameters, if any
+    fn compress<T: Float>(&self, spec: &mut [Vec<T>]) {
+        let Some(compression) = self.params.compression else {
+            return;
+        };
+        let (spectrogram_type, amin) = (self.params.spectrogram_type, self.params.amin());
+        if self.parallel {
+            par_compress_spectrogram_with_amin(spec, compression, spectrogram_type, amin)
+        } else {
+            compress_spectrogram_with_amin(spec, compression, spectrogram_type, amin)
+        }
+    }
+}
+
+/// Builder for `SpectrogramPipeline`, validating the settings on `build()`
+#[derive(Debug, Clone, Copy)]
+pub struct SpectrogramPipelineBuilder {
+    params: SpectrogramParams,
+    win_length: Option<usize>,
+    hop_length: Option<usize>,
+    sample_rate: Option<u32>,
+    resample_quality: ResampleQuality,
+    parallel: bool,
+}
+
+impl Default for SpectrogramPipelineBuilder {
+    fn default() -> Self {
+        Self {
+            params: SpectrogramParams::default(),
+            win_length: None,
+            hop_length: None,
+            sample_rate: None,
+            resample_quality: ResampleQuality::default(),
+            parallel: true,
+        }
+    }
+}
+
+impl SpectrogramPipelineBuilder {
+    /// Start from existing parameters, keeping their window and hop lengths
+    pub fn params(mut self, params: SpectrogramParams) -> Self {
+        self.params = params;
+        self.win_length = Some(params.win_length);
+        self.hop_length = Some(params.hop_length);
+        self
+    }
+
+    /// Resample the audio to this rate (by default, audio is processed at its own rate)
+    pub fn sample_rate(mut self, sr: u32) -> Self {
+        self.sample_rate = Some(sr);
+        self
+    }
+
+    pub fn resample_quality(mut self, quality: ResampleQuality) -> Self {
+        self.resample_quality = quality;
+        self
+    }
+
+    /// FFT size. The window defaults to the FFT size and the hop to a quarter of the window
+    pub fn n_fft(mut self, n_fft: usize) -> Self {
+        self.params.n_fft = n_fft;
+        self
+    }
+
+    pub fn hop_length(mut self, hop_length: usize) -> Self {
+        self.hop_length = Some(hop_length);
+        self
+    }
+
+    pub fn win_length(mut self, win_length: usize) -> Self {
+        self.win_length = Some(win_length);
+        self
+    }
+
+    pub fn center(mut self, center: bool) -> Self {
+        self.params.center = center;
+        self
+    }
+
+    /// Frame the signal as librosa does (see `FrameAlignment`), so that the number of frames
+    /// is 1 + n / hop with `center` and 1 + (n - n_fft) / hop without
+    pub fn frame_alignment(mut self, frame_alignment: FrameAlignment) -> Self {
+        self.params.frame_alignment = frame_alignment;
+        self
+    }
+
+    /// Periodic (as librosa and torchaudio) or symmetric Hann window; by default periodic with
+    /// librosa's frame alignment and the torchaudio preset only
+    pub fn periodic_window(mut self, periodic: bool) -> Self {
+        self.params.periodic_window = Some(periodic);
+        self
+    }
+
+    /// Normalize the window (see `WindowNorm`), e.g. by its sum to match the magnitudes of
+    /// scipy.signal.stft
+    pub fn window_norm(mut self, window_norm: WindowNorm) -> Self {
+        self.params.window_norm = window_norm;
+        self
+    }
+
+    pub fn spectrogram_type(mut self, spectrogram_type: SpectrogramType) -> Self {
+        self.params.spectrogram_type = spectrogram_type;
+        self
+    }
+
+    /// Project on `n_mels` mel bands
+    pub fn mel(mut self, n_mels: usize) -> Self {
+        self.params.n_mels = Some(n_mels);
+        self
+    }
+
+    /// Filter bank of the `mel` bands (mel by default)
+    pub fn filter_bank(mut self, filter_bank: FilterBankType) -> Self {
+        self.params.filter_bank = filter_bank;
+        self
+    }
+
+    /// Frequency scale of the triangular `mel` bands (mel by default)
+    pub fn frequency_scale(mut self, frequency_scale: FrequencyScale) -> Self {
+        self.params.frequency_scale = frequency_scale;
+        self
+    }
+
+    pub fn f_min(mut self, f_min: f32) -> Self {
+        self.params.f_min = Some(f_min);
+        self
+    }
+
+    pub fn f_max(mut self, f_max: f32) -> Self {
+        self.params.f_max = Some(f_max);
+        self
+    }
+
+    pub fn mel_scale(mut self, mel_scale: MelScale) -> Self {
+        self.params.mel_scale = mel_scale;
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.params.compat = compat;
+        self
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.params.precision = precision;
+        self
+    }
+
+    /// Compute the FFTs with another library (see `FftBackendType`), checked on `build()`
+    pub fn fft_backend(mut self, fft_backend: FftBackendType) -> Self {
+        self.params.fft_backend = fft_backend;
+        self
+    }
+
+    /// Convert the values to dB (10·log10 of powers, 20·log10 of magnitudes), a shorthand for
+    /// `compression(Some(Compression::Db { top_db: None }))`
+    pub fn db(mut self, db: bool) -> Self {
+        self.params.compression = db.then_some(Compression::Db { top_db: None });
+        self
+    }
+
+    /// Replace every frame of the STFT by the envelope of its all-pole model of this order
+    /// (e.g. 2 + sr/1000 to follow the formants of speech), before the weighting and the filter
+    /// bank
+    pub fn lpc_order(mut self, lpc_order: Option<usize>) -> Self {
+        self.params.lpc_order = lpc_order;
+        self
+    }
+
+    /// Weight the FFT bins with the A or C curve before the filter bank (Z, no weighting, by
+    /// default)
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.params.weighting = weighting;
+        self
+    }
+
+    /// Compress the values to a log scale (see `Compression`)
+    pub fn compression(mut self, compression: Option<Compression>) -> Self {
+        self.params.compression = compression;
+        self
+    }
+
+    /// Floor values at `amin` before the log or dB compression takes their logarithm (1e-10,
+    /// as librosa, by default)
+    pub fn amin(mut self, amin: f32) -> Self {
+        self.params.amin = Some(amin);
+        self
+    }
+
+    /// Raise the filter bank bands to at least `mel_floor`, so that silent frames stay
+    /// positive
+    pub fn mel_floor(mut self, mel_floor: f32) -> Self {
+        self.params.mel_floor = Some(mel_floor);
+        self
+    }
+
+    /// Parallelize over frames and mel bands with rayon (the default)
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Validate the settings and return the pipeline
+    pub fn build(self) -> Result<SpectrogramPipeline> {
+        let mut params = self.params;
+        params.win_length = self.win_length.unwrap_or(params.n_fft);
+        params.hop_length = self.hop_length.unwrap_or((params.win_length / 4).max(1));
+        match self.sample_rate {
+            Some(sr) => params.validate_for_sample_rate(sr)?,
+            None => params.validate()?,
+        }
+        Ok(SpectrogramPipeline {
+            params,
+            sample_rate: self.sample_rate,
+            resample_quality: self.resample_quality,
+            parallel: self.parallel && !params.deterministic,
+        })
+    }
+}
@@ -0,0 +1,196 @@
+// Average pooling used to shrink spectrograms to a maximum image size. Frames (or bins) are
+// split into contiguous groups of (almost) equal size: item i of n goes to group i * size / n.
+// `reduce_time` instead pools a fixed number of frames per column (by mean or max), and
+// `fit_frames` brings a spectrogram to an exact number of frames for fixed-shape datasets.
+
+use crate::spectrogram::stft::{Float, constant};
+
+/// Group of item `index` when `n_items` are pooled into `size` groups
+fn group_of(index: usize, n_items: usize, size: usize) -> usize {
+    if n_items <= size {
+        index
+    } else {
+        (index * size / n_items).min(size - 1)
+    }
+}
+
+/// Average adjacent frames so the [freq][time] spectrogram has at most `width` frames
+pub fn pool_time(spectrogram: &[Vec<f32>], width: usize) -> Vec<Vec<f32>> {
+    let n_frames = spectrogram.first().map_or(0, |row| row.len());
+    if width == 0 || n_frames <= width {
+        return spectrogram.to_vec();
+    }
+    let mut pooler = FramePooler::new(n_frames, width, spectrogram.len());
+    let mut frame = vec![0.0f32; spectrogram.len()];
+    for t in 0..n_frames {
+        for (value, row) in frame.iter_mut().zip(spectrogram) {
+            *value = row[t];
+        }
+        pooler.push(&frame);
+    }
+    pooler.finish()
+}
+
+/// Average adjacent frequency bins so the [freq][time] spectrogram has at most `height` rows
+pub fn pool_freq(spectrogram: &[Vec<f32>], height: usize) -> Vec<Vec<f32>> {
+    let n_bins = spectrogram.len();
+    if height == 0 || n_bins <= height {
+        return spectrogram.to_vec();
+    }
+    let n_frames = spectrogram[0].len();
+    let mut pooled = vec![vec![0.0f32; n_frames]; height];
+    let mut counts = vec![0usize; height];
+    for (k, row) in spectrogram.iter().enumerate() {
+        let group = group_of(k, n_bins, height);
+        counts[group] += 1;
+        for (acc, &v) in pooled[group].iter_mut().zip(row) {
+            *acc += v;
+        }
+    }
+    for (row, &count) in pooled.iter_mut().zip(&counts) {
+        row.iter_mut().for_each(|v| *v /= count as f32);
+    }
+    pooled
+}
+
+/// How a group of frames is reduced to one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PoolMode {
+    /// Average of the group
+    Mean,
+    /// Maximum of the group, which keeps short events visible
+    Max,
+}
+
+/// Reduce every `factor` consecutive frames of the [freq][time] spectrogram to one (the last
+/// group may be shorter), for compact overviews of long recordings
+pub fn reduce_time(spectrogram: &[Vec<f32>], factor: usize, mode: PoolMode) -> Vec<Vec<f32>> {
+    if factor <= 1 {
+        return spectrogram.to_vec();
+    }
+    spectrogram
+        .iter()
+        .map(|row| {
+            row.chunks(factor)
+                .map(|group| match mode {
+                    PoolMode::Mean => group.iter().sum::<f32>() / group.len() as f32,
+                    PoolMode::Max => group.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// How a spectrogram is brought to a fixed number of frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum FitMode {
+    /// Pad after the last frame, or drop the frames beyond the count
+    #[default]
+    Pad,
+    /// Interpolate linearly along time, keeping the first and last frames
+    Resample,
+}
+
+/// Bring the [freq][time] spectrogram to exactly `n_frames` frames, padding with `pad` (e.g.
+/// zero for linear values, i.e. silence)
+pub fn fit_frames<T: Float>(
+    spectrogram: &[Vec<T>],
+    n_frames: usize,
+    mode: FitMode,
+    pad: T,
+) -> Vec<Vec<T>> {
+    spectrogram
+        .iter()
+        .map(|row| match mode {
+            _ if row.is_empty() => vec![pad; n_frames],
+            FitMode::Pad => {
+                let mut fitted = row[..row.len().min(n_frames)].to_vec();
+                fitted.resize(n_frames, pad);
+                fitted
+            }
+            FitMode::Resample => (0..n_frames)
+                .map(|t| {
+                    let position = if n_frames > 1 {
+                        t as f64 * (row.len() - 1) as f64 / (n_frames - 1) as f64
+                    } else {
+                        (row.len() - 1) as f64 / 2.0
+                    };
+                    let left = position.floor() as usize;
+                    let right = (left + 1).min(row.len() - 1);
+                    let weight: T = constant(position - left as f64);
+                    row[left] + weight * (row[right] - row[left])
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Average-pools frames on the fly as they are produced (e.g. by a `StreamingStft`), so that a
+/// long recording can be reduced to `width` frames without ever holding the full-resolution
+/// spectrogram. `n_frames` is the number of frames that will be pushed; should more arrive,
+/// they are averaged into the last column.
+pub struct FramePooler {
+    n_frames: usize,
+    width: usize,
+    /// Running sum of the current group
+    acc: Vec<f32>,
+    /// Number of frames in the current group
+    count: usize,
+    /// Group of the frames being accumulated
+    group: usize,
+    /// Number of frames pushed so far
+    pushed: usize,
+    /// Pooled spectrogram in [freq][time] format
+    output: Vec<Vec<f32>>,
+}
+
+impl FramePooler {
+    pub fn new(n_frames: usize, width: usize, n_bins: usize) -> Self {
+        Self {
+            n_frames,
+            width: width.max(1),
+            acc: vec![0.0; n_bins],
+            count: 0,
+            group: 0,
+            pushed: 0,
+            output: vec![Vec::new(); n_bins],
+        }
+    }
+
+    /// Add the next frame (one value per frequency bin)
+    pub fn push(&mut self, frame: &[f32]) {
+        let group = group_of(
+            self.pushed.min(self.n_frames.saturating_sub(1)),
+            self.n_frames,
+            self.width,
+        );
+        if group != self.group && self.count > 0 {
+            self.flush();
+        }
+        self.group = group;
+        for (acc, &v) in self.acc.iter_mut().zip(frame) {
+            *acc += v;
+        }
+        self.count += 1;
+        self.pushed += 1;
+    }
+
+    /// Return the pooled spectrogram in [freq][time] format
+    pub fn finish(mut self) -> Vec<Vec<f32>> {
+        if self.count > 0 {
+            self.flush();
+        }
+        self.output
+    }
+
+    /// Append the average of the current group as a new column
+    fn flush(&mut self) {
+        for (row, acc) in self.output.iter_mut().zip(self.acc.iter_mut()) {
+            row.push(*acc / self.count as f32);
+            *acc = 0.0;
+        }
+        self.count = 0;
+    }
+}
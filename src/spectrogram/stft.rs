@@ -1,102 +1,350 @@
 //use clap::ValueEnum;
+use crate::cancel::CancellationToken;
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::fbank::Compat;
+use crate::spectrogram::fft::{FftBackend, ForwardFft, RustFftBackend};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::weighting::Weighting;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use rustfft::{FftPlanner, num_complex::Complex};
-use std::f32::consts::PI;
+use rustfft::{FftNum, num_complex::Complex};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Floating-point type the STFT and the mel projection are computed in: `f32` (the default
+/// everywhere) or `f64` for double-precision analyses
+pub trait Float:
+    FftNum + rustfft::num_traits::Float + rustfft::num_traits::NumAssign + std::iter::Sum
+{
+    /// Plan a forward FFT of `n` points in this precision with the backend
+    fn plan_fft(backend: &dyn FftBackend, n: usize) -> Result<Arc<dyn ForwardFft<Self>>>;
+}
+
+impl Float for f32 {
+    fn plan_fft(backend: &dyn FftBackend, n: usize) -> Result<Arc<dyn ForwardFft<Self>>> {
+        backend.plan_f32(n)
+    }
+}
+
+impl Float for f64 {
+    fn plan_fft(backend: &dyn FftBackend, n: usize) -> Result<Arc<dyn ForwardFft<Self>>> {
+        backend.plan_f64(n)
+    }
+}
+
+/// Convert a constant to the computation type
+pub(crate) fn constant<T: Float>(value: f64) -> T {
+    T::from_f64(value).expect("finite constant")
+}
 
 // Different spectrogram types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
 pub enum SpectrogramType {
     Magnitude,
     Power,
 }
 
+/// Floating-point precision of the computation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Single precision (`f32`)
+    #[default]
+    F32,
+    /// Double precision (`f64`)
+    F64,
+}
+
+/// How frames are laid over the signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum FrameAlignment {
+    /// Frames start every hop from the first sample, as many as hold a whole window
+    /// ((n - win_length) / hop + 1); `center` centers the window inside the FFT frame
+    #[default]
+    Spectrs,
+    /// librosa's framing: with `center` the signal is padded with n_fft/2 zeros on both sides
+    /// and frame t is centered on sample t * hop (1 + n / hop frames), otherwise frames of
+    /// n_fft samples start every hop (1 + (n - n_fft) / hop frames). The window is always
+    /// centered inside the FFT frame.
+    Librosa,
+}
+
+/// Normalization of the window, scaling the values of the STFT so that absolute magnitudes
+/// match other conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum WindowNorm {
+    /// Unnormalized window (librosa, and torch.stft by default)
+    #[default]
+    None,
+    /// Divide by the sum of the window, so that a sinusoid of amplitude A peaks at A / 2 in
+    /// magnitude (the default "spectrum" scaling of scipy.signal.stft)
+    Sum,
+    /// Divide by the square root of the sum of the squared window, so that the power of white
+    /// noise does not depend on the window (torchaudio's Spectrogram with normalized=True)
+    Energy,
+    /// Divide by the square root of n_fft (torch.stft with normalized=True)
+    Length,
+}
+
+impl WindowNorm {
+    /// Factor the window is multiplied by (1 without normalization)
+    pub fn scale<T: Float>(self, window: &[T], n_fft: usize) -> f64 {
+        let sum = |f: fn(f64) -> f64| -> f64 {
+            window.iter().map(|&w| f(w.to_f64().unwrap_or(0.0))).sum()
+        };
+        match self {
+            WindowNorm::None => 1.0,
+            WindowNorm::Sum => 1.0 / sum(|w| w),
+            WindowNorm::Energy => 1.0 / sum(|w| w * w).sqrt(),
+            WindowNorm::Length => 1.0 / (n_fft as f64).sqrt(),
+        }
+    }
+}
+
+/// Number of frames of librosa's STFT of `n` samples (see `FrameAlignment::Librosa`), zero if
+/// the signal is shorter than n_fft without `center`
+pub fn librosa_n_frames(n: usize, n_fft: usize, hop_length: usize, center: bool) -> usize {
+    let padded = if center { n + 2 * (n_fft / 2) } else { n };
+    match padded.checked_sub(n_fft) {
+        Some(extra) => extra / hop_length + 1,
+        None => 0,
+    }
+}
+
+/// Samples whose STFT with the window centered inside the FFT frame (`center` set) has the
+/// frames of librosa's: the signal padded with n_fft/2 zeros on both sides if `center`, from
+/// the offset of the window inside the FFT frame to the end of the last whole frame. None if
+/// there is no whole frame.
+pub fn librosa_framed_signal<T: Float>(
+    audio: &[T],
+    n_fft: usize,
+    hop_length: usize,
+    win_length: usize,
+    center: bool,
+) -> Option<Vec<T>> {
+    let n_frames = librosa_n_frames(audio.len(), n_fft, hop_length, center);
+    if n_frames == 0 {
+        return None;
+    }
+    let pad = if center { n_fft / 2 } else { 0 };
+    let offset = (n_fft - win_length) / 2;
+    // Frame t covers [t * hop + offset, t * hop + offset + win_length) of the padded signal
+    let (start, end) = (offset, (n_frames - 1) * hop_length + offset + win_length);
+    Some(
+        (start..end)
+            .map(|i| match i.checked_sub(pad) {
+                Some(i) if i < audio.len() => audio[i],
+                _ => T::zero(),
+            })
+            .collect(),
+    )
+}
+
 /// Create Hann window, see e.g. https://en.wikipedia.org/wiki/Hann_function
-fn create_hann_window(length: usize) -> Vec<f32> {
+/// A symmetric window (dividing by `length - 1`) ends with a zero like it starts; a periodic
+/// one is the first `length` points of a `length + 1` symmetric window, as returned by
+/// `scipy.signal.get_window("hann", length)` (used by librosa) and `torch.hann_window`, and
+/// leaks a little less between the bins of the FFT.
+pub fn create_hann_window<T: Float>(length: usize, periodic: bool) -> Vec<T> {
+    // A single-sample symmetric window is flat (as scipy's), not 0 / 0
+    if !periodic && length < 2 {
+        return vec![T::one(); length];
+    }
+    let half: T = constant(0.5);
+    let two_pi: T = constant::<T>(2.0) * constant(PI);
+    let denominator: T = match periodic {
+        true => constant(length as f64),
+        false => constant((length - 1) as f64),
+    };
     (0..length)
-        .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f32 / (length - 1) as f32).cos()))
+        .map(|i| half * (T::one() - (two_pi * constant(i as f64) / denominator).cos()))
         .collect()
 }
 
-/// Compute the spectrogram (single-threaded)
+/// Compute the hop length corresponding to an overlap fraction (in [0, 1)) between
+/// successive windows of win_length samples, e.g. 0.75 overlap of 2048 samples gives 512.
+/// The hop is always at least one sample.
+pub fn hop_length_from_overlap(win_length: usize, overlap: f32) -> usize {
+    ((win_length as f32 * (1.0 - overlap)).round() as usize).max(1)
+}
+
+/// Number of samples lasting `ms` milliseconds at `sr` (rounded, at least one), e.g. 25 ms at
+/// 16 kHz gives 400
+pub fn samples_from_ms(ms: f32, sr: u32) -> usize {
+    ((ms as f64 * sr as f64 / 1000.0).round() as usize).max(1)
+}
+
+/// Compute the spectrogram (single-threaded), in the precision of the samples
 /// n_samples: number of samples in each Fast Fourier Transform (FFT) window
 /// hop_length: stride between windows, i.e. number of samples between successive FFT frames
 /// win_length: number of samples in the window function applied before FFT
 /// Pad with zeros if needed. This is because usually win_length < n_samples
 /// and the missing are just zeros (in this case complex zeros)
-pub fn compute_spectrogram(
-    audio: &[f32],
+pub fn compute_spectrogram<T: Float>(
+    audio: &[T],
     n_samples: usize,
     hop_length: usize,
     win_length: usize,
     center: bool,
     spectrogram_type: SpectrogramType,
-) -> Vec<Vec<f32>> {
-    // Set-up FFT
-    let mut planner = FftPlanner::<f32>::new();
-    let fft = planner.plan_fft_forward(n_samples);
-
-    // Choose the transformation function to create the spectrogram
-    let transform_fn: fn(&Complex<f32>) -> f32 = match spectrogram_type {
-        SpectrogramType::Magnitude => |c| c.norm(),
-        SpectrogramType::Power => |c| c.norm_sqr(),
-    };
+) -> Vec<Vec<T>> {
+    compute_spectrogram_with_window(
+        audio,
+        n_samples,
+        hop_length,
+        &create_hann_window(win_length, false),
+        center,
+        spectrogram_type,
+    )
+}
 
-    // Create (Hann) window
-    let window = create_hann_window(win_length);
+/// Number of frames transformed together, with a single FFT call over a contiguous buffer: the
+/// unit of work of the parallel STFT, large enough to amortize the scheduling of rayon tasks
+/// and to keep the buffers in cache for small n_fft
+pub const FRAMES_PER_BLOCK: usize = 64;
 
-    // Determine the number of frames
-    let n_frames = (audio.len().saturating_sub(win_length)) / hop_length + 1;
+/// Compute the spectrogram (single-threaded) like `compute_spectrogram`, with the given
+/// window (e.g. a periodic Hann window) of win_length = `window.len()` samples
+pub fn compute_spectrogram_with_window<T: Float>(
+    audio: &[T],
+    n_samples: usize,
+    hop_length: usize,
+    window: &[T],
+    center: bool,
+    spectrogram_type: SpectrogramType,
+) -> Vec<Vec<T>> {
+    compute_spectrogram_with_fft(
+        audio,
+        &*rustfft_forward(n_samples),
+        hop_length,
+        window,
+        center,
+        spectrogram_type,
+    )
+}
 
-    // Determine number of frequency bins
-    let n_freq_bins = n_samples / 2 + 1;
+/// Compute the spectrogram (single-threaded) like `compute_spectrogram_with_window`, with an
+/// FFT of n_samples = `fft.len()` points planned by any backend (see `FftBackendType`)
+pub fn compute_spectrogram_with_fft<T: Float>(
+    audio: &[T],
+    fft: &dyn ForwardFft<T>,
+    hop_length: usize,
+    window: &[T],
+    center: bool,
+    spectrogram_type: SpectrogramType,
+) -> Vec<Vec<T>> {
+    let blocks = FrameBlocks::new(audio, fft, hop_length, window, center, spectrogram_type);
+    compute_blocks(&blocks, None)
+}
 
+/// Sequential loop over the blocks of frames, stopping once cancelled
+fn compute_blocks<T: Float>(
+    blocks: &FrameBlocks<T>,
+    cancel: Option<&CancellationToken>,
+) -> Vec<Vec<T>> {
     // Directly create spectrogram in [freq][time] format (no transpose needed)
-    let mut spectrogram = vec![vec![0.0f32; n_frames]; n_freq_bins];
-
-    // Sequential loop over frames
-    for frame_idx in 0..n_frames {
-        // Determine start and end sample for each frame
-        let start = frame_idx * hop_length;
-        let end = (start + win_length).clamp(0, audio.len());
+    let mut spectrogram = vec![vec![T::zero(); blocks.n_frames]; blocks.n_freq_bins];
 
-        // Skip if start is beyond the end of the file
-        if start > audio.len() {
-            continue;
+    // Sequential loop over blocks of frames, reusing the buffer
+    let mut buffer = Vec::new();
+    for first in (0..blocks.n_frames).step_by(FRAMES_PER_BLOCK) {
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            break;
         }
+        let frames = first..(first + FRAMES_PER_BLOCK).min(blocks.n_frames);
+        blocks.process(frames, &mut buffer, |frame_idx, bins| {
+            for (freq_row, value) in spectrogram.iter_mut().zip(bins) {
+                freq_row[frame_idx] = value;
+            }
+        });
+    }
 
-        // Init buffer to be filled with windowed audio
-        let mut frame = vec![Complex::<f32>::new(0.0, 0.0); n_samples];
+    spectrogram
+}
 
-        // Add an offset if the window needs to be centered
-        let centering_offset = if center {
-            (n_samples - win_length) / 2_usize
-        } else {
-            0_usize
-        };
+/// Compute the linear spectrogram of `params` (single-threaded) into caller-allocated storage,
+/// so that repeated analyses (e.g. of a sliding window) reuse the same output buffer. The values
+/// are written row-major in [freq][time] order: bin `k` of frame `t` is at
+/// `out[k * n_frames + t]`, with `params.n_freq_bins()` rows of `n_frames =
+/// params.n_frames(audio.len())` values. Returns `n_frames`; `out` may be longer, the values
+/// after the spectrogram being left untouched. The window, frame alignment and FFT backend of
+/// the parameters are applied; filter banks, weighting, compression and the compatibility
+/// presets are not, and are rejected.
+pub fn compute_spectrogram_into<T: Float>(
+    audio: &[T],
+    params: &SpectrogramParams,
+    out: &mut [T],
+) -> Result<usize> {
+    let unsupported = [
+        ("filter banks (n_mels)", params.n_mels.is_some()),
+        ("weighting", params.weighting != Weighting::Z),
+        ("compression", params.compression.is_some()),
+        ("compatibility presets", params.compat != Compat::Librosa),
+    ];
+    if let Some((what, _)) = unsupported.iter().find(|(_, given)| *given) {
+        return Err(SpectrsError::InvalidParams(format!(
+            "compute_spectrogram_into computes linear spectrograms; {} are not applied",
+            what
+        )));
+    }
+    let (n_freq_bins, n_frames) = (params.n_freq_bins(), params.n_frames(audio.len()));
+    if out.len() < n_freq_bins * n_frames {
+        return Err(SpectrsError::InvalidParams(format!(
+            "The output buffer holds {} values, the spectrogram {} ({} bins x {} frames)",
+            out.len(),
+            n_freq_bins * n_frames,
+            n_freq_bins,
+            n_frames
+        )));
+    }
 
-        // Window & copy into complex buffer
-        let src = &audio[start..end];
-        let win = &window[..src.len()];
-        for (dst, (&s, &w)) in frame
-            .iter_mut()
-            .skip(centering_offset)
-            .zip(src.iter().zip(win.iter()))
-        {
-            dst.re = s * w; // Convolve audio and window
-            dst.im = 0.0; // No imaginary part
+    // librosa's framing is the STFT of the padded and trimmed signal
+    let framed;
+    let signal = match params.frame_alignment {
+        FrameAlignment::Spectrs => audio,
+        FrameAlignment::Librosa => {
+            framed = librosa_framed_signal(
+                audio,
+                params.n_fft,
+                params.hop_length,
+                params.win_length,
+                params.center,
+            );
+            match &framed {
+                Some(signal) => signal,
+                None => return Ok(0),
+            }
         }
+    };
+    let fft = params.fft::<T>()?;
+    let window = params.window::<T>();
+    let center = params.center || params.frame_alignment == FrameAlignment::Librosa;
+    let blocks = FrameBlocks::new(
+        signal,
+        &*fft,
+        params.hop_length,
+        &window,
+        center,
+        params.spectrogram_type,
+    );
 
-        // Run FFT
-        fft.process(&mut frame);
-
-        // Store positive freqs only and apply transformation fn
-        for (freq_idx, c) in frame.iter().take(n_freq_bins).enumerate() {
-            spectrogram[freq_idx][frame_idx] = transform_fn(c);
-        }
+    let mut buffer = Vec::new();
+    for first in (0..n_frames).step_by(FRAMES_PER_BLOCK) {
+        let frames = first..(first + FRAMES_PER_BLOCK).min(n_frames);
+        blocks.process(frames, &mut buffer, |frame_idx, bins| {
+            for (k, value) in bins.enumerate() {
+                out[k * n_frames + frame_idx] = value;
+            }
+        });
     }
-
-    spectrogram
+    Ok(n_frames)
 }
 
 /// Compute the spectrogram (parallelized with rayon)
@@ -105,89 +353,224 @@ pub fn compute_spectrogram(
 /// win_length: number of samples in the window function applied before FFT
 /// Pad with zeros if needed. This is because usually win_length < n_samples
 /// and the missing are just zeros (in this case complex zeros)
-pub fn par_compute_spectrogram(
-    audio: &[f32],
+pub fn par_compute_spectrogram<T: Float>(
+    audio: &[T],
     n_samples: usize,
     hop_length: usize,
     win_length: usize,
     center: bool,
     spectrogram_type: SpectrogramType,
-) -> Vec<Vec<f32>> {
-    // Set-up FFT
-    let mut planner = FftPlanner::<f32>::new();
-    let fft = planner.plan_fft_forward(n_samples);
-
-    // Choose the transformation function to create the spectrogram
-    let transform_fn: fn(&Complex<f32>) -> f32 = match spectrogram_type {
-        SpectrogramType::Magnitude => |c| c.norm(),
-        SpectrogramType::Power => |c| c.norm_sqr(),
-    };
+) -> Vec<Vec<T>> {
+    par_compute_spectrogram_with_window(
+        audio,
+        n_samples,
+        hop_length,
+        &create_hann_window(win_length, false),
+        center,
+        spectrogram_type,
+    )
+}
 
-    // Create (Hann) window
-    let window = create_hann_window(win_length);
+/// Compute the spectrogram (parallelized with rayon) like `par_compute_spectrogram`, with the
+/// given window of win_length = `window.len()` samples
+pub fn par_compute_spectrogram_with_window<T: Float>(
+    audio: &[T],
+    n_samples: usize,
+    hop_length: usize,
+    window: &[T],
+    center: bool,
+    spectrogram_type: SpectrogramType,
+) -> Vec<Vec<T>> {
+    par_compute_spectrogram_with_fft(
+        audio,
+        &*rustfft_forward(n_samples),
+        hop_length,
+        window,
+        center,
+        spectrogram_type,
+    )
+}
+
+/// Compute the spectrogram (parallelized with rayon) like `compute_spectrogram_with_fft`.
+/// Every task transforms a block of `FRAMES_PER_BLOCK` frames, so that the result is identical
+/// to the sequential one.
+pub fn par_compute_spectrogram_with_fft<T: Float>(
+    audio: &[T],
+    fft: &dyn ForwardFft<T>,
+    hop_length: usize,
+    window: &[T],
+    center: bool,
+    spectrogram_type: SpectrogramType,
+) -> Vec<Vec<T>> {
+    let blocks = FrameBlocks::new(audio, fft, hop_length, window, center, spectrogram_type);
+    par_compute_blocks(&blocks, None)
+}
 
-    // Determine the number of frames
-    let n_frames = (audio.len().saturating_sub(win_length)) / hop_length + 1;
+/// Like `par_compute_spectrogram_with_fft`, stopping early once `cancel` is cancelled.
+/// Blocks not yet started are skipped and `SpectrsError::Cancelled` is returned instead of a
+/// partial spectrogram.
+pub fn par_compute_spectrogram_cancellable<T: Float>(
+    audio: &[T],
+    fft: &dyn ForwardFft<T>,
+    hop_length: usize,
+    window: &[T],
+    center: bool,
+    spectrogram_type: SpectrogramType,
+    cancel: &CancellationToken,
+) -> Result<Vec<Vec<T>>> {
+    cancel.check()?;
+    let blocks = FrameBlocks::new(audio, fft, hop_length, window, center, spectrogram_type);
+    let spectrogram = par_compute_blocks(&blocks, Some(cancel));
+    cancel.check()?;
+    Ok(spectrogram)
+}
 
-    // Determine number of frequency bins
-    let n_freq_bins = n_samples / 2 + 1;
+/// Parallel loop over the blocks of frames, skipping the remaining blocks once cancelled
+#[cfg(feature = "parallel")]
+fn par_compute_blocks<T: Float>(
+    blocks: &FrameBlocks<T>,
+    cancel: Option<&CancellationToken>,
+) -> Vec<Vec<T>> {
+    let (n_frames, n_freq_bins) = (blocks.n_frames, blocks.n_freq_bins);
 
-    // Frame-major spectrogram for safe parallel writes: spectrogram[frame][freq]
+    // Frame-major spectrogram for safe parallel writes: spectrogram[frame * n_freq_bins + freq]
     // Eventually to be transposed
-    let mut transposed_spectrogram = vec![vec![0.0f32; n_freq_bins]; n_frames];
+    let mut transposed_spectrogram = vec![T::zero(); n_frames * n_freq_bins];
 
-    // Parallel loop over frames
+    // Parallel loop over blocks of frames, with a buffer local to each task
     transposed_spectrogram
-        .par_iter_mut() // Auto-parallelize with rayon
-        .enumerate() // Extract frame idx
-        .for_each(|(frame_idx, out_row)| {
-            // Determine start and end sample for each frame, recalling that hop_length is a stride
-            // If the end is after the end of the audio it might still be good (depending on start, see after)
-            let start = frame_idx * hop_length;
-            let end = (start + win_length).clamp(0, audio.len());
-
-            // Start is beyond the end of the file
-            if start > audio.len() {
+        .par_chunks_mut(FRAMES_PER_BLOCK * n_freq_bins.max(1))
+        .enumerate()
+        .for_each(|(block_idx, out_rows)| {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
                 return;
             }
+            let first = block_idx * FRAMES_PER_BLOCK;
+            let frames = first..(first + FRAMES_PER_BLOCK).min(n_frames);
+            let mut buffer = Vec::new();
+            blocks.process(frames, &mut buffer, |frame_idx, bins| {
+                let row = (frame_idx - first) * n_freq_bins;
+                for (out, value) in out_rows[row..row + n_freq_bins].iter_mut().zip(bins) {
+                    *out = value;
+                }
+            });
+        });
 
-            // Init thread-local buffers to be filled with windowed audio
-            let mut frame = vec![Complex::<f32>::new(0.0, 0.0); n_samples];
+    // If your downstream expects [freq][frame], transpose once (cache-friendly)
+    let mut spectrogram = vec![vec![T::zero(); n_frames]; n_freq_bins];
+    for (t, row) in transposed_spectrogram
+        .chunks(n_freq_bins.max(1))
+        .enumerate()
+    {
+        for (f, &v) in row.iter().enumerate() {
+            spectrogram[f][t] = v;
+        }
+    }
+    spectrogram
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the blocks are computed sequentially
+#[cfg(not(feature = "parallel"))]
+fn par_compute_blocks<T: Float>(
+    blocks: &FrameBlocks<T>,
+    cancel: Option<&CancellationToken>,
+) -> Vec<Vec<T>> {
+    compute_blocks(blocks, cancel)
+}
 
+/// RustFFT forward transform of n_samples points, the FFT of the `*_with_window` functions
+fn rustfft_forward<T: Float>(n_samples: usize) -> Arc<dyn ForwardFft<T>> {
+    T::plan_fft(&RustFftBackend, n_samples).expect("RustFFT plans any length")
+}
+
+/// Framing of the audio shared by the sequential and parallel STFT, transforming blocks of
+/// frames with one call to the FFT
+struct FrameBlocks<'a, T: Float> {
+    audio: &'a [T],
+    window: &'a [T],
+    hop_length: usize,
+    centering_offset: usize,
+    fft: &'a dyn ForwardFft<T>,
+    transform_fn: fn(&Complex<T>) -> T,
+    n_frames: usize,
+    n_freq_bins: usize,
+}
+
+impl<'a, T: Float> FrameBlocks<'a, T> {
+    fn new(
+        audio: &'a [T],
+        fft: &'a dyn ForwardFft<T>,
+        hop_length: usize,
+        window: &'a [T],
+        center: bool,
+        spectrogram_type: SpectrogramType,
+    ) -> Self {
+        let n_samples = fft.len();
+        let win_length = window.len();
+
+        // Choose the transformation function to create the spectrogram
+        let transform_fn: fn(&Complex<T>) -> T = match spectrogram_type {
+            SpectrogramType::Magnitude => |c| c.norm(),
+            SpectrogramType::Power => |c| c.norm_sqr(),
+        };
+
+        Self {
+            audio,
+            window,
+            hop_length,
             // Add an offset if the window needs to be centered
-            let centering_offset = if center {
+            centering_offset: if center {
                 (n_samples - win_length) / 2_usize
             } else {
                 0_usize
-            };
+            },
+            fft,
+            transform_fn,
+            n_frames: (audio.len().saturating_sub(win_length)) / hop_length + 1,
+            n_freq_bins: n_samples / 2 + 1,
+        }
+    }
+
+    /// Window the frames of `frames` into one contiguous buffer, run the FFT over all of them
+    /// at once and pass the index and the transformed positive frequencies of every frame to
+    /// `store`
+    fn process(
+        &self,
+        frames: std::ops::Range<usize>,
+        buffer: &mut Vec<Complex<T>>,
+        mut store: impl FnMut(usize, &mut dyn Iterator<Item = T>),
+    ) {
+        let n_samples = self.fft.len();
+        buffer.clear();
+        buffer.resize(frames.len() * n_samples, Complex::new(T::zero(), T::zero()));
+
+        for (frame_idx, frame) in frames.clone().zip(buffer.chunks_mut(n_samples.max(1))) {
+            // Determine start and end sample for each frame, recalling that hop_length is a stride
+            let start = (frame_idx * self.hop_length).min(self.audio.len());
+            let end = (start + self.window.len()).min(self.audio.len());
 
             // Window & copy into complex buffer
-            let src = &audio[start..end];
-            let win = &window[..src.len()];
+            let src = &self.audio[start..end];
+            let win = &self.window[..src.len()];
             for (dst, (&s, &w)) in frame
                 .iter_mut()
-                .skip(centering_offset)
+                .skip(self.centering_offset)
                 .zip(src.iter().zip(win.iter()))
             {
                 dst.re = s * w; // Convolve audio and window
-                dst.im = 0.0; // No imaginary part
             }
+        }
 
-            // Run FFT
-            fft.process(&mut frame);
-
-            // Store positive freqs only and apply transformation fn depending on request
-            for (k, c) in frame.iter().take(n_freq_bins).enumerate() {
-                out_row[k] = transform_fn(c);
-            }
-        });
+        // Run FFT over all the frames of the block
+        self.fft.process(buffer);
 
-    // If your downstream expects [freq][frame], transpose once (cache-friendly)
-    let mut spectrogram = vec![vec![0.0f32; n_frames]; n_freq_bins];
-    for (t, row) in transposed_spectrogram.into_iter().enumerate() {
-        for (f, v) in row.into_iter().enumerate() {
-            spectrogram[f][t] = v;
+        // Store positive freqs only and apply transformation fn
+        for (frame_idx, frame) in frames.zip(buffer.chunks(n_samples.max(1))) {
+            let mut bins = frame
+                .iter()
+                .take(self.n_freq_bins)
+                .map(|c| (self.transform_fn)(c));
+            store(frame_idx, &mut bins);
         }
     }
-    spectrogram
 }
@@ -0,0 +1,303 @@
+use crate::error::{Result, SpectrsError};
+use crate::io::audio::{MonoChunks, ResampleQuality, StreamingResampler, resampled_len};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::pooling::FramePooler;
+use crate::spectrogram::stft::{FrameAlignment, SpectrogramType, create_hann_window};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::io::Read;
+use std::sync::Arc;
+
+/// Number of mono frames decoded at a time by `stream_spectrogram`
+pub const STREAM_CHUNK_SIZE: usize = 65536;
+
+/// Incremental STFT: audio is pushed in pieces of any size and every frame is emitted as soon as
+/// its window is complete, so only win_length samples have to be buffered.
+/// The emitted frames are identical to the columns of `compute_spectrogram` on the whole signal.
+pub struct StreamingStft {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    n_samples: usize,
+    hop_length: usize,
+    win_length: usize,
+    centering_offset: usize,
+    transform_fn: fn(&Complex<f32>) -> f32,
+    /// Compute the frames available after each push in parallel
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    parallel: bool,
+    /// Samples not yet consumed by a frame
+    buffer: Vec<f32>,
+    /// Position in the signal of the first buffered sample
+    buffer_start: usize,
+    /// Number of frames emitted so far
+    n_frames: usize,
+}
+
+impl StreamingStft {
+    /// Same parameters as `compute_spectrogram`
+    pub fn new(
+        n_samples: usize,
+        hop_length: usize,
+        win_length: usize,
+        center: bool,
+        spectrogram_type: SpectrogramType,
+    ) -> Self {
+        // Set-up FFT
+        let mut planner = FftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(n_samples);
+
+        // Choose the transformation function to create the spectrogram
+        let transform_fn: fn(&Complex<f32>) -> f32 = match spectrogram_type {
+            SpectrogramType::Magnitude => |c| c.norm(),
+            SpectrogramType::Power => |c| c.norm_sqr(),
+        };
+
+        // Add an offset if the window needs to be centered
+        let centering_offset = if center {
+            (n_samples - win_length) / 2_usize
+        } else {
+            0_usize
+        };
+
+        Self {
+            fft,
+            window: create_hann_window(win_length, false),
+            n_samples,
+            hop_length,
+            win_length,
+            centering_offset,
+            transform_fn,
+            parallel: false,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            n_frames: 0,
+        }
+    }
+
+    /// Build from validated spectrogram parameters (with their window)
+    pub fn from_params(params: &SpectrogramParams) -> Self {
+        Self::new(
+            params.n_fft,
+            params.hop_length,
+            params.win_length,
+            params.center,
+            params.spectrogram_type,
+        )
+        .window(params.window())
+    }
+
+    /// Replace the (symmetric) Hann window, e.g. with a periodic one.
+    /// The window must have win_length coefficients.
+    pub fn window(mut self, window: Vec<f32>) -> Self {
+        assert_eq!(
+            window.len(),
+            self.win_length,
+            "window must have win_length coefficients"
+        );
+        self.window = window;
+        self
+    }
+
+    /// Compute the frames completed by each push in parallel (with rayon; no-op without the
+    /// `parallel` feature)
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Number of frequency bins of each emitted frame
+    pub fn n_freq_bins(&self) -> usize {
+        self.n_samples / 2 + 1
+    }
+
+    /// Number of frames emitted so far
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    /// Append samples, calling `on_frame` with the frequency bins of every completed frame
+    pub fn process<F: FnMut(&[f32])>(&mut self, samples: &[f32], mut on_frame: F) {
+        self.buffer.extend_from_slice(samples);
+        let buffer_end = self.buffer_start + self.buffer.len();
+
+        // Offsets (within the buffer) of the frames whose window is complete
+        let mut starts = Vec::new();
+        let mut start = (self.n_frames + starts.len()) * self.hop_length;
+        while start + self.win_length <= buffer_end {
+            starts.push(start - self.buffer_start);
+            start = (self.n_frames + starts.len()) * self.hop_length;
+        }
+
+        let frame_at =
+            |&offset: &usize| self.compute_frame(&self.buffer[offset..offset + self.win_length]);
+        #[cfg(feature = "parallel")]
+        let frames: Vec<Vec<f32>> = if self.parallel {
+            starts.par_iter().map(frame_at).collect()
+        } else {
+            starts.iter().map(frame_at).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let frames: Vec<Vec<f32>> = starts.iter().map(frame_at).collect();
+        for frame in &frames {
+            on_frame(frame);
+        }
+        self.n_frames += frames.len();
+
+        // Drop the samples no future frame will use
+        let keep_from = (self.n_frames * self.hop_length).min(buffer_end);
+        self.buffer.drain(..keep_from - self.buffer_start);
+        self.buffer_start = keep_from;
+    }
+
+    /// Signal the end of the audio. Like `compute_spectrogram`, audio shorter than one window
+    /// still yields a single zero-padded frame.
+    pub fn finish<F: FnMut(&[f32])>(mut self, mut on_frame: F) {
+        if self.n_frames == 0 {
+            let end = self.buffer.len().min(self.win_length);
+            let frame = self.compute_frame(&self.buffer[..end]);
+            on_frame(&frame);
+            self.n_frames += 1;
+        }
+    }
+
+    /// Window `src` (at most win_length samples) into an FFT buffer and transform it
+    fn compute_frame(&self, src: &[f32]) -> Vec<f32> {
+        // Init buffer to be filled with windowed audio
+        let mut frame = vec![Complex::<f32>::new(0.0, 0.0); self.n_samples];
+
+        // Window & copy into complex buffer
+        for (dst, (&s, &w)) in frame
+            .iter_mut()
+            .skip(self.centering_offset)
+            .zip(src.iter().zip(self.window.iter()))
+        {
+            dst.re = s * w; // Convolve audio and window
+        }
+
+        // Run FFT
+        self.fft.process(&mut frame);
+
+        // Keep positive freqs only and apply transformation fn
+        frame
+            .iter()
+            .take(self.n_freq_bins())
+            .map(self.transform_fn)
+            .collect()
+    }
+}
+
+/// Run a decoded stream through the resampler and the STFT chunk by chunk, calling `on_frame`
+/// with every frame as soon as it is complete. Neither the decoded nor the resampled signal is
+/// ever held in memory as a whole.
+/// `inspect` is called with every decoded chunk before resampling (e.g. to gather statistics).
+/// Frames are computed sequentially when `params.deterministic` is set, whatever `parallel`.
+/// librosa's frame alignment, which depends on the length of the whole signal, is rejected.
+/// Returns the sample rate of the frames.
+pub fn stream_frames<R: Read, F: FnMut(&[f32]), G: FnMut(&[f32])>(
+    chunks: &mut MonoChunks<R>,
+    target_sr: Option<u32>,
+    quality: ResampleQuality,
+    params: &SpectrogramParams,
+    parallel: bool,
+    mut inspect: F,
+    mut on_frame: G,
+) -> Result<u32> {
+    if params.frame_alignment != FrameAlignment::Spectrs {
+        return Err(SpectrsError::InvalidParams(
+            "librosa's frame alignment needs the whole signal and cannot be streamed".to_string(),
+        ));
+    }
+    let original_sr = chunks.sample_rate();
+    let target_sr = target_sr.unwrap_or(original_sr);
+
+    let mut resampler = StreamingResampler::new(original_sr, target_sr, quality)?;
+    let mut stft = StreamingStft::from_params(params).parallel(parallel && !params.deterministic);
+
+    let mut resampled = Vec::new();
+    loop {
+        let chunk = chunks.next_chunk(STREAM_CHUNK_SIZE)?;
+        if chunk.is_empty() {
+            break;
+        }
+        inspect(&chunk);
+
+        resampled.clear();
+        resampler.process(&chunk, &mut resampled)?;
+        stft.process(&resampled, &mut on_frame);
+    }
+
+    resampled.clear();
+    resampler.finish(&mut resampled)?;
+    stft.process(&resampled, &mut on_frame);
+    stft.finish(&mut on_frame);
+
+    Ok(target_sr)
+}
+
+/// Compute the (linear-frequency) spectrogram of a decoded stream chunk by chunk:
+/// chunked read → resample → STFT (see `stream_frames`); only the output spectrogram is held
+/// in memory. Returns the spectrogram in [freq][time] format and its sample rate.
+pub fn stream_spectrogram<R: Read, F: FnMut(&[f32])>(
+    chunks: &mut MonoChunks<R>,
+    target_sr: Option<u32>,
+    quality: ResampleQuality,
+    params: &SpectrogramParams,
+    parallel: bool,
+    inspect: F,
+) -> Result<(Vec<Vec<f32>>, u32)> {
+    // Directly create spectrogram in [freq][time] format
+    let mut spectrogram = vec![Vec::new(); params.n_freq_bins()];
+    let target_sr = stream_frames(
+        chunks,
+        target_sr,
+        quality,
+        params,
+        parallel,
+        inspect,
+        |frame| {
+            for (freq_row, &value) in spectrogram.iter_mut().zip(frame) {
+                freq_row.push(value);
+            }
+        },
+    )?;
+
+    Ok((spectrogram, target_sr))
+}
+
+/// Same as `stream_spectrogram`, but average-pooling frames on the fly so that the result has
+/// at most `width` frames: the full-resolution spectrogram is never materialized, which keeps
+/// memory low when rendering long recordings to small images.
+/// Pooling is done on the linear spectrogram, which is equivalent to pooling after a mel
+/// conversion since the mel filter bank is linear.
+pub fn stream_pooled_spectrogram<R: Read, F: FnMut(&[f32])>(
+    chunks: &mut MonoChunks<R>,
+    target_sr: Option<u32>,
+    quality: ResampleQuality,
+    params: &SpectrogramParams,
+    parallel: bool,
+    width: usize,
+    inspect: F,
+) -> Result<(Vec<Vec<f32>>, u32)> {
+    // Number of frames the stream will produce, to split them into equal groups up front
+    let original_sr = chunks.sample_rate();
+    let n_samples = resampled_len(
+        chunks.remaining_frames(),
+        original_sr,
+        target_sr.unwrap_or(original_sr),
+    );
+    let n_frames = params.n_frames(n_samples);
+
+    let mut pooler = FramePooler::new(n_frames, width, params.n_freq_bins());
+    let target_sr = stream_frames(
+        chunks,
+        target_sr,
+        quality,
+        params,
+        parallel,
+        inspect,
+        |frame| pooler.push(frame),
+    )?;
+
+    Ok((pooler.finish(), target_sr))
+}
@@ -0,0 +1,154 @@
+// Spectrograms matching `torchaudio.transforms.Spectrogram`/`MelSpectrogram`, which differ from
+// librosa in three ways: `center` reflect-pads the signal by n_fft/2 on both ends (instead of
+// centering the window inside the FFT frame), the Hann window is periodic, and the mel filters
+// are not normalized.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::compression::floor_spectrogram;
+use crate::spectrogram::fbank::Compat;
+use crate::spectrogram::fft::FftBackendType;
+use crate::spectrogram::filterbank::{FilterBankType, FrequencyScale};
+use crate::spectrogram::mel::{
+    MelScale, apply_filter_bank, mel_filter_bank, par_apply_filter_bank,
+};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::stft::{FrameAlignment, Precision, SpectrogramType, WindowNorm};
+use crate::spectrogram::streaming::StreamingStft;
+use crate::spectrogram::weighting::Weighting;
+
+/// Defaults of `torchaudio.transforms.MelSpectrogram`: n_fft=400, win_length=400,
+/// hop_length=200, 128 HTK mel bands over the full band, power spectrum, center=True
+pub fn torchaudio_defaults() -> SpectrogramParams {
+    SpectrogramParams {
+        n_fft: 400,
+        hop_length: 200,
+        win_length: 400,
+        center: true,
+        frame_alignment: FrameAlignment::Spectrs,
+        periodic_window: None,
+        window_norm: WindowNorm::None,
+        spectrogram_type: SpectrogramType::Power,
+        n_mels: Some(128),
+        f_min: Some(0.0),
+        f_max: None,
+        mel_scale: MelScale::HTK,
+        filter_bank: FilterBankType::Mel,
+        frequency_scale: FrequencyScale::Mel,
+        lpc_order: None,
+        weighting: Weighting::Z,
+        compression: None,
+        amin: None,
+        mel_floor: None,
+        compat: Compat::Torchaudio,
+        dither: 0.0,
+        deterministic: false,
+        precision: Precision::F32,
+        fft_backend: FftBackendType::RustFft,
+    }
+}
+
+/// Pad both ends of the signal by reflecting it around the edge samples (which are not
+/// repeated), as numpy's and torch's "reflect" mode
+pub fn reflect_pad(audio: &[f32], pad: usize) -> Result<Vec<f32>> {
+    if pad >= audio.len() {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Cannot reflect-pad {} samples by {} (the audio must be longer than n_fft/2)",
+            audio.len(),
+            pad
+        )));
+    }
+    let mut padded = Vec::with_capacity(audio.len() + 2 * pad);
+    padded.extend(audio[1..=pad].iter().rev());
+    padded.extend_from_slice(audio);
+    padded.extend(audio[audio.len() - 1 - pad..audio.len() - 1].iter().rev());
+    Ok(padded)
+}
+
+/// Compute the spectrogram (single-threaded) the way torchaudio does, in [freq][time] format.
+/// With `n_mels` the unnormalized mel filter bank of `mel_scale` is applied.
+pub fn torchaudio_spectrogram(
+    audio: &[f32],
+    sr: u32,
+    params: &SpectrogramParams,
+) -> Result<Vec<Vec<f32>>> {
+    compute(audio, sr, params, false)
+}
+
+/// Compute the spectrogram the way torchaudio does (parallelized with rayon, unless
+/// `params.deterministic` is set)
+pub fn par_torchaudio_spectrogram(
+    audio: &[f32],
+    sr: u32,
+    params: &SpectrogramParams,
+) -> Result<Vec<Vec<f32>>> {
+    compute(audio, sr, params, true)
+}
+
+fn compute(
+    audio: &[f32],
+    sr: u32,
+    params: &SpectrogramParams,
+    parallel: bool,
+) -> Result<Vec<Vec<f32>>> {
+    params.validate_for_sample_rate(sr)?;
+    let parallel = parallel && !params.deterministic;
+
+    let padded = if params.center {
+        reflect_pad(audio, params.n_fft / 2)?
+    } else {
+        audio.to_vec()
+    };
+    if padded.len() < params.n_fft {
+        return Err(SpectrsError::InvalidParams(format!(
+            "Audio ({} samples) is shorter than n_fft ({})",
+            padded.len(),
+            params.n_fft
+        )));
+    }
+    let n_frames = 1 + (padded.len() - params.n_fft) / params.hop_length;
+
+    // torch centers the window inside the FFT frame, which is what the STFT does when
+    // `center` is set: skipping the offset aligns the frames with torch's
+    let offset = (params.n_fft - params.win_length) / 2;
+    let mut stft = StreamingStft::new(
+        params.n_fft,
+        params.hop_length,
+        params.win_length,
+        true,
+        params.spectrogram_type,
+    )
+    .window(params.window())
+    .parallel(parallel);
+
+    let mut spectrogram = vec![Vec::with_capacity(n_frames); params.n_freq_bins()];
+    stft.process(&padded[offset..], |frame| {
+        if spectrogram[0].len() < n_frames {
+            for (freq_row, &value) in spectrogram.iter_mut().zip(frame) {
+                freq_row.push(value);
+            }
+        }
+    });
+
+    // Convert to mel if necessary
+    if let Some(n_mels) = params.n_mels {
+        let filters = mel_filter_bank(
+            sr,
+            params.n_fft,
+            n_mels,
+            params.f_min,
+            params.f_max,
+            params.mel_scale,
+            false,
+        );
+        spectrogram = if parallel {
+            par_apply_filter_bank(&spectrogram, &filters)
+        } else {
+            apply_filter_bank(&spectrogram, &filters)
+        };
+        if let Some(mel_floor) = params.mel_floor {
+            floor_spectrogram(&mut spectrogram, mel_floor);
+        }
+    }
+
+    Ok(spectrogram)
+}
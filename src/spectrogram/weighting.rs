@@ -0,0 +1,103 @@
+// Frequency weighting of linear spectrograms, for acoustics and noise monitoring: the A and C
+// curves of IEC 61672-1 (sound level meters), or Z (flat, no weighting). Each FFT bin is
+// scaled by the gain of the curve at its frequency before the filter bank and the feature
+// statistics, so that bands and levels are weighted alike.
+
+use crate::spectrogram::stft::{Float, SpectrogramType, constant};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Frequency weighting curve of IEC 61672-1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    /// A-weighting, following the loudness of quiet sounds (dBA)
+    A,
+    /// C-weighting, nearly flat over the audible range, for loud sounds and peaks (dBC)
+    C,
+    /// Zero weighting: the spectrogram is left unchanged
+    #[default]
+    Z,
+}
+
+impl Weighting {
+    /// Gain (dB) of the curve at `hz`, normalized to 0 dB at 1 kHz (-inf at 0 Hz for A and C)
+    pub fn gain_db(self, hz: f64) -> f64 {
+        // Pole frequencies (Hz) of the analogue filters
+        const F1: f64 = 20.598_997;
+        const F2: f64 = 107.652_65;
+        const F3: f64 = 737.862_23;
+        const F4: f64 = 12_194.217;
+        let f2 = hz * hz;
+        match self {
+            Weighting::A => {
+                let response = F4 * F4 * f2 * f2
+                    / ((f2 + F1 * F1) * ((f2 + F2 * F2) * (f2 + F3 * F3)).sqrt() * (f2 + F4 * F4));
+                20.0 * response.log10() + 2.0
+            }
+            Weighting::C => {
+                let response = F4 * F4 * f2 / ((f2 + F1 * F1) * (f2 + F4 * F4));
+                20.0 * response.log10() + 0.062
+            }
+            Weighting::Z => 0.0,
+        }
+    }
+
+    /// Factor applied to every FFT bin of a `spectrogram_type` spectrogram of `n_fft` points at
+    /// `sr` (squared gains for powers), or None for Z-weighting
+    pub fn bin_gains<T: Float>(
+        self,
+        n_fft: usize,
+        sr: u32,
+        spectrogram_type: SpectrogramType,
+    ) -> Option<Vec<T>> {
+        if self == Weighting::Z {
+            return None;
+        }
+        let divisor = match spectrogram_type {
+            SpectrogramType::Power => 10.0,
+            SpectrogramType::Magnitude => 20.0,
+        };
+        Some(
+            (0..n_fft / 2 + 1)
+                .map(|bin| {
+                    let hz = bin as f64 * sr as f64 / n_fft as f64;
+                    constant(10f64.powf(self.gain_db(hz) / divisor))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Scale the rows of a linear [freq][time] spectrogram by the gains of `bin_gains`
+/// (single-threaded)
+pub fn apply_weighting<T: Float>(spectrogram: &mut [Vec<T>], gains: &[T]) {
+    for (row, &gain) in spectrogram.iter_mut().zip(gains) {
+        row.iter_mut().for_each(|v| *v *= gain);
+    }
+}
+
+/// Scale the rows of a linear [freq][time] spectrogram by the gains of `bin_gains`
+/// (parallelized over rows)
+#[cfg(feature = "parallel")]
+pub fn par_apply_weighting<T: Float>(spectrogram: &mut [Vec<T>], gains: &[T]) {
+    spectrogram
+        .par_iter_mut()
+        .zip(gains)
+        .for_each(|(row, &gain)| row.iter_mut().for_each(|v| *v *= gain));
+}
+
+/// Weight the bins the filters of a [band][freq] filter bank are applied to, so that the bands
+/// of an unweighted spectrogram come out weighted
+pub fn weight_filter_bank<T: Float>(filters: &mut [Vec<T>], gains: &[T]) {
+    for filter in filters.iter_mut() {
+        filter.iter_mut().zip(gains).for_each(|(w, &g)| *w *= g);
+    }
+}
+
+/// Without the `parallel` feature (e.g. on wasm32) the rows are scaled sequentially
+#[cfg(not(feature = "parallel"))]
+pub fn par_apply_weighting<T: Float>(spectrogram: &mut [Vec<T>], gains: &[T]) {
+    apply_weighting(spectrogram, gains)
+}
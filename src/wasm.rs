@@ -0,0 +1,166 @@
+//! Browser-friendly API for `wasm32-unknown-unknown` (enabled with the `wasm` feature).
+//!
+//! Everything runs on the calling thread and takes PCM samples that are already decoded,
+//! e.g. from `AudioBuffer.getChannelData()` in the Web Audio API.
+
+use crate::error::{Result, SpectrsError};
+use crate::spectrogram::mel::{MelScale, convert_to_mel};
+use crate::spectrogram::params::SpectrogramParams;
+use crate::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use wasm_bindgen::prelude::*;
+
+/// Options of `compute_mel_from_pcm` (a JS object with mutable fields)
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct MelOptions {
+    /// Number of samples in each FFT window
+    pub n_fft: usize,
+    /// Stride between successive FFT frames
+    pub hop_length: usize,
+    /// Number of samples in the window function applied before FFT
+    pub win_length: usize,
+    /// Center the window inside the FFT frame
+    pub center: bool,
+    /// Power (true) or magnitude (false) spectrogram
+    pub power: bool,
+    /// Number of mel bands
+    pub n_mels: usize,
+    /// Lower cut-off frequency (Hz)
+    pub f_min: f32,
+    /// Upper cut-off frequency (Hz, 0 for Nyquist)
+    pub f_max: f32,
+    /// HTK (true) or Slaney (false) mel scale
+    pub htk: bool,
+}
+
+impl Default for MelOptions {
+    fn default() -> Self {
+        let params = SpectrogramParams::default();
+        Self {
+            n_fft: params.n_fft,
+            hop_length: params.hop_length,
+            win_length: params.win_length,
+            center: params.center,
+            power: params.spectrogram_type == SpectrogramType::Power,
+            n_mels: 128,
+            f_min: 0.0,
+            f_max: 0.0,
+            htk: params.mel_scale == MelScale::HTK,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl MelOptions {
+    /// Default options (n_fft=2048, hop_length=512, win_length=2048, 128 Slaney mel bands)
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MelOptions {
+    /// Validated spectrogram parameters for audio sampled at `sample_rate`
+    pub fn to_params(&self, sample_rate: u32) -> Result<SpectrogramParams> {
+        SpectrogramParams::builder()
+            .n_fft(self.n_fft)
+            .hop_length(self.hop_length)
+            .win_length(self.win_length)
+            .center(self.center)
+            .spectrogram_type(if self.power {
+                SpectrogramType::Power
+            } else {
+                SpectrogramType::Magnitude
+            })
+            .n_mels(Some(self.n_mels))
+            .f_min(Some(self.f_min))
+            .f_max((self.f_max > 0.0).then_some(self.f_max))
+            .mel_scale(if self.htk {
+                MelScale::HTK
+            } else {
+                MelScale::Slaney
+            })
+            .sample_rate(sample_rate)
+            .build()
+    }
+}
+
+/// Mel spectrogram returned to JS, stored row-major (`n_mels` rows of `n_frames` values,
+/// lowest band first) so it can be copied straight into a canvas or WebGL texture
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct MelSpectrogram {
+    n_mels: usize,
+    n_frames: usize,
+    data: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl MelSpectrogram {
+    #[wasm_bindgen(getter)]
+    pub fn n_mels(&self) -> usize {
+        self.n_mels
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    /// Copy of the values as a `Float32Array` of length `n_mels * n_frames`
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<f32> {
+        self.data.clone()
+    }
+
+    /// Value of band `mel` at frame `frame`
+    pub fn get(&self, mel: usize, frame: usize) -> f32 {
+        self.data[mel * self.n_frames + frame]
+    }
+}
+
+/// Compute a mel spectrogram from mono PCM samples (a `Float32Array` in [-1, 1]).
+/// Options default to `new MelOptions()` when omitted.
+#[wasm_bindgen]
+pub fn compute_mel_from_pcm(
+    pcm: &[f32],
+    sample_rate: u32,
+    options: Option<MelOptions>,
+) -> Result<MelSpectrogram, JsError> {
+    mel_from_pcm(pcm, sample_rate, options.unwrap_or_default())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn mel_from_pcm(pcm: &[f32], sample_rate: u32, options: MelOptions) -> Result<MelSpectrogram> {
+    if pcm.is_empty() {
+        return Err(SpectrsError::InvalidParams(
+            "No samples to process".to_string(),
+        ));
+    }
+    let params = options.to_params(sample_rate)?;
+
+    let spectrogram = compute_spectrogram(
+        pcm,
+        params.n_fft,
+        params.hop_length,
+        params.win_length,
+        params.center,
+        params.spectrogram_type,
+    );
+    let mel = convert_to_mel(
+        &spectrogram,
+        sample_rate,
+        params.n_fft,
+        options.n_mels,
+        params.f_min,
+        params.f_max,
+        params.mel_scale,
+    );
+
+    let n_frames = mel.first().map_or(0, Vec::len);
+    Ok(MelSpectrogram {
+        n_mels: mel.len(),
+        n_frames,
+        data: mel.concat(),
+    })
+}
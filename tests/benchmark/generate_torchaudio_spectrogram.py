@@ -0,0 +1,80 @@
+# /// script
+# requires-python = ">=3.12"
+# dependencies = [
+#     "numpy",
+#     "soundfile",
+#     "torch",
+#     "torchaudio",
+# ]
+# ///
+"""
+Script to generate spectrograms using torchaudio for comparison with spectrs.
+Reads test parameters from command line and outputs spectrogram as JSON.
+"""
+
+import sys
+import json
+import numpy as np
+import soundfile as sf
+import torch
+import torchaudio
+
+
+def compute_torchaudio_spectrogram(audio_file: str, params: dict) -> tuple[np.ndarray, int]:
+    """Compute a (mel) spectrogram with torchaudio.transforms, keeping its defaults."""
+    # Load audio and downmix to mono like spectrs
+    y, sr = sf.read(audio_file, dtype="float32", always_2d=True)
+    waveform = torch.from_numpy(y.mean(axis=1))
+
+    # Only forward the parameters set by the test, everything else keeps torchaudio's defaults
+    kwargs = {k: v for k, v in params.items() if k != "type" and v is not None}
+
+    if params["type"] == "mel":
+        transform = torchaudio.transforms.MelSpectrogram(sample_rate=sr, **kwargs)
+    else:
+        kwargs.pop("n_mels", None)
+        kwargs.pop("mel_scale", None)
+        transform = torchaudio.transforms.Spectrogram(**kwargs)
+
+    return transform(waveform).numpy(), sr
+
+
+def main():
+    if len(sys.argv) < 3:
+        print(
+            "Usage: python generate_torchaudio_spectrogram.py <audio_file> <output_json> [params_json]"
+        )
+        sys.exit(1)
+
+    audio_file = sys.argv[1]
+    output_file = sys.argv[2]
+
+    # Default parameters (torchaudio's own defaults apply to anything not listed)
+    params = {"type": "mel"}
+
+    # Load custom parameters if provided
+    if len(sys.argv) > 3:
+        with open(sys.argv[3], "r") as f:
+            params.update(json.load(f))
+
+    spec, sr = compute_torchaudio_spectrogram(audio_file, params)
+
+    # Convert to list for JSON serialization
+    output = {
+        "data": spec.tolist(),
+        "shape": spec.shape,
+        "sample_rate": int(sr),
+        "params": params,
+    }
+
+    # Save to JSON
+    with open(output_file, "w") as f:
+        json.dump(output, f)
+
+    print(f"Torchaudio spectrogram saved to {output_file}")
+    print(f"Shape: {spec.shape}")
+    print(f"Sample rate: {sr}")
+
+
+if __name__ == "__main__":
+    main()
@@ -1,3 +1,6 @@
+// Not every test crate uses every helper
+#![allow(dead_code)]
+
 use anyhow::Result;
 use std::fs;
 use std::path::Path;
@@ -17,6 +20,14 @@ pub fn setup_test_dir() -> Result<PathBuf> {
     Ok(test_dir)
 }
 
+/// Creates a fresh test directory in the system temporary directory, for tests that keep their
+/// data around for inspection when they fail
+pub fn setup_temp_test_dir() -> Result<PathBuf> {
+    let test_dir = std::env::temp_dir().join(format!("spectrs-test-data-{}", Uuid::new_v4()));
+    fs::create_dir_all(&test_dir)?;
+    Ok(test_dir)
+}
+
 /// Cleans up the test directory after tests are complete
 pub fn cleanup_test_dir(test_dir: &Path) -> Result<()> {
     if test_dir.exists() {
@@ -53,6 +64,7 @@ pub fn create_test_wav(
             match bits_per_sample {
                 8 => writer.write_sample((sample * i8::MAX as f32) as i8)?,
                 16 => writer.write_sample((sample * i16::MAX as f32) as i16)?,
+                24 => writer.write_sample((sample * 8_388_607.0) as i32)?,
                 32 => writer.write_sample((sample * i32::MAX as f32) as i32)?,
                 _ => {
                     return Err(anyhow::anyhow!(
@@ -103,6 +115,7 @@ pub fn create_complex_test_wav(
             match bits_per_sample {
                 8 => writer.write_sample((sample * i8::MAX as f32) as i8)?,
                 16 => writer.write_sample((sample * i16::MAX as f32) as i16)?,
+                24 => writer.write_sample((sample * 8_388_607.0) as i32)?,
                 32 => writer.write_sample((sample * i32::MAX as f32) as i32)?,
                 _ => {
                     return Err(anyhow::anyhow!(
@@ -115,3 +128,167 @@ pub fn create_complex_test_wav(
     }
     Ok(())
 }
+
+/// Create an IEEE float WAV file (32 or 64 bits per sample) with a sine wave of amplitude 0.5,
+/// in opposite phase on the second channel (if any). 64-bit files are written by hand since
+/// hound only writes 32-bit floats.
+#[allow(dead_code)]
+pub fn create_float_test_wav(
+    path: &Path,
+    duration_sec: f32,
+    sample_rate: u32,
+    channels: usize,
+    bits_per_sample: u16,
+) -> Result<Vec<f64>> {
+    let num_samples = (duration_sec * sample_rate as f32) as usize;
+    let samples: Vec<f64> = (0..num_samples)
+        .map(|t| 0.5 * (t as f64 * 440.0 * 2.0 * std::f64::consts::PI / sample_rate as f64).sin())
+        .collect();
+    let channel_sample = |s: f64, channel: usize| if channel == 0 { s } else { -s };
+
+    match bits_per_sample {
+        32 => {
+            let spec = hound::WavSpec {
+                channels: channels as u16,
+                sample_rate,
+                bits_per_sample,
+                sample_format: hound::SampleFormat::Float,
+            };
+            let mut writer = hound::WavWriter::create(path, spec)?;
+            for &s in &samples {
+                for channel in 0..channels {
+                    writer.write_sample(channel_sample(s, channel) as f32)?;
+                }
+            }
+            writer.finalize()?;
+        }
+        64 => {
+            let data_len = (num_samples * channels * 8) as u32;
+            let block_align = (channels * 8) as u16;
+            let mut bytes = Vec::new();
+            bytes.extend_from_slice(b"RIFF");
+            bytes.extend_from_slice(&(4 + 8 + 16 + 8 + data_len).to_le_bytes());
+            bytes.extend_from_slice(b"WAVEfmt ");
+            bytes.extend_from_slice(&16u32.to_le_bytes());
+            bytes.extend_from_slice(&3u16.to_le_bytes()); // WAVE_FORMAT_IEEE_FLOAT
+            bytes.extend_from_slice(&(channels as u16).to_le_bytes());
+            bytes.extend_from_slice(&sample_rate.to_le_bytes());
+            bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+            bytes.extend_from_slice(&block_align.to_le_bytes());
+            bytes.extend_from_slice(&64u16.to_le_bytes());
+            bytes.extend_from_slice(b"data");
+            bytes.extend_from_slice(&data_len.to_le_bytes());
+            for &s in &samples {
+                for channel in 0..channels {
+                    bytes.extend_from_slice(&channel_sample(s, channel).to_le_bytes());
+                }
+            }
+            std::fs::write(path, bytes)?;
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported bits per sample: {}",
+                bits_per_sample
+            ));
+        }
+    }
+    Ok(samples)
+}
+
+/// Create a 24-bit integer WAV file from interleaved samples (in [-2^23, 2^23)). With `padded`,
+/// each sample is stored left-justified in a 4-byte container of a WAVE_FORMAT_EXTENSIBLE
+/// file, written by hand since hound stores padded samples in the low bytes.
+#[allow(dead_code)]
+pub fn create_int24_test_wav(
+    path: &Path,
+    interleaved: &[i32],
+    sample_rate: u32,
+    channels: usize,
+    padded: bool,
+) -> Result<()> {
+    if !padded {
+        let spec = hound::WavSpec {
+            channels: channels as u16,
+            sample_rate,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for &sample in interleaved {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize()?;
+        return Ok(());
+    }
+
+    let data_len = (interleaved.len() * 4) as u32;
+    let block_align = (channels * 4) as u16;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(4 + 8 + 40 + 8 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&40u32.to_le_bytes());
+    bytes.extend_from_slice(&0xFFFEu16.to_le_bytes()); // WAVE_FORMAT_EXTENSIBLE
+    bytes.extend_from_slice(&(channels as u16).to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&32u16.to_le_bytes()); // Container size
+    bytes.extend_from_slice(&22u16.to_le_bytes()); // Extension size
+    bytes.extend_from_slice(&24u16.to_le_bytes()); // Valid bits per sample
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // Channel mask
+    // KSDATAFORMAT_SUBTYPE_PCM
+    bytes.extend_from_slice(&[
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B,
+        0x71,
+    ]);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for &sample in interleaved {
+        bytes.extend_from_slice(&(sample << 8).to_le_bytes());
+    }
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// `n` samples of a sine of `hz` Hz and the given amplitude, sampled at `sr`
+pub fn sine(hz: f32, amplitude: f32, n: usize, sr: u32) -> Vec<f32> {
+    (0..n)
+        .map(|i| amplitude * (2.0 * std::f32::consts::PI * hz * i as f32 / sr as f32).sin())
+        .collect()
+}
+
+/// Sine of `hz` Hz and the given amplitude, `seconds` long at `sr`
+pub fn tone(hz: f32, amplitude: f32, seconds: f32, sr: u32) -> Vec<f32> {
+    sine(hz, amplitude, (seconds * sr as f32) as usize, sr)
+}
+
+/// Deterministic white noise in [-1, 1]
+pub fn noise(n: usize) -> Vec<f32> {
+    let mut state = 12345u32;
+    (0..n)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// [freq][time] spectrogram whose values encode their position: `offset + k * n_frames + t`
+/// at bin k and frame t
+pub fn ramp(n_bins: usize, n_frames: usize, offset: f32) -> Vec<Vec<f32>> {
+    (0..n_bins)
+        .map(|k| {
+            (0..n_frames)
+                .map(|t| offset + (k * n_frames + t) as f32)
+                .collect()
+        })
+        .collect()
+}
+
+/// [freq][time] spectrogram of `k * 1000 + t` at bin k and frame t, readable at a glance
+pub fn grid(n_bins: usize, n_frames: usize) -> Vec<Vec<f32>> {
+    (0..n_bins)
+        .map(|k| (0..n_frames).map(|t| (k * 1000 + t) as f32).collect())
+        .collect()
+}
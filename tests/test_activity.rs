@@ -0,0 +1,118 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::features::activity::{ActivityParams, Segment, segment_activity};
+use spectrs::io::annotations::Annotation;
+use spectrs::spectrogram::stft::SpectrogramType;
+
+/// Power spectrogram of two bins with frames at full power where `levels` is 1 and at -60 dB
+/// where it is 0
+fn spectrogram(levels: &[u8]) -> Vec<Vec<f32>> {
+    let row: Vec<f32> = levels
+        .iter()
+        .map(|&level| if level == 1 { 1.0 } else { 1e-6 })
+        .collect();
+    vec![row.clone(), row]
+}
+
+fn assert_segments(found: &[Segment], expected: &[(f32, f32, bool)]) {
+    assert_eq!(found.len(), expected.len(), "{:?}", found);
+    for (segment, &(start, end, active)) in found.iter().zip(expected) {
+        assert!(
+            (segment.start - start).abs() < 1e-5 && (segment.end - end).abs() < 1e-5,
+            "{:?}",
+            found
+        );
+        assert_eq!(segment.active, active, "{:?}", found);
+    }
+}
+
+#[test]
+fn test_segments_of_bursts() -> Result<()> {
+    // Ten frames per second: a burst with a one-frame pause, then a one-frame click
+    let spec = spectrogram(&[0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+    let params = ActivityParams {
+        threshold_db: -40.0,
+        min_silence: 0.2,
+        min_active: 0.15,
+    };
+    let segments = segment_activity(&spec, SpectrogramType::Power, 10.0, 0.0, 2.0, &params)?;
+    // The pause is bridged, the click dropped, and boundaries fall halfway between frames
+    assert_segments(
+        &segments,
+        &[(0.0, 0.15, false), (0.15, 0.95, true), (0.95, 2.0, false)],
+    );
+
+    // Without minimum durations every run is a segment; frames centered later shift the times
+    let params = ActivityParams {
+        min_silence: 0.0,
+        min_active: 0.0,
+        ..params
+    };
+    let segments = segment_activity(&spec, SpectrogramType::Power, 10.0, 0.05, 2.0, &params)?;
+    assert_eq!(segments.len(), 7);
+    assert_segments(&segments[..2], &[(0.0, 0.2, false), (0.2, 0.5, true)]);
+    assert_eq!(segments.last().map(|s| s.end), Some(2.0));
+
+    // Magnitudes are squared into energies
+    let magnitudes = spectrogram(&[1, 0, 1]);
+    let magnitudes: Vec<Vec<f32>> = magnitudes
+        .iter()
+        .map(|row| row.iter().map(|v| v.sqrt()).collect())
+        .collect();
+    let segments = segment_activity(
+        &magnitudes,
+        SpectrogramType::Magnitude,
+        10.0,
+        0.0,
+        0.3,
+        &params,
+    )?;
+    assert_segments(
+        &segments,
+        &[(0.0, 0.05, true), (0.05, 0.15, false), (0.15, 0.3, true)],
+    );
+
+    // Segments become annotations labeled by activity
+    let annotation = Annotation::from(segments[1]);
+    assert_eq!(annotation.label, "silence");
+    assert_eq!((annotation.start, annotation.end), (0.05, 0.15));
+    Ok(())
+}
+
+#[test]
+fn test_segments_of_silence() -> Result<()> {
+    let silence = vec![vec![0.0f32; 20]; 4];
+    let params = ActivityParams::default();
+    let segments = segment_activity(&silence, SpectrogramType::Power, 10.0, 0.0, 2.0, &params)?;
+    assert_segments(&segments, &[(0.0, 2.0, false)]);
+
+    // A steady tone is active throughout
+    let tone = vec![vec![1.0f32; 20]; 4];
+    let segments = segment_activity(&tone, SpectrogramType::Power, 10.0, 0.0, 2.0, &params)?;
+    assert_segments(&segments, &[(0.0, 2.0, true)]);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_activity_params() {
+    let spec = spectrogram(&[1, 0, 1]);
+    for params in [
+        ActivityParams {
+            threshold_db: 3.0,
+            ..ActivityParams::default()
+        },
+        ActivityParams {
+            min_silence: -0.1,
+            ..ActivityParams::default()
+        },
+        ActivityParams {
+            min_active: f32::NAN,
+            ..ActivityParams::default()
+        },
+    ] {
+        assert!(matches!(
+            segment_activity(&spec, SpectrogramType::Power, 10.0, 0.0, 0.3, &params),
+            Err(SpectrsError::InvalidParams(_))
+        ));
+    }
+}
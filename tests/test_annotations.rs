@@ -0,0 +1,114 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, setup_test_dir};
+use spectrs::io::annotations::{
+    Annotation, format_audacity_labels, load_annotations, parse_annotations_json,
+    parse_audacity_labels, save_annotations,
+};
+use std::fs;
+
+#[test]
+fn test_audacity_labels() -> Result<()> {
+    // Frequency ranges follow their label on a line starting with a backslash
+    let text = "1.250000\t3.500000\tdog bark\n\\\t500.000000\t4000.000000\n4.0\t4.0\t\n";
+    let annotations = parse_audacity_labels(text)?;
+    assert_eq!(
+        annotations,
+        [
+            Annotation {
+                start: 1.25,
+                end: 3.5,
+                label: "dog bark".to_string(),
+                f_min: Some(500.0),
+                f_max: Some(4000.0),
+            },
+            Annotation {
+                start: 4.0,
+                end: 4.0,
+                label: String::new(),
+                f_min: None,
+                f_max: None,
+            },
+        ]
+    );
+
+    // Windows line endings and blank lines
+    assert_eq!(parse_audacity_labels("0.5\t1\ta\r\n\r\n")?[0].label, "a");
+
+    assert!(parse_audacity_labels("1.0 2.0 words").is_err());
+    assert!(parse_audacity_labels("\\\t100\t200").is_err());
+    assert!(parse_audacity_labels("3.0\t2.0\tbackwards").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_json_annotations() -> Result<()> {
+    let annotations = parse_annotations_json(
+        r#"[{"start": 0.5, "end": 1.0, "label": "a"},
+            {"begin": 2.0, "end": 3.0, "value": "b", "f_min": 100, "f_max": 200}]"#,
+    )?;
+    assert_eq!(annotations.len(), 2);
+    assert_eq!(annotations[1].start, 2.0);
+    assert_eq!(annotations[1].label, "b");
+    assert_eq!(annotations[1].f_max, Some(200.0));
+
+    assert!(parse_annotations_json(r#"{"start": 0.5}"#).is_err());
+    assert!(parse_annotations_json(r#"[{"start": -1, "end": 1}]"#).is_err());
+    assert!(
+        parse_annotations_json(r#"[{"start": 0, "end": 1, "f_min": 300, "f_max": 200}]"#).is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_load_annotations_by_extension() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::write(test_dir.join("labels.txt"), "0.1\t0.2\tx\n")?;
+    fs::write(
+        test_dir.join("labels.json"),
+        r#"[{"start": 0.1, "end": 0.2, "label": "x"}]"#,
+    )?;
+
+    assert_eq!(
+        load_annotations(&test_dir.join("labels.txt"))?,
+        load_annotations(&test_dir.join("labels.json"))?
+    );
+    let error = load_annotations(&test_dir.join("missing.json")).unwrap_err();
+    assert!(error.to_string().contains("missing.json"), "{}", error);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_save_annotations_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let annotations = vec![
+        Annotation {
+            start: 0.0,
+            end: 1.5,
+            label: "active".to_string(),
+            f_min: None,
+            f_max: None,
+        },
+        Annotation {
+            start: 1.5,
+            end: 2.25,
+            label: "dog bark".to_string(),
+            f_min: Some(500.0),
+            f_max: Some(4000.0),
+        },
+    ];
+    assert_eq!(
+        format_audacity_labels(&annotations),
+        "0.000000\t1.500000\tactive\n1.500000\t2.250000\tdog bark\n\\\t500.000000\t4000.000000\n"
+    );
+    for name in ["labels.txt", "labels.json"] {
+        save_annotations(&annotations, &test_dir.join(name))?;
+        assert_eq!(load_annotations(&test_dir.join(name))?, annotations);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
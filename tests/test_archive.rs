@@ -0,0 +1,123 @@
+#![cfg(feature = "archive")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use spectrs::io::archive::{Archive, ArchiveSource};
+use spectrs::io::source::{AudioSource, FileSource, is_archive_path};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Files of the test archives: (path in the archive, contents)
+fn members(test_dir: &Path) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let mut members = Vec::new();
+    for (name, duration) in [
+        ("spk2/c.wav", 0.25),
+        ("spk1/a.wav", 0.5),
+        ("spk1/b.wav", 1.0),
+    ] {
+        let wav = test_dir.join("source.wav");
+        create_test_wav(&wav, duration, 16000, 1, 16)?;
+        members.push((name, fs::read(&wav)?));
+    }
+    members.push(("README.txt", b"not audio".to_vec()));
+    Ok(members)
+}
+
+fn write_zip(path: &Path, members: &[(&str, Vec<u8>)]) -> Result<()> {
+    let mut writer = zip::ZipWriter::new(fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default();
+    writer.add_directory("spk1/", options)?;
+    for (name, bytes) in members {
+        writer.start_file(*name, options)?;
+        writer.write_all(bytes)?;
+    }
+    // Left out: paths escaping the archive and macOS resource forks
+    writer.start_file("../evil.wav", options)?;
+    writer.write_all(&members[0].1)?;
+    writer.start_file("__MACOSX/spk1/._a.wav", options)?;
+    writer.write_all(b"resource fork")?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, members: &[(&str, Vec<u8>)]) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    for (name, bytes) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, bytes.as_slice())?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+#[test]
+fn test_archive_paths() {
+    for name in ["a.zip", "corpus.tar", "corpus.tar.gz", "CORPUS.TGZ"] {
+        assert!(is_archive_path(Path::new(name)), "{}", name);
+    }
+    for name in ["a.wav", "corpus.gz", "zip", "tar.gz/a.wav"] {
+        assert!(!is_archive_path(Path::new(name)), "{}", name);
+    }
+    assert!(Archive::open(Path::new("corpus.rar")).is_err());
+}
+
+#[test]
+fn test_archive_members() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let members = members(&test_dir)?;
+    let zip = test_dir.join("corpus.zip");
+    write_zip(&zip, &members)?;
+    let tar = test_dir.join("corpus.tar");
+    write_tar(fs::File::create(&tar)?, &members)?;
+    let tgz = test_dir.join("corpus.tar.gz");
+    let encoder =
+        flate2::write::GzEncoder::new(fs::File::create(&tgz)?, flate2::Compression::default());
+    write_tar(encoder, &members)?.finish()?;
+
+    for path in [&zip, &tar, &tgz] {
+        let archive = Arc::new(Archive::open(path)?);
+        let paths: Vec<PathBuf> = archive.members().iter().map(|m| m.path.clone()).collect();
+        assert_eq!(
+            paths,
+            ["README.txt", "spk1/a.wav", "spk1/b.wav", "spk2/c.wav"].map(PathBuf::from),
+            "{}",
+            path.display()
+        );
+
+        // Members read out of order (restarting the decompression of gzipped tar files) and
+        // more than once hold the bytes they were archived with
+        for index in [3, 1, 2, 1, 0] {
+            let member = &archive.members()[index];
+            let (_, expected) = members
+                .iter()
+                .find(|(name, _)| Path::new(name) == member.path)
+                .expect("archived member");
+            assert_eq!(&archive.read(member)?, expected, "{}", path.display());
+            assert_eq!(member.size, expected.len() as u64);
+        }
+
+        // Members decode like the files they were archived from
+        let source = ArchiveSource::new(archive.clone(), archive.members()[2].clone());
+        assert_eq!(source.name(), format!("{}/spk1/b.wav", path.display()));
+        assert_eq!(source.relative_path(), PathBuf::from("spk1/b.wav"));
+        let wav = test_dir.join("b.wav");
+        fs::write(&wav, &members[2].1)?;
+        assert_eq!(source.read_mono()?, FileSource::new(&wav).read_mono()?);
+        assert_eq!(source.read_segment(0.5, None)?.0.len(), 8000);
+    }
+
+    // Archives that cannot be listed
+    let broken = test_dir.join("broken.zip");
+    fs::write(&broken, b"not a zip archive")?;
+    assert!(Archive::open(&broken).is_err());
+    assert!(Archive::open(&test_dir.join("missing.tar")).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "arrow")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, ramp, setup_test_dir};
+use spectrs::io::arrow::{
+    IpcWriter, SpectrogramBatchBuilder, batch_entries, is_ipc_path, load_ipc, read_ipc,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+use std::path::Path;
+
+#[test]
+fn test_record_batch_round_trip() -> Result<()> {
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray;
+
+    let mut builder = SpectrogramBatchBuilder::new();
+    builder.append("a.wav", "dog", 16000, &ramp(4, 3, 0.0))?;
+    builder.append("b.wav", "cat", 22050, &ramp(4, 5, 100.0))?;
+    // An empty spectrogram is a row without values
+    builder.append("c.wav", "cat", 8000, &[])?;
+    assert_eq!(builder.len(), 3);
+    let batch = builder.finish()?;
+    assert!(builder.is_empty());
+    assert_eq!(batch.num_rows(), 3);
+
+    // Values of every row in one buffer, row by row
+    let values = batch.column_by_name("values").unwrap().as_list::<i32>();
+    assert_eq!(values.values().len(), 4 * 3 + 4 * 5);
+    assert_eq!(values.value_offsets(), &[0, 12, 32, 32]);
+
+    let entries = batch_entries(&batch)?;
+    assert_eq!(entries[0].path, "a.wav");
+    assert_eq!(entries[0].label, "dog");
+    assert_eq!(entries[1].sample_rate, 22050);
+    assert_eq!(entries[0].spectrogram, ramp(4, 3, 0.0));
+    assert_eq!(entries[1].spectrogram, ramp(4, 5, 100.0));
+    assert!(entries[2].spectrogram.is_empty());
+
+    // Ragged spectrograms are rejected, leaving the builder as it was
+    assert!(
+        builder
+            .append("d.wav", ".", 16000, &[vec![0.0; 3], vec![0.0; 2]])
+            .is_err()
+    );
+    assert!(builder.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_ipc_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("features.arrow");
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(512)
+        .n_mels(Some(40))
+        .build()?;
+    assert!(is_ipc_path(&path));
+    assert!(is_ipc_path(Path::new("features.Feather")));
+    assert!(!is_ipc_path(Path::new("features.parquet")));
+
+    // More rows than a batch, and a batch built by the caller
+    let spectrograms: Vec<Vec<Vec<f32>>> =
+        (0..70).map(|i| ramp(40, 10 + i % 7, i as f32)).collect();
+    let mut writer = IpcWriter::create(&path, &params)?;
+    for (i, spec) in spectrograms.iter().enumerate() {
+        writer.append(&format!("in/{}.wav", i), "dog", 16000, spec)?;
+    }
+    let mut builder = SpectrogramBatchBuilder::new();
+    builder.append("extra.wav", "cat", 8000, &ramp(40, 2, -1.0))?;
+    writer.write(&builder.finish()?)?;
+    writer.finish()?;
+
+    let dataset = load_ipc(&path)?;
+    assert_eq!(dataset.params, Some(params));
+    assert_eq!(dataset.entries.len(), spectrograms.len() + 1);
+    for (i, (entry, spec)) in dataset.entries.iter().zip(&spectrograms).enumerate() {
+        assert_eq!(entry.path, format!("in/{}.wav", i));
+        assert_eq!(&entry.spectrogram, spec);
+    }
+    assert_eq!(dataset.entries[70].label, "cat");
+    assert_eq!(dataset.entries[70].spectrogram, ramp(40, 2, -1.0));
+
+    // In memory
+    let mut writer = IpcWriter::new(Vec::new(), &params)?;
+    writer.append("a.wav", "dog", 16000, &ramp(2, 2, 0.0))?;
+    let bytes = writer.finish()?;
+    let dataset = read_ipc(std::io::Cursor::new(bytes))?;
+    assert_eq!(dataset.entries[0].spectrogram, ramp(2, 2, 0.0));
+
+    // Batches with other columns are rejected
+    let other = arrow_array::RecordBatch::try_from_iter([(
+        "path",
+        std::sync::Arc::new(arrow_array::StringArray::from(vec!["a.wav"])) as _,
+    )])?;
+    let mut writer = IpcWriter::new(Vec::new(), &params)?;
+    assert!(writer.write(&other).is_err());
+
+    // Not an Arrow file
+    std::fs::write(test_dir.join("broken.arrow"), b"not arrow")?;
+    assert!(load_ipc(&test_dir.join("broken.arrow")).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,259 @@
+mod common;
+
+use anyhow::Result;
+use common::tone;
+use spectrs::augment::{Augmentation, MaskFill, NoiseColor, SpecAugment, augment};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+fn power(samples: &[f32]) -> f32 {
+    samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32
+}
+
+#[test]
+fn test_noise_at_snr() -> Result<()> {
+    let clean = tone(440.0, 0.5, 1.0, 16000);
+    for color in [NoiseColor::White, NoiseColor::Pink] {
+        let noisy = augment(
+            clean.clone(),
+            16000,
+            &[Augmentation::Noise {
+                color,
+                snr_db: 10.0,
+            }],
+            7,
+        )?;
+        let noise: Vec<f32> = noisy.iter().zip(&clean).map(|(n, c)| n - c).collect();
+        let snr = 10.0 * (power(&clean) / power(&noise)).log10();
+        assert!((snr - 10.0).abs() < 1e-3, "{:?}: {} dB", color, snr);
+    }
+
+    // Pink noise has more power in low frequencies than in high ones, white noise does not
+    let silence_plus = |color| -> Result<Vec<Vec<f32>>> {
+        let noisy = augment(
+            vec![1e-3; 16000],
+            16000,
+            &[Augmentation::Noise {
+                color,
+                snr_db: -40.0,
+            }],
+            3,
+        )?;
+        Ok(compute_spectrogram(
+            &noisy,
+            512,
+            128,
+            512,
+            true,
+            SpectrogramType::Power,
+        ))
+    };
+    let band = |spec: &[Vec<f32>], bins: std::ops::Range<usize>| -> f32 {
+        spec[bins].iter().flatten().sum()
+    };
+    let pink = silence_plus(NoiseColor::Pink)?;
+    let white = silence_plus(NoiseColor::White)?;
+    assert!(band(&pink, 4..20) > 10.0 * band(&pink, 200..216));
+    assert!(band(&white, 4..20) < 2.0 * band(&white, 200..216));
+
+    // Nothing is added to silence
+    assert_eq!(
+        augment(
+            vec![0.0; 100],
+            16000,
+            &[Augmentation::Noise {
+                color: NoiseColor::White,
+                snr_db: 0.0
+            }],
+            1
+        )?,
+        vec![0.0; 100]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_gain_shift_and_stretch() -> Result<()> {
+    let clean = tone(440.0, 0.5, 1.0, 16000);
+    let gain = Augmentation::Gain {
+        min_db: -6.0,
+        max_db: 6.0,
+    };
+    for seed in 0..10 {
+        let louder = augment(clean.clone(), 16000, &[gain], seed)?;
+        let db = 10.0 * (power(&louder) / power(&clean)).log10();
+        assert!((-6.001..=6.001).contains(&db));
+    }
+
+    // Shifts keep the length and move the samples by whole samples
+    let shifted = augment(
+        clean.clone(),
+        16000,
+        &[Augmentation::TimeShift { max_seconds: 0.1 }],
+        5,
+    )?;
+    assert_eq!(shifted.len(), clean.len());
+    let offset = (-1600isize..=1600)
+        .find(|&d| (2000..2010).all(|i| shifted[i] == clean[(i as isize - d) as usize]))
+        .expect("shifted copy of the audio");
+    assert!(offset.abs() <= 1600);
+
+    // Stretching by resampling changes the duration by the inverse of the rate
+    let stretch = |rate: f32| {
+        augment(
+            clean.clone(),
+            16000,
+            &[Augmentation::TimeStretch {
+                min_rate: rate,
+                max_rate: rate,
+            }],
+            0,
+        )
+    };
+    assert!((stretch(2.0)?.len() as f32 - 8000.0).abs() < 100.0);
+    assert!((stretch(0.8)?.len() as f32 - 20000.0).abs() < 100.0);
+    Ok(())
+}
+
+#[test]
+fn test_stretch_short_and_empty_audio() -> Result<()> {
+    let stretch = |audio: Vec<f32>| {
+        augment(
+            audio,
+            16000,
+            &[Augmentation::TimeStretch {
+                min_rate: 0.8,
+                max_rate: 0.8,
+            }],
+            0,
+        )
+    };
+
+    // Shorter than a resampler chunk
+    let short = stretch(tone(440.0, 0.5, 0.002, 16000))?;
+    assert_eq!(short.len(), 40);
+    assert!(short.iter().all(|s| s.is_finite()));
+
+    assert!(stretch(Vec::new())?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_augmentations_are_reproducible() -> Result<()> {
+    let chain = [
+        Augmentation::Noise {
+            color: NoiseColor::Pink,
+            snr_db: 20.0,
+        },
+        Augmentation::Gain {
+            min_db: -3.0,
+            max_db: 3.0,
+        },
+        Augmentation::TimeShift { max_seconds: 0.05 },
+    ];
+    let clean = tone(440.0, 0.5, 0.5, 8000);
+    let a = augment(clean.clone(), 8000, &chain, 42)?;
+    assert_eq!(a, augment(clean.clone(), 8000, &chain, 42)?);
+    assert_ne!(a, augment(clean.clone(), 8000, &chain, 43)?);
+    assert_eq!(augment(clean.clone(), 8000, &[], 42)?, clean);
+    Ok(())
+}
+
+#[test]
+fn test_augmentation_validation() {
+    for invalid in [
+        Augmentation::Noise {
+            color: NoiseColor::White,
+            snr_db: f32::NAN,
+        },
+        Augmentation::Gain {
+            min_db: 6.0,
+            max_db: -6.0,
+        },
+        Augmentation::TimeShift { max_seconds: -1.0 },
+        Augmentation::TimeStretch {
+            min_rate: 0.0,
+            max_rate: 1.1,
+        },
+    ] {
+        assert!(invalid.validate().is_err(), "{:?}", invalid);
+        assert!(augment(vec![0.1; 100], 8000, &[invalid], 0).is_err());
+    }
+}
+
+#[test]
+fn test_spec_augment_masks() {
+    let spec: Vec<Vec<f32>> = (0..40)
+        .map(|f| (0..100).map(|t| (1 + f * 100 + t) as f32).collect())
+        .collect();
+    let masks = SpecAugment {
+        freq_masks: 2,
+        max_freq_width: 8,
+        time_masks: 3,
+        max_time_width: 10,
+        fill: MaskFill::Value(-1.0),
+    };
+    let mut masked = spec.clone();
+    masks.apply(&mut masked, 11);
+
+    // Whole rows and whole columns are masked, within the maximum widths
+    let masked_rows: Vec<usize> = (0..40)
+        .filter(|&f| masked[f].iter().all(|&v| v == -1.0))
+        .collect();
+    let masked_frames: Vec<usize> = (0..100)
+        .filter(|&t| masked.iter().all(|row| row[t] == -1.0))
+        .collect();
+    assert!(masked_rows.len() <= 16);
+    assert!(masked_frames.len() <= 30);
+    for (f, row) in masked.iter().enumerate() {
+        for (t, &v) in row.iter().enumerate() {
+            let in_mask = masked_rows.contains(&f) || masked_frames.contains(&t);
+            assert_eq!(v == -1.0, in_mask);
+            if !in_mask {
+                assert_eq!(v, spec[f][t]);
+            }
+        }
+    }
+
+    // Reproducible for a seed, in any precision
+    let mut again = spec.clone();
+    masks.apply(&mut again, 11);
+    assert_eq!(again, masked);
+    let mut f64_spec: Vec<Vec<f64>> = spec
+        .iter()
+        .map(|row| row.iter().map(|&v| v as f64).collect())
+        .collect();
+    masks.apply(&mut f64_spec, 11);
+    assert!(
+        f64_spec
+            .iter()
+            .flatten()
+            .zip(masked.iter().flatten())
+            .all(|(&a, &b)| a == b as f64)
+    );
+
+    // Masks filled with the mean, never wider than the spectrogram
+    let mean = spec.iter().flatten().sum::<f32>() / 4000.0;
+    let mut wide = spec.clone();
+    SpecAugment {
+        freq_masks: 1,
+        max_freq_width: 1000,
+        time_masks: 0,
+        max_time_width: 0,
+        fill: MaskFill::Mean,
+    }
+    .apply(&mut wide, 4);
+    assert!(
+        wide.iter()
+            .flatten()
+            .all(|&v| v == mean || spec.iter().flatten().any(|&s| s == v))
+    );
+
+    assert!(
+        SpecAugment {
+            fill: MaskFill::Value(f32::NAN),
+            ..masks
+        }
+        .validate()
+        .is_err()
+    );
+}
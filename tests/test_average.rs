@@ -0,0 +1,54 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::average::AverageAccumulator;
+
+#[test]
+fn test_average_spectrum_and_spectrogram() -> Result<()> {
+    // A long input rising over time and a short constant one
+    let a = vec![vec![1.0, 1.0, 3.0, 3.0], vec![0.0, 0.0, 2.0, 2.0]];
+    let b = vec![vec![5.0], vec![4.0]];
+
+    // Accumulating file by file and merging partial sums give the same average
+    let mut sequential = AverageAccumulator::new(2);
+    sequential.add(&a)?;
+    sequential.add(&b)?;
+    let mut first = AverageAccumulator::new(2);
+    first.add(&a)?;
+    let mut second = AverageAccumulator::new(2);
+    second.add(&b)?;
+    let merged = first.merge(second)?.merge(AverageAccumulator::new(2))?;
+
+    let average = sequential.finish()?;
+    assert_eq!(merged.finish()?, average);
+    assert_eq!((average.n_inputs, average.n_frames), (2, 5));
+    // The spectrum weighs every frame equally
+    assert!((average.spectrum[0] - 13.0 / 5.0).abs() < 1e-6);
+    assert!((average.spectrum[1] - 8.0 / 5.0).abs() < 1e-6);
+    // The spectrogram weighs every input equally: the long one pooled, the short one stretched
+    assert_eq!(average.spectrogram, vec![vec![3.0, 4.0], vec![2.0, 3.0]]);
+    Ok(())
+}
+
+#[test]
+fn test_average_rejects_mismatches() -> Result<()> {
+    let mut average = AverageAccumulator::new(4);
+    // Inputs without frames are skipped
+    average.add(&[Vec::new(), Vec::new()])?;
+    assert!(matches!(
+        average.clone().finish(),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+
+    average.add(&[vec![1.0; 8], vec![1.0; 8]])?;
+    assert!(matches!(
+        average.add(&[vec![1.0; 8]]),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    let mut other = AverageAccumulator::new(4);
+    other.add(&[vec![1.0; 8]])?;
+    assert!(average.clone().merge(other).is_err());
+    let mut wider = AverageAccumulator::new(8);
+    wider.add(&[vec![1.0; 8], vec![1.0; 8]])?;
+    assert!(average.merge(wider).is_err());
+    Ok(())
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "cache")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use serde::{Deserialize, Serialize};
+use spectrs::io::cache::SpectrogramCache;
+use spectrs::spectrogram::params::SpectrogramParams;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Metadata {
+    sample_rate: u32,
+}
+
+#[test]
+fn test_cache_keys() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test.wav");
+    create_test_wav(&audio_path, 0.5, 16000, 1, 16)?;
+
+    let params = SpectrogramParams::default();
+    let key = SpectrogramCache::key_for_file(&audio_path, &params)?;
+    assert_eq!(key.len(), 32);
+    // Hashing the file as it is read matches hashing its bytes
+    assert_eq!(
+        key,
+        SpectrogramCache::key(&std::fs::read(&audio_path)?, &params)?
+    );
+
+    // Any change of the settings or of the audio gives another key
+    let other_params = SpectrogramParams::builder()
+        .n_fft(1024)
+        .win_length(1024)
+        .build()?;
+    assert_ne!(
+        key,
+        SpectrogramCache::key_for_file(&audio_path, &other_params)?
+    );
+    create_test_wav(&audio_path, 0.5, 22050, 1, 16)?;
+    assert_ne!(key, SpectrogramCache::key_for_file(&audio_path, &params)?);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cache_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let cache = SpectrogramCache::new(test_dir.join("cache"));
+    let key = SpectrogramCache::key(b"audio", &"settings")?;
+    assert!(cache.load::<Metadata>(&key)?.is_none());
+
+    let spec = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
+    let metadata = Metadata { sample_rate: 16000 };
+    cache.store(&key, &spec, &metadata)?;
+    assert_eq!(cache.load::<Metadata>(&key)?, Some((spec, metadata)));
+
+    // Only the entry itself is left in the cache directory
+    assert_eq!(std::fs::read_dir(cache.dir())?.count(), 2);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_complex_test_wav, setup_test_dir};
+use spectrs::cancel::CancellationToken;
+use spectrs::error::SpectrsError;
+use spectrs::io::audio::MonoChunks;
+use spectrs::spectrogram::fft::FftBackendType;
+use spectrs::spectrogram::stft::{
+    Float, SpectrogramType, create_hann_window, par_compute_spectrogram_cancellable,
+    par_compute_spectrogram_with_fft,
+};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+#[test]
+fn test_cancellation_token() {
+    let token = CancellationToken::new();
+    assert!(!token.is_cancelled());
+    assert!(token.check().is_ok());
+
+    // Clones share the state, e.g. with a signal handler
+    let handle = token.clone();
+    handle.cancel();
+    assert!(token.is_cancelled());
+    assert!(matches!(token.check(), Err(SpectrsError::Cancelled(_))));
+
+    // A flag owned by the caller
+    let flag = Arc::new(AtomicBool::new(true));
+    assert!(CancellationToken::from(flag).is_cancelled());
+}
+
+#[test]
+fn test_cancellable_spectrogram() -> Result<()> {
+    let samples: Vec<f32> = (0..50000).map(|t| (t as f32 * 0.05).sin()).collect();
+    let window = create_hann_window(512, true);
+    let fft = f32::plan_fft(&*FftBackendType::RustFft.backend()?, 512)?;
+
+    // Not cancelled: the spectrogram of the parallel STFT
+    let token = CancellationToken::new();
+    let spec = par_compute_spectrogram_cancellable(
+        &samples,
+        &*fft,
+        128,
+        &window,
+        true,
+        SpectrogramType::Power,
+        &token,
+    )?;
+    let expected = par_compute_spectrogram_with_fft(
+        &samples,
+        &*fft,
+        128,
+        &window,
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(spec, expected);
+
+    // Cancelled: no partial spectrogram
+    token.cancel();
+    let result = par_compute_spectrogram_cancellable(
+        &samples,
+        &*fft,
+        128,
+        &window,
+        true,
+        SpectrogramType::Power,
+        &token,
+    );
+    assert!(matches!(result, Err(SpectrsError::Cancelled(_))));
+    Ok(())
+}
+
+#[test]
+fn test_cancel_mono_chunks() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("long.wav");
+    create_complex_test_wav(&audio_path, 5.0, 16000, 1, 16)?;
+
+    let token = CancellationToken::new();
+    let mut chunks = MonoChunks::open(&audio_path)?.with_cancellation(token.clone());
+    assert_eq!(chunks.next_chunk(4096)?.len(), 4096);
+
+    // Decoding stops at the next chunk
+    token.cancel();
+    assert!(matches!(
+        chunks.next_chunk(4096),
+        Err(SpectrsError::Cancelled(_))
+    ));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use anyhow::Result;
+use common::noise;
+use spectrs::error::SpectrsError;
+use spectrs::features::cepstrum::{CppParams, cepstral_peak_prominence, real_cepstrum};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+const SR: u32 = 16000;
+const N_FFT: usize = 1024;
+
+/// Glottal-like pulse train: a decaying resonance repeated every `period` samples
+fn pulse_train(period: usize, n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|t| {
+            let phase = (t % period) as f32;
+            (-phase / 20.0).exp() * (2.0 * std::f32::consts::PI * 700.0 * phase / SR as f32).cos()
+        })
+        .collect()
+}
+
+#[test]
+fn test_real_cepstrum() -> Result<()> {
+    // A flat magnitude of e has a log spectrum of 1: all the cepstrum at quefrency 0
+    let flat = vec![vec![std::f32::consts::E; 2]; N_FFT / 2 + 1];
+    let cepstrum = real_cepstrum(&flat, SpectrogramType::Magnitude, N_FFT)?;
+    assert_eq!(cepstrum.len(), N_FFT / 2 + 1);
+    assert_eq!(cepstrum[0].len(), 2);
+    assert!((cepstrum[0][0] - 1.0).abs() < 1e-5);
+    assert!(cepstrum[1..].iter().flatten().all(|c| c.abs() < 1e-5));
+
+    // Powers are squared magnitudes
+    let audio = pulse_train(128, SR as usize);
+    let magnitude =
+        compute_spectrogram(&audio, N_FFT, 256, N_FFT, true, SpectrogramType::Magnitude);
+    let power = compute_spectrogram(&audio, N_FFT, 256, N_FFT, true, SpectrogramType::Power);
+    let from_magnitude = real_cepstrum(&magnitude, SpectrogramType::Magnitude, N_FFT)?;
+    let from_power = real_cepstrum(&power, SpectrogramType::Power, N_FFT)?;
+    for (a, b) in from_magnitude
+        .iter()
+        .flatten()
+        .zip(from_power.iter().flatten())
+    {
+        assert!((a - b).abs() < 1e-3);
+    }
+
+    // The bins of another FFT size
+    assert!(matches!(
+        real_cepstrum(&flat, SpectrogramType::Magnitude, 2 * N_FFT),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_cepstral_peak_prominence() -> Result<()> {
+    let params = CppParams::default();
+    let cpp = |audio: &[f32]| -> Result<Vec<_>> {
+        let power = compute_spectrogram(audio, N_FFT, 256, N_FFT, true, SpectrogramType::Power);
+        let cepstrum = real_cepstrum(&power, SpectrogramType::Power, N_FFT)?;
+        Ok(cepstral_peak_prominence(&cepstrum, SR, &params)?)
+    };
+
+    // Pulses every 128 samples: a cepstral peak at 8 ms (125 Hz) standing out of the trend
+    let voiced = cpp(&pulse_train(128, SR as usize))?;
+    assert_eq!(voiced.len(), 59);
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    for peak in &voiced[2..voiced.len() - 2] {
+        assert!((peak.quefrency - 0.008).abs() < 1e-6);
+        assert!((peak.f0 - 125.0).abs() < 1e-3);
+    }
+    let voiced_cpp = mean(&voiced.iter().map(|peak| peak.cpp_db).collect::<Vec<_>>());
+
+    // Noise has no periodicity
+    let unvoiced = cpp(&noise(SR as usize))?;
+    let unvoiced_cpp = mean(&unvoiced.iter().map(|peak| peak.cpp_db).collect::<Vec<_>>());
+    assert!(voiced_cpp > unvoiced_cpp + 20.0);
+    Ok(())
+}
+
+#[test]
+fn test_cpp_params_validation() {
+    // The period of 60 Hz at 16 kHz (267 samples) needs n_fft of 534
+    assert!(CppParams::default().validate(SR, N_FFT).is_ok());
+    assert!(matches!(
+        CppParams::default().validate(SR, 512),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    let reversed = CppParams {
+        f0_min: 330.0,
+        f0_max: 60.0,
+        ..CppParams::default()
+    };
+    assert!(reversed.validate(SR, N_FFT).is_err());
+    let late_trend = CppParams {
+        trend_from: 0.01,
+        ..CppParams::default()
+    };
+    assert!(late_trend.validate(SR, N_FFT).is_err());
+}
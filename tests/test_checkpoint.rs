@@ -0,0 +1,73 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, setup_test_dir};
+use spectrs::io::checkpoint::Checkpoint;
+use spectrs::spectrogram::params::SpectrogramParams;
+use std::fs;
+use std::time::Duration;
+
+fn params(n_fft: usize) -> Result<SpectrogramParams> {
+    Ok(SpectrogramParams::builder()
+        .n_fft(n_fft)
+        .hop_length(n_fft / 4)
+        .win_length(n_fft)
+        .build()?)
+}
+
+#[test]
+fn test_checkpoint_resumes() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("state/checkpoint.json");
+
+    let mut checkpoint = Checkpoint::open(&path, &params(512)?)?;
+    assert!(checkpoint.is_empty());
+    checkpoint.complete("in/a.wav", (None, None));
+    checkpoint.complete("in/b.wav", (Some(0.5), Some(1.5)));
+    // Completing an input again records it once
+    checkpoint.complete("in/a.wav", (None, None));
+    assert_eq!(checkpoint.len(), 2);
+    checkpoint.save()?;
+    assert!(path.exists());
+    assert!(!test_dir.join("state/checkpoint.json.tmp").exists());
+
+    let checkpoint = Checkpoint::open(&path, &params(512)?)?;
+    assert_eq!(checkpoint.len(), 2);
+    assert!(checkpoint.is_completed("in/a.wav", (None, None)));
+    assert!(checkpoint.is_completed("in/b.wav", (Some(0.5), Some(1.5))));
+    // Other segments are not completed
+    assert!(!checkpoint.is_completed("in/b.wav", (None, None)));
+    assert!(!checkpoint.is_completed("in/c.wav", (None, None)));
+    assert_eq!(checkpoint.completed()[1].start, Some(0.5));
+
+    // A checkpoint of other parameters is not resumed
+    let error = Checkpoint::open(&path, &params(1024)?)
+        .err()
+        .expect("params differ");
+    assert!(error.to_string().contains("other parameters"));
+
+    fs::write(&path, "{ not json")?;
+    assert!(Checkpoint::open(&path, &params(512)?).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_checkpoint_saves_when_due() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("checkpoint.json");
+
+    let mut checkpoint = Checkpoint::open(&path, &params(512)?)?;
+    // Nothing to save yet
+    assert!(!checkpoint.save_if_due(Duration::ZERO)?);
+    checkpoint.complete("in/a.wav", (None, None));
+    assert!(!checkpoint.save_if_due(Duration::from_secs(3600))?);
+    assert!(!path.exists());
+    assert!(checkpoint.save_if_due(Duration::ZERO)?);
+    assert!(!checkpoint.save_if_due(Duration::ZERO)?);
+    assert_eq!(Checkpoint::open(&path, &params(512)?)?.len(), 1);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -1,10 +1,14 @@
 mod common;
 
 use anyhow::Result;
-use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use common::{
+    cleanup_test_dir, create_complex_test_wav, create_float_test_wav, create_test_wav,
+    setup_test_dir,
+};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 /// Helper function to get the path to the compiled binary
 fn get_binary_path() -> PathBuf {
@@ -403,3 +407,5094 @@ fn test_cli_nonexistent_input() -> Result<()> {
     cleanup_test_dir(&test_dir)?;
     Ok(())
 }
+
+/// Test CLI time-range selection with --start/--end
+#[test]
+fn test_cli_start_end_segment() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("long.wav");
+    let expected_output = test_dir.join("long.png");
+
+    create_test_wav(&input_wav, 3.0, 16000, 1, 16)?;
+
+    // Mix timestamp and plain seconds notation
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--start")
+        .arg("00:00:01")
+        .arg("--end")
+        .arg("2")
+        .arg("--n-fft")
+        .arg("512")
+        .arg("--win-length")
+        .arg("512")
+        .arg("--hop-length")
+        .arg("256")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // One second of audio at 16 kHz: (16000 - 512) / 256 + 1 frames
+    let (width, height) = image::image_dimensions(&expected_output)?;
+    assert_eq!(width, 61);
+    assert_eq!(height, 257);
+
+    // Invalid range should fail
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--start")
+        .arg("2")
+        .arg("--end")
+        .arg("1")
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success(), "CLI should fail for end < start");
+
+    // Timestamps that are not finite, negative in any field or with minutes or seconds
+    // beyond 59
+    for start in [
+        "nan", "inf", "-1", "1:-30", "-0:30", "1:90", "1:60:00", "0:00:60",
+    ] {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .arg(format!("--start={}", start))
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(
+            !output.status.success(),
+            "--start {} should be rejected",
+            start
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("Invalid time"),
+            "{:?}",
+            output
+        );
+    }
+    // Seconds beyond 59 are fine on their own
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--start", "0:00:01.5", "--end", "90"])
+        .args([
+            "--n-fft",
+            "512",
+            "--win-length",
+            "512",
+            "--hop-length",
+            "256",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success(), "{:?}", output);
+    let (width, _) = image::image_dimensions(&expected_output)?;
+    assert_eq!(width, (24000 - 512) / 256 + 1);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --overlap as an alternative to --hop-length
+#[test]
+fn test_cli_overlap_percentage() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("overlap.wav");
+    let expected_output = test_dir.join("overlap.png");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    // 75% overlap of 512 samples is a hop of 128
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--n-fft")
+        .arg("512")
+        .arg("--win-length")
+        .arg("512")
+        .arg("--overlap")
+        .arg("75%")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let (width, _) = image::image_dimensions(&expected_output)?;
+    assert_eq!(width as usize, (16000 - 512) / 128 + 1);
+
+    // --overlap and --hop-length are mutually exclusive
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--overlap")
+        .arg("50%")
+        .arg("--hop-length")
+        .arg("256")
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    // Overlap must be below 100%
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--overlap")
+        .arg("100%")
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --win-ms/--hop-ms resolved against the output sample rate
+#[test]
+fn test_cli_window_in_milliseconds() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("ms.wav");
+    create_test_wav(&input_wav, 1.0, 22050, 1, 16)?;
+
+    let run = |args: &[&str], output: &str| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(["--format", "npy", "--sidecar"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // 25 ms windows every 10 ms at 16 kHz, with a 512-point FFT
+    let output = run(
+        &["--sr", "16000", "--win-ms", "25", "--hop-ms", "10ms"],
+        "16k",
+    );
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let metadata = spectrs::io::metadata::read_metadata(&test_dir.join("16k/ms.meta.json"))?;
+    assert_eq!(metadata.sample_rate, 16000);
+    assert_eq!(metadata.params.win_length, 400);
+    assert_eq!(metadata.params.hop_length, 160);
+    assert_eq!(metadata.params.n_fft, 512);
+    assert_eq!(metadata.shape[0], 257);
+
+    // The same durations at 8 kHz, the hop defaulting to a quarter of the window
+    let output = run(&["--sr", "8000", "--win-ms", "25", "--n-fft", "1024"], "8k");
+    assert!(output.status.success());
+    let metadata = spectrs::io::metadata::read_metadata(&test_dir.join("8k/ms.meta.json"))?;
+    assert_eq!(metadata.params.win_length, 200);
+    assert_eq!(metadata.params.hop_length, 50);
+    assert_eq!(metadata.params.n_fft, 1024);
+
+    // Without --sr the durations are resolved at the sample rate of the input
+    let output = run(&["--win-ms", "25"], "native");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let metadata = spectrs::io::metadata::read_metadata(&test_dir.join("native/ms.meta.json"))?;
+    assert_eq!(metadata.sample_rate, 22050);
+    assert_eq!(metadata.params.win_length, 551);
+    assert_eq!(metadata.params.hop_length, 137);
+    assert_eq!(metadata.params.n_fft, 1024);
+
+    // Milliseconds conflict with sample counts
+    assert!(
+        !run(
+            &["--sr", "16000", "--hop-ms", "10", "--hop-length", "160"],
+            "invalid"
+        )
+        .status
+        .success()
+    );
+    assert!(
+        !run(&["--sr", "16000", "--win-ms", "0"], "invalid")
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI rejects invalid parameter combinations with a descriptive error
+#[test]
+fn test_cli_invalid_parameters() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("invalid.wav");
+
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    // win_length larger than n_fft
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--n-fft")
+        .arg("512")
+        .arg("--win-length")
+        .arg("1024")
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("win_length"));
+
+    // f_max above the Nyquist frequency of the file
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--n-mels")
+        .arg("40")
+        .arg("--f-max")
+        .arg("12000")
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Nyquist"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the presets of --sr and the f_max capped to its Nyquist frequency
+#[test]
+fn test_cli_sr_presets() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 0.5, 22050, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args([
+                "a.wav",
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+                "--format",
+                "npy",
+            ])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let npy = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"));
+
+    let output = run(&["--sr", "16000"]);
+    assert!(output.status.success(), "{:?}", output);
+    let numeric = npy()?;
+    let output = run(&["--sr", "speech16k"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(npy()?, numeric);
+
+    // Mel bands up to 11 kHz stop at 8 kHz once resampled to 16 kHz
+    let output = run(&[
+        "--sr",
+        "speech16k",
+        "--n-mels",
+        "40",
+        "--f-max",
+        "11000",
+        "--sidecar",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Nyquist"));
+    let sidecar: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.meta.json"))?)?;
+    assert_eq!(sidecar["params"]["f_max"], 8000.0);
+
+    assert!(!run(&["--sr", "voice8k"]).status.success());
+    assert!(!run(&["--sr", "0"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI batch summary figure
+#[test]
+fn test_cli_summary_png() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("dataset");
+    let summary = test_dir.join("summary.png");
+
+    fs::create_dir_all(input_dir.join("dog"))?;
+    fs::create_dir_all(input_dir.join("cat"))?;
+    create_test_wav(&input_dir.join("dog").join("a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&input_dir.join("dog").join("b.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&input_dir.join("cat").join("c.wav"), 1.5, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.to_str().unwrap())
+        .arg("--summary-png")
+        .arg(summary.to_str().unwrap())
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Three panels side by side
+    let (width, height) = image::image_dimensions(&summary)?;
+    assert_eq!(width, 1080);
+    assert_eq!(height, 300);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI average spectrogram and spectrum of a directory
+#[test]
+fn test_cli_average_out() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("dataset");
+    fs::create_dir_all(&input_dir)?;
+    create_test_wav(&input_dir.join("a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&input_dir.join("b.wav"), 1.5, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["dataset", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // One spectrogram of --average-frames frames, brightest on the row of the 440 Hz sine
+    let output = run(&[
+        "--format",
+        "npy",
+        "--average-out",
+        "average.npy",
+        "--average-frames",
+        "32",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let average = spectrs::io::export::load_spectrogram_npy(&test_dir.join("average.npy"))?;
+    assert_eq!((average.len(), average[0].len()), (257, 32));
+    let loudest = (0..average.len())
+        .max_by(|&a, &b| average[a][16].total_cmp(&average[b][16]))
+        .unwrap();
+    assert_eq!(loudest, 14);
+
+    // The long-term spectrum has one row per bin, peaking at 440 Hz
+    let content = fs::read_to_string(test_dir.join("average.spectrum.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "frequency_hz,mean,mean_db");
+    assert_eq!(lines.len(), 258);
+    let rows: Vec<Vec<f32>> = lines[1..]
+        .iter()
+        .map(|line| line.split(',').map(|v| v.parse().unwrap()).collect())
+        .collect();
+    let peak = rows.iter().max_by(|a, b| a[1].total_cmp(&b[1])).unwrap();
+    assert!((peak[0] - 437.5).abs() < 1e-3, "{:?}", peak);
+    assert!((peak[2] - 10.0 * peak[1].log10()).abs() < 1e-3);
+
+    // Images of mel spectrograms
+    let output = run(&["--n-mels", "64", "--average-out", "average.png"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        image::image_dimensions(test_dir.join("average.png"))?,
+        (256, 64)
+    );
+
+    // Other formats and mixed sample rates
+    let output = run(&["--average-out", "average.csv"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    create_test_wav(&input_dir.join("c.wav"), 0.5, 22050, 1, 16)?;
+    let output = run(&["--average-out", "average.png"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("same sample rate"));
+    assert!(
+        run(&["--average-out", "average.png", "--sr", "16000"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI HTML report embedding a thumbnail of every input
+#[test]
+fn test_cli_report_html() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("dataset");
+    let report = test_dir.join("out").join("report.html");
+
+    fs::create_dir_all(input_dir.join("dog"))?;
+    create_test_wav(&input_dir.join("dog").join("a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&input_dir.join("b.wav"), 1.0, 16000, 1, 16)?;
+    fs::write(input_dir.join("broken.wav"), b"not audio")?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.to_str().unwrap())
+        .args(["--format", "npy", "--n-mels", "32"])
+        .arg("--report-html")
+        .arg(report.to_str().unwrap())
+        .output()
+        .expect("Failed to execute spectrs");
+    // The report is written even though an input failed
+    assert_eq!(output.status.code(), Some(2));
+
+    let html = fs::read_to_string(&report)?;
+    assert_eq!(html.matches("data:image/png;base64,").count(), 2);
+    assert!(html.contains("a.wav"));
+    assert!(html.contains("0.50s"));
+    assert!(html.contains("32&times;"));
+    assert!(html.contains("broken.wav: "));
+
+    // Images cannot be rendered from the log energies of filter bank presets
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.join("b.wav").to_str().unwrap())
+        .args(["--compat", "kaldi", "--format", "npy"])
+        .arg("--report-html")
+        .arg(report.to_str().unwrap())
+        .output()
+        .expect("Failed to execute spectrs");
+    assert_eq!(output.status.code(), Some(3));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --dump-resampled writes the audio fed to the STFT
+#[test]
+fn test_cli_dump_resampled() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("dump.wav");
+    let expected_dump = test_dir.join("dump.resampled.wav");
+
+    create_test_wav(&input_wav, 1.0, 44100, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--sr")
+        .arg("16000")
+        .arg("--dump-resampled")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let reader = hound::WavReader::open(&expected_dump)?;
+    assert_eq!(reader.spec().sample_rate, 16000);
+    assert!((reader.duration() as i64 - 16000).abs() < 100);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --format csv with locale options
+#[test]
+fn test_cli_csv_output() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("table.wav");
+    let expected_output = test_dir.join("table.csv");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--format")
+        .arg("csv")
+        .arg("--n-fft")
+        .arg("512")
+        .arg("--win-length")
+        .arg("512")
+        .arg("--csv-delimiter")
+        .arg(";")
+        .arg("--csv-decimal")
+        .arg(",")
+        .arg("--csv-precision")
+        .arg("4")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // One line per frequency bin, fixed number of decimals with a comma separator
+    let content = fs::read_to_string(&expected_output)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 257);
+    for field in lines[0].split(';') {
+        let (_, decimals) = field.split_once(',').expect("missing decimal separator");
+        assert_eq!(decimals.len(), 4);
+    }
+    assert!(!test_dir.join("table.png").exists());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI rejects ambiguous CSV options
+#[test]
+fn test_cli_csv_ambiguous_options() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("table.wav");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--format")
+        .arg("csv")
+        .arg("--csv-decimal")
+        .arg(",")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid CSV options"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --lenient salvages truncated files instead of failing
+#[test]
+fn test_cli_lenient_truncated_file() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("truncated.wav");
+    let expected_output = test_dir.join("truncated.png");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+    let bytes = fs::read(&input_wav)?;
+    fs::write(&input_wav, &bytes[..bytes.len() / 2 + 1])?;
+
+    // Strict mode rejects the file
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    // Lenient mode recovers the first half and warns about it
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .arg("--lenient")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("WARN"), "missing warning: {}", stderr);
+    assert!(stderr.contains("of 1.000s"), "missing duration: {}", stderr);
+    assert!(expected_output.exists());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --stats-out writes one row of features per input file
+#[test]
+fn test_cli_stats_out() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    let stats = test_dir.join("stats.csv");
+
+    fs::create_dir_all(input_dir.join("sub"))?;
+    create_test_wav(&input_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&input_dir.join("sub").join("b.wav"), 0.5, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.to_str().unwrap())
+        .arg("--stats-out")
+        .arg(stats.to_str().unwrap())
+        .arg("--csv-delimiter")
+        .arg(";")
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let content = fs::read_to_string(&stats)?;
+    let mut lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("file;duration_s;rms_mean"));
+
+    lines[1..].sort();
+    let row: Vec<&str> = lines[1].split(';').collect();
+    assert_eq!(row[0], "a.wav");
+    assert_eq!(row.len(), 13);
+    let duration: f32 = row[1].parse()?;
+    assert!((duration - 1.0).abs() < 1e-3);
+
+    // 440 Hz sine: the spectral centroid sits near the tone
+    let centroid: f32 = row[5].parse()?;
+    assert!((centroid - 440.0).abs() < 100.0, "centroid {}", centroid);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --width/--height shrink the image, with and without the fused pooling path
+#[test]
+fn test_cli_image_size_limits() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("long.wav");
+    let output_png = test_dir.join("long.png");
+    let stats = test_dir.join("stats.csv");
+
+    create_test_wav(&input_wav, 5.0, 16000, 1, 16)?;
+
+    // Image only: frames are pooled while they are computed
+    // With statistics: the full spectrogram is kept and pooled afterwards
+    for extra in [vec![], vec!["--stats-out", stats.to_str().unwrap()]] {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--width", "100", "--height", "64"])
+            .args(&extra)
+            .output()
+            .expect("Failed to execute spectrs");
+
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let (width, height) = image::image_dimensions(&output_png)?;
+        assert_eq!(width, 100);
+        assert_eq!(height, 64);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test CLI --sidecar writes versioned metadata describing each output
+#[test]
+fn test_cli_sidecar() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("meta.wav");
+    let sidecar = test_dir.join("meta.meta.json");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--sidecar", "--n-mels", "40", "--width", "20"])
+        .output()
+        .expect("Failed to execute spectrs");
+
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let metadata = spectrs::io::metadata::read_metadata(&sidecar)?;
+    assert_eq!(
+        metadata.schema_version,
+        spectrs::io::metadata::SCHEMA_VERSION
+    );
+    assert_eq!(metadata.sample_rate, 16000);
+    assert_eq!(metadata.params.n_mels, Some(40));
+    assert_eq!(metadata.shape, [40, 20]);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_cmvn() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("train");
+    let infer_dir = test_dir.join("infer");
+    let stats_path = test_dir.join("cmvn.json");
+    fs::create_dir_all(&input_dir)?;
+    fs::create_dir_all(&infer_dir)?;
+
+    create_test_wav(&input_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    create_complex_test_wav(&input_dir.join("b.wav"), 1.0, 16000, 2, 16)?;
+    create_complex_test_wav(&infer_dir.join("c.wav"), 0.5, 16000, 1, 16)?;
+
+    let common_args = [
+        "--format",
+        "csv",
+        "--n-mels",
+        "16",
+        "--n-fft",
+        "512",
+        "--win-length",
+        "512",
+    ];
+
+    // Pass over the training set computes and applies the statistics
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.to_str().unwrap())
+        .args(common_args)
+        .args(["--cmvn-out", stats_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stats = spectrs::spectrogram::cmvn::CmvnStats::load(&stats_path)?;
+    assert_eq!(stats.mean.len(), 16);
+
+    // Each band has zero mean over the whole training set
+    let mut sums = vec![0.0f64; 16];
+    let mut n_frames = 0;
+    for name in ["a.csv", "b.csv"] {
+        let content = fs::read_to_string(input_dir.join(name))?;
+        for (sum, line) in sums.iter_mut().zip(content.lines()) {
+            let values: Vec<f64> = line.split(',').map(|v| v.parse().unwrap()).collect();
+            *sum += values.iter().sum::<f64>();
+        }
+        n_frames += content.lines().next().unwrap().split(',').count();
+    }
+    assert_eq!(n_frames as u64, stats.n_frames);
+    for sum in sums {
+        assert!((sum / n_frames as f64).abs() < 1e-3, "mean {}", sum);
+    }
+
+    // Inference data reuses the saved statistics
+    let output = Command::new(get_binary_path())
+        .arg(infer_dir.to_str().unwrap())
+        .args(common_args)
+        .args(["--cmvn-in", stats_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(infer_dir.join("c.csv").exists());
+
+    // Images cannot hold normalized values
+    let output = Command::new(get_binary_path())
+        .arg(infer_dir.to_str().unwrap())
+        .args(["--cmvn-in", stats_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_kaldi_compat() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("speech.wav");
+    let output_csv = test_dir.join("speech.csv");
+
+    create_complex_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--compat", "kaldi", "--format", "csv"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // 23 filters, 25 ms frames every 10 ms with snipped edges
+    let content = fs::read_to_string(&output_csv)?;
+    assert_eq!(content.lines().count(), 23);
+    assert_eq!(content.lines().next().unwrap().split(',').count(), 98);
+
+    // Log energies cannot be rendered as images
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--compat", "kaldi"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_torchaudio_compat() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("torch.wav");
+    let output_csv = test_dir.join("torch.csv");
+
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    // Preset defaults (n_fft=400, hop=200, 128 mels) apply unless given explicitly
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args([
+            "--compat",
+            "torchaudio",
+            "--format",
+            "csv",
+            "--n-mels",
+            "64",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let content = fs::read_to_string(&output_csv)?;
+    assert_eq!(content.lines().count(), 64);
+    assert_eq!(content.lines().next().unwrap().split(',').count(), 81);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_device_gpu_validation() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("gpu.wav");
+
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    // The GPU FFT only supports power-of-two sizes, checked before any device is opened
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--device", "gpu", "--n-fft", "1000", "--win-length", "1000"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("power of two"));
+
+    // Compatibility presets always run on the CPU
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--device", "gpu", "--compat", "torchaudio"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    // Without the gpu feature the device cannot be selected
+    if !cfg!(feature = "gpu") {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--device", "gpu"])
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("GPU feature not enabled"));
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that --deterministic outputs are identical across runs and across input modes
+#[test]
+fn test_cli_deterministic() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    fs::create_dir_all(&input_dir)?;
+
+    create_complex_test_wav(&input_dir.join("a.wav"), 1.0, 16000, 2, 16)?;
+    create_test_wav(&input_dir.join("b.wav"), 0.5, 16000, 1, 16)?;
+
+    let run = |input: &Path, output_dir: &Path| -> Result<()> {
+        let output = Command::new(get_binary_path())
+            .arg(input.to_str().unwrap())
+            .args(["--format", "npy", "--n-mels", "32", "--deterministic"])
+            .args(["--output-dir", output_dir.to_str().unwrap()])
+            .args(["--cmvn-out", output_dir.join("cmvn.json").to_str().unwrap()])
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok(())
+    };
+
+    let first = test_dir.join("first");
+    let second = test_dir.join("second");
+    run(&input_dir, &first)?;
+    run(&input_dir, &second)?;
+    for name in ["a.npy", "b.npy", "cmvn.json"] {
+        assert_eq!(fs::read(first.join(name))?, fs::read(second.join(name))?);
+    }
+
+    // A single file with its own statistics gives the same bytes as in a directory of one
+    let single = test_dir.join("single");
+    fs::create_dir_all(test_dir.join("only"))?;
+    fs::copy(input_dir.join("a.wav"), test_dir.join("only/a.wav"))?;
+    run(&test_dir.join("only/a.wav"), &single)?;
+    let batch = test_dir.join("batch");
+    run(&test_dir.join("only"), &batch)?;
+    assert_eq!(
+        fs::read(single.join("a.npy"))?,
+        fs::read(batch.join("a.npy"))?
+    );
+
+    // The GPU cannot guarantee bit-identical results
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.join("a.wav").to_str().unwrap())
+        .args(["--deterministic", "--device", "gpu"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--deterministic"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test double-precision numerical outputs and their restrictions
+#[test]
+fn test_cli_precision_f64() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--format", "npy", "--n-mels", "32", "--precision", "f64"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let bytes = fs::read(test_dir.join("tone.npy"))?;
+    assert!(String::from_utf8_lossy(&bytes[..128]).contains("'descr': '<f8'"));
+
+    // Same values as in single precision, up to rounding
+    let spec = spectrs::io::export::read_spectrogram_npy(&bytes[..])?;
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--format", "npy", "--n-mels", "32"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success());
+    let spec_f32 = spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"))?;
+    assert_eq!(spec.len(), spec_f32.len());
+    let peak = spec_f32.iter().flatten().fold(0.0f32, |acc, &v| acc.max(v));
+    for (row, row_f32) in spec.iter().zip(&spec_f32) {
+        for (v, v_f32) in row.iter().zip(row_f32) {
+            assert!((v - v_f32).abs() <= 1e-4 * peak);
+        }
+    }
+
+    // Images, CMVN and the GPU are single precision
+    for args in [
+        &["--precision", "f64"][..],
+        &["--precision", "f64", "--format", "csv", "--device", "gpu"],
+        &["--precision", "f64", "--format", "csv", "--compat", "kaldi"],
+    ] {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success());
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Run the CLI with `input` piped to stdin, returning its output
+fn run_with_stdin(args: &[&str], input: &[u8]) -> std::process::Output {
+    let mut child = Command::new(get_binary_path())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute spectrs");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input)
+        .expect("Failed to write stdin");
+    child
+        .wait_with_output()
+        .expect("Failed to wait for spectrs")
+}
+
+/// Test reading WAV or raw PCM from stdin and writing the output to stdout
+#[test]
+fn test_cli_stdin_stdout() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("test_audio.wav");
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    // Reference output written next to the input
+    let output = Command::new(get_binary_path())
+        .args([
+            input_wav.to_str().unwrap(),
+            "--format",
+            "npy",
+            "--n-mels",
+            "40",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success());
+    let expected = fs::read(test_dir.join("test_audio.npy"))?;
+
+    // WAV on stdin
+    let wav = fs::read(&input_wav)?;
+    let output = run_with_stdin(&["-", "--format", "npy", "--n-mels", "40"], &wav);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, expected);
+
+    // Streamed WAV whose header leaves the lengths open, as `ffmpeg -f wav -` writes it
+    for placeholder in [u32::MAX, 0] {
+        let mut streamed = wav.clone();
+        let data_len = streamed.windows(4).position(|w| w == b"data").unwrap() + 4;
+        streamed[4..8].copy_from_slice(&placeholder.to_le_bytes());
+        streamed[data_len..data_len + 4].copy_from_slice(&placeholder.to_le_bytes());
+        let output = run_with_stdin(&["-", "--format", "npy", "--n-mels", "40"], &streamed);
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(output.stdout, expected);
+    }
+
+    // The same samples as headerless PCM
+    let (samples, _) = spectrs::io::audio::read_audio_file_mono(&input_wav)?;
+    let pcm: Vec<u8> = samples
+        .iter()
+        .flat_map(|s| ((s * 32768.0).round() as i16).to_le_bytes())
+        .collect();
+    let output = run_with_stdin(
+        &[
+            "-",
+            "--format",
+            "npy",
+            "--n-mels",
+            "40",
+            "--raw-pcm",
+            "sr=16000,fmt=s16le",
+        ],
+        &pcm,
+    );
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+
+    // PNG output
+    let output = run_with_stdin(&["-"], &wav);
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"\x89PNG"));
+
+    // Options writing files next to the output are rejected
+    let output = run_with_stdin(&["-", "--sidecar"], &wav);
+    assert!(!output.status.success());
+    for option in [
+        "--chroma-out",
+        "--tonnetz-out",
+        "--band-levels-out",
+        "--cpp-out",
+    ] {
+        let output = run_with_stdin(&["-", option, "csv"], &wav);
+        assert!(!output.status.success(), "accepted {}", option);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("when writing to stdout"));
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test validation of the raw PCM description
+#[test]
+fn test_cli_raw_pcm_validation() {
+    for description in [
+        "sr=16000",
+        "fmt=s16le",
+        "sr=0,fmt=s16le",
+        "sr=16000,fmt=mp3",
+        "sr=16000,fmt=s16le,channels=0",
+    ] {
+        let output = run_with_stdin(&["-", "--raw-pcm", description], &[]);
+        assert!(!output.status.success(), "accepted {}", description);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid raw PCM description"));
+    }
+
+    // URLs are always WAV
+    let output = Command::new(get_binary_path())
+        .args([
+            "https://example.com/a.wav",
+            "--raw-pcm",
+            "sr=16000,fmt=s16le",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+}
+
+/// Test headerless PCM files, alone and inside directories
+#[test]
+fn test_cli_raw_pcm_files() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("test_audio.wav");
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    // Reference output of the WAV file
+    let output = Command::new(get_binary_path())
+        .args([input_wav.to_str().unwrap(), "--format", "npy"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success());
+    let expected = fs::read(test_dir.join("test_audio.npy"))?;
+
+    // The data chunk of a 16-bit mono WAV file is raw s16le PCM
+    let bytes = fs::read(&input_wav)?;
+    let data_pos = bytes
+        .windows(4)
+        .position(|w| w == b"data")
+        .expect("data chunk");
+    let raw_dir = test_dir.join("raw");
+    fs::create_dir_all(raw_dir.join("nested"))?;
+    fs::write(raw_dir.join("capture.pcm"), &bytes[data_pos + 8..])?;
+    fs::write(
+        raw_dir.join("nested").join("call.raw"),
+        &bytes[data_pos + 8..],
+    )?;
+    fs::copy(&input_wav, raw_dir.join("ignored.wav"))?;
+
+    let output = Command::new(get_binary_path())
+        .args([
+            raw_dir.to_str().unwrap(),
+            "--format",
+            "npy",
+            "--raw-pcm",
+            "sr=16000,fmt=s16le",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(fs::read(raw_dir.join("capture.npy"))?, expected);
+    assert_eq!(fs::read(raw_dir.join("nested").join("call.npy"))?, expected);
+    // WAV files are not decoded as raw PCM
+    assert!(!raw_dir.join("ignored.npy").exists());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test validation of the live mode, done before any device is opened
+#[test]
+fn test_cli_live_validation() {
+    for args in [
+        vec!["--live", "--sr", "16000"],
+        vec!["--live", "--format", "csv"],
+        vec!["--live", "--compat", "kaldi", "--format", "npy"],
+        vec!["--live", "input.wav"],
+        vec!["--live", "--live-interval", "0"],
+    ] {
+        let output = Command::new(get_binary_path())
+            .args(&args)
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success(), "accepted {:?}", args);
+    }
+
+    // Without the live feature no device can be opened
+    if !cfg!(feature = "live") {
+        let output = Command::new(get_binary_path())
+            .args(["--live", "--live-duration", "1"])
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Live feature not enabled"));
+    }
+}
+
+#[test]
+fn test_cli_view() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let wav = test_dir.join("tone.wav");
+    create_test_wav(&wav, 1.0, 16000, 1, 16)?;
+
+    // Unreadable input is reported before the terminal is touched
+    let output = Command::new(get_binary_path())
+        .args(["view", "missing.wav"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to read missing.wav"));
+
+    if !cfg!(feature = "tui") {
+        let output = Command::new(get_binary_path())
+            .arg("view")
+            .arg(&wav)
+            .output()?;
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("TUI feature not enabled"));
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_subcommands() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let wav = test_dir.join("tone.wav");
+    create_test_wav(&wav, 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // The bare invocation is `compute`
+    let bare = run(&[
+        "tone.wav",
+        "--format",
+        "npy",
+        "--sidecar",
+        "--output-dir",
+        "bare",
+    ]);
+    assert!(bare.status.success());
+    let explicit = run(&["compute", "tone.wav", "--format", "npy", "--sidecar"]);
+    assert!(explicit.status.success());
+    assert_eq!(
+        fs::read(test_dir.join("bare/tone.npy"))?,
+        fs::read(test_dir.join("tone.npy"))?
+    );
+
+    // Statistics table on stdout
+    let stats = run(&["stats", "tone.wav"]);
+    assert!(stats.status.success());
+    let table = String::from_utf8(stats.stdout)?;
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("file,duration_s,"));
+    assert!(lines[1].starts_with("tone.wav,1,"));
+
+    // Audio back from the spectrogram, parameters taken from the sidecar
+    let reconstruct = run(&["reconstruct", "tone.npy", "--n-iter", "4"]);
+    assert!(reconstruct.status.success());
+    let reader = hound::WavReader::open(test_dir.join("tone.reconstructed.wav"))?;
+    assert_eq!(reader.spec().sample_rate, 16000);
+    assert!(reader.duration() > 15000);
+
+    // Mel spectrograms are brought back to linear frequencies first
+    let mel = run(&[
+        "tone.wav",
+        "--format",
+        "npy",
+        "--sidecar",
+        "--n-mels",
+        "64",
+        "--output-dir",
+        "mel",
+    ]);
+    assert!(mel.status.success());
+    for inversion in ["nnls", "transpose"] {
+        let reconstruct = run(&[
+            "reconstruct",
+            "mel/tone.npy",
+            "--n-iter",
+            "4",
+            "--mel-inversion",
+            inversion,
+        ]);
+        assert!(
+            reconstruct.status.success(),
+            "{}",
+            String::from_utf8_lossy(&reconstruct.stderr)
+        );
+        let reader = hound::WavReader::open(test_dir.join("mel/tone.reconstructed.wav"))?;
+        assert!(reader.duration() > 15000);
+    }
+
+    // Without a sidecar, the sample rate is required
+    fs::remove_file(test_dir.join("tone.meta.json"))?;
+    let reconstruct = run(&["reconstruct", "tone.npy"]);
+    assert!(!reconstruct.status.success());
+    assert!(String::from_utf8_lossy(&reconstruct.stderr).contains("--sr"));
+
+    let compare = run(&["compare", "tone.npy", "bare/tone.npy"]);
+    assert!(compare.status.success());
+    assert!(String::from_utf8(compare.stdout)?.contains("mse: 0\n"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[cfg(feature = "opus")]
+#[test]
+fn test_cli_reconstruct_opus() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("tone.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let compute = run(&["tone.wav", "--format", "npy", "--sidecar", "--n-mels", "64"]);
+    assert!(compute.status.success());
+    let reconstruct = run(&[
+        "reconstruct",
+        "tone.npy",
+        "--n-iter",
+        "4",
+        "--format",
+        "opus",
+    ]);
+    assert!(
+        reconstruct.status.success(),
+        "{}",
+        String::from_utf8_lossy(&reconstruct.stderr)
+    );
+    let opus = fs::read(test_dir.join("tone.reconstructed.opus"))?;
+    assert_eq!(&opus[..4], b"OggS");
+    // Smaller than the 16-bit samples
+    assert!(opus.len() < 16000 * 2 / 2);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_compare() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::write(test_dir.join("a.csv"), "1,2,3\n4,5,6\n")?;
+    fs::write(test_dir.join("b.json"), r#"{"data": [[1, 2], [4, 7]]}"#)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("compare")
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Different formats and shapes: the common 2x2 region is compared, with a warning
+    let output = run(&["a.csv", "b.json", "--metrics", "mse,max-abs-error"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "mse: 1\nmax_abs_error: 2\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Shapes differ: 2x3"));
+
+    // Unless shapes are required to match
+    let output = run(&["a.csv", "b.json", "--strict-shape"]);
+    assert!(!output.status.success());
+
+    // Unknown metrics and formats are rejected
+    assert!(
+        !run(&["a.csv", "b.json", "--metrics", "snr"])
+            .status
+            .success()
+    );
+    fs::write(test_dir.join("c.txt"), "1,2\n")?;
+    assert!(!run(&["a.csv", "c.txt"]).status.success());
+
+    // Difference maps, padded to the longer spectrogram
+    let output = run(&[
+        "b.json",
+        "a.csv",
+        "--metrics",
+        "mse",
+        "--diff-out",
+        "diff.csv",
+        "--align",
+        "pad",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(test_dir.join("diff.csv"))?,
+        "0,0,3\n0,2,6\n"
+    );
+    let output = run(&[
+        "a.csv",
+        "a.csv",
+        "--diff-out",
+        "diff.json",
+        "--diff-mode",
+        "snr",
+    ]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(test_dir.join("diff.json"))?.contains("\"shape\":[2,3]"));
+    assert!(
+        !run(&["a.csv", "b.json", "--diff-out", "diff.png"])
+            .status
+            .success()
+    );
+    assert!(!test_dir.join("diff.png").exists());
+    assert!(
+        !run(&["a.csv", "b.json", "--diff-mode", "snr"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test before/after comparison images of two recordings computed with the compute options
+#[test]
+fn test_cli_compare_image() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 2.0, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("short.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("fast.wav"), 2.0, 22050, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("compare-image")
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Three panels of 64 mel bands over the 59 frames of the recording; a recording compared
+    // with itself has no difference below the label of the difference panel
+    let output = run(&["a.wav", "a.wav", "--", "--n-mels", "64"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let img = image::open(test_dir.join("a.comparison.png"))?.to_rgb8();
+    assert_eq!(img.dimensions(), (59, 3 * 64 + 2 * 2));
+    assert!(
+        (2 * 66 + 12..img.height())
+            .all(|y| (0..img.width()).all(|x| img.get_pixel(x, y).0 == [255, 255, 255]))
+    );
+
+    // Different lengths are compared over the common duration, images shrunk to --width
+    let output = run(&[
+        "a.wav",
+        "short.wav",
+        "--output",
+        "out/cmp.png",
+        "--",
+        "--n-mels",
+        "64",
+        "--width",
+        "20",
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Lengths differ"));
+    assert_eq!(image::open(test_dir.join("out/cmp.png"))?.width(), 20);
+
+    // Different sample rates need --sr; options writing other outputs are refused
+    let output = run(&["a.wav", "fast.wav", "--", "--n-mels", "64"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--sr"));
+    let output = run(&["a.wav", "fast.wav", "--", "--n-mels", "64", "--sr", "16000"]);
+    assert!(output.status.success());
+    // Both inputs are prepared in one process, which configures the thread pool only once
+    let output = run(&[
+        "a.wav",
+        "a.wav",
+        "--output",
+        "det.png",
+        "--",
+        "--n-mels",
+        "64",
+        "--deterministic",
+    ]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join("det.png").exists());
+    for options in [
+        &["--format", "npy"][..],
+        &["--sidecar"],
+        &["--tile-width", "8"],
+    ] {
+        let output = run(&[&["a.wav", "a.wav", "--"][..], options].concat());
+        assert_eq!(output.status.code(), Some(3), "{:?}", options);
+    }
+    assert_eq!(run(&["a.wav", "missing.wav"]).status.code(), Some(3));
+    assert!(
+        !run(&["a.wav", "a.wav", "--diff-range", "0"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test images read back into values through their sidecar
+#[test]
+fn test_cli_image_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&[
+        "a.wav",
+        "--n-fft",
+        "512",
+        "--win-length",
+        "512",
+        "--n-mels",
+        "32",
+        "--format",
+        "png,npy",
+        "--sidecar",
+        "--with-waveform",
+    ]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let image = spectrs::io::image::load_spectrogram_image(&test_dir.join("a.png"))?;
+    let values = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"))?;
+    assert_eq!(image.len(), 32);
+    assert_eq!(image[0].len(), values[0].len());
+
+    let output = run(&["compare", "a.png", "a.npy", "--metrics", "correlation"]);
+    assert!(output.status.success());
+    let correlation: f32 = String::from_utf8(output.stdout)?
+        .trim()
+        .trim_start_matches("correlation: ")
+        .parse()?;
+    assert!(correlation > 0.99, "correlation {}", correlation);
+
+    // Images without a sidecar cannot be read back
+    fs::remove_file(test_dir.join("a.meta.json"))?;
+    assert!(!run(&["compare", "a.png", "a.npy"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the downmix strategies of multichannel inputs
+#[test]
+fn test_cli_downmix() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("opposite.wav");
+    // Channels in opposite phase: their average is silent, each channel alone is not
+    create_float_test_wav(&input_wav, 0.5, 16000, 2, 32)?;
+
+    let peak_with = |downmix: &str| -> Result<f32> {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--downmix", downmix])
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("opposite.npy"))?;
+        Ok(spec.iter().flatten().fold(0.0f32, |acc, &v| acc.max(v)))
+    };
+    assert_eq!(peak_with("average")?, 0.0);
+    let first = peak_with("first")?;
+    assert!(first > 1.0);
+    assert_eq!(peak_with("channel=1")?, first);
+
+    // Missing channels and unknown strategies are rejected
+    for args in [
+        &["--downmix", "channel=2"][..],
+        &["--downmix", "itu775"],
+        &["--downmix", "loudest"],
+    ] {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success(), "{:?} should fail", args);
+    }
+
+    // Raw PCM is downmixed the same way, whatever its number of channels: only the third of
+    // four channels carries a tone
+    let raw_path = test_dir.join("quad.raw");
+    let bytes: Vec<u8> = (0..8000)
+        .flat_map(|i| {
+            let tone = (0.5
+                * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin()
+                * 32767.0) as i16;
+            [0i16, 0, tone, 0]
+        })
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    fs::write(&raw_path, bytes)?;
+    let raw_peak_with = |downmix: &str| -> Result<Option<f32>> {
+        let output = Command::new(get_binary_path())
+            .arg(raw_path.to_str().unwrap())
+            .args(["--raw-pcm", "sr=16000,fmt=s16le,channels=4"])
+            .args(["--format", "npy", "--downmix", downmix])
+            .output()
+            .expect("Failed to execute spectrs");
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("quad.npy"))?;
+        Ok(Some(
+            spec.iter().flatten().fold(0.0f32, |acc, &v| acc.max(v)),
+        ))
+    };
+    assert_eq!(raw_peak_with("first")?, Some(0.0));
+    let third = raw_peak_with("channel=2")?.expect("channel=2 of 4 channels");
+    assert!(third > 1.0);
+    let average = raw_peak_with("average")?.expect("average of 4 channels");
+    assert!(average > 0.0 && average < third);
+    assert_eq!(raw_peak_with("channel=4")?, None);
+    assert_eq!(raw_peak_with("itu775")?, None);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test splitting images into tiles with an index
+#[test]
+fn test_cli_tile_width() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("long.wav");
+    create_test_wav(&input_wav, 2.0, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args([
+            "--hop-length",
+            "160",
+            "--tile-width",
+            "40",
+            "--start",
+            "0.5",
+        ])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // 1.5 s every 10 ms with 2048-sample windows: 138 frames in 4 tiles, no single image
+    assert!(!test_dir.join("long.png").exists());
+    let index = spectrs::io::metadata::read_tile_index(&test_dir.join("long.tiles.json"))?;
+    assert_eq!(index.n_frames, 138);
+    assert_eq!(index.tiles.len(), 4);
+    for (i, tile) in index.tiles.iter().enumerate() {
+        assert_eq!(tile.file, format!("long.tile{:04}.png", i));
+        assert!(test_dir.join(&tile.file).exists());
+        assert!((tile.start_time - (0.5 + i as f64 * 0.4)).abs() < 1e-9);
+    }
+    assert_eq!(index.tiles[3].end_frame, 138);
+
+    // Tiles are images at full resolution
+    for args in [
+        &["--tile-width", "40", "--format", "npy"][..],
+        &["--tile-width", "40", "--width", "100"],
+    ] {
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(!output.status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test stacking the waveform beneath the spectrogram
+#[cfg(feature = "image")]
+#[test]
+fn test_cli_with_waveform() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // 257 bins: the panel adds 64 rows, whether the audio is streamed or decoded at once
+    for args in [
+        &["--with-waveform"][..],
+        &["--with-waveform", "--sr", "8000"],
+    ] {
+        let output = run(args);
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let img = image::open(test_dir.join("tone.png"))?;
+        assert_eq!(img.height(), 257 + 64);
+    }
+
+    for args in [
+        &["--with-waveform", "--format", "npy"][..],
+        &["--with-waveform", "--tile-width", "10"],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test writing several formats from a single computation
+#[test]
+fn test_cli_multiple_formats() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    let output = Command::new(get_binary_path())
+        .arg(input_wav.to_str().unwrap())
+        .args(["--format", "png,npy,json", "--n-mels", "32", "--sidecar"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(test_dir.join("tone.png").exists());
+    let npy = spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"))?;
+    let json = spectrs::io::export::load_spectrogram_json(&test_dir.join("tone.json"))?;
+    assert_eq!(npy.len(), 32);
+    assert_eq!(npy, json);
+
+    // The sidecar describes the first format
+    let metadata = spectrs::io::metadata::read_metadata(&test_dir.join("tone.meta.json"))?;
+    assert_eq!(metadata.format, spectrs::io::export::OutputFormat::Png);
+
+    // A single stream cannot hold several outputs
+    let output = Command::new(get_binary_path())
+        .args(["-", "--format", "npy,csv"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Only one --format"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test spectral subtraction of a noise floor
+#[test]
+fn test_cli_denoise() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    let noise_wav = test_dir.join("noise.wav");
+    create_complex_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+    create_test_wav(&noise_wav, 0.5, 8000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"));
+
+    assert!(run(&[]).status.success());
+    let plain = load()?;
+    let output = run(&["--denoise", "--noise-percent", "20%"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let denoised = load()?;
+    assert_eq!(denoised.len(), plain.len());
+    for (denoised, plain) in denoised.iter().flatten().zip(plain.iter().flatten()) {
+        assert!(denoised <= plain);
+    }
+    let total = |spec: &[Vec<f32>]| spec.iter().flatten().sum::<f32>();
+    assert!(total(&denoised) < total(&plain));
+
+    // The noise recording must be at the sample rate of the input
+    let noise = noise_wav.to_str().unwrap();
+    let output = run(&["--denoise", "--noise-wav", noise]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--sr"));
+    assert!(
+        run(&["--denoise", "--noise-wav", noise, "--sr", "8000"])
+            .status
+            .success()
+    );
+
+    for args in [
+        &["--noise-wav", noise][..],
+        &["--denoise-floor", "0.1"],
+        &["--denoise", "--noise-percent", "0"],
+        &["--denoise", "--denoise-floor", "2"],
+        &["--denoise", "--compat", "kaldi"],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test gating the quiet frames of a recording with --gate-db
+#[test]
+fn test_cli_gate_db() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("sparse.wav");
+    // Half a second of tone, then half a second of faint noise (about -80 dB)
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&input_wav, spec)?;
+    for i in 0..16000 {
+        let t = i as f32 / 16000.0;
+        let sample = if i < 8000 {
+            ((2.0 * std::f32::consts::PI * 440.0 * t).sin() * 0.5 * i16::MAX as f32) as i16
+        } else {
+            (i * 7919 % 7) as i16 - 3
+        };
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("sparse.npy"));
+    let silent_frames = |spec: &[Vec<f32>]| {
+        (0..spec[0].len())
+            .filter(|&t| spec.iter().all(|row| row[t] == 0.0))
+            .count()
+    };
+
+    assert!(run(&[]).status.success());
+    let plain = load()?;
+
+    let output = run(&["--gate-db", "-60"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let zeroed = load()?;
+    assert_eq!(zeroed[0].len(), plain[0].len());
+    assert_eq!(silent_frames(&plain), 0);
+    assert!(silent_frames(&zeroed) > 0);
+
+    // Dropped frames shorten the output, also after the mel projection
+    let output = run(&["--gate-db", "-60", "--gate-mode", "drop", "--n-mels", "40"]);
+    assert!(output.status.success());
+    let dropped = load()?;
+    assert_eq!(dropped.len(), 40);
+    assert_eq!(dropped[0].len(), plain[0].len() - silent_frames(&zeroed));
+
+    for args in [
+        &["--gate-db", "6"][..],
+        &["--gate-mode", "drop"],
+        &["--gate-db", "-60", "--compat", "kaldi"],
+        &[
+            "--gate-db",
+            "-60",
+            "--gate-mode",
+            "drop",
+            "--onsets-out",
+            "csv",
+        ],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the handling of NaN samples (corrupted float WAV) and --strict
+#[test]
+fn test_cli_strict_non_finite() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("corrupted.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&input_wav, spec)?;
+    for i in 0..16000 {
+        let sample = match i {
+            8000..8010 => f32::NAN,
+            _ => (i as f32 * 0.1).sin() * 0.5,
+        };
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Replaced with zero, with the frames named in a warning
+    let output = run(&["--n-mels", "32"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("NaN or infinite"), "{}", stderr);
+    assert!(stderr.contains("(frames 15)"), "{}", stderr);
+    let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("corrupted.npy"))?;
+    assert!(spec.iter().flatten().all(|v| v.is_finite()));
+    assert!(spec.iter().all(|row| row[15] == 0.0));
+    assert!(spec.iter().any(|row| row[10] > 0.0));
+
+    let output = run(&["--strict"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("NaN or infinite"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the color scale options of images
+#[cfg(feature = "image")]
+#[test]
+fn test_cli_color_scale() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // The same tone, 6 dB apart
+    create_test_wav(&test_dir.join("loud.wav"), 0.5, 16000, 1, 16)?;
+    create_float_test_wav(&test_dir.join("quiet.wav"), 0.5, 16000, 1, 32)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(test_dir.to_str().unwrap())
+            .args(["--colormap", "gray"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let brightness = |name: &str| -> Result<f64> {
+        let img = image::open(test_dir.join(name))?.to_luma8();
+        Ok(img.pixels().map(|p| p.0[0] as f64).sum::<f64>() / img.len() as f64)
+    };
+
+    // With a shared dB range, the louder file is brighter
+    let output = run(&["--vmin", "-100", "--vmax", "40", "--gamma", "0.5"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(brightness("loud.png")? > brightness("quiet.png")? + 1.0);
+    assert!(run(&["--percentile-clip", "2,98"]).status.success());
+
+    for args in [
+        &["--vmin", "0", "--vmax", "-10"][..],
+        &["--percentile-clip", "98,2"],
+        &["--percentile-clip", "2,98", "--vmin", "-80"],
+        &["--gamma", "0"],
+        &["--gamma", "2", "--format", "npy"],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test cropping images to a display frequency band
+#[cfg(feature = "image")]
+#[test]
+fn test_cli_display_band() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test.wav");
+    create_test_wav(&audio_path, 0.5, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(audio_path.to_str().unwrap())
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // 0-4 kHz of the 257 linear rows, 31.25 Hz apart
+    let output = run(&["--display-fmax", "4000", "--format", "png,npy"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(image::open(test_dir.join("test.png"))?.height(), 129);
+    // Numerical outputs keep every row
+    let npy = std::fs::read(test_dir.join("test.npy"))?;
+    assert!(String::from_utf8_lossy(&npy[..128]).contains("(257, "));
+
+    // Mel bands are cropped by their centre frequency
+    let output = run(&["--n-mels", "64", "--display-fmin", "1000"]);
+    assert!(output.status.success());
+    let height = image::open(test_dir.join("test.png"))?.height();
+    assert!(height > 0 && height < 64);
+
+    for args in [
+        &["--display-fmin", "4000", "--display-fmax", "1000"][..],
+        &["--display-fmax", "4000", "--format", "csv"],
+        &["--display-fmin", "9000"],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test pooling the frames of overview images with --time-reduce
+#[cfg(feature = "image")]
+#[test]
+fn test_cli_time_reduce() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test.wav");
+    create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(audio_path.to_str().unwrap())
+            .args([
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+                "--hop-length",
+                "128",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    assert!(run(&[]).status.success());
+    let full = image::open(test_dir.join("test.png"))?.width();
+
+    for mode in ["mean", "max"] {
+        let output = run(&[
+            "--time-reduce",
+            &format!("{}:16", mode),
+            "--format",
+            "png,npy",
+        ]);
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            image::open(test_dir.join("test.png"))?.width(),
+            full.div_ceil(16)
+        );
+        // The NPY export keeps every frame
+        let npy = std::fs::read(test_dir.join("test.npy"))?;
+        assert!(String::from_utf8_lossy(&npy[..128]).contains(&format!(", {})", full)));
+    }
+
+    for args in [
+        &["--time-reduce", "median:4"][..],
+        &["--time-reduce", "max:0"],
+        &["--time-reduce", "max:4", "--format", "csv"],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that a second run with the same settings reads the spectrogram from --cache-dir
+#[test]
+fn test_cli_cache_dir() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test.wav");
+    let cache_dir = test_dir.join("cache");
+    create_test_wav(&audio_path, 0.5, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(audio_path.to_str().unwrap())
+            .args([
+                "--cache-dir",
+                cache_dir.to_str().unwrap(),
+                "--format",
+                "npy",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let entries = || -> Result<Vec<PathBuf>> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(&cache_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter(|path| {
+                path.as_ref()
+                    .is_ok_and(|path| path.extension().is_some_and(|ext| ext == "npy"))
+            })
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        Ok(entries)
+    };
+
+    let output = run(&[]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let computed = spectrs::io::export::load_spectrogram_npy(&test_dir.join("test.npy"))?;
+    let cached = entries()?;
+    assert_eq!(cached.len(), 1);
+    assert_eq!(
+        spectrs::io::export::load_spectrogram_npy(&cached[0])?,
+        computed
+    );
+
+    // Tamper with the entry: the next run with the same settings outputs it as is
+    let zeros = vec![vec![0.0f32; computed[0].len()]; computed.len()];
+    spectrs::io::export::save_spectrogram_npy(&zeros, &cached[0])?;
+    assert!(run(&[]).status.success());
+    assert_eq!(
+        spectrs::io::export::load_spectrogram_npy(&test_dir.join("test.npy"))?,
+        zeros
+    );
+
+    // Other settings are computed and cached separately
+    assert!(run(&["--n-mels", "40"]).status.success());
+    assert_eq!(entries()?.len(), 2);
+    assert_eq!(
+        spectrs::io::export::load_spectrogram_npy(&test_dir.join("test.npy"))?.len(),
+        40
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test selecting the files of a directory with globs, a maximum depth and a symlink policy
+#[cfg(unix)]
+#[test]
+fn test_cli_walk_filters() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    for name in ["a.wav", "sub/b.wav", "sub/deep/d.wav", "backup/c.wav"] {
+        let path = input_dir.join(name);
+        fs::create_dir_all(path.parent().unwrap())?;
+        create_test_wav(&path, 0.2, 16000, 1, 16)?;
+    }
+    fs::write(input_dir.join("notes.txt"), "not audio")?;
+    // Relative targets are resolved from the directory of the link
+    std::os::unix::fs::symlink("sub", input_dir.join("linked"))?;
+    std::os::unix::fs::symlink("a.wav", input_dir.join("alias.wav"))?;
+
+    // Relative paths of the outputs of a run
+    let run = |name: &str, args: &[&str]| -> Result<Vec<String>> {
+        let output_dir = test_dir.join(name);
+        let output = Command::new(get_binary_path())
+            .arg(input_dir.to_str().unwrap())
+            .args([
+                "--format",
+                "npy",
+                "--output-dir",
+                output_dir.to_str().unwrap(),
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs");
+        anyhow::ensure!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let mut outputs: Vec<String> = walkdir::WalkDir::new(&output_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let relative = e.path().strip_prefix(&output_dir).unwrap();
+                relative.to_string_lossy().into_owned()
+            })
+            .collect();
+        outputs.sort();
+        Ok(outputs)
+    };
+
+    assert_eq!(
+        run("default", &[])?,
+        [
+            "a.npy",
+            "alias.npy",
+            "backup/c.npy",
+            "sub/b.npy",
+            "sub/deep/d.npy"
+        ]
+    );
+    assert_eq!(
+        run("include", &["--include", "sub/**/*.wav"])?,
+        ["sub/b.npy", "sub/deep/d.npy"]
+    );
+    assert_eq!(
+        run(
+            "exclude",
+            &["--exclude", "backup", "--exclude", "**/deep/**"]
+        )?,
+        ["a.npy", "alias.npy", "sub/b.npy"]
+    );
+    assert_eq!(run("depth", &["--max-depth", "1"])?, ["a.npy", "alias.npy"]);
+    assert_eq!(
+        run("follow", &["--symlinks", "follow", "--max-depth", "2"])?,
+        [
+            "a.npy",
+            "alias.npy",
+            "backup/c.npy",
+            "linked/b.npy",
+            "sub/b.npy"
+        ]
+    );
+    assert_eq!(
+        run("skip", &["--symlinks", "skip", "--max-depth", "1"])?,
+        ["a.npy"]
+    );
+    assert!(run("invalid", &["--include", "[a-"]).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test processing the entries of a manifest and writing the output manifest
+#[test]
+fn test_cli_manifest() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let data_dir = test_dir.join("data");
+    let output_dir = test_dir.join("out");
+    fs::create_dir_all(data_dir.join("sub"))?;
+    create_test_wav(&data_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&data_dir.join("sub/b.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&data_dir.join("unlisted.wav"), 0.5, 16000, 1, 16)?;
+
+    let manifest = data_dir.join("manifest.csv");
+    fs::write(
+        &manifest,
+        "path,start,end,label,output\n\
+         a.wav,0.25,0.75,dog,\n\
+         a.wav,,,dog,a_full\n\
+         sub/b.wav,,,cat,\n",
+    )?;
+    let run = |manifest: &Path| {
+        Command::new(get_binary_path())
+            .args(["--manifest", manifest.to_str().unwrap()])
+            .args([
+                "--output-dir",
+                output_dir.to_str().unwrap(),
+                "--format",
+                "npy",
+            ])
+            .args(["--sidecar", "--manifest-out"])
+            .arg(output_dir.join("manifest.jsonl"))
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&manifest);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let frames = |name: &str| -> Result<usize> {
+        Ok(spectrs::io::export::load_spectrogram_npy(&output_dir.join(name))?[0].len())
+    };
+    assert!(frames("a.npy")? < frames("a_full.npy")?);
+    assert!(output_dir.join("sub/b.npy").exists());
+    assert!(!output_dir.join("unlisted.npy").exists());
+
+    // One record per entry, in the order of the manifest
+    let records: Vec<serde_json::Value> = fs::read_to_string(output_dir.join("manifest.jsonl"))?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["label"], "dog");
+    assert_eq!(records[0]["start"], 0.25);
+    assert_eq!(records[0]["duration"], 0.5);
+    assert_eq!(records[2]["label"], "cat");
+    let outputs = records[1]["outputs"].as_array().unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs[0].as_str().unwrap().ends_with("a_full.npy"));
+    assert!(outputs[1].as_str().unwrap().ends_with("a_full.meta.json"));
+
+    // JSON Lines manifests; entries writing the same output are rejected
+    let jsonl = data_dir.join("manifest.jsonl");
+    fs::write(&jsonl, "{\"path\": \"sub/b.wav\", \"end\": 0.25}\n")?;
+    assert!(run(&jsonl).status.success());
+    fs::write(&manifest, "path\na.wav\na.wav\n")?;
+    assert!(!run(&manifest).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the dataset-wide colour scale of a batch
+#[cfg(feature = "image")]
+#[test]
+fn test_cli_global_scale() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // The same tone, 6 dB apart
+    create_test_wav(&test_dir.join("loud.wav"), 0.5, 16000, 1, 16)?;
+    create_float_test_wav(&test_dir.join("quiet.wav"), 0.5, 16000, 1, 32)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(test_dir.to_str().unwrap())
+            .args(["--colormap", "gray"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let brightest = |name: &str| -> Result<u8> {
+        let img = image::open(test_dir.join(name))?.to_luma8();
+        Ok(img.pixels().map(|p| p.0[0]).max().unwrap_or(0))
+    };
+
+    // Each image is normalized on its own: both peaks are white
+    assert!(run(&[]).status.success());
+    assert_eq!(brightest("loud.png")?, 255);
+    assert_eq!(brightest("quiet.png")?, 255);
+
+    // Shared scale: only the loudest file reaches white
+    let output = run(&["--global-scale"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(brightest("loud.png")?, 255);
+    assert!(brightest("quiet.png")? < 255);
+    assert!(
+        run(&["--global-scale", "--percentile-clip", "1,99"])
+            .status
+            .success()
+    );
+
+    for args in [
+        &["--global-scale", "--format", "npy"][..],
+        &[
+            "--global-scale",
+            "--vmin",
+            "-80",
+            "--vmax",
+            "0",
+            "--format",
+            "csv",
+        ],
+    ] {
+        assert!(!run(args).status.success(), "{:?} should fail", args);
+    }
+    let output = Command::new(get_binary_path())
+        .args(["-", "--global-scale"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_report() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let data_dir = test_dir.join("data");
+    let output_dir = test_dir.join("out");
+    let report_path = test_dir.join("report.json");
+    fs::create_dir_all(&data_dir)?;
+    create_test_wav(&data_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    fs::write(data_dir.join("broken.wav"), b"not a wav file")?;
+    fs::write(data_dir.join("notes.txt"), b"notes")?;
+
+    let output = Command::new(get_binary_path())
+        .arg(&data_dir)
+        .args(["--output-dir", output_dir.to_str().unwrap()])
+        .args(["--n-fft", "512", "--win-length", "512"])
+        .args(["--log-level", "debug", "--log-json", "--report"])
+        .arg(&report_path)
+        .output()
+        .expect("Failed to execute spectrs");
+
+    // The broken file fails the run, without stopping the other one
+    assert!(!output.status.success());
+    assert!(output_dir.join("a.png").exists());
+
+    let report = spectrs::io::metadata::read_run_report(&report_path)?;
+    assert_eq!(report.processed.len(), 1);
+    assert!(report.processed[0].input.ends_with("a.wav"));
+    assert!((report.audio_duration - 1.0).abs() < 1e-6);
+    assert_eq!(report.failed.len(), 1);
+    assert!(report.failed[0].input.ends_with("broken.wav"));
+    assert!(!report.failed[0].reason.is_empty());
+    assert_eq!(report.skipped.len(), 1);
+    assert!(report.skipped[0].input.ends_with("notes.txt"));
+    assert!(report.wall_time > 0.0 && report.files_per_second > 0.0);
+
+    // Every message is a JSON object, including a debug event per input
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert!(events.iter().any(|e| e["level"] == "ERROR"));
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e["fields"]["message"] == "Processed input")
+            .count(),
+        2
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_filterbank() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input = test_dir.join("a.wav");
+    create_test_wav(&input, 1.0, 16000, 1, 16)?;
+
+    let run = |filter_bank: &str, output: &str| {
+        Command::new(get_binary_path())
+            .arg(&input)
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(["--n-fft", "512", "--win-length", "512", "--n-mels", "32"])
+            .args(["--format", "npy", "--filterbank", filter_bank])
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    for (filter_bank, output) in [("mel", "mel"), ("gammatone", "gammatone")] {
+        let result = run(filter_bank, output);
+        assert!(
+            result.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+    let mel = spectrs::io::export::load_spectrogram_npy(&test_dir.join("mel/a.npy"))?;
+    let gammatone = spectrs::io::export::load_spectrogram_npy(&test_dir.join("gammatone/a.npy"))?;
+    assert_eq!(gammatone.len(), 32);
+    assert_eq!(gammatone[0].len(), mel[0].len());
+    assert_ne!(gammatone, mel);
+
+    // Triangular bands on the Bark scale
+    let output = Command::new(get_binary_path())
+        .arg(&input)
+        .args(["--output-dir", test_dir.join("bark").to_str().unwrap()])
+        .args(["--n-fft", "512", "--win-length", "512", "--n-mels", "32"])
+        .args(["--format", "npy", "--frequency-scale", "bark"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success());
+    let bark = spectrs::io::export::load_spectrogram_npy(&test_dir.join("bark/a.npy"))?;
+    assert_eq!(bark.len(), 32);
+    assert_ne!(bark, mel);
+
+    // The gammatone filter bank needs a number of bands
+    let output = Command::new(get_binary_path())
+        .arg(&input)
+        .args(["--filterbank", "gammatone"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_compress() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input = test_dir.join("a.wav");
+    create_test_wav(&input, 1.0, 16000, 1, 16)?;
+
+    let run = |compress: &str, output: &str, format: &str| {
+        Command::new(get_binary_path())
+            .arg(&input)
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(["--n-fft", "512", "--win-length", "512", "--n-mels", "32"])
+            .args(["--format", format, "--compress", compress])
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let output = Command::new(get_binary_path())
+        .arg(&input)
+        .args(["--output-dir", test_dir.join("linear").to_str().unwrap()])
+        .args(["--n-fft", "512", "--win-length", "512", "--n-mels", "32"])
+        .args(["--format", "npy"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success());
+    let linear = spectrs::io::export::load_spectrogram_npy(&test_dir.join("linear/a.npy"))?;
+
+    let output = run("db", "db", "npy,png");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join("db/a.png").exists());
+    let db = spectrs::io::export::load_spectrogram_npy(&test_dir.join("db/a.npy"))?;
+    assert!((db[4][10] - 10.0 * linear[4][10].max(1e-10).log10()).abs() < 1e-3);
+
+    // At most 80 dB below the peak
+    assert!(run("db:80", "top_db", "npy").status.success());
+    let top_db = spectrs::io::export::load_spectrogram_npy(&test_dir.join("top_db/a.npy"))?;
+    let values = top_db.iter().flatten();
+    let max = values.clone().copied().fold(f32::MIN, f32::max);
+    let min = values.copied().fold(f32::MAX, f32::min);
+    assert!(max - min <= 80.0 + 1e-3);
+
+    assert!(run("log1p", "log1p", "npy").status.success());
+    let log1p = spectrs::io::export::load_spectrogram_npy(&test_dir.join("log1p/a.npy"))?;
+    assert!((log1p[4][10] - linear[4][10].ln_1p()).abs() < 1e-4);
+
+    // Invalid compressions, and images alone
+    assert!(!run("db:-3", "invalid", "npy").status.success());
+    assert!(!run("sqrt", "invalid", "npy").status.success());
+    assert!(!run("log", "invalid", "png").status.success());
+
+    // Explicit log floor and mel floor
+    let run_floored = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(&input)
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(["--n-fft", "512", "--win-length", "512", "--n-mels", "32"])
+            .args(["--format", "npy"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let output = run_floored("amin", &["--compress", "db", "--amin", "1e-3"]);
+    assert!(output.status.success());
+    let floored = spectrs::io::export::load_spectrogram_npy(&test_dir.join("amin/a.npy"))?;
+    assert!(floored.iter().flatten().all(|&v| v >= -30.0 - 1e-3));
+    assert!((floored[4][10] - 10.0 * linear[4][10].max(1e-3).log10()).abs() < 1e-3);
+
+    assert!(
+        run_floored("mel_floor", &["--mel-floor", "1e-4"])
+            .status
+            .success()
+    );
+    let floored = spectrs::io::export::load_spectrogram_npy(&test_dir.join("mel_floor/a.npy"))?;
+    assert!(floored.iter().flatten().all(|&v| v >= 1e-4));
+
+    for args in [
+        &["--amin", "1e-3"][..],
+        &["--compress", "db", "--amin", "0"],
+        &["--mel-floor", "-1"],
+    ] {
+        assert!(!run_floored("invalid", args).status.success(), "{:?}", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_augment() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("a.wav")
+            .args(["--output-dir", output, "--format", "npy"])
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = |output: &str| spectrs::io::export::load_spectrogram_npy(&test_dir.join(output));
+
+    assert!(run("clean", &[]).status.success());
+    let augment = ["--augment", "noise:pink:10,gain:-3:3,stretch:0.9:1.1"];
+    let output = run("first", &augment);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(run("second", &augment).status.success());
+    assert!(
+        run("seeded", &[&augment[..], &["--augment-seed", "9"]].concat())
+            .status
+            .success()
+    );
+
+    // Augmented, reproducibly for a seed
+    let (clean, first) = (load("clean/a.npy")?, load("first/a.npy")?);
+    assert_ne!(first, clean);
+    assert_eq!(first, load("second/a.npy")?);
+    assert_ne!(first, load("seeded/a.npy")?);
+
+    // The dumped audio is the augmented one
+    let output = run("dump", &["--augment", "shift:0.2", "--dump-resampled"]);
+    assert!(output.status.success());
+    let (dumped, _) =
+        spectrs::io::audio::read_audio_file_mono(&test_dir.join("dump/a.resampled.wav"))?;
+    let (original, _) = spectrs::io::audio::read_audio_file_mono(&test_dir.join("a.wav"))?;
+    assert_eq!(dumped.len(), original.len());
+    assert_ne!(dumped, original);
+
+    // Invalid specs
+    for spec in ["noise:brown:10", "gain:6:-6", "shift", "stretch:0:1"] {
+        assert!(
+            !run("invalid", &["--augment", spec]).status.success(),
+            "{}",
+            spec
+        );
+    }
+
+    // SpecAugment masks of the exported features
+    let output = run(
+        "masked",
+        &[
+            "--n-mels",
+            "40",
+            "--spec-augment",
+            "freq=2:10,time=2:20,fill=-5",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let masked = load("masked/a.npy")?;
+    let masked_rows = masked
+        .iter()
+        .filter(|row| row.iter().all(|&v| v == -5.0))
+        .count();
+    assert!(masked_rows <= 20);
+    assert!(masked.iter().flatten().any(|&v| v == -5.0));
+    for spec in ["freq=2", "time=a:3", "fill=nan", "width=3:3"] {
+        assert!(
+            !run("invalid", &["--spec-augment", spec]).status.success(),
+            "{}",
+            spec
+        );
+    }
+    let images = Command::new(get_binary_path())
+        .args(["a.wav", "--spec-augment", "time=1:5"])
+        .current_dir(&test_dir)
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!images.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_filter() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // 440 Hz sine
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("a.wav")
+            .args(["--output-dir", output, "--format", "npy"])
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    // Energy of the 440 Hz bin (31.25 Hz per bin)
+    let tone = |output: &str| -> Result<f32> {
+        let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join(output))?;
+        Ok(spec[14].iter().sum())
+    };
+
+    assert!(run("plain", &[]).status.success());
+    let output = run("notched", &["--notch", "50,440"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(run("band", &["--bandpass", "1000:4000"]).status.success());
+    assert!(run("passed", &["--bandpass", "300:3400"]).status.success());
+
+    let plain = tone("plain/a.npy")?;
+    assert!(tone("notched/a.npy")? < plain / 100.0);
+    assert!(tone("band/a.npy")? < plain / 100.0);
+    assert!(tone("passed/a.npy")? > plain / 2.0);
+
+    // Inverted band, band above Nyquist and notch at 0 Hz
+    for args in [
+        ["--bandpass", "3400:300"],
+        ["--bandpass", "300:9000"],
+        ["--notch", "0"],
+    ] {
+        assert!(!run("invalid", &args).status.success(), "{:?}", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_weighting() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // 440 Hz sine
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("a.wav")
+            .args(["--output-dir", output, "--format", "npy"])
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = |output: &str| spectrs::io::export::load_spectrogram_npy(&test_dir.join(output));
+
+    assert!(run("plain", &[]).status.success());
+    let output = run("a", &["--weighting", "A"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(run("c", &["--weighting", "c"]).status.success());
+    assert!(run("z", &["--weighting", "Z"]).status.success());
+
+    // Powers of the 440 Hz bin (437.5 Hz) scaled by the squared gain of the curve
+    let plain = load("plain/a.npy")?;
+    let tone = |spec: &[Vec<f32>]| spec[14].iter().sum::<f32>();
+    use spectrs::spectrogram::weighting::Weighting;
+    for (output, weighting) in [("a/a.npy", Weighting::A), ("c/a.npy", Weighting::C)] {
+        let gain_db = 10.0 * (tone(&load(output)?) / tone(&plain)).log10();
+        assert!(
+            (gain_db as f64 - weighting.gain_db(437.5)).abs() < 0.01,
+            "{:?}: {} dB",
+            weighting,
+            gain_db
+        );
+    }
+    assert_eq!(load("z/a.npy")?, plain);
+
+    // Weighted mel bands
+    let output = run("mel", &["--weighting", "A", "--n-mels", "40"]);
+    assert!(output.status.success());
+    assert_eq!(load("mel/a.npy")?.len(), 40);
+
+    // Presets compute their own features
+    assert!(
+        !run("preset", &["--weighting", "A", "--compat", "torchaudio"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_loudness() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 4.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let (audio, sr) = spectrs::io::audio::read_audio_file_mono(&test_dir.join("a.wav"))?;
+    let expected = spectrs::features::loudness::Loudness::from_samples(&audio, sr);
+
+    // Loudness columns after the descriptors, metered while streaming the file and from the
+    // whole decoded audio alike
+    for (stats, extra) in [("streamed.csv", None), ("decoded.csv", Some("--lenient"))] {
+        let output = run(&[
+            &[
+                "a.wav",
+                "--format",
+                "npy",
+                "--stats-out",
+                stats,
+                "--loudness",
+            ][..],
+            extra.as_slice(),
+        ]
+        .concat());
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let content = fs::read_to_string(test_dir.join(stats))?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert!(lines[0].ends_with(
+            "flatness_std,integrated_lufs,short_term_max_lufs,true_peak_dbtp,sample_peak_dbfs"
+        ));
+        let row: Vec<f32> = lines[1]
+            .split(',')
+            .skip(13)
+            .map(|v| v.parse())
+            .collect::<std::result::Result<_, _>>()?;
+        assert_eq!(row.len(), 4);
+        assert!((row[0] - expected.integrated_lufs).abs() < 1e-3);
+        assert!((row[0] + 3.0).abs() < 1.0, "{} LUFS", row[0]);
+        assert!((row[3] - expected.sample_peak_dbfs).abs() < 1e-3);
+    }
+
+    // JSON records with the short-term loudness over time (3 s windows every 100 ms)
+    let output = run(&[
+        "a.wav",
+        "--format",
+        "npy",
+        "--stats-out",
+        "stats.json",
+        "--loudness",
+    ]);
+    assert!(output.status.success());
+    let records: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("stats.json"))?)?;
+    assert_eq!(records[0]["file"], "a.wav");
+    assert_eq!(
+        records[0]["loudness"]["short_term_lufs"]
+            .as_array()
+            .map(Vec::len),
+        Some(11)
+    );
+
+    // `spectrs stats`, as CSV or JSON on stdout
+    let output = run(&["stats", "a.wav", "--loudness"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("integrated_lufs"));
+    let output = run(&["stats", "a.wav", "--loudness", "--json"]);
+    assert!(output.status.success());
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let integrated = records[0]["loudness"]["integrated_lufs"].as_f64().unwrap();
+    assert!((integrated as f32 - expected.integrated_lufs).abs() < 1e-3);
+    let output = run(&["stats", "a.wav"]);
+    assert!(!String::from_utf8(output.stdout)?.contains("lufs"));
+
+    // Loudness is reported in the statistics
+    assert!(!run(&["a.wav", "--loudness"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_pitch() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // One row per frame of the spectrogram, at the pitch of the sine
+    let output = run(&["a.wav", "--format", "npy", "--pitch-out", "csv"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let content = fs::read_to_string(test_dir.join("a.f0.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "time_s,f0_hz");
+    let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"))?;
+    assert_eq!(lines.len() - 1, spec[0].len());
+    let (time, f0) = lines[16].split_once(',').unwrap();
+    assert!((time.parse::<f32>()? - (15.0 * 512.0 + 256.0) / 16000.0).abs() < 1e-6);
+    assert!((f0.parse::<f32>()? - 440.0).abs() < 1.0, "{} Hz", f0);
+
+    // JSON tracks, with unvoiced frames as null
+    let output = run(&["a.wav", "--pitch-out", "json", "--pitch-range", "80:1000"]);
+    assert!(output.status.success());
+    let track: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.f0.json"))?)?;
+    let f0 = track["f0"].as_array().unwrap();
+    assert_eq!(f0.len(), track["times"].as_array().unwrap().len());
+    assert!((f0[16].as_f64().unwrap() - 440.0).abs() < 1.0);
+
+    // The contour is drawn in cyan along the row of 440 Hz (31.25 Hz per row, lowest at the
+    // bottom), and only when requested
+    let cyan_rows = |name: &str| -> Result<Vec<u32>> {
+        let img = image::open(test_dir.join(name))?.to_rgb8();
+        Ok(img
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0 == [0, 255, 255])
+            .map(|(_, y, _)| y)
+            .collect())
+    };
+    assert!(run(&["a.wav"]).status.success());
+    assert!(cyan_rows("a.png")?.is_empty());
+    assert!(run(&["a.wav", "--pitch-overlay"]).status.success());
+    let rows = cyan_rows("a.png")?;
+    assert!(rows.len() >= 30, "{} pixels", rows.len());
+    let expected = 256.0 - 440.0 / 31.25;
+    assert!(rows.iter().all(|&y| (y as f32 - expected).abs() <= 1.0));
+
+    // Invalid ranges and formats, and overlays of numerical outputs
+    assert!(
+        !run(&["a.wav", "--pitch-out", "csv", "--pitch-range", "400:80"])
+            .status
+            .success()
+    );
+    assert!(
+        !run(&["a.wav", "--pitch-out", "csv", "--pitch-range", "80:9000"])
+            .status
+            .success()
+    );
+    assert!(!run(&["a.wav", "--pitch-out", "npy"]).status.success());
+    assert!(
+        !run(&["a.wav", "--format", "npy", "--pitch-overlay"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_segments() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // A 1 kHz tone from 0.5 s to 1 s in 1.5 s of silence
+    let sr = 16000;
+    let audio: Vec<f32> = (0..3 * sr as usize / 2)
+        .map(|i| {
+            let t = i as f32 / sr as f32;
+            if (0.5..1.0).contains(&t) {
+                0.5 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    spectrs::io::audio::write_audio_file(
+        &test_dir.join("a.wav"),
+        &audio,
+        sr,
+        spectrs::io::audio::WavFormat::Float32,
+    )?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["a.wav", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Silence, the tone and silence again, covering the whole input
+    let output = run(&["--segments-out", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let segments = spectrs::io::annotations::load_annotations(&test_dir.join("a.segments.json"))?;
+    let labels: Vec<&str> = segments.iter().map(|s| s.label.as_str()).collect();
+    assert_eq!(labels, ["silence", "active", "silence"]);
+    assert_eq!(segments[0].start, 0.0);
+    assert!((segments[1].start - 0.5).abs() < 0.04, "{:?}", segments);
+    assert!((segments[1].end - 1.0).abs() < 0.04, "{:?}", segments);
+    assert!((segments[2].end - 1.5).abs() < 1e-4, "{:?}", segments);
+
+    // Audacity label tracks, which --annotations reads back
+    let output = run(&["--segments-out", "audacity"]);
+    assert!(output.status.success(), "{:?}", output);
+    let labels = fs::read_to_string(test_dir.join("a.segments.txt"))?;
+    assert_eq!(labels.lines().count(), 3);
+    assert_eq!(
+        spectrs::io::annotations::load_annotations(&test_dir.join("a.segments.txt"))?.len(),
+        3
+    );
+    let output = run(&["--annotations", "a.segments.txt"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // Bursts shorter than --min-active-ms are silence
+    let output = run(&["--segments-out", "json", "--min-active-ms", "600"]);
+    assert!(output.status.success(), "{:?}", output);
+    let segments = spectrs::io::annotations::load_annotations(&test_dir.join("a.segments.json"))?;
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].label, "silence");
+
+    // Thresholds above the loudest frame and presets
+    assert!(
+        !run(&["--segments-out", "json", "--activity-db", "3"])
+            .status
+            .success()
+    );
+    let output = run(&["--segments-out", "json", "--compat", "kaldi"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test naming outputs after --name-template
+#[test]
+fn test_cli_name_template() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("data/dogs"))?;
+    create_test_wav(&test_dir.join("data/dogs/a.wav"), 1.0, 16000, 1, 16)?;
+    let template = "{parent}_{stem}__{n_mels}mel_{sr}hz.{ext}";
+    let run = |input: &str, template: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input)
+            .args([
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+                "--output-dir",
+                "out",
+            ])
+            .args(["--name-template", template])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Variants of the same input side by side
+    for n_mels in ["32", "64"] {
+        let output = run("data", template, &["--n-mels", n_mels, "--sidecar"]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+    let out = test_dir.join("out/dogs");
+    assert!(out.join("dogs_a__32mel_16000hz.png").exists());
+    assert!(out.join("dogs_a__64mel_16000hz.png").exists());
+    assert!(out.join("dogs_a__64mel_16000hz.meta.json").exists());
+    let output = run(
+        "data/dogs/a.wav",
+        template,
+        &["--sr", "8000", "--format", "npy"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(test_dir.join("out/dogs_a__0mel_8000hz.npy").exists());
+
+    // Segments of the same input in a manifest are told apart by {chunk}
+    fs::write(
+        test_dir.join("data/manifest.csv"),
+        "path,start,end\ndogs/a.wav,0,0.5\ndogs/a.wav,0.5,1\n",
+    )?;
+    let chunked = |template: &str| {
+        Command::new(get_binary_path())
+            .args(["--manifest", "data/manifest.csv", "--n-fft", "512"])
+            .args(["--win-length", "512", "--output-dir", "chunks"])
+            .args(["--name-template", template])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let output = chunked("{stem}_{chunk}.{ext}");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(test_dir.join("chunks/dogs/a_0.png").exists());
+    assert!(test_dir.join("chunks/dogs/a_1.png").exists());
+    let output = chunked("{stem}.{ext}");
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+
+    // Templates that would not keep the extension or use unknown variables
+    for template in ["{stem}.png", "{stem}_{mels}.{ext}", "{stem.{ext}"] {
+        let output = run("data", template, &[]);
+        assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test computing several variants of each input with --sweep
+#[test]
+fn test_cli_sweep() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("data"))?;
+    create_test_wav(&test_dir.join("data/a.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("data/b.wav"), 0.5, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["data", "--win-length", "512", "--format", "npy"])
+            .args(["--output-dir", "out"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&[
+        "--sweep",
+        "n_mels=32,64",
+        "--sweep",
+        "n_fft=512,1024",
+        "--name-template",
+        "{stem}_{n_mels}_{n_fft}.{ext}",
+        "--manifest-out",
+        "outputs.jsonl",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    for stem in ["a", "b"] {
+        for n_mels in [32, 64] {
+            for n_fft in [512, 1024] {
+                let path = test_dir.join(format!("out/{}_{}_{}.npy", stem, n_mels, n_fft));
+                let spec = spectrs::io::export::load_spectrogram_npy(&path)?;
+                assert_eq!(spec.len(), n_mels);
+            }
+        }
+    }
+    // One record per input, listing the outputs of every variant
+    let records: Vec<serde_json::Value> = fs::read_to_string(test_dir.join("outputs.jsonl"))?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["outputs"].as_array().unwrap().len(), 4);
+
+    // Variants the template cannot tell apart, invalid combinations and run statistics
+    let output = run(&["--sweep", "n_mels=32,64", "--name-template", "{stem}.{ext}"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    let output = run(&[
+        "--sweep",
+        "n_fft=256",
+        "--name-template",
+        "{stem}_{n_fft}.{ext}",
+    ]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    let output = run(&[
+        "--sweep",
+        "n_mels=32",
+        "--name-template",
+        "{stem}_{n_mels}.{ext}",
+        "--stats-out",
+        "stats.csv",
+    ]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    assert!(
+        !run(&[
+            "--sweep",
+            "n_mels=32,32",
+            "--name-template",
+            "{stem}_{n_mels}.{ext}"
+        ])
+        .status
+        .success()
+    );
+    assert!(!run(&["--sweep", "n_mels=32"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test writing the outputs of a directory tree into a single directory
+#[test]
+fn test_cli_flatten() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    for path in ["data/dogs/a.wav", "data/cats/a.wav", "data/cats/b.wav"] {
+        fs::create_dir_all(test_dir.join(path).parent().unwrap())?;
+        create_test_wav(&test_dir.join(path), 0.5, 16000, 1, 16)?;
+    }
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["data", "--n-fft", "512", "--win-length", "512", "--flatten"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&[
+        "--output-dir",
+        "out",
+        "--report",
+        "report.json",
+        "--sidecar",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let mut names: Vec<String> = fs::read_dir(test_dir.join("out"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_>>()?;
+    names.sort();
+    // Both a.wav are renamed, b.wav keeps its name
+    assert_eq!(names.len(), 6, "{:?}", names);
+    assert!(names.contains(&"b.png".to_string()));
+    assert!(!names.contains(&"a.png".to_string()));
+    let renamed: Vec<&String> = names
+        .iter()
+        .filter(|name| name.starts_with("a_") && name.ends_with(".png"))
+        .collect();
+    assert_eq!(renamed.len(), 2);
+    let sidecar = test_dir
+        .join("out")
+        .join(renamed[0].replace(".png", ".meta.json"));
+    assert!(sidecar.exists());
+
+    let report = spectrs::io::metadata::read_run_report(&test_dir.join("report.json"))?;
+    assert_eq!(report.processed.len(), 3);
+    assert_eq!(report.renamed.len(), 2);
+    assert!(report.renamed.iter().all(|r| r.input.ends_with("a.wav")));
+
+    // The same names in another run
+    let output = run(&["--output-dir", "again"]);
+    assert!(output.status.success(), "{:?}", output);
+    for name in renamed {
+        assert!(test_dir.join("again").join(name).exists());
+    }
+
+    // Only with an output directory
+    assert!(!run(&[]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_onsets() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // Decaying 1 kHz bursts at 0.25 s and 0.75 s
+    let sr = 16000;
+    let mut audio = vec![0.0f32; sr as usize];
+    for onset in [0.25, 0.75] {
+        let start = (onset * sr as f32) as usize;
+        for (i, s) in audio[start..].iter_mut().take(3200).enumerate() {
+            let t = i as f32 / sr as f32;
+            *s = 0.8 * (-t * 30.0).exp() * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+        }
+    }
+    spectrs::io::audio::write_audio_file(
+        &test_dir.join("a.wav"),
+        &audio,
+        sr,
+        spectrs::io::audio::WavFormat::Float32,
+    )?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args([
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+                "--hop-length",
+                "128",
+            ])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&["a.wav", "--format", "npy", "--onsets-out", "csv"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let content = fs::read_to_string(test_dir.join("a.onsets.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "frame,time_s,strength");
+    assert_eq!(lines.len(), 3, "{}", content);
+    for (line, expected) in lines[1..].iter().zip([0.25, 0.75]) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let time: f32 = fields[1].parse()?;
+        assert!((time - expected).abs() < 0.02, "{} s", time);
+    }
+
+    // JSON events, on mel spectrograms as well
+    let output = run(&["a.wav", "--n-mels", "40", "--onsets-out", "json"]);
+    assert!(output.status.success());
+    let onsets: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.onsets.json"))?)?;
+    assert_eq!(onsets.as_array().map(Vec::len), Some(2));
+    assert!((onsets[1]["time"].as_f64().unwrap() - 0.75).abs() < 0.02);
+
+    // Markers across the spectrogram, above the waveform panel, only when requested
+    let magenta_columns = |name: &str| -> Result<Vec<u32>> {
+        let img = image::open(test_dir.join(name))?.to_rgb8();
+        let mut columns: Vec<u32> = img
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0 == [255, 0, 255])
+            .map(|(x, _, _)| x)
+            .collect();
+        columns.sort();
+        columns.dedup();
+        Ok(columns)
+    };
+    assert!(run(&["a.wav"]).status.success());
+    assert!(magenta_columns("a.png")?.is_empty());
+    let output = run(&["a.wav", "--onset-overlay", "--with-waveform"]);
+    assert!(output.status.success());
+    assert_eq!(magenta_columns("a.png")?.len(), 2);
+    let img = image::open(test_dir.join("a.png"))?.to_rgb8();
+    let x = magenta_columns("a.png")?[0];
+    assert_eq!(img.get_pixel(x, 0).0, [255, 0, 255]);
+    assert_ne!(img.get_pixel(x, img.height() - 1).0, [255, 0, 255]);
+
+    assert!(!run(&["a.wav", "--onsets-out", "png"]).status.success());
+    assert!(
+        !run(&["a.wav", "--format", "csv", "--onset-overlay"])
+            .status
+            .success()
+    );
+    assert!(
+        !run(&[
+            "a.wav",
+            "--compat",
+            "kaldi",
+            "--format",
+            "csv",
+            "--onsets-out",
+            "csv"
+        ])
+        .status
+        .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_fixed_frames() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/short.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/long.wav"), 2.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args([
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+                "--hop-length",
+                "128",
+            ])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = |name: &str| spectrs::io::export::load_spectrogram_npy(&test_dir.join(name));
+
+    // Every file of the directory shares one shape
+    let output = run(&[
+        "in",
+        "--output-dir",
+        "pad",
+        "--format",
+        "npy",
+        "--fixed-frames",
+        "100",
+    ]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let (short, long) = (load("pad/short.npy")?, load("pad/long.npy")?);
+    assert_eq!((short.len(), short[0].len()), (257, 100));
+    assert_eq!((long.len(), long[0].len()), (257, 100));
+    // 0.5 s make 59 frames, followed by silence; the long file is truncated
+    assert!(short[14][58] > 0.0);
+    assert!(short.iter().all(|row| row[59..].iter().all(|&v| v == 0.0)));
+    let output = run(&["in/long.wav", "--format", "npy", "--output-dir", "full"]);
+    assert!(output.status.success());
+    let full = load("full/long.npy")?;
+    assert_eq!(long[14][..], full[14][..100]);
+
+    // Resampled along time, the last frame kept
+    let output = run(&[
+        "in",
+        "--output-dir",
+        "resample",
+        "--format",
+        "npy",
+        "--fixed-frames",
+        "100",
+        "--fixed-mode",
+        "resample",
+    ]);
+    assert!(output.status.success());
+    let long = load("resample/long.npy")?;
+    assert_eq!(long[0].len(), 100);
+    assert_eq!(long[14][99], full[14][full[0].len() - 1]);
+
+    // Compressed values are padded with their lowest level
+    let output = run(&[
+        "in/short.wav",
+        "--format",
+        "npy",
+        "--fixed-frames",
+        "100",
+        "--compress",
+        "db:80",
+        "--output-dir",
+        "db",
+    ]);
+    assert!(output.status.success());
+    let db = load("db/short.npy")?;
+    let lowest = db.iter().flatten().copied().fold(f32::INFINITY, f32::min);
+    assert_eq!(db[14][99], lowest);
+
+    assert!(
+        !run(&["in/short.wav", "--fixed-frames", "0"])
+            .status
+            .success()
+    );
+    assert!(
+        !run(&["in/short.wav", "--fixed-mode", "resample"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test appending every spectrogram of a directory to a Parquet dataset (--dataset-out)
+#[cfg(feature = "dataset")]
+#[test]
+fn test_cli_dataset_out() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in/dog"))?;
+    fs::create_dir_all(test_dir.join("in/cat"))?;
+    create_test_wav(&test_dir.join("in/dog/a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/cat/b.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&[
+        "in",
+        "--dataset-out",
+        "features.parquet",
+        "--compress",
+        "db",
+        "--deterministic",
+    ]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // No output per input
+    assert!(!test_dir.join("in/dog/a.png").exists());
+    assert!(!test_dir.join("in/dog/a.npy").exists());
+
+    let dataset = spectrs::io::dataset::load_dataset(&test_dir.join("features.parquet"))?;
+    assert_eq!(dataset.params.map(|params| params.n_fft), Some(512));
+    let mut entries = dataset.entries;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].label, "cat");
+    assert_eq!(entries[1].label, "dog");
+    assert!(entries.iter().all(|entry| entry.sample_rate == 16000));
+
+    // Same values as a numerical export of the file
+    let output = run(&[
+        "in/dog/a.wav",
+        "--format",
+        "npy",
+        "--compress",
+        "db",
+        "--output-dir",
+        "npy",
+    ]);
+    assert!(output.status.success());
+    let exported = spectrs::io::export::load_spectrogram_npy(&test_dir.join("npy/a.npy"))?;
+    assert_eq!(entries[1].spectrogram, exported);
+
+    // Per-input outputs cannot be combined with the dataset
+    assert!(
+        !run(&["in", "--dataset-out", "x.parquet", "--format", "npy"])
+            .status
+            .success()
+    );
+    assert!(
+        !run(&["in", "--dataset-out", "x.parquet", "--sidecar"])
+            .status
+            .success()
+    );
+    // The same rows as an Arrow IPC file
+    let output = run(&[
+        "in",
+        "--dataset-out",
+        "features.arrow",
+        "--compress",
+        "db",
+        "--deterministic",
+    ]);
+    assert!(output.status.success());
+    let ipc = spectrs::io::dataset::load_dataset(&test_dir.join("features.arrow"))?;
+    let mut ipc_entries = ipc.entries;
+    ipc_entries.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(ipc_entries, entries);
+
+    // HDF5 is not written
+    let output = run(&["in", "--dataset-out", "features.h5"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Parquet"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test recording the inputs of a run in a SQLite index and skipping them (--index-db)
+#[cfg(feature = "index")]
+#[test]
+fn test_cli_index_db() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in/dog"))?;
+    create_test_wav(&test_dir.join("in/dog/a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/b.wav"), 2.0, 16000, 1, 16)?;
+    fs::write(test_dir.join("in/broken.wav"), b"not a wav file")?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args(["--n-fft", "512", "--win-length", "512"])
+            .args(["--output-dir", "out", "--index-db", "spectrs.db"])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // The failure is recorded with the error
+    let output = run(&["in", "--stats-out", "stats.csv", "--report", "report.json"]);
+    assert!(!output.status.success());
+    let connection = rusqlite::Connection::open(test_dir.join("spectrs.db"))?;
+    let inputs = |sql: &str| -> Result<Vec<String>> {
+        let mut statement = connection.prepare(sql)?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    };
+    assert_eq!(
+        inputs("SELECT input FROM files WHERE status = 'failed'")?,
+        ["in/broken.wav"]
+    );
+    assert_eq!(
+        inputs("SELECT input FROM files WHERE duration_s < 1.0")?,
+        ["in/dog/a.wav"]
+    );
+    assert_eq!(
+        inputs("SELECT label FROM files WHERE status = 'processed' ORDER BY input")?,
+        [".", "dog"]
+    );
+    assert_eq!(
+        inputs(
+            "SELECT input FROM file_features WHERE name = 'centroid_mean_hz' AND value > 400 \
+             ORDER BY input"
+        )?,
+        ["in/b.wav", "in/dog/a.wav"]
+    );
+    let outputs: String = connection.query_row(
+        "SELECT outputs FROM files WHERE input = 'in/b.wav'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(outputs, r#"["out/b.png"]"#);
+
+    // Up-to-date inputs are skipped, the others processed again
+    fs::remove_file(test_dir.join("in/broken.wav"))?;
+    fs::remove_file(test_dir.join("out/b.png"))?;
+    let output = run(&["in", "--skip-indexed", "--report", "report.json"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report = fs::read_to_string(test_dir.join("report.json"))?;
+    assert!(report.contains("Up to date in the index"));
+    assert!(test_dir.join("out/b.png").exists());
+    let report: serde_json::Value = serde_json::from_str(&report)?;
+    assert_eq!(report["processed"][0]["input"], "in/b.wav");
+
+    assert!(
+        !run(&["in", "--skip-indexed", "--global-scale"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test resuming a batch run from the inputs recorded in a checkpoint (--checkpoint)
+#[test]
+fn test_cli_checkpoint() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/b.wav"), 0.5, 16000, 1, 16)?;
+    fs::write(test_dir.join("in/broken.wav"), b"not a wav file")?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["in", "--format", "npy", "--output-dir", "out"])
+            .args(["--checkpoint", "checkpoint.json", "--report", "report.json"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // The inputs that succeeded are recorded even though the run failed
+    assert!(
+        !run(&["--n-fft", "512", "--win-length", "512"])
+            .status
+            .success()
+    );
+    let checkpoint: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("checkpoint.json"))?)?;
+    let mut completed: Vec<&str> = checkpoint["completed"]
+        .as_array()
+        .expect("completed inputs")
+        .iter()
+        .filter_map(|input| input["input"].as_str())
+        .collect();
+    completed.sort();
+    assert_eq!(completed, ["in/a.wav", "in/b.wav"]);
+
+    // Completed inputs are skipped even if their outputs are gone; failed ones are retried
+    fs::remove_file(test_dir.join("out/a.npy"))?;
+    fs::remove_file(test_dir.join("in/broken.wav"))?;
+    create_test_wav(&test_dir.join("in/c.wav"), 0.5, 16000, 1, 16)?;
+    let output = run(&["--n-fft", "512", "--win-length", "512"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!test_dir.join("out/a.npy").exists());
+    assert!(test_dir.join("out/c.npy").exists());
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("report.json"))?)?;
+    assert_eq!(report["processed"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        report["skipped"][0]["reason"],
+        "Completed in the checkpoint"
+    );
+
+    // The checkpoint of other parameters is not resumed
+    let output = run(&["--n-fft", "1024", "--win-length", "1024"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("other parameters"));
+    assert!(!run(&["--global-scale"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that --annotations outlines labeled intervals over images, from one file or from a
+/// directory of files named after the inputs
+#[test]
+fn test_cli_annotations() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    fs::create_dir_all(test_dir.join("labels"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 2.0, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/b.wav"), 2.0, 16000, 1, 16)?;
+    fs::write(test_dir.join("labels.txt"), "0.5\t1.0\tbark\n")?;
+    fs::write(
+        test_dir.join("labels/a.json"),
+        r#"[{"start": 1.0, "end": 2.0, "label": "bird"}]"#,
+    )?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    // Ends of the bottom edge of the outlines (below the labels), as fractions of the width
+    let green_columns = |name: &str| -> Result<Option<(f32, f32)>> {
+        let img = image::open(test_dir.join(name))?.to_rgb8();
+        let bottom = img.height() - 1;
+        let columns: Vec<u32> = (0..img.width())
+            .filter(|x| img.get_pixel(*x, bottom).0 == [0, 255, 0])
+            .collect();
+        let fraction = |x: &u32| *x as f32 / img.width() as f32;
+        Ok(columns
+            .iter()
+            .min()
+            .zip(columns.iter().max())
+            .map(|(min, max)| (fraction(min), fraction(max))))
+    };
+
+    let output = run(&["in/a.wav", "--annotations", "labels.txt"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let (left, right) = green_columns("in/a.png")?.expect("outline");
+    assert!((left - 0.25).abs() < 0.04, "{}", left);
+    assert!((right - 0.5).abs() < 0.04, "{}", right);
+
+    // Times are relative to the input, not to the processed segment
+    assert!(
+        run(&["in/a.wav", "--annotations", "labels.txt", "--start", "0.5"])
+            .status
+            .success()
+    );
+    let (left, _) = green_columns("in/a.png")?.expect("outline");
+    assert!(left < 0.04, "{}", left);
+
+    // Inputs without a file in the directory are left as they are
+    let output = run(&["in", "--annotations", "labels"]);
+    assert!(output.status.success());
+    let (left, _) = green_columns("in/a.png")?.expect("outline");
+    assert!((left - 0.5).abs() < 0.04, "{}", left);
+    assert_eq!(green_columns("in/b.png")?, None);
+
+    assert!(
+        !run(&["in/a.wav", "--annotations", "labels.txt", "--format", "npy"])
+            .status
+            .success()
+    );
+    fs::write(test_dir.join("broken.txt"), "not labels")?;
+    assert!(
+        !run(&["in/a.wav", "--annotations", "broken.txt"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test the exit codes of a run: 0 when every input succeeds, 2 when some fail, 3 for a
+/// configuration error, and the summary table of a batch
+#[test]
+fn test_cli_exit_codes() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 0.5, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let batch = ["in", "--format", "npy", "--output-dir", "out"];
+
+    let output = run(&batch);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Summary"), "{}", stderr);
+    assert!(stderr.contains("processed"), "{}", stderr);
+    assert_eq!(run(&["--version"]).status.code(), Some(0));
+
+    // Some inputs failed: the others are processed, and the failures listed in the summary
+    fs::write(test_dir.join("in/broken.wav"), b"not a wav file")?;
+    let output = run(&batch);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed: in/broken.wav"), "{}", stderr);
+    assert!(stderr.contains("1 of 2 inputs failed"), "{}", stderr);
+    assert_eq!(run(&["in/broken.wav"]).status.code(), Some(2));
+    // Quiet runs print no summary
+    let output = run(&["--log-level", "error", "in", "--output-dir", "out"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Summary"));
+
+    // Configuration errors: rejected arguments, invalid parameters or missing inputs
+    assert_eq!(run(&["in", "--no-such-option"]).status.code(), Some(3));
+    assert_eq!(run(&["in", "--n-fft", "0"]).status.code(), Some(3));
+    assert_eq!(run(&["missing.wav"]).status.code(), Some(3));
+    assert_eq!(
+        run(&["in", "--compress", "db", "--format", "png"])
+            .status
+            .code(),
+        Some(3)
+    );
+    assert_eq!(
+        run(&["in", "--start", "0.5", "--end", "0.2"]).status.code(),
+        Some(3)
+    );
+
+    // Options and commands of features not compiled in
+    let mut missing: Vec<&[&str]> = Vec::new();
+    if !cfg!(feature = "index") {
+        missing.push(&["in", "--index-db", "index.db"]);
+    }
+    if !cfg!(feature = "dataset") {
+        missing.push(&["in", "--dataset-out", "dataset.parquet"]);
+    }
+    if !cfg!(feature = "object-store") {
+        missing.push(&["s3://bucket/in"]);
+        missing.push(&["in", "--output-dir", "s3://bucket/out"]);
+    }
+    if !cfg!(feature = "watch") {
+        missing.push(&["watch", "in"]);
+    }
+    if !cfg!(feature = "serve") {
+        missing.push(&["serve"]);
+    }
+    if !cfg!(feature = "tui") {
+        missing.push(&["view", "in/a.wav"]);
+    }
+    for args in missing {
+        assert_eq!(run(args).status.code(), Some(3), "{:?}", args);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that --dry-run lists the inputs with their outputs after the skip logic, without
+/// computing or writing anything
+#[test]
+fn test_cli_dry_run() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in/sub"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/sub/b.wav"), 1.0, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/long.wav"), 30.0, 16000, 1, 16)?;
+    fs::write(test_dir.join("in/notes.txt"), b"not audio")?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args([
+                "in",
+                "--output-dir",
+                "out",
+                "--format",
+                "npy",
+                "--format",
+                "png",
+            ])
+            .args(["--dry-run", "--max-memory-mb", "4", "--sr", "8000"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    let output = run(&["--exclude", "sub"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("in/a.wav -> out/a.npy, out/a.png"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("in/sub/b.wav ->"), "{}", stdout);
+    assert!(stdout.contains("fail in/long.wav"), "{}", stdout);
+    assert!(stdout.contains("Parameters at 8000 Hz"), "{}", stdout);
+    assert!(!test_dir.join("out").exists());
+
+    // The same plan as JSON, on stdout or in a file
+    let output = run(&["--plan-out", "-"]);
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(run(&["--plan-out", "plan.json"]).status.success());
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("plan.json"))?)?;
+    assert_eq!(plan, saved);
+    assert_eq!(plan["sample_rate"], 8000);
+    assert_eq!(plan["params"]["n_fft"], 2048);
+    let inputs: Vec<&str> = plan["inputs"]
+        .as_array()
+        .expect("planned inputs")
+        .iter()
+        .filter_map(|input| input["input"].as_str())
+        .collect();
+    assert_eq!(inputs, ["in/a.wav", "in/sub/b.wav"]);
+    assert_eq!(plan["inputs"][1]["duration"], 1.0);
+    assert_eq!(plan["inputs"][1]["outputs"][0], "out/sub/b.npy");
+    assert_eq!(plan["skipped"][0]["input"], "in/notes.txt");
+    assert_eq!(plan["failed"][0]["input"], "in/long.wav");
+    assert!(!test_dir.join("out").exists());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that inputs exceeding --max-memory-mb or --per-file-timeout are reported as failed
+/// without stopping the others
+#[test]
+fn test_cli_resource_limits() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/short.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/long.wav"), 30.0, 16000, 2, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["in", "--format", "npy", "--output-dir", "out"])
+            .args(["--report", "report.json"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let read_report = || -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(&fs::read_to_string(
+            test_dir.join("report.json"),
+        )?)?)
+    };
+
+    // The long input is estimated to need more memory than allowed, the short one is processed
+    assert!(!run(&["--max-memory-mb", "4"]).status.success());
+    assert!(test_dir.join("out/short.npy").exists());
+    assert!(!test_dir.join("out/long.npy").exists());
+    let report = read_report()?;
+    assert_eq!(report["processed"][0]["input"], "in/short.wav");
+    assert_eq!(report["failed"][0]["input"], "in/long.wav");
+    let reason = report["failed"][0]["reason"].as_str().unwrap_or_default();
+    assert!(reason.contains("--max-memory-mb"), "{}", reason);
+
+    // A generous timeout changes nothing, a tiny one fails the input
+    let output = run(&["--per-file-timeout", "120s"]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join("out/long.npy").exists());
+    fs::remove_file(test_dir.join("in/short.wav"))?;
+    assert!(!run(&["--per-file-timeout", "0.001s"]).status.success());
+    let reason = read_report()?["failed"][0]["reason"].clone();
+    assert!(
+        reason.as_str().unwrap_or_default().contains("Timed out"),
+        "{}",
+        reason
+    );
+    assert!(!run(&["--per-file-timeout", "soon"]).status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test reading inputs from and writing outputs to an object store (file:// URLs go through
+/// the same code path as s3:// ones)
+#[cfg(feature = "object-store")]
+#[test]
+fn test_cli_object_store() -> Result<()> {
+    let test_dir = fs::canonicalize(setup_test_dir()?)?;
+    fs::create_dir_all(test_dir.join("in/sub"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 0.5, 16000, 1, 16)?;
+    create_test_wav(&test_dir.join("in/sub/b.wav"), 0.5, 16000, 1, 16)?;
+    fs::write(test_dir.join("in/notes.txt"), "not audio")?;
+    let root = format!("file://{}", test_dir.display());
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Every output is uploaded with the structure of the inputs, and listed by its URL
+    let output = run(&[
+        &format!("{}/in", root),
+        "--output-dir",
+        &format!("{}/out", root),
+        "--format",
+        "png,npy",
+        "--manifest-out",
+        "manifest.jsonl",
+    ]);
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join("out/a.png").exists());
+    assert!(test_dir.join("out/sub/b.npy").exists());
+    assert!(!test_dir.join("out/notes.png").exists());
+    let manifest = fs::read_to_string(test_dir.join("manifest.jsonl"))?;
+    assert!(manifest.contains(&format!("\"input\":\"{}/in/sub/b.wav\"", root)));
+    assert!(manifest.contains(&format!("\"{}/out/sub/b.npy\"", root)));
+
+    // Single objects, and local inputs into a store
+    let output = run(&[&format!("{}/in/a.wav", root), "--output-dir", "local"]);
+    assert!(output.status.success());
+    assert!(test_dir.join("local/a.png").exists());
+    let output = run(&["in/a.wav", "--output-dir", &format!("{}/uploaded", root)]);
+    assert!(output.status.success());
+    assert!(test_dir.join("uploaded/a.png").exists());
+
+    assert!(!run(&["gs://bucket/audio"]).status.success());
+    assert!(
+        !run(&[&format!("{}/in", root), "--raw-pcm", "sr=16000,fmt=s16le"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test processing the WAV files of zip and gzipped tar archives without extracting them
+#[cfg(feature = "archive")]
+#[test]
+fn test_cli_archive() -> Result<()> {
+    use std::io::Write;
+
+    let test_dir = setup_test_dir()?;
+    let wav = test_dir.join("source.wav");
+    create_test_wav(&wav, 0.5, 16000, 1, 16)?;
+    let bytes = fs::read(&wav)?;
+    let names = ["spk1/a.wav", "spk2/b.wav", "notes.txt"];
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(test_dir.join("corpus.zip"))?);
+    for name in names {
+        zip.start_file(name, zip::write::SimpleFileOptions::default())?;
+        zip.write_all(&bytes)?;
+    }
+    zip.finish()?;
+    let encoder = flate2::write::GzEncoder::new(
+        fs::File::create(test_dir.join("corpus.tar.gz"))?,
+        flate2::Compression::default(),
+    );
+    let mut tar = tar::Builder::new(encoder);
+    for name in names {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, name, bytes.as_slice())?;
+    }
+    tar.into_inner()?.finish()?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args(["--format", "npy"])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    // The structure of the archive is kept in the output tree
+    for archive in ["corpus.zip", "corpus.tar.gz"] {
+        let out = format!("out_{}", archive);
+        let output = run(&[archive, "--output-dir", &out, "--report", "report.json"]);
+        assert!(
+            output.status.success(),
+            "CLI failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let direct = run(&["source.wav", "--output-dir", "direct"]);
+        assert!(direct.status.success());
+        let expected = fs::read(test_dir.join("direct/source.npy"))?;
+        assert_eq!(fs::read(test_dir.join(&out).join("spk1/a.npy"))?, expected);
+        assert_eq!(fs::read(test_dir.join(&out).join("spk2/b.npy"))?, expected);
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(test_dir.join("report.json"))?)?;
+        assert_eq!(
+            report["processed"][0]["input"],
+            format!("{}/spk1/a.wav", archive)
+        );
+        assert_eq!(report["skipped"][0]["reason"], "not an audio file");
+    }
+
+    // Walk options apply to the paths inside the archive
+    let output = run(&["corpus.zip", "--output-dir", "only", "--exclude", "spk2"]);
+    assert!(output.status.success());
+    assert!(test_dir.join("only/spk1/a.npy").exists());
+    assert!(!test_dir.join("only/spk2").exists());
+
+    assert!(
+        !run(&["corpus.zip", "--raw-pcm", "sr=16000,fmt=s16le"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test processing the files dropped into a watched directory (spectrs watch)
+#[cfg(feature = "watch")]
+#[test]
+fn test_cli_watch() -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in/sub"))?;
+    create_test_wav(&test_dir.join("in/old.wav"), 0.5, 16000, 1, 16)?;
+    let watch = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg("watch")
+            .args(args)
+            .args(["--", "--n-fft", "512", "--win-length", "512"])
+            .args(["--format", "npy", "--output-dir", "out"])
+            .current_dir(&test_dir)
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute spectrs")
+    };
+    let wait = |mut child: std::process::Child| -> Result<bool> {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while Instant::now() < deadline {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status.success());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        child.kill()?;
+        child.wait()?;
+        anyhow::bail!("spectrs watch did not stop")
+    };
+
+    // New files are processed once settled, keeping the structure of the directory; existing
+    // ones are left alone
+    let child = watch(&["in", "--settle", "0.3", "--max-files", "2"]);
+    std::thread::sleep(Duration::from_millis(500));
+    let created = (|| -> Result<()> {
+        create_test_wav(&test_dir.join("in/new.wav"), 0.5, 16000, 1, 16)?;
+        create_test_wav(&test_dir.join("in/sub/nested.wav"), 0.5, 16000, 1, 16)?;
+        fs::write(test_dir.join("in/notes.txt"), "not audio")?;
+        Ok(())
+    })();
+    assert!(wait(child)?);
+    created?;
+    assert!(test_dir.join("out/new.npy").exists());
+    assert!(test_dir.join("out/sub/nested.npy").exists());
+    assert!(!test_dir.join("out/old.npy").exists());
+
+    // Existing files on request
+    let child = watch(&["in", "--settle", "0", "--existing", "--max-files", "3"]);
+    assert!(wait(child)?);
+    assert!(test_dir.join("out/old.npy").exists());
+
+    // Options every batch cannot honour are rejected
+    let output = Command::new(get_binary_path())
+        .args(["watch", "in", "--", "--global-scale"])
+        .current_dir(&test_dir)
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test that an input given up on by --per-file-timeout leaves no outputs behind, even when the
+/// process outlives its computation (spectrs watch)
+#[cfg(feature = "watch")]
+#[test]
+fn test_cli_timeout_leaves_no_outputs() -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    let test_dir = setup_test_dir()?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/long.wav"), 60.0, 16000, 2, 16)?;
+    let compute = [
+        "--n-fft",
+        "512",
+        "--win-length",
+        "512",
+        "--format",
+        "npy",
+        "--sidecar",
+    ];
+
+    // Time the input takes without a timeout
+    let started = Instant::now();
+    let output = Command::new(get_binary_path())
+        .args(["in", "--output-dir", "measured"])
+        .args(compute)
+        .current_dir(&test_dir)
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let elapsed = started.elapsed();
+
+    // Timed out at once, then given ample time to finish its computation in the background
+    let mut child = Command::new(get_binary_path())
+        .args(["watch", "in", "--settle", "0", "--existing", "--"])
+        .args(compute)
+        .args(["--output-dir", "out", "--per-file-timeout", "0.001s"])
+        .current_dir(&test_dir)
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to execute spectrs");
+    std::thread::sleep(elapsed * 3 + Duration::from_secs(1));
+    let killed = child.kill();
+    child.wait()?;
+    killed?;
+    let outputs: Vec<_> = match fs::read_dir(test_dir.join("out")) {
+        Ok(entries) => entries.collect::<std::io::Result<_>>()?,
+        Err(_) => Vec::new(),
+    };
+    assert!(
+        outputs.is_empty(),
+        "timed-out input left {:?}",
+        outputs.iter().map(|e| e.file_name()).collect::<Vec<_>>()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test computing the spectrograms of audio posted to the HTTP service (spectrs serve)
+#[cfg(feature = "serve")]
+#[test]
+fn test_cli_serve() -> Result<()> {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpStream;
+
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+    let audio = fs::read(&input_wav)?;
+
+    // Status code and body of a request to the service
+    let request = |port: u16, method: &str, target: &str, body: &[u8]| -> Result<(u16, Vec<u8>)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            method,
+            target,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let split = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| anyhow::anyhow!("Malformed response"))?;
+        let head = String::from_utf8_lossy(&response[..split]).to_string();
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Malformed status line: {}", head))?;
+        Ok((status, response[split + 4..].to_vec()))
+    };
+
+    // Any free port, read back from the log
+    let mut child = Command::new(get_binary_path())
+        .args(["serve", "--port", "0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute spectrs");
+    let served = (|| -> Result<()> {
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let mut lines = BufReader::new(stderr).lines();
+        let port: u16 = lines
+            .by_ref()
+            .find_map(|line| {
+                let line = line.ok()?;
+                let address = line.split("http://").nth(1)?;
+                address.rsplit(':').next()?.trim().parse().ok()
+            })
+            .ok_or_else(|| anyhow::anyhow!("spectrs serve did not start listening"))?;
+
+        // Keep draining the log, so that the service never blocks on a full pipe
+        std::thread::spawn(move || lines.for_each(drop));
+        assert_eq!(request(port, "GET", "/health", b"")?, (200, b"ok".to_vec()));
+
+        // Same values as spectrs compute with the options of the query string
+        let (status, body) = request(
+            port,
+            "POST",
+            "/spectrogram?format=npy&n_fft=512&win_length=512&n_mels=32",
+            &audio,
+        )?;
+        assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+        let served = spectrs::io::export::read_spectrogram_npy(&body[..])?;
+        let output = Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(["--n-mels", "32"])
+            .output()
+            .expect("Failed to execute spectrs");
+        assert!(output.status.success());
+        let computed = spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"))?;
+        assert_eq!(served, computed);
+
+        // Images by default, flags with or without a value
+        let (status, body) = request(port, "POST", "/spectrogram?lenient&loudness=false", &audio)?;
+        assert_eq!(status, 200);
+        assert!(body.starts_with(b"\x89PNG"));
+        let (status, body) = request(port, "POST", "/spectrogram?format=json", &audio)?;
+        assert_eq!(status, 200);
+        assert!(serde_json::from_slice::<serde_json::Value>(&body).is_ok());
+
+        // Unknown options, options touching the files of the server and invalid audio
+        assert_eq!(
+            request(port, "POST", "/spectrogram?n_bands=3", &audio)?.0,
+            400
+        );
+        assert_eq!(
+            request(port, "POST", "/spectrogram?cache_dir=/tmp", &audio)?.0,
+            400
+        );
+        assert_eq!(request(port, "POST", "/spectrogram", b"not audio")?.0, 422);
+        Ok(())
+    })();
+    // Fails only if the service already exited
+    child.kill().ok();
+    child.wait()?;
+    served?;
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_frame_alignment() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 1.0, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(["--hop-length", "160"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let n_frames = || -> Result<usize> {
+        Ok(spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"))?[0].len())
+    };
+
+    assert!(run(&[]).status.success());
+    assert_eq!(n_frames()?, 1 + (16000 - 512) / 160);
+    let output = run(&["--frame-alignment", "librosa"]);
+    assert!(output.status.success());
+    assert_eq!(n_frames()?, 1 + 16000 / 160);
+
+    // Only with librosa's conventions
+    let output = run(&["--frame-alignment", "librosa", "--compat", "kaldi"]);
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_periodic_window() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "400"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"));
+
+    assert!(run(&[]).status.success());
+    let symmetric = load()?;
+    assert!(run(&["--periodic-window", "true"]).status.success());
+    let periodic = load()?;
+    assert_eq!(periodic.len(), symmetric.len());
+    assert_eq!(periodic[0].len(), symmetric[0].len());
+    assert_ne!(periodic, symmetric);
+
+    // Periodic by default with librosa's frame alignment
+    assert!(run(&["--frame-alignment", "librosa"]).status.success());
+    let librosa = load()?;
+    let output = run(&["--frame-alignment", "librosa", "--periodic-window", "true"]);
+    assert!(output.status.success());
+    assert_eq!(load()?, librosa);
+
+    // The fbank presets have windows of their own
+    let output = run(&["--compat", "kaldi", "--periodic-window", "true"]);
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_window_norm() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"));
+
+    assert!(run(&[]).status.success());
+    let plain = load()?;
+    assert!(run(&["--window-norm", "sum"]).status.success());
+    let normalized = load()?;
+
+    // Powers scaled by the square of the inverse of the sum of the (symmetric) window
+    let scale = spectrs::spectrogram::params::SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(512)
+        .window_norm(spectrs::spectrogram::stft::WindowNorm::Sum)
+        .build()?
+        .win_scale();
+    assert!((scale - 2.0 / 511.0).abs() < 1e-9);
+    let peak = plain[14][3];
+    assert!(
+        (normalized[14][3] as f64 - peak as f64 * scale * scale).abs()
+            < 1e-4 * peak as f64 * scale * scale
+    );
+
+    // The fbank presets have windows of their own
+    let output = run(&["--compat", "kaldi", "--window-norm", "sum"]);
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_fft_backend() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_wav = test_dir.join("tone.wav");
+    create_test_wav(&input_wav, 0.5, 16000, 1, 16)?;
+
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_wav.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let load = || spectrs::io::export::load_spectrogram_npy(&test_dir.join("tone.npy"));
+
+    assert!(run(&[]).status.success());
+    let default = load()?;
+    assert!(run(&["--fft-backend", "rustfft"]).status.success());
+    assert_eq!(load()?, default);
+
+    // FFTW gives the same values up to rounding
+    let output = run(&["--fft-backend", "fftw"]);
+    if cfg!(feature = "fftw") {
+        assert!(output.status.success());
+        let fftw = load()?;
+        let peak = default.iter().flatten().fold(0.0f32, |a, &b| a.max(b));
+        for (row, fftw_row) in default.iter().zip(&fftw) {
+            for (v, w) in row.iter().zip(fftw_row) {
+                assert!((v - w).abs() <= 1e-5 * peak);
+            }
+        }
+    } else {
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("fftw"));
+    }
+
+    // The presets compute their own FFTs
+    let output = run(&["--compat", "kaldi", "--fft-backend", "fftw"]);
+    assert!(!output.status.success());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test stopping a batch with Ctrl-C: the inputs not started are skipped, the outputs written
+/// are complete and the reports are saved
+#[cfg(unix)]
+#[test]
+fn test_cli_interrupt() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    let output_dir = test_dir.join("output");
+    fs::create_dir_all(&input_dir)?;
+    let n_inputs = 200;
+    for i in 0..n_inputs {
+        create_complex_test_wav(&input_dir.join(format!("{:03}.wav", i)), 3.0, 16000, 1, 16)?;
+    }
+    let report_path = test_dir.join("report.json");
+
+    let mut child = Command::new(get_binary_path())
+        .arg(input_dir.to_str().unwrap())
+        .args(["--output-dir", output_dir.to_str().unwrap(), "--report"])
+        .arg(&report_path)
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to execute spectrs");
+
+    // Interrupted once the first outputs are written
+    while fs::read_dir(&output_dir).map_or(true, |mut entries| entries.next().is_none()) {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("Failed to send SIGINT");
+    let status = child.wait()?;
+    assert_eq!(status.code(), Some(130));
+
+    let report = spectrs::io::metadata::read_run_report(&report_path)?;
+    assert!(report.failed.is_empty());
+    assert!(!report.skipped.is_empty());
+    assert!(
+        report
+            .skipped
+            .iter()
+            .all(|input| input.reason == "Interrupted")
+    );
+    assert_eq!(report.processed.len() + report.skipped.len(), n_inputs);
+
+    // Only the processed inputs have outputs, none of them truncated
+    let outputs: Vec<PathBuf> = fs::read_dir(&output_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(outputs.len(), report.processed.len());
+    for output in outputs {
+        image::open(&output)?;
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+/// Test overriding the parameters of the inputs of subdirectories with .spectrs.toml files
+#[test]
+fn test_cli_directory_params() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    for name in ["studio/a.wav", "phone/b.wav", "phone/mel/c.wav"] {
+        let path = input_dir.join(name);
+        fs::create_dir_all(path.parent().unwrap())?;
+        create_test_wav(&path, 1.0, 16000, 1, 16)?;
+    }
+    // Every input, then the telephone folder and its mel subfolder
+    fs::write(input_dir.join(".spectrs.toml"), "hop_length = 256\n")?;
+    fs::write(
+        input_dir.join("phone/.spectrs.toml"),
+        "n_fft = 256\nwin_length = 256\n",
+    )?;
+    fs::write(input_dir.join("phone/mel/.spectrs.toml"), "n_mels = 20\n")?;
+
+    let run = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_dir.to_str().unwrap())
+            .args(["--format", "npy", "--n-fft", "512", "--win-length", "512"])
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let shape = |path: &Path| -> Result<(usize, usize)> {
+        let spec = spectrs::io::export::load_spectrogram_npy(path)?;
+        Ok((spec.len(), spec[0].len()))
+    };
+
+    let output = run(
+        "out",
+        &["--report", test_dir.join("report.json").to_str().unwrap()],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let out = test_dir.join("out");
+    assert_eq!(shape(&out.join("studio/a.npy"))?, (257, 61));
+    assert_eq!(shape(&out.join("phone/b.npy"))?, (129, 62));
+    assert_eq!(shape(&out.join("phone/mel/c.npy"))?, (20, 62));
+    // The files are not inputs
+    let report = spectrs::io::metadata::read_run_report(&test_dir.join("report.json"))?;
+    assert_eq!(report.processed.len(), 3);
+    assert!(report.skipped.is_empty());
+
+    // The plan lists the parameters changed for the inputs
+    let output = run("plan", &["--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().find(|line| line.contains("b.wav")).unwrap();
+    assert!(
+        line.contains("n_fft=256") && line.contains("hop_length=256"),
+        "{}",
+        line
+    );
+    assert!(!line.contains("n_mels"), "{}", line);
+
+    // Ignored on request
+    let output = run("ignored", &["--no-directory-params"]);
+    assert!(output.status.success());
+    assert_eq!(shape(&test_dir.join("ignored/phone/mel/c.npy"))?, (257, 31));
+
+    // Unknown and invalid parameters are configuration errors
+    fs::write(input_dir.join("phone/.spectrs.toml"), "nfft = 256\n")?;
+    let output = run("unknown", &[]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown parameter: nfft"));
+    fs::write(input_dir.join("phone/.spectrs.toml"), "n_fft = 256\n")?;
+    assert_eq!(run("invalid", &[]).status.code(), Some(3));
+    assert!(!test_dir.join("invalid").exists());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_auto_params() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let input_dir = test_dir.join("input");
+    fs::create_dir_all(&input_dir)?;
+    create_test_wav(&input_dir.join("phone.wav"), 1.0, 8000, 1, 16)?;
+    create_test_wav(&input_dir.join("music.wav"), 1.0, 44100, 1, 16)?;
+
+    let run = |output: &str, args: &[&str]| {
+        Command::new(get_binary_path())
+            .arg(input_dir.to_str().unwrap())
+            .args(["--format", "npy", "--auto-params"])
+            .args(["--output-dir", test_dir.join(output).to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+    let shape = |path: &Path| -> Result<(usize, usize)> {
+        let spec = spectrs::io::export::load_spectrogram_npy(path)?;
+        Ok((spec.len(), spec[0].len()))
+    };
+
+    // 25 ms windows every 10 ms, mel bands following the sample rate
+    let output = run("out", &[]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 input at 8000 Hz: n_fft=256, win_length=200, hop_length=80, n_mels=40")
+    );
+    assert!(
+        stderr.contains(
+            "1 input at 44100 Hz: n_fft=2048, win_length=1103, hop_length=441, n_mels=128"
+        )
+    );
+    assert_eq!(shape(&test_dir.join("out/phone.npy"))?, (40, 98));
+    assert_eq!(shape(&test_dir.join("out/music.npy"))?, (128, 98));
+
+    // Options given explicitly are kept, the target sample rate is the one inspected
+    let output = run("given", &["--n-mels", "20", "--sr", "16000"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .contains("2 inputs at 16000 Hz: n_fft=512, win_length=400, hop_length=160, n_mels=20")
+    );
+    assert_eq!(shape(&test_dir.join("given/phone.npy"))?, (20, 98));
+
+    // A single input, printed with the plan of a dry run
+    let output = Command::new(get_binary_path())
+        .arg(input_dir.join("phone.wav").to_str().unwrap())
+        .args(["--auto-params", "--dry-run"])
+        .output()
+        .expect("Failed to execute spectrs");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("n_fft=256"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_tonal_features() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(args)
+            .args(["--n-fft", "1024", "--win-length", "1024"])
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // One column per frame of the spectrogram, even of a mel one, the sine in the class A
+    let output = run(&[
+        "a.wav",
+        "--format",
+        "npy",
+        "--n-mels",
+        "40",
+        "--chroma-out",
+        "npy",
+        "--tonnetz-out",
+        "csv",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"))?;
+    let chroma = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.chroma.npy"))?;
+    assert_eq!(chroma.len(), 12);
+    assert_eq!(chroma[0].len(), spec[0].len());
+    assert!(chroma[9].iter().all(|&v| (v - 1.0).abs() < 1e-6));
+    let tonnetz = fs::read_to_string(test_dir.join("a.tonnetz.csv"))?;
+    let rows: Vec<&str> = tonnetz.lines().collect();
+    assert_eq!(rows.len(), 6);
+    assert_eq!(rows[0].split(',').count(), spec[0].len());
+
+    // Matrices only, from the linear bins
+    for args in [
+        &["a.wav", "--chroma-out", "png"][..],
+        &[
+            "a.wav",
+            "--format",
+            "csv",
+            "--tonnetz-out",
+            "csv",
+            "--compat",
+            "kaldi",
+        ],
+    ] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_band_levels() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args([
+                "a.wav",
+                "--format",
+                "npy",
+                "--n-fft",
+                "512",
+                "--win-length",
+                "512",
+            ])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // Octave bands up to 4 kHz at 16 kHz, the 440 Hz sine at -3 dB in the 500 Hz band
+    let output = run(&[
+        "--band-levels-out",
+        "csv",
+        "--bands",
+        "octave",
+        "--band-frame-ms",
+        "100",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let content = fs::read_to_string(test_dir.join("a.bands.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        lines[0],
+        "time_s,31.5_hz,63_hz,125_hz,250_hz,500_hz,1000_hz,2000_hz,4000_hz"
+    );
+    assert_eq!(lines.len(), 11);
+    let last: Vec<f32> = lines[10]
+        .split(',')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert!((last[0] - 0.9).abs() < 1e-6);
+    assert!((last[5] + 3.01).abs() < 0.2, "{}", lines[10]);
+    assert!(last[4] < -15.0 && last[6] < -15.0, "{}", lines[10]);
+
+    // Third-octave levels over the frames of the hop: a [band][frame] matrix and JSON with Leq
+    let output = run(&["--band-levels-out", "npy"]);
+    assert!(output.status.success(), "{:?}", output);
+    let levels = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.bands.npy"))?;
+    assert_eq!((levels.len(), levels[0].len()), (25, 32));
+    let output = run(&["--band-levels-out", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.bands.json"))?)?;
+    assert_eq!(json["fraction"], "third");
+    assert_eq!(json["bands_hz"][0], 25.0);
+    assert_eq!(json["leq_db"].as_array().unwrap().len(), 25);
+
+    let output = run(&["--band-levels-out", "png"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_cepstral_peaks() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["a.wav", "--format", "npy", "--hop-length", "160"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // One row per frame of the spectrogram, at the centre of the frame
+    let n_fft = ["--n-fft", "1024", "--win-length", "1024"];
+    let output = run(&[&n_fft[..], &["--cpp-out", "csv"]].concat());
+    assert!(output.status.success(), "{:?}", output);
+    let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"))?;
+    let content = fs::read_to_string(test_dir.join("a.cpp.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "time_s,f0_hz,cpp_db");
+    assert_eq!(lines.len(), spec[0].len() + 1);
+    let first: Vec<f32> = lines[1]
+        .split(',')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert!((first[0] - 0.032).abs() < 1e-6, "{}", lines[1]);
+    assert!((60.0..=330.0).contains(&first[1]), "{}", lines[1]);
+
+    let output = run(&[&n_fft[..], &["--cpp-out", "json", "--cpp-range", "80:300"]].concat());
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.cpp.json"))?)?;
+    assert_eq!(json["cpp_db"].as_array().unwrap().len(), spec[0].len());
+    assert!(json["cpp_mean_db"].is_number());
+
+    // An FFT of 512 points cannot hold the period of 60 Hz
+    let output = run(&["--n-fft", "512", "--win-length", "512", "--cpp-out", "csv"]);
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("n_fft of at least 534"));
+
+    let output = run(&["--cpp-out", "npy"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_lpc_order() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    create_complex_test_wav(&test_dir.join("a.wav"), 1.0, 16000, 1, 16)?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["a.wav", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // The envelope has the shape of the spectrogram, mel bands included
+    let output = run(&["--format", "npy", "--output-dir", "linear"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = run(&[
+        "--format",
+        "npy",
+        "--lpc-order",
+        "18",
+        "--output-dir",
+        "lpc",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let linear = spectrs::io::export::load_spectrogram_npy(&test_dir.join("linear/a.npy"))?;
+    let envelope = spectrs::io::export::load_spectrogram_npy(&test_dir.join("lpc/a.npy"))?;
+    assert_eq!(
+        (envelope.len(), envelope[0].len()),
+        (linear.len(), linear[0].len())
+    );
+    assert_ne!(envelope, linear);
+    let output = run(&["--lpc-order", "18", "--n-mels", "40"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(test_dir.join("a.png").exists());
+
+    // Orders of at least half the FFT
+    let output = run(&["--lpc-order", "256"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("LPC order"));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli_formants() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    // Vowel /a/: a 120 Hz pulse train through resonators at 700, 1220 and 2600 Hz
+    let sr = 16000;
+    let mut audio: Vec<f32> = (0..sr as usize)
+        .map(|i| if i % 133 == 0 { 0.5 } else { 0.0 })
+        .collect();
+    for (frequency, bandwidth) in [(700.0f64, 130.0f64), (1220.0, 70.0), (2600.0, 160.0)] {
+        let radius = (-std::f64::consts::PI * bandwidth / sr as f64).exp();
+        let angle = 2.0 * std::f64::consts::PI * frequency / sr as f64;
+        let (a1, a2) = (
+            (-2.0 * radius * angle.cos()) as f32,
+            (radius * radius) as f32,
+        );
+        for i in 2..audio.len() {
+            audio[i] -= a1 * audio[i - 1] + a2 * audio[i - 2];
+        }
+    }
+    let peak = audio.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    audio.iter_mut().for_each(|s| *s *= 0.9 / peak);
+    spectrs::io::audio::write_audio_file(
+        &test_dir.join("a.wav"),
+        &audio,
+        sr,
+        spectrs::io::audio::WavFormat::Float32,
+    )?;
+    let run = |args: &[&str]| {
+        Command::new(get_binary_path())
+            .args(["a.wav", "--n-fft", "512", "--win-length", "512"])
+            .args(args)
+            .current_dir(&test_dir)
+            .output()
+            .expect("Failed to execute spectrs")
+    };
+
+    // One row per frame of the spectrogram, with F1 to F3 and their bandwidths
+    let output = run(&["--format", "npy", "--formants-out", "csv"]);
+    assert!(output.status.success(), "{:?}", output);
+    let content = fs::read_to_string(test_dir.join("a.formants.csv"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "time_s,f1_hz,b1_hz,f2_hz,b2_hz,f3_hz,b3_hz");
+    let spec = spectrs::io::export::load_spectrogram_npy(&test_dir.join("a.npy"))?;
+    assert_eq!(lines.len() - 1, spec[0].len());
+    let fields: Vec<f32> = lines[lines.len() / 2]
+        .split(',')
+        .map(|field| field.parse())
+        .collect::<std::result::Result<_, _>>()?;
+    for (frequency, expected) in [fields[1], fields[3], fields[5]]
+        .into_iter()
+        .zip([700.0, 1220.0, 2600.0])
+    {
+        assert!((frequency / expected - 1.0).abs() < 0.05, "{:?}", fields);
+    }
+
+    // JSON tracks list every formant found in each frame
+    let output = run(&["--formants-out", "json", "--formant-ceiling", "5000"]);
+    assert!(output.status.success(), "{:?}", output);
+    let track: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(test_dir.join("a.formants.json"))?)?;
+    let frames = track["formants"].as_array().unwrap();
+    assert_eq!(frames.len(), track["times"].as_array().unwrap().len());
+    let f1 = frames[frames.len() / 2][0]["frequency"].as_f64().unwrap();
+    assert!((f1 - 700.0).abs() < 35.0, "{} Hz", f1);
+
+    // F1 is drawn in red along the row of 700 Hz (31.25 Hz per row, lowest at the bottom),
+    // and only when requested
+    let red_rows = |name: &str| -> Result<Vec<u32>> {
+        let img = image::open(test_dir.join(name))?.to_rgb8();
+        Ok(img
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0 == [255, 0, 0])
+            .map(|(_, y, _)| y)
+            .collect())
+    };
+    assert!(run(&[]).status.success());
+    assert!(red_rows("a.png")?.is_empty());
+    assert!(run(&["--formant-overlay"]).status.success());
+    let rows = red_rows("a.png")?;
+    assert!(rows.len() >= 30, "{} pixels", rows.len());
+    let expected = 256.0 - 700.0 / 31.25;
+    let near = rows
+        .iter()
+        .filter(|&&y| (y as f32 - expected).abs() <= 2.0)
+        .count();
+    assert!(near * 10 >= rows.len() * 9, "{:?}", rows);
+
+    // Invalid ceilings and formats, and overlays of numerical outputs
+    let output = run(&["--formants-out", "csv", "--formant-ceiling", "50"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    assert!(!run(&["--formants-out", "npy"]).status.success());
+    assert!(
+        !run(&["--format", "npy", "--formant-overlay"])
+            .status
+            .success()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
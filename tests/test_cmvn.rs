@@ -0,0 +1,77 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, setup_test_dir};
+use spectrs::io::metadata::SCHEMA_VERSION;
+use spectrs::spectrogram::cmvn::{CmvnAccumulator, CmvnStats};
+use std::fs;
+
+#[test]
+fn test_cmvn_statistics_and_normalization() -> Result<()> {
+    let a = vec![vec![1.0, 2.0, 3.0], vec![10.0, 10.0, 10.0]];
+    let b = vec![vec![4.0, 5.0], vec![10.0, 10.0]];
+
+    // Accumulating file by file and merging partial sums give the same statistics
+    let mut sequential = CmvnAccumulator::default();
+    sequential.add(&a)?;
+    sequential.add(&b)?;
+    let mut first = CmvnAccumulator::default();
+    first.add(&a)?;
+    let mut second = CmvnAccumulator::default();
+    second.add(&b)?;
+    let merged = first.merge(second)?.merge(CmvnAccumulator::default())?;
+
+    let stats = sequential.finish()?;
+    assert_eq!(merged.finish()?, stats);
+    assert_eq!(stats.n_frames, 5);
+    assert!((stats.mean[0] - 3.0).abs() < 1e-6);
+    assert!((stats.variance[0] - 2.0).abs() < 1e-6);
+    assert!((stats.mean[1] - 10.0).abs() < 1e-6);
+    assert!(stats.variance[1].abs() < 1e-6);
+
+    // Normalized rows have zero mean and unit variance; constant rows become zero
+    let mut all = vec![
+        vec![1.0, 2.0, 3.0, 4.0, 5.0],
+        vec![10.0, 10.0, 10.0, 10.0, 10.0],
+    ];
+    stats.apply(&mut all)?;
+    let mean: f32 = all[0].iter().sum::<f32>() / 5.0;
+    let variance: f32 = all[0].iter().map(|v| v * v).sum::<f32>() / 5.0;
+    assert!(mean.abs() < 1e-5);
+    assert!((variance - 1.0).abs() < 1e-4);
+    assert!(all[1].iter().all(|v| v.abs() < 1e-6));
+
+    // Spectrograms of a different height are rejected
+    assert!(stats.apply(&mut vec![vec![0.0]; 3]).is_err());
+    let mut accumulator = CmvnAccumulator::default();
+    accumulator.add(&a)?;
+    assert!(accumulator.add(&[vec![0.0]]).is_err());
+    assert!(CmvnAccumulator::default().finish().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cmvn_stats_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("stats").join("cmvn.json");
+
+    let mut accumulator = CmvnAccumulator::default();
+    accumulator.add(&[vec![1.0, 3.0], vec![-2.0, 2.0]])?;
+    let stats = accumulator.finish()?;
+    stats.save(&path)?;
+
+    let raw: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    assert_eq!(raw["schema_version"], SCHEMA_VERSION);
+    assert_eq!(raw["n_frames"], 2);
+    assert_eq!(CmvnStats::load(&path)?, stats);
+
+    // Files from newer versions are refused
+    let mut newer = raw.clone();
+    newer["schema_version"] = serde_json::Value::from(SCHEMA_VERSION + 1);
+    fs::write(&path, serde_json::to_string(&newer)?)?;
+    assert!(CmvnStats::load(&path).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use spectrs::spectrogram::compare::{
+    DiffMode, Metric, TimeAlignment, common_shape, diff_spectrograms,
+};
+
+#[test]
+fn test_identical_spectrograms() {
+    let spec = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
+    assert!((Metric::Correlation.compute(&spec, &spec) - 1.0).abs() < 1e-12);
+    for metric in [
+        Metric::Mse,
+        Metric::Rmse,
+        Metric::NormalizedRmse,
+        Metric::RelativeError,
+        Metric::MaxAbsError,
+    ] {
+        assert_eq!(metric.compute(&spec, &spec), 0.0, "{}", metric.name());
+    }
+}
+
+#[test]
+fn test_metric_values() {
+    let reference = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+    let spec = vec![vec![1.0, 2.0], vec![3.0, 6.0]];
+
+    assert_eq!(Metric::Mse.compute(&spec, &reference), 1.0);
+    assert_eq!(Metric::Rmse.compute(&spec, &reference), 1.0);
+    assert_eq!(Metric::NormalizedRmse.compute(&spec, &reference), 0.4);
+    assert_eq!(Metric::MaxAbsError.compute(&spec, &reference), 2.0);
+    // Only the last bin differs, by half of its value
+    assert_eq!(Metric::RelativeError.compute(&spec, &reference), 0.125);
+
+    // Correlation ignores offset and scale, and flips with the sign
+    let scaled: Vec<Vec<f32>> = reference
+        .iter()
+        .map(|row| row.iter().map(|v| 10.0 - 2.0 * v).collect())
+        .collect();
+    assert!((Metric::Correlation.compute(&scaled, &reference) + 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_relative_error_ignores_silent_bins() {
+    // The near-silent bin is off by 100%, but below 1% of the maximum
+    let reference = vec![vec![100.0, 0.5]];
+    let spec = vec![vec![101.0, 1.0]];
+    assert!((Metric::RelativeError.compute(&spec, &reference) - 0.01).abs() < 1e-12);
+}
+
+#[test]
+fn test_common_shape() {
+    let a = vec![vec![1.0; 5]; 3];
+    let b = vec![vec![1.0; 4]; 4];
+    assert_eq!(common_shape(&a, &b), [3, 4]);
+
+    // Metrics only look at the common region
+    let mut longer = b.clone();
+    longer.iter_mut().for_each(|row| row.push(100.0));
+    assert_eq!(Metric::MaxAbsError.compute(&b, &longer), 0.0);
+
+    // Nothing to compare
+    assert!(Metric::Mse.compute(&a, &[]).is_nan());
+}
+
+#[test]
+fn test_diff_modes() -> spectrs::error::Result<()> {
+    let reference = vec![vec![1.0, 10.0], vec![100.0, 0.0]];
+    let spec = vec![vec![1.0, 100.0], vec![90.0, 0.0]];
+
+    let absolute = diff_spectrograms(&spec, &reference, DiffMode::Absolute, TimeAlignment::Crop)?;
+    assert_eq!(absolute, [[0.0, 90.0], [10.0, 0.0]]);
+
+    // Levels in dB, silent bins at the -100 dB floor
+    let ratio = diff_spectrograms(&spec, &reference, DiffMode::LogRatio, TimeAlignment::Crop)?;
+    assert!((ratio[0][1] - 10.0).abs() < 1e-5);
+    assert!((ratio[1][0] + 0.4576).abs() < 1e-3);
+    assert_eq!(ratio[1][1], 0.0);
+
+    // Reference over difference: 100 / 10 is 10 dB, identical bins are 100 dB or more
+    let snr = diff_spectrograms(&spec, &reference, DiffMode::Snr, TimeAlignment::Crop)?;
+    assert!((snr[1][0] - 10.0).abs() < 1e-5);
+    assert!(snr[0][0] >= 100.0 && snr[0][0].is_finite());
+    assert!((snr[0][1] + 9.5424).abs() < 1e-3);
+    Ok(())
+}
+
+#[test]
+fn test_diff_alignment() -> spectrs::error::Result<()> {
+    let long = vec![vec![1.0, 2.0, 3.0]; 2];
+    let short = vec![vec![1.0, 1.0]; 2];
+
+    let cropped = diff_spectrograms(&long, &short, DiffMode::Absolute, TimeAlignment::Crop)?;
+    assert_eq!(cropped, [[0.0, 1.0], [0.0, 1.0]]);
+    // The missing frame of the shorter one is silence
+    let padded = diff_spectrograms(&short, &long, DiffMode::Absolute, TimeAlignment::Pad)?;
+    assert_eq!(padded, [[0.0, 1.0, 3.0], [0.0, 1.0, 3.0]]);
+
+    assert!(diff_spectrograms(&long, &short[..1], DiffMode::Absolute, TimeAlignment::Pad).is_err());
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use spectrs::spectrogram::compression::{
+    Compression, compress_spectrogram, compress_spectrogram_with_amin, floor_spectrogram,
+    par_compress_spectrogram, par_compress_spectrogram_with_amin,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::SpectrogramType;
+
+fn powers() -> Vec<Vec<f32>> {
+    vec![vec![1.0, 10.0, 100.0], vec![0.0, 1e-3, 1e4]]
+}
+
+#[test]
+fn test_log_power_and_log1p() {
+    let mut spec = powers();
+    compress_spectrogram(&mut spec, Compression::LogPower, SpectrogramType::Power);
+    assert_eq!(spec[0][0], 0.0);
+    assert!((spec[0][2] - 100f32.ln()).abs() < 1e-5);
+    // Zeros are floored at 1e-10
+    assert!((spec[1][0] - 1e-10f32.ln()).abs() < 1e-3);
+
+    let mut spec = powers();
+    compress_spectrogram(&mut spec, Compression::Log1p, SpectrogramType::Power);
+    assert_eq!(spec[1][0], 0.0);
+    assert!((spec[0][1] - 11f32.ln()).abs() < 1e-5);
+}
+
+#[test]
+fn test_db_with_top_db() {
+    let mut spec = powers();
+    compress_spectrogram(
+        &mut spec,
+        Compression::Db { top_db: None },
+        SpectrogramType::Power,
+    );
+    assert!((spec[0][1] - 10.0).abs() < 1e-4);
+    assert!((spec[1][2] - 40.0).abs() < 1e-4);
+    assert!((spec[1][0] + 100.0).abs() < 1e-3);
+
+    // Clipped 50 dB below the peak of the whole spectrogram
+    let mut spec = powers();
+    compress_spectrogram(
+        &mut spec,
+        Compression::Db { top_db: Some(50.0) },
+        SpectrogramType::Power,
+    );
+    assert_eq!(spec[1][0], -10.0);
+    assert_eq!(spec[1][1], -10.0);
+    assert!((spec[0][2] - 20.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_magnitudes_are_compressed_as_powers() {
+    let mut magnitudes: Vec<Vec<f32>> = powers()
+        .iter()
+        .map(|row| row.iter().map(|v| v.sqrt()).collect())
+        .collect();
+    let mut spec = powers();
+    let compression = Compression::Db { top_db: Some(80.0) };
+    compress_spectrogram(&mut magnitudes, compression, SpectrogramType::Magnitude);
+    compress_spectrogram(&mut spec, compression, SpectrogramType::Power);
+    for (row, expected) in magnitudes.iter().zip(&spec) {
+        for (v, e) in row.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn test_par_compress_matches_sequential() {
+    let spec: Vec<Vec<f64>> = (0..64)
+        .map(|f| (0..50).map(|t| ((f * 50 + t) as f64).powi(2)).collect())
+        .collect();
+    for compression in [
+        Compression::LogPower,
+        Compression::Db { top_db: Some(60.0) },
+        Compression::Log1p,
+    ] {
+        let mut sequential = spec.clone();
+        let mut parallel = spec.clone();
+        compress_spectrogram(&mut sequential, compression, SpectrogramType::Power);
+        par_compress_spectrogram(&mut parallel, compression, SpectrogramType::Power);
+        assert_eq!(sequential, parallel);
+    }
+}
+
+#[test]
+fn test_compression_validation() {
+    let params = SpectrogramParams::default();
+    assert!(
+        SpectrogramParams {
+            compression: Some(Compression::Db { top_db: Some(-1.0) }),
+            ..params
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(
+        SpectrogramParams {
+            compression: Some(Compression::Db { top_db: Some(80.0) }),
+            ..params
+        }
+        .validate()
+        .is_ok()
+    );
+    assert!(
+        SpectrogramParams {
+            compression: Some(Compression::Log1p),
+            compat: spectrs::spectrogram::fbank::Compat::Kaldi,
+            n_mels: Some(23),
+            ..params
+        }
+        .validate()
+        .is_err()
+    );
+}
+
+#[test]
+fn test_amin_floors_logarithms() {
+    // Silence is at -100 dB with librosa's amin, at -60 dB with 1e-6
+    let mut spec = powers();
+    compress_spectrogram(
+        &mut spec,
+        Compression::Db { top_db: None },
+        SpectrogramType::Power,
+    );
+    assert!((spec[1][0] + 100.0).abs() < 1e-4);
+
+    let mut spec = powers();
+    let compression = Compression::Db { top_db: None };
+    compress_spectrogram_with_amin(&mut spec, compression, SpectrogramType::Power, 1e-6);
+    assert!((spec[1][0] + 60.0).abs() < 1e-4);
+    assert!((spec[1][1] + 30.0).abs() < 1e-4);
+
+    let mut parallel = powers();
+    par_compress_spectrogram_with_amin(&mut parallel, compression, SpectrogramType::Power, 1e-6);
+    assert_eq!(parallel, spec);
+
+    let mut spec = powers();
+    compress_spectrogram_with_amin(
+        &mut spec,
+        Compression::LogPower,
+        SpectrogramType::Power,
+        1.0,
+    );
+    assert_eq!(spec[1], vec![0.0, 0.0, 1e4f32.ln()]);
+}
+
+#[test]
+fn test_floor_spectrogram() {
+    let mut spec = vec![vec![0.0f32, f32::NAN, 2.0], vec![-1.0, 1e-12, 1e-3]];
+    floor_spectrogram(&mut spec, 1e-10);
+    assert_eq!(
+        spec,
+        vec![vec![1e-10, 1e-10, 2.0], vec![1e-10, 1e-10, 1e-3]]
+    );
+}
+
+#[test]
+fn test_mel_floor_and_amin_in_pipeline() -> spectrs::error::Result<()> {
+    // Half a second of silence before a tone
+    let sr = 16000;
+    let samples: Vec<f32> = (0..sr)
+        .map(|i| match i < sr / 2 {
+            true => 0.0,
+            false => (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sr as f32).sin(),
+        })
+        .collect();
+    let builder = || {
+        SpectrogramPipeline::builder()
+            .sample_rate(sr as u32)
+            .n_fft(512)
+            .mel(40)
+    };
+
+    let plain = builder().build()?.process_samples(&samples)?;
+    assert!(plain.iter().any(|row| row[0] == 0.0));
+    let floored = builder()
+        .mel_floor(1e-8)
+        .build()?
+        .process_samples(&samples)?;
+    assert!(floored.iter().flatten().all(|&v| v >= 1e-8));
+
+    let db = builder()
+        .db(true)
+        .amin(1e-5)
+        .build()?
+        .process_samples(&samples)?;
+    assert!(
+        db.iter()
+            .flatten()
+            .all(|&v| v >= -50.0 - 1e-3 && v.is_finite())
+    );
+    assert!(db.iter().any(|row| (row[0] + 50.0).abs() < 1e-3));
+
+    for params in [
+        SpectrogramParams {
+            amin: Some(0.0),
+            ..SpectrogramParams::default()
+        },
+        // Without bands, or with a preset flooring its own filter banks
+        SpectrogramParams {
+            mel_floor: Some(1e-10),
+            ..SpectrogramParams::default()
+        },
+        SpectrogramParams {
+            mel_floor: Some(1e-10),
+            n_mels: Some(23),
+            compat: spectrs::spectrogram::fbank::Compat::Kaldi,
+            ..SpectrogramParams::default()
+        },
+    ] {
+        assert!(params.validate().is_err(), "{:?} should be invalid", params);
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "dataset")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, ramp, setup_test_dir};
+use spectrs::io::dataset::{DatasetWriter, load_dataset};
+use spectrs::spectrogram::params::SpectrogramParams;
+
+#[test]
+fn test_dataset_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("features.parquet");
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(512)
+        .n_mels(Some(40))
+        .build()?;
+
+    // Spectrograms of different lengths, more than a row group
+    let spectrograms: Vec<Vec<Vec<f32>>> =
+        (0..70).map(|i| ramp(40, 10 + i % 7, i as f32)).collect();
+    let mut writer = DatasetWriter::create(&path, &params)?;
+    for (i, spec) in spectrograms.iter().enumerate() {
+        let label = if i % 2 == 0 { "dog" } else { "cat" };
+        writer.append(&format!("in/{}.wav", i), label, 16000, spec)?;
+    }
+    writer.finish()?;
+
+    let dataset = load_dataset(&path)?;
+    assert_eq!(dataset.params, Some(params));
+    assert_eq!(dataset.entries.len(), spectrograms.len());
+    for (i, (entry, spec)) in dataset.entries.iter().zip(&spectrograms).enumerate() {
+        assert_eq!(entry.path, format!("in/{}.wav", i));
+        assert_eq!(entry.label, if i % 2 == 0 { "dog" } else { "cat" });
+        assert_eq!(entry.sample_rate, 16000);
+        assert_eq!(&entry.spectrogram, spec);
+    }
+
+    // Ragged spectrograms are rejected
+    let mut writer = DatasetWriter::create(&test_dir.join("ragged.parquet"), &params)?;
+    assert!(
+        writer
+            .append("a.wav", ".", 16000, &[vec![0.0; 3], vec![0.0; 2]])
+            .is_err()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::denoise::NoiseProfile;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use std::f32::consts::PI;
+
+/// Deterministic white noise in [-amplitude, amplitude]
+fn white_noise(n_samples: usize, amplitude: f32, seed: u64) -> Vec<f32> {
+    let mut state = seed;
+    (0..n_samples)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            amplitude * ((state >> 40) as f32 / (1u64 << 23) as f32 - 1.0)
+        })
+        .collect()
+}
+
+#[test]
+fn test_noise_profile_from_quietest_frames() -> Result<()> {
+    // Eight frames of background, two loud frames
+    let mut spec = vec![vec![1.0f32; 10], vec![2.0f32; 10]];
+    spec[0][3] = 10.0;
+    spec[1][3] = 20.0;
+    spec[0][7] = 11.0;
+    spec[1][7] = 19.0;
+
+    let profile = NoiseProfile::from_quietest_frames(&spec, 50.0)?;
+    assert_eq!(profile.floor, vec![1.0, 2.0]);
+    // A tiny percentage still uses one frame; all frames include the loud ones
+    assert_eq!(
+        NoiseProfile::from_quietest_frames(&spec, 0.1)?.floor,
+        vec![1.0, 2.0]
+    );
+    assert_eq!(
+        NoiseProfile::from_quietest_frames(&spec, 100.0)?.floor,
+        vec![2.9, 5.5]
+    );
+
+    // Plain subtraction clears the background
+    let mut plain = spec.clone();
+    profile.subtract(&mut plain, 0.0)?;
+    assert_eq!(plain[0][0], 0.0);
+    assert_eq!(plain[0][3], 9.0);
+    assert_eq!(plain[1][7], 17.0);
+
+    // The spectral floor keeps a fraction of the original level
+    let mut floored = spec.clone();
+    profile.subtract(&mut floored, 0.1)?;
+    assert!((floored[1][0] - 0.2).abs() < 1e-6);
+    assert_eq!(floored[1][3], 18.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_noise_profile_errors() {
+    let spec = vec![vec![1.0f32; 4]; 3];
+    for percent in [0.0, -5.0, 100.5, f32::NAN] {
+        let error = NoiseProfile::from_quietest_frames(&spec, percent).unwrap_err();
+        assert!(matches!(error, SpectrsError::InvalidParams(_)));
+    }
+    let error = NoiseProfile::from_noise(&[vec![], vec![]]).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+
+    let profile = NoiseProfile::from_noise(&spec).unwrap();
+    let error = profile.subtract(&mut [vec![1.0; 4]], 0.0).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+    let error = profile.subtract(&mut spec.clone(), 1.5).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+}
+
+#[test]
+fn test_denoise_tone_in_noise() -> Result<()> {
+    let sr = 16000.0;
+    let noise = white_noise(16000, 0.05, 1);
+    let noisy_tone: Vec<f32> = white_noise(16000, 0.05, 2)
+        .iter()
+        .enumerate()
+        .map(|(i, n)| n + 0.5 * (2.0 * PI * 1000.0 * i as f32 / sr).sin())
+        .collect();
+    let stft =
+        |audio: &[f32]| compute_spectrogram(audio, 512, 256, 512, true, SpectrogramType::Power);
+
+    let mut spec = stft(&noisy_tone);
+    let before = spec.clone();
+    NoiseProfile::from_noise(&stft(&noise))?.subtract(&mut spec, 0.0)?;
+
+    let energy = |spec: &[Vec<f32>], bins: std::ops::Range<usize>| -> f32 {
+        spec[bins].iter().flatten().sum()
+    };
+    // 1 kHz is bin 32: the tone survives, the background mostly goes
+    let tone = 31..34;
+    let background = 64..256;
+    assert!(energy(&spec, tone.clone()) > 0.95 * energy(&before, tone));
+    assert!(energy(&spec, background.clone()) < 0.5 * energy(&before, background));
+    Ok(())
+}
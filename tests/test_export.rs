@@ -0,0 +1,360 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, setup_test_dir};
+use spectrs::error::SpectrsError;
+use spectrs::features::onset::Onset;
+use spectrs::features::pitch::PitchTrack;
+use spectrs::io::atomic::{partial_path, write_atomically};
+use spectrs::io::audio::{WavFormat, write_audio_file};
+use spectrs::io::export::{
+    CsvOptions, NpyFrameWriter, load_spectrogram, load_spectrogram_csv, load_spectrogram_json,
+    load_spectrogram_npy, read_spectrogram_json, read_spectrogram_npy, save_onsets_csv,
+    save_pitch_json, save_spectrogram_csv, save_spectrogram_json, save_spectrogram_npy,
+    write_spectrogram_json, write_spectrogram_npy,
+};
+use std::fs;
+
+#[test]
+fn test_csv_default_format() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let csv_path = test_dir.join("spec.csv");
+
+    let spec = vec![vec![0.5, 1.25], vec![2.0, 0.125]];
+    save_spectrogram_csv(&spec, &csv_path, &CsvOptions::default())?;
+
+    let content = fs::read_to_string(&csv_path)?;
+    assert_eq!(content, "0.5,1.25\n2,0.125\n");
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_csv_european_locale() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let csv_path = test_dir.join("spec.csv");
+
+    let options = CsvOptions {
+        delimiter: ';',
+        decimal: ',',
+        precision: Some(3),
+    };
+    let spec = vec![vec![0.5, 1.23456, -2.0]];
+    save_spectrogram_csv(&spec, &csv_path, &options)?;
+
+    let content = fs::read_to_string(&csv_path)?;
+    assert_eq!(content, "0,500;1,235;-2,000\n");
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_csv_precision() {
+    let options = CsvOptions {
+        precision: Some(0),
+        ..CsvOptions::default()
+    };
+    assert_eq!(options.format_value(2.6), "3");
+
+    let options = CsvOptions {
+        precision: Some(6),
+        ..CsvOptions::default()
+    };
+    assert_eq!(options.format_value(0.1), "0.100000");
+}
+
+#[test]
+fn test_csv_invalid_options() {
+    // Delimiter and decimal separator must differ
+    let options = CsvOptions {
+        delimiter: ',',
+        decimal: ',',
+        precision: None,
+    };
+    assert!(options.validate().is_err());
+
+    // Digits and minus signs would make the values ambiguous
+    let options = CsvOptions {
+        delimiter: '1',
+        ..CsvOptions::default()
+    };
+    assert!(options.validate().is_err());
+    let options = CsvOptions {
+        decimal: '-',
+        ..CsvOptions::default()
+    };
+    assert!(options.validate().is_err());
+
+    assert!(CsvOptions::default().validate().is_ok());
+}
+
+#[test]
+fn test_npy_layout() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let npy_path = test_dir.join("spec.npy");
+
+    let spec = vec![vec![0.5, 1.25, -3.0], vec![2.0, 0.125, 7.5]];
+    save_spectrogram_npy(&spec, &npy_path)?;
+    let bytes = fs::read(&npy_path)?;
+
+    // Magic, version 1.0, then a header padded so the data is 64-byte aligned
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0);
+    let header = std::str::from_utf8(&bytes[10..10 + header_len])?;
+    assert!(header.contains("'descr': '<f4'"));
+    assert!(header.contains("'fortran_order': False"));
+    assert!(header.contains("'shape': (2, 3)"));
+    assert!(header.ends_with('\n'));
+
+    // Row-major float32 data
+    let values: Vec<f32> = bytes[10 + header_len..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    assert_eq!(values, spec.concat());
+
+    // Writing to memory produces the same bytes
+    let mut buffer = Vec::new();
+    write_spectrogram_npy(&spec, &mut buffer)?;
+    assert_eq!(buffer, bytes);
+
+    // Ragged rows are rejected
+    assert!(write_spectrogram_npy(&[vec![1.0], vec![]], Vec::new()).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_npy_float64() -> Result<()> {
+    let spec = vec![vec![0.1f64, 1.0 / 3.0], vec![-2.5, 1e-300]];
+
+    let mut bytes = Vec::new();
+    write_spectrogram_npy(&spec, &mut bytes)?;
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let header = std::str::from_utf8(&bytes[10..10 + header_len])?;
+    assert!(header.contains("'descr': '<f8'"));
+    let values: Vec<f64> = bytes[10 + header_len..]
+        .chunks_exact(8)
+        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(values, spec.concat());
+
+    // Reading rounds to single precision
+    let loaded = read_spectrogram_npy(&bytes[..])?;
+    assert_eq!(loaded, vec![vec![0.1f32, 1.0 / 3.0], vec![-2.5, 0.0]]);
+
+    Ok(())
+}
+
+#[test]
+fn test_npy_frame_writer() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let npy_path = test_dir.join("live.npy");
+
+    let mut writer = NpyFrameWriter::create(&npy_path, 2)?;
+    writer.push(&[1.0, 2.0])?;
+    writer.push(&[3.0, 4.0])?;
+    writer.flush()?;
+    assert!(writer.push(&[1.0]).is_err());
+
+    // Valid after every flush: column-major (2, n_frames) array
+    let read_shape = || -> Result<(String, Vec<f32>)> {
+        let bytes = fs::read(&npy_path)?;
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        let header = std::str::from_utf8(&bytes[10..10 + header_len])?.to_string();
+        let values = bytes[10 + header_len..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok((header, values))
+    };
+    let (header, values) = read_shape()?;
+    assert!(header.contains("'fortran_order': True"));
+    assert!(header.contains("'shape': (2, 2)"));
+    assert_eq!(values, vec![1.0, 2.0, 3.0, 4.0]);
+
+    // Appending more frames rewrites the header in place
+    writer.push(&[5.0, 6.0])?;
+    writer.flush()?;
+    let (header, values) = read_shape()?;
+    assert!(header.contains("'shape': (2, 3)"));
+    assert_eq!(values.len(), 6);
+    assert_eq!(writer.n_frames(), 3);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_load_npy() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let spec = vec![vec![0.5, 1.25, -3.0], vec![2.0, 0.125, 7.5]];
+
+    // Row-major files
+    let npy_path = test_dir.join("spec.npy");
+    save_spectrogram_npy(&spec, &npy_path)?;
+    assert_eq!(load_spectrogram_npy(&npy_path)?, spec);
+
+    // Column-major growing files
+    let live_path = test_dir.join("live.npy");
+    let mut writer = NpyFrameWriter::create(&live_path, 2)?;
+    for (&low, &high) in spec[0].iter().zip(&spec[1]) {
+        writer.push(&[low, high])?;
+    }
+    writer.flush()?;
+    assert_eq!(load_spectrogram_npy(&live_path)?, spec);
+
+    // Other data is rejected with the matching error kind
+    let error = read_spectrogram_npy(&b"not a numpy array"[..]).unwrap_err();
+    assert!(matches!(error, SpectrsError::UnsupportedFormat(_)));
+    let mut truncated = Vec::new();
+    write_spectrogram_npy(&spec, &mut truncated)?;
+    truncated.truncate(truncated.len() - 4);
+    let error = read_spectrogram_npy(&truncated[..]).unwrap_err();
+    assert!(matches!(error, SpectrsError::Io { .. }));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_load_csv_and_json() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let spec = vec![vec![0.5, 1.25, -3.0], vec![2.0, 0.125, 7.5]];
+
+    // CSV written with European-locale options reads back with the same options
+    let options = CsvOptions {
+        delimiter: ';',
+        decimal: ',',
+        precision: None,
+    };
+    let csv_path = test_dir.join("spec.csv");
+    save_spectrogram_csv(&spec, &csv_path, &options)?;
+    assert_eq!(load_spectrogram_csv(&csv_path, &options)?, spec);
+    assert!(load_spectrogram_csv(&csv_path, &CsvOptions::default()).is_err());
+
+    // JSON: object with `data` (comparison scripts) or bare rows
+    let expected = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+    let object = br#"{"data": [[1.0, 2.0], [3, 4]], "shape": [2, 2]}"#;
+    assert_eq!(read_spectrogram_json(&object[..])?, expected);
+    assert_eq!(read_spectrogram_json(&b"[[1, 2], [3, 4]]"[..])?, expected);
+    let error = read_spectrogram_json(&b"[[1, 2], [3]]"[..]).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidData { .. }));
+
+    // Format chosen by extension
+    let json_path = test_dir.join("spec.json");
+    fs::write(&json_path, "[[1, 2], [3, 4]]")?;
+    assert_eq!(load_spectrogram(&json_path, &options)?, expected);
+    assert_eq!(load_spectrogram(&csv_path, &options)?, spec);
+    let error = load_spectrogram(&test_dir.join("spec.wav"), &options).unwrap_err();
+    assert!(matches!(error, SpectrsError::UnsupportedFormat(_)));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_json_layout() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let spec = vec![vec![0.5f32, 1.25, -3.0], vec![2.0, 0.1, 7.5]];
+
+    let mut bytes = Vec::new();
+    write_spectrogram_json(&spec, &mut bytes)?;
+    assert_eq!(
+        String::from_utf8(bytes)?,
+        r#"{"shape":[2,3],"data":[[0.5,1.25,-3.0],[2.0,0.1,7.5]]}"#
+    );
+
+    // Values read back exactly, including f64 values representable in f32
+    let path = test_dir.join("nested/spec.json");
+    save_spectrogram_json(&spec, &path)?;
+    assert_eq!(load_spectrogram_json(&path)?, spec);
+    let spec_f64: Vec<Vec<f64>> = spec
+        .iter()
+        .map(|row| row.iter().map(|&v| v as f64).collect())
+        .collect();
+    save_spectrogram_json(&spec_f64, &path)?;
+    assert_eq!(load_spectrogram(&path, &CsvOptions::default())?, spec);
+
+    let error = write_spectrogram_json(&[vec![1.0f32], vec![]], Vec::new()).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_atomic_outputs() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let npy_path = test_dir.join("out").join("spec.npy");
+
+    // Temporary files are hidden next to the output, and unique
+    let partial = partial_path(&npy_path);
+    assert_eq!(partial.parent(), npy_path.parent());
+    let name = partial.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with(".spec.npy.") && name.ends_with(".partial"));
+    assert_ne!(partial_path(&npy_path), partial);
+
+    let spec = vec![vec![1.0f32, 2.0], vec![3.0, 4.0]];
+    save_spectrogram_npy(&spec, &npy_path)?;
+    save_spectrogram_csv(
+        &spec,
+        &test_dir.join("out").join("spec.csv"),
+        &Default::default(),
+    )?;
+    let onsets = [Onset {
+        frame: 1,
+        time: 0.5,
+        strength: 1.0,
+    }];
+    save_onsets_csv(
+        &onsets,
+        &test_dir.join("out").join("onsets.csv"),
+        &Default::default(),
+    )?;
+    let pitch = PitchTrack {
+        times: vec![0.0, 0.5],
+        f0: vec![None, Some(220.0)],
+    };
+    save_pitch_json(&pitch, &test_dir.join("out").join("pitch.json"))?;
+    write_audio_file(
+        &test_dir.join("out").join("clip.wav"),
+        &[0.0, 0.5, -0.5],
+        8000,
+        WavFormat::Int16,
+    )?;
+
+    // A failed save keeps the previous output
+    let ragged = vec![vec![1.0f32, 2.0], vec![3.0]];
+    assert!(save_spectrogram_npy(&ragged, &npy_path).is_err());
+    assert_eq!(load_spectrogram_npy(&npy_path)?, spec);
+
+    // A failed write removes its temporary file
+    let result: spectrs::error::Result<()> = write_atomically(&npy_path, |partial| {
+        fs::write(partial, b"truncated").expect("temporary file written");
+        Err(SpectrsError::InvalidParams("stopped".to_string()))
+    });
+    assert!(result.is_err());
+    assert_eq!(load_spectrogram_npy(&npy_path)?, spec);
+    let mut names: Vec<String> = fs::read_dir(test_dir.join("out"))?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_>>()?;
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "clip.wav",
+            "onsets.csv",
+            "pitch.json",
+            "spec.csv",
+            "spec.npy"
+        ]
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use anyhow::Result;
+use common::sine;
+use spectrs::spectrogram::fbank::{
+    Compat, FbankOptions, FbankWindow, compute_fbank, fbank_mel_banks, par_compute_fbank,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+
+const SR: u32 = 16000;
+
+#[test]
+fn test_kaldi_frame_layout() {
+    let options = FbankOptions::kaldi(SR);
+    assert_eq!(options.frame_length, 400);
+    assert_eq!(options.frame_shift, 160);
+    assert_eq!(options.padded_frame_length(), 512);
+
+    // Snipped edges keep only complete frames, otherwise frames depend on the shift only
+    assert_eq!(options.num_frames(16000), 98);
+    assert_eq!(options.num_frames(399), 0);
+    let unsnipped = FbankOptions {
+        snip_edges: false,
+        ..options
+    };
+    assert_eq!(unsnipped.num_frames(16000), 100);
+    assert_eq!(unsnipped.num_frames(399), 2);
+}
+
+#[test]
+fn test_mel_banks_are_triangles_on_the_mel_axis() {
+    let options = FbankOptions::kaldi(SR);
+    let banks = fbank_mel_banks(&options);
+    assert_eq!(banks.len(), 23);
+
+    for filter in &banks {
+        assert_eq!(filter.len(), 257);
+        let peak = filter.iter().copied().fold(0.0f32, f32::max);
+        assert!(peak > 0.5 && peak <= 1.0);
+        // Never covers the Nyquist bin
+        assert_eq!(filter[256], 0.0);
+    }
+
+    // Adjacent triangles sum to one between the first and last centers
+    for bin in 20..200 {
+        let total: f32 = banks.iter().map(|filter| filter[bin]).sum();
+        assert!((total - 1.0).abs() < 1e-4, "bin {}: {}", bin, total);
+    }
+}
+
+#[test]
+fn test_fbank_tone_and_floor() -> Result<()> {
+    let options = FbankOptions::kaldi(SR);
+
+    // A 1 kHz tone peaks in the filter centered closest to 1 kHz
+    let fbank = compute_fbank(&sine(1000.0, 0.5, 16000, SR), &options)?;
+    assert_eq!(fbank.len(), 23);
+    assert_eq!(fbank[0].len(), 98);
+    let loudest = (0..23)
+        .max_by(|&a, &b| fbank[a][50].total_cmp(&fbank[b][50]))
+        .unwrap();
+    let banks = fbank_mel_banks(&options);
+    let tone_bin = (1000.0 / (SR as f32 / 512.0)).round() as usize;
+    assert!(banks[loudest][tone_bin] > 0.5);
+
+    // DC is removed before the FFT, so a constant signal hits the log floor everywhere
+    let constant = compute_fbank(&vec![0.25; 4000], &options)?;
+    let floor = f32::EPSILON.ln();
+    assert!(constant.iter().flatten().all(|&v| v == floor));
+
+    // HTK floors magnitudes at 1, i.e. log energies are never negative
+    let htk = compute_fbank(&sine(1000.0, 0.5, 16000, SR), &FbankOptions::htk(SR))?;
+    assert_eq!(htk.len(), 20);
+    assert!(htk.iter().flatten().all(|&v| v >= 0.0));
+
+    Ok(())
+}
+
+#[test]
+fn test_fbank_parallel_and_dither_are_deterministic() -> Result<()> {
+    let audio = sine(440.0, 0.5, 8000, SR);
+    let options = FbankOptions {
+        snip_edges: false,
+        window: FbankWindow::Hamming,
+        ..FbankOptions::kaldi(SR)
+    };
+    assert_eq!(
+        compute_fbank(&audio, &options)?,
+        par_compute_fbank(&audio, &options)?
+    );
+
+    let dithered = FbankOptions {
+        dither: 1.0,
+        ..options
+    };
+    let a = compute_fbank(&audio, &dithered)?;
+    assert_eq!(a, par_compute_fbank(&audio, &dithered)?);
+    assert_ne!(a, compute_fbank(&audio, &options)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_fbank_options_from_params() -> Result<()> {
+    let librosa = SpectrogramParams::default();
+    assert!(FbankOptions::from_params(&librosa, SR).is_none());
+
+    let params = SpectrogramParams::builder()
+        .compat(Compat::Kaldi)
+        .n_mels(Some(80))
+        .f_max(Some(7600.0))
+        .build()?;
+    let options = FbankOptions::from_params(&params, SR).unwrap();
+    assert_eq!(options.num_mel_bins, 80);
+    assert_eq!(options.high_freq_hz(), 7600.0);
+    assert_eq!(options.low_freq, 20.0);
+
+    // Invalid ranges are reported instead of producing empty filters
+    let too_high = FbankOptions {
+        high_freq: 9000.0,
+        ..options
+    };
+    assert!(compute_fbank(&sine(440.0, 0.5, 1000, SR), &too_high).is_err());
+    assert!(
+        SpectrogramParams::builder()
+            .compat(Compat::Htk)
+            .dither(-1.0)
+            .build()
+            .is_err()
+    );
+
+    Ok(())
+}
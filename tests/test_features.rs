@@ -0,0 +1,210 @@
+mod common;
+
+use common::{noise, sine};
+use spectrs::features::spectral::{
+    fft_frequencies, rms_from_spectrogram, spectral_bandwidth, spectral_centroid,
+    spectral_flatness, spectral_rolloff,
+};
+use spectrs::features::temporal::{ZeroCrossingCounter, zero_crossing_rate};
+use spectrs::features::tonal::{N_CHROMA, N_TONNETZ, chroma, chroma_filter_bank, tonnetz};
+use spectrs::features::{FeatureSummary, Stats};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+const SR: u32 = 16000;
+const N_FFT: usize = 1024;
+
+fn magnitude(audio: &[f32]) -> Vec<Vec<f32>> {
+    compute_spectrogram(audio, N_FFT, 256, N_FFT, true, SpectrogramType::Magnitude)
+}
+
+#[test]
+fn test_fft_frequencies() {
+    let freqs = fft_frequencies(SR, N_FFT);
+    assert_eq!(freqs.len(), N_FFT / 2 + 1);
+    assert_eq!(freqs[0], 0.0);
+    assert_eq!(*freqs.last().unwrap(), 8000.0);
+}
+
+#[test]
+fn test_centroid_bandwidth_rolloff_pure_tone() {
+    let spec = magnitude(&sine(1000.0, 1.0, SR as usize, SR));
+
+    let centroid = Stats::from_values(&spectral_centroid(&spec, SR, N_FFT));
+    assert!((centroid.mean - 1000.0).abs() < 20.0, "{:?}", centroid);
+
+    // A pure tone only leaks into the window sidelobes (magnitudes decay slowly)
+    let bandwidth = Stats::from_values(&spectral_bandwidth(&spec, SR, N_FFT));
+    assert!(bandwidth.mean < 200.0, "{:?}", bandwidth);
+
+    let rolloff = Stats::from_values(&spectral_rolloff(&spec, SR, N_FFT, 0.85));
+    assert!((rolloff.mean - 1000.0).abs() < 50.0, "{:?}", rolloff);
+}
+
+#[test]
+fn test_flatness_tone_vs_noise() {
+    let tone = Stats::from_values(&spectral_flatness(&magnitude(&sine(
+        1000.0, 1.0, 16000, SR,
+    ))));
+    let white = Stats::from_values(&spectral_flatness(&magnitude(&noise(16000))));
+
+    assert!(tone.mean < 0.01, "{:?}", tone);
+    assert!(white.mean > 0.3, "{:?}", white);
+    assert!(white.mean <= 1.0);
+}
+
+#[test]
+fn test_rms_parseval() {
+    // Non-centered frames without overlap so each frame maps to a known slice of audio
+    let audio = noise(4096);
+    let spec = compute_spectrogram(&audio, 512, 512, 512, false, SpectrogramType::Magnitude);
+    let rms = rms_from_spectrogram(&spec, 512);
+
+    let window: Vec<f32> = (0..512)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / 511.0).cos()))
+        .collect();
+    for (t, &value) in rms.iter().enumerate() {
+        let frame = &audio[t * 512..(t + 1) * 512];
+        let expected = (frame
+            .iter()
+            .zip(&window)
+            .map(|(x, w)| (x * w).powi(2))
+            .sum::<f32>()
+            / 512.0)
+            .sqrt();
+        assert!((value - expected).abs() < 1e-4, "{} vs {}", value, expected);
+    }
+}
+
+#[test]
+fn test_silence() {
+    let spec = magnitude(&vec![0.0; 4096]);
+    assert!(
+        spectral_centroid(&spec, SR, N_FFT)
+            .iter()
+            .all(|&c| c == 0.0)
+    );
+    assert!(
+        spectral_bandwidth(&spec, SR, N_FFT)
+            .iter()
+            .all(|&b| b == 0.0)
+    );
+    assert!(rms_from_spectrogram(&spec, N_FFT).iter().all(|&r| r == 0.0));
+}
+
+#[test]
+fn test_zero_crossing_rate() {
+    // 440 Hz crosses zero 880 times per second
+    let audio = sine(440.0, 1.0, SR as usize, SR);
+    let expected = 880.0 / SR as f32;
+
+    let framewise = Stats::from_values(&zero_crossing_rate(&audio, 2048, 512));
+    assert!((framewise.mean - expected).abs() < 0.005, "{:?}", framewise);
+
+    // Chunked counting gives the same result as counting in one go
+    let mut whole = ZeroCrossingCounter::default();
+    whole.push(&audio);
+    let mut chunked = ZeroCrossingCounter::default();
+    for chunk in audio.chunks(333) {
+        chunked.push(chunk);
+    }
+    assert_eq!(whole.crossings(), chunked.crossings());
+    assert!((whole.rate() - expected).abs() < 0.001);
+}
+
+#[test]
+fn test_stats_and_summary() {
+    let stats = Stats::from_values(&[1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(stats.mean, 2.5);
+    assert!((stats.std - 1.118034).abs() < 1e-5);
+    assert_eq!(Stats::from_values(&[]), Stats::default());
+
+    let summary = FeatureSummary::from_spectrogram(
+        &magnitude(&sine(2000.0, 1.0, 8000, SR)),
+        SR,
+        N_FFT,
+        0.5,
+        0.25,
+    );
+    let values = summary.values();
+    assert_eq!(values.len(), FeatureSummary::COLUMNS.len());
+    assert_eq!(values[0], 0.5);
+    assert_eq!(values[3], 0.25);
+    assert!((summary.centroid.mean - 2000.0).abs() < 30.0);
+}
+
+/// Pitch class (0 = C) of the strongest chroma bin of every frame
+fn strongest_pitch_classes(chromagram: &[Vec<f32>]) -> Vec<usize> {
+    (0..chromagram[0].len())
+        .map(|t| {
+            (0..N_CHROMA)
+                .max_by(|&a, &b| chromagram[a][t].total_cmp(&chromagram[b][t]))
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_chroma() {
+    let filters = chroma_filter_bank(SR, N_FFT);
+    assert_eq!(filters.len(), N_CHROMA);
+    assert!(filters.iter().all(|row| row.len() == N_FFT / 2 + 1));
+
+    // A4 and C5 in the pitch classes A (9) and C (0), the strongest scaled to 1
+    for (freq, pitch_class) in [(440.0, 9), (523.25, 0)] {
+        let spec = magnitude(&sine(freq, 1.0, SR as usize, SR));
+        let chromagram = chroma(&spec, SpectrogramType::Magnitude, SR, N_FFT);
+        assert_eq!(chromagram.len(), N_CHROMA);
+        assert_eq!(chromagram[0].len(), spec[0].len());
+        for (t, &class) in strongest_pitch_classes(&chromagram).iter().enumerate() {
+            assert_eq!(class, pitch_class);
+            assert!((chromagram[class][t] - 1.0).abs() < 1e-6);
+        }
+
+        // Powers are squared magnitudes
+        let power: Vec<Vec<f32>> = spec
+            .iter()
+            .map(|row| row.iter().map(|v| v * v).collect())
+            .collect();
+        let from_power = chroma(&power, SpectrogramType::Power, SR, N_FFT);
+        for (a, b) in chromagram.iter().flatten().zip(from_power.iter().flatten()) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    // Silent frames stay 0
+    let silence = vec![vec![0.0f32; 5]; N_FFT / 2 + 1];
+    let chromagram = chroma(&silence, SpectrogramType::Power, SR, N_FFT);
+    assert!(chromagram.iter().flatten().all(|&v| v == 0.0));
+}
+
+#[test]
+fn test_tonnetz() {
+    let single = |class: usize| {
+        let mut chromagram = vec![vec![0.0f32; 1]; N_CHROMA];
+        chromagram[class][0] = 0.5;
+        tonnetz(&chromagram)
+    };
+    // C at the angle 0 of every circle, G a twelfth of a turn away on the circle of fifths
+    let c = single(0);
+    assert_eq!(c.len(), N_TONNETZ);
+    let expected = [0.0, 1.0, 0.0, 1.0, 0.0, 0.5];
+    for (row, expected) in c.iter().zip(expected) {
+        assert!((row[0] - expected).abs() < 1e-6, "{:?}", c);
+    }
+    let g = single(7);
+    assert!((g[0][0] - 0.5).abs() < 1e-6);
+    assert!((g[1][0] - 3f32.sqrt() / 2.0).abs() < 1e-6);
+
+    // Every frame is a distribution over the pitch classes: its scale does not matter
+    let mut chord = vec![vec![0.0f32; 2]; N_CHROMA];
+    for class in [0, 4, 7] {
+        chord[class] = vec![1.0, 3.0];
+    }
+    for row in &tonnetz(&chord) {
+        assert!((row[0] - row[1]).abs() < 1e-6);
+    }
+
+    // Silent frames stay 0
+    let silence = vec![vec![0.0f32; 3]; N_CHROMA];
+    assert!(tonnetz(&silence).iter().flatten().all(|&v| v == 0.0));
+}
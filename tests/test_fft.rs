@@ -0,0 +1,250 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::fft::{Complex, FftBackend, FftBackendType, ForwardFft, RustFftBackend};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::{
+    Float, SpectrogramType, compute_spectrogram_with_fft, compute_spectrogram_with_window,
+    create_hann_window, par_compute_spectrogram_with_fft,
+};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+fn signal(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|t| (t as f32 * 0.21).sin() + 0.3 * (t as f32 * 1.7).cos())
+        .collect()
+}
+
+fn assert_close<T: Float>(a: &[Vec<T>], b: &[Vec<T>], tolerance: f64) {
+    assert_eq!(a.len(), b.len());
+    let peak = a
+        .iter()
+        .flatten()
+        .map(|v| v.to_f64().unwrap())
+        .fold(0.0, f64::max);
+    for (row_a, row_b) in a.iter().zip(b) {
+        assert_eq!(row_a.len(), row_b.len());
+        for (x, y) in row_a.iter().zip(row_b) {
+            let (x, y) = (x.to_f64().unwrap(), y.to_f64().unwrap());
+            assert!((x - y).abs() <= tolerance * peak, "{} != {}", x, y);
+        }
+    }
+}
+
+/// Direct O(n²) DFT, a backend of its own
+struct NaiveDft;
+
+struct Dft(usize);
+
+impl<T: Float> ForwardFft<T> for Dft {
+    fn len(&self) -> usize {
+        self.0
+    }
+
+    fn process(&self, buffer: &mut [Complex<T>]) {
+        let n = self.0;
+        for chunk in buffer.chunks_exact_mut(n) {
+            let input = chunk.to_vec();
+            for (k, out) in chunk.iter_mut().enumerate() {
+                let mut sum = Complex::new(0.0f64, 0.0);
+                for (t, x) in input.iter().enumerate() {
+                    let angle = -2.0 * PI * (k * t % n) as f64 / n as f64;
+                    let x = Complex::new(x.re.to_f64().unwrap(), x.im.to_f64().unwrap());
+                    sum += x * Complex::from_polar(1.0, angle);
+                }
+                out.re = T::from_f64(sum.re).unwrap();
+                out.im = T::from_f64(sum.im).unwrap();
+            }
+        }
+    }
+}
+
+impl FftBackend for NaiveDft {
+    fn name(&self) -> &'static str {
+        "naive"
+    }
+
+    fn plan_f32(&self, n: usize) -> spectrs::error::Result<Arc<dyn ForwardFft<f32>>> {
+        Ok(Arc::new(Dft(n)))
+    }
+
+    fn plan_f64(&self, n: usize) -> spectrs::error::Result<Arc<dyn ForwardFft<f64>>> {
+        Ok(Arc::new(Dft(n)))
+    }
+}
+
+#[test]
+fn test_rustfft_backend_matches_default_stft() -> Result<()> {
+    let samples = signal(4000);
+    let window = create_hann_window(200, true);
+    let fft = f32::plan_fft(&*FftBackendType::RustFft.backend()?, 256)?;
+    assert_eq!(fft.len(), 256);
+
+    let expected =
+        compute_spectrogram_with_window(&samples, 256, 80, &window, true, SpectrogramType::Power);
+    let spec =
+        compute_spectrogram_with_fft(&samples, &*fft, 80, &window, true, SpectrogramType::Power);
+    let par_spec = par_compute_spectrogram_with_fft(
+        &samples,
+        &*fft,
+        80,
+        &window,
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(spec, expected);
+    assert_eq!(par_spec, expected);
+    assert_eq!(RustFftBackend.name(), "rustfft");
+    Ok(())
+}
+
+#[test]
+fn test_custom_fft_backend() -> Result<()> {
+    // Any transform implementing the traits computes the STFT, including odd sizes
+    let samples = signal(1500);
+    for n_fft in [64, 100] {
+        let window = create_hann_window(n_fft, false);
+        let expected = compute_spectrogram_with_window(
+            &samples,
+            n_fft,
+            50,
+            &window,
+            false,
+            SpectrogramType::Magnitude,
+        );
+        let fft = f32::plan_fft(&NaiveDft, n_fft)?;
+        let spec = par_compute_spectrogram_with_fft(
+            &samples,
+            &*fft,
+            50,
+            &window,
+            false,
+            SpectrogramType::Magnitude,
+        );
+        assert_close(&spec, &expected, 1e-5);
+
+        let samples: Vec<f64> = samples.iter().map(|&s| s as f64).collect();
+        let window = create_hann_window(n_fft, false);
+        let expected = compute_spectrogram_with_window(
+            &samples,
+            n_fft,
+            50,
+            &window,
+            false,
+            SpectrogramType::Magnitude,
+        );
+        let fft = f64::plan_fft(&NaiveDft, n_fft)?;
+        let spec = compute_spectrogram_with_fft(
+            &samples,
+            &*fft,
+            50,
+            &window,
+            false,
+            SpectrogramType::Magnitude,
+        );
+        assert_close(&spec, &expected, 1e-10);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_params_fft_backend() -> Result<()> {
+    // The default backend is RustFFT, also when absent from serialized parameters
+    let params = SpectrogramParams::builder().build()?;
+    assert_eq!(params.fft_backend, FftBackendType::RustFft);
+    let json = serde_json::to_value(params)?;
+    assert_eq!(json["fft_backend"], "rustfft");
+    let mut object = json.as_object().unwrap().clone();
+    object.remove("fft_backend");
+    let parsed: SpectrogramParams = serde_json::from_value(object.into())?;
+    assert_eq!(parsed, params);
+
+    // The compatibility presets compute their own FFTs
+    let result = SpectrogramParams::builder()
+        .compat(Compat::Kaldi)
+        .fft_backend(FftBackendType::Fftw)
+        .build();
+    assert!(matches!(result, Err(SpectrsError::InvalidParams(_))));
+    Ok(())
+}
+
+#[test]
+fn test_unavailable_fft_backends() {
+    #[cfg(not(feature = "fftw"))]
+    {
+        assert!(matches!(
+            FftBackendType::Fftw.backend(),
+            Err(SpectrsError::UnsupportedFormat(_))
+        ));
+        let result = SpectrogramPipeline::builder()
+            .n_fft(512)
+            .fft_backend(FftBackendType::Fftw)
+            .build();
+        assert!(matches!(result, Err(SpectrsError::UnsupportedFormat(_))));
+    }
+    #[cfg(not(all(feature = "accelerate", target_os = "macos")))]
+    assert!(matches!(
+        FftBackendType::Accelerate.backend(),
+        Err(SpectrsError::UnsupportedFormat(_))
+    ));
+}
+
+#[cfg(feature = "fftw")]
+#[test]
+fn test_fftw_backend() -> Result<()> {
+    let samples = signal(20000);
+    let window = create_hann_window(400, true);
+    let backend = FftBackendType::Fftw.backend()?;
+    assert_eq!(backend.name(), "fftw");
+    for n_fft in [400, 512] {
+        let expected = compute_spectrogram_with_window(
+            &samples,
+            n_fft,
+            160,
+            &window,
+            true,
+            SpectrogramType::Power,
+        );
+        let fft = f32::plan_fft(&*backend, n_fft)?;
+        let spec = compute_spectrogram_with_fft(
+            &samples,
+            &*fft,
+            160,
+            &window,
+            true,
+            SpectrogramType::Power,
+        );
+        let par_spec = par_compute_spectrogram_with_fft(
+            &samples,
+            &*fft,
+            160,
+            &window,
+            true,
+            SpectrogramType::Power,
+        );
+        assert_close(&spec, &expected, 1e-5);
+        assert_eq!(par_spec, spec);
+    }
+
+    // Through the pipeline, in double precision
+    let expected = SpectrogramPipeline::builder()
+        .sample_rate(16000)
+        .n_fft(512)
+        .mel(40)
+        .build()?;
+    let pipeline = SpectrogramPipeline::builder()
+        .sample_rate(16000)
+        .n_fft(512)
+        .mel(40)
+        .fft_backend(FftBackendType::Fftw)
+        .build()?;
+    let samples: Vec<f64> = samples.iter().map(|&s| s as f64).collect();
+    assert_close(
+        &pipeline.spectrogram(&samples, 16000),
+        &expected.spectrogram(&samples, 16000),
+        1e-12,
+    );
+    Ok(())
+}
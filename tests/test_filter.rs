@@ -0,0 +1,97 @@
+mod common;
+
+use anyhow::Result;
+use common::tone;
+use spectrs::filter::{AudioFilter, Biquad};
+
+/// Gain (dB) of the filter on a sine, measured over the last half second once the narrow
+/// notches have settled
+fn gain_db(filter: &AudioFilter, hz: f32, sr: u32) -> Result<f32> {
+    let input = tone(hz, 1.0, 2.0, sr);
+    let mut output = input.clone();
+    filter.apply(&mut output, sr)?;
+    let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+    let settled = input.len() * 3 / 4;
+    Ok(20.0 * (rms(&output[settled..]) / rms(&input[settled..])).log10())
+}
+
+#[test]
+fn test_notch_removes_hum() -> Result<()> {
+    let filter = AudioFilter {
+        bandpass: None,
+        notches: vec![50.0],
+    };
+    assert!(gain_db(&filter, 50.0, 16000)? < -40.0);
+    assert!(gain_db(&filter, 1000.0, 16000)?.abs() < 0.1);
+    Ok(())
+}
+
+#[test]
+fn test_bandpass() -> Result<()> {
+    let filter = AudioFilter {
+        bandpass: Some((300.0, 3400.0)),
+        notches: Vec::new(),
+    };
+    assert!(gain_db(&filter, 1000.0, 16000)?.abs() < 0.5);
+    // 4th-order edges: -3 dB at the cut-offs, 24 dB per octave beyond them
+    assert!((gain_db(&filter, 300.0, 16000)? + 3.0).abs() < 0.5);
+    assert!(gain_db(&filter, 50.0, 16000)? < -50.0);
+    assert!(gain_db(&filter, 7000.0, 16000)? < -20.0);
+    assert!(AudioFilter::default().is_empty());
+    assert!(!filter.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_biquad_in_chunks() -> Result<()> {
+    let input = tone(440.0, 1.0, 2.0, 8000);
+    let mut whole = input.clone();
+    Biquad::highpass(8000, 1000.0, 0.7).process(&mut whole);
+
+    // State carries over from one chunk to the next
+    let mut biquad = Biquad::highpass(8000, 1000.0, 0.7);
+    let mut chunked = input;
+    for chunk in chunked.chunks_mut(100) {
+        biquad.process(chunk);
+    }
+    assert_eq!(chunked, whole);
+    Ok(())
+}
+
+#[test]
+fn test_filter_validation() {
+    let bandpass = |low, high| AudioFilter {
+        bandpass: Some((low, high)),
+        notches: Vec::new(),
+    };
+    assert!(
+        bandpass(300.0, 3400.0)
+            .validate_for_sample_rate(8000)
+            .is_ok()
+    );
+    assert!(
+        bandpass(300.0, 9000.0)
+            .validate_for_sample_rate(16000)
+            .is_err()
+    );
+    assert!(
+        bandpass(3400.0, 300.0)
+            .validate_for_sample_rate(16000)
+            .is_err()
+    );
+    assert!(
+        bandpass(0.0, 300.0)
+            .validate_for_sample_rate(16000)
+            .is_err()
+    );
+
+    let notch = |hz| AudioFilter {
+        bandpass: None,
+        notches: vec![50.0, hz],
+    };
+    assert!(notch(60.0).validate_for_sample_rate(16000).is_ok());
+    assert!(notch(0.0).validate_for_sample_rate(16000).is_err());
+    assert!(notch(8000.0).biquads(16000).is_err());
+    let mut samples = vec![0.5; 10];
+    assert!(notch(8000.0).apply(&mut samples, 16000).is_err());
+}
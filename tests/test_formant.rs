@@ -0,0 +1,92 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::features::formant::{
+    FormantParams, TRACKED_FORMANTS, formants, par_track_formants, track_formants,
+};
+
+/// Vowel /a/ synthesized at `sr`: a 120 Hz pulse train through resonators at 700, 1220 and
+/// 2600 Hz
+fn vowel(sr: u32, seconds: f32) -> Vec<f32> {
+    let period = (sr as f32 / 120.0).round() as usize;
+    let mut audio: Vec<f32> = (0..(seconds * sr as f32) as usize)
+        .map(|i| if i % period == 0 { 1.0 } else { 0.0 })
+        .collect();
+    for (frequency, bandwidth) in [(700.0, 130.0), (1220.0, 70.0), (2600.0, 160.0)] {
+        let radius = (-std::f64::consts::PI * bandwidth / sr as f64).exp();
+        let angle = 2.0 * std::f64::consts::PI * frequency / sr as f64;
+        let (a1, a2) = (
+            (-2.0 * radius * angle.cos()) as f32,
+            (radius * radius) as f32,
+        );
+        for i in 2..audio.len() {
+            audio[i] -= a1 * audio[i - 1] + a2 * audio[i - 2];
+        }
+    }
+    audio
+}
+
+#[test]
+fn test_formants_of_vowel() -> Result<()> {
+    for sr in [16000, 44100] {
+        let params = FormantParams {
+            frame_length: (0.05 * sr as f32) as usize,
+            hop_length: sr as usize / 100,
+            ..FormantParams::default()
+        };
+        let track = track_formants(&vowel(sr, 0.5), sr, &params)?;
+        assert_eq!(track.times.len(), track.formants.len());
+        assert_eq!(track.times.len(), 51);
+        assert!((track.times[1] - 0.01).abs() < 1e-6);
+        // Frames away from the edges find the three resonances
+        for frame in &track.formants[5..45] {
+            assert!(frame.len() >= TRACKED_FORMANTS, "{} Hz: {:?}", sr, frame);
+            for (formant, expected) in frame.iter().zip([700.0, 1220.0, 2600.0]) {
+                assert!(
+                    (formant.frequency / expected - 1.0).abs() < 0.05,
+                    "{} Hz: {:?}",
+                    sr,
+                    frame
+                );
+                assert!(formant.bandwidth > 0.0);
+            }
+        }
+        assert_eq!(par_track_formants(&vowel(sr, 0.5), sr, &params)?, track);
+        let f1 = track.frequencies(0);
+        assert_eq!(f1.len(), track.times.len());
+        assert!(f1[20].is_some());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_formants_of_filter() {
+    // A single resonance at 1 kHz with a bandwidth of 100 Hz
+    let sr = 10000;
+    let radius = (-std::f64::consts::PI * 100.0 / sr as f64).exp();
+    let angle = 2.0 * std::f64::consts::PI * 1000.0 / sr as f64;
+    let coefficients = [
+        1.0,
+        (-2.0 * radius * angle.cos()) as f32,
+        (radius * radius) as f32,
+    ];
+    let found = formants(&coefficients, sr);
+    assert_eq!(found.len(), 1);
+    assert!((found[0].frequency - 1000.0).abs() < 0.1);
+    assert!((found[0].bandwidth - 100.0).abs() < 0.1);
+}
+
+#[test]
+fn test_formants_of_silence() -> Result<()> {
+    let track = track_formants(&[0.0; 4000], 16000, &FormantParams::default())?;
+    assert!(track.formants.iter().all(|frame| frame.is_empty()));
+
+    let invalid = FormantParams {
+        n_formants: 0,
+        ..FormantParams::default()
+    };
+    assert!(matches!(
+        track_formants(&[0.0; 4000], 16000, &invalid),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    Ok(())
+}
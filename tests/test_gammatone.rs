@@ -0,0 +1,102 @@
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::filterbank::{FilterBankType, band_frequencies, filter_bank};
+use spectrs::spectrogram::gammatone::{
+    erb, erb_rate_to_hz, gammatone_band_frequencies, gammatone_filter_bank, hz_to_erb_rate,
+};
+use spectrs::spectrogram::mel::{MelScale, mel_filter_bank};
+use spectrs::spectrogram::params::SpectrogramParams;
+
+#[test]
+fn test_erb_scale() {
+    // Glasberg & Moore: 24.7 Hz at DC, about 132.6 Hz at 1 kHz
+    assert!((erb(0.0) - 24.7).abs() < 1e-9);
+    assert!((erb(1000.0) - 132.639).abs() < 1e-3);
+    for hz in [0.0, 50.0, 440.0, 8000.0] {
+        assert!((erb_rate_to_hz(hz_to_erb_rate(hz)) - hz).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_gammatone_filters_peak_at_their_centres() {
+    let (sr, n_fft) = (16000, 1024);
+    let centres = gammatone_band_frequencies(sr, 32, Some(50.0), None);
+    let filters: Vec<Vec<f32>> = gammatone_filter_bank(sr, n_fft, 32, Some(50.0), None);
+    assert_eq!(filters.len(), 32);
+    assert_eq!(filters[0].len(), n_fft / 2 + 1);
+
+    // Increasing centres strictly inside the band, spaced evenly in ERB rate
+    assert!(centres[0] > 50.0 && centres[31] < 8000.0);
+    assert!(centres.windows(2).all(|w| w[0] < w[1]));
+    let step = hz_to_erb_rate(centres[1] as f64) - hz_to_erb_rate(centres[0] as f64);
+    let last_step = hz_to_erb_rate(centres[31] as f64) - hz_to_erb_rate(centres[30] as f64);
+    assert!((step - last_step).abs() < 1e-3);
+
+    let bin_width = sr as f32 / n_fft as f32;
+    for (filter, &centre) in filters.iter().zip(&centres) {
+        let peak = filter
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert!((peak as f32 * bin_width - centre).abs() <= bin_width);
+        assert!(filter.iter().all(|&w| (0.0..=1.0).contains(&w)));
+    }
+    // Wider filters at higher frequencies
+    let width = |f: &Vec<f32>| f.iter().filter(|&&w| w > 0.5).count();
+    assert!(width(&filters[31]) > width(&filters[0]));
+}
+
+#[test]
+fn test_filter_bank_dispatch() {
+    let mel = SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(512)
+        .n_mels(Some(20))
+        .build()
+        .unwrap();
+    let filters: Vec<Vec<f32>> = filter_bank(&mel, 16000).unwrap();
+    assert_eq!(
+        filters,
+        mel_filter_bank::<f32>(16000, 512, 20, None, None, MelScale::Slaney, true)
+    );
+    assert_eq!(band_frequencies(&mel, 16000).len(), 20);
+
+    let gammatone = SpectrogramParams {
+        filter_bank: FilterBankType::Gammatone,
+        ..mel
+    };
+    let filters: Vec<Vec<f32>> = filter_bank(&gammatone, 16000).unwrap();
+    assert_eq!(
+        filters,
+        gammatone_filter_bank::<f32>(16000, 512, 20, None, None)
+    );
+    assert_eq!(
+        band_frequencies(&gammatone, 16000),
+        gammatone_band_frequencies(16000, 20, None, None)
+    );
+
+    let linear = SpectrogramParams {
+        n_mels: None,
+        ..mel
+    };
+    assert!(filter_bank::<f32>(&linear, 16000).is_none());
+    assert_eq!(band_frequencies(&linear, 16000).len(), 257);
+}
+
+#[test]
+fn test_gammatone_params_validation() {
+    let builder = SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(512)
+        .filter_bank(FilterBankType::Gammatone);
+    assert!(builder.build().is_err());
+    assert!(builder.n_mels(Some(32)).build().is_ok());
+    assert!(
+        builder
+            .n_mels(Some(32))
+            .compat(Compat::Torchaudio)
+            .build()
+            .is_err()
+    );
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "gpu")]
+
+use anyhow::Result;
+use spectrs::spectrogram::gpu::GpuStft;
+use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use std::f32::consts::PI;
+
+fn test_signal(sr: usize, seconds: f32) -> Vec<f32> {
+    (0..(sr as f32 * seconds) as usize)
+        .map(|i| {
+            let t = i as f32 / sr as f32;
+            0.5 * (2.0 * PI * 440.0 * t).sin() + 0.25 * (2.0 * PI * 1320.0 * t).sin()
+        })
+        .collect()
+}
+
+/// Largest absolute difference relative to the largest value of the reference
+fn max_relative_error(actual: &[Vec<f32>], expected: &[Vec<f32>]) -> f32 {
+    let scale = expected
+        .iter()
+        .flatten()
+        .fold(0.0f32, |acc, v| acc.max(v.abs()));
+    actual
+        .iter()
+        .flatten()
+        .zip(expected.iter().flatten())
+        .fold(0.0f32, |acc, (a, e)| acc.max((a - e).abs()))
+        / scale
+}
+
+#[test]
+#[ignore = "requires a GPU adapter"]
+fn test_gpu_spectrogram_matches_cpu() -> Result<()> {
+    let gpu = GpuStft::new()?;
+    let audio = test_signal(16000, 1.3);
+
+    for (n_fft, hop_length, win_length, center, spectrogram_type) in [
+        (512, 128, 512, false, SpectrogramType::Power),
+        (1024, 256, 800, true, SpectrogramType::Magnitude),
+        (2048, 512, 2048, true, SpectrogramType::Power),
+    ] {
+        let expected = compute_spectrogram(
+            &audio,
+            n_fft,
+            hop_length,
+            win_length,
+            center,
+            spectrogram_type,
+        );
+        let actual = gpu.compute_spectrogram(
+            &audio,
+            n_fft,
+            hop_length,
+            win_length,
+            center,
+            spectrogram_type,
+        )?;
+        assert_eq!(actual.len(), expected.len());
+        assert_eq!(actual[0].len(), expected[0].len());
+        let error = max_relative_error(&actual, &expected);
+        assert!(error < 1e-4, "n_fft={}: relative error {}", n_fft, error);
+    }
+
+    // Shorter than one window: a single zero-padded frame
+    let short = &audio[..300];
+    let expected = compute_spectrogram(short, 512, 128, 512, true, SpectrogramType::Power);
+    let actual = gpu.compute_spectrogram(short, 512, 128, 512, true, SpectrogramType::Power)?;
+    assert!(max_relative_error(&actual, &expected) < 1e-4);
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires a GPU adapter"]
+fn test_gpu_mel_spectrogram_matches_cpu() -> Result<()> {
+    let gpu = GpuStft::new()?;
+    let audio = test_signal(22050, 2.0);
+    let params = SpectrogramParams::builder()
+        .n_fft(1024)
+        .hop_length(256)
+        .win_length(1024)
+        .n_mels(Some(64))
+        .mel_scale(MelScale::HTK)
+        .build()?;
+
+    let linear = compute_spectrogram(&audio, 1024, 256, 1024, true, SpectrogramType::Power);
+    let expected = convert_to_mel(&linear, 22050, 1024, 64, None, None, MelScale::HTK);
+    let actual = gpu.spectrogram(&audio, 22050, &params)?;
+    assert_eq!(actual.len(), 64);
+    assert_eq!(actual[0].len(), expected[0].len());
+    assert!(max_relative_error(&actual, &expected) < 1e-4);
+
+    // Only power-of-two FFT sizes are supported
+    assert!(
+        gpu.compute_spectrogram(&audio, 1000, 250, 1000, true, SpectrogramType::Power)
+            .is_err()
+    );
+    Ok(())
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "index")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use spectrs::io::index::{FileIndex, FileStatus, IndexRecord};
+use spectrs::spectrogram::params::SpectrogramParams;
+use std::fs;
+
+fn params(n_fft: usize) -> Result<SpectrogramParams> {
+    Ok(SpectrogramParams::builder()
+        .n_fft(n_fft)
+        .hop_length(n_fft / 4)
+        .win_length(n_fft)
+        .build()?)
+}
+
+#[test]
+fn test_index_records_and_queries() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let db = test_dir.join("spectrs.db");
+    let (wav, png) = (test_dir.join("a.wav"), test_dir.join("a.png"));
+    create_test_wav(&wav, 0.5, 16000, 1, 16)?;
+    fs::write(&png, b"")?;
+    let input = wav.display().to_string();
+
+    let mut index = FileIndex::open(&db, &params(512)?)?;
+    let processed = IndexRecord {
+        input: input.clone(),
+        segment: (None, None),
+        label: Some("dog".to_string()),
+        status: FileStatus::Processed,
+        error: None,
+        outputs: vec![png.display().to_string()],
+        duration: Some(0.5),
+        peak_db: Some(-6.0),
+        zero_crossing_rate: Some(0.05),
+        features: vec![("centroid_mean_hz".to_string(), 440.0)],
+        params: None,
+    };
+    index.record(&processed, Some(&wav))?;
+    // Another segment of the same input, and an input that failed
+    index.record(
+        &IndexRecord {
+            segment: (Some(0.1), Some(0.3)),
+            duration: Some(0.2),
+            ..processed.clone()
+        },
+        Some(&wav),
+    )?;
+    index.record(
+        &IndexRecord::failed("broken.wav", (None, None), None, "Failed to decode"),
+        None,
+    )?;
+    // Recording an input again replaces its outcome
+    index.record(&processed, Some(&wav))?;
+
+    let connection = rusqlite::Connection::open(&db)?;
+    let count = |sql: &str| -> Result<i64> { Ok(connection.query_row(sql, [], |row| row.get(0))?) };
+    assert_eq!(count("SELECT COUNT(*) FROM files")?, 3);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM files WHERE duration_s < 0.3")?,
+        1
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM file_features")?, 2);
+    let (failed, error): (String, String) = connection.query_row(
+        "SELECT input, error FROM files WHERE status = 'failed'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(
+        (failed.as_str(), error.as_str()),
+        ("broken.wav", "Failed to decode")
+    );
+
+    // Up to date with the same parameters, file and outputs only
+    assert!(index.is_up_to_date(&input, (None, None), Some(&wav))?);
+    assert!(index.is_up_to_date(&input, (Some(0.1), Some(0.3)), Some(&wav))?);
+    assert!(!index.is_up_to_date(&input, (Some(0.2), None), Some(&wav))?);
+    assert!(!index.is_up_to_date("broken.wav", (None, None), None)?);
+    assert!(!index.is_up_to_date("unknown.wav", (None, None), None)?);
+    let other = FileIndex::open(&db, &params(1024)?)?;
+    assert!(!other.is_up_to_date(&input, (None, None), Some(&wav))?);
+    create_test_wav(&wav, 1.0, 16000, 1, 16)?;
+    assert!(!index.is_up_to_date(&input, (None, None), Some(&wav))?);
+    index.record(&processed, Some(&wav))?;
+    assert!(index.is_up_to_date(&input, (None, None), Some(&wav))?);
+    // An input processed with its own parameters is up to date with those only
+    let own = IndexRecord {
+        params: Some(params(1024)?),
+        ..processed.clone()
+    };
+    index.record(&own, Some(&wav))?;
+    assert!(!index.is_up_to_date(&input, (None, None), Some(&wav))?);
+    assert!(index.is_up_to_date_with(&input, (None, None), Some(&wav), &params(1024)?)?);
+    fs::remove_file(&png)?;
+    assert!(!index.is_up_to_date_with(&input, (None, None), Some(&wav), &params(1024)?)?);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
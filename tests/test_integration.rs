@@ -39,7 +39,7 @@ fn test_full_pipeline_mono_16bit() -> Result<()> {
     );
 
     assert_eq!(spec.len(), 257); // n_fft / 2 + 1
-    assert!(spec[0].len() > 0);
+    assert!(!spec[0].is_empty());
 
     // Convert to mel
     let n_mels = 40;
@@ -76,7 +76,7 @@ fn test_full_pipeline_stereo_16bit() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, 16000, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -105,7 +105,7 @@ fn test_full_pipeline_mono_8bit() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, 16000, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -134,7 +134,7 @@ fn test_full_pipeline_mono_32bit() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, 16000, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -156,7 +156,7 @@ fn test_full_pipeline_stereo_32bit() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, 16000, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -190,7 +190,7 @@ fn test_full_pipeline_different_fft_sizes() -> Result<()> {
         let mel_spec = convert_to_mel(&spec, sr, n_fft, 40, None, None, MelScale::HTK);
 
         assert_eq!(mel_spec.len(), 40);
-        assert!(mel_spec[0].len() > 0);
+        assert!(!mel_spec[0].is_empty());
     }
 
     cleanup_test_dir(&test_dir)?;
@@ -240,7 +240,7 @@ fn test_full_pipeline_different_sample_rates() -> Result<()> {
         let mel_spec = convert_to_mel(&spec, sr, 512, 40, None, None, MelScale::HTK);
 
         assert_eq!(mel_spec.len(), 40);
-        assert!(mel_spec[0].len() > 0);
+        assert!(!mel_spec[0].is_empty());
     }
 
     cleanup_test_dir(&test_dir)?;
@@ -261,7 +261,7 @@ fn test_full_pipeline_magnitude_spectrogram() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, sr, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -281,7 +281,7 @@ fn test_full_pipeline_centered() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, sr, 512, 40, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -301,7 +301,7 @@ fn test_full_pipeline_slaney_mel_scale() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, sr, 512, 40, None, None, MelScale::Slaney);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -321,7 +321,7 @@ fn test_full_pipeline_custom_freq_range() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, sr, 512, 40, Some(300.0), Some(4000.0), MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -368,7 +368,7 @@ fn test_full_pipeline_short_audio() -> Result<()> {
     let mel_spec = convert_to_mel(&spec, sr, 256, 20, None, None, MelScale::HTK);
 
     assert_eq!(mel_spec.len(), 20);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
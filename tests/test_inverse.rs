@@ -0,0 +1,165 @@
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::inverse::{
+    MelInversion, compute_complex_stft, griffin_lim, istft, mel_to_linear,
+};
+use spectrs::spectrogram::mel::{MelScale, apply_filter_bank, mel_filter_bank};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+fn chirp(n_samples: usize, sr: f32) -> Vec<f32> {
+    (0..n_samples)
+        .map(|i| {
+            let t = i as f32 / sr;
+            0.5 * (2.0 * std::f32::consts::PI * (200.0 + 400.0 * t) * t).sin()
+        })
+        .collect()
+}
+
+#[test]
+fn test_complex_stft_matches_spectrogram() {
+    let audio = chirp(4000, 8000.0);
+    for center in [false, true] {
+        let stft = compute_complex_stft(&audio, 512, 128, 400, center);
+        let magnitude =
+            compute_spectrogram(&audio, 512, 128, 400, center, SpectrogramType::Magnitude);
+
+        assert_eq!(stft.len(), magnitude.len());
+        for (complex_row, row) in stft.iter().zip(&magnitude) {
+            assert_eq!(complex_row.len(), row.len());
+            for (c, &m) in complex_row.iter().zip(row) {
+                assert!((c.norm() - m).abs() < 1e-3);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_istft_inverts_stft() -> Result<(), SpectrsError> {
+    let audio = chirp(4000, 8000.0);
+    for (n_fft, hop_length, win_length, center) in [(512, 128, 512, false), (512, 100, 400, true)] {
+        let stft = compute_complex_stft(&audio, n_fft, hop_length, win_length, center);
+        let reconstructed = istft(&stft, n_fft, hop_length, win_length, center)?;
+
+        let n_frames = stft[0].len();
+        assert_eq!(
+            reconstructed.len(),
+            (n_frames - 1) * hop_length + win_length
+        );
+        // Perfect reconstruction wherever windows overlap
+        for (i, (&x, &y)) in audio
+            .iter()
+            .zip(&reconstructed)
+            .enumerate()
+            .skip(win_length)
+        {
+            if i + win_length >= reconstructed.len() {
+                break;
+            }
+            assert!((x - y).abs() < 1e-3, "sample {}: {} vs {}", i, x, y);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_griffin_lim_converges() -> Result<(), SpectrsError> {
+    let audio = chirp(8000, 8000.0);
+    let (n_fft, hop_length) = (512, 128);
+    let target = compute_spectrogram(
+        &audio,
+        n_fft,
+        hop_length,
+        n_fft,
+        true,
+        SpectrogramType::Magnitude,
+    );
+
+    // Spectral convergence: relative distance between the target and achieved magnitudes
+    let convergence = |n_iter| -> Result<f32, SpectrsError> {
+        let estimate = griffin_lim(&target, n_fft, hop_length, n_fft, true, n_iter)?;
+        let achieved = compute_spectrogram(
+            &estimate,
+            n_fft,
+            hop_length,
+            n_fft,
+            true,
+            SpectrogramType::Magnitude,
+        );
+        let (mut error, mut norm) = (0.0f32, 0.0f32);
+        for (t, a) in target.iter().flatten().zip(achieved.iter().flatten()) {
+            error += (t - a).powi(2);
+            norm += t.powi(2);
+        }
+        Ok((error / norm).sqrt())
+    };
+    let initial = convergence(0)?;
+    let refined = convergence(32)?;
+    assert!(refined < 0.2, "spectral convergence {}", refined);
+    assert!(refined < initial / 2.0);
+
+    // Deterministic
+    assert_eq!(
+        griffin_lim(&target, n_fft, hop_length, n_fft, true, 2)?,
+        griffin_lim(&target, n_fft, hop_length, n_fft, true, 2)?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_inverse_rejects_mismatched_bins() {
+    let magnitude = vec![vec![1.0; 10]; 100];
+    let error = griffin_lim(&magnitude, 512, 128, 512, true, 1).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+}
+
+#[test]
+fn test_mel_to_linear() -> Result<(), SpectrsError> {
+    let audio = chirp(8000, 8000.0);
+    let (n_fft, hop_length) = (512, 128);
+    let linear = compute_spectrogram(
+        &audio,
+        n_fft,
+        hop_length,
+        n_fft,
+        true,
+        SpectrogramType::Power,
+    );
+    let filters: Vec<Vec<f32>> =
+        mel_filter_bank(8000, n_fft, 64, None, None, MelScale::Slaney, true);
+    let mel = apply_filter_bank(&linear, &filters);
+
+    // Relative distance between the bands and those of the inverted spectrogram
+    let residual = |method| -> Result<f32, SpectrsError> {
+        let inverted = mel_to_linear(&mel, &filters, method, 200)?;
+        assert_eq!(inverted.len(), n_fft / 2 + 1);
+        assert_eq!(inverted[0].len(), linear[0].len());
+        assert!(inverted.iter().flatten().all(|&v| v >= 0.0));
+        let projected = apply_filter_bank(&inverted, &filters);
+        let (mut error, mut norm) = (0.0f32, 0.0f32);
+        for (m, p) in mel.iter().flatten().zip(projected.iter().flatten()) {
+            error += (m - p).powi(2);
+            norm += m.powi(2);
+        }
+        Ok((error / norm).sqrt())
+    };
+    let transpose = residual(MelInversion::Transpose)?;
+    let nnls = residual(MelInversion::Nnls)?;
+    assert!(nnls < 0.05, "NNLS residual {}", nnls);
+    assert!(nnls < transpose);
+
+    // Flat spectra are recovered exactly by the transpose, within the band of the filters
+    let flat = vec![vec![2.0f32; 3]; n_fft / 2 + 1];
+    let inverted = mel_to_linear(
+        &apply_filter_bank(&flat, &filters),
+        &filters,
+        MelInversion::Transpose,
+        0,
+    )?;
+    for row in &inverted[1..n_fft / 2] {
+        assert!((row[0] - 2.0).abs() < 1e-3, "{}", row[0]);
+    }
+
+    // One band per filter
+    let error = mel_to_linear(&mel[1..], &filters, MelInversion::Nnls, 1).unwrap_err();
+    assert!(matches!(error, SpectrsError::InvalidParams(_)));
+    Ok(())
+}
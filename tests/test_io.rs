@@ -1,8 +1,18 @@
 mod common;
 
 use anyhow::Result;
-use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
-use spectrs::io::audio::{read_audio_file_mono, resample};
+use common::{
+    cleanup_test_dir, create_float_test_wav, create_int24_test_wav, create_test_wav, setup_test_dir,
+};
+use spectrs::error::SpectrsError;
+use spectrs::io::audio::{
+    AudioReader, DownmixMode, MonoChunks, PcmFormat, ResampleQuality, WavFormat,
+    read_audio_file_mono, read_audio_file_mono_lenient, read_audio_mono_from_reader,
+    read_audio_mono_lenient_from_reader, read_audio_segment, read_audio_segment_from_reader,
+    read_raw_pcm, read_raw_pcm_from_reader, resample, resample_with_quality, write_audio_file,
+};
+use std::error::Error;
+use std::io::Cursor;
 
 #[test]
 fn test_read_audio_file_mono_mono_16bit() -> Result<()> {
@@ -208,7 +218,7 @@ fn test_resample_extreme_rates() -> Result<()> {
     let resampled = resample(samples, original_sr, target_sr)?;
 
     // Check that it worked and values are valid
-    assert!(resampled.len() > 0);
+    assert!(!resampled.is_empty());
     for sample in &resampled {
         assert!(sample.abs() <= 1.1); // Allow slight overshoot due to interpolation
     }
@@ -233,7 +243,7 @@ fn test_read_and_resample_integration() -> Result<()> {
 
     // Verify resampled length
     let expected_len = 16000; // 1 second at 16000 Hz
-    assert!((resampled.len() as i32 - expected_len as i32).abs() < 100);
+    assert!((resampled.len() as i32 - expected_len).abs() < 100);
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -286,6 +296,383 @@ fn test_save_spectrogram_image_all_colormaps() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "image")]
+#[test]
+fn test_save_spectrogram_tiles() -> Result<()> {
+    use spectrs::io::image::{
+        ColorScale, Colormap, save_spectrogram_image, save_spectrogram_tiles, tile_path,
+    };
+    use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test_audio.wav");
+    common::create_complex_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+    let (audio, _) = read_audio_file_mono(&audio_path)?;
+    let spec = compute_spectrogram(&audio, 512, 128, 512, true, SpectrogramType::Power);
+    let n_frames = spec[0].len();
+    assert_eq!(n_frames, 122);
+
+    let output = test_dir.join("long.png");
+    let scale = ColorScale::default();
+    let tiles = save_spectrogram_tiles(&spec, &output, 50, Colormap::Magma, &scale)?;
+    assert_eq!(tiles.len(), 3);
+    assert_eq!(tiles[0].path, test_dir.join("long.tile0000.png"));
+    assert_eq!(tiles[2].path, tile_path(&output, 2));
+    assert_eq!(tiles[1].frames, 50..100);
+    assert_eq!(tiles[2].frames, 100..122);
+
+    // Tiles are normalized over the whole spectrogram: side by side, they form the single image
+    save_spectrogram_image(&spec, output.clone(), Colormap::Magma)?;
+    let whole = image::open(&output)?.to_rgb8();
+    for tile in &tiles {
+        let image = image::open(&tile.path)?.to_rgb8();
+        assert_eq!(image.width() as usize, tile.frames.len());
+        assert_eq!(image.height() as usize, spec.len());
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel, whole.get_pixel(x + tile.frames.start as u32, y));
+        }
+    }
+
+    assert!(save_spectrogram_tiles(&spec, &output, 0, Colormap::Magma, &scale).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_save_waveform_image() -> Result<()> {
+    use spectrs::io::image::{
+        ColorScale, Colormap, apply_colormap, save_spectrogram_image, save_waveform_image,
+    };
+
+    let test_dir = setup_test_dir()?;
+    // 16 frames, 200 bins: the panel is 50 pixels high
+    let spec: Vec<Vec<f32>> = (0..200)
+        .map(|f| (0..16).map(|t| (f * t) as f32).collect())
+        .collect();
+    // Silence in the first half, full-scale square wave in the second half
+    let audio: Vec<f32> = (0..1600)
+        .map(|i| match i {
+            0..800 => 0.0,
+            _ if i % 2 == 0 => 1.0,
+            _ => -1.0,
+        })
+        .collect();
+
+    let plain_path = test_dir.join("plain.png");
+    let waveform_path = test_dir.join("nested/waveform.png");
+    save_spectrogram_image(&spec, plain_path.clone(), Colormap::Gray)?;
+    save_waveform_image(
+        &spec,
+        &audio,
+        waveform_path.clone(),
+        Colormap::Gray,
+        &ColorScale::default(),
+    )?;
+    let plain = image::open(&plain_path)?.to_rgb8();
+    let img = image::open(&waveform_path)?.to_rgb8();
+    assert_eq!(img.dimensions(), (16, 250));
+
+    // The spectrogram is on top, unchanged
+    for (x, y, pixel) in plain.enumerate_pixels() {
+        assert_eq!(pixel, img.get_pixel(x, y));
+    }
+
+    // Silence only lights the middle row of the panel, full scale lights the whole column
+    let lit = |x: u32| {
+        (200..250)
+            .filter(|&y| img.get_pixel(x, y).0 == apply_colormap(1.0, Colormap::Gray))
+            .count()
+    };
+    assert_eq!(lit(3), 1);
+    assert_eq!(lit(12), 50);
+
+    // Short spectrograms get a minimum panel height
+    let short = vec![vec![1.0f32; 4]; 8];
+    save_waveform_image(
+        &short,
+        &audio,
+        waveform_path.clone(),
+        Colormap::Gray,
+        &ColorScale::default(),
+    )?;
+    assert_eq!(image::open(&waveform_path)?.height(), 8 + 32);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_save_comparison_image() -> Result<()> {
+    use spectrs::io::image::{ColorScale, Colormap, apply_colormap, save_comparison_image};
+
+    let test_dir = setup_test_dir()?;
+    // 40 bins and 100 frames: `after` is 10 dB louder in the last 50 frames
+    let before = vec![vec![1.0f32; 100]; 40];
+    let after: Vec<Vec<f32>> = (0..40)
+        .map(|_| (0..100).map(|t| if t < 50 { 1.0 } else { 10.0 }).collect())
+        .collect();
+    let output = test_dir.join("nested/comparison.png");
+    save_comparison_image(
+        &before,
+        &after,
+        output.clone(),
+        Colormap::Gray,
+        &ColorScale::default(),
+        Some(20.0),
+    )?;
+    let img = image::open(&output)?.to_rgb8();
+    // Three panels separated by two rows
+    assert_eq!(img.dimensions(), (100, 3 * 40 + 2 * 2));
+
+    // Shared colour scale: the quietest colour in both panels, the loudest in `after` only
+    let (dark, bright) = (
+        apply_colormap(0.0, Colormap::Gray),
+        apply_colormap(1.0, Colormap::Gray),
+    );
+    assert_eq!(img.get_pixel(20, 30).0, dark);
+    assert_eq!(img.get_pixel(20, 42 + 30).0, dark);
+    assert_eq!(img.get_pixel(80, 42 + 30).0, bright);
+    assert_eq!(img.get_pixel(80, 30).0, dark);
+    // Panels are labelled, the gap between them is black
+    let label = (2..40)
+        .flat_map(|x| (2..9).map(move |y| (x, y)))
+        .filter(|&(x, y)| img.get_pixel(x, y).0 == [255, 255, 255]);
+    assert!(label.count() > 0);
+    assert_eq!(img.get_pixel(50, 40).0, [0, 0, 0]);
+
+    // No difference is white, +10 dB half way to red on a range of 20 dB
+    assert_eq!(img.get_pixel(20, 84 + 30).0, [255, 255, 255]);
+    assert_eq!(img.get_pixel(80, 84 + 30).0, [255, 128, 128]);
+
+    // The default range is the 99th percentile of the differences: full red
+    save_comparison_image(
+        &before,
+        &after,
+        output.clone(),
+        Colormap::Gray,
+        &ColorScale::default(),
+        None,
+    )?;
+    assert_eq!(
+        image::open(&output)?.to_rgb8().get_pixel(80, 84 + 30).0,
+        [255, 0, 0]
+    );
+
+    let shorter = vec![vec![1.0f32; 99]; 40];
+    let scale = ColorScale::default();
+    assert!(
+        save_comparison_image(
+            &before,
+            &shorter,
+            output.clone(),
+            Colormap::Gray,
+            &scale,
+            None
+        )
+        .is_err()
+    );
+    assert!(
+        save_comparison_image(&before, &after, output, Colormap::Gray, &scale, Some(0.0)).is_err()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_read_spectrogram_image() -> Result<()> {
+    use spectrs::io::image::{
+        ColorScale, Colormap, encode_scaled_spectrogram_png, image_scale, read_spectrogram_image,
+    };
+
+    let spec: Vec<Vec<f32>> = (0..8)
+        .map(|f| {
+            (0..30)
+                .map(|t| ((f * 30 + t) as f32 / 4.0).powi(2))
+                .collect()
+        })
+        .collect();
+    for (colormap, scale) in [
+        (Colormap::Viridis, ColorScale::default()),
+        (
+            Colormap::Magma,
+            ColorScale {
+                vmin: Some(-20.0),
+                vmax: Some(60.0),
+                gamma: 0.5,
+                ..Default::default()
+            },
+        ),
+        (Colormap::Gray, ColorScale::default()),
+    ] {
+        let png = encode_scaled_spectrogram_png(&spec, colormap, &scale)?;
+        let mapping = image_scale(&spec, colormap, &scale)?;
+        let values = read_spectrogram_image(Cursor::new(png), &mapping, spec.len())?;
+        assert_eq!(values.len(), spec.len());
+        assert_eq!(values[0].len(), spec[0].len());
+
+        // Equal up to the resolution of the colormap, in the scale of the image
+        let scaled = |v: f32| match mapping.db {
+            true => (10.0 * v.max(1e-10).log10()).clamp(mapping.min, mapping.max),
+            false => v.ln_1p(),
+        };
+        let resolution = 2.0 * (mapping.max - mapping.min) / 255.0;
+        for (row, expected) in values.iter().zip(&spec) {
+            for (&v, &e) in row.iter().zip(expected) {
+                assert!((scaled(v) - scaled(e)).abs() <= resolution);
+            }
+        }
+    }
+
+    // The image must have the rows of the spectrogram
+    let png = encode_scaled_spectrogram_png(&spec, Colormap::Viridis, &ColorScale::default())?;
+    let mapping = image_scale(&spec, Colormap::Viridis, &ColorScale::default())?;
+    assert!(read_spectrogram_image(Cursor::new(png), &mapping, 9).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_color_scale() -> Result<()> {
+    use spectrs::error::SpectrsError;
+    use spectrs::io::image::{
+        ColorScale, Colormap, apply_colormap, encode_scaled_spectrogram_png, encode_spectrogram_png,
+    };
+
+    let render = |spec: &[Vec<f32>], scale: &ColorScale| -> Result<image::RgbImage> {
+        let png = encode_scaled_spectrogram_png(spec, Colormap::Gray, scale)?;
+        Ok(image::load_from_memory(&png)?.to_rgb8())
+    };
+    let gray = |v: f32| image::Rgb(apply_colormap(v, Colormap::Gray));
+
+    // The default scale is the log1p min-max normalization
+    let spec = vec![vec![0.0, 1.0, 10.0, 100.0], vec![3.0, 0.5, 7.0, 20.0]];
+    let default = ColorScale::default();
+    assert_eq!(
+        encode_scaled_spectrogram_png(&spec, Colormap::Viridis, &default)?,
+        encode_spectrogram_png(&spec, Colormap::Viridis)?
+    );
+
+    // A fixed dB range gives the same level the same colour in any image, clipping the rest
+    let fixed = ColorScale {
+        vmin: Some(-80.0),
+        vmax: Some(0.0),
+        ..Default::default()
+    };
+    for peak in [1.0, 1e6] {
+        let img = render(&[vec![1e-12, 1e-8, 1e-4, peak]], &fixed)?;
+        assert_eq!(*img.get_pixel(0, 0), gray(0.0));
+        assert_eq!(*img.get_pixel(1, 0), gray(0.0));
+        assert_eq!(*img.get_pixel(2, 0), gray(0.5));
+        assert_eq!(*img.get_pixel(3, 0), gray(1.0));
+    }
+    // Magnitudes are 20 log10 in dB
+    let magnitude = ColorScale {
+        magnitude: true,
+        ..fixed
+    };
+    assert_eq!(
+        *render(&[vec![1e-2, 1.0]], &magnitude)?.get_pixel(0, 0),
+        gray(0.5)
+    );
+
+    // A single bound takes the other one from the image
+    let floor = ColorScale {
+        vmin: Some(-20.0),
+        ..Default::default()
+    };
+    let img = render(&[vec![1e-3, 0.1, 1.0]], &floor)?;
+    assert_eq!(*img.get_pixel(0, 0), gray(0.0));
+    assert_eq!(*img.get_pixel(1, 0), gray(0.5));
+    assert_eq!(*img.get_pixel(2, 0), gray(1.0));
+
+    // Percentiles keep an outlier from compressing the other values
+    let ramp: Vec<f32> = (0..=100).map(|i| (i as f32).exp_m1()).collect();
+    let clipped = ColorScale {
+        percentile_clip: Some((0.0, 50.0)),
+        ..Default::default()
+    };
+    let img = render(&[ramp], &clipped)?;
+    assert_eq!(*img.get_pixel(25, 0), gray(0.5));
+    assert_eq!(*img.get_pixel(50, 0), gray(1.0));
+    assert_eq!(*img.get_pixel(100, 0), gray(1.0));
+
+    // Gamma bends the normalized values
+    let gamma = ColorScale {
+        gamma: 2.0,
+        ..fixed
+    };
+    assert_eq!(
+        *render(&[vec![1e-4, 1.0]], &gamma)?.get_pixel(0, 0),
+        gray(0.25)
+    );
+
+    for scale in [
+        ColorScale {
+            vmin: Some(0.0),
+            vmax: Some(-10.0),
+            ..Default::default()
+        },
+        ColorScale {
+            percentile_clip: Some((50.0, 101.0)),
+            ..Default::default()
+        },
+        ColorScale {
+            gamma: 0.0,
+            ..Default::default()
+        },
+    ] {
+        let error = render(&spec, &scale).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<SpectrsError>(),
+            Some(SpectrsError::InvalidParams(_))
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_scale_accumulator() -> Result<()> {
+    use spectrs::io::image::{ColorScale, ScaleAccumulator};
+
+    let default = ColorScale::default();
+    let mut a = ScaleAccumulator::new(&default);
+    a.add(&[vec![1e-4, 1e-2]]);
+    let mut b = ScaleAccumulator::new(&default);
+    b.add(&[vec![1.0], vec![1e-3]]);
+    let merged = a.clone().merge(b).finish()?;
+    assert_eq!(merged.vmin, Some(-40.0));
+    assert_eq!(merged.vmax, Some(0.0));
+    assert_eq!(merged.percentile_clip, None);
+
+    // Percentiles of the whole dataset, to the bin width; given bounds are kept
+    let levels: Vec<f32> = (0..=100).map(|i| 10f32.powf(i as f32 / 10.0)).collect();
+    let clipped = ColorScale {
+        vmax: Some(95.0),
+        percentile_clip: Some((10.0, 90.0)),
+        ..default
+    };
+    let mut accumulator = ScaleAccumulator::new(&clipped);
+    accumulator.add(&[levels[..50].to_vec()]);
+    accumulator.add(&[levels[50..].to_vec()]);
+    let scale = accumulator.finish()?;
+    assert!((scale.vmin.unwrap() - 10.0).abs() <= 0.01);
+    assert_eq!(scale.vmax, Some(95.0));
+
+    // Nothing to scale, or nothing left between the bounds
+    assert!(ScaleAccumulator::new(&default).finish().is_err());
+    let mut constant = ScaleAccumulator::new(&default);
+    constant.add(&[vec![1.0; 4]]);
+    assert!(constant.finish().is_err());
+
+    Ok(())
+}
+
 #[cfg(feature = "image")]
 #[test]
 fn test_save_spectrogram_image_viridis() -> Result<()> {
@@ -471,3 +858,683 @@ fn test_save_spectrogram_image_feature_disabled() {
             .contains("Image feature not enabled")
     );
 }
+
+#[test]
+fn test_read_audio_segment() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test_segment.wav");
+
+    // Create stereo 16-bit WAV file of 2 seconds
+    create_test_wav(&audio_path, 2.0, 16000, 2, 16)?;
+
+    // Read only [0.5, 1.5) seconds
+    let (segment, sr) = read_audio_segment(&audio_path, 0.5, Some(1.5))?;
+    assert_eq!(sr, 16000);
+    assert_eq!(segment.len(), 16000);
+
+    // Segment must match the same slice of the fully decoded file
+    let (full, _) = read_audio_file_mono(&audio_path)?;
+    assert_eq!(segment, full[8000..24000]);
+
+    // Open-ended segment runs until the end of the file
+    let (tail, _) = read_audio_segment(&audio_path, 1.5, None)?;
+    assert_eq!(tail.len(), 8000);
+
+    // End past the end of file is clamped
+    let (clamped, _) = read_audio_segment(&audio_path, 1.0, Some(10.0))?;
+    assert_eq!(clamped.len(), 16000);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_audio_segment_invalid_range() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("test_segment_invalid.wav");
+
+    create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+
+    // End before start
+    assert!(read_audio_segment(&audio_path, 0.8, Some(0.2)).is_err());
+
+    // Start beyond the end of the file
+    assert!(read_audio_segment(&audio_path, 5.0, None).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_audio_file_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+
+    let sr = 16000;
+    let samples: Vec<f32> = (0..sr)
+        .map(|t| 0.5 * (t as f32 * 440.0 * 2.0 * std::f32::consts::PI / sr as f32).sin())
+        .collect();
+
+    // Integer formats round-trip through the reader within quantization error
+    for (format, tolerance) in [
+        (WavFormat::Int16, 1e-4),
+        (WavFormat::Int24, 1e-6),
+        (WavFormat::Int32, 1e-6),
+    ] {
+        let path = test_dir.join(format!("{:?}.wav", format));
+        write_audio_file(&path, &samples, sr as u32, format)?;
+
+        let (read, read_sr) = read_audio_file_mono(&path)?;
+        assert_eq!(read_sr, sr as u32);
+        assert_eq!(read.len(), samples.len());
+        for (a, b) in read.iter().zip(samples.iter()) {
+            assert!((a - b).abs() < tolerance, "{:?}: {} vs {}", format, a, b);
+        }
+    }
+
+    // Float format stores the exact samples
+    let path = test_dir.join("float.wav");
+    write_audio_file(&path, &samples, sr as u32, WavFormat::Float32)?;
+    let mut reader = hound::WavReader::open(&path)?;
+    assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+    let read: Vec<f32> = reader.samples::<f32>().collect::<Result<_, _>>()?;
+    assert_eq!(read, samples);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_float_wav() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+
+    for bits in [32, 64] {
+        // Mono samples are read as is (rounded to f32)
+        let path = test_dir.join(format!("float{}.wav", bits));
+        let samples = create_float_test_wav(&path, 0.5, 16000, 1, bits)?;
+        let expected: Vec<f32> = samples.iter().map(|&s| s as f32).collect();
+
+        let (read, sr) = read_audio_file_mono(&path)?;
+        assert_eq!(sr, 16000);
+        assert_eq!(read, expected, "{}-bit float", bits);
+
+        // Non-seekable readers, segments, chunked and lenient decoding agree
+        let bytes = std::fs::read(&path)?;
+        let (streamed, _) = read_audio_mono_from_reader(&bytes[..], DownmixMode::Average)?;
+        assert_eq!(streamed, expected);
+        let (segment, _) = read_audio_segment(&path, 0.25, Some(0.375))?;
+        assert_eq!(segment, expected[4000..6000]);
+        let mut chunks = MonoChunks::open(&path)?;
+        assert_eq!(chunks.remaining_frames(), expected.len());
+        let mut chunked = Vec::new();
+        loop {
+            let chunk = chunks.next_chunk(1000)?;
+            if chunk.is_empty() {
+                break;
+            }
+            chunked.extend(chunk);
+        }
+        assert_eq!(chunked, expected);
+        let (lenient, _, report) = read_audio_file_mono_lenient(&path)?;
+        assert_eq!(lenient, expected);
+        assert!(report.is_none());
+
+        // Channels in opposite phase cancel out
+        let path = test_dir.join(format!("float{}_stereo.wav", bits));
+        create_float_test_wav(&path, 0.5, 16000, 2, bits)?;
+        let (read, _) = read_audio_file_mono(&path)?;
+        assert_eq!(read.len(), expected.len());
+        assert!(read.iter().all(|&s| s == 0.0));
+    }
+
+    // Truncated 64-bit data is recovered in lenient mode only
+    let path = test_dir.join("float64.wav");
+    let bytes = std::fs::read(&path)?;
+    let truncated = test_dir.join("truncated.wav");
+    std::fs::write(&truncated, &bytes[..bytes.len() - 804])?;
+    assert!(read_audio_file_mono(&truncated).is_err());
+    let (lenient, _, report) = read_audio_file_mono_lenient(&truncated)?;
+    assert_eq!(lenient.len(), 8000 - 101);
+    assert!(report.is_some());
+
+    // A data chunk declaring gigabytes fails at the end of the file instead of sizing an
+    // allocation
+    let data_len = bytes.windows(4).position(|w| w == b"data").unwrap() + 4;
+    let mut oversized = bytes.clone();
+    oversized[data_len..data_len + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+    let oversized_path = test_dir.join("oversized.wav");
+    std::fs::write(&oversized_path, &oversized)?;
+    assert!(read_audio_file_mono(&oversized_path).is_err());
+    assert!(read_audio_mono_from_reader(&oversized[..], DownmixMode::Average).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_24bit_wav() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+
+    // Full scale, smallest steps and a ramp spanning the whole range
+    let mut mono = vec![8_388_607, -8_388_608, 1, -1, 0];
+    mono.extend((0..7995).map(|i| (i - 4000) * 2097));
+    let expected: Vec<f32> = mono.iter().map(|&s| s as f32 / 8_388_608.0).collect();
+    assert_eq!(expected[0], 8_388_607.0 / 8_388_608.0);
+    assert_eq!(expected[1], -1.0);
+
+    // Left and right channel are averaged
+    let stereo: Vec<i32> = mono.iter().flat_map(|&s| [s, -s / 2]).collect();
+    let expected_stereo: Vec<f32> = mono
+        .iter()
+        .map(|&s| (s + -s / 2) as f32 / 2.0 / 8_388_608.0)
+        .collect();
+
+    for padded in [false, true] {
+        let path = test_dir.join(format!("mono_{}.wav", padded));
+        create_int24_test_wav(&path, &mono, 16000, 1, padded)?;
+        let (read, sr) = read_audio_file_mono(&path)?;
+        assert_eq!(sr, 16000);
+        assert_eq!(read, expected, "padded: {}", padded);
+
+        // Non-seekable readers, segments, chunked and lenient decoding agree
+        let bytes = std::fs::read(&path)?;
+        let (streamed, _) = read_audio_mono_from_reader(&bytes[..], DownmixMode::Average)?;
+        assert_eq!(streamed, expected);
+        let (segment, _) = read_audio_segment(&path, 0.25, Some(0.375))?;
+        assert_eq!(segment, expected[4000..6000]);
+        let mut chunks = MonoChunks::open(&path)?;
+        let mut chunked = Vec::new();
+        loop {
+            let chunk = chunks.next_chunk(1000)?;
+            if chunk.is_empty() {
+                break;
+            }
+            chunked.extend(chunk);
+        }
+        assert_eq!(chunked, expected);
+        let (lenient, _, report) = read_audio_file_mono_lenient(&path)?;
+        assert_eq!(lenient, expected);
+        assert!(report.is_none());
+
+        let path = test_dir.join(format!("stereo_{}.wav", padded));
+        create_int24_test_wav(&path, &stereo, 16000, 2, padded)?;
+        let (read, _) = read_audio_file_mono(&path)?;
+        assert_eq!(read, expected_stereo, "padded: {}", padded);
+        let (segment, _) = read_audio_segment(&path, 0.25, Some(0.375))?;
+        assert_eq!(segment, expected_stereo[4000..6000]);
+    }
+
+    // Truncated padded data is recovered in lenient mode only
+    let bytes = std::fs::read(test_dir.join("stereo_true.wav"))?;
+    let truncated = test_dir.join("truncated.wav");
+    std::fs::write(&truncated, &bytes[..bytes.len() - 804])?;
+    assert!(read_audio_file_mono(&truncated).is_err());
+    let (lenient, _, report) = read_audio_file_mono_lenient(&truncated)?;
+    assert_eq!(lenient, expected_stereo[..8000 - 101]);
+    assert!(report.is_some());
+
+    // A full-scale sine reaches the top of the range
+    let path = test_dir.join("sine.wav");
+    create_test_wav(&path, 0.5, 16000, 2, 24)?;
+    let (read, _) = read_audio_file_mono(&path)?;
+    let peak = read.iter().fold(0.0f32, |m, &s| m.max(s.abs()));
+    assert!(peak > 0.999 && peak < 1.0, "peak {}", peak);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_multichannel_wav() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("surround.wav");
+
+    // 5.1 file where channel c holds ±(c + 1) * 1000, alternating in sign
+    let spec = hound::WavSpec {
+        channels: 6,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec)?;
+    for t in 0..1600 {
+        let sign = if t % 2 == 0 { 1 } else { -1 };
+        for c in 0..6 {
+            writer.write_sample((sign * (c + 1) * 1000) as i16)?;
+        }
+    }
+    writer.finalize()?;
+    let channel = |c: usize| (c + 1) as f64 * 1000.0 / 32768.0;
+
+    let sqrt_half = std::f64::consts::FRAC_1_SQRT_2;
+    let itu = (channel(0) + channel(1)) / 2.0
+        + sqrt_half * channel(2)
+        + sqrt_half * (channel(4) + channel(5)) / 2.0;
+    for (downmix, level) in [
+        (DownmixMode::Average, 3.5 * 1000.0 / 32768.0),
+        (DownmixMode::First, channel(0)),
+        (DownmixMode::Channel(3), channel(3)),
+        (DownmixMode::Itu775, itu),
+    ] {
+        let expected: Vec<f32> = (0..1600)
+            .map(|t| if t % 2 == 0 { level } else { -level } as f32)
+            .collect();
+        let bytes = std::fs::read(&path)?;
+        let (samples, sr) = read_audio_mono_from_reader(&bytes[..], downmix)?;
+        assert_eq!(sr, 16000);
+        assert_eq!(samples.len(), expected.len());
+        for (s, e) in samples.iter().zip(&expected) {
+            assert!((s - e).abs() < 1e-6, "{:?}: {} != {}", downmix, s, e);
+        }
+
+        // Segments, chunks and lenient decoding downmix the same way
+        let (segment, _) =
+            read_audio_segment_from_reader(Cursor::new(&bytes), 0.025, Some(0.05), downmix)?;
+        assert_eq!(segment, samples[400..800]);
+        let mut chunks = MonoChunks::open(&path)?.with_downmix(downmix)?;
+        assert_eq!(chunks.next_chunk(1000)?, samples[..1000]);
+        let (lenient, _, report) =
+            read_audio_mono_lenient_from_reader(Cursor::new(&bytes), downmix)?;
+        assert_eq!(lenient, samples);
+        assert!(report.is_none());
+    }
+
+    // Averaging is the default
+    let (samples, _) = read_audio_file_mono(&path)?;
+    assert!((samples[0] - 3.5 * 1000.0 / 32768.0).abs() < 1e-6);
+
+    // Channels that do not exist cannot be selected
+    let stereo = test_dir.join("stereo.wav");
+    create_test_wav(&stereo, 0.1, 16000, 2, 16)?;
+    for downmix in [DownmixMode::Channel(2), DownmixMode::Itu775] {
+        let err = MonoChunks::open(&stereo)?
+            .with_downmix(downmix)
+            .err()
+            .unwrap();
+        assert!(matches!(err, SpectrsError::InvalidParams(_)), "{:?}", err);
+    }
+    assert!(
+        MonoChunks::open(&path)?
+            .with_downmix(DownmixMode::Channel(6))
+            .is_err()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_audio_reader() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("stereo.wav");
+    create_test_wav(&path, 1.0, 16000, 2, 16)?;
+    let (expected, _) = read_audio_file_mono(&path)?;
+
+    // Metadata is available before decoding anything
+    let mut reader = AudioReader::open(&path)?;
+    assert_eq!(reader.sample_rate(), 16000);
+    assert_eq!(reader.channels(), 2);
+    assert_eq!(reader.n_frames(), 16000);
+    assert_eq!(reader.duration(), 1.0);
+    assert_eq!(reader.position(), 0.0);
+
+    // Only the requested frames are decoded, from wherever the reader was moved to
+    assert_eq!(reader.read_frames(100)?, expected[..100]);
+    reader.seek(0.5)?;
+    assert_eq!(reader.position(), 0.5);
+    assert_eq!(reader.read_frames(1000)?, expected[8000..9000]);
+    assert_eq!(reader.remaining_frames(), 7000);
+    reader.seek(0.25)?;
+    assert_eq!(reader.read_frames(10)?, expected[4000..4010]);
+
+    // Reading stops at the end of the stream, including after seeking past it
+    reader.seek(0.9)?;
+    assert_eq!(reader.read_frames(5000)?, expected[14400..]);
+    assert!(reader.read_frames(10)?.is_empty());
+    reader.seek(5.0)?;
+    assert!(reader.read_frames(10)?.is_empty());
+    assert!(matches!(
+        reader.seek(-1.0),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+
+    // Seeking deep into a long stream lands on the exact frame: 7 minutes at 48 kHz hold more
+    // frames than f32 counts exactly
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let n_frames = 420 * 48000;
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec)?;
+    let mut samples = writer.get_i16_writer(n_frames);
+    for _ in 0..n_frames {
+        samples.write_sample(0i16);
+    }
+    samples.flush()?;
+    writer.finalize()?;
+    let mut reader = AudioReader::new(Cursor::new(bytes.into_inner()))?;
+    for seconds in [350.000_02f32, 371.111_1, 399.999_97, 419.123_4] {
+        reader.seek(seconds)?;
+        let frame = (seconds as f64 * 48000.0).round() as usize;
+        assert_eq!(
+            reader.remaining_frames(),
+            n_frames as usize - frame,
+            "{}s",
+            seconds
+        );
+    }
+
+    // Hand-decoded layouts (64-bit float) seek the same way
+    let path = test_dir.join("float64.wav");
+    let samples = create_float_test_wav(&path, 0.5, 16000, 1, 64)?;
+    let mut reader = AudioReader::new(Cursor::new(std::fs::read(&path)?))?;
+    reader.seek(0.125)?;
+    let expected: Vec<f32> = samples[2000..2100].iter().map(|&s| s as f32).collect();
+    assert_eq!(reader.read_frames(100)?, expected);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "opus"))]
+#[test]
+fn test_write_audio_file_opus_requires_feature() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("tone.opus");
+    let result = write_audio_file(&path, &[0.0; 960], 48000, WavFormat::Opus);
+    assert!(matches!(result, Err(SpectrsError::UnsupportedFormat(_))));
+    assert!(!path.exists());
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_audio_file_clips_integer_formats() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("clipped.wav");
+
+    write_audio_file(&path, &[2.0, -2.0, 0.0], 8000, WavFormat::Int16)?;
+
+    let mut reader = hound::WavReader::open(&path)?;
+    let read: Vec<i16> = reader.samples::<i16>().collect::<Result<_, _>>()?;
+    assert_eq!(read, vec![i16::MAX, i16::MIN, 0]);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_resample_quality_presets() -> Result<()> {
+    let original_sr = 44100;
+    let target_sr = 16000;
+    let num_samples = 44100;
+
+    // 1 kHz tone, well below the new Nyquist frequency
+    let samples: Vec<f32> = (0..num_samples)
+        .map(|t| (t as f32 * 1000.0 * 2.0 * std::f32::consts::PI / original_sr as f32).sin())
+        .collect();
+
+    for quality in [ResampleQuality::Medium, ResampleQuality::Best] {
+        let resampled = resample_with_quality(samples.clone(), original_sr, target_sr, quality)?;
+
+        // Chunked resamplers produce exactly the expected length
+        assert_eq!(resampled.len(), 16000, "{:?}", quality);
+
+        // Level of the tone is preserved (RMS of a unit sine is 1/sqrt(2))
+        let middle = &resampled[1000..15000];
+        let rms = (middle.iter().map(|v| v * v).sum::<f32>() / middle.len() as f32).sqrt();
+        assert!(
+            (rms - 0.5f32.sqrt()).abs() < 0.01,
+            "{:?}: rms {}",
+            quality,
+            rms
+        );
+
+        // Frequency is preserved: a 1 kHz tone crosses zero 2000 times per second
+        let crossings = middle
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        let expected = 2000.0 * middle.len() as f32 / target_sr as f32;
+        assert!((crossings as f32 - expected).abs() < 5.0, "{:?}", quality);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_resample_short_input() -> Result<()> {
+    // Shorter than a single resampler chunk
+    let samples = vec![0.1f32; 300];
+    let resampled = resample_with_quality(samples, 48000, 16000, ResampleQuality::Best)?;
+    assert_eq!(resampled.len(), 100);
+    Ok(())
+}
+
+#[test]
+fn test_read_lenient_clean_file() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("clean.wav");
+
+    create_test_wav(&audio_path, 0.5, 16000, 2, 16)?;
+
+    let (strict, sr) = read_audio_file_mono(&audio_path)?;
+    let (lenient, lenient_sr, report) = read_audio_file_mono_lenient(&audio_path)?;
+
+    // Clean files decode exactly as in strict mode
+    assert!(report.is_none());
+    assert_eq!(sr, lenient_sr);
+    assert_eq!(strict, lenient);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_lenient_truncated_data() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("truncated.wav");
+
+    // 1s of 16-bit mono audio, with the last 0.25s (plus half a sample) cut off
+    create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+    let bytes = std::fs::read(&audio_path)?;
+    std::fs::write(&audio_path, &bytes[..bytes.len() - 4000 * 2 - 1])?;
+
+    assert!(read_audio_file_mono(&audio_path).is_err());
+
+    let (samples, sr, report) = read_audio_file_mono_lenient(&audio_path)?;
+    let report = report.expect("truncation should be reported");
+
+    assert_eq!(sr, 16000);
+    assert_eq!(samples.len(), 11999);
+    assert_eq!(report.recovered_frames, 11999);
+    assert_eq!(report.declared_frames, Some(16000));
+    assert!((report.recovered_duration() - 0.75).abs() < 1e-3);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_lenient_wrong_header_length() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("bad_header.wav");
+
+    create_test_wav(&audio_path, 0.5, 16000, 2, 16)?;
+    let (expected, _) = read_audio_file_mono(&audio_path)?;
+
+    // Corrupt the data chunk length so it is no multiple of the frame size and far too large
+    let mut bytes = std::fs::read(&audio_path)?;
+    let data_pos = bytes
+        .windows(4)
+        .position(|w| w == b"data")
+        .expect("data chunk");
+    bytes[data_pos + 4..data_pos + 8].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+    std::fs::write(&audio_path, &bytes)?;
+
+    assert!(read_audio_file_mono(&audio_path).is_err());
+
+    let (samples, sr, report) = read_audio_file_mono_lenient(&audio_path)?;
+    assert!(report.is_some());
+    assert_eq!(sr, 16000);
+    assert_eq!(samples, expected);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_errors_are_classified() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+
+    // Not a WAV file at all: decoding fails and the hound error is kept as the source
+    let err = read_audio_mono_from_reader(
+        Cursor::new(b"not a wav file".to_vec()),
+        DownmixMode::Average,
+    )
+    .unwrap_err();
+    assert!(matches!(err, SpectrsError::DecodeError { .. }), "{:?}", err);
+    assert!(err.source().is_some());
+
+    // A valid WAV file whose channels do not fit the requested downmix
+    let audio_path = test_dir.join("quad.wav");
+    create_test_wav(&audio_path, 0.1, 16000, 4, 16)?;
+    let bytes = std::fs::read(&audio_path)?;
+    let err = read_audio_mono_from_reader(&bytes[..], DownmixMode::Itu775).unwrap_err();
+    assert!(matches!(err, SpectrsError::InvalidParams(_)), "{:?}", err);
+
+    // A fmt chunk declaring gigabytes fails to decode instead of sizing an allocation
+    let mut oversized = b"RIFF\x24\x00\x00\x00WAVEfmt \xf0\xff\xff\xff".to_vec();
+    oversized.extend([1, 0, 1, 0, 0x80, 0x3e, 0, 0, 0, 0x7d, 0, 0, 2, 0, 16, 0]);
+    let err = read_audio_mono_from_reader(Cursor::new(oversized.clone()), DownmixMode::Average)
+        .unwrap_err();
+    assert!(matches!(err, SpectrsError::DecodeError { .. }), "{:?}", err);
+    assert!(read_audio_mono_from_reader(&oversized[..], DownmixMode::Average).is_err());
+    assert!(
+        read_audio_mono_lenient_from_reader(Cursor::new(oversized), DownmixMode::Average).is_err()
+    );
+
+    // Missing file
+    let err = read_audio_file_mono(&test_dir.join("missing.wav")).unwrap_err();
+    assert!(matches!(err, SpectrsError::Io { .. }), "{:?}", err);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_raw_pcm() -> Result<()> {
+    // 16-bit mono: same values as the WAV reader
+    let bytes: Vec<u8> = [0i16, 16384, -32768, 32767]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(bytes),
+        PcmFormat::S16le,
+        1,
+        DownmixMode::Average,
+    )?;
+    assert_eq!(samples, vec![0.0, 0.5, -1.0, 32767.0 / 32768.0]);
+
+    // 24-bit stereo is averaged to mono, negative values are sign-extended
+    let bytes = vec![
+        0x00, 0x00, 0x40, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x40, 0x00, 0x00, 0x40,
+    ];
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(bytes),
+        PcmFormat::S24le,
+        2,
+        DownmixMode::Average,
+    )?;
+    assert_eq!(samples, vec![0.0, 0.5]);
+
+    // Unsigned 8-bit is centered on 128
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(vec![128, 192, 0]),
+        PcmFormat::U8,
+        1,
+        DownmixMode::Average,
+    )?;
+    assert_eq!(samples, vec![0.0, 0.5, -1.0]);
+
+    // Float samples are kept as is; the incomplete trailing frame is dropped
+    let mut bytes: Vec<u8> = [0.25f32, -0.75]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    bytes.extend_from_slice(&[0, 0]);
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(bytes),
+        PcmFormat::F32le,
+        1,
+        DownmixMode::Average,
+    )?;
+    assert_eq!(samples, vec![0.25, -0.75]);
+
+    // Any number of channels is downmixed like WAV audio, float samples included
+    let bytes: Vec<u8> = [0.3f32, 0.6, 0.9, -0.3, -0.6, -0.9]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(bytes.clone()),
+        PcmFormat::F32le,
+        3,
+        DownmixMode::Channel(2),
+    )?;
+    assert_eq!(samples, vec![0.9, -0.9]);
+    let samples = read_raw_pcm_from_reader(
+        Cursor::new(bytes),
+        PcmFormat::F32le,
+        3,
+        DownmixMode::Average,
+    )?;
+    assert_eq!(samples.len(), 2);
+    assert!((samples[0] - 0.6).abs() < 1e-6 && (samples[1] + 0.6).abs() < 1e-6);
+
+    // Missing channels are rejected
+    let err = read_raw_pcm_from_reader(
+        Cursor::new(vec![0; 12]),
+        PcmFormat::S16le,
+        3,
+        DownmixMode::Channel(3),
+    )
+    .unwrap_err();
+    assert!(matches!(err, SpectrsError::InvalidParams(_)));
+    assert!(
+        read_raw_pcm_from_reader(
+            Cursor::new(vec![0; 12]),
+            PcmFormat::S16le,
+            0,
+            DownmixMode::Average
+        )
+        .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_read_raw_pcm_file() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let raw_path = test_dir.join("capture.raw");
+
+    let bytes: Vec<u8> = [0.5f32, -0.5, 0.25]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    std::fs::write(&raw_path, bytes)?;
+
+    let (samples, sr) = read_raw_pcm(&raw_path, 8000, PcmFormat::F32le, 1, DownmixMode::Average)?;
+    assert_eq!(sr, 8000);
+    assert_eq!(samples, vec![0.5, -0.5, 0.25]);
+
+    // The sample rate cannot be guessed
+    let err = read_raw_pcm(&raw_path, 0, PcmFormat::F32le, 1, DownmixMode::Average).unwrap_err();
+    assert!(matches!(err, SpectrsError::InvalidParams(_)));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
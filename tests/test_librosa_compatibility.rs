@@ -1,9 +1,10 @@
 mod common;
 
 use anyhow::{Context, Result};
-use common::{cleanup_test_dir, create_complex_test_wav, create_test_wav, setup_test_dir};
-use serde_json::Value;
+use common::{cleanup_test_dir, create_complex_test_wav, create_test_wav, setup_temp_test_dir};
 use spectrs::io::audio::read_audio_file_mono;
+use spectrs::io::export::load_spectrogram_json;
+use spectrs::spectrogram::compare::Metric;
 use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
 use spectrs::spectrogram::stft::{SpectrogramType, par_compute_spectrogram};
 use std::fs;
@@ -48,22 +49,14 @@ fn run_python_script(script_path: &str, args: &[&str]) -> Result<()> {
 fn compare_with_librosa(
     spectrs_json: &str,
     librosa_json: &str,
-    comparison_json: &str,
     test_name: &str,
 ) -> Result<(f32, f32, bool)> {
-    // Run comparison script
-    run_python_script(
-        "tests/benchmark/compare_spectrograms.py",
-        &[spectrs_json, librosa_json, comparison_json],
-    )?;
-
-    // Load comparison results
-    let results_str = fs::read_to_string(comparison_json)?;
-    let results: Value = serde_json::from_str(&results_str)?;
-
-    let correlation = results["correlation"].as_f64().unwrap() as f32;
-    let relative_error = results["relative_error"].as_f64().unwrap() as f32;
-    let normalized_rmse = results["normalized_rmse"].as_f64().unwrap_or(0.0) as f32;
+    // Same metrics as `spectrs compare`, over the common shape
+    let spectrs_spec = load_spectrogram_json(std::path::Path::new(spectrs_json))?;
+    let librosa_spec = load_spectrogram_json(std::path::Path::new(librosa_json))?;
+    let correlation = Metric::Correlation.compute(&spectrs_spec, &librosa_spec) as f32;
+    let relative_error = Metric::RelativeError.compute(&spectrs_spec, &librosa_spec) as f32;
+    let normalized_rmse = Metric::NormalizedRmse.compute(&spectrs_spec, &librosa_spec) as f32;
 
     // Check if test passes
     let passes = correlation >= CORRELATION_THRESHOLD && relative_error <= RELATIVE_ERROR_THRESHOLD;
@@ -108,8 +101,9 @@ fn compare_with_librosa(
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_stft_compatibility_basic() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_stft.wav");
 
     // Create test file
@@ -148,11 +142,9 @@ fn test_stft_compatibility_basic() -> Result<()> {
     )?;
 
     // Compare
-    let comparison_json = test_dir.join("comparison.json");
     let (correlation, relative_error, passes) = compare_with_librosa(
         spectrs_json.to_str().unwrap(),
         librosa_json.to_str().unwrap(),
-        comparison_json.to_str().unwrap(),
         "stft_basic",
     )?;
 
@@ -174,8 +166,9 @@ fn test_stft_compatibility_basic() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_stft_compatibility_different_fft_sizes() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_fft.wav");
 
     create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
@@ -225,11 +218,9 @@ fn test_stft_compatibility_different_fft_sizes() -> Result<()> {
         )?;
 
         // Compare
-        let comparison_json = test_dir.join(format!("comparison_{}.json", n_fft));
         let (correlation, relative_error, passes) = compare_with_librosa(
             spectrs_json.to_str().unwrap(),
             librosa_json.to_str().unwrap(),
-            comparison_json.to_str().unwrap(),
             &format!("stft_fft{}", n_fft),
         )?;
 
@@ -253,8 +244,9 @@ fn test_stft_compatibility_different_fft_sizes() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_mel_compatibility_htk() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_mel.wav");
 
     create_test_wav(&audio_path, 2.0, 16000, 1, 16)?;
@@ -296,11 +288,9 @@ fn test_mel_compatibility_htk() -> Result<()> {
     )?;
 
     // Compare
-    let comparison_json = test_dir.join("comparison_mel.json");
     let (correlation, relative_error, passes) = compare_with_librosa(
         spectrs_json.to_str().unwrap(),
         librosa_json.to_str().unwrap(),
-        comparison_json.to_str().unwrap(),
         "mel_htk",
     )?;
 
@@ -320,8 +310,9 @@ fn test_mel_compatibility_htk() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_mel_compatibility_different_n_mels() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_nmels.wav");
 
     create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
@@ -366,11 +357,9 @@ fn test_mel_compatibility_different_n_mels() -> Result<()> {
         )?;
 
         // Compare
-        let comparison_json = test_dir.join(format!("comparison_mel_{}.json", n_mels));
         let (correlation, relative_error, passes) = compare_with_librosa(
             spectrs_json.to_str().unwrap(),
             librosa_json.to_str().unwrap(),
-            comparison_json.to_str().unwrap(),
             &format!("mel_nmels{}", n_mels),
         )?;
 
@@ -393,8 +382,9 @@ fn test_mel_compatibility_different_n_mels() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_mel_compatibility_slaney() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_slaney.wav");
 
     create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
@@ -436,11 +426,9 @@ fn test_mel_compatibility_slaney() -> Result<()> {
     )?;
 
     // Compare
-    let comparison_json = test_dir.join("comparison_mel_slaney.json");
     let (correlation, relative_error, passes) = compare_with_librosa(
         spectrs_json.to_str().unwrap(),
         librosa_json.to_str().unwrap(),
-        comparison_json.to_str().unwrap(),
         "mel_slaney",
     )?;
 
@@ -460,8 +448,9 @@ fn test_mel_compatibility_slaney() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_compatibility_complex_signal() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
     let audio_path = test_dir.join("test_librosa_complex.wav");
 
     create_complex_test_wav(&audio_path, 2.0, 16000, 1, 16)?;
@@ -503,11 +492,9 @@ fn test_compatibility_complex_signal() -> Result<()> {
     )?;
 
     // Compare
-    let comparison_json = test_dir.join("comparison_complex.json");
     let (correlation, relative_error, passes) = compare_with_librosa(
         spectrs_json.to_str().unwrap(),
         librosa_json.to_str().unwrap(),
-        comparison_json.to_str().unwrap(),
         "complex_signal",
     )?;
 
@@ -527,8 +514,9 @@ fn test_compatibility_complex_signal() -> Result<()> {
 }
 
 #[test]
+#[ignore = "requires uv"]
 fn test_compatibility_different_sample_rates() -> Result<()> {
-    let test_dir = setup_test_dir()?;
+    let test_dir = setup_temp_test_dir()?;
 
     let sample_rates = vec![8000, 16000, 22050];
 
@@ -573,11 +561,9 @@ fn test_compatibility_different_sample_rates() -> Result<()> {
         )?;
 
         // Compare
-        let comparison_json = test_dir.join(format!("comparison_sr_{}.json", sr));
         let (correlation, relative_error, passes) = compare_with_librosa(
             spectrs_json.to_str().unwrap(),
             librosa_json.to_str().unwrap(),
-            comparison_json.to_str().unwrap(),
             &format!("sr{}", sr),
         )?;
 
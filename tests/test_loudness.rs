@@ -0,0 +1,101 @@
+mod common;
+
+use common::tone;
+use spectrs::features::loudness::{Loudness, LoudnessMeter};
+
+#[test]
+fn test_integrated_loudness_of_a_tone() {
+    // A full-scale 997 Hz sine measures -3.01 LUFS (BS.1770-4)
+    for sr in [44100, 48000] {
+        for amplitude in [1.0, 0.1] {
+            let loudness = Loudness::from_samples(&tone(997.0, amplitude, 5.0, sr), sr);
+            let expected = -3.01 + 20.0 * amplitude.log10();
+            assert!(
+                (loudness.integrated_lufs - expected).abs() < 0.05,
+                "{} Hz, {}: {} LUFS",
+                sr,
+                amplitude,
+                loudness.integrated_lufs
+            );
+        }
+    }
+}
+
+#[test]
+fn test_gating() {
+    let sr = 48000;
+    let loud = Loudness::from_samples(&tone(997.0, 0.5, 10.0, sr), sr).integrated_lufs;
+
+    // Silence is below the absolute gate (only the blocks straddling the end of the tone count)
+    let mut audio = tone(997.0, 0.5, 10.0, sr);
+    audio.extend(vec![0.0; 10 * sr as usize]);
+    assert!((Loudness::from_samples(&audio, sr).integrated_lufs - loud).abs() < 0.1);
+
+    // A passage 30 dB quieter is below the relative gate
+    let mut audio = tone(997.0, 0.5, 10.0, sr);
+    audio.extend(tone(997.0, 0.5 * 10f32.powf(-1.5), 10.0, sr));
+    assert!((Loudness::from_samples(&audio, sr).integrated_lufs - loud).abs() < 0.1);
+
+    let silence = Loudness::from_samples(&vec![0.0; sr as usize], sr);
+    assert_eq!(silence.integrated_lufs, f32::NEG_INFINITY);
+    assert_eq!(silence.sample_peak_dbfs, f32::NEG_INFINITY);
+}
+
+#[test]
+fn test_short_term_loudness() {
+    let sr = 48000;
+    // A 3 s window every 100 ms
+    let mut audio = tone(997.0, 0.1, 3.0, sr);
+    audio.extend(tone(997.0, 1.0, 2.0, sr));
+    let loudness = Loudness::from_samples(&audio, sr);
+    assert_eq!(loudness.short_term_lufs.len(), 21);
+    assert!((loudness.short_term_lufs[0] + 23.01).abs() < 0.05);
+    assert!(loudness.short_term_lufs.windows(2).all(|w| w[1] >= w[0]));
+    assert_eq!(
+        loudness.short_term_max_lufs(),
+        *loudness.short_term_lufs.last().unwrap()
+    );
+
+    let short = Loudness::from_samples(&tone(997.0, 1.0, 2.0, sr), sr);
+    assert!(short.short_term_lufs.is_empty());
+    assert_eq!(short.short_term_max_lufs(), f32::NEG_INFINITY);
+    assert_eq!(short.values().len(), Loudness::COLUMNS.len());
+}
+
+#[test]
+fn test_true_peak() {
+    // Samples of a sine at a quarter of the sample rate, halfway between its peaks, reach
+    // -3 dB of the peak
+    let sr = 48000;
+    let audio: Vec<f32> = (0..sr)
+        .map(|i| (std::f32::consts::FRAC_PI_2 * (i % 4) as f32 + std::f32::consts::FRAC_PI_4).sin())
+        .collect();
+    let loudness = Loudness::from_samples(&audio, sr);
+    assert!((loudness.sample_peak_dbfs + 3.01).abs() < 0.01);
+    assert!(
+        loudness.true_peak_dbtp.abs() < 0.3,
+        "{} dBTP",
+        loudness.true_peak_dbtp
+    );
+    // True peaks are never below sample peaks
+    let loudness = Loudness::from_samples(&tone(100.0, 0.5, 1.0, sr), sr);
+    assert!(loudness.true_peak_dbtp >= loudness.sample_peak_dbfs);
+    assert!((loudness.true_peak_dbtp - 20.0 * 0.5f32.log10()).abs() < 0.01);
+}
+
+#[test]
+fn test_meter_in_chunks() {
+    let sr = 44100;
+    let mut audio = tone(440.0, 0.3, 4.0, sr);
+    audio.extend(tone(3000.0, 0.8, 1.0, sr));
+    let mut meter = LoudnessMeter::new(sr);
+    for chunk in audio.chunks(1000) {
+        meter.push(chunk);
+    }
+    let chunked = meter.finish();
+    let whole = Loudness::from_samples(&audio, sr);
+    assert_eq!(chunked.short_term_lufs.len(), whole.short_term_lufs.len());
+    assert!((chunked.integrated_lufs - whole.integrated_lufs).abs() < 1e-4);
+    assert_eq!(chunked.true_peak_dbtp, whole.true_peak_dbtp);
+    assert_eq!(chunked.sample_peak_dbfs, whole.sample_peak_dbfs);
+}
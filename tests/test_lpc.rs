@@ -0,0 +1,158 @@
+mod common;
+
+use anyhow::Result;
+use common::noise;
+use spectrs::error::SpectrsError;
+use spectrs::features::lpc::{LpcMethod, lpc, lpc_envelope, par_lpc_envelope};
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+const SR: u32 = 16000;
+const N_FFT: usize = 1024;
+
+/// White noise through the resonator x[n] = 1.3 x[n-1] - 0.8 x[n-2] + e[n]
+fn resonance(n: usize) -> Vec<f32> {
+    let mut audio: Vec<f32> = noise(n);
+    for i in 2..n {
+        audio[i] += 1.3 * audio[i - 1] - 0.8 * audio[i - 2];
+    }
+    audio
+}
+
+/// Vowel-like pulse train: a decaying 700 Hz resonance repeated every 128 samples (125 Hz)
+fn vowel(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|t| {
+            let phase = (t % 128) as f32;
+            (-phase / 20.0).exp() * (2.0 * std::f32::consts::PI * 700.0 * phase / SR as f32).cos()
+        })
+        .collect()
+}
+
+#[test]
+fn test_lpc_coefficients() -> Result<()> {
+    // Both methods recover the resonator from a long frame, with the error of the noise (1/3)
+    let audio = resonance(20000);
+    for method in [LpcMethod::Autocorrelation, LpcMethod::Burg] {
+        let model = lpc(&audio, 2, method)?;
+        assert_eq!(model.order(), 2);
+        assert_eq!(model.coefficients[0], 1.0);
+        assert!((model.coefficients[1] + 1.3).abs() < 0.02, "{:?}", model);
+        assert!((model.coefficients[2] - 0.8).abs() < 0.02, "{:?}", model);
+        assert!((model.error - 1.0 / 3.0).abs() < 0.02, "{:?}", model);
+
+        // The envelope peaks at the resonance, acos(1.3 / (2 sqrt(0.8))) = 0.1166 turns
+        let envelope = model.envelope(N_FFT);
+        assert_eq!(envelope.len(), N_FFT / 2 + 1);
+        let peak = (0..envelope.len())
+            .max_by(|&a, &b| envelope[a].total_cmp(&envelope[b]))
+            .unwrap();
+        assert!(
+            (peak as f32 / N_FFT as f32 - 0.1166).abs() < 0.005,
+            "{}",
+            peak
+        );
+    }
+
+    // Silence: no prediction
+    let model = lpc(&[0.0; 64], 4, LpcMethod::Autocorrelation)?;
+    assert_eq!(model.coefficients, vec![1.0, 0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(model.error, 0.0);
+
+    assert!(matches!(
+        lpc(&audio[..8], 8, LpcMethod::Burg),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_lpc_envelope() -> Result<()> {
+    let audio = vowel(SR as usize);
+    let power = compute_spectrogram(&audio, N_FFT, 256, N_FFT, true, SpectrogramType::Power);
+    let envelope = lpc_envelope(&power, SpectrogramType::Power, N_FFT, 18)?;
+    assert_eq!(envelope.len(), power.len());
+    assert_eq!(envelope[0].len(), power[0].len());
+    assert_eq!(
+        par_lpc_envelope(&power, SpectrogramType::Power, N_FFT, 18)?,
+        envelope
+    );
+
+    let t = power[0].len() / 2;
+    let column = |spec: &[Vec<f32>]| spec.iter().map(|row| row[t]).collect::<Vec<f32>>();
+    let (spectrum, smooth) = (column(&power), column(&envelope));
+    // The envelope peaks at the formant, with the power of the frame
+    let peak = (0..smooth.len())
+        .max_by(|&a, &b| smooth[a].total_cmp(&smooth[b]))
+        .unwrap();
+    assert!(
+        (peak as f32 * SR as f32 / N_FFT as f32 - 700.0).abs() < 50.0,
+        "{}",
+        peak
+    );
+    let total = |values: &[f32]| values.iter().map(|&v| v as f64).sum::<f64>();
+    assert!((total(&smooth) / total(&spectrum) - 1.0).abs() < 0.05);
+    // Without the harmonics: the spectrum drops by orders of magnitude between them (every 8
+    // bins), the envelope does not
+    let ripple = |values: &[f32]| {
+        values[16..160]
+            .windows(9)
+            .map(|w| {
+                w.iter().fold(0.0f32, |a, &b| a.max(b)) / w.iter().fold(f32::MAX, |a, &b| a.min(b))
+            })
+            .fold(0.0f32, f32::max)
+    };
+    assert!(ripple(&spectrum) > 100.0);
+    assert!(ripple(&smooth) < 10.0);
+
+    // Magnitudes give the square root of the envelope of the powers
+    let magnitude: Vec<Vec<f32>> = power
+        .iter()
+        .map(|row| row.iter().map(|v| v.sqrt()).collect())
+        .collect();
+    let from_magnitude = lpc_envelope(&magnitude, SpectrogramType::Magnitude, N_FFT, 18)?;
+    for (m, p) in from_magnitude
+        .iter()
+        .flatten()
+        .zip(envelope.iter().flatten())
+    {
+        assert!((m * m / p - 1.0).abs() < 1e-3);
+    }
+
+    assert!(lpc_envelope(&power, SpectrogramType::Power, N_FFT, N_FFT / 2).is_err());
+    assert!(lpc_envelope(&power, SpectrogramType::Power, 2 * N_FFT, 18).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_lpc_order() -> Result<()> {
+    let audio = vowel(SR as usize);
+    let builder = || {
+        SpectrogramPipeline::builder()
+            .sample_rate(SR)
+            .n_fft(N_FFT)
+            .hop_length(256)
+    };
+    let linear = builder().build()?.process_samples(&audio)?;
+    let envelope = builder()
+        .lpc_order(Some(18))
+        .build()?
+        .process_samples(&audio)?;
+    assert_eq!(
+        envelope,
+        lpc_envelope(&linear, SpectrogramType::Power, N_FFT, 18)?
+    );
+
+    // Banded like spectra; orders beyond half the FFT rejected
+    let mel = builder()
+        .lpc_order(Some(18))
+        .mel(40)
+        .build()?
+        .process_samples(&audio)?;
+    assert_eq!(mel.len(), 40);
+    assert!(matches!(
+        builder().lpc_order(Some(N_FFT / 2)).build(),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use spectrs::io::manifest::{
+    ManifestEntry, OutputRecord, read_manifest_csv, read_manifest_jsonl, write_output_manifest,
+};
+
+#[test]
+fn test_read_manifest_csv() -> Result<()> {
+    let csv = "\
+id,path,label,start,end,output
+1,a.wav,dog,0.5,1.5,
+2,\"b, with comma.wav\",\"say \"\"hi\"\"\",,,b_out
+
+3,c.wav,,,2,
+";
+    let entries = read_manifest_csv(csv.as_bytes())?;
+    assert_eq!(
+        entries,
+        vec![
+            ManifestEntry {
+                path: "a.wav".to_string(),
+                start: Some(0.5),
+                end: Some(1.5),
+                label: Some("dog".to_string()),
+                output: None,
+            },
+            ManifestEntry {
+                path: "b, with comma.wav".to_string(),
+                label: Some("say \"hi\"".to_string()),
+                output: Some("b_out".to_string()),
+                ..Default::default()
+            },
+            ManifestEntry {
+                path: "c.wav".to_string(),
+                end: Some(2.0),
+                ..Default::default()
+            },
+        ]
+    );
+
+    // Only the path column is required
+    assert_eq!(read_manifest_csv("path\nx.wav\n".as_bytes())?.len(), 1);
+    assert!(read_manifest_csv("file\nx.wav\n".as_bytes()).is_err());
+    assert!(read_manifest_csv("path,start\nx.wav,soon\n".as_bytes()).is_err());
+    assert!(read_manifest_csv("path,start,end\nx.wav,2,1\n".as_bytes()).is_err());
+    assert!(read_manifest_csv("path\n\"x.wav\n".as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_read_manifest_jsonl() -> Result<()> {
+    let jsonl = r#"{"path": "a.wav", "start": 1.0, "label": "dog"}
+
+{"path": "https://example.com/b.wav", "output": "b", "extra": 3}
+"#;
+    let entries = read_manifest_jsonl(jsonl.as_bytes())?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].start, Some(1.0));
+    assert_eq!(entries[0].label.as_deref(), Some("dog"));
+    assert_eq!(entries[1].output.as_deref(), Some("b"));
+
+    assert!(read_manifest_jsonl(r#"{"label": "dog"}"#.as_bytes()).is_err());
+    assert!(read_manifest_jsonl(r#"{"path": "a.wav", "start": -1}"#.as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_write_output_manifest() -> Result<()> {
+    let records = vec![
+        OutputRecord::new(
+            "data/a.wav",
+            Some("dog".to_string()),
+            (Some(0.5), None),
+            1.0,
+            vec!["out/a.png".to_string(), "out/a.npy".to_string()],
+        ),
+        OutputRecord::new("data/b.wav", None, (None, None), 2.0, vec![]),
+    ];
+    let mut buffer = Vec::new();
+    write_output_manifest(&records, &mut buffer)?;
+
+    let lines: Vec<&str> = std::str::from_utf8(&buffer)?.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let parsed: OutputRecord = serde_json::from_str(lines[0])?;
+    assert_eq!(parsed, records[0]);
+    assert_eq!(parsed.schema_version, spectrs::io::metadata::SCHEMA_VERSION);
+    Ok(())
+}
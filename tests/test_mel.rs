@@ -2,8 +2,16 @@ mod common;
 
 use anyhow::Result;
 use common::{cleanup_test_dir, create_complex_test_wav, create_test_wav, setup_test_dir};
+use spectrs::features::spectral::fft_frequencies;
 use spectrs::io::audio::read_audio_file_mono;
-use spectrs::spectrogram::mel::{MelScale, convert_to_mel, par_convert_to_mel};
+use spectrs::io::image::crop_frequency_band;
+use spectrs::spectrogram::filterbank::{
+    FilterBankType, FrequencyScale, band_frequencies, filter_bank,
+};
+use spectrs::spectrogram::mel::{
+    MelScale, convert_to_mel, mel_band_frequencies, mel_filter_bank, par_convert_to_mel,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
 use spectrs::spectrogram::stft::{SpectrogramType, par_compute_spectrogram};
 
 #[test]
@@ -222,7 +230,7 @@ fn test_convert_to_mel_from_file() -> Result<()> {
 
     // Verify dimensions
     assert_eq!(mel_spec.len(), n_mels);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -350,7 +358,7 @@ fn test_convert_to_mel_different_sample_rates() -> Result<()> {
 
         // Verify it worked
         assert_eq!(mel_spec.len(), n_mels);
-        assert!(mel_spec[0].len() > 0);
+        assert!(!mel_spec[0].is_empty());
     }
 
     cleanup_test_dir(&test_dir)?;
@@ -527,7 +535,7 @@ fn test_par_convert_to_mel_basic() -> Result<()> {
 
     // Check dimensions
     assert_eq!(mel_spec.len(), 40);
-    assert!(mel_spec[0].len() > 0);
+    assert!(!mel_spec[0].is_empty());
 
     // Check non-negativity
     for mel_bin in &mel_spec {
@@ -548,3 +556,128 @@ fn test_par_convert_to_mel_basic() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_convert_to_mel_f64() -> Result<()> {
+    let sr = 22050;
+    let samples: Vec<f64> = (0..sr as usize / 2)
+        .map(|t| (t as f64 * 1000.0 * 2.0 * std::f64::consts::PI / sr as f64).sin())
+        .collect();
+    let samples_f32: Vec<f32> = samples.iter().map(|&s| s as f32).collect();
+
+    let spec = par_compute_spectrogram(&samples, 1024, 256, 1024, true, SpectrogramType::Power);
+    let mel = convert_to_mel(&spec, sr, 1024, 64, None, None, MelScale::Slaney);
+    assert_eq!(
+        mel,
+        par_convert_to_mel(&spec, sr, 1024, 64, None, None, MelScale::Slaney)
+    );
+
+    // Same filters as in single precision, up to rounding
+    let spec_f32 =
+        par_compute_spectrogram(&samples_f32, 1024, 256, 1024, true, SpectrogramType::Power);
+    let mel_f32 = convert_to_mel(&spec_f32, sr, 1024, 64, None, None, MelScale::Slaney);
+    let peak = mel.iter().flatten().fold(0.0f64, |acc, &v| acc.max(v));
+    for (row, row_f32) in mel.iter().zip(&mel_f32) {
+        for (&v, &v_f32) in row.iter().zip(row_f32) {
+            assert!((v - v_f32 as f64).abs() < 1e-5 * peak);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_crop_frequency_band() -> Result<()> {
+    // Band centres are increasing, strictly inside [f_min, f_max]
+    let centres = mel_band_frequencies(16000, 40, Some(100.0), None, MelScale::Slaney);
+    assert_eq!(centres.len(), 40);
+    assert!(centres.windows(2).all(|w| w[0] < w[1]));
+    assert!(centres[0] > 100.0 && centres[39] < 8000.0);
+
+    // Linear rows of a 16 kHz, 512-point FFT are 31.25 Hz apart: 0..=4000 Hz keeps 129 rows
+    let frequencies = fft_frequencies(16000, 512);
+    let spec: Vec<Vec<f32>> = (0..frequencies.len()).map(|i| vec![i as f32; 3]).collect();
+    let cropped = crop_frequency_band(&spec, &frequencies, None, Some(4000.0))?;
+    assert_eq!(cropped.len(), 129);
+    assert_eq!(cropped[128], vec![128.0; 3]);
+
+    let cropped = crop_frequency_band(&spec, &frequencies, Some(1000.0), Some(2000.0))?;
+    assert_eq!(cropped.len(), 33);
+    assert_eq!(cropped[0][0], 32.0);
+
+    // An empty band or mismatched frequencies are errors
+    assert!(crop_frequency_band(&spec, &frequencies, Some(10.0), Some(20.0)).is_err());
+    assert!(crop_frequency_band(&spec, &frequencies[1..], None, None).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_bark_and_erb_filter_banks() -> Result<()> {
+    let params = SpectrogramParams::builder()
+        .n_fft(1024)
+        .win_length(1024)
+        .n_mels(Some(24))
+        .f_min(Some(20.0))
+        .build()?;
+    let (sr, bin_width) = (16000, 16000.0 / 1024.0);
+
+    // The mel scale keeps the librosa filters
+    let mel: Vec<Vec<f32>> = filter_bank(&params, sr).unwrap();
+    let librosa: Vec<Vec<f32>> =
+        mel_filter_bank(sr, 1024, 24, Some(20.0), None, MelScale::Slaney, true);
+    assert_eq!(mel, librosa);
+
+    for scale in [FrequencyScale::Bark, FrequencyScale::Erb] {
+        let params = SpectrogramParams {
+            frequency_scale: scale,
+            ..params
+        };
+        let filters: Vec<Vec<f32>> = filter_bank(&params, sr).unwrap();
+        let centres = band_frequencies(&params, sr);
+        assert_eq!(filters.len(), 24);
+        assert_ne!(filters, mel);
+
+        // Centres evenly spaced on the scale
+        let positions: Vec<f64> = centres
+            .iter()
+            .map(|&f| scale.from_hz(f as f64, MelScale::Slaney))
+            .collect();
+        let step = positions[1] - positions[0];
+        assert!(
+            positions
+                .windows(2)
+                .all(|w| (w[1] - w[0] - step).abs() < 1e-3)
+        );
+        for &f in &[100.0, 1000.0, 6000.0] {
+            let back = scale.to_hz(scale.from_hz(f, MelScale::Slaney), MelScale::Slaney);
+            assert!((back - f).abs() < 1e-6);
+        }
+
+        // Triangles peaking at their centres, with unit area (Slaney normalization)
+        for (filter, &centre) in filters.iter().zip(&centres).skip(2) {
+            let peak = filter
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .unwrap()
+                .0;
+            assert!((peak as f32 * bin_width - centre).abs() <= bin_width);
+            let area = filter.iter().sum::<f32>() * bin_width;
+            assert!((area - 1.0).abs() < 0.1, "area {}", area);
+        }
+    }
+
+    // Bark-scale bands need a number of bands, and gammatone filters have their own spacing
+    let bark = SpectrogramParams::builder()
+        .n_fft(1024)
+        .win_length(1024)
+        .frequency_scale(FrequencyScale::Bark);
+    assert!(bark.build().is_err());
+    assert!(
+        bark.n_mels(Some(24))
+            .filter_bank(FilterBankType::Gammatone)
+            .build()
+            .is_err()
+    );
+    Ok(())
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, setup_test_dir};
+use spectrs::io::export::OutputFormat;
+use spectrs::io::image::ImageTile;
+use spectrs::io::metadata::{
+    PlannedInput, ReportedInput, RunPlan, SCHEMA_VERSION, SpectrogramMetadata, TileIndex,
+    metadata_path, migrate, read_metadata, read_run_plan, read_tile_index, tile_index_path,
+    write_metadata, write_run_plan, write_tile_index,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn sample_metadata() -> Result<SpectrogramMetadata> {
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(400)
+        .n_mels(Some(64))
+        .build()?;
+    Ok(SpectrogramMetadata::new(
+        "audio/a.wav",
+        OutputFormat::Png,
+        16000,
+        params,
+        [64, 120],
+    ))
+}
+
+#[test]
+fn test_metadata_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = metadata_path(&test_dir.join("a.png"));
+    assert_eq!(path, test_dir.join("a.meta.json"));
+
+    let metadata = sample_metadata()?;
+    write_metadata(&path, &metadata)?;
+
+    // Stamped with the current schema and crate versions
+    let raw: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    assert_eq!(raw["schema_version"], SCHEMA_VERSION);
+    assert_eq!(raw["spectrs_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(raw["params"]["mel_scale"], "slaney");
+
+    assert_eq!(read_metadata(&path)?, metadata);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_metadata_migrates_unversioned_documents() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("old.meta.json");
+
+    // Written before outputs were versioned
+    let mut document = serde_json::to_value(sample_metadata()?)?;
+    let object = document.as_object_mut().unwrap();
+    object.remove("schema_version");
+    object.remove("spectrs_version");
+    fs::write(&path, serde_json::to_string(&document)?)?;
+
+    let metadata = read_metadata(&path)?;
+    assert_eq!(metadata.schema_version, SCHEMA_VERSION);
+    assert_eq!(metadata.spectrs_version, "unknown");
+    assert_eq!(metadata.shape, [64, 120]);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_metadata_rejects_newer_versions() -> Result<()> {
+    let mut document = serde_json::to_value(sample_metadata()?)?;
+    document["schema_version"] = serde_json::Value::from(SCHEMA_VERSION + 1);
+
+    let error = migrate(document).unwrap_err();
+    assert!(error.to_string().contains("newer"), "{}", error);
+
+    // Garbage versions and non-objects are rejected too
+    assert!(migrate(serde_json::json!({ "schema_version": "one" })).is_err());
+    assert!(migrate(serde_json::json!([1, 2, 3])).is_err());
+    assert!(read_metadata(Path::new("does-not-exist.meta.json")).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_tile_index_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = tile_index_path(&test_dir.join("a.png"));
+    assert_eq!(path, test_dir.join("a.tiles.json"));
+
+    let tiles = [
+        ImageTile {
+            path: PathBuf::from("out/a.tile0000.png"),
+            frames: 0..4096,
+        },
+        ImageTile {
+            path: PathBuf::from("out/a.tile0001.png"),
+            frames: 4096..5000,
+        },
+    ];
+    // 10 ms frames of a segment starting at 60 s
+    let index = TileIndex::new("audio/a.wav", 16000, 160, 60.0, &tiles);
+    assert_eq!(index.n_frames, 5000);
+    assert_eq!(index.tiles[1].file, "a.tile0001.png");
+    assert_eq!(index.tiles[1].start_frame, 4096);
+    assert!((index.tiles[1].start_time - 100.96).abs() < 1e-9);
+    assert!((index.tiles[1].end_time - 110.0).abs() < 1e-9);
+
+    write_tile_index(&path, &index)?;
+    let raw: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    assert_eq!(raw["schema_version"], SCHEMA_VERSION);
+    assert_eq!(read_tile_index(&path)?, index);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_run_plan_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("plan.json");
+
+    let planned = |input: &str, duration| PlannedInput {
+        input: input.to_string(),
+        label: None,
+        start: None,
+        end: None,
+        duration,
+        outputs: vec![input.replace(".wav", ".npy")],
+        params: None,
+    };
+    let plan = RunPlan::new(
+        sample_metadata()?.params,
+        Some(16000),
+        vec![OutputFormat::Npy],
+        vec![planned("a.wav", Some(1.5)), planned("b.wav", None)],
+        vec![ReportedInput {
+            input: "c.txt".to_string(),
+            reason: "not an audio file".to_string(),
+        }],
+        Vec::new(),
+    );
+    // Inputs of unknown duration are left out of the total
+    assert_eq!(plan.audio_duration(), 1.5);
+
+    write_run_plan(&path, &plan)?;
+    let raw: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    assert_eq!(raw["schema_version"], SCHEMA_VERSION);
+    assert_eq!(raw["inputs"][1]["duration"], serde_json::Value::Null);
+    assert_eq!(read_run_plan(&path)?, plan);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
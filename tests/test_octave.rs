@@ -0,0 +1,99 @@
+mod common;
+
+use anyhow::Result;
+use common::tone;
+use spectrs::error::SpectrsError;
+use spectrs::features::octave::{BandFraction, band_levels};
+
+#[test]
+fn test_band_frequencies() -> Result<()> {
+    let levels = band_levels(&[0.0; 4800], 48000, BandFraction::Third, 4800)?;
+    assert_eq!(levels.nominal_hz.len(), 30);
+    assert_eq!(levels.nominal_hz[0], 25.0);
+    assert_eq!(levels.nominal_hz[1], 31.5);
+    assert_eq!(levels.nominal_hz[29], 20000.0);
+    let index = levels
+        .nominal_hz
+        .iter()
+        .position(|&hz| hz == 1000.0)
+        .unwrap();
+    assert_eq!(levels.center_hz[index], 1000.0);
+    // Base-ten midbands: 10^0.1 apart
+    assert!((levels.center_hz[index + 1] / 1000.0 - 10f32.powf(0.1)).abs() < 1e-5);
+
+    let levels = band_levels(&[0.0; 4800], 48000, BandFraction::Octave, 4800)?;
+    assert_eq!(
+        levels.nominal_hz,
+        [
+            31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0
+        ]
+    );
+    // Bands reaching above 22.05 kHz are left out at 44.1 kHz: the 16 kHz octave band and the
+    // 20 kHz third-octave band
+    let levels = band_levels(&[0.0; 4410], 44100, BandFraction::Octave, 4410)?;
+    assert_eq!(levels.nominal_hz.last(), Some(&8000.0));
+    let levels = band_levels(&[0.0; 4410], 44100, BandFraction::Third, 4410)?;
+    assert_eq!(levels.nominal_hz.last(), Some(&16000.0));
+    Ok(())
+}
+
+#[test]
+fn test_band_levels_of_tones() -> Result<()> {
+    let sr = 48000;
+    // Tones at the midband of low (filtered after decimation), middle and high bands
+    for (fraction, nominal) in [
+        (BandFraction::Third, 50.0),
+        (BandFraction::Third, 1000.0),
+        (BandFraction::Third, 10000.0),
+        (BandFraction::Octave, 125.0),
+        (BandFraction::Octave, 8000.0),
+    ] {
+        let levels = band_levels(&tone(1.0, 1.0, 0.1, sr), sr, fraction, sr as usize / 10)?;
+        let index = levels
+            .nominal_hz
+            .iter()
+            .position(|&hz| hz == nominal)
+            .unwrap();
+        let center = levels.center_hz[index];
+        let audio = tone(center, 0.5, 3.0, sr);
+        let levels = band_levels(&audio, sr, fraction, sr as usize / 10)?;
+        assert_eq!(levels.times.len(), 30);
+        assert!((levels.times[1] - 0.1).abs() < 1e-6);
+
+        // Past the settling of the filters: a sine of amplitude 0.5 at -9.03 dB
+        let db = levels.levels_db();
+        for &level in &db[index][10..] {
+            assert!((level + 9.03).abs() < 0.2, "{} Hz: {} dB", nominal, level);
+        }
+        // The neighbouring bands are far below
+        for neighbour in [index - 1, index + 1] {
+            let level = db[neighbour][10..].iter().copied().fold(f32::MIN, f32::max);
+            assert!(
+                level < -9.03 - 15.0,
+                "{} Hz: {} dB next door",
+                nominal,
+                level
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_band_level_frames() -> Result<()> {
+    // A last frame shorter than the others, silence far below any tone
+    let levels = band_levels(&vec![0.0; 25000], 16000, BandFraction::Octave, 10000)?;
+    assert_eq!(levels.times, [0.0, 0.625, 1.25]);
+    assert!(levels.mean_square.iter().all(|band| band.len() == 3));
+    assert!(levels.leq_db().iter().all(|&db| db <= -199.0));
+
+    assert!(matches!(
+        band_levels(&[0.0; 100], 16000, BandFraction::Third, 0),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    assert!(matches!(
+        band_levels(&[0.0; 100], 40, BandFraction::Octave, 10),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use spectrs::features::onset::{PeakPick, detect_onsets, onset_strength, pick_peaks};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+
+const SR: u32 = 16000;
+const N_FFT: usize = 512;
+const HOP: usize = 128;
+
+/// Silence with decaying 1 kHz tone bursts starting at `onsets` (seconds)
+fn bursts(onsets: &[f32], seconds: f32) -> Vec<f32> {
+    let mut audio = vec![0.0f32; (seconds * SR as f32) as usize];
+    for &onset in onsets {
+        let start = (onset * SR as f32) as usize;
+        for (i, s) in audio[start..].iter_mut().take(SR as usize / 5).enumerate() {
+            let t = i as f32 / SR as f32;
+            *s += (-t * 30.0).exp() * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+        }
+    }
+    audio
+}
+
+#[test]
+fn test_onsets_of_bursts() -> Result<()> {
+    let expected = [0.25, 0.8, 1.3];
+    let frame_rate = SR as f32 / HOP as f32;
+    for spectrogram_type in [SpectrogramType::Power, SpectrogramType::Magnitude] {
+        let spec = compute_spectrogram(
+            &bursts(&expected, 2.0),
+            N_FFT,
+            HOP,
+            N_FFT,
+            true,
+            spectrogram_type,
+        );
+        // Frames are timed at the center of their window
+        let time_offset = N_FFT as f32 / 2.0 / SR as f32;
+        let onsets = detect_onsets(
+            &spec,
+            spectrogram_type,
+            frame_rate,
+            time_offset,
+            &PeakPick::for_frame_rate(frame_rate),
+        )?;
+        assert_eq!(onsets.len(), expected.len(), "{:?}", onsets);
+        for (onset, expected) in onsets.iter().zip(expected) {
+            assert!(
+                (onset.time - expected).abs() < 0.02,
+                "onset at {} s detected at {} s",
+                expected,
+                onset.time
+            );
+            assert_eq!(onset.time, time_offset + onset.frame as f32 / frame_rate);
+            assert!(onset.strength > 0.5 && onset.strength <= 1.0);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_onset_strength() {
+    // Increases count, decreases do not, and the first frame has no predecessor
+    let spec = vec![vec![1.0f32, 1.0, 10.0, 1.0], vec![1.0, 1.0, 1.0, 1.0]];
+    let strength = onset_strength(&spec, SpectrogramType::Power);
+    assert_eq!(strength.len(), 4);
+    assert_eq!(strength[0], 0.0);
+    assert_eq!(strength[1], 0.0);
+    assert!((strength[2] - 5.0).abs() < 1e-4);
+    assert_eq!(strength[3], 0.0);
+
+    assert!(onset_strength::<f32>(&[], SpectrogramType::Power).is_empty());
+}
+
+#[test]
+fn test_pick_peaks() -> Result<()> {
+    let params = PeakPick {
+        pre_max: 1,
+        post_max: 2,
+        pre_avg: 2,
+        post_avg: 3,
+        delta: 0.1,
+        wait: 2,
+    };
+    let envelope = [0.0, 1.0, 0.2, 0.9, 0.0, 0.0, 0.0, 0.5, 0.4, 0.4, 0.4, 0.4];
+    // 3 follows 1 too closely, the plateau after 7 is not a peak
+    assert_eq!(pick_peaks(&envelope, &params)?, vec![1, 7]);
+    assert_eq!(
+        pick_peaks(&envelope, &PeakPick { wait: 0, ..params })?,
+        vec![1, 3, 7]
+    );
+
+    // Flat envelopes (e.g. silence) have no onsets
+    assert!(pick_peaks(&[0.3; 10], &params)?.is_empty());
+    assert!(pick_peaks(&[], &params)?.is_empty());
+
+    assert!(
+        pick_peaks(
+            &envelope,
+            &PeakPick {
+                post_max: 0,
+                ..params
+            }
+        )
+        .is_err()
+    );
+    assert!(
+        pick_peaks(
+            &envelope,
+            &PeakPick {
+                delta: f32::NAN,
+                ..params
+            }
+        )
+        .is_err()
+    );
+    Ok(())
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use common::grid;
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::ops::{
+    GateMode, Padding, concat_time, crop_freq, crop_time, frame_energy_db, gate_frames,
+    non_finite_frames, pad, resize, sanitize_non_finite, shape,
+};
+use spectrs::spectrogram::stft::SpectrogramType;
+
+#[test]
+fn test_shape_rejects_ragged() {
+    assert_eq!(shape(&grid(3, 7)).unwrap(), (3, 7));
+    assert_eq!(shape::<f32>(&[]).unwrap(), (0, 0));
+
+    let ragged = vec![vec![0.0f32; 4], vec![0.0; 3]];
+    assert!(matches!(
+        shape(&ragged),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    assert!(crop_time(&ragged, 0..2).is_err());
+    assert!(pad(&ragged, Padding::time(1, 1), 0.0).is_err());
+}
+
+#[test]
+fn test_crop_time_and_freq() {
+    let spec = grid(4, 10);
+
+    let cropped = crop_time(&spec, 2..5).unwrap();
+    assert_eq!(cropped.len(), 4);
+    assert_eq!(cropped[0], vec![2.0, 3.0, 4.0]);
+    assert_eq!(cropped[3], vec![3002.0, 3003.0, 3004.0]);
+
+    let cropped = crop_freq(&spec, 1..3).unwrap();
+    assert_eq!(cropped, spec[1..3].to_vec());
+
+    // Empty ranges are allowed, ranges past the end are not
+    assert_eq!(
+        crop_time(&spec, 10..10).unwrap(),
+        vec![Vec::<f32>::new(); 4]
+    );
+    assert!(matches!(
+        crop_time(&spec, 5..11),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+    assert!(crop_freq(&spec, 0..5).is_err());
+    #[allow(clippy::reversed_empty_ranges)]
+    let backwards = 3..2;
+    assert!(crop_time(&spec, backwards).is_err());
+}
+
+#[test]
+fn test_pad_surrounds_with_value() {
+    let spec = grid(2, 3);
+    let padded = pad(
+        &spec,
+        Padding {
+            start: 1,
+            end: 2,
+            low: 1,
+            high: 0,
+        },
+        -80.0,
+    )
+    .unwrap();
+
+    assert_eq!(shape(&padded).unwrap(), (3, 6));
+    assert_eq!(padded[0], vec![-80.0; 6]);
+    assert_eq!(padded[1], vec![-80.0, 0.0, 1.0, 2.0, -80.0, -80.0]);
+    assert_eq!(padded[2], vec![-80.0, 1000.0, 1001.0, 1002.0, -80.0, -80.0]);
+
+    assert_eq!(pad(&spec, Padding::default(), 0.0).unwrap(), spec);
+    assert_eq!(
+        shape(&pad(&spec, Padding::freq(2, 3), 0.0).unwrap()).unwrap(),
+        (7, 3)
+    );
+}
+
+#[test]
+fn test_concat_time_round_trips_crop() {
+    let spec = grid(3, 10);
+    let parts = [
+        crop_time(&spec, 0..4).unwrap(),
+        crop_time(&spec, 4..4).unwrap(),
+        crop_time(&spec, 4..10).unwrap(),
+    ];
+    assert_eq!(concat_time(&parts).unwrap(), spec);
+
+    // Slices work as well as owned spectrograms
+    let slices: [&[Vec<f32>]; 2] = [&spec, &spec[..]];
+    assert_eq!(concat_time(&slices).unwrap()[1].len(), 20);
+
+    assert!(concat_time::<f32, Vec<Vec<f32>>>(&[]).unwrap().is_empty());
+    assert!(matches!(
+        concat_time(&[grid(3, 2), grid(4, 2)]),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+}
+
+#[test]
+fn test_resize_bilinear() {
+    // A plane a*k + b*t is reproduced exactly by bilinear interpolation
+    let spec = grid(3, 5);
+    let resized = resize(&spec, 5, 9).unwrap();
+
+    assert_eq!(shape(&resized).unwrap(), (5, 9));
+    for (k, row) in resized.iter().enumerate() {
+        for (t, &value) in row.iter().enumerate() {
+            let expected = k as f32 * 500.0 + t as f32 * 0.5;
+            assert!((value - expected).abs() < 1e-3, "{} != {}", value, expected);
+        }
+    }
+
+    // Corners are kept when shrinking
+    let shrunk = resize(&grid(8, 20), 2, 3).unwrap();
+    assert_eq!(
+        shrunk,
+        vec![vec![0.0, 9.5, 19.0], vec![7000.0, 7009.5, 7019.0]]
+    );
+
+    // Same size is the identity, f64 works too
+    let spec64: Vec<Vec<f64>> = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+    assert_eq!(resize(&spec64, 2, 2).unwrap(), spec64);
+    assert_eq!(resize(&spec64, 1, 1).unwrap(), vec![vec![2.5]]);
+
+    assert!(resize::<f32>(&[], 4, 4).is_err());
+    assert!(resize::<f32>(&[], 0, 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_gate_frames() {
+    // Frames at 0, -20, -70 dB and silent, relative to the loudest one
+    let power = vec![vec![1.0f32, 0.01, 1e-7, 0.0], vec![1.0, 0.01, 1e-7, 0.0]];
+    let energies = frame_energy_db(&power, SpectrogramType::Power).unwrap();
+    assert!((energies[1] + 20.0).abs() < 1e-3);
+    assert!((energies[2] + 70.0).abs() < 1e-3);
+    assert_eq!(energies[3], f32::NEG_INFINITY);
+
+    let mut zeroed = power.clone();
+    let n = gate_frames(&mut zeroed, -60.0, SpectrogramType::Power, GateMode::Zero).unwrap();
+    assert_eq!(n, 2);
+    assert_eq!(zeroed[0], vec![1.0, 0.01, 0.0, 0.0]);
+
+    let mut dropped = power.clone();
+    gate_frames(&mut dropped, -10.0, SpectrogramType::Power, GateMode::Drop).unwrap();
+    assert_eq!(dropped, vec![vec![1.0], vec![1.0]]);
+
+    // Magnitudes are squared: 0.1 is 20 dB below 1
+    let mut magnitude = vec![vec![1.0f64, 0.1]];
+    gate_frames(
+        &mut magnitude,
+        -15.0,
+        SpectrogramType::Magnitude,
+        GateMode::Drop,
+    )
+    .unwrap();
+    assert_eq!(magnitude, vec![vec![1.0]]);
+
+    // Silence has no reference level, positive thresholds are invalid
+    let mut silent = vec![vec![0.0f32; 3]; 2];
+    assert_eq!(
+        gate_frames(&mut silent, -60.0, SpectrogramType::Power, GateMode::Drop).unwrap(),
+        0
+    );
+    assert_eq!(silent[0].len(), 3);
+    assert!(matches!(
+        gate_frames(&mut zeroed, 3.0, SpectrogramType::Power, GateMode::Zero),
+        Err(SpectrsError::InvalidParams(_))
+    ));
+}
+
+#[test]
+fn test_non_finite_frames_and_sanitize() {
+    let mut spec = grid(3, 6);
+    spec[0][1] = f32::NAN;
+    spec[2][1] = f32::INFINITY;
+    spec[1][4] = f32::NEG_INFINITY;
+
+    assert_eq!(non_finite_frames(&spec).unwrap(), vec![1, 4]);
+    assert_eq!(sanitize_non_finite(&mut spec, 0.0).unwrap(), 3);
+    assert!(non_finite_frames(&spec).unwrap().is_empty());
+    assert_eq!(spec[0][1], 0.0);
+    assert_eq!(spec[1][3], 1003.0);
+
+    assert!(non_finite_frames(&[vec![0.0f32; 2], vec![0.0; 1]]).is_err());
+}
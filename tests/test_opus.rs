@@ -0,0 +1,105 @@
+#![cfg(feature = "opus")]
+
+mod common;
+
+use anyhow::Result;
+use audiopus::coder::Decoder;
+use audiopus::{Channels, MutSignals, SampleRate};
+use common::{cleanup_test_dir, setup_test_dir, tone};
+use ogg::reading::PacketReader;
+use spectrs::io::audio::{WavFormat, write_audio_file};
+use spectrs::io::opus::{DEFAULT_OPUS_BITRATE, encode_opus};
+use std::io::Cursor;
+
+/// Decode an Ogg Opus stream: header packets, then samples at 48 kHz with the pre-skip
+/// removed and the end trimmed to the last granule position
+fn decode(bytes: Vec<u8>) -> Result<(Vec<u8>, Vec<f32>)> {
+    let mut reader = PacketReader::new(Cursor::new(bytes));
+    let head = reader.read_packet_expected()?.data;
+    assert_eq!(&reader.read_packet_expected()?.data[..8], b"OpusTags");
+    let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+
+    let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Mono)?;
+    let mut decoded = Vec::new();
+    let mut buffer = vec![0.0f32; 5760];
+    let mut last_granule = 0;
+    while let Some(packet) = reader.read_packet()? {
+        let n = decoder.decode_float(
+            Some(packet.data.as_slice().try_into()?),
+            MutSignals::try_from(&mut buffer[..])?,
+            false,
+        )?;
+        decoded.extend_from_slice(&buffer[..n]);
+        last_granule = packet.absgp_page() as usize;
+    }
+    decoded.truncate(last_granule);
+    Ok((head, decoded[pre_skip..].to_vec()))
+}
+
+#[test]
+fn test_encode_opus_round_trip() -> Result<()> {
+    let samples = tone(440.0, 0.5, 1.0, 48000);
+    let mut bytes = Vec::new();
+    encode_opus(&mut bytes, &samples, 48000, DEFAULT_OPUS_BITRATE)?;
+    assert_eq!(&bytes[..4], b"OggS");
+    // Far smaller than 16-bit PCM
+    assert!(bytes.len() < samples.len() * 2 / 8);
+
+    let (head, decoded) = decode(bytes)?;
+    assert_eq!(&head[..8], b"OpusHead");
+    assert_eq!(head[9], 1);
+    assert_eq!(
+        u32::from_le_bytes([head[12], head[13], head[14], head[15]]),
+        48000
+    );
+    // Exactly the input samples, with the same level
+    assert_eq!(decoded.len(), samples.len());
+    let rms = |x: &[f32]| (x.iter().map(|v| v * v).sum::<f32>() / x.len() as f32).sqrt();
+    assert!((rms(&decoded[4800..43200]) - rms(&samples[4800..43200])).abs() < 0.02);
+    Ok(())
+}
+
+#[test]
+fn test_encode_opus_resamples() -> Result<()> {
+    // 0.5 s at 16 kHz is 0.5 s at 48 kHz, the original rate recorded in the header
+    let mut bytes = Vec::new();
+    encode_opus(
+        &mut bytes,
+        &tone(440.0, 0.5, 0.5, 16000),
+        16000,
+        DEFAULT_OPUS_BITRATE,
+    )?;
+    let (head, decoded) = decode(bytes)?;
+    assert_eq!(
+        u32::from_le_bytes([head[12], head[13], head[14], head[15]]),
+        16000
+    );
+    assert!((decoded.len() as i64 - 24000).abs() <= 48);
+
+    // Shorter than a packet
+    let mut bytes = Vec::new();
+    encode_opus(
+        &mut bytes,
+        &tone(440.0, 0.5, 0.001, 48000),
+        48000,
+        DEFAULT_OPUS_BITRATE,
+    )?;
+    assert_eq!(decode(bytes)?.1.len(), 48);
+    Ok(())
+}
+
+#[test]
+fn test_write_audio_file_opus() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("nested").join("tone.opus");
+    write_audio_file(&path, &tone(440.0, 0.5, 0.5, 22050), 22050, WavFormat::Opus)?;
+    let (_, decoded) = decode(std::fs::read(&path)?)?;
+    assert!((decoded.len() as i64 - 24000).abs() <= 48);
+
+    // Non-positive bitrates are rejected by the encoder
+    let mut bytes = Vec::new();
+    assert!(encode_opus(&mut bytes, &tone(440.0, 0.5, 0.1, 48000), 48000, 0).is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
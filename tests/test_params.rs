@@ -0,0 +1,196 @@
+use anyhow::Result;
+use spectrs::error::SpectrsError;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::{FrameAlignment, Precision};
+
+#[test]
+fn test_params_defaults_are_valid() -> Result<()> {
+    let params = SpectrogramParams::builder().build()?;
+
+    assert_eq!(params.n_fft, 2048);
+    assert_eq!(params.hop_length, 512);
+    assert_eq!(params.win_length, 2048);
+    assert_eq!(params.n_freq_bins(), 1025);
+
+    Ok(())
+}
+
+#[test]
+fn test_params_rejects_invalid_combinations() {
+    // Window longer than the FFT
+    let err = SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(1024)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, SpectrsError::InvalidParams(_)));
+    assert!(err.to_string().contains("win_length"));
+
+    // Zero hop
+    assert!(SpectrogramParams::builder().hop_length(0).build().is_err());
+
+    // More mel bands than frequency bins
+    assert!(
+        SpectrogramParams::builder()
+            .n_fft(256)
+            .win_length(256)
+            .n_mels(Some(200))
+            .build()
+            .is_err()
+    );
+
+    // Inverted frequency range
+    assert!(
+        SpectrogramParams::builder()
+            .f_min(Some(4000.0))
+            .f_max(Some(1000.0))
+            .build()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_params_power_of_two() -> Result<()> {
+    // Non power-of-two sizes are accepted by default...
+    SpectrogramParams::builder()
+        .n_fft(400)
+        .win_length(400)
+        .build()?;
+
+    // ...but can be rejected on request
+    let err = SpectrogramParams::builder()
+        .n_fft(400)
+        .win_length(400)
+        .require_power_of_two(true)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("512"));
+
+    Ok(())
+}
+
+#[test]
+fn test_params_sample_rate_checks() -> Result<()> {
+    // f_max above Nyquist
+    assert!(
+        SpectrogramParams::builder()
+            .f_max(Some(10000.0))
+            .sample_rate(16000)
+            .build()
+            .is_err()
+    );
+
+    // Same parameters are fine at a higher sample rate
+    SpectrogramParams::builder()
+        .f_max(Some(10000.0))
+        .sample_rate(44100)
+        .build()?;
+
+    // Narrow band with too few FFT bins for the requested mel bands
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(512)
+        .n_mels(Some(64))
+        .f_min(Some(0.0))
+        .f_max(Some(1000.0))
+        .build()?;
+    assert!(params.validate_for_sample_rate(16000).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_params_deterministic() -> Result<()> {
+    let params = SpectrogramParams::builder().deterministic(true).build()?;
+    assert!(params.deterministic);
+    assert!(!SpectrogramParams::default().deterministic);
+
+    // Parameters serialized before the option existed are not deterministic
+    let mut json = serde_json::to_value(SpectrogramParams::default())?;
+    json.as_object_mut().unwrap().remove("deterministic");
+    let params: SpectrogramParams = serde_json::from_value(json)?;
+    assert!(!params.deterministic);
+
+    Ok(())
+}
+
+#[test]
+fn test_params_precision() -> Result<()> {
+    let params = SpectrogramParams::builder()
+        .precision(Precision::F64)
+        .build()?;
+    assert_eq!(params.precision, Precision::F64);
+    assert_eq!(SpectrogramParams::default().precision, Precision::F32);
+
+    // Presets reproduce single-precision toolkits
+    let err = SpectrogramParams::builder()
+        .precision(Precision::F64)
+        .compat(Compat::Kaldi)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, SpectrsError::InvalidParams(_)));
+
+    Ok(())
+}
+
+#[test]
+fn test_params_periodic_window() -> Result<()> {
+    // Symmetric by default, periodic with the presets reproducing librosa and torchaudio
+    let params = SpectrogramParams::builder().build()?;
+    assert!(!params.periodic_window());
+    assert_eq!(params.window::<f32>().last(), Some(&0.0));
+    let params = SpectrogramParams::builder()
+        .frame_alignment(FrameAlignment::Librosa)
+        .build()?;
+    assert!(params.periodic_window());
+    let params = SpectrogramParams::builder()
+        .compat(Compat::Torchaudio)
+        .build()?;
+    assert!(params.periodic_window());
+
+    // Explicit choices override the defaults
+    let params = SpectrogramParams::builder()
+        .compat(Compat::Torchaudio)
+        .periodic_window(Some(false))
+        .build()?;
+    assert!(!params.periodic_window());
+    let params = SpectrogramParams::builder()
+        .periodic_window(Some(true))
+        .build()?;
+    assert!(params.periodic_window());
+    assert!(params.window::<f32>().last() > Some(&0.0));
+
+    // The fbank presets have windows of their own
+    assert!(
+        SpectrogramParams::builder()
+            .compat(Compat::Kaldi)
+            .periodic_window(Some(true))
+            .build()
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_params_suggested() -> Result<()> {
+    // Telephone speech: 25 ms windows every 10 ms, 40 mel bands
+    let params = SpectrogramParams::suggested(8000, 3.0);
+    assert_eq!(
+        (params.n_fft, params.win_length, params.hop_length),
+        (256, 200, 80)
+    );
+    assert_eq!(params.n_mels, Some(40));
+    params.validate_for_sample_rate(8000)?;
+
+    let params = SpectrogramParams::suggested(16000, 3.0);
+    assert_eq!((params.n_fft, params.n_mels), (512, Some(64)));
+    let params = SpectrogramParams::suggested(48000, 3.0);
+    assert_eq!((params.n_fft, params.n_mels), (2048, Some(128)));
+
+    // A clip shorter than a window gets a window it holds
+    let params = SpectrogramParams::suggested(16000, 0.01);
+    assert_eq!((params.n_fft, params.win_length), (128, 128));
+    params.validate()?;
+    Ok(())
+}
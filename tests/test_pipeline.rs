@@ -0,0 +1,224 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir, tone};
+use spectrs::io::audio::{read_audio_file_mono, resample};
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::{
+    FrameAlignment, SpectrogramType, WindowNorm, compute_spectrogram, librosa_n_frames,
+};
+
+#[test]
+fn test_pipeline_matches_free_functions() -> Result<()> {
+    let samples = tone(440.0, 1.0, 0.5, 16000);
+    let pipeline = SpectrogramPipeline::builder()
+        .sample_rate(16000)
+        .n_fft(512)
+        .hop_length(160)
+        .mel(40)
+        .build()?;
+    assert_eq!(pipeline.params().win_length, 512);
+
+    let spec = compute_spectrogram(&samples, 512, 160, 512, true, SpectrogramType::Power);
+    let expected = convert_to_mel(&spec, 16000, 512, 40, None, None, MelScale::Slaney);
+    let mel = pipeline.process_samples(&samples)?;
+    assert_eq!(mel.len(), 40);
+    assert_eq!(mel[0].len(), expected[0].len());
+    for (row, expected_row) in mel.iter().zip(&expected) {
+        for (v, e) in row.iter().zip(expected_row) {
+            assert!((v - e).abs() <= 1e-4 * e.abs().max(1.0));
+        }
+    }
+
+    // dB values of the same spectrogram
+    let db = SpectrogramPipeline::builder()
+        .params(*pipeline.params())
+        .db(true)
+        .build()?
+        .process_samples_at(&samples, 16000)?;
+    assert!((db[5][3] - 10.0 * mel[5][3].max(1e-10).log10()).abs() < 1e-3);
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_resamples_files() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let path = test_dir.join("a.wav");
+    create_test_wav(&path, 1.0, 22050, 1, 16)?;
+
+    // The hop defaults to a quarter of the window
+    let pipeline = SpectrogramPipeline::builder()
+        .sample_rate(16000)
+        .n_fft(256)
+        .build()?;
+    assert_eq!(pipeline.params().hop_length, 64);
+    let spec = pipeline.process_file(&path)?;
+
+    let (samples, sr) = read_audio_file_mono(&path)?;
+    let expected = compute_spectrogram(
+        &resample(samples, sr, 16000)?,
+        256,
+        64,
+        256,
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(spec.len(), 129);
+    assert_eq!(spec[0].len(), expected[0].len());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_rejects_invalid_settings() {
+    assert!(
+        SpectrogramPipeline::builder()
+            .n_fft(512)
+            .win_length(1024)
+            .build()
+            .is_err()
+    );
+    assert!(
+        SpectrogramPipeline::builder()
+            .sample_rate(8000)
+            .f_min(5000.0)
+            .mel(40)
+            .build()
+            .is_err()
+    );
+    assert!(
+        SpectrogramPipeline::builder()
+            .compat(Compat::Kaldi)
+            .db(true)
+            .build()
+            .is_err()
+    );
+
+    // Samples without a sample rate
+    let pipeline = SpectrogramPipeline::builder().n_fft(256).build().unwrap();
+    assert!(
+        pipeline
+            .process_samples(&tone(440.0, 1.0, 0.1, 8000))
+            .is_err()
+    );
+    assert!(
+        pipeline
+            .process_samples_at(&tone(440.0, 1.0, 0.1, 8000), 8000)
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_pipeline_librosa_frame_alignment() -> Result<()> {
+    let samples = tone(440.0, 1.0, 0.5, 16000);
+    let librosa = |center: bool| {
+        SpectrogramPipeline::builder()
+            .sample_rate(16000)
+            .n_fft(512)
+            .win_length(400)
+            .hop_length(160)
+            .center(center)
+            .frame_alignment(FrameAlignment::Librosa)
+            .build()
+    };
+
+    // 1 + n / hop frames with centering, 1 + (n - n_fft) / hop without
+    let centered = librosa(true)?;
+    let spec = centered.process_samples(&samples)?;
+    assert_eq!(spec.len(), 257);
+    assert_eq!(spec[0].len(), 1 + 8000 / 160);
+    assert_eq!(centered.params().n_frames(8000), 1 + 8000 / 160);
+    assert_eq!(centered.params().first_frame_center(), 0.0);
+    let spec = librosa(false)?.process_samples(&samples)?;
+    assert_eq!(spec[0].len(), 1 + (8000 - 512) / 160);
+    assert_eq!(librosa_n_frames(8000, 512, 160, false), spec[0].len());
+
+    // Without centering the frames are those of spectrs when the window fills the FFT frame
+    // (with the same window, periodic by default with librosa's alignment only)
+    let frames = |alignment: FrameAlignment| {
+        SpectrogramPipeline::builder()
+            .sample_rate(16000)
+            .n_fft(512)
+            .hop_length(160)
+            .center(false)
+            .frame_alignment(alignment)
+            .periodic_window(true)
+            .build()?
+            .process_samples(&samples)
+    };
+    assert_eq!(
+        frames(FrameAlignment::Librosa)?,
+        frames(FrameAlignment::Spectrs)?
+    );
+
+    // Too short for a single frame
+    let spec = librosa(false)?.process_samples(&samples[..100])?;
+    assert_eq!(spec.len(), 257);
+    assert!(spec[0].is_empty());
+
+    // librosa's framing is a librosa convention
+    assert!(
+        SpectrogramPipeline::builder()
+            .compat(Compat::Kaldi)
+            .frame_alignment(FrameAlignment::Librosa)
+            .build()
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_window_norm() -> Result<()> {
+    // 1 kHz falls exactly on bin 32 of a 512-point FFT at 16 kHz
+    let samples: Vec<f32> = (0..8000)
+        .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 16000.0).sin())
+        .collect();
+    let pipeline = |norm: WindowNorm| {
+        SpectrogramPipeline::builder()
+            .sample_rate(16000)
+            .n_fft(512)
+            .hop_length(256)
+            .periodic_window(true)
+            .window_norm(norm)
+            .spectrogram_type(SpectrogramType::Magnitude)
+            .build()
+    };
+
+    // Normalized by its sum, the window gives half the amplitude of the sinusoid (as scipy)
+    let sum = pipeline(WindowNorm::Sum)?;
+    assert!((sum.params().win_scale() - 2.0 / 512.0).abs() < 1e-12);
+    let spec = sum.process_samples(&samples)?;
+    assert!((spec[32][10] - 0.5).abs() < 1e-4);
+
+    // The other normalizations scale the unnormalized values
+    let plain = pipeline(WindowNorm::None)?.process_samples(&samples)?;
+    for (norm, scale) in [
+        (WindowNorm::Energy, (8.0f64 / (3.0 * 512.0)).sqrt()),
+        (WindowNorm::Length, 1.0 / 512f64.sqrt()),
+    ] {
+        let pipeline = pipeline(norm)?;
+        assert!((pipeline.params().win_scale() - scale).abs() < 1e-9);
+        let spec = pipeline.process_samples(&samples)?;
+        assert!((spec[32][10] - plain[32][10] * scale as f32).abs() < 1e-3 * spec[32][10]);
+    }
+
+    // Powers scale by the square of the factor
+    let power = SpectrogramPipeline::builder()
+        .params(*sum.params())
+        .spectrogram_type(SpectrogramType::Power)
+        .build()?
+        .process_samples_at(&samples, 16000)?;
+    assert!((power[32][10] - 0.25).abs() < 1e-4);
+
+    assert!(
+        SpectrogramPipeline::builder()
+            .compat(Compat::Kaldi)
+            .window_norm(WindowNorm::Sum)
+            .build()
+            .is_err()
+    );
+    Ok(())
+}
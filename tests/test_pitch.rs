@@ -0,0 +1,112 @@
+use anyhow::Result;
+use spectrs::features::pitch::{YinParams, par_yin, yin};
+
+const SR: u32 = 16000;
+
+/// Tone with the first harmonics of `f0`, the second one the loudest
+fn harmonic_tone(f0: f32, seconds: f32) -> Vec<f32> {
+    (0..(seconds * SR as f32) as usize)
+        .map(|i| {
+            let t = i as f32 / SR as f32;
+            [(1.0, 0.4), (2.0, 0.8), (3.0, 0.3)]
+                .iter()
+                .map(|(k, a)| a * (2.0 * std::f32::consts::PI * k * f0 * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+fn params() -> YinParams {
+    YinParams {
+        frame_length: 1024,
+        hop_length: 256,
+        ..YinParams::default()
+    }
+}
+
+#[test]
+fn test_yin_tracks_fundamental() -> Result<()> {
+    for f0 in [82.0, 150.0, 440.0, 1000.0] {
+        let track = yin(&harmonic_tone(f0, 1.0), SR, &params())?;
+        // Frames away from the padded edges
+        let inner = &track.f0[4..track.f0.len() - 4];
+        assert!(inner.iter().all(|f| f.is_some()), "{} Hz", f0);
+        for f in inner.iter().flatten() {
+            assert!((f - f0).abs() < 0.005 * f0, "{} Hz tracked as {} Hz", f0, f);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_yin_unvoiced() -> Result<()> {
+    let track = yin(&vec![0.0; SR as usize], SR, &params())?;
+    assert!(track.f0.iter().all(Option::is_none));
+
+    // Deterministic white noise has no period
+    let mut state = 12345u32;
+    let noise: Vec<f32> = (0..SR)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+        })
+        .collect();
+    let track = yin(&noise, SR, &params())?;
+    let voiced = track.f0.iter().filter(|f| f.is_some()).count();
+    assert!(voiced * 10 < track.f0.len(), "{} voiced frames", voiced);
+    Ok(())
+}
+
+#[test]
+fn test_yin_frames() -> Result<()> {
+    let audio = harmonic_tone(200.0, 0.5);
+    // Centered frames like librosa: one every hop, the first at 0 s
+    let track = yin(&audio, SR, &params())?;
+    assert_eq!(track.f0.len(), 1 + audio.len() / 256);
+    assert_eq!(track.times.len(), track.f0.len());
+    assert_eq!(track.times[0], 0.0);
+    assert_eq!(track.times[2], 512.0 / SR as f32);
+
+    let uncentered = yin(
+        &audio,
+        SR,
+        &YinParams {
+            center: false,
+            ..params()
+        },
+    )?;
+    assert_eq!(uncentered.f0.len(), 1 + (audio.len() - 1024) / 256);
+    assert_eq!(uncentered.times[0], 512.0 / SR as f32);
+
+    assert_eq!(par_yin(&audio, SR, &params())?, track);
+    Ok(())
+}
+
+#[test]
+fn test_yin_validation() {
+    let audio = vec![0.0; 1000];
+    let invalid = [
+        YinParams {
+            f_max: 9000.0,
+            ..params()
+        },
+        YinParams {
+            f_min: 500.0,
+            f_max: 400.0,
+            ..params()
+        },
+        YinParams {
+            threshold: 0.0,
+            ..params()
+        },
+        // Two periods of 65 Hz do not fit in 256 samples
+        YinParams {
+            frame_length: 256,
+            ..params()
+        },
+    ];
+    for params in invalid {
+        assert!(yin(&audio, SR, &params).is_err(), "{:?}", params);
+    }
+    assert!(YinParams::min_frame_length(65.0, SR) <= 1024);
+}
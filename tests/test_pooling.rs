@@ -0,0 +1,124 @@
+mod common;
+
+use common::grid;
+use spectrs::spectrogram::pooling::{
+    FitMode, FramePooler, PoolMode, fit_frames, pool_freq, pool_time, reduce_time,
+};
+
+#[test]
+fn test_pool_time_averages_groups() {
+    let spec = grid(3, 10);
+    let pooled = pool_time(&spec, 5);
+
+    assert_eq!(pooled.len(), 3);
+    assert_eq!(pooled[0], vec![0.5, 2.5, 4.5, 6.5, 8.5]);
+    assert_eq!(pooled[2][0], 2000.5);
+}
+
+#[test]
+fn test_pool_time_uneven_groups() {
+    // 10 frames into 3 columns: groups of 4, 3 and 3 frames
+    let spec = grid(1, 10);
+    let pooled = pool_time(&spec, 3);
+
+    assert_eq!(pooled[0], vec![1.5, 5.0, 8.0]);
+}
+
+#[test]
+fn test_pool_noop_when_small_enough() {
+    let spec = grid(4, 6);
+    assert_eq!(pool_time(&spec, 6), spec);
+    assert_eq!(pool_time(&spec, 100), spec);
+    assert_eq!(pool_freq(&spec, 4), spec);
+    assert_eq!(pool_freq(&spec, 0), spec);
+}
+
+#[test]
+fn test_pool_freq_averages_bins() {
+    let spec = grid(4, 2);
+    let pooled = pool_freq(&spec, 2);
+
+    assert_eq!(pooled.len(), 2);
+    assert_eq!(pooled[0], vec![500.0, 501.0]);
+    assert_eq!(pooled[1], vec![2500.0, 2501.0]);
+}
+
+#[test]
+fn test_frame_pooler_matches_pool_time() {
+    let spec = grid(5, 1001);
+
+    let mut pooler = FramePooler::new(1001, 37, 5);
+    for t in 0..1001 {
+        let frame: Vec<f32> = spec.iter().map(|row| row[t]).collect();
+        pooler.push(&frame);
+    }
+
+    let streamed = pooler.finish();
+    assert_eq!(streamed[0].len(), 37);
+    assert_eq!(streamed, pool_time(&spec, 37));
+}
+
+#[test]
+fn test_frame_pooler_extra_frames() {
+    // More frames than announced end up in the last column
+    let mut pooler = FramePooler::new(4, 2, 1);
+    for v in [1.0, 3.0, 5.0, 7.0, 9.0] {
+        pooler.push(&[v]);
+    }
+    assert_eq!(pooler.finish(), vec![vec![2.0, 7.0]]);
+}
+
+#[test]
+fn test_reduce_time() {
+    // Groups of 4 frames, the last one shorter
+    let spec = vec![vec![1.0, 5.0, 3.0, 3.0, 2.0, 0.0, 4.0, 6.0, 7.0, 1.0]];
+    assert_eq!(
+        reduce_time(&spec, 4, PoolMode::Mean),
+        vec![vec![3.0, 3.0, 4.0]]
+    );
+    assert_eq!(
+        reduce_time(&spec, 4, PoolMode::Max),
+        vec![vec![5.0, 6.0, 7.0]]
+    );
+    assert_eq!(reduce_time(&spec, 1, PoolMode::Max), spec);
+    assert_eq!(reduce_time(&grid(3, 8), 2, PoolMode::Mean)[2][0], 2000.5);
+}
+
+#[test]
+fn test_fit_frames() {
+    let spec = grid(2, 5);
+
+    // Padded after the last frame, or truncated
+    let padded = fit_frames(&spec, 7, FitMode::Pad, 0.0);
+    assert_eq!(
+        padded[1],
+        vec![1000.0, 1001.0, 1002.0, 1003.0, 1004.0, 0.0, 0.0]
+    );
+    assert_eq!(
+        fit_frames(&spec, 3, FitMode::Pad, 0.0)[0],
+        vec![0.0, 1.0, 2.0]
+    );
+    assert_eq!(fit_frames(&spec, 5, FitMode::Pad, 0.0), spec);
+
+    // Interpolated between the first and last frames
+    assert_eq!(
+        fit_frames(&spec, 9, FitMode::Resample, 0.0)[0],
+        vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0]
+    );
+    assert_eq!(
+        fit_frames(&spec, 3, FitMode::Resample, 0.0)[1],
+        vec![1000.0, 1002.0, 1004.0]
+    );
+    assert_eq!(fit_frames(&spec, 1, FitMode::Resample, 0.0)[0], vec![2.0]);
+    assert_eq!(
+        fit_frames(&grid(1, 1), 3, FitMode::Resample, 0.0)[0],
+        vec![0.0; 3]
+    );
+
+    // Spectrograms without frames are all padding
+    let empty = vec![Vec::<f64>::new(); 2];
+    assert_eq!(
+        fit_frames(&empty, 2, FitMode::Resample, -1.0),
+        vec![vec![-1.0; 2]; 2]
+    );
+}
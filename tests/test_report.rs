@@ -0,0 +1,56 @@
+use spectrs::io::metadata::ReportedInput;
+use spectrs::io::report::{HtmlReport, ReportEntry, base64};
+
+#[test]
+fn test_base64() {
+    // RFC 4648 test vectors
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(b"foob"), "Zm9vYg==");
+    assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+    assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+}
+
+#[test]
+fn test_html_report() {
+    let report = HtmlReport {
+        title: "dataset".to_string(),
+        entries: vec![
+            ReportEntry {
+                input: "dogs/<bark>.wav".to_string(),
+                label: "dogs".to_string(),
+                outputs: vec!["out/dogs/<bark>.png".to_string()],
+                duration: 1.5,
+                sample_rate: 16000,
+                peak_db: -3.0,
+                zero_crossing_rate: 0.1,
+                shape: [128, 47],
+                thumbnail: Some(b"foo".to_vec()),
+            },
+            ReportEntry {
+                input: "cats/meow.wav".to_string(),
+                duration: 0.5,
+                ..ReportEntry::default()
+            },
+        ],
+        failed: vec![ReportedInput {
+            input: "broken.wav".to_string(),
+            reason: "Failed to read audio".to_string(),
+        }],
+    };
+    let html = report.to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("2 inputs, 2.0s of audio, 1 failed"));
+
+    // Thumbnails are inlined, names escaped
+    assert_eq!(html.matches("<img ").count(), 1);
+    assert!(html.contains("src=\"data:image/png;base64,Zm9v\""));
+    assert!(html.contains("dogs/&lt;bark&gt;.wav"));
+    assert!(!html.contains("<bark>"));
+    assert!(html.contains("16000 Hz"));
+    assert!(html.contains("128&times;47"));
+    assert!(html.contains("broken.wav: Failed to read audio"));
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use spectrs::io::audio::{DownmixMode, PcmFormat, RawPcm};
+use spectrs::io::source::{
+    AudioInfo, AudioSource, BufferSource, FileSource, RawPcmSource, SamplesSource, is_url,
+};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn test_file_and_buffer_sources_agree() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("source.wav");
+
+    create_test_wav(&audio_path, 1.0, 16000, 2, 16)?;
+
+    let file_source = FileSource::new(&audio_path);
+    let buffer_source = BufferSource::new("source.wav", fs::read(&audio_path)?);
+
+    // Both sources must decode to exactly the same samples
+    let (file_samples, file_sr) = file_source.read_mono()?;
+    let (buffer_samples, buffer_sr) = buffer_source.read_mono()?;
+    assert_eq!(file_sr, buffer_sr);
+    assert_eq!(file_samples, buffer_samples);
+
+    // Same for segments
+    let (file_segment, _) = file_source.read_segment(0.25, Some(0.75))?;
+    let (buffer_segment, _) = buffer_source.read_segment(0.25, Some(0.75))?;
+    assert_eq!(file_segment.len(), 8000);
+    assert_eq!(file_segment, buffer_segment);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_sources_downmix() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("stereo.wav");
+    create_test_wav(&audio_path, 0.5, 16000, 2, 16)?;
+
+    // Both channels hold the same sine: any downmix gives the same samples
+    let (average, _) = FileSource::new(&audio_path).read_mono()?;
+    for downmix in [DownmixMode::First, DownmixMode::Channel(1)] {
+        let file_source = FileSource::new(&audio_path).with_downmix(downmix);
+        let buffer_source =
+            BufferSource::new("stereo.wav", fs::read(&audio_path)?).with_downmix(downmix);
+        assert_eq!(file_source.read_mono()?.0, average);
+        assert_eq!(buffer_source.read_mono()?.0, average);
+        assert_eq!(
+            file_source.read_segment(0.1, Some(0.2))?.0,
+            average[1600..3200]
+        );
+        assert_eq!(buffer_source.read_mono_lenient()?.0, average);
+    }
+
+    // A channel the file does not have
+    let source = FileSource::new(&audio_path).with_downmix(DownmixMode::Channel(2));
+    assert!(source.read_mono().is_err());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_raw_pcm_source_matches_wav() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("source.wav");
+    let raw_path = test_dir.join("nested").join("source.raw");
+
+    create_test_wav(&audio_path, 0.5, 16000, 2, 16)?;
+
+    // Strip the header: the data chunk of a 16-bit stereo WAV file is raw s16le PCM
+    let bytes = fs::read(&audio_path)?;
+    let data_pos = bytes
+        .windows(4)
+        .position(|w| w == b"data")
+        .expect("data chunk");
+    fs::create_dir_all(raw_path.parent().unwrap())?;
+    fs::write(&raw_path, &bytes[data_pos + 8..])?;
+
+    let layout = RawPcm {
+        sample_rate: 16000,
+        format: PcmFormat::S16le,
+        channels: 2,
+    };
+    let raw_source = RawPcmSource::with_base(&raw_path, &test_dir, layout);
+    assert_eq!(
+        raw_source.relative_path(),
+        PathBuf::from("nested/source.raw")
+    );
+    assert_eq!(
+        raw_source.read_mono()?,
+        FileSource::new(&audio_path).read_mono()?
+    );
+    assert_eq!(raw_source.info(), FileSource::new(&audio_path).info());
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_samples_source_segment() -> Result<()> {
+    let samples: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+    let source = SamplesSource::new("generated", samples, 1000);
+
+    let (segment, sr) = source.read_segment(0.1, Some(0.2))?;
+    assert_eq!(sr, 1000);
+    assert_eq!(segment.len(), 100);
+    assert_eq!(segment[0], 100.0);
+
+    // Start beyond the end of the samples
+    assert!(source.read_segment(2.0, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_source_info() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("source.wav");
+
+    create_test_wav(&audio_path, 0.5, 16000, 2, 16)?;
+
+    // Known from the header, without decoding
+    let expected = AudioInfo {
+        sample_rate: 16000,
+        channels: 2,
+        frames: 8000,
+    };
+    assert_eq!(FileSource::new(&audio_path).info(), Some(expected));
+    let buffer = BufferSource::new("source.wav", fs::read(&audio_path)?);
+    assert_eq!(buffer.info(), Some(expected));
+    assert_eq!(expected.duration(), 0.5);
+
+    // Unknown when the header cannot be read
+    assert_eq!(
+        BufferSource::new("broken.wav", b"not a wav".to_vec()).info(),
+        None
+    );
+    assert_eq!(FileSource::new(test_dir.join("missing.wav")).info(), None);
+
+    let samples = SamplesSource::new("samples", vec![0.0; 100], 1000);
+    assert_eq!(samples.info().map(|info| info.frames), Some(100));
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_source_relative_paths() {
+    // Single file: just the file name
+    let single = FileSource::new("raw/sound.wav");
+    assert_eq!(single.relative_path(), PathBuf::from("sound.wav"));
+    assert!(single.local_path().is_some());
+
+    // File discovered in a directory: structure below the base is preserved
+    let nested = FileSource::with_base("raw/b/sound.wav", "raw");
+    assert_eq!(nested.relative_path(), PathBuf::from("b/sound.wav"));
+
+    // In-memory sources have no local path
+    let buffer = BufferSource::new("upload.wav", Vec::new());
+    assert!(buffer.local_path().is_none());
+
+    assert!(is_url("https://example.com/a.wav"));
+    assert!(!is_url("data/a.wav"));
+}
+
+/// Serve `body` over HTTP on a local port to `requests` successive requests, returning the URL
+#[cfg(feature = "http")]
+fn serve_body(body: Vec<u8>, requests: usize) -> Result<String> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/long.wav", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut request = BufReader::new(&stream);
+            let mut line = String::new();
+            while request.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            // The client may hang up once it has seen enough of the body
+            let _ = stream
+                .write_all(header.as_bytes())
+                .and_then(|_| stream.write_all(&body));
+        }
+    });
+    Ok(url)
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_http_source_downloads_long_files() -> Result<()> {
+    use spectrs::io::source::HttpSource;
+
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("long.wav");
+
+    // About 12 MB, past the default body limit of ureq
+    create_test_wav(&audio_path, 70.0, 44100, 2, 16)?;
+    let bytes = fs::read(&audio_path)?;
+    assert!(bytes.len() > 10 * 1024 * 1024);
+
+    let url = serve_body(bytes.clone(), 2)?;
+    let (samples, sr) = HttpSource::new(&url).read_mono()?;
+    assert_eq!(sr, 44100);
+    assert_eq!(samples.len(), 70 * 44100);
+
+    // Bodies past the chosen limit fail
+    assert!(
+        HttpSource::new(&url)
+            .with_max_size(1024 * 1024)
+            .read_mono()
+            .is_err()
+    );
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
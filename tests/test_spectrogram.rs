@@ -3,7 +3,13 @@ mod common;
 use anyhow::Result;
 use common::{cleanup_test_dir, create_complex_test_wav, create_test_wav, setup_test_dir};
 use spectrs::io::audio::read_audio_file_mono;
-use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram, par_compute_spectrogram};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::{
+    FRAMES_PER_BLOCK, FrameAlignment, SpectrogramType, compute_spectrogram,
+    compute_spectrogram_into, compute_spectrogram_with_window, create_hann_window,
+    hop_length_from_overlap, par_compute_spectrogram, par_compute_spectrogram_with_window,
+};
 
 #[test]
 fn test_compute_spectrogram_basic() -> Result<()> {
@@ -112,10 +118,10 @@ fn test_compute_spectrogram_centered() -> Result<()> {
     );
 
     // Both should have valid shapes
-    assert!(spec_centered.len() > 0);
-    assert!(spec_not_centered.len() > 0);
-    assert!(spec_centered[0].len() > 0);
-    assert!(spec_not_centered[0].len() > 0);
+    assert!(!spec_centered.is_empty());
+    assert!(!spec_not_centered.is_empty());
+    assert!(!spec_centered[0].is_empty());
+    assert!(!spec_not_centered[0].is_empty());
 
     Ok(())
 }
@@ -146,7 +152,7 @@ fn test_compute_spectrogram_different_fft_sizes() -> Result<()> {
 
         let expected_freq_bins = n_fft / 2 + 1;
         assert_eq!(spec.len(), expected_freq_bins);
-        assert!(spec[0].len() > 0);
+        assert!(!spec[0].is_empty());
     }
 
     Ok(())
@@ -176,8 +182,8 @@ fn test_compute_spectrogram_different_hop_lengths() -> Result<()> {
         );
 
         // Smaller hop length should give more frames
-        assert!(spec.len() > 0);
-        assert!(spec[0].len() > 0);
+        assert!(!spec.is_empty());
+        assert!(!spec[0].is_empty());
     }
 
     Ok(())
@@ -212,7 +218,7 @@ fn test_compute_spectrogram_from_file() -> Result<()> {
     // Verify dimensions
     let n_freq_bins = n_fft / 2 + 1;
     assert_eq!(spec.len(), n_freq_bins);
-    assert!(spec[0].len() > 0);
+    assert!(!spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -337,8 +343,8 @@ fn test_compute_spectrogram_short_audio() -> Result<()> {
     );
 
     // Should still produce valid output
-    assert!(spec.len() > 0);
-    assert!(spec[0].len() > 0);
+    assert!(!spec.is_empty());
+    assert!(!spec[0].is_empty());
 
     Ok(())
 }
@@ -370,8 +376,8 @@ fn test_compute_spectrogram_stereo_to_mono() -> Result<()> {
     );
 
     // Verify it worked
-    assert!(spec.len() > 0);
-    assert!(spec[0].len() > 0);
+    assert!(!spec.is_empty());
+    assert!(!spec[0].is_empty());
 
     cleanup_test_dir(&test_dir)?;
     Ok(())
@@ -433,6 +439,61 @@ fn test_compute_vs_par_compute_same_results() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_compute_spectrogram_f64() -> Result<()> {
+    let sr = 16000.0;
+    let samples: Vec<f64> = (0..8000)
+        .map(|t| (t as f64 * 440.0 * 2.0 * std::f64::consts::PI / sr).sin())
+        .collect();
+    let n_fft = 512;
+
+    let spec = compute_spectrogram(&samples, n_fft, 256, n_fft, false, SpectrogramType::Power);
+    assert_eq!(
+        spec,
+        par_compute_spectrogram(&samples, n_fft, 256, n_fft, false, SpectrogramType::Power)
+    );
+
+    // Parseval's theorem holds to double precision on the first frame
+    let window: Vec<f64> = (0..n_fft)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f64::consts::PI * i as f64 / (n_fft - 1) as f64).cos()))
+        .collect();
+    let time_energy: f64 = samples
+        .iter()
+        .zip(&window)
+        .map(|(s, w)| (s * w).powi(2))
+        .sum::<f64>()
+        * n_fft as f64;
+    let freq_energy: f64 = spec
+        .iter()
+        .enumerate()
+        .map(|(k, row)| match k {
+            0 => row[0],
+            k if k == n_fft / 2 => row[0],
+            _ => 2.0 * row[0],
+        })
+        .sum();
+    assert!(((freq_energy - time_energy) / time_energy).abs() < 1e-12);
+
+    // Single precision agrees up to its own rounding
+    let samples_f32: Vec<f32> = samples.iter().map(|&s| s as f32).collect();
+    let spec_f32 = compute_spectrogram(
+        &samples_f32,
+        n_fft,
+        256,
+        n_fft,
+        false,
+        SpectrogramType::Power,
+    );
+    let peak = spec.iter().flatten().fold(0.0f64, |acc, &v| acc.max(v));
+    for (row, row_f32) in spec.iter().zip(&spec_f32) {
+        for (&v, &v_f32) in row.iter().zip(row_f32) {
+            assert!((v - v_f32 as f64).abs() < 1e-5 * peak);
+        }
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_compute_spectrogram_single_threaded() -> Result<()> {
     // Test the single-threaded version
@@ -459,7 +520,7 @@ fn test_compute_spectrogram_single_threaded() -> Result<()> {
     // Check dimensions
     let n_freq_bins = n_fft / 2 + 1;
     assert_eq!(spec.len(), n_freq_bins);
-    assert!(spec[0].len() > 0);
+    assert!(!spec[0].is_empty());
 
     // Check that values are non-negative
     for freq_bin in &spec {
@@ -470,3 +531,191 @@ fn test_compute_spectrogram_single_threaded() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_hop_length_from_overlap() {
+    assert_eq!(hop_length_from_overlap(2048, 0.75), 512);
+    assert_eq!(hop_length_from_overlap(400, 0.6), 160);
+    assert_eq!(hop_length_from_overlap(512, 0.0), 512);
+
+    // Hop is never zero, even for extreme overlaps
+    assert_eq!(hop_length_from_overlap(16, 0.999), 1);
+}
+
+#[test]
+fn test_hann_window_periodicity() {
+    // scipy.signal.get_window("hann", 4) (periodic) and scipy.signal.windows.hann(5)
+    let periodic: Vec<f64> = create_hann_window(4, true);
+    for (w, e) in periodic.iter().zip([0.0, 0.5, 1.0, 0.5]) {
+        assert!((w - e).abs() < 1e-12);
+    }
+    let symmetric: Vec<f64> = create_hann_window(5, false);
+    for (w, e) in symmetric.iter().zip([0.0, 0.5, 1.0, 0.5, 0.0]) {
+        assert!((w - e).abs() < 1e-12);
+    }
+    // A periodic window is the start of the symmetric one a sample longer
+    let periodic: Vec<f32> = create_hann_window(400, true);
+    let symmetric: Vec<f32> = create_hann_window(401, false);
+    assert_eq!(periodic[..], symmetric[..400]);
+    assert_eq!(create_hann_window::<f32>(1, false), vec![1.0]);
+}
+
+#[test]
+fn test_compute_spectrogram_with_window() {
+    let samples: Vec<f32> = (0..8000)
+        .map(|t| (t as f32 * 1000.0 * 2.0 * std::f32::consts::PI / 16000.0).sin())
+        .collect();
+    let symmetric = create_hann_window(400, false);
+    let periodic = create_hann_window(400, true);
+
+    // The symmetric window is the default one
+    let default = compute_spectrogram(&samples, 512, 160, 400, true, SpectrogramType::Power);
+    let with_window = compute_spectrogram_with_window(
+        &samples,
+        512,
+        160,
+        &symmetric,
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(default, with_window);
+
+    let spec = compute_spectrogram_with_window(
+        &samples,
+        512,
+        160,
+        &periodic,
+        true,
+        SpectrogramType::Power,
+    );
+    let par_spec = par_compute_spectrogram_with_window(
+        &samples,
+        512,
+        160,
+        &periodic,
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(spec.len(), 257);
+    assert_eq!(spec[0].len(), default[0].len());
+    for (row, par_row) in spec.iter().zip(&par_spec) {
+        for (v, p) in row.iter().zip(par_row) {
+            assert!((v - p).abs() <= 1e-5 * v.abs().max(1.0));
+        }
+    }
+    // Same peak, slightly different values
+    assert_ne!(spec, default);
+    assert!((spec[32][10] - default[32][10]).abs() < 1e-2 * default[32][10]);
+}
+
+#[test]
+fn test_compute_spectrogram_frame_blocks() {
+    // Frame counts around the size of the blocks transformed together, with small FFTs
+    let hop = 32;
+    for n_fft in [16, 64, 100] {
+        for n_frames in [
+            1,
+            FRAMES_PER_BLOCK - 1,
+            FRAMES_PER_BLOCK,
+            2 * FRAMES_PER_BLOCK + 1,
+        ] {
+            let samples: Vec<f32> = (0..(n_frames - 1) * hop + n_fft)
+                .map(|t| (t as f32 * 0.37).sin() + 0.1 * (t as f32 * 2.9).cos())
+                .collect();
+            let spec =
+                compute_spectrogram(&samples, n_fft, hop, n_fft, false, SpectrogramType::Power);
+            let par_spec =
+                par_compute_spectrogram(&samples, n_fft, hop, n_fft, false, SpectrogramType::Power);
+            assert_eq!(spec.len(), n_fft / 2 + 1);
+            assert_eq!(spec[0].len(), n_frames);
+            assert_eq!(spec, par_spec);
+
+            // Every frame is the spectrum of its samples alone
+            for t in [0, n_frames / 2, n_frames - 1] {
+                let frame = &samples[t * hop..t * hop + n_fft];
+                let single =
+                    compute_spectrogram(frame, n_fft, hop, n_fft, false, SpectrogramType::Power);
+                for (row, single_row) in spec.iter().zip(&single) {
+                    assert_eq!(row[t], single_row[0]);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compute_spectrogram_into() -> Result<()> {
+    let samples: Vec<f32> = (0..12000).map(|t| (t as f32 * 0.05).sin()).collect();
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(160)
+        .win_length(400)
+        .build()?;
+    let expected = compute_spectrogram_with_window(
+        &samples,
+        512,
+        160,
+        &params.window::<f32>(),
+        true,
+        SpectrogramType::Power,
+    );
+
+    // Row-major [freq][time] values, the buffer reused for a second window of the signal
+    let n_frames = params.n_frames(samples.len());
+    let mut out = vec![-1.0f32; params.n_freq_bins() * n_frames + 3];
+    for _ in 0..2 {
+        assert_eq!(
+            compute_spectrogram_into(&samples, &params, &mut out)?,
+            n_frames
+        );
+        for (k, row) in expected.iter().enumerate() {
+            assert_eq!(&out[k * n_frames..(k + 1) * n_frames], &row[..]);
+        }
+        // Values after the spectrogram are untouched
+        assert_eq!(&out[params.n_freq_bins() * n_frames..], &[-1.0; 3]);
+    }
+    let n = compute_spectrogram_into(&samples[..4000], &params, &mut out)?;
+    assert_eq!(n, params.n_frames(4000));
+    let shorter = compute_spectrogram_with_window(
+        &samples[..4000],
+        512,
+        160,
+        &params.window::<f32>(),
+        true,
+        SpectrogramType::Power,
+    );
+    assert_eq!(&out[..n], &shorter[0][..]);
+
+    // librosa's framing, as computed by the pipeline
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(512)
+        .frame_alignment(FrameAlignment::Librosa)
+        .build()?;
+    let pipeline = SpectrogramPipeline::builder().params(params).build()?;
+    let expected = pipeline.spectrogram(&samples, 16000);
+    let mut out = vec![0.0f32; params.n_freq_bins() * params.n_frames(samples.len())];
+    let n_frames = compute_spectrogram_into(&samples, &params, &mut out)?;
+    assert_eq!(n_frames, expected[0].len());
+    assert_eq!(out, expected.concat());
+    // No whole frame without the padding of center
+    let uncentered = SpectrogramParams {
+        center: false,
+        ..params
+    };
+    assert_eq!(
+        compute_spectrogram_into(&[0.0f32; 10], &uncentered, &mut out)?,
+        0
+    );
+
+    // Buffers too small and settings that are not applied are rejected
+    assert!(compute_spectrogram_into(&samples, &params, &mut out[..100]).is_err());
+    let mel = SpectrogramParams::builder()
+        .n_fft(512)
+        .win_length(512)
+        .n_mels(Some(40))
+        .build()?;
+    assert!(compute_spectrogram_into(&samples, &mel, &mut out).is_err());
+    Ok(())
+}
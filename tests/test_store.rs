@@ -0,0 +1,94 @@
+#![cfg(feature = "object-store")]
+
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_test_wav, setup_test_dir};
+use object_store::memory::InMemory;
+use object_store::path::Path as ObjectPath;
+use spectrs::io::source::{AudioSource, is_store_url};
+use spectrs::io::store::{ObjectSource, StoreLocation};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[test]
+fn test_store_urls() {
+    assert!(is_store_url("s3://bucket/prefix"));
+    assert!(is_store_url("file:///data/audio"));
+    assert!(!is_store_url("https://example.com/a.wav"));
+    assert!(!is_store_url("data/audio"));
+
+    assert!(StoreLocation::parse("gs://bucket/prefix").is_err());
+    assert!(StoreLocation::parse("s3:///prefix").is_err());
+    assert!(StoreLocation::parse("file://server/share").is_err());
+}
+
+#[test]
+fn test_store_round_trip() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let wav = test_dir.join("a.wav");
+    create_test_wav(&wav, 0.5, 16000, 1, 16)?;
+
+    let location = Arc::new(StoreLocation::new(
+        Arc::new(InMemory::new()),
+        "memory://bucket/audio",
+        ObjectPath::from("audio"),
+    ));
+    location.put(Path::new("b/a.wav"), fs::read(&wav)?)?;
+    location.put(Path::new("notes.txt"), b"not audio".to_vec())?;
+    assert_eq!(
+        location.list()?,
+        [PathBuf::from("b/a.wav"), PathBuf::from("notes.txt")]
+    );
+
+    // Objects are decoded like the files they were uploaded from
+    let source = ObjectSource::new(location.clone(), "b/a.wav");
+    assert_eq!(source.name(), "memory://bucket/audio/b/a.wav");
+    assert_eq!(source.relative_path(), PathBuf::from("b/a.wav"));
+    let (samples, sample_rate) = source.read_mono()?;
+    assert_eq!(sample_rate, 16000);
+    assert_eq!(samples.len(), 8000);
+    let (segment, _) = source.read_segment(0.25, None)?;
+    assert_eq!(segment.len(), 4000);
+    assert!(
+        ObjectSource::new(location.clone(), "missing.wav")
+            .read_mono()
+            .is_err()
+    );
+
+    // Directories are uploaded keeping their structure
+    let outputs = test_dir.join("out");
+    fs::create_dir_all(outputs.join("sub"))?;
+    fs::write(outputs.join("x.png"), b"png")?;
+    fs::write(outputs.join("sub/y.npy"), b"npy")?;
+    let urls = location.upload_dir(&outputs)?;
+    assert_eq!(
+        urls,
+        [
+            "memory://bucket/audio/sub/y.npy",
+            "memory://bucket/audio/x.png"
+        ]
+    );
+    assert_eq!(location.get(Path::new("sub/y.npy"))?, b"npy");
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_store_local_directory() -> Result<()> {
+    let test_dir = fs::canonicalize(setup_test_dir()?)?;
+    fs::create_dir_all(test_dir.join("in"))?;
+    create_test_wav(&test_dir.join("in/a.wav"), 0.5, 16000, 1, 16)?;
+
+    let url = format!("file://{}/in/", test_dir.display());
+    let location = StoreLocation::parse(&url)?;
+    assert_eq!(location.url(), url.trim_end_matches('/'));
+    assert_eq!(location.list()?, [PathBuf::from("a.wav")]);
+    let source = ObjectSource::parse(&format!("{}a.wav", url))?;
+    assert_eq!(source.read_mono()?.1, 16000);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
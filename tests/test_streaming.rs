@@ -0,0 +1,262 @@
+mod common;
+
+use anyhow::Result;
+use common::{cleanup_test_dir, create_complex_test_wav, setup_test_dir};
+use spectrs::io::audio::{
+    MonoChunks, ResampleQuality, StreamingResampler, read_audio_file_mono, read_audio_segment,
+    resample_with_quality,
+};
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pooling::pool_time;
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use spectrs::spectrogram::streaming::{
+    StreamingStft, stream_pooled_spectrogram, stream_spectrogram,
+};
+
+/// Push `audio` through a streaming STFT in pieces of `piece` samples
+fn streamed(audio: &[f32], piece: usize, stft: StreamingStft) -> Vec<Vec<f32>> {
+    let mut stft = stft;
+    let mut spec = vec![Vec::new(); stft.n_freq_bins()];
+    let mut store = |frame: &[f32]| {
+        for (row, &v) in spec.iter_mut().zip(frame) {
+            row.push(v);
+        }
+    };
+    for chunk in audio.chunks(piece) {
+        stft.process(chunk, &mut store);
+    }
+    stft.finish(&mut store);
+    spec
+}
+
+fn test_signal(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|t| {
+            let t = t as f32 / 16000.0;
+            0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                + 0.3 * (2.0 * std::f32::consts::PI * 1250.0 * t).sin()
+        })
+        .collect()
+}
+
+#[test]
+fn test_streaming_stft_matches_batch() {
+    let audio = test_signal(16000);
+
+    for &(n_fft, hop_length, win_length, center) in &[
+        (512, 160, 400, false),
+        (512, 128, 512, true),
+        (1024, 256, 800, true),
+        // Hop longer than the window: some samples belong to no frame
+        (256, 300, 256, false),
+    ] {
+        let expected = compute_spectrogram(
+            &audio,
+            n_fft,
+            hop_length,
+            win_length,
+            center,
+            SpectrogramType::Power,
+        );
+
+        for &piece in &[1, 97, 1000, audio.len()] {
+            for parallel in [false, true] {
+                let stft = StreamingStft::new(
+                    n_fft,
+                    hop_length,
+                    win_length,
+                    center,
+                    SpectrogramType::Power,
+                )
+                .parallel(parallel);
+                let spec = streamed(&audio, piece, stft);
+                assert_eq!(
+                    spec, expected,
+                    "mismatch for n_fft={} hop={} win={} piece={}",
+                    n_fft, hop_length, win_length, piece
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_streaming_stft_short_audio() {
+    // Shorter than one window: a single zero-padded frame, as in batch mode
+    let audio = test_signal(100);
+    let expected = compute_spectrogram(&audio, 512, 128, 512, true, SpectrogramType::Magnitude);
+    let stft = StreamingStft::new(512, 128, 512, true, SpectrogramType::Magnitude);
+    let spec = streamed(&audio, 30, stft);
+
+    assert_eq!(spec[0].len(), 1);
+    assert_eq!(spec, expected);
+}
+
+#[test]
+fn test_streaming_resampler_matches_batch() -> Result<()> {
+    let audio = test_signal(44100);
+
+    for quality in [ResampleQuality::Medium, ResampleQuality::Best] {
+        let expected = resample_with_quality(audio.clone(), 44100, 16000, quality)?;
+
+        // The sinc resampler always works on fixed chunks, whatever the piece size
+        let mut resampler = StreamingResampler::new(44100, 16000, quality)?;
+        let mut output = Vec::new();
+        for chunk in audio.chunks(777) {
+            resampler.process(chunk, &mut output)?;
+        }
+        resampler.finish(&mut output)?;
+
+        assert_eq!(output, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_streaming_resampler_fast_length() -> Result<()> {
+    let audio = test_signal(44100);
+
+    for &target_sr in &[8000, 16000, 22050, 48000] {
+        let mut resampler = StreamingResampler::new(44100, target_sr, ResampleQuality::Fast)?;
+        let mut output = Vec::new();
+        for chunk in audio.chunks(5000) {
+            resampler.process(chunk, &mut output)?;
+        }
+        resampler.finish(&mut output)?;
+
+        assert_eq!(output.len(), target_sr as usize);
+
+        // Energy is preserved (away from the edges)
+        let n = output.len();
+        let rms_in = (audio.iter().map(|s| s * s).sum::<f32>() / audio.len() as f32).sqrt();
+        let rms_out = (output[n / 10..n - n / 10]
+            .iter()
+            .map(|s| s * s)
+            .sum::<f32>()
+            / (n - n / 5) as f32)
+            .sqrt();
+        assert!(
+            (rms_in - rms_out).abs() < 0.02,
+            "rms {} vs {} at {} Hz",
+            rms_in,
+            rms_out,
+            target_sr
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_spectrogram_from_file() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("long.wav");
+
+    // Longer than one decoding chunk
+    create_complex_test_wav(&audio_path, 5.0, 16000, 2, 16)?;
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(512)
+        .build()?;
+
+    // Without resampling the result is identical to the in-memory pipeline
+    let (audio, _) = read_audio_file_mono(&audio_path)?;
+    let expected = compute_spectrogram(&audio, 512, 128, 512, true, SpectrogramType::Power);
+
+    let mut chunks = MonoChunks::open(&audio_path)?;
+    let mut n_decoded = 0;
+    let (spec, sr) = stream_spectrogram(
+        &mut chunks,
+        None,
+        ResampleQuality::Fast,
+        &params,
+        false,
+        |chunk| n_decoded += chunk.len(),
+    )?;
+    assert_eq!(sr, 16000);
+    assert_eq!(n_decoded, audio.len());
+    assert_eq!(spec, expected);
+
+    // With sinc resampling as well
+    let resampled = resample_with_quality(audio, 16000, 8000, ResampleQuality::Medium)?;
+    let expected = compute_spectrogram(&resampled, 512, 128, 512, true, SpectrogramType::Power);
+
+    let mut chunks = MonoChunks::open(&audio_path)?;
+    let (spec, sr) = stream_spectrogram(
+        &mut chunks,
+        Some(8000),
+        ResampleQuality::Medium,
+        &params,
+        true,
+        |_| {},
+    )?;
+    assert_eq!(sr, 8000);
+    assert_eq!(spec, expected);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_mono_chunks_segment() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("segment.wav");
+
+    create_complex_test_wav(&audio_path, 2.0, 16000, 1, 16)?;
+    let (expected, _) = read_audio_segment(&audio_path, 0.5, Some(1.25))?;
+
+    let mut chunks = MonoChunks::open_segment(&audio_path, 0.5, Some(1.25))?;
+    let mut samples = Vec::new();
+    loop {
+        let chunk = chunks.next_chunk(1000)?;
+        if chunk.is_empty() {
+            break;
+        }
+        assert!(chunk.len() <= 1000);
+        samples.extend(chunk);
+    }
+    assert_eq!(samples, expected);
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_stream_pooled_spectrogram() -> Result<()> {
+    let test_dir = setup_test_dir()?;
+    let audio_path = test_dir.join("pooled.wav");
+
+    create_complex_test_wav(&audio_path, 6.0, 22050, 1, 16)?;
+    let params = SpectrogramParams::builder()
+        .n_fft(512)
+        .hop_length(128)
+        .win_length(512)
+        .build()?;
+
+    for (target_sr, quality) in [
+        (None, ResampleQuality::Fast),
+        (Some(16000), ResampleQuality::Medium),
+    ] {
+        let mut chunks = MonoChunks::open(&audio_path)?;
+        let (full, _) =
+            stream_spectrogram(&mut chunks, target_sr, quality, &params, false, |_| {})?;
+
+        let mut chunks = MonoChunks::open(&audio_path)?;
+        let (pooled, _) =
+            stream_pooled_spectrogram(&mut chunks, target_sr, quality, &params, true, 200, |_| {})?;
+
+        // Pooling on the fly gives the same image as pooling the full spectrogram
+        assert_eq!(pooled[0].len(), 200);
+        let expected = pool_time(&full, 200);
+        for (row, expected_row) in pooled.iter().zip(&expected) {
+            for (a, b) in row.iter().zip(expected_row) {
+                assert!((a - b).abs() <= 1e-4 * b.abs().max(1.0), "{} vs {}", a, b);
+            }
+        }
+    }
+
+    cleanup_test_dir(&test_dir)?;
+    Ok(())
+}
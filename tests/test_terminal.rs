@@ -0,0 +1,66 @@
+use spectrs::io::image::{Colormap, apply_colormap};
+use spectrs::io::terminal::{log_range, render_half_blocks};
+
+// Pixel value of bin `k` (0 = lowest frequency): e^k - 1, so log1p gives k
+fn staircase(n_bins: usize, n_frames: usize) -> Vec<Vec<f32>> {
+    (0..n_bins)
+        .map(|k| vec![(k as f32).exp() - 1.0; n_frames])
+        .collect()
+}
+
+#[test]
+fn test_log_range() {
+    let (min, max) = log_range(&staircase(4, 3));
+    assert!(min.abs() < 1e-6);
+    assert!((max - 3.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_render_half_blocks_layout() {
+    let spec = staircase(4, 5);
+    let cells = render_half_blocks(&spec, Colormap::Gray, log_range(&spec), 2);
+
+    // One line per row, one cell per frame
+    assert_eq!(cells.len(), 2);
+    assert!(cells.iter().all(|line| line.len() == 5));
+
+    // Highest frequency on the upper half of the first line, lowest at the bottom
+    assert_eq!(cells[0][0].top, [255, 255, 255]);
+    assert_eq!(cells[1][0].bottom, [0, 0, 0]);
+    assert_eq!(
+        cells[0][0].bottom,
+        apply_colormap(2.0 / 3.0, Colormap::Gray)
+    );
+}
+
+#[test]
+fn test_render_half_blocks_resamples_bins() {
+    // 8 bins into 2 pixels: each pixel averages 4 bins (in log space)
+    let spec = staircase(8, 1);
+    let cells = render_half_blocks(&spec, Colormap::Gray, (0.0, 7.0), 1);
+    assert_eq!(
+        cells[0][0].bottom,
+        apply_colormap(1.5 / 7.0, Colormap::Gray)
+    );
+    assert_eq!(cells[0][0].top, apply_colormap(5.5 / 7.0, Colormap::Gray));
+
+    // 2 bins into 6 pixels: bins are repeated
+    let spec = staircase(2, 1);
+    let cells = render_half_blocks(&spec, Colormap::Gray, log_range(&spec), 3);
+    assert_eq!(cells[0][0].top, [255, 255, 255]);
+    assert_eq!(cells[2][0].bottom, [0, 0, 0]);
+}
+
+#[test]
+fn test_render_half_blocks_constant() {
+    // No dynamic range: everything gets the middle color
+    let spec = vec![vec![1.0; 3]; 4];
+    let cells = render_half_blocks(&spec, Colormap::Viridis, log_range(&spec), 2);
+    let middle = apply_colormap(0.5, Colormap::Viridis);
+    assert!(
+        cells
+            .iter()
+            .flatten()
+            .all(|c| c.top == middle && c.bottom == middle)
+    );
+}
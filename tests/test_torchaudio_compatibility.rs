@@ -0,0 +1,221 @@
+mod common;
+
+use anyhow::{Context, Result};
+use common::{cleanup_test_dir, create_complex_test_wav, create_test_wav, setup_temp_test_dir};
+use serde_json::Value;
+use spectrs::io::audio::read_audio_file_mono;
+use spectrs::io::export::load_spectrogram_json;
+use spectrs::spectrogram::compare::Metric;
+use spectrs::spectrogram::mel::MelScale;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::stft::SpectrogramType;
+use spectrs::spectrogram::torchaudio::{
+    par_torchaudio_spectrogram, reflect_pad, torchaudio_defaults, torchaudio_spectrogram,
+};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Compatibility thresholds for torchaudio comparison
+const CORRELATION_THRESHOLD: f32 = 0.999;
+const RELATIVE_ERROR_THRESHOLD: f32 = 0.03;
+
+/// Helper function to run Python script using uv
+fn run_python_script(script_path: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new("uv")
+        .arg("run")
+        .arg(script_path)
+        .args(args)
+        .output()
+        .context("Failed to execute Python script with uv")?;
+
+    if !output.status.success() {
+        eprintln!(
+            "Python script stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        anyhow::bail!("Python script failed with status: {}", output.status);
+    }
+
+    Ok(())
+}
+
+/// Compute the spectrogram of `audio_path` with spectrs and torchaudio and compare them.
+/// `torch_params` are forwarded to torchaudio.transforms; everything else keeps its defaults.
+fn compare_with_torchaudio(
+    test_dir: &Path,
+    audio_path: &Path,
+    params: &SpectrogramParams,
+    torch_params: Value,
+) -> Result<bool> {
+    let (samples, sr) = read_audio_file_mono(audio_path)?;
+    let spec = par_torchaudio_spectrogram(&samples, sr, params)?;
+
+    let torch_json = test_dir.join("torchaudio.json");
+    let params_json = test_dir.join("params.json");
+    fs::write(&params_json, torch_params.to_string())?;
+    run_python_script(
+        "tests/benchmark/generate_torchaudio_spectrogram.py",
+        &[
+            audio_path.to_str().unwrap(),
+            torch_json.to_str().unwrap(),
+            params_json.to_str().unwrap(),
+        ],
+    )?;
+
+    // Shapes must match exactly (padding and frame count included)
+    let torch_spec = load_spectrogram_json(&torch_json)?;
+    assert_eq!(torch_spec.len(), spec.len());
+    assert_eq!(torch_spec[0].len(), spec[0].len());
+
+    let correlation = Metric::Correlation.compute(&spec, &torch_spec) as f32;
+    let relative_error = Metric::RelativeError.compute(&spec, &torch_spec) as f32;
+    println!(
+        "Correlation: {:.6}, Relative Error: {:.6}",
+        correlation, relative_error
+    );
+
+    Ok(correlation >= CORRELATION_THRESHOLD && relative_error <= RELATIVE_ERROR_THRESHOLD)
+}
+
+#[test]
+fn test_reflect_pad() -> Result<()> {
+    let audio = [1.0, 2.0, 3.0, 4.0, 5.0];
+    assert_eq!(
+        reflect_pad(&audio, 2)?,
+        vec![3.0, 2.0, 1.0, 2.0, 3.0, 4.0, 5.0, 4.0, 3.0]
+    );
+    assert!(reflect_pad(&audio, 5).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_torchaudio_layout() -> Result<()> {
+    let audio: Vec<f32> = (0..16000).map(|i| (i as f32 * 0.05).sin()).collect();
+    let params = torchaudio_defaults();
+
+    // Centered frames: 1 + len / hop, with 128 mel bands
+    let spec = torchaudio_spectrogram(&audio, 16000, &params)?;
+    assert_eq!(spec.len(), 128);
+    assert_eq!(spec[0].len(), 1 + 16000 / 200);
+    assert_eq!(spec, par_torchaudio_spectrogram(&audio, 16000, &params)?);
+
+    // Without centering frames must fit inside the signal
+    let linear = SpectrogramParams {
+        center: false,
+        n_mels: None,
+        ..params
+    };
+    let spec = torchaudio_spectrogram(&audio, 16000, &linear)?;
+    assert_eq!(spec.len(), 201);
+    assert_eq!(spec[0].len(), 1 + (16000 - 400) / 200);
+
+    // Too short to be reflect-padded
+    assert!(torchaudio_spectrogram(&audio[..100], 16000, &params).is_err());
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires uv and downloads torch"]
+fn test_torchaudio_mel_defaults() -> Result<()> {
+    let test_dir = setup_temp_test_dir()?;
+    let audio_path = test_dir.join("torch_default.wav");
+    create_test_wav(&audio_path, 2.0, 16000, 1, 16)?;
+
+    let passes = compare_with_torchaudio(
+        &test_dir,
+        &audio_path,
+        &torchaudio_defaults(),
+        serde_json::json!({ "type": "mel" }),
+    )?;
+    if passes {
+        cleanup_test_dir(&test_dir)?;
+    }
+    assert!(passes, "torchaudio compatibility test failed");
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires uv and downloads torch"]
+fn test_torchaudio_mel_custom_sizes() -> Result<()> {
+    let test_dir = setup_temp_test_dir()?;
+    let audio_path = test_dir.join("torch_custom.wav");
+    create_complex_test_wav(&audio_path, 2.0, 22050, 1, 16)?;
+
+    let params = SpectrogramParams {
+        n_fft: 1024,
+        win_length: 800,
+        hop_length: 256,
+        n_mels: Some(80),
+        f_max: Some(8000.0),
+        ..torchaudio_defaults()
+    };
+    let passes = compare_with_torchaudio(
+        &test_dir,
+        &audio_path,
+        &params,
+        serde_json::json!({
+            "type": "mel",
+            "n_fft": 1024,
+            "win_length": 800,
+            "hop_length": 256,
+            "n_mels": 80,
+            "f_max": 8000.0,
+        }),
+    )?;
+    if passes {
+        cleanup_test_dir(&test_dir)?;
+    }
+    assert!(passes, "torchaudio compatibility test failed");
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires uv and downloads torch"]
+fn test_torchaudio_slaney_scale_magnitude() -> Result<()> {
+    let test_dir = setup_temp_test_dir()?;
+    let audio_path = test_dir.join("torch_slaney.wav");
+    create_complex_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+
+    // Slaney mel scale without Slaney normalization, magnitude instead of power
+    let params = SpectrogramParams {
+        mel_scale: MelScale::Slaney,
+        spectrogram_type: SpectrogramType::Magnitude,
+        ..torchaudio_defaults()
+    };
+    let passes = compare_with_torchaudio(
+        &test_dir,
+        &audio_path,
+        &params,
+        serde_json::json!({ "type": "mel", "mel_scale": "slaney", "power": 1.0 }),
+    )?;
+    if passes {
+        cleanup_test_dir(&test_dir)?;
+    }
+    assert!(passes, "torchaudio compatibility test failed");
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires uv and downloads torch"]
+fn test_torchaudio_linear_spectrogram() -> Result<()> {
+    let test_dir = setup_temp_test_dir()?;
+    let audio_path = test_dir.join("torch_linear.wav");
+    create_test_wav(&audio_path, 1.0, 16000, 1, 16)?;
+
+    let params = SpectrogramParams {
+        n_mels: None,
+        ..torchaudio_defaults()
+    };
+    let passes = compare_with_torchaudio(
+        &test_dir,
+        &audio_path,
+        &params,
+        serde_json::json!({ "type": "stft" }),
+    )?;
+    if passes {
+        cleanup_test_dir(&test_dir)?;
+    }
+    assert!(passes, "torchaudio compatibility test failed");
+    Ok(())
+}
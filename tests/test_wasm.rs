@@ -0,0 +1,55 @@
+#![cfg(feature = "wasm")]
+
+use spectrs::spectrogram::mel::{MelScale, convert_to_mel};
+use spectrs::spectrogram::stft::{SpectrogramType, compute_spectrogram};
+use spectrs::wasm::{MelOptions, compute_mel_from_pcm};
+use std::f32::consts::PI;
+
+#[test]
+fn test_compute_mel_from_pcm_matches_library() {
+    let sr = 16000;
+    let pcm: Vec<f32> = (0..sr)
+        .map(|i| (2.0 * PI * 440.0 * i as f32 / sr as f32).sin())
+        .collect();
+
+    let mut options = MelOptions::new();
+    options.n_fft = 512;
+    options.hop_length = 128;
+    options.win_length = 512;
+    options.n_mels = 40;
+    options.htk = true;
+    let mel = compute_mel_from_pcm(&pcm, sr as u32, Some(options)).unwrap();
+
+    let spectrogram = compute_spectrogram(&pcm, 512, 128, 512, true, SpectrogramType::Power);
+    let expected = convert_to_mel(
+        &spectrogram,
+        sr as u32,
+        512,
+        40,
+        Some(0.0),
+        None,
+        MelScale::HTK,
+    );
+
+    // Row-major copy of the [mel][time] layout
+    assert_eq!(mel.n_mels(), 40);
+    assert_eq!(mel.n_frames(), expected[0].len());
+    assert_eq!(mel.data(), expected.concat());
+    assert_eq!(mel.get(3, 7), expected[3][7]);
+
+    // Omitted options fall back to the defaults
+    let default = compute_mel_from_pcm(&pcm, sr as u32, None).unwrap();
+    assert_eq!(default.n_mels(), 128);
+}
+
+#[test]
+fn test_mel_options_validation() {
+    let mut options = MelOptions::new();
+    options.f_max = 12000.0;
+    assert!(options.to_params(16000).is_err());
+
+    options.f_max = 0.0;
+    let params = options.to_params(16000).unwrap();
+    assert_eq!(params.f_max, None);
+    assert_eq!(params.n_mels, Some(128));
+}
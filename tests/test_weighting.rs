@@ -0,0 +1,115 @@
+use anyhow::Result;
+use spectrs::spectrogram::fbank::Compat;
+use spectrs::spectrogram::filterbank::filter_bank;
+use spectrs::spectrogram::mel::apply_filter_bank;
+use spectrs::spectrogram::params::SpectrogramParams;
+use spectrs::spectrogram::pipeline::SpectrogramPipeline;
+use spectrs::spectrogram::stft::SpectrogramType;
+use spectrs::spectrogram::weighting::{Weighting, apply_weighting, par_apply_weighting};
+
+#[test]
+fn test_weighting_curves() {
+    // Values of IEC 61672-1 (dB) at the exact frequencies of the nominal bands
+    let nominal = [
+        (31.623, -39.4, -3.0),
+        (100.0, -19.1, -0.3),
+        (1000.0, 0.0, 0.0),
+        (4000.0, 1.0, -0.8),
+        (10000.0, -2.5, -4.4),
+    ];
+    for (hz, a, c) in nominal {
+        assert!((Weighting::A.gain_db(hz) - a).abs() < 0.1, "A at {} Hz", hz);
+        assert!((Weighting::C.gain_db(hz) - c).abs() < 0.1, "C at {} Hz", hz);
+        assert_eq!(Weighting::Z.gain_db(hz), 0.0);
+    }
+}
+
+#[test]
+fn test_bin_gains() {
+    assert!(
+        Weighting::Z
+            .bin_gains::<f32>(512, 16000, SpectrogramType::Power)
+            .is_none()
+    );
+    let power: Vec<f64> = Weighting::A
+        .bin_gains(512, 16000, SpectrogramType::Power)
+        .unwrap();
+    let magnitude: Vec<f64> = Weighting::A
+        .bin_gains(512, 16000, SpectrogramType::Magnitude)
+        .unwrap();
+    assert_eq!(power.len(), 257);
+    // DC is removed, 1 kHz (bin 32) is left unchanged
+    assert_eq!(power[0], 0.0);
+    assert!((power[32] - 1.0).abs() < 1e-3);
+    // Powers are weighted by the squared gains of magnitudes
+    for (p, m) in power.iter().zip(&magnitude) {
+        assert!((p - m * m).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_weighted_pipeline() -> Result<()> {
+    let sr = 16000;
+    let samples: Vec<f32> = (0..sr)
+        .map(|i| (i as f32 * 0.37).sin() + 0.5 * (i as f32 * 2.1).sin())
+        .collect();
+    let builder = SpectrogramPipeline::builder()
+        .sample_rate(sr)
+        .n_fft(512)
+        .hop_length(128);
+    let gains: Vec<f32> = Weighting::C
+        .bin_gains(512, sr, SpectrogramType::Power)
+        .unwrap();
+
+    // Linear rows scaled by the gains of their bins
+    let mut expected = builder.build()?.process_samples(&samples)?;
+    let weighted = builder
+        .weighting(Weighting::C)
+        .build()?
+        .process_samples(&samples)?;
+    let mut parallel = expected.clone();
+    par_apply_weighting(&mut parallel, &gains);
+    apply_weighting(&mut expected, &gains);
+    assert_eq!(parallel, expected);
+    for (row, expected_row) in weighted.iter().zip(&expected) {
+        for (v, e) in row.iter().zip(expected_row) {
+            assert!((v - e).abs() <= 1e-5 * e.abs().max(1e-3));
+        }
+    }
+
+    // Mel bands of the weighted bins
+    let mel = builder
+        .weighting(Weighting::C)
+        .mel(40)
+        .build()?
+        .process_samples(&samples)?;
+    let params = SpectrogramParams {
+        n_mels: Some(40),
+        ..*builder.build()?.params()
+    };
+    let filters: Vec<Vec<f32>> = filter_bank(&params, sr).unwrap();
+    let expected = apply_filter_bank(&expected, &filters);
+    for (row, expected_row) in mel.iter().zip(&expected) {
+        for (v, e) in row.iter().zip(expected_row) {
+            assert!((v - e).abs() <= 1e-4 * e.abs().max(1e-3));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_weighting_validation() {
+    let params = SpectrogramParams {
+        weighting: Weighting::A,
+        ..SpectrogramParams::default()
+    };
+    assert!(params.validate().is_ok());
+    assert!(
+        SpectrogramParams {
+            compat: Compat::Torchaudio,
+            ..params
+        }
+        .validate()
+        .is_err()
+    );
+}